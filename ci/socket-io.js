@@ -30,6 +30,11 @@ var callback = client => {
         console.log(['binary', data]);
     });
 
+    client.on('proto', (type, data) => {
+        console.log(['proto', type]);
+        client.emit('proto-received', type, data);
+    });
+
     client.on('binary', function (arg, ack) {
         console.log(['binary', 'Ack received, answer with binary'])
         if (ack) {
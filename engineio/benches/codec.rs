@@ -0,0 +1,75 @@
+//! Benchmarks of the packet codec that don't need a running engine.io server.
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rust_engineio::{
+    bench::{decode_payload, encode_payload},
+    Packet, PacketId,
+};
+
+const BATCH_SIZES: [usize; 3] = [16, 256, 4096];
+
+fn text_packet() -> Packet {
+    Packet::new(
+        PacketId::Message,
+        Bytes::from(r#"["message",{"user":"bench","text":"hello world"}]"#),
+    )
+}
+
+fn binary_packet() -> Packet {
+    Packet::new(PacketId::MessageBinary, Bytes::from(vec![0xab; 1024]))
+}
+
+fn criterion_packet_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("packet encode");
+    for (name, packet) in [("text", text_packet()), ("binary", binary_packet())] {
+        group.bench_function(name, |b| b.iter(|| Bytes::from(black_box(packet.clone()))));
+    }
+    group.finish();
+}
+
+fn criterion_packet_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("packet decode");
+    for (name, packet) in [("text", text_packet()), ("binary", binary_packet())] {
+        let encoded = Bytes::from(packet);
+        group.throughput(Throughput::Bytes(encoded.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| Packet::try_from(black_box(encoded.clone())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn criterion_payload_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("payload decode");
+    for size in BATCH_SIZES {
+        let encoded = encode_payload(vec![text_packet(); size]).unwrap();
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &encoded, |b, encoded| {
+            b.iter(|| decode_payload(black_box(encoded.clone())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn criterion_payload_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("payload encode");
+    for size in BATCH_SIZES {
+        let packets = vec![text_packet(); size];
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &packets, |b, packets| {
+            b.iter(|| encode_payload(black_box(packets.clone())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    criterion_packet_encode,
+    criterion_packet_decode,
+    criterion_payload_decode,
+    criterion_payload_encode
+);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+//! Measures the round trip of an emit against a mock server on localhost, which
+//! answers the handshake and echoes every message packet. Unlike the `engineio`
+//! benches this doesn't need the test servers and isn't influenced by them.
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_engineio::{ClientBuilder, Packet, PacketId};
+use tungstenite::Message;
+use url::Url;
+
+const OPEN_PACKET: &str =
+    r#"0{"sid":"bench","upgrades":[],"pingInterval":300000,"pingTimeout":300000}"#;
+
+/// Spawns a websocket server that accepts a single client.
+fn mock_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut websocket = tungstenite::accept(stream).unwrap();
+        websocket.send(Message::text(OPEN_PACKET)).unwrap();
+
+        while let Ok(message) = websocket.read() {
+            let is_message = match &message {
+                Message::Text(text) => text.starts_with('4'),
+                Message::Binary(_) => true,
+                Message::Close(_) => break,
+                _ => false,
+            };
+            if is_message && websocket.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    addr
+}
+
+fn criterion_emit_round_trip(c: &mut Criterion) {
+    let url = Url::parse(&format!("http://{}/", mock_server())).unwrap();
+    let client = ClientBuilder::new(url).build_websocket().unwrap();
+    client.connect().unwrap();
+
+    let packet = Packet::new(PacketId::Message, Bytes::from("hello world"));
+
+    c.bench_function("engine io emit round trip", |b| {
+        b.iter(|| {
+            client.emit(black_box(packet.clone())).unwrap();
+            client.poll().unwrap().unwrap()
+        })
+    });
+    client.disconnect().unwrap();
+}
+
+criterion_group!(benches, criterion_emit_round_trip);
+criterion_main!(benches);
@@ -1,22 +1,35 @@
 use std::{
     fmt::Debug,
+    future::Future,
     pin::Pin,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 use async_stream::try_stream;
 use bytes::Bytes;
-use futures_util::{stream, Stream, StreamExt};
-use tokio::{runtime::Handle, sync::Mutex, time::Instant};
+use futures_util::{
+    future::{self, Either},
+    stream, Stream, StreamExt,
+};
+use tokio::{
+    runtime::Handle,
+    sync::{watch, Mutex},
+    time::Instant,
+};
 
 use crate::{
     asynchronous::{callback::OptionalCallback, transport::AsyncTransportType},
+    clock::{Timestamp, WAKE_CHECK_INTERVAL},
     error::Result,
     packet::{HandshakePacket, Payload},
-    Error, Packet, PacketId,
+    pool::{BufferPool, BufferPoolStats},
+    timing::EmitTimer,
+    CloseReason, DataInfo, DisconnectDescription, EmitTimingStats, Error, HttpVersion, Packet,
+    PacketId, TransportKind,
 };
 
 #[derive(Clone)]
@@ -24,24 +37,70 @@ pub struct Socket {
     handle: Handle,
     transport: Arc<Mutex<AsyncTransportType>>,
     transport_raw: AsyncTransportType,
-    on_close: OptionalCallback<()>,
-    on_data: OptionalCallback<Bytes>,
+    on_close: OptionalCallback<DisconnectDescription>,
+    on_data: OptionalCallback<(Bytes, DataInfo)>,
     on_error: OptionalCallback<String>,
     on_open: OptionalCallback<()>,
     on_packet: OptionalCallback<Packet>,
     connected: Arc<AtomicBool>,
-    last_ping: Arc<Mutex<Instant>>,
+    /// Whether the close of the connection was reported to `on_close`.
+    close_reported: Arc<AtomicBool>,
+    last_ping: Arc<Mutex<Timestamp>>,
     last_pong: Arc<Mutex<Instant>>,
     connection_data: Arc<HandshakePacket>,
     max_ping_timeout: u64,
+    buffer_pool: BufferPool,
+    half_open_probe: Option<HalfOpenProbe>,
+    /// When data was received from the server the last time.
+    last_received: Arc<Mutex<Instant>>,
+    /// The size of the packets that are being sent.
+    bytes_in_flight: Arc<AtomicUsize>,
+    emit_timer: Option<EmitTimer>,
+    open_ordering: OpenOrdering,
+    /// Whether `on_open` returned, or `true` if there's no such callback.
+    opened: Arc<watch::Sender<bool>>,
+}
+
+/// The order of the `on_open` callback and the callbacks of the packets
+/// received after it, see `ClientBuilder::open_ordering`. The callbacks of
+/// the packets are spawned on their own, so they may run concurrently with
+/// each other either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenOrdering {
+    /// `on_open` is called before the callbacks of the first packets, but
+    /// they don't wait for the future it returned to complete.
+    #[default]
+    Invoked,
+    /// The callbacks of the packets wait until the future returned by
+    /// `on_open` completed, e.g. so that a handler initialized there can be
+    /// relied on.
+    Completed,
+}
+
+/// Probes the connection once no data was received for `idle`, see
+/// `ClientBuilder::half_open_probe`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HalfOpenProbe {
+    pub(crate) idle: Duration,
+    pub(crate) timeout: Duration,
+}
+
+/// Marks the socket as opened once it's dropped, i.e. when `on_open`
+/// completed or panicked.
+struct OpenedGuard(Arc<watch::Sender<bool>>);
+
+impl Drop for OpenedGuard {
+    fn drop(&mut self) {
+        self.0.send_replace(true);
+    }
 }
 
 impl Socket {
     pub(crate) fn new(
         transport: AsyncTransportType,
         handshake: HandshakePacket,
-        on_close: OptionalCallback<()>,
-        on_data: OptionalCallback<Bytes>,
+        on_close: OptionalCallback<DisconnectDescription>,
+        on_data: OptionalCallback<(Bytes, DataInfo)>,
         on_error: OptionalCallback<String>,
         on_open: OptionalCallback<()>,
         on_packet: OptionalCallback<Packet>,
@@ -58,29 +117,75 @@ impl Socket {
             transport: Arc::new(Mutex::new(transport.clone())),
             transport_raw: transport,
             connected: Arc::new(AtomicBool::default()),
-            last_ping: Arc::new(Mutex::new(Instant::now())),
+            close_reported: Arc::new(AtomicBool::default()),
+            last_ping: Arc::new(Mutex::new(Timestamp::now())),
             last_pong: Arc::new(Mutex::new(Instant::now())),
             connection_data: Arc::new(handshake),
             max_ping_timeout,
+            buffer_pool: BufferPool::default(),
+            half_open_probe: None,
+            last_received: Arc::new(Mutex::new(Instant::now())),
+            bytes_in_flight: Arc::new(AtomicUsize::new(0)),
+            emit_timer: None,
+            open_ordering: OpenOrdering::default(),
+            opened: Arc::new(watch::Sender::new(false)),
         }
     }
 
+    /// Sets the pool that outgoing packets are encoded with.
+    pub(crate) fn with_buffer_pool(mut self, buffer_pool: BufferPool) -> Self {
+        self.buffer_pool = buffer_pool;
+        self
+    }
+
+    /// Records the timings of the emits with `timer`.
+    pub(crate) fn with_emit_timer(mut self, timer: Option<EmitTimer>) -> Self {
+        self.emit_timer = timer;
+        self
+    }
+
+    /// Orders the callbacks of the received packets after `on_open`.
+    pub(crate) fn with_open_ordering(mut self, ordering: OpenOrdering) -> Self {
+        self.open_ordering = ordering;
+        self
+    }
+
+    /// Probes the connection once it's idle, to detect half-open connections
+    /// before the ping timeout passed.
+    pub(crate) fn with_half_open_probe(mut self, probe: Option<HalfOpenProbe>) -> Self {
+        self.half_open_probe = probe;
+        self
+    }
+
     /// Opens the connection to a specified server. The first Pong packet is sent
     /// to the server to trigger the Ping-cycle.
     pub async fn connect(&self) -> Result<()> {
         // SAFETY: Has valid handshake due to type
         self.connected.store(true, Ordering::Release);
+        self.close_reported.store(false, Ordering::Release);
 
+        self.opened.send_replace(false);
         if let Some(on_open) = self.on_open.as_ref() {
-            let on_open = on_open.clone();
-            self.handle.spawn(async move { on_open(()).await });
+            // call the callback right away, so that it's called before the
+            // callbacks of the first packets, which are spawned as well
+            let open = on_open(());
+            let opened = OpenedGuard(self.opened.clone());
+            self.handle.spawn(async move {
+                // the guard marks the socket as opened even if `on_open`
+                // panics, so that the callbacks of the packets don't wait forever
+                let _opened = opened;
+                open.await;
+            });
+        } else {
+            self.opened.send_replace(true);
         }
 
         // set the last ping to now and set the connected state
-        *self.last_ping.lock().await = Instant::now();
+        *self.last_ping.lock().await = Timestamp::now();
+        *self.last_received.lock().await = Instant::now();
 
         // emit a pong packet to keep trigger the ping cycle on the server
-        self.emit(Packet::new(PacketId::Pong, Bytes::new())).await?;
+        self.pong().await?;
 
         Ok(())
     }
@@ -90,11 +195,8 @@ impl Socket {
         // check for the appropriate action or callback
         self.handle_packet(packet.clone());
         match packet.packet_id {
-            PacketId::MessageBinary => {
-                self.handle_data(packet.data.clone());
-            }
-            PacketId::Message => {
-                self.handle_data(packet.data.clone());
+            PacketId::MessageBinary | PacketId::Message => {
+                self.handle_data(packet.data.clone(), packet.packet_id);
             }
             PacketId::Close => {
                 self.handle_close();
@@ -104,7 +206,7 @@ impl Socket {
             }
             PacketId::Ping => {
                 self.pinged().await;
-                self.emit(Packet::new(PacketId::Pong, Bytes::new())).await?;
+                self.pong().await?;
             }
             PacketId::Pong | PacketId::Open => {
                 // this will never happen as the pong and open
@@ -144,17 +246,23 @@ impl Socket {
     }
 
     pub async fn disconnect(&self) -> Result<()> {
-        if let Some(on_close) = self.on_close.as_ref() {
-            let on_close = on_close.clone();
-            self.handle.spawn(async move { on_close(()).await });
-        }
+        self.report_close(CloseReason::ForcedClose, None);
 
-        self.emit(Packet::new(PacketId::Close, Bytes::new()))
-            .await?;
+        let result = self.emit(Packet::new(PacketId::Close, Bytes::new())).await;
 
         self.connected.store(false, Ordering::Release);
+        // stop polling the server, even if the close packet couldn't be sent
+        self.transport_raw.cancel();
 
-        Ok(())
+        result
+    }
+
+    /// Closes the connection right away without sending a close packet. Polling
+    /// stops and nothing is sent afterwards.
+    pub fn abort(&self) {
+        self.connected.store(false, Ordering::Release);
+        self.transport_raw.abort();
+        self.report_close(CloseReason::ForcedClose, None);
     }
 
     /// Sends a packet to the server.
@@ -172,13 +280,21 @@ impl Socket {
         let data: Bytes = if is_binary {
             packet.data
         } else {
-            packet.into()
+            packet.encode(&self.buffer_pool)
         };
 
+        let queued = self.emit_timer.as_ref().map(|_| Instant::now());
         let lock = self.transport.lock().await;
-        let fut = lock.as_transport().emit(data, is_binary);
+        let written = queued.map(|queued| (queued, Instant::now()));
+        let len = data.len();
+        self.bytes_in_flight.fetch_add(len, Ordering::AcqRel);
+        let result = lock.as_transport().emit(data, is_binary).await;
+        self.bytes_in_flight.fetch_sub(len, Ordering::AcqRel);
+        if let (Some(timer), Some((queued, written))) = (&self.emit_timer, written) {
+            timer.record(written - queued, written.elapsed());
+        }
 
-        if let Err(error) = fut.await {
+        if let Err(error) = result {
             self.call_error_callback(error.to_string());
             return Err(error);
         }
@@ -186,6 +302,38 @@ impl Socket {
         Ok(())
     }
 
+    /// Answers a ping of the server.
+    async fn pong(&self) -> Result<()> {
+        self.emit(Packet::new(PacketId::Pong, Bytes::new())).await?;
+        *self.last_pong.lock().await = Instant::now();
+        Ok(())
+    }
+
+    /// Calls the close callback with a description of the connection, once per
+    /// connection.
+    fn report_close(&self, reason: CloseReason, error: Option<&Error>) {
+        if self.close_reported.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let Some(on_close) = self.on_close.as_ref() else {
+            return;
+        };
+        let on_close = on_close.clone();
+        let mut description = DisconnectDescription::new(reason, self.transport_raw.kind());
+        if let Some(error) = error {
+            description = description.with_error(error);
+        }
+        description.bytes_in_flight = self.bytes_in_flight.load(Ordering::Acquire);
+        description.close_frame = self.transport_raw.close_frame();
+        let socket = self.clone();
+        self.handle.spawn(async move {
+            description.since_last_received = socket.last_received.lock().await.elapsed();
+            description.since_last_ping = socket.last_ping.lock().await.elapsed();
+            description.since_last_pong = socket.last_pong.lock().await.elapsed();
+            on_close(description).await
+        });
+    }
+
     /// Calls the error callback with a given message.
     #[inline]
     fn call_error_callback(&self, text: String) {
@@ -200,48 +348,132 @@ impl Socket {
         self.connected.load(Ordering::Acquire)
     }
 
+    /// Returns the handshake data the server sent when opening the connection.
+    pub(crate) fn handshake(&self) -> &HandshakePacket {
+        &self.connection_data
+    }
+
+    pub(crate) fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.buffer_pool.stats()
+    }
+
+    pub(crate) fn emit_timing_stats(&self) -> Option<EmitTimingStats> {
+        self.emit_timer.as_ref().map(EmitTimer::stats)
+    }
+
+    pub(crate) fn http_version(&self) -> Option<HttpVersion> {
+        self.transport_raw.http_version()
+    }
+
+    pub(crate) fn transport_kind(&self) -> TransportKind {
+        self.transport_raw.kind()
+    }
+
     pub(crate) async fn pinged(&self) {
-        *self.last_ping.lock().await = Instant::now();
+        *self.last_ping.lock().await = Timestamp::now();
     }
 
     /// Returns the time in milliseconds that is left until a new ping must be received.
     /// This is used to detect whether we have been disconnected from the server.
     /// See https://socket.io/docs/v4/how-it-works/#disconnection-detection
     async fn time_to_next_ping(&self) -> u64 {
-        match Instant::now().checked_duration_since(*self.last_ping.lock().await) {
-            Some(since_last_ping) => {
-                let since_last_ping = since_last_ping.as_millis() as u64;
-                if since_last_ping > self.max_ping_timeout {
-                    0
-                } else {
-                    self.max_ping_timeout - since_last_ping
+        let since_last_ping = self.last_ping.lock().await.elapsed().as_millis() as u64;
+        self.max_ping_timeout.saturating_sub(since_last_ping)
+    }
+
+    /// Waits for the next item of the stream until the next ping should have been
+    /// received. Wakes up regularly to notice a heartbeat that expired while the
+    /// system was suspended, instead of waiting for the full remaining timeout.
+    /// If a probe is configured, the connection is probed once it's idle. Fails
+    /// with the reason why the connection is considered dead.
+    async fn next_before_ping_timeout<S>(&self, stream: &mut S) -> Result<Option<S::Item>>
+    where
+        S: Stream + Unpin,
+    {
+        loop {
+            let mut timeout =
+                Duration::from_millis(self.time_to_next_ping().await).min(WAKE_CHECK_INTERVAL);
+            if let Some(probe) = self.half_open_probe {
+                let idle = self.last_received.lock().await.elapsed();
+                timeout = timeout.min(probe.idle.saturating_sub(idle));
+            }
+            // the stream keeps its state when the pending `next` is dropped
+            if let Ok(item) = tokio::time::timeout(timeout, stream.next()).await {
+                *self.last_received.lock().await = Instant::now();
+                return Ok(item);
+            }
+
+            if self.time_to_next_ping().await == 0 {
+                return Err(Error::PingTimeout());
+            }
+            let Some(probe) = self.half_open_probe else {
+                continue;
+            };
+            if self.last_received.lock().await.elapsed() < probe.idle {
+                continue;
+            }
+
+            // keep reading while probing, the answer to a websocket probe
+            // arrives on the stream
+            let probed = async {
+                let transport = self.transport.lock().await;
+                tokio::time::timeout(probe.timeout, transport.probe()).await
+            };
+            tokio::pin!(probed);
+            match future::select(stream.next(), probed).await {
+                Either::Left((item, _)) => {
+                    *self.last_received.lock().await = Instant::now();
+                    return Ok(item);
                 }
+                Either::Right((Ok(Ok(())), _)) => {
+                    *self.last_received.lock().await = Instant::now();
+                }
+                Either::Right(_) => return Err(Error::HalfOpenConnection()),
             }
-            None => 0,
         }
     }
 
     pub(crate) fn handle_packet(&self, packet: Packet) {
         if let Some(on_packet) = self.on_packet.as_ref() {
             let on_packet = on_packet.clone();
-            self.handle.spawn(async move { on_packet(packet).await });
+            let opened = self.wait_for_open();
+            self.handle.spawn(async move {
+                opened.await;
+                on_packet(packet).await
+            });
         }
     }
 
-    pub(crate) fn handle_data(&self, data: Bytes) {
+    pub(crate) fn handle_data(&self, data: Bytes, packet_id: PacketId) {
         if let Some(on_data) = self.on_data.as_ref() {
             let on_data = on_data.clone();
-            self.handle.spawn(async move { on_data(data).await });
+            let info = DataInfo::new(packet_id, self.transport_raw.kind());
+            let opened = self.wait_for_open();
+            self.handle.spawn(async move {
+                opened.await;
+                on_data((data, info)).await
+            });
         }
     }
 
-    pub(crate) fn handle_close(&self) {
-        if let Some(on_close) = self.on_close.as_ref() {
-            let on_close = on_close.clone();
-            self.handle.spawn(async move { on_close(()).await });
+    /// Resolves once the callbacks of the packets may be called according to
+    /// the [`OpenOrdering`].
+    fn wait_for_open(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut opened = (self.open_ordering == OpenOrdering::Completed && !*self.opened.borrow())
+            .then(|| self.opened.subscribe());
+        async move {
+            if let Some(opened) = opened.as_mut() {
+                // the sender lives as long as the socket
+                let _ = opened.wait_for(|opened| *opened).await;
+            }
         }
+    }
+
+    pub(crate) fn handle_close(&self) {
+        self.report_close(CloseReason::ServerClose, None);
 
         self.connected.store(false, Ordering::Release);
+        self.transport_raw.cancel();
     }
 
     /// Returns the packet stream for the client.
@@ -252,20 +484,34 @@ impl Socket {
             Self::stream(self.transport_raw.clone()),
             |mut stream| async {
                 // Wait for the next payload or until we should have received the next ping.
-                match tokio::time::timeout(
-                    std::time::Duration::from_millis(self.time_to_next_ping().await),
-                    stream.next(),
-                )
-                .await
-                {
-                    Ok(result) => result.map(|result| (result, stream)),
+                match self.next_before_ping_timeout(&mut stream).await {
+                    // The stream ends after an error.
+                    Ok(Some(Err(error))) => {
+                        self.report_close(CloseReason::TransportError, Some(&error));
+                        Some((Err(error), stream))
+                    }
+                    Ok(Some(Ok(packet))) => Some((Ok(packet), stream)),
+                    Ok(None) => {
+                        if self.is_connected() {
+                            self.report_close(CloseReason::TransportClose, None);
+                        }
+                        None
+                    }
+                    // The connection is half-open, nothing can be sent anymore.
+                    Err(error @ Error::HalfOpenConnection()) => {
+                        self.report_close(CloseReason::HalfOpenConnection, Some(&error));
+                        self.call_error_callback(error.to_string());
+                        self.abort();
+                        Some((Err(error), stream))
+                    }
                     // We didn't receive a ping in time and now consider the connection as closed.
-                    Err(_) => {
+                    Err(error) => {
+                        self.report_close(CloseReason::PingTimeout, Some(&error));
                         // Be nice and disconnect properly.
                         if let Err(e) = self.disconnect().await {
                             Some((Err(e), stream))
                         } else {
-                            Some((Err(Error::PingTimeout()), stream))
+                            Some((Err(error), stream))
                         }
                     }
                 }
@@ -0,0 +1,339 @@
+use std::fmt::Debug;
+use std::io;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use crate::asynchronous::generator::StreamGenerator;
+use crate::asynchronous::transport::{AsyncTransport, AsyncTransportType};
+use crate::error::Result;
+use crate::Error;
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use url::Url;
+
+/// The faults a [`ChaosTransport`] injects into a connection, see
+/// [`crate::asynchronous::ClientBuilder::chaos`]. Without any setting, the
+/// connection stays untouched.
+///
+/// The faults are rolled for every frame of the underlying transport, which is
+/// a single packet for websockets and a whole payload for polling requests.
+/// Incoming frames can be delayed, dropped and reordered, outgoing ones
+/// delayed and dropped. A random disconnect ends the incoming stream and
+/// fails all further emits, like a connection that broke down.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use rust_engineio::asynchronous::{async_transports::Chaos, ClientBuilder};
+/// use url::Url;
+///
+/// let builder = ClientBuilder::new(Url::parse("http://localhost:4201").unwrap()).chaos(
+///     Chaos::new()
+///         .latency(Duration::from_millis(20), Duration::from_millis(200))
+///         .drop_rate(0.05)
+///         .reorder_rate(0.1)
+///         .disconnect_rate(0.01)
+///         .seed(42),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Chaos {
+    latency: Option<(Duration, Duration)>,
+    drop_rate: f64,
+    reorder_rate: f64,
+    disconnect_rate: f64,
+    seed: Option<u64>,
+}
+
+impl Chaos {
+    /// Creates a configuration that injects no faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays every frame by a random duration between `min` and `max`.
+    pub fn latency(mut self, min: Duration, max: Duration) -> Self {
+        self.latency = Some((min, max.max(min)));
+        self
+    }
+
+    /// Drops frames with the given probability, between `0.0` and `1.0`.
+    pub fn drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Holds incoming frames back with the given probability, between `0.0`
+    /// and `1.0`, and delivers them after the next frame.
+    pub fn reorder_rate(mut self, rate: f64) -> Self {
+        self.reorder_rate = rate;
+        self
+    }
+
+    /// Breaks the connection down with the given probability per frame,
+    /// between `0.0` and `1.0`.
+    pub fn disconnect_rate(mut self, rate: f64) -> Self {
+        self.disconnect_rate = rate;
+        self
+    }
+
+    /// Seeds the random faults, so that a run can be repeated. Seeded from the
+    /// clock by default.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// The faults of a connection, shared by the clones of its transport.
+#[derive(Debug)]
+struct Faults {
+    chaos: Chaos,
+    /// The state of a xorshift generator, which is never `0`.
+    random: Mutex<u64>,
+    disconnected: AtomicBool,
+}
+
+impl Faults {
+    fn new(chaos: Chaos) -> Self {
+        let seed = chaos.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64)
+        });
+        Faults {
+            chaos,
+            random: Mutex::new(seed | 1),
+            disconnected: AtomicBool::new(false),
+        }
+    }
+
+    /// A random number in `[0, 1)`.
+    fn random(&self) -> f64 {
+        let Ok(mut state) = self.random.lock() else {
+            return 1.0;
+        };
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        (*state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn roll(&self, rate: f64) -> bool {
+        rate > 0.0 && self.random() < rate
+    }
+
+    /// Rolls whether the connection breaks down now.
+    fn disconnects(&self) -> bool {
+        if self.roll(self.chaos.disconnect_rate) {
+            self.disconnected.store(true, Ordering::Release);
+        }
+        self.is_disconnected()
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::Acquire)
+    }
+
+    async fn delay(&self) {
+        if let Some((min, max)) = self.chaos.latency {
+            tokio::time::sleep(min + (max - min).mul_f64(self.random())).await;
+        }
+    }
+}
+
+/// Wraps a transport and injects the faults of a [`Chaos`] configuration into
+/// its connection, to test how a client copes with an unreliable network.
+#[derive(Clone)]
+pub struct ChaosTransport {
+    inner: Box<AsyncTransportType>,
+    faults: Arc<Faults>,
+    generator: StreamGenerator<Bytes>,
+}
+
+impl ChaosTransport {
+    /// Injects the faults of `chaos` into the connection of `inner`.
+    pub fn new(inner: AsyncTransportType, chaos: Chaos) -> Self {
+        let faults = Arc::new(Faults::new(chaos));
+        ChaosTransport {
+            generator: StreamGenerator::new(Box::pin(Self::stream(inner.clone(), faults.clone()))),
+            inner: Box::new(inner),
+            faults,
+        }
+    }
+
+    /// The transport the faults are injected into.
+    pub fn inner(&self) -> &AsyncTransportType {
+        &self.inner
+    }
+
+    fn stream(
+        mut inner: AsyncTransportType,
+        faults: Arc<Faults>,
+    ) -> impl Stream<Item = Result<Bytes>> + Send + 'static {
+        stream! {
+            let mut held = None;
+            let mut frames = inner.as_pin_box();
+            while let Some(frame) = frames.next().await {
+                if faults.disconnects() {
+                    break;
+                }
+                let frame = match frame {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                if faults.roll(faults.chaos.drop_rate) {
+                    continue;
+                }
+                faults.delay().await;
+                if held.is_none() && faults.roll(faults.chaos.reorder_rate) {
+                    held = Some(frame);
+                    continue;
+                }
+                yield Ok(frame);
+                if let Some(frame) = held.take() {
+                    yield Ok(frame);
+                }
+            }
+            drop(frames);
+            if faults.is_disconnected() {
+                inner.abort();
+            }
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.inner.cancel();
+        self.generator.cancel();
+    }
+
+    pub(crate) fn abort(&self) {
+        self.inner.abort();
+        self.generator.cancel();
+    }
+
+    pub(crate) async fn probe(&self) -> Result<()> {
+        if self.faults.is_disconnected() {
+            return Err(Error::HalfOpenConnection());
+        }
+        // boxed, as the inner transport might be chaotic as well
+        Box::pin(self.inner.probe()).await
+    }
+}
+
+impl Stream for ChaosTransport {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.generator.poll_next_unpin(cx)
+    }
+}
+
+#[async_trait]
+impl AsyncTransport for ChaosTransport {
+    async fn emit(&self, data: Bytes, is_binary_att: bool) -> Result<()> {
+        if self.faults.disconnects() {
+            return Err(Error::IncompleteIo(io::ErrorKind::ConnectionReset.into()));
+        }
+        if self.faults.roll(self.faults.chaos.drop_rate) {
+            return Ok(());
+        }
+        self.faults.delay().await;
+        self.inner.as_transport().emit(data, is_binary_att).await
+    }
+
+    async fn base_url(&self) -> Result<Url> {
+        self.inner.as_transport().base_url().await
+    }
+
+    async fn set_base_url(&self, base_url: Url) -> Result<()> {
+        self.inner.as_transport().set_base_url(base_url).await
+    }
+}
+
+impl Debug for ChaosTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChaosTransport")
+            .field("inner", &self.inner)
+            .field("chaos", &self.faults.chaos)
+            .field("disconnected", &self.faults.is_disconnected())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asynchronous::async_transports::loopback;
+    use crate::{Packet, PacketId};
+
+    fn message(data: &'static str) -> Packet {
+        Packet::new(PacketId::Message, data)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_faults() -> Result<()> {
+        let (transport, server) = loopback();
+        let mut sut = ChaosTransport::new(transport.into(), Chaos::new().reorder_rate(1.0));
+        for data in ["1", "2", "3", "4"] {
+            server.send(message(data))?;
+        }
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(Packet::try_from(sut.next().await.unwrap()?)?);
+        }
+        // every other frame is held back for one frame
+        assert_eq!(
+            received,
+            vec![message("2"), message("1"), message("4"), message("3")]
+        );
+
+        let (transport, server) = loopback();
+        let sut = ChaosTransport::new(
+            transport.into(),
+            Chaos::new()
+                .latency(Duration::from_secs(1), Duration::from_secs(2))
+                .seed(7),
+        );
+        let start = tokio::time::Instant::now();
+        sut.emit(Bytes::from("4hi"), false).await?;
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.receive().await, Some(message("hi")));
+
+        let (transport, server) = loopback();
+        let sut = ChaosTransport::new(transport.into(), Chaos::new().drop_rate(1.0));
+        sut.emit(Bytes::from("4lost"), false).await?;
+        drop(sut);
+        assert_eq!(server.receive().await, None);
+
+        let (transport, server) = loopback();
+        let mut sut = ChaosTransport::new(transport.into(), Chaos::new().disconnect_rate(1.0));
+        server.send(message("1"))?;
+        assert!(sut.next().await.is_none());
+        assert!(sut.emit(Bytes::from("4late"), false).await.is_err());
+        assert!(sut.probe().await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed() {
+        let rolls = |seed| {
+            let faults = Faults::new(Chaos::new().seed(seed));
+            (0..8).map(|_| faults.random()).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(1), rolls(1));
+        assert_ne!(rolls(1), rolls(2));
+        assert!(rolls(3).iter().all(|roll| (0.0..1.0).contains(roll)));
+    }
+}
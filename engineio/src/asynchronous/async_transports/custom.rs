@@ -0,0 +1,193 @@
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::asynchronous::transport::AsyncTransport;
+use crate::error::Result;
+use crate::transport::TransportOptions;
+use crate::{Packet, PacketId};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{future::BoxFuture, Stream};
+use url::Url;
+
+/// A transport implemented outside of this crate, e.g. over QUIC or a serial
+/// line, see `ClientBuilder::transport_factory`.
+///
+/// The client clones its transport to read from one clone while it emits
+/// through another one, so the clones of the wrapped transport have to share
+/// the connection, like the transports of this crate do. The first item of
+/// the stream is the handshake of the server, i.e. the data of its `Open`
+/// packet, and the stream ends once the server closed the connection or the
+/// client called [`AsyncTransport::abort`] on disconnect.
+pub struct CustomTransport {
+    inner: Box<dyn DynTransport>,
+    max_payload_size: Option<usize>,
+}
+
+impl CustomTransport {
+    /// Wraps `transport` to be used by a client.
+    pub fn new<T>(transport: T) -> Self
+    where
+        T: AsyncTransport + Clone + Send + Sync + 'static,
+    {
+        CustomTransport {
+            inner: Box::new(transport),
+            max_payload_size: None,
+        }
+    }
+
+    /// Fails the items of the stream that exceed `limit` bytes, i.e. the
+    /// data after the wrapped transport decoded or decompressed it.
+    pub(crate) fn with_max_payload_size(mut self, limit: Option<usize>) -> Self {
+        self.max_payload_size = limit;
+        self
+    }
+
+    /// Emits a `Noop` packet, which fails if the connection broke down.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        self.emit(Packet::new(PacketId::Noop, Bytes::new()).into(), false)
+            .await
+    }
+}
+
+/// An [`AsyncTransport`] that can be cloned behind a `Box`.
+trait DynTransport: AsyncTransport + Send + Sync {
+    fn clone_box(&self) -> Box<dyn DynTransport>;
+}
+
+impl<T> DynTransport for T
+where
+    T: AsyncTransport + Clone + Send + Sync + 'static,
+{
+    fn clone_box(&self) -> Box<dyn DynTransport> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for CustomTransport {
+    fn clone(&self) -> Self {
+        CustomTransport {
+            inner: self.inner.clone_box(),
+            max_payload_size: self.max_payload_size,
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncTransport for CustomTransport {
+    async fn emit(&self, data: Bytes, is_binary_att: bool) -> Result<()> {
+        self.inner.emit(data, is_binary_att).await
+    }
+
+    async fn base_url(&self) -> Result<Url> {
+        self.inner.base_url().await
+    }
+
+    async fn set_base_url(&self, base_url: Url) -> Result<()> {
+        self.inner.set_base_url(base_url).await
+    }
+
+    fn abort(&self) {
+        self.inner.abort()
+    }
+}
+
+impl Stream for CustomTransport {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let max_payload_size = self.max_payload_size;
+        Pin::new(&mut *self.inner).poll_next(cx).map(|item| {
+            item.map(|data| {
+                let data = data?;
+                TransportOptions::check_payload_size(max_payload_size, data.len())?;
+                Ok(data)
+            })
+        })
+    }
+}
+
+#[cfg_attr(tarpaulin, ignore)]
+impl Debug for CustomTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomTransport").finish_non_exhaustive()
+    }
+}
+
+/// Connects the transports of `ClientBuilder::transport_factory`.
+#[derive(Clone)]
+pub(crate) struct TransportFactory(
+    Arc<dyn Fn(Url) -> BoxFuture<'static, Result<CustomTransport>> + Send + Sync>,
+);
+
+impl TransportFactory {
+    pub(crate) fn new<F, T>(factory: F) -> Self
+    where
+        F: Fn(Url) -> BoxFuture<'static, Result<T>> + Send + Sync + 'static,
+        T: AsyncTransport + Clone + Send + Sync + 'static,
+    {
+        TransportFactory(Arc::new(move |url| {
+            let connecting = factory(url);
+            Box::pin(async move { Ok(CustomTransport::new(connecting.await?)) })
+        }))
+    }
+
+    /// Connects a transport to the server at `url`.
+    pub(crate) async fn connect(&self, url: Url) -> Result<CustomTransport> {
+        (self.0)(url).await
+    }
+}
+
+#[cfg_attr(tarpaulin, ignore)]
+impl Debug for TransportFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransportFactory").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asynchronous::async_transports::loopback;
+    use crate::asynchronous::transport::AsyncTransportType;
+    use futures_util::StreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_abort_ends_stream() -> Result<()> {
+        let (transport, server) = loopback();
+        let mut custom = CustomTransport::new(transport);
+
+        server.send(Packet::new(PacketId::Noop, ""))?;
+        // the server stays connected, only the hook of the wrapped transport
+        // ends the stream
+        AsyncTransportType::from(custom.clone()).abort();
+        assert!(custom.next().await.is_some());
+        let end = tokio::time::timeout(Duration::from_secs(1), custom.next()).await;
+        assert!(matches!(end, Ok(None)));
+
+        let (transport, _server) = loopback();
+        let mut custom = CustomTransport::new(transport);
+        AsyncTransportType::from(custom.clone()).cancel();
+        let end = tokio::time::timeout(Duration::from_secs(1), custom.next()).await;
+        assert!(matches!(end, Ok(None)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_payload_size() -> Result<()> {
+        let (transport, server) = loopback();
+        let mut custom = CustomTransport::new(transport).with_max_payload_size(Some(8));
+
+        server.send(Packet::new(PacketId::Message, "x".repeat(7)))?;
+        server.send(Packet::new(PacketId::Message, "x".repeat(8)))?;
+        assert!(custom.next().await.unwrap().is_ok());
+        assert!(matches!(
+            custom.next().await,
+            Some(Err(crate::Error::PayloadTooLarge(8)))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt::Debug;
+use std::io;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex as StdMutex,
+};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::asynchronous::transport::AsyncTransport;
+use crate::error::Result;
+use crate::packet::HandshakePacket;
+use crate::{Error, Packet, PacketId};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::Stream;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use url::Url;
+
+/// Creates a connected pair of a client transport and the handle of the
+/// server on the other end, which exchange packets over in-memory channels.
+///
+/// The transport is used by [`crate::asynchronous::ClientBuilder::build_loopback`],
+/// the test drives the connection through the [`LoopbackServer`]: it answers
+/// the handshake with [`LoopbackServer::open`], sends packets to the client
+/// and receives the packets the client emits. Nothing touches the network, so
+/// the packets arrive in the order they're sent and tests stay deterministic.
+pub fn loopback() -> (LoopbackTransport, LoopbackServer) {
+    let (to_client, from_server) = mpsc::unbounded_channel();
+    let (to_server, from_client) = mpsc::unbounded_channel();
+    let transport = LoopbackTransport {
+        sender: to_server,
+        receiver: Arc::new(StdMutex::new(from_server)),
+        base_url: Arc::new(RwLock::new(
+            Url::parse("http://loopback/engine.io/?EIO=4&transport=loopback").unwrap(),
+        )),
+    };
+    let server = LoopbackServer {
+        sender: StdMutex::new(Some(to_client)),
+        receiver: Mutex::new(from_client),
+    };
+    (transport, server)
+}
+
+/// The open packet of a new session of an in-memory transport.
+pub(super) fn open_packet(ping_interval: Duration, ping_timeout: Duration) -> Packet {
+    static SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+    let handshake = HandshakePacket {
+        sid: format!("memory-{}", SESSIONS.fetch_add(1, Ordering::Relaxed)),
+        upgrades: Vec::new(),
+        ping_interval: ping_interval.as_millis() as u64,
+        ping_timeout: ping_timeout.as_millis() as u64,
+        extensions: Default::default(),
+    };
+    // SAFETY: the handshake consists of strings and numbers only
+    Packet::new(PacketId::Open, serde_json::to_vec(&handshake).unwrap())
+}
+
+/// The client side of a [`loopback`] connection. Clones share the
+/// connection.
+#[derive(Clone)]
+pub struct LoopbackTransport {
+    sender: mpsc::UnboundedSender<Packet>,
+    receiver: Arc<StdMutex<mpsc::UnboundedReceiver<Bytes>>>,
+    base_url: Arc<RwLock<Url>>,
+}
+
+impl LoopbackTransport {
+    /// Ends the stream of incoming data after the packets that were already
+    /// sent by the server.
+    pub(crate) fn cancel(&self) {
+        if let Ok(mut receiver) = self.receiver.lock() {
+            receiver.close();
+        }
+    }
+
+    /// Fails once the server handle is dropped.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        if self.sender.is_closed() {
+            return Err(Error::HalfOpenConnection());
+        }
+        Ok(())
+    }
+}
+
+impl Stream for LoopbackTransport {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.receiver.lock() {
+            Ok(mut receiver) => receiver.poll_recv(cx).map(|data| data.map(Ok)),
+            Err(_) => Poll::Ready(Some(Err(Error::InvalidPoisonedLock()))),
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncTransport for LoopbackTransport {
+    async fn emit(&self, data: Bytes, is_binary_att: bool) -> Result<()> {
+        let packet = if is_binary_att {
+            Packet::new(PacketId::MessageBinary, data)
+        } else {
+            Packet::try_from(data)?
+        };
+        self.sender
+            .send(packet)
+            .map_err(|_| Error::IncompleteIo(io::ErrorKind::BrokenPipe.into()))
+    }
+
+    async fn base_url(&self) -> Result<Url> {
+        Ok(self.base_url.read().await.clone())
+    }
+
+    async fn set_base_url(&self, base_url: Url) -> Result<()> {
+        *self.base_url.write().await = base_url;
+        Ok(())
+    }
+
+    fn abort(&self) {
+        self.cancel();
+    }
+}
+
+impl Debug for LoopbackTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoopbackTransport")
+            .field("connected", &!self.sender.is_closed())
+            .finish()
+    }
+}
+
+/// The server side of a [`loopback`] connection.
+#[derive(Debug)]
+pub struct LoopbackServer {
+    sender: StdMutex<Option<mpsc::UnboundedSender<Bytes>>>,
+    receiver: Mutex<mpsc::UnboundedReceiver<Packet>>,
+}
+
+impl LoopbackServer {
+    /// Answers the handshake of the client with a new session that expects
+    /// pings in the given interval.
+    pub fn open(&self, ping_interval: Duration, ping_timeout: Duration) -> Result<()> {
+        self.send(open_packet(ping_interval, ping_timeout))
+    }
+
+    /// Sends `packet` to the client.
+    pub fn send(&self, packet: Packet) -> Result<()> {
+        let sender = self
+            .sender
+            .lock()
+            .map_err(|_| Error::InvalidPoisonedLock())?;
+        sender
+            .as_ref()
+            .and_then(|sender| sender.send(Bytes::from(packet)).ok())
+            .ok_or_else(|| Error::IncompleteIo(io::ErrorKind::BrokenPipe.into()))
+    }
+
+    /// Waits for the next packet the client emits. Returns `None` once the
+    /// client is gone.
+    pub async fn receive(&self) -> Option<Packet> {
+        self.receiver.lock().await.recv().await
+    }
+
+    /// Drops the connection like a network failure: the stream of the client
+    /// ends after the packets that were already sent.
+    pub fn disconnect(&self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_loopback() -> Result<()> {
+        let (mut transport, server) = loopback();
+
+        server.send(Packet::new(PacketId::Message, "hello"))?;
+        let data = transport.next().await.unwrap()?;
+        assert_eq!(
+            Packet::try_from(data)?,
+            Packet::new(PacketId::Message, "hello")
+        );
+
+        transport.emit(Bytes::from("4world"), false).await?;
+        transport.emit(Bytes::from_static(&[1, 2]), true).await?;
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Message, "world"))
+        );
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::MessageBinary, vec![1u8, 2]))
+        );
+
+        server.send(Packet::new(PacketId::Noop, ""))?;
+        server.disconnect();
+        assert!(transport.next().await.is_some());
+        assert!(transport.next().await.is_none());
+        assert!(server.send(Packet::new(PacketId::Noop, "")).is_err());
+
+        drop(server);
+        assert!(transport.emit(Bytes::from("4bye"), false).await.is_err());
+        assert!(transport.probe().await.is_err());
+        Ok(())
+    }
+}
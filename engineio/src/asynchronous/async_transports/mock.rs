@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::asynchronous::transport::AsyncTransport;
+use crate::error::Result;
+use crate::{Error, Packet, PacketId};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::Stream;
+use tokio::sync::RwLock;
+use url::Url;
+
+use super::loopback::open_packet;
+
+/// A transport whose traffic is scripted by a test, for unit tests of
+/// handlers and reconnects without a network or a server.
+///
+/// The script is a sequence of steps that the transport plays in order: it
+/// hands the packets of [`MockTransport::receive`] to the client, waits for
+/// the client to emit the packets of [`MockTransport::expect`] and ends the
+/// connection at [`MockTransport::close`] or [`MockTransport::fail`]. The
+/// client reads from the same script after a reconnect, so the handshake of
+/// each connection is scripted with [`MockTransport::open`].
+///
+/// Emits are matched against the next expected packet, packets scheduled to
+/// be received before it don't have to be read first. An emit that doesn't
+/// match panics, except for the `Pong` packets of the heartbeat, which are
+/// only checked where the script expects them. [`MockTransport::assert_done`]
+/// checks that the whole script was played.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use rust_engineio::asynchronous::{async_transports::MockTransport, ClientBuilder};
+/// use rust_engineio::{Packet, PacketId};
+/// use url::Url;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let transport = MockTransport::new()
+///         .open(Duration::from_secs(25), Duration::from_secs(20))
+///         .expect(Packet::new(PacketId::Message, "hello"))
+///         .receive(Packet::new(PacketId::Message, "hello client"));
+///
+///     let client = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+///         .build_mock(transport)
+///         .await
+///         .unwrap();
+///     client.connect().await.unwrap();
+///     client
+///         .emit(Packet::new(PacketId::Message, "hello"))
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MockTransport {
+    script: Arc<Mutex<Script>>,
+    base_url: Arc<RwLock<Url>>,
+}
+
+#[derive(Debug)]
+enum Step {
+    Receive(Packet),
+    Expect(Packet),
+    Close,
+    Fail,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    steps: VecDeque<Step>,
+    /// The stream that waits for the next step.
+    waker: Option<Waker>,
+    /// Whether the waiting stream ends on its next poll.
+    cancelled: bool,
+}
+
+impl MockTransport {
+    /// Creates a transport with an empty script, which neither receives nor
+    /// accepts any packet.
+    pub fn new() -> Self {
+        MockTransport {
+            script: Arc::default(),
+            base_url: Arc::new(RwLock::new(
+                Url::parse("http://mock/engine.io/?EIO=4&transport=mock").unwrap(),
+            )),
+        }
+    }
+
+    /// Answers the handshake with a new session that expects pings in the
+    /// given interval.
+    pub fn open(self, ping_interval: Duration, ping_timeout: Duration) -> Self {
+        self.receive(open_packet(ping_interval, ping_timeout))
+    }
+
+    /// Hands `packet` to the client.
+    pub fn receive(self, packet: Packet) -> Self {
+        self.push(Step::Receive(packet))
+    }
+
+    /// Waits for the client to emit `packet`.
+    pub fn expect(self, packet: Packet) -> Self {
+        self.push(Step::Expect(packet))
+    }
+
+    /// Ends the connection like the server or the network closed it, the
+    /// client reconnects if it's configured to.
+    pub fn close(self) -> Self {
+        self.push(Step::Close)
+    }
+
+    /// Ends the connection with an error, e.g. to let a handshake fail.
+    pub fn fail(self) -> Self {
+        self.push(Step::Fail)
+    }
+
+    /// Whether all steps of the script were played.
+    pub fn is_done(&self) -> bool {
+        self.script().steps.is_empty()
+    }
+
+    /// Panics if a step of the script wasn't played yet.
+    pub fn assert_done(&self) {
+        let script = self.script();
+        assert!(
+            script.steps.is_empty(),
+            "the script of the mock transport isn't done, next step: {:?}",
+            script.steps.front()
+        );
+    }
+
+    fn push(self, step: Step) -> Self {
+        let mut script = self.script();
+        script.steps.push_back(step);
+        if let Some(waker) = script.waker.take() {
+            waker.wake();
+        }
+        drop(script);
+        self
+    }
+
+    fn script(&self) -> std::sync::MutexGuard<'_, Script> {
+        // a panic while holding the lock is an assertion of a test
+        self.script
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Ends the stream of incoming data if it waits for the script, the rest
+    /// of the script is played on the next connection.
+    pub(crate) fn cancel(&self) {
+        let mut script = self.script();
+        if let Some(waker) = script.waker.take() {
+            script.cancelled = true;
+            waker.wake();
+        }
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stream for MockTransport {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut script = self.script();
+        if script.cancelled {
+            script.cancelled = false;
+            return Poll::Ready(None);
+        }
+        match script.steps.front() {
+            Some(Step::Receive(_)) => match script.steps.pop_front() {
+                Some(Step::Receive(packet)) => Poll::Ready(Some(Ok(Bytes::from(packet)))),
+                _ => unreachable!(),
+            },
+            Some(Step::Close) => {
+                script.steps.pop_front();
+                Poll::Ready(None)
+            }
+            Some(Step::Fail) => {
+                script.steps.pop_front();
+                Poll::Ready(Some(Err(Error::IncompleteIo(
+                    io::ErrorKind::ConnectionReset.into(),
+                ))))
+            }
+            Some(Step::Expect(_)) | None => {
+                script.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncTransport for MockTransport {
+    async fn emit(&self, data: Bytes, is_binary_att: bool) -> Result<()> {
+        let packet = if is_binary_att {
+            Packet::new(PacketId::MessageBinary, data)
+        } else {
+            Packet::try_from(data)?
+        };
+
+        let mut script = self.script();
+        let expected = script
+            .steps
+            .iter()
+            .position(|step| !matches!(step, Step::Receive(_)))
+            .filter(|index| matches!(&script.steps[*index], Step::Expect(_)));
+        match expected {
+            Some(index) if matches!(&script.steps[index], Step::Expect(next) if *next == packet) => {
+                script.steps.remove(index);
+                if let Some(waker) = script.waker.take() {
+                    waker.wake();
+                }
+            }
+            _ if packet.packet_id == PacketId::Pong => {}
+            Some(index) => panic!(
+                "the mock transport expected {:?}, but {:?} was emitted",
+                script.steps[index], packet
+            ),
+            None => panic!(
+                "the mock transport expected no emit, but {:?} was emitted",
+                packet
+            ),
+        }
+        Ok(())
+    }
+
+    async fn base_url(&self) -> Result<Url> {
+        Ok(self.base_url.read().await.clone())
+    }
+
+    async fn set_base_url(&self, base_url: Url) -> Result<()> {
+        *self.base_url.write().await = base_url;
+        Ok(())
+    }
+}
+
+impl Debug for MockTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockTransport")
+            .field("steps", &self.script().steps.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::{FutureExt, StreamExt};
+
+    #[tokio::test]
+    async fn test_script() -> Result<()> {
+        let mut transport = MockTransport::new()
+            .receive(Packet::new(PacketId::Message, "first"))
+            .expect(Packet::new(PacketId::Message, "answer"))
+            .receive(Packet::new(PacketId::Message, "second"))
+            .close()
+            .receive(Packet::new(PacketId::Message, "reconnected"))
+            .fail();
+
+        assert_eq!(
+            Packet::try_from(transport.next().await.unwrap()?)?,
+            Packet::new(PacketId::Message, "first")
+        );
+        // the stream waits for the expected emit
+        assert!(transport.next().now_or_never().is_none());
+        // pongs are only checked where they're expected
+        transport.emit(Bytes::from("3"), false).await?;
+        transport.emit(Bytes::from("4answer"), false).await?;
+        assert_eq!(
+            Packet::try_from(transport.next().await.unwrap()?)?,
+            Packet::new(PacketId::Message, "second")
+        );
+        assert!(transport.next().await.is_none());
+
+        assert!(!transport.is_done());
+        assert!(transport.next().await.unwrap().is_ok());
+        assert!(transport.next().await.unwrap().is_err());
+        transport.assert_done();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected Expect(Packet { packet_id: Message")]
+    async fn test_unexpected_emit() {
+        let transport = MockTransport::new()
+            .receive(Packet::new(PacketId::Message, "first"))
+            .expect(Packet::new(PacketId::Message, "answer"));
+
+        let _ = transport.emit(Bytes::from("4other"), false).await;
+    }
+}
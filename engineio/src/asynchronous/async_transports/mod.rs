@@ -1,8 +1,30 @@
+#[cfg(any(test, feature = "test-support"))]
+mod chaos;
+mod custom;
+#[cfg(any(test, feature = "test-support"))]
+mod loopback;
+#[cfg(any(test, feature = "test-support"))]
+mod mock;
 mod polling;
+#[cfg(feature = "websocket")]
 mod websocket;
+#[cfg(feature = "websocket")]
 mod websocket_general;
+#[cfg(feature = "websocket")]
 mod websocket_secure;
 
+#[cfg(any(test, feature = "test-support"))]
+pub use self::chaos::{Chaos, ChaosTransport};
+pub use self::custom::CustomTransport;
+pub(crate) use self::custom::TransportFactory;
+#[cfg(any(test, feature = "test-support"))]
+pub use self::loopback::{loopback, LoopbackServer, LoopbackTransport};
+#[cfg(any(test, feature = "test-support"))]
+pub use self::mock::MockTransport;
 pub use self::polling::PollingTransport;
+#[cfg(feature = "websocket")]
 pub use self::websocket::WebsocketTransport;
+#[cfg(feature = "server")]
+pub(crate) use self::websocket_general::AsyncWebsocketGeneralTransport;
+#[cfg(feature = "websocket")]
 pub use self::websocket_secure::WebsocketSecureTransport;
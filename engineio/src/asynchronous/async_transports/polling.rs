@@ -6,23 +6,34 @@ use bytes::{BufMut, Bytes, BytesMut};
 use futures_util::{Stream, StreamExt};
 use http::HeaderMap;
 use native_tls::TlsConnector;
-use reqwest::{Client, ClientBuilder, Response};
+use reqwest::{Client, ClientBuilder, Request, Response};
 use std::fmt::Debug;
 use std::time::SystemTime;
 use std::{pin::Pin, sync::Arc};
 use tokio::sync::RwLock;
 use url::Url;
 
-use crate::asynchronous::generator::StreamGenerator;
-use crate::{asynchronous::transport::AsyncTransport, error::Result, Error};
+#[cfg(feature = "tower")]
+use tower::{Service, ServiceExt};
+
+#[cfg(feature = "tower")]
+use crate::asynchronous::HttpService;
+use crate::asynchronous::{generator::StreamGenerator, PreparedConnection};
+use crate::{
+    asynchronous::transport::AsyncTransport,
+    error::Result,
+    transport::{HttpProtocol, PollRetry, TransportOptions},
+    Error, HttpVersion, Packet, PacketId,
+};
 
 /// An asynchronous polling type. Makes use of the nonblocking reqwest types and
 /// methods.
 #[derive(Clone)]
 pub struct PollingTransport {
-    client: Client,
+    client: HttpClient,
     base_url: Arc<RwLock<Url>>,
     generator: StreamGenerator<Bytes>,
+    http_version: Arc<std::sync::RwLock<Option<HttpVersion>>>,
 }
 
 impl PollingTransport {
@@ -31,30 +42,107 @@ impl PollingTransport {
         tls_config: Option<TlsConnector>,
         opening_headers: Option<HeaderMap>,
     ) -> Self {
-        let client = match (tls_config, opening_headers) {
-            (Some(config), Some(map)) => ClientBuilder::new()
-                .use_preconfigured_tls(config)
-                .default_headers(map)
-                .build()
-                .unwrap(),
-            (Some(config), None) => ClientBuilder::new()
-                .use_preconfigured_tls(config)
-                .build()
-                .unwrap(),
-            (None, Some(map)) => ClientBuilder::new().default_headers(map).build().unwrap(),
-            (None, None) => Client::new(),
-        };
+        Self::with_http_protocol(
+            base_url,
+            tls_config,
+            opening_headers,
+            HttpProtocol::default(),
+        )
+    }
+
+    /// Creates an instance of `PollingTransport` that uses the given HTTP protocol version.
+    pub fn with_http_protocol(
+        base_url: Url,
+        tls_config: Option<TlsConnector>,
+        opening_headers: Option<HeaderMap>,
+        http_protocol: HttpProtocol,
+    ) -> Self {
+        Self::with_options(
+            base_url,
+            tls_config,
+            opening_headers,
+            None,
+            &TransportOptions {
+                http_protocol,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Creates an instance of `PollingTransport` with the given transport
+    /// `options`, which connects to the addresses of the prepared connection
+    /// instead of resolving the host again.
+    pub(crate) fn with_options(
+        base_url: Url,
+        tls_config: Option<TlsConnector>,
+        opening_headers: Option<HeaderMap>,
+        prepared: Option<&PreparedConnection>,
+        options: &TransportOptions,
+    ) -> Self {
+        let mut builder = options.local_binding.apply(ClientBuilder::new());
+        if let Some(resolver) = &options.resolver {
+            builder = builder.dns_resolver(Arc::new(resolver.clone()));
+        }
+        if let Some(prepared) = prepared {
+            builder = builder.resolve_to_addrs(prepared.host(), prepared.addrs());
+        }
+        if let Some(config) = tls_config {
+            builder = builder.use_preconfigured_tls(config);
+        }
+        if let Some(map) = opening_headers {
+            builder = builder.default_headers(map);
+        }
+        let client = match options.http_protocol {
+            HttpProtocol::Auto => builder,
+            HttpProtocol::Http1 => builder.http1_only(),
+            HttpProtocol::Http2 => builder.http2_prior_knowledge(),
+        }
+        .build()
+        .unwrap();
+        let client = HttpClient::new(client, options);
 
         let mut url = base_url;
         url.query_pairs_mut().append_pair("transport", "polling");
 
+        let http_version = Arc::new(std::sync::RwLock::new(None));
+
         PollingTransport {
             client: client.clone(),
             base_url: Arc::new(RwLock::new(url.clone())),
-            generator: StreamGenerator::new(Self::stream(url, client)),
+            generator: StreamGenerator::new(Self::stream(
+                url,
+                client,
+                http_version.clone(),
+                options.poll_retry,
+                options.max_payload_size,
+            )),
+            http_version,
         }
     }
 
+    /// Returns the HTTP version of the last response received from the server.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        self.http_version.read().ok().and_then(|version| *version)
+    }
+
+    fn record_version(http_version: &std::sync::RwLock<Option<HttpVersion>>, response: &Response) {
+        if let Ok(mut version) = http_version.write() {
+            *version = Some(response.version());
+        }
+    }
+
+    /// Sends a `Noop` packet in a request of its own, the response of the
+    /// server proves that it's still reachable while a long-poll is pending.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        self.emit(Packet::new(PacketId::Noop, Bytes::new()).into(), false)
+            .await
+    }
+
+    /// Stops polling the server, pending requests are aborted.
+    pub(crate) fn cancel(&self) {
+        self.generator.cancel();
+    }
+
     fn address(mut url: Url) -> Result<Url> {
         let reader = format!("{:#?}", SystemTime::now());
         let hash = adler32(reader.as_bytes()).unwrap();
@@ -62,26 +150,48 @@ impl PollingTransport {
         Ok(url)
     }
 
-    fn send_request(url: Url, client: Client) -> impl Stream<Item = Result<Response>> {
-        try_stream! {
-            let address = Self::address(url);
+    /// Sends a `GET` request, retrying it according to `retry` if a gateway
+    /// responds with a transient error.
+    async fn send_request(url: Url, client: HttpClient, retry: PollRetry) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let request = client.get(Self::address(url.clone())?).build()?;
+            let response = client.execute(request).await?;
 
-            yield client
-                .get(address?)
-                .send().await?
+            let status = response.status();
+            if !PollRetry::is_transient(status) {
+                return Ok(response);
+            }
+            let Some(delay) = retry.delay(attempt) else {
+                return Err(Error::IncompleteHttp(status.as_u16()));
+            };
+            attempt += 1;
+            tokio::time::sleep(delay).await;
         }
     }
 
     fn stream(
         url: Url,
-        client: Client,
+        client: HttpClient,
+        http_version: Arc<std::sync::RwLock<Option<HttpVersion>>>,
+        retry: PollRetry,
+        max_payload_size: Option<usize>,
     ) -> Pin<Box<dyn Stream<Item = Result<Bytes>> + 'static + Send>> {
         Box::pin(try_stream! {
             loop {
-                for await elem in Self::send_request(url.clone(), client.clone()) {
-                    for await bytes in elem?.bytes_stream() {
-                        yield bytes?;
-                    }
+                let response = Self::send_request(url.clone(), client.clone(), retry).await?;
+                Self::record_version(&http_version, &response);
+                // the length of a chunked response is unknown upfront, so the
+                // received bytes are counted as well
+                if let Some(length) = response.content_length() {
+                    TransportOptions::check_payload_size(max_payload_size, length as usize)?;
+                }
+                let mut received = 0;
+                for await bytes in response.bytes_stream() {
+                    let bytes = bytes?;
+                    received += bytes.len();
+                    TransportOptions::check_payload_size(max_payload_size, received)?;
+                    yield bytes;
                 }
             }
         })
@@ -115,14 +225,14 @@ impl AsyncTransport for PollingTransport {
             data
         };
 
-        let status = self
+        let request = self
             .client
             .post(self.address().await?)
             .body(data_to_send)
-            .send()
-            .await?
-            .status()
-            .as_u16();
+            .build()?;
+        let response = self.client.execute(request).await?;
+        Self::record_version(&self.http_version, &response);
+        let status = response.status().as_u16();
 
         if status != 200 {
             let error = Error::IncompleteHttp(status);
@@ -149,6 +259,55 @@ impl AsyncTransport for PollingTransport {
     }
 }
 
+/// The client that sends the requests of the polling transport, through the
+/// middleware set via `ClientBuilder::http_layer` if there is one.
+#[derive(Clone, Debug)]
+struct HttpClient {
+    client: Client,
+    #[cfg(feature = "tower")]
+    service: Option<Arc<tokio::sync::Mutex<HttpService>>>,
+}
+
+impl HttpClient {
+    #[cfg_attr(not(feature = "tower"), allow(unused_variables))]
+    fn new(client: Client, options: &TransportOptions) -> Self {
+        HttpClient {
+            #[cfg(feature = "tower")]
+            service: options
+                .http_layer
+                .as_ref()
+                .map(|layer| Arc::new(tokio::sync::Mutex::new(layer.service(client.clone())))),
+            client,
+        }
+    }
+
+    fn get(&self, url: Url) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    fn post(&self, url: Url) -> reqwest::RequestBuilder {
+        self.client.post(url)
+    }
+
+    async fn execute(&self, request: Request) -> Result<Response> {
+        #[cfg(feature = "tower")]
+        if let Some(service) = &self.service {
+            // layers like a rate limit keep their state in the service, so all
+            // requests go through the same one instead of a clone of it
+            let response = {
+                let mut service = service.lock().await;
+                service
+                    .ready()
+                    .await
+                    .map_err(Error::from_http_service)?
+                    .call(request)
+            };
+            return response.await.map_err(Error::from_http_service);
+        }
+        Ok(self.client.execute(request).await?)
+    }
+}
+
 impl Debug for PollingTransport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PollingTransport")
@@ -192,4 +351,107 @@ mod test {
         assert_ne!(transport.base_url().await?.to_string(), url);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_retry() -> Result<()> {
+        use std::time::Duration;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        // the first two requests fail at the gateway, the third one reaches the server
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        let server = tokio::spawn(async move {
+            for status in ["503 Service Unavailable", "502 Bad Gateway", "200 OK"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: 2\r\n\r\n4a"
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let client = HttpClient::new(Client::new(), &TransportOptions::default());
+        let retry = PollRetry::new(2, Duration::from_millis(1));
+        let response = PollingTransport::send_request(url.clone(), client.clone(), retry).await?;
+        assert_eq!(response.bytes().await?, Bytes::from_static(b"4a"));
+        server.await.unwrap();
+
+        // without retries the first failure is returned
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n")
+                .await;
+        });
+        assert!(matches!(
+            PollingTransport::send_request(url, client, PollRetry::disabled()).await,
+            Err(Error::IncompleteHttp(502))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_http_layer_keeps_state() -> Result<()> {
+        use crate::asynchronous::http_service::HttpLayer;
+        use std::task::{Context, Poll};
+        use tower::{BoxError, Layer};
+
+        /// Answers the given number of requests itself and fails afterwards.
+        #[derive(Clone)]
+        struct Quota(usize);
+
+        impl<S> Layer<S> for Quota {
+            type Service = Quota;
+
+            fn layer(&self, _: S) -> Quota {
+                self.clone()
+            }
+        }
+
+        impl Service<Request> for Quota {
+            type Response = Response;
+            type Error = BoxError;
+            type Future = std::future::Ready<std::result::Result<Response, BoxError>>;
+
+            fn poll_ready(
+                &mut self,
+                _: &mut Context<'_>,
+            ) -> Poll<std::result::Result<(), BoxError>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, _: Request) -> Self::Future {
+                std::future::ready(match self.0.checked_sub(1) {
+                    Some(remaining) => {
+                        self.0 = remaining;
+                        Ok(Response::from(http::Response::new("ok")))
+                    }
+                    None => Err("quota exceeded".into()),
+                })
+            }
+        }
+
+        let options = TransportOptions {
+            http_layer: Some(HttpLayer::new(Quota(2))),
+            ..Default::default()
+        };
+        let client = HttpClient::new(Client::new(), &options);
+        let url = Url::parse("http://localhost/")?;
+        for _ in 0..2 {
+            client.execute(client.get(url.clone()).build()?).await?;
+        }
+        assert!(matches!(
+            client.execute(client.get(url).build()?).await,
+            Err(Error::HttpMiddleware(_))
+        ));
+        Ok(())
+    }
 }
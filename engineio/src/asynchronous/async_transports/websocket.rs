@@ -1,17 +1,22 @@
 use std::fmt::Debug;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::asynchronous::transport::AsyncTransport;
+use crate::asynchronous::{transport::AsyncTransport, PreparedConnection};
 use crate::error::Result;
+use crate::pool::BufferPool;
+use crate::transport::TransportOptions;
+use crate::{CloseFrame, Error};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::stream::StreamExt;
 use futures_util::Stream;
 use http::HeaderMap;
 use tokio::sync::RwLock;
-use tokio_tungstenite::connect_async;
+use tokio_tungstenite::{client_async_tls_with_config, connect_async_with_config};
 use tungstenite::client::IntoClientRequest;
+use tungstenite::handshake::client::Request;
 use url::Url;
 
 use super::websocket_general::AsyncWebsocketGeneralTransport;
@@ -28,20 +33,69 @@ pub struct WebsocketTransport {
 impl WebsocketTransport {
     /// Creates a new instance over a request that might hold additional headers and an URL.
     pub async fn new(base_url: Url, headers: Option<HeaderMap>) -> Result<Self> {
+        Self::with_options(base_url, headers, None, &TransportOptions::default()).await
+    }
+
+    /// Creates a new instance that performs the websocket handshake over the
+    /// prepared connection if there is one left, otherwise it connects from the
+    /// local binding of the `options`.
+    pub(crate) async fn with_options(
+        base_url: Url,
+        headers: Option<HeaderMap>,
+        prepared: Option<&PreparedConnection>,
+        options: &TransportOptions,
+    ) -> Result<Self> {
         let mut url = base_url;
         url.query_pairs_mut().append_pair("transport", "websocket");
         url.set_scheme("ws").unwrap();
 
-        let mut req = url.clone().into_client_request()?;
-        if let Some(map) = headers {
-            // SAFETY: this unwrap never panics as the underlying request is just initialized and in proper state
-            req.headers_mut().extend(map);
-        }
-
-        let (ws_stream, _) = connect_async(req).await?;
+        let request = || -> Result<Request> {
+            let mut req = url.clone().into_client_request()?;
+            if let Some(map) = headers.clone() {
+                // SAFETY: this unwrap never panics as the underlying request is just initialized and in proper state
+                req.headers_mut().extend(map);
+            }
+            Ok(req)
+        };
+
+        let prepared_stream = match prepared {
+            Some(prepared) => {
+                prepared
+                    .websocket(request()?, options.websocket_config())
+                    .await
+            }
+            None => None,
+        };
+        let ws_stream = match prepared_stream {
+            Some(ws_stream) => ws_stream,
+            None => match options
+                .local_binding
+                .connect(&url, options.resolver.as_ref())
+                .await
+            {
+                Some(stream) => {
+                    client_async_tls_with_config(
+                        request()?,
+                        stream?,
+                        options.websocket_config(),
+                        None,
+                    )
+                    .await
+                    .map_err(Error::from_websocket_handshake)?
+                    .0
+                }
+                None => {
+                    connect_async_with_config(request()?, options.websocket_config(), false)
+                        .await
+                        .map_err(Error::from_websocket_handshake)?
+                        .0
+                }
+            },
+        };
         let (sen, rec) = ws_stream.split();
 
-        let inner = AsyncWebsocketGeneralTransport::new(sen, rec).await;
+        let mut inner = AsyncWebsocketGeneralTransport::new(sen, rec).await;
+        inner.set_write_retry(options.write_retry);
         Ok(WebsocketTransport {
             inner,
             base_url: Arc::new(RwLock::new(url)),
@@ -54,6 +108,36 @@ impl WebsocketTransport {
         self.inner.upgrade().await
     }
 
+    /// Sets the pool that incoming binary frames are copied into.
+    pub(crate) fn set_buffer_pool(&mut self, buffer_pool: BufferPool) {
+        self.inner.set_buffer_pool(buffer_pool)
+    }
+
+    /// Starts sending websocket `Ping` frames in the given interval.
+    pub(crate) fn start_keepalive(&self, interval: Duration) {
+        self.inner.start_keepalive(interval)
+    }
+
+    /// Sends a websocket `Ping` frame and waits for the `Pong`.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        self.inner.probe().await
+    }
+
+    /// Stops the transport and closes the websocket in the background.
+    pub(crate) fn abort(&self) {
+        self.inner.abort()
+    }
+
+    /// Closes the websocket with a close frame.
+    pub(crate) async fn close(&self) {
+        self.inner.close().await
+    }
+
+    /// The close frame the server ended the websocket with, if any.
+    pub(crate) fn close_frame(&self) -> Option<CloseFrame> {
+        self.inner.close_frame()
+    }
+
     pub(crate) async fn poll_next(&self) -> Result<Option<Bytes>> {
         self.inner.poll_next().await
     }
@@ -1,18 +1,41 @@
-use std::{borrow::Cow, str::from_utf8, sync::Arc, task::Poll};
+use std::{
+    borrow::Cow,
+    pin::Pin,
+    str::from_utf8,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::Poll,
+    time::Duration,
+};
 
-use crate::{error::Result, Error, Packet, PacketId};
-use bytes::{BufMut, Bytes, BytesMut};
+use crate::{
+    error::Result, pool::BufferPool, transport::WriteRetry, CloseFrame, Error, Packet, PacketId,
+};
+use bytes::{BufMut, Bytes};
 use futures_util::{
+    future::poll_fn,
     ready,
     stream::{SplitSink, SplitStream},
-    FutureExt, SinkExt, Stream, StreamExt,
+    task::AtomicWaker,
+    FutureExt, Sink, SinkExt, Stream, StreamExt,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{Mutex, Notify},
 };
-use tokio::{net::TcpStream, sync::Mutex};
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::WebSocketStream;
 use tungstenite::Message;
 
-type AsyncWebsocketSender = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
-type AsyncWebsocketReceiver = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+/// The shortest interval of the keepalive `Ping` frames.
+pub(crate) const MIN_KEEPALIVE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The halves of a websocket, boxed so that the client and the server can use
+/// websockets over different streams.
+type AsyncWebsocketSender = Pin<Box<dyn Sink<Message, Error = tungstenite::Error> + Send>>;
+type AsyncWebsocketReceiver =
+    Pin<Box<dyn Stream<Item = std::result::Result<Message, tungstenite::Error>> + Send>>;
 
 /// A general purpose asynchronous websocket transport type. Holds
 /// the sender and receiver stream of a websocket connection
@@ -22,19 +45,48 @@ type AsyncWebsocketReceiver = SplitStream<WebSocketStream<MaybeTlsStream<TcpStre
 pub(crate) struct AsyncWebsocketGeneralTransport {
     sender: Arc<Mutex<AsyncWebsocketSender>>,
     receiver: Arc<Mutex<AsyncWebsocketReceiver>>,
+    buffer_pool: BufferPool,
+    aborted: Arc<AtomicBool>,
+    /// Wakes the task that waits for the next frame once the transport is
+    /// aborted.
+    abort_waker: Arc<AtomicWaker>,
+    /// Notified whenever a `Pong` frame is received.
+    pong: Arc<Notify>,
+    /// The close frame the server ended the websocket with.
+    close_frame: Arc<std::sync::Mutex<Option<CloseFrame>>>,
+    write_retry: WriteRetry,
 }
 
 impl AsyncWebsocketGeneralTransport {
-    pub(crate) async fn new(
-        sender: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-        receiver: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    ) -> Self {
+    pub(crate) async fn new<S>(
+        sender: SplitSink<WebSocketStream<S>, Message>,
+        receiver: SplitStream<WebSocketStream<S>>,
+    ) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
         AsyncWebsocketGeneralTransport {
-            sender: Arc::new(Mutex::new(sender)),
-            receiver: Arc::new(Mutex::new(receiver)),
+            sender: Arc::new(Mutex::new(Box::pin(sender))),
+            receiver: Arc::new(Mutex::new(Box::pin(receiver))),
+            buffer_pool: BufferPool::default(),
+            aborted: Arc::new(AtomicBool::new(false)),
+            abort_waker: Arc::default(),
+            pong: Arc::new(Notify::new()),
+            close_frame: Arc::default(),
+            write_retry: WriteRetry::default(),
         }
     }
 
+    /// Sets the pool that incoming binary frames are copied into.
+    pub(crate) fn set_buffer_pool(&mut self, buffer_pool: BufferPool) {
+        self.buffer_pool = buffer_pool;
+    }
+
+    /// Sets how failed writes of emitted packets are retried.
+    pub(crate) fn set_write_retry(&mut self, write_retry: WriteRetry) {
+        self.write_retry = write_retry;
+    }
+
     /// Sends probe packet to ensure connection is valid, then sends upgrade
     /// request
     pub(crate) async fn upgrade(&self) -> Result<()> {
@@ -47,13 +99,10 @@ impl AsyncWebsocketGeneralTransport {
             ))?)))
             .await?;
 
-        let msg = receiver
-            .next()
-            .await
-            .ok_or(Error::IllegalWebsocketUpgrade())??;
+        let msg = receiver.next().await.ok_or(Error::UpgradeRejected())??;
 
         if msg.into_data() != Bytes::from(Packet::new(PacketId::Pong, Bytes::from("probe"))) {
-            return Err(Error::InvalidPacket());
+            return Err(Error::UpgradeRejected());
         }
 
         sender
@@ -65,6 +114,95 @@ impl AsyncWebsocketGeneralTransport {
         Ok(())
     }
 
+    /// Sends a websocket `Ping` frame in the given interval, which is raised
+    /// to [`MIN_KEEPALIVE_INTERVAL`] if it's shorter. This is independent of the
+    /// engine.io heartbeat and stops once the transport is dropped or sending a
+    /// frame fails. Needs to be called within a tokio runtime.
+    pub(crate) fn start_keepalive(&self, interval: Duration) {
+        let sender = Arc::downgrade(&self.sender);
+        let aborted = self.aborted.clone();
+        let interval = interval.max(MIN_KEEPALIVE_INTERVAL);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // the first tick completes immediately
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                if aborted.load(Ordering::Acquire) {
+                    break;
+                }
+                let Some(sender) = sender.upgrade() else {
+                    break;
+                };
+                if sender
+                    .lock()
+                    .await
+                    .send(Message::Ping(Vec::new()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Sends a websocket `Ping` frame and waits for the `Pong` of the server,
+    /// which proves that the connection still works in both directions. The
+    /// `Pong` is read by the stream of the transport, which needs to be polled
+    /// meanwhile.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        let pong = self.pong.notified();
+        tokio::pin!(pong);
+        // a pong that arrives before the ping was flushed must not be missed
+        pong.as_mut().enable();
+
+        self.sender
+            .lock()
+            .await
+            .send(Message::Ping(Vec::new()))
+            .await?;
+        pong.await;
+        Ok(())
+    }
+
+    /// Stops the transport right away: no more keepalive frames are sent, a
+    /// task waiting for the next frame gets the end of the stream, and the
+    /// websocket is closed without waiting for the peer. The socket makes sure
+    /// that no packets are emitted afterwards. The websocket is only closed
+    /// within a tokio runtime, otherwise it's closed once it's dropped.
+    pub(crate) fn abort(&self) {
+        self.aborted.store(true, Ordering::Release);
+        self.abort_waker.wake();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let sender = self.sender.clone();
+            runtime.spawn(async move {
+                let _ = sender.lock().await.close().await;
+            });
+        }
+    }
+
+    /// The close frame the server ended the websocket with, if any.
+    pub(crate) fn close_frame(&self) -> Option<CloseFrame> {
+        self.close_frame.lock().ok()?.clone()
+    }
+
+    fn closed(&self, frame: Option<tungstenite::protocol::CloseFrame>) {
+        if let (Some(frame), Ok(mut close_frame)) = (frame, self.close_frame.lock()) {
+            *close_frame = Some(CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.to_string(),
+            });
+        }
+    }
+
+    /// Closes the websocket with a close frame.
+    pub(crate) async fn close(&self) {
+        let _ = self.sender.lock().await.close().await;
+    }
+
     pub(crate) async fn emit(&self, data: Bytes, is_binary_att: bool) -> Result<()> {
         let mut sender = self.sender.lock().await;
 
@@ -74,27 +212,94 @@ impl AsyncWebsocketGeneralTransport {
             Message::text(Cow::Borrowed(std::str::from_utf8(data.as_ref())?))
         };
 
-        sender.send(message).await?;
+        self.send_retrying(&mut sender, message).await
+    }
 
-        Ok(())
+    /// Sends `message` and retries transient failures according to the
+    /// [`WriteRetry`]. The frame is only sent again while the websocket didn't
+    /// queue it, as it can't tell how much of a queued frame was written, so
+    /// afterwards only the flush is retried. The transport is aborted once
+    /// the write failed for good.
+    async fn send_retrying(
+        &self,
+        sender: &mut AsyncWebsocketSender,
+        message: Message,
+    ) -> Result<()> {
+        let mut unqueued = Some(message);
+        let mut attempt = 0;
+        loop {
+            let error = match unqueued.take() {
+                Some(message) => match poll_fn(|cx| sender.as_mut().poll_ready(cx)).await {
+                    Err(error) => {
+                        unqueued = Some(message);
+                        error
+                    }
+                    Ok(()) => match sender.as_mut().start_send(message) {
+                        Err(tungstenite::Error::WriteBufferFull(message)) => {
+                            unqueued = Some(message.clone());
+                            tungstenite::Error::WriteBufferFull(message)
+                        }
+                        Err(error) => error,
+                        Ok(()) => match sender.flush().await {
+                            Ok(()) => return Ok(()),
+                            Err(error) => error,
+                        },
+                    },
+                },
+                None => match sender.flush().await {
+                    Ok(()) => return Ok(()),
+                    Err(error) => error,
+                },
+            };
+
+            match self.write_retry.delay(attempt) {
+                Some(delay) if WriteRetry::is_transient(&error) => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                _ => {
+                    self.abort();
+                    return Err(error.into());
+                }
+            }
+        }
+    }
+
+    /// Prefixes a binary frame with the id of a message packet.
+    fn binary_message(&self, data: &[u8]) -> Bytes {
+        self.buffer_pool.encode(data.len() + 1, |msg| {
+            msg.put_u8(PacketId::Message as u8);
+            msg.put(data);
+        })
     }
 
     pub(crate) async fn poll_next(&self) -> Result<Option<Bytes>> {
         loop {
+            if self.aborted.load(Ordering::Acquire) {
+                return Ok(None);
+            }
             let mut receiver = self.receiver.lock().await;
-            let next = receiver.next().await;
+            let next = poll_fn(|cx| {
+                self.abort_waker.register(cx.waker());
+                if self.aborted.load(Ordering::Acquire) {
+                    return Poll::Ready(None);
+                }
+                receiver.poll_next_unpin(cx).map(Some)
+            })
+            .await;
+            let Some(next) = next else {
+                return Ok(None);
+            };
             match next {
                 Some(Ok(Message::Text(str))) => return Ok(Some(Bytes::from(str))),
                 Some(Ok(Message::Binary(data))) => {
-                    let mut msg = BytesMut::with_capacity(data.len() + 1);
-                    msg.put_u8(PacketId::Message as u8);
-                    msg.put(data.as_ref());
-
-                    return Ok(Some(msg.freeze()));
+                    return Ok(Some(self.binary_message(&data)));
                 }
+                Some(Ok(Message::Pong(_))) => self.pong.notify_waiters(),
+                Some(Ok(Message::Close(frame))) => self.closed(frame),
                 // ignore packets other than text and binary
                 Some(Ok(_)) => (),
-                Some(Err(err)) => return Err(err.into()),
+                Some(Err(err)) => return Err(Error::from_websocket_read(err)),
                 None => return Ok(None),
             }
         }
@@ -109,23 +314,150 @@ impl Stream for AsyncWebsocketGeneralTransport {
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
         loop {
+            // registered before checking the flag, so that an abort in
+            // between isn't missed
+            self.abort_waker.register(cx.waker());
+            if self.aborted.load(Ordering::Acquire) {
+                return Poll::Ready(None);
+            }
             let mut lock = ready!(Box::pin(self.receiver.lock()).poll_unpin(cx));
             let next = ready!(lock.poll_next_unpin(cx));
 
             match next {
                 Some(Ok(Message::Text(str))) => return Poll::Ready(Some(Ok(Bytes::from(str)))),
                 Some(Ok(Message::Binary(data))) => {
-                    let mut msg = BytesMut::with_capacity(data.len() + 1);
-                    msg.put_u8(PacketId::Message as u8);
-                    msg.put(data.as_ref());
-
-                    return Poll::Ready(Some(Ok(msg.freeze())));
+                    return Poll::Ready(Some(Ok(self.binary_message(&data))));
                 }
+                Some(Ok(Message::Pong(_))) => self.pong.notify_waiters(),
+                Some(Ok(Message::Close(frame))) => self.closed(frame),
                 // ignore packets other than text and binary
                 Some(Ok(_)) => (),
-                Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                Some(Err(err)) => return Poll::Ready(Some(Err(Error::from_websocket_read(err)))),
                 None => return Poll::Ready(None),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{io, sync::atomic::AtomicUsize, task::Context};
+    use tokio::io::{DuplexStream, ReadBuf};
+    use tungstenite::protocol::Role;
+
+    /// A stream whose next writes fail with a timeout.
+    struct FlakyStream {
+        inner: DuplexStream,
+        failing_writes: Arc<AtomicUsize>,
+    }
+
+    impl AsyncRead for FlakyStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for FlakyStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let failing =
+                self.failing_writes
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+            if failing.is_ok() {
+                return Poll::Ready(Err(io::ErrorKind::TimedOut.into()));
+            }
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    async fn flaky_pair(
+        write_retry: WriteRetry,
+    ) -> (
+        AsyncWebsocketGeneralTransport,
+        WebSocketStream<DuplexStream>,
+        Arc<AtomicUsize>,
+    ) {
+        let (client, server) = tokio::io::duplex(1024);
+        let failing_writes = Arc::new(AtomicUsize::new(0));
+        let client = FlakyStream {
+            inner: client,
+            failing_writes: failing_writes.clone(),
+        };
+        let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let (sender, receiver) = client.split();
+        let mut transport = AsyncWebsocketGeneralTransport::new(sender, receiver).await;
+        transport.set_write_retry(write_retry);
+        (transport, server, failing_writes)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_retry() -> Result<()> {
+        let (transport, mut server, failing_writes) = flaky_pair(WriteRetry::default()).await;
+        failing_writes.store(2, Ordering::Release);
+        transport.emit(Bytes::from("4hello"), false).await?;
+        transport.emit(Bytes::from("4world"), false).await?;
+
+        // the frame that failed to be written is neither lost nor duplicated
+        assert_eq!(server.next().await.unwrap()?, Message::text("4hello"));
+        assert_eq!(server.next().await.unwrap()?, Message::text("4world"));
+        assert!(!transport.aborted.load(Ordering::Acquire));
+
+        failing_writes.store(usize::MAX, Ordering::Release);
+        transport
+            .emit(Bytes::from("4lost"), false)
+            .await
+            .expect_err("the retries are exhausted");
+        assert!(transport.aborted.load(Ordering::Acquire));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keepalive_zero_interval() -> Result<()> {
+        let (transport, mut server, _) = flaky_pair(WriteRetry::default()).await;
+        transport.start_keepalive(Duration::ZERO);
+        let ping = tokio::time::timeout(Duration::from_secs(1), server.next()).await;
+        assert!(matches!(ping.unwrap(), Some(Ok(Message::Ping(_)))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_abort() -> Result<()> {
+        // the server stays silent, the readers only end due to the abort
+        let (transport, mut server, _) = flaky_pair(WriteRetry::default()).await;
+        let reader = transport.clone();
+        let next = tokio::spawn(async move { reader.poll_next().await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        transport.abort();
+        let next = tokio::time::timeout(Duration::from_secs(5), next).await;
+        assert!(next.unwrap().unwrap()?.is_none());
+        // the websocket is closed
+        let close = tokio::time::timeout(Duration::from_secs(5), server.next()).await;
+        assert!(matches!(close.unwrap(), Some(Ok(Message::Close(_)))));
+
+        let (transport, _server, _) = flaky_pair(WriteRetry::default()).await;
+        let mut stream = transport.clone();
+        let next = tokio::spawn(async move { stream.next().await.is_none() });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        transport.abort();
+        let next = tokio::time::timeout(Duration::from_secs(5), next).await;
+        assert!(next.unwrap().unwrap());
+        Ok(())
+    }
+}
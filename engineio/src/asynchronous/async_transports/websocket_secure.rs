@@ -1,9 +1,13 @@
 use std::fmt::Debug;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::asynchronous::transport::AsyncTransport;
+use crate::asynchronous::{transport::AsyncTransport, PreparedConnection};
 use crate::error::Result;
+use crate::pool::BufferPool;
+use crate::transport::TransportOptions;
+use crate::{CloseFrame, Error};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::Stream;
@@ -11,9 +15,10 @@ use futures_util::StreamExt;
 use http::HeaderMap;
 use native_tls::TlsConnector;
 use tokio::sync::RwLock;
-use tokio_tungstenite::connect_async_tls_with_config;
 use tokio_tungstenite::Connector;
+use tokio_tungstenite::{client_async_tls_with_config, connect_async_tls_with_config};
 use tungstenite::client::IntoClientRequest;
+use tungstenite::handshake::client::Request;
 use url::Url;
 
 use super::websocket_general::AsyncWebsocketGeneralTransport;
@@ -29,39 +34,79 @@ pub struct WebsocketSecureTransport {
 
 impl WebsocketSecureTransport {
     /// Creates a new instance over a request that might hold additional headers, a possible
-    /// Tls connector and an URL.
+    /// Tls connector and an URL. The websocket handshake is performed over the prepared
+    /// connection if there is one left, otherwise it connects from the local binding of
+    /// the `options`.
     pub(crate) async fn new(
         base_url: Url,
         tls_config: Option<TlsConnector>,
         headers: Option<HeaderMap>,
+        prepared: Option<&PreparedConnection>,
+        options: &TransportOptions,
     ) -> Result<Self> {
         let mut url = base_url;
         url.query_pairs_mut().append_pair("transport", "websocket");
         url.set_scheme("wss").unwrap();
 
-        let mut req = url.clone().into_client_request()?;
-        if let Some(map) = headers {
-            // SAFETY: this unwrap never panics as the underlying request is just initialized and in proper state
-            req.headers_mut().extend(map);
-        }
+        let request = || -> Result<Request> {
+            let mut req = url.clone().into_client_request()?;
+            if let Some(map) = headers.clone() {
+                // SAFETY: this unwrap never panics as the underlying request is just initialized and in proper state
+                req.headers_mut().extend(map);
+            }
+            Ok(req)
+        };
 
-        // `disable_nagle` Sets the value of the TCP_NODELAY option on this socket.
-        //
-        // If set to `true`, this option disables the Nagle algorithm.
-        // This means that segments are always sent as soon as possible, even if there is only a small amount of data.
-        // When `false`, data is buffered until there is a sufficient amount to send out, thereby avoiding the frequent sending of small packets.
-        //
-        // See the docs: https://docs.rs/tokio/latest/tokio/net/struct.TcpStream.html#method.set_nodelay
-        let (ws_stream, _) = connect_async_tls_with_config(
-            req,
-            None,
-            /*disable_nagle=*/ false,
-            tls_config.map(Connector::NativeTls),
-        )
-        .await?;
+        let prepared_stream = match prepared {
+            Some(prepared) => {
+                prepared
+                    .websocket(request()?, options.websocket_config())
+                    .await
+            }
+            None => None,
+        };
+        let ws_stream = match prepared_stream {
+            Some(ws_stream) => ws_stream,
+            None => match options
+                .local_binding
+                .connect(&url, options.resolver.as_ref())
+                .await
+            {
+                Some(stream) => {
+                    client_async_tls_with_config(
+                        request()?,
+                        stream?,
+                        options.websocket_config(),
+                        tls_config.map(Connector::NativeTls),
+                    )
+                    .await
+                    .map_err(Error::from_websocket_handshake)?
+                    .0
+                }
+                None => {
+                    // `disable_nagle` Sets the value of the TCP_NODELAY option on this socket.
+                    //
+                    // If set to `true`, this option disables the Nagle algorithm.
+                    // This means that segments are always sent as soon as possible, even if there is only a small amount of data.
+                    // When `false`, data is buffered until there is a sufficient amount to send out, thereby avoiding the frequent sending of small packets.
+                    //
+                    // See the docs: https://docs.rs/tokio/latest/tokio/net/struct.TcpStream.html#method.set_nodelay
+                    connect_async_tls_with_config(
+                        request()?,
+                        options.websocket_config(),
+                        /*disable_nagle=*/ false,
+                        tls_config.map(Connector::NativeTls),
+                    )
+                    .await
+                    .map_err(Error::from_websocket_handshake)?
+                    .0
+                }
+            },
+        };
 
         let (sen, rec) = ws_stream.split();
-        let inner = AsyncWebsocketGeneralTransport::new(sen, rec).await;
+        let mut inner = AsyncWebsocketGeneralTransport::new(sen, rec).await;
+        inner.set_write_retry(options.write_retry);
 
         Ok(WebsocketSecureTransport {
             inner,
@@ -75,6 +120,36 @@ impl WebsocketSecureTransport {
         self.inner.upgrade().await
     }
 
+    /// Sets the pool that incoming binary frames are copied into.
+    pub(crate) fn set_buffer_pool(&mut self, buffer_pool: BufferPool) {
+        self.inner.set_buffer_pool(buffer_pool)
+    }
+
+    /// Starts sending websocket `Ping` frames in the given interval.
+    pub(crate) fn start_keepalive(&self, interval: Duration) {
+        self.inner.start_keepalive(interval)
+    }
+
+    /// Sends a websocket `Ping` frame and waits for the `Pong`.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        self.inner.probe().await
+    }
+
+    /// Stops the transport and closes the websocket in the background.
+    pub(crate) fn abort(&self) {
+        self.inner.abort()
+    }
+
+    /// Closes the websocket with a close frame.
+    pub(crate) async fn close(&self) {
+        self.inner.close().await
+    }
+
+    /// The close frame the server ended the websocket with, if any.
+    pub(crate) fn close_frame(&self) -> Option<CloseFrame> {
+        self.inner.close_frame()
+    }
+
     pub(crate) async fn poll_next(&self) -> Result<Option<Bytes>> {
         self.inner.poll_next().await
     }
@@ -143,6 +218,8 @@ mod test {
             Url::from_str(&url[..])?,
             Some(crate::test::tls_connector()?),
             None,
+            None,
+            &TransportOptions::default(),
         )
         .await
     }
@@ -2,7 +2,7 @@ use bytes::Bytes;
 use futures_util::future::BoxFuture;
 use std::{fmt::Debug, ops::Deref, sync::Arc};
 
-use crate::Packet;
+use crate::{DataInfo, DisconnectDescription, Packet};
 
 /// Internal type, provides a way to store futures and return them in a boxed manner.
 pub(crate) type DynAsyncCallback<I> = dyn 'static + Send + Sync + Fn(I) -> BoxFuture<'static, ()>;
@@ -71,12 +71,12 @@ impl Debug for OptionalCallback<Packet> {
 }
 
 #[cfg_attr(tarpaulin, ignore)]
-impl Debug for OptionalCallback<Bytes> {
+impl Debug for OptionalCallback<(Bytes, DataInfo)> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         f.write_fmt(format_args!(
             "Callback({:?})",
             if self.inner.is_some() {
-                "Fn(Bytes)"
+                "Fn(Bytes, DataInfo)"
             } else {
                 "None"
             }
@@ -90,3 +90,17 @@ impl<I> Deref for OptionalCallback<I> {
         &self.inner
     }
 }
+
+#[cfg_attr(tarpaulin, ignore)]
+impl Debug for OptionalCallback<DisconnectDescription> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        f.write_fmt(format_args!(
+            "Callback({:?})",
+            if self.inner.is_some() {
+                "Fn(DisconnectDescription)"
+            } else {
+                "None"
+            }
+        ))
+    }
+}
@@ -3,7 +3,9 @@ use std::{fmt::Debug, pin::Pin};
 use crate::{
     asynchronous::{async_socket::Socket as InnerSocket, generator::StreamGenerator},
     error::Result,
-    Packet,
+    packet::HandshakePacket,
+    pool::BufferPoolStats,
+    EmitTimingStats, HttpVersion, Packet, TransportKind,
 };
 use async_stream::try_stream;
 use futures_util::{Stream, StreamExt};
@@ -44,6 +46,14 @@ impl Client {
         self.socket.disconnect().await
     }
 
+    /// Closes the connection right away, without sending a close packet to the
+    /// server. Unlike [`Client::disconnect`], nothing is sent to the server
+    /// anymore once this returns, and a running poll is cancelled. The server
+    /// notices the closed connection by the missing heartbeat.
+    pub fn abort(&self) {
+        self.socket.abort()
+    }
+
     /// Sends a packet to the server.
     pub async fn emit(&self, packet: Packet) -> Result<()> {
         self.socket.emit(packet).await
@@ -67,6 +77,35 @@ impl Client {
     pub fn is_connected(&self) -> bool {
         self.socket.is_connected()
     }
+
+    /// Returns the handshake data the server sent when opening the connection,
+    /// including any extension fields.
+    pub fn handshake(&self) -> &HandshakePacket {
+        self.socket.handshake()
+    }
+
+    /// Returns statistics about the pool of buffers that packets are encoded with.
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.socket.buffer_pool_stats()
+    }
+
+    /// Returns how long the emits waited for the transport and how long the
+    /// transport took to write them, `None` unless enabled via
+    /// [`crate::asynchronous::ClientBuilder::emit_timing`].
+    pub fn emit_timing_stats(&self) -> Option<EmitTimingStats> {
+        self.socket.emit_timing_stats()
+    }
+
+    /// Returns the HTTP version that was negotiated with the server, `None` if
+    /// a websocket transport is used.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        self.socket.http_version()
+    }
+
+    /// Returns the transport of the connection.
+    pub fn transport_kind(&self) -> TransportKind {
+        self.socket.transport_kind()
+    }
 }
 
 impl Stream for Client {
@@ -153,6 +192,7 @@ mod test {
 
         assert!(sut.next().await.unwrap().is_ok());
 
+        #[cfg(feature = "websocket")]
         assert!(builder(Url::parse("fake://fake.fake").unwrap())
             .build_websocket()
             .await
@@ -293,6 +333,7 @@ mod test {
     }
 
     #[tokio::test]
+    #[cfg(feature = "websocket")]
     async fn test_connection_wss() -> Result<()> {
         let url = crate::test::engine_io_polling_server()?;
         assert!(builder(url).build_websocket_with_upgrade().await.is_err());
@@ -330,6 +371,7 @@ mod test {
     }
 
     #[tokio::test]
+    #[cfg(feature = "websocket")]
     async fn test_connection_ws() -> Result<()> {
         let url = crate::test::engine_io_polling_server()?;
         assert!(builder(url.clone()).build_websocket().await.is_err());
@@ -394,4 +436,217 @@ mod test {
         let _ = builder(url).headers(headers).build().await?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_loopback() -> Result<()> {
+        use crate::asynchronous::async_transports::loopback;
+        use std::time::Duration;
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let mut sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .build_loopback(transport)
+            .await?;
+        sut.connect().await?;
+        // the first pong triggers the ping cycle
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Pong, ""))
+        );
+
+        server.send(Packet::new(PacketId::Ping, ""))?;
+        server.send(Packet::new(PacketId::Message, "hello client"))?;
+        assert_eq!(sut.next().await.unwrap()?, Packet::new(PacketId::Ping, ""));
+        assert_eq!(
+            sut.next().await.unwrap()?,
+            Packet::new(PacketId::Message, "hello client")
+        );
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Pong, ""))
+        );
+
+        sut.emit(Packet::new(PacketId::Message, "respond")).await?;
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Message, "respond"))
+        );
+
+        sut.disconnect().await?;
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Close, ""))
+        );
+        assert!(!sut.is_connected());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chaos() -> Result<()> {
+        use crate::asynchronous::async_transports::{loopback, Chaos};
+        use std::time::Duration;
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let mut sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .chaos(Chaos::new().disconnect_rate(1.0))
+            .build_loopback(transport)
+            .await?;
+        sut.connect().await.expect_err("the connection breaks down");
+
+        server.send(Packet::new(PacketId::Message, "lost"))?;
+        assert!(sut.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_emit_timing() -> Result<()> {
+        use crate::asynchronous::async_transports::{loopback, Chaos};
+        use std::time::Duration;
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let delay = Duration::from_millis(100);
+        let sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .chaos(Chaos::new().latency(delay, delay))
+            .emit_timing(true)
+            .build_loopback(transport)
+            .await?;
+        sut.connect().await?;
+        let connected = sut.emit_timing_stats().unwrap();
+
+        // the second emit waits for the slow write of the first one
+        let (first, second) = futures_util::future::join(
+            sut.emit(Packet::new(PacketId::Message, "first")),
+            sut.emit(Packet::new(PacketId::Message, "second")),
+        )
+        .await;
+        first?;
+        second?;
+        let stats = sut.emit_timing_stats().unwrap();
+        assert_eq!(stats.emits, connected.emits + 2);
+        assert!(stats.max_queue_wait >= delay);
+        assert!(stats.max_write >= delay);
+        assert!(stats.write - connected.write >= 2 * delay);
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .build_loopback(transport)
+            .await?;
+        assert_eq!(sut.emit_timing_stats(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transport_factory() -> Result<()> {
+        use crate::asynchronous::async_transports::loopback;
+        use futures_util::FutureExt;
+        use std::time::Duration;
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .transport_factory(move |url| {
+                assert_eq!(url.path(), "/engine.io/");
+                let transport = transport.clone();
+                async move { Ok(transport) }.boxed()
+            })
+            .build_with_fallback()
+            .await?;
+        assert_eq!(sut.transport_kind(), TransportKind::Custom);
+
+        sut.connect().await?;
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Pong, ""))
+        );
+        sut.emit(Packet::new(PacketId::Message, "hello")).await?;
+        assert_eq!(
+            server.receive().await,
+            Some(Packet::new(PacketId::Message, "hello"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_ordering() -> Result<()> {
+        use crate::asynchronous::{async_transports::loopback, OpenOrdering};
+        use futures_util::FutureExt;
+        use std::{
+            sync::{
+                atomic::{AtomicBool, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+        use tokio::sync::mpsc;
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let opened = Arc::new(AtomicBool::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let on_open = opened.clone();
+        let sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .open_ordering(OpenOrdering::Completed)
+            .on_open(move |_| {
+                let opened = on_open.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    opened.store(true, Ordering::Release);
+                }
+                .boxed()
+            })
+            .on_packet(move |packet| {
+                let (opened, tx) = (opened.clone(), tx.clone());
+                async move {
+                    let _ = tx.send((packet, opened.load(Ordering::Acquire)));
+                }
+                .boxed()
+            })
+            .build_loopback(transport)
+            .await?;
+        sut.connect().await?;
+        server.send(Packet::new(PacketId::Message, "first"))?;
+
+        let mut stream = sut.clone();
+        tokio::spawn(async move { while stream.next().await.is_some() {} });
+        let (packet, opened) = rx.recv().await.unwrap();
+        assert_eq!(packet, Packet::new(PacketId::Message, "first"));
+        assert!(opened, "on_packet ran before on_open completed");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_open_ordering_panic() -> Result<()> {
+        use crate::asynchronous::{async_transports::loopback, OpenOrdering};
+        use futures_util::FutureExt;
+        use std::time::Duration;
+        use tokio::{sync::mpsc, time::timeout};
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let sut = ClientBuilder::new(Url::parse("http://localhost/").unwrap())
+            .open_ordering(OpenOrdering::Completed)
+            .on_open(|_| async { panic!("on_open failed") }.boxed())
+            .on_packet(move |packet| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send(packet);
+                }
+                .boxed()
+            })
+            .build_loopback(transport)
+            .await?;
+        sut.connect().await?;
+        server.send(Packet::new(PacketId::Message, "first"))?;
+
+        // a panicking on_open doesn't hold back the packets
+        let mut stream = sut.clone();
+        tokio::spawn(async move { while stream.next().await.is_some() {} });
+        let packet = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert_eq!(packet, Some(Packet::new(PacketId::Message, "first")));
+        Ok(())
+    }
 }
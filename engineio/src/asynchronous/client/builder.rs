@@ -1,18 +1,40 @@
+#[cfg(any(test, feature = "test-support"))]
+use crate::asynchronous::async_transports::{
+    Chaos, ChaosTransport, LoopbackTransport, MockTransport,
+};
+#[cfg(feature = "websocket")]
+use crate::asynchronous::async_transports::{WebsocketSecureTransport, WebsocketTransport};
+#[cfg(feature = "tower")]
+use crate::asynchronous::{HttpLayer, HttpService};
 use crate::{
     asynchronous::{
-        async_socket::Socket as InnerSocket,
-        async_transports::{PollingTransport, WebsocketSecureTransport, WebsocketTransport},
+        async_socket::{HalfOpenProbe, OpenOrdering, Socket as InnerSocket},
+        async_transports::{PollingTransport, TransportFactory},
         callback::OptionalCallback,
-        transport::AsyncTransport,
+        transport::{AsyncTransport, AsyncTransportType},
+        PreparedConnection,
     },
     error::Result,
     header::HeaderMap,
     packet::HandshakePacket,
-    Error, Packet, ENGINE_IO_VERSION,
+    pool::{BufferPool, DEFAULT_BUFFER_POOL_SIZE},
+    resolver::{Resolver, SharedResolver},
+    timing::EmitTimer,
+    transport::{HttpProtocol, PollRetry, TransportOptions},
+    DataInfo, DisconnectDescription, Error, Packet, ENGINE_IO_VERSION,
+};
+#[cfg(feature = "websocket")]
+use crate::{
+    transport::WriteRetry,
+    upgrade::{SharedUpgradeStore, UpgradeStore},
 };
 use bytes::Bytes;
 use futures_util::{future::BoxFuture, StreamExt};
 use native_tls::TlsConnector;
+use std::net::IpAddr;
+use std::time::Duration;
+#[cfg(feature = "tower")]
+use tower::{BoxError, Layer, Service};
 use url::Url;
 
 use super::Client;
@@ -25,11 +47,33 @@ pub struct ClientBuilder {
     handshake: Option<HandshakePacket>,
     on_error: OptionalCallback<String>,
     on_open: OptionalCallback<()>,
-    on_close: OptionalCallback<()>,
-    on_data: OptionalCallback<Bytes>,
+    on_close: OptionalCallback<DisconnectDescription>,
+    on_data: OptionalCallback<(Bytes, DataInfo)>,
     on_packet: OptionalCallback<Packet>,
+    #[cfg(feature = "websocket")]
+    on_upgrade: OptionalCallback<()>,
+    #[cfg(feature = "websocket")]
+    websocket_ping_interval: Option<Duration>,
+    #[cfg(feature = "websocket")]
+    upgrade_store: Option<SharedUpgradeStore>,
+    buffer_pool_size: usize,
+    transport_options: TransportOptions,
+    prepared: Option<PreparedConnection>,
+    half_open_probe: Option<HalfOpenProbe>,
+    emit_timing: bool,
+    open_ordering: OpenOrdering,
+    transport_factory: Option<TransportFactory>,
+    chaos: Option<Faults>,
 }
 
+/// The faults of [`ClientBuilder::chaos`], which only exists with the
+/// `test-support` feature.
+#[cfg(any(test, feature = "test-support"))]
+type Faults = Chaos;
+#[cfg(not(any(test, feature = "test-support")))]
+#[derive(Debug, Clone)]
+enum Faults {}
+
 impl ClientBuilder {
     pub fn new(url: Url) -> Self {
         let mut url = url;
@@ -50,6 +94,20 @@ impl ClientBuilder {
             on_error: OptionalCallback::default(),
             on_open: OptionalCallback::default(),
             on_packet: OptionalCallback::default(),
+            #[cfg(feature = "websocket")]
+            on_upgrade: OptionalCallback::default(),
+            #[cfg(feature = "websocket")]
+            websocket_ping_interval: None,
+            #[cfg(feature = "websocket")]
+            upgrade_store: None,
+            buffer_pool_size: DEFAULT_BUFFER_POOL_SIZE,
+            transport_options: TransportOptions::default(),
+            prepared: None,
+            half_open_probe: None,
+            emit_timing: false,
+            open_ordering: OpenOrdering::default(),
+            transport_factory: None,
+            chaos: None,
         }
     }
 
@@ -59,17 +117,241 @@ impl ClientBuilder {
         self
     }
 
-    /// Specify transport's HTTP headers
-    pub fn headers(mut self, headers: HeaderMap) -> Self {
-        self.headers = Some(headers);
+    /// Specify transport's HTTP headers, either as a [`HeaderMap`] or as an
+    /// [`http::HeaderMap`]
+    pub fn headers<H: Into<HeaderMap>>(mut self, headers: H) -> Self {
+        self.headers = Some(headers.into());
+        self
+    }
+
+    /// Sends websocket `Ping` frames in the given interval if a websocket
+    /// transport is used. This is separate from the engine.io heartbeat and meant
+    /// for proxies that track the liveness of websocket connections on the
+    /// protocol layer. Intervals below 1 ms are raised to 1 ms. Disabled by
+    /// default.
+    #[cfg(feature = "websocket")]
+    pub fn websocket_ping_interval(mut self, interval: Duration) -> Self {
+        self.websocket_ping_interval = Some(interval);
+        self
+    }
+
+    /// Sets how often the websocket transport retries a write that failed for
+    /// a moment, e.g. because of backpressure, before the emit fails and the
+    /// connection is considered broken. Defaults to [`WriteRetry::default`],
+    /// use [`WriteRetry::disabled`] to fail right away.
+    #[cfg(feature = "websocket")]
+    pub fn websocket_write_retry(mut self, write_retry: WriteRetry) -> Self {
+        self.transport_options.write_retry = write_retry;
+        self
+    }
+
+    /// Remembers the servers that accepted an upgrade to websocket in `store`
+    /// and lets `build` connect to them with a websocket right away, skipping
+    /// the polling handshake. See [`UpgradeStore`] for details.
+    #[cfg(feature = "websocket")]
+    pub fn remember_upgrade<S: UpgradeStore + 'static>(mut self, store: S) -> Self {
+        self.upgrade_store = Some(SharedUpgradeStore::new(store));
+        self
+    }
+
+    /// Sets the number of idle buffers that are kept for encoding outgoing
+    /// packets and reading incoming binary frames. Reusing buffers reduces the
+    /// pressure on the allocator at high message rates, `0` disables pooling.
+    /// Defaults to [`DEFAULT_BUFFER_POOL_SIZE`].
+    pub fn buffer_pool_size(mut self, size: usize) -> Self {
+        self.buffer_pool_size = size;
+        self
+    }
+
+    /// Times the emits of the client: how long each waited for the transport,
+    /// which the emit before it and reconnects hold, and how long the
+    /// transport took to write it, see [`Client::emit_timing_stats`]. Tells a
+    /// slow transport from emits that block each other. Disabled by default.
+    pub fn emit_timing(mut self, enabled: bool) -> Self {
+        self.emit_timing = enabled;
         self
     }
 
+    /// Sets whether the callbacks of the received packets, i.e. `on_packet`
+    /// and `on_data`, wait until the future returned by `on_open` completed.
+    /// Defaults to [`OpenOrdering::Invoked`], which only calls `on_open`
+    /// first.
+    pub fn open_ordering(mut self, ordering: OpenOrdering) -> Self {
+        self.open_ordering = ordering;
+        self
+    }
+
+    /// Sets the HTTP protocol version of the polling transport. Defaults to
+    /// [`HttpProtocol::Http1`]. Negotiating HTTP/2 via ALPN needs the `http2`
+    /// feature, and a custom [`TlsConnector`] needs to request the ALPN
+    /// protocols itself.
+    pub fn http_protocol(mut self, http_protocol: HttpProtocol) -> Self {
+        self.transport_options.http_protocol = http_protocol;
+        self
+    }
+
+    /// Sets how often a long-poll request of the polling transport is retried
+    /// when a gateway responds with a transient error. Defaults to
+    /// [`PollRetry::default`], use [`PollRetry::disabled`] to fail right away.
+    pub fn poll_retry(mut self, poll_retry: PollRetry) -> Self {
+        self.transport_options.poll_retry = poll_retry;
+        self
+    }
+
+    /// Limits the size of the payloads that are received from the server. A
+    /// larger payload fails with [`Error::PayloadTooLarge`] before it's read
+    /// completely, which protects against malicious or misconfigured servers.
+    /// The built-in transports don't decompress data, so the limit applies to
+    /// the received bytes. For the transports of
+    /// [`ClientBuilder::transport_factory`], it applies to the data they
+    /// yield, i.e. after they decompressed it. Unlimited by default, except
+    /// for websocket messages, which tungstenite limits to 64 MiB.
+    pub fn max_payload_size(mut self, limit: usize) -> Self {
+        self.transport_options.max_payload_size = Some(limit);
+        self
+    }
+
+    /// Probes the connection once no data was received for `idle`, which
+    /// should be well below the ping interval of the server. If the server
+    /// doesn't acknowledge the probe within `timeout`, the connection is
+    /// considered half-open, e.g. after a NAT gateway dropped it silently, and
+    /// closed with [`Error::HalfOpenConnection`]. Without a probe, such a
+    /// connection is only noticed once the ping timeout passed.
+    ///
+    /// Websocket transports send a `Ping` frame and wait for the `Pong`, the
+    /// polling transport sends a `Noop` packet and waits for the response.
+    /// Disabled by default.
+    pub fn half_open_probe(mut self, idle: Duration, timeout: Duration) -> Self {
+        self.half_open_probe = Some(HalfOpenProbe { idle, timeout });
+        self
+    }
+
+    /// Injects the faults of `chaos` into the connection once the handshake
+    /// is done, like latency, dropped packets and random disconnects, to test
+    /// the heartbeat and the reconnects under adverse conditions. See
+    /// [`ChaosTransport`].
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn chaos(mut self, chaos: Chaos) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Wraps the HTTP requests of the polling transport, including the
+    /// handshake, in the given `tower` layer, e.g. to add retries, rate
+    /// limiting, authentication or tracing. The innermost service is the
+    /// `reqwest` client of the transport. Layers set by later calls wrap the
+    /// earlier ones. Websocket connections aren't affected.
+    #[cfg(feature = "tower")]
+    pub fn http_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<HttpService> + Clone + Send + Sync + 'static,
+        L::Service:
+            Service<reqwest::Request, Response = reqwest::Response> + Clone + Send + 'static,
+        <L::Service as Service<reqwest::Request>>::Future: Send + 'static,
+        <L::Service as Service<reqwest::Request>>::Error: Into<BoxError>,
+    {
+        let layer = HttpLayer::new(layer);
+        self.transport_options.http_layer = Some(match self.transport_options.http_layer.take() {
+            Some(inner) => inner.wrap(layer),
+            None => layer,
+        });
+        self
+    }
+
+    /// Binds the connections to the server to the given local address, for
+    /// hosts with several network interfaces. Only addresses of the same family
+    /// as `address` are connected to.
+    pub fn local_address(mut self, address: IpAddr) -> Self {
+        self.transport_options.local_binding.address = Some(address);
+        self
+    }
+
+    /// Binds the connections to the server to the given network interface
+    /// (`SO_BINDTODEVICE`), which usually requires elevated privileges.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(mut self, interface: impl Into<String>) -> Self {
+        self.transport_options.local_binding.interface = Some(interface.into());
+        self
+    }
+
+    /// Sets the zone of a link-local IPv6 address of the server, e.g. `eth0`
+    /// for `http://[fe80::1%eth0]:3000/`, which [`url::Url`] can't hold, see
+    /// [`crate::split_zone`]. The websocket transports connect to the address
+    /// scoped to the zone. The polling transports are bound to the interface
+    /// the zone names instead, so they need its name rather than its index and
+    /// reach link-local addresses on Linux only.
+    pub fn ipv6_zone(mut self, zone: impl Into<String>) -> Self {
+        self.transport_options.local_binding.zone = Some(zone.into());
+        self
+    }
+
+    /// Resolves the host of the server with `resolver` instead of the DNS
+    /// resolver of the system, e.g. with a [`crate::DohResolver`]. Applies to the
+    /// polling and the websocket transports.
+    pub fn resolver<R: Resolver + 'static>(mut self, resolver: R) -> Self {
+        self.transport_options.resolver = Some(SharedResolver::new(resolver));
+        self
+    }
+
+    /// Resolves the address of the server and establishes the TCP and TLS
+    /// connection without starting the engine.io handshake, so that a later
+    /// `build` completes faster. See [`PreparedConnection`] for details.
+    pub async fn prepare(self) -> Result<Self> {
+        let prepared = PreparedConnection::with_options(
+            &self.url,
+            self.tls_config.clone(),
+            &self.transport_options,
+        )
+        .await?;
+        Ok(self.prepared(prepared))
+    }
+
+    /// Connects with a transport of `factory` instead of the built-in ones,
+    /// e.g. over QUIC or a serial line. `factory` is called with the URL of
+    /// the builder on every `build` and `build_with_fallback`, and the
+    /// handshake is read from the transport it returns, see
+    /// [`CustomTransport`] for what it has to implement. The builds of a
+    /// specific built-in transport, like `build_polling`, ignore it.
+    ///
+    /// [`CustomTransport`]: crate::asynchronous::async_transports::CustomTransport
+    pub fn transport_factory<F, T>(mut self, factory: F) -> Self
+    where
+        F: Fn(Url) -> BoxFuture<'static, Result<T>> + Send + Sync + 'static,
+        T: AsyncTransport + Clone + Send + Sync + 'static,
+    {
+        self.transport_factory = Some(TransportFactory::new(factory));
+        self
+    }
+
+    /// Uses a connection that was prepared ahead of time.
+    pub fn prepared(mut self, prepared: PreparedConnection) -> Self {
+        self.prepared = Some(prepared);
+        self
+    }
+
+    /// The connection that was prepared ahead of time, if any.
+    pub fn prepared_connection(&self) -> Option<&PreparedConnection> {
+        self.prepared.as_ref()
+    }
+
     /// Registers the `on_close` callback.
     #[cfg(feature = "async-callbacks")]
     pub fn on_close<T>(mut self, callback: T) -> Self
     where
         T: 'static + Send + Sync + Fn(()) -> BoxFuture<'static, ()>,
+    {
+        self.on_close = OptionalCallback::new(move |_| callback(()));
+        self
+    }
+
+    /// Registers the `on_close` callback along with a [`DisconnectDescription`]
+    /// that tells why the connection closed and what its state was, e.g. when
+    /// data was received the last time. Replaces the callback registered with
+    /// [`ClientBuilder::on_close`].
+    #[cfg(feature = "async-callbacks")]
+    pub fn on_close_with_description<T>(mut self, callback: T) -> Self
+    where
+        T: 'static + Send + Sync + Fn(DisconnectDescription) -> BoxFuture<'static, ()>,
     {
         self.on_close = OptionalCallback::new(callback);
         self
@@ -81,7 +363,19 @@ impl ClientBuilder {
     where
         T: 'static + Send + Sync + Fn(Bytes) -> BoxFuture<'static, ()>,
     {
-        self.on_data = OptionalCallback::new(callback);
+        self.on_data = OptionalCallback::new(move |(data, _)| callback(data));
+        self
+    }
+
+    /// Registers the `on_data` callback along with a [`DataInfo`] that tells
+    /// whether the data was sent as text or binary and on which transport.
+    /// Replaces the callback registered with [`ClientBuilder::on_data`].
+    #[cfg(feature = "async-callbacks")]
+    pub fn on_data_with_info<T>(mut self, callback: T) -> Self
+    where
+        T: 'static + Send + Sync + Fn(Bytes, DataInfo) -> BoxFuture<'static, ()>,
+    {
+        self.on_data = OptionalCallback::new(move |(data, info)| callback(data, info));
         self
     }
 
@@ -115,6 +409,17 @@ impl ClientBuilder {
         self
     }
 
+    /// Registers the `on_upgrade` callback, which is called once the polling
+    /// transport of the handshake was upgraded to a websocket transport.
+    #[cfg(all(feature = "async-callbacks", feature = "websocket"))]
+    pub fn on_upgrade<T>(mut self, callback: T) -> Self
+    where
+        T: 'static + Send + Sync + Fn(()) -> BoxFuture<'static, ()>,
+    {
+        self.on_upgrade = OptionalCallback::new(callback);
+        self
+    }
+
     /// Performs the handshake
     async fn handshake_with_transport<T: AsyncTransport + Unpin>(
         &mut self,
@@ -127,9 +432,9 @@ impl ClientBuilder {
 
         let mut url = self.url.clone();
 
-        let handshake: HandshakePacket =
-            Packet::try_from(transport.next().await.ok_or(Error::IncompletePacket())??)?
-                .try_into()?;
+        let handshake = HandshakePacket::from_response(
+            transport.next().await.ok_or(Error::IncompletePacket())??,
+        )?;
 
         // update the base_url with the new sid
         url.query_pairs_mut().append_pair("sid", &handshake.sid[..]);
@@ -153,47 +458,75 @@ impl ClientBuilder {
         };
 
         // Start with polling transport
-        let mut transport =
-            PollingTransport::new(self.url.clone(), self.tls_config.clone(), headers);
+        let mut transport = PollingTransport::with_options(
+            self.url.clone(),
+            self.tls_config.clone(),
+            headers,
+            self.prepared.as_ref(),
+            &self.transport_options,
+        );
 
         self.handshake_with_transport(&mut transport).await
     }
 
-    /// Build websocket if allowed, if not fall back to polling
+    /// Build websocket if allowed, if not fall back to polling. Without the
+    /// `websocket` feature, this always builds a polling transport. Builds a
+    /// transport of the [`ClientBuilder::transport_factory`] if there's one.
     pub async fn build(mut self) -> Result<Client> {
+        if let Some(factory) = self.transport_factory.clone() {
+            let transport = factory
+                .connect(self.url.clone())
+                .await?
+                .with_max_payload_size(self.transport_options.max_payload_size);
+            return self.build_in_memory(transport).await;
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(store) = self.upgrade_store.clone() {
+            return self.build_remembering_upgrade(store).await;
+        }
         self.handshake().await?;
 
+        #[cfg(feature = "websocket")]
         if self.websocket_upgrade()? {
-            self.build_websocket_with_upgrade().await
-        } else {
-            self.build_polling().await
+            return self.build_websocket_with_upgrade().await;
         }
+        self.build_polling().await
     }
 
     /// Build socket with polling transport
     pub async fn build_polling(mut self) -> Result<Client> {
+        let buffer_pool = BufferPool::new(self.buffer_pool_size);
         self.handshake().await?;
 
         // Make a polling transport with new sid
-        let transport = PollingTransport::new(
+        let transport = PollingTransport::with_options(
             self.url,
             self.tls_config,
             self.headers.map(|v| v.try_into().unwrap()),
+            self.prepared.as_ref(),
+            &self.transport_options,
         );
 
         // SAFETY: handshake function called previously.
-        Ok(Client::new(InnerSocket::new(
-            transport.into(),
-            self.handshake.unwrap(),
-            self.on_close,
-            self.on_data,
-            self.on_error,
-            self.on_open,
-            self.on_packet,
-        )))
+        Ok(Client::new(
+            InnerSocket::new(
+                with_chaos(transport.into(), self.chaos),
+                self.handshake.unwrap(),
+                self.on_close,
+                self.on_data,
+                self.on_error,
+                self.on_open,
+                self.on_packet,
+            )
+            .with_buffer_pool(buffer_pool)
+            .with_half_open_probe(self.half_open_probe)
+            .with_emit_timer(self.emit_timing.then(EmitTimer::default))
+            .with_open_ordering(self.open_ordering),
+        ))
     }
 
     /// Build socket with a polling transport then upgrade to websocket transport
+    #[cfg(feature = "websocket")]
     pub async fn build_websocket_with_upgrade(mut self) -> Result<Client> {
         self.handshake().await?;
 
@@ -205,7 +538,9 @@ impl ClientBuilder {
     }
 
     /// Build socket with only a websocket transport
+    #[cfg(feature = "websocket")]
     pub async fn build_websocket(mut self) -> Result<Client> {
+        let buffer_pool = BufferPool::new(self.buffer_pool_size);
         let headers = if let Some(map) = self.headers.clone() {
             Some(map.try_into()?)
         } else {
@@ -214,49 +549,83 @@ impl ClientBuilder {
 
         match self.url.scheme() {
             "http" | "ws" => {
-                let mut transport = WebsocketTransport::new(self.url.clone(), headers).await?;
+                let mut transport = WebsocketTransport::with_options(
+                    self.url.clone(),
+                    headers,
+                    self.prepared.as_ref(),
+                    &self.transport_options,
+                )
+                .await?;
 
                 if self.handshake.is_some() {
                     transport.upgrade().await?;
+                    if let Some(on_upgrade) = self.on_upgrade.as_ref() {
+                        on_upgrade(()).await;
+                    }
                 } else {
                     self.handshake_with_transport(&mut transport).await?;
                 }
+                transport.set_buffer_pool(buffer_pool.clone());
+                if let Some(interval) = self.websocket_ping_interval {
+                    transport.start_keepalive(interval);
+                }
                 // NOTE: Although self.url contains the sid, it does not propagate to the transport
                 // SAFETY: handshake function called previously.
-                Ok(Client::new(InnerSocket::new(
-                    transport.into(),
-                    self.handshake.unwrap(),
-                    self.on_close,
-                    self.on_data,
-                    self.on_error,
-                    self.on_open,
-                    self.on_packet,
-                )))
+                Ok(Client::new(
+                    InnerSocket::new(
+                        with_chaos(transport.into(), self.chaos),
+                        self.handshake.unwrap(),
+                        self.on_close,
+                        self.on_data,
+                        self.on_error,
+                        self.on_open,
+                        self.on_packet,
+                    )
+                    .with_buffer_pool(buffer_pool)
+                    .with_half_open_probe(self.half_open_probe)
+                    .with_emit_timer(self.emit_timing.then(EmitTimer::default))
+                    .with_open_ordering(self.open_ordering),
+                ))
             }
             "https" | "wss" => {
                 let mut transport = WebsocketSecureTransport::new(
                     self.url.clone(),
                     self.tls_config.clone(),
                     headers,
+                    self.prepared.as_ref(),
+                    &self.transport_options,
                 )
                 .await?;
 
                 if self.handshake.is_some() {
                     transport.upgrade().await?;
+                    if let Some(on_upgrade) = self.on_upgrade.as_ref() {
+                        on_upgrade(()).await;
+                    }
                 } else {
                     self.handshake_with_transport(&mut transport).await?;
                 }
+                transport.set_buffer_pool(buffer_pool.clone());
+                if let Some(interval) = self.websocket_ping_interval {
+                    transport.start_keepalive(interval);
+                }
                 // NOTE: Although self.url contains the sid, it does not propagate to the transport
                 // SAFETY: handshake function called previously.
-                Ok(Client::new(InnerSocket::new(
-                    transport.into(),
-                    self.handshake.unwrap(),
-                    self.on_close,
-                    self.on_data,
-                    self.on_error,
-                    self.on_open,
-                    self.on_packet,
-                )))
+                Ok(Client::new(
+                    InnerSocket::new(
+                        with_chaos(transport.into(), self.chaos),
+                        self.handshake.unwrap(),
+                        self.on_close,
+                        self.on_data,
+                        self.on_error,
+                        self.on_open,
+                        self.on_packet,
+                    )
+                    .with_buffer_pool(buffer_pool)
+                    .with_half_open_probe(self.half_open_probe)
+                    .with_emit_timer(self.emit_timing.then(EmitTimer::default))
+                    .with_open_ordering(self.open_ordering),
+                ))
             }
             _ => Err(Error::InvalidUrlScheme(self.url.scheme().to_string())),
         }
@@ -265,6 +634,9 @@ impl ClientBuilder {
     /// Build websocket if allowed, if not allowed or errored fall back to polling.
     /// WARNING: websocket errors suppressed, no indication of websocket success or failure.
     pub async fn build_with_fallback(self) -> Result<Client> {
+        if self.transport_factory.is_some() {
+            return self.build().await;
+        }
         let result = self.clone().build().await;
         if result.is_err() {
             self.build_polling().await
@@ -273,7 +645,78 @@ impl ClientBuilder {
         }
     }
 
+    /// Build socket over the client end of a [`loopback`] pair. The handshake
+    /// is read from the transport, so the server handle has to answer it with
+    /// [`LoopbackServer::open`]. The URL of the builder isn't connected to.
+    ///
+    /// [`loopback`]: crate::asynchronous::async_transports::loopback
+    /// [`LoopbackServer::open`]: crate::asynchronous::async_transports::LoopbackServer::open
+    #[cfg(any(test, feature = "test-support"))]
+    pub async fn build_loopback(self, transport: LoopbackTransport) -> Result<Client> {
+        self.build_in_memory(transport).await
+    }
+
+    /// Build socket over a [`MockTransport`], which plays the handshake and
+    /// the rest of the connection from its script. The URL of the builder
+    /// isn't connected to.
+    #[cfg(any(test, feature = "test-support"))]
+    pub async fn build_mock(self, transport: MockTransport) -> Result<Client> {
+        self.build_in_memory(transport).await
+    }
+
+    /// Build socket over a transport that is already connected, the
+    /// handshake is read from the transport itself.
+    async fn build_in_memory<T>(mut self, transport: T) -> Result<Client>
+    where
+        T: AsyncTransport + Into<AsyncTransportType>,
+    {
+        let buffer_pool = BufferPool::new(self.buffer_pool_size);
+        let mut transport = transport;
+        self.handshake_with_transport(&mut transport).await?;
+        transport.set_base_url(self.url).await?;
+
+        // SAFETY: handshake function called previously.
+        Ok(Client::new(
+            InnerSocket::new(
+                with_chaos(transport.into(), self.chaos),
+                self.handshake.unwrap(),
+                self.on_close,
+                self.on_data,
+                self.on_error,
+                self.on_open,
+                self.on_packet,
+            )
+            .with_buffer_pool(buffer_pool)
+            .with_half_open_probe(self.half_open_probe)
+            .with_emit_timer(self.emit_timing.then(EmitTimer::default))
+            .with_open_ordering(self.open_ordering),
+        ))
+    }
+
+    /// Like `build`, connects to the servers of `store` with a websocket
+    /// right away and updates `store` with the outcome of the upgrade.
+    #[cfg(feature = "websocket")]
+    async fn build_remembering_upgrade(mut self, store: SharedUpgradeStore) -> Result<Client> {
+        if self.handshake.is_none() && store.is_remembered(&self.url) {
+            match self.clone().build_websocket().await {
+                Ok(client) => return Ok(client),
+                Err(_) => store.forget(&self.url),
+            }
+        }
+
+        self.handshake().await?;
+        if self.websocket_upgrade()? {
+            let url = self.url.clone();
+            let client = self.build_websocket_with_upgrade().await?;
+            store.remember(&url);
+            return Ok(client);
+        }
+        store.forget(&self.url);
+        self.build_polling().await
+    }
+
     /// Checks the handshake to see if websocket upgrades are allowed
+    #[cfg(feature = "websocket")]
     fn websocket_upgrade(&mut self) -> Result<bool> {
         if self.handshake.is_none() {
             return Ok(false);
@@ -288,3 +731,12 @@ impl ClientBuilder {
             .any(|upgrade| upgrade.to_lowercase() == *"websocket"))
     }
 }
+
+/// Wraps `transport` in a `ChaosTransport` if faults are configured.
+fn with_chaos(transport: AsyncTransportType, chaos: Option<Faults>) -> AsyncTransportType {
+    match chaos {
+        #[cfg(any(test, feature = "test-support"))]
+        Some(chaos) => ChaosTransport::new(transport, chaos).into(),
+        _ => transport,
+    }
+}
@@ -1,7 +1,14 @@
-use std::{pin::Pin, sync::Arc};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Poll, Waker},
+};
 
 use crate::error::Result;
-use futures_util::{ready, FutureExt, Stream, StreamExt};
+use futures_util::{ready, stream, FutureExt, Stream, StreamExt};
 use tokio::sync::Mutex;
 
 /// A generator is an internal type that represents a [`Send`] [`futures_util::Stream`]
@@ -10,27 +17,117 @@ pub(crate) type Generator<T> = Pin<Box<dyn Stream<Item = T> + 'static + Send>>;
 
 /// An internal type that implements stream by repeatedly calling [`Stream::poll_next`] on an
 /// underlying stream. Note that the generic parameter will be wrapped in a [`Result`].
+///
+/// The underlying stream is dropped, together with any pending request it holds, once the
+/// last clone of the generator is dropped or the generator is cancelled explicitly.
 #[derive(Clone)]
 pub(crate) struct StreamGenerator<T> {
     inner: Arc<Mutex<Generator<Result<T>>>>,
+    cancellation: Arc<Cancellation>,
 }
 
-impl<T> Stream for StreamGenerator<T> {
+/// Shared state that allows to end a [`StreamGenerator`] from the outside.
+#[derive(Default)]
+struct Cancellation {
+    cancelled: AtomicBool,
+    /// The tasks that are waiting for the next item and need to be woken up on cancellation.
+    wakers: std::sync::Mutex<Vec<Waker>>,
+}
+
+impl Cancellation {
+    fn register(&self, waker: &Waker) {
+        if let Ok(mut wakers) = self.wakers.lock() {
+            if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+                wakers.push(waker.clone());
+            }
+        }
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        if let Ok(mut wakers) = self.wakers.lock() {
+            wakers.drain(..).for_each(Waker::wake);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl<T: Send + 'static> Stream for StreamGenerator<T> {
     type Item = Result<T>;
 
     fn poll_next(
         self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
+        if self.cancellation.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        self.cancellation.register(cx.waker());
+
         let mut lock = ready!(Box::pin(self.inner.lock()).poll_unpin(cx));
+        // the generator might have been cancelled while waiting for the lock
+        if self.cancellation.is_cancelled() {
+            *lock = Box::pin(stream::empty());
+            return Poll::Ready(None);
+        }
         lock.poll_next_unpin(cx)
     }
 }
 
-impl<T> StreamGenerator<T> {
+impl<T: Send + 'static> StreamGenerator<T> {
     pub(crate) fn new(generator_stream: Generator<Result<T>>) -> Self {
         StreamGenerator {
             inner: Arc::new(Mutex::new(generator_stream)),
+            cancellation: Arc::default(),
         }
     }
+
+    /// Ends the generator for all of its clones. The underlying stream is dropped
+    /// right away if it's not polled at the moment, otherwise on the next poll.
+    pub(crate) fn cancel(&self) {
+        self.cancellation.cancel();
+
+        if let Ok(mut lock) = self.inner.try_lock() {
+            *lock = Box::pin(stream::empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::stream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_pending_generator() {
+        let mut generator: StreamGenerator<()> = StreamGenerator::new(Box::pin(stream::pending()));
+        let cloned = generator.clone();
+
+        let handle = tokio::spawn(async move { generator.next().await });
+        tokio::task::yield_now().await;
+
+        cloned.cancel();
+        assert!(handle.await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_drops_stream() {
+        let items = Arc::new(());
+        let held = items.clone();
+        let mut generator: StreamGenerator<()> =
+            StreamGenerator::new(Box::pin(stream::repeat(()).map(move |item| {
+                let _ = &held;
+                Ok(item)
+            })));
+
+        assert!(generator.next().await.is_some());
+        generator.cancel();
+
+        assert!(generator.next().await.is_none());
+        assert_eq!(Arc::strong_count(&items), 1);
+    }
 }
@@ -0,0 +1,119 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use reqwest::{Client, Request, Response};
+use tower::{util::BoxCloneService, BoxError, Layer, Service, ServiceExt};
+
+use crate::Error;
+
+/// The HTTP request that is passed through the layers set via
+/// `ClientBuilder::http_layer`.
+pub type HttpRequest = Request;
+
+/// The HTTP response that is passed back through the layers set via
+/// `ClientBuilder::http_layer`.
+pub type HttpResponse = Response;
+
+/// The boxed `tower` service that sends the HTTP requests of the polling
+/// transport. The innermost service is the `reqwest` client of the transport,
+/// layers added via `ClientBuilder::http_layer` wrap around it.
+pub type HttpService = BoxCloneService<Request, Response, BoxError>;
+
+/// A user provided layer stack that wraps the HTTP requests of the polling
+/// transport, e.g. to add retries, rate limiting, authentication or tracing.
+#[derive(Clone)]
+pub(crate) struct HttpLayer(Arc<dyn Fn(HttpService) -> HttpService + Send + Sync>);
+
+impl HttpLayer {
+    pub(crate) fn new<L>(layer: L) -> Self
+    where
+        L: Layer<HttpService> + Send + Sync + 'static,
+        L::Service: Service<Request, Response = Response> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+        <L::Service as Service<Request>>::Error: Into<BoxError>,
+    {
+        HttpLayer(Arc::new(move |service| {
+            BoxCloneService::new(layer.layer(service).map_err(Into::into))
+        }))
+    }
+
+    /// Stacks `outer` on top of this layer, so that it sees the requests first.
+    pub(crate) fn wrap(self, outer: HttpLayer) -> Self {
+        HttpLayer(Arc::new(move |service| (outer.0)((self.0)(service))))
+    }
+
+    /// Wraps the given client into the layer stack.
+    pub(crate) fn service(&self, client: Client) -> HttpService {
+        (self.0)(BoxCloneService::new(client.map_err(Into::into)))
+    }
+}
+
+impl Debug for HttpLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HttpLayer")
+    }
+}
+
+impl Error {
+    /// Converts the error of an HTTP service, keeping errors of the `reqwest`
+    /// client and the transport itself as they are.
+    pub(crate) fn from_http_service(error: BoxError) -> Self {
+        let error = match error.downcast::<reqwest::Error>() {
+            Ok(error) => return Error::IncompleteResponseFromReqwest(*error),
+            Err(error) => error,
+        };
+        match error.downcast::<Error>() {
+            Ok(error) => *error,
+            Err(error) => Error::HttpMiddleware(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tower::util::MapRequestLayer;
+
+    use super::*;
+    use crate::asynchronous::ClientBuilder;
+    use crate::error::Result;
+
+    fn counting_layer(
+        counter: Arc<AtomicUsize>,
+    ) -> MapRequestLayer<impl Fn(Request) -> Request + Clone> {
+        MapRequestLayer::new(move |request: Request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            request
+        })
+    }
+
+    #[tokio::test]
+    async fn test_http_layer() -> Result<()> {
+        let url = crate::test::engine_io_server()?;
+        let inner = Arc::new(AtomicUsize::new(0));
+        let outer = Arc::new(AtomicUsize::new(0));
+
+        let client = ClientBuilder::new(url)
+            .http_layer(counting_layer(inner.clone()))
+            .http_layer(counting_layer(outer.clone()))
+            .build_polling()
+            .await?;
+        client.connect().await?;
+        client.disconnect().await?;
+
+        // the handshake and the long-poll go through both layers
+        assert!(inner.load(Ordering::SeqCst) > 0);
+        assert_eq!(inner.load(Ordering::SeqCst), outer.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_http_service() {
+        let error = Error::from_http_service(Box::new(Error::PingTimeout()));
+        assert!(matches!(error, Error::PingTimeout()));
+
+        let error = Error::from_http_service("rate limited".into());
+        assert!(matches!(error, Error::HttpMiddleware(_)));
+    }
+}
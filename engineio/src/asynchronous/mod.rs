@@ -7,9 +7,19 @@ mod callback;
 #[cfg(feature = "async")]
 pub mod client;
 mod generator;
+#[cfg(feature = "tower")]
+mod http_service;
+mod prepared;
 
+pub use async_socket::OpenOrdering;
 #[cfg(feature = "async")]
 pub use client::Client;
 
 #[cfg(feature = "async")]
 pub use client::ClientBuilder;
+
+#[cfg(feature = "tower")]
+pub(crate) use http_service::HttpLayer;
+#[cfg(feature = "tower")]
+pub use http_service::{HttpRequest, HttpResponse, HttpService};
+pub use prepared::PreparedConnection;
@@ -0,0 +1,184 @@
+#[cfg(feature = "websocket")]
+use std::sync::{Arc, Mutex};
+use std::{fmt::Debug, net::SocketAddr};
+
+use native_tls::TlsConnector;
+#[cfg(feature = "websocket")]
+use tokio::net::TcpStream;
+#[cfg(feature = "websocket")]
+use tokio_tungstenite::{client_async_with_config, MaybeTlsStream, WebSocketStream};
+#[cfg(feature = "websocket")]
+use tungstenite::{handshake::client::Request, protocol::WebSocketConfig};
+use url::{ParseError, Url};
+
+#[cfg(feature = "websocket")]
+use crate::binding::LocalBinding;
+use crate::{error::Result, transport::TransportOptions, Error};
+
+/// A connection to the server that is set up ahead of the engine.io handshake.
+///
+/// Preparing resolves the address of the server and establishes a TCP (and, for
+/// `https`/`wss` urls, a TLS) connection to it. Polling transports that are
+/// created afterwards connect to the resolved addresses, so no further DNS
+/// lookups are needed. The established connection is handed to the first
+/// websocket transport, which then only needs to perform the websocket
+/// handshake. The connection is used only once: later websockets, e.g. of a
+/// reconnect, connect from scratch with a full TLS handshake, as `native-tls`
+/// can't resume the TLS session. Without the `websocket` feature, preparing
+/// only resolves the addresses.
+///
+/// Cloning yields a handle to the same prepared connection.
+#[derive(Clone)]
+pub struct PreparedConnection {
+    host: String,
+    addrs: Vec<SocketAddr>,
+    #[cfg(feature = "websocket")]
+    stream: Arc<Mutex<Option<MaybeTlsStream<TcpStream>>>>,
+}
+
+impl PreparedConnection {
+    /// Resolves the host of `url` and connects to it. The `tls_config` is used for
+    /// secure connections, a default [`TlsConnector`] is used if it's `None`.
+    pub async fn new(url: &Url, tls_config: Option<TlsConnector>) -> Result<Self> {
+        Self::with_options(url, tls_config, &TransportOptions::default()).await
+    }
+
+    /// Resolves the host of `url` with the resolver of the `options`, if any,
+    /// and connects to it from their local binding.
+    #[cfg_attr(not(feature = "websocket"), allow(unused_variables))]
+    pub(crate) async fn with_options(
+        url: &Url,
+        tls_config: Option<TlsConnector>,
+        options: &TransportOptions,
+    ) -> Result<Self> {
+        let secure = match url.scheme() {
+            "https" | "wss" => true,
+            "http" | "ws" => false,
+            scheme => return Err(Error::InvalidUrlScheme(scheme.to_owned())),
+        };
+        let host = url
+            .host_str()
+            .ok_or(Error::InvalidUrl(ParseError::EmptyHost))?;
+        // SAFETY: all of the schemes above have a default port
+        let port = url.port_or_known_default().unwrap();
+
+        let addrs = options
+            .local_binding
+            .lookup(host, port, options.resolver.as_ref())
+            .await?;
+
+        Ok(PreparedConnection {
+            host: host.to_owned(),
+            #[cfg(feature = "websocket")]
+            stream: Arc::new(Mutex::new(Some(
+                Self::connect(host, &addrs, secure, tls_config, &options.local_binding).await?,
+            ))),
+            addrs,
+        })
+    }
+
+    /// Connects to one of the resolved `addrs`, via TLS if the connection is `secure`.
+    #[cfg(feature = "websocket")]
+    async fn connect(
+        host: &str,
+        addrs: &[SocketAddr],
+        secure: bool,
+        tls_config: Option<TlsConnector>,
+        binding: &LocalBinding,
+    ) -> Result<MaybeTlsStream<TcpStream>> {
+        let tcp_stream = if binding == &LocalBinding::default() {
+            TcpStream::connect(addrs).await?
+        } else {
+            binding.connect_to(addrs).await?
+        };
+
+        let stream = if secure {
+            let connector = match tls_config {
+                Some(connector) => connector,
+                None => TlsConnector::new()?,
+            };
+            let tls_stream = tokio_native_tls::TlsConnector::from(connector)
+                .connect(host, tcp_stream)
+                .await?;
+            MaybeTlsStream::NativeTls(tls_stream)
+        } else {
+            MaybeTlsStream::Plain(tcp_stream)
+        };
+        Ok(stream)
+    }
+
+    /// The host name the addresses were resolved for.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The resolved addresses of the server.
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// Performs the websocket handshake over the established connection. Returns
+    /// `None` if the connection was already used, or if the handshake failed, e.g.
+    /// because the server closed the idle connection in the meantime.
+    #[cfg(feature = "websocket")]
+    pub(crate) async fn websocket(
+        &self,
+        request: Request,
+        config: Option<WebSocketConfig>,
+    ) -> Option<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let stream = self.stream.lock().ok()?.take()?;
+        client_async_with_config(request, stream, config)
+            .await
+            .ok()
+            .map(|(ws_stream, _)| ws_stream)
+    }
+}
+
+impl Debug for PreparedConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PreparedConnection")
+            .field("host", &self.host)
+            .field("addrs", &self.addrs)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "websocket")]
+    use tungstenite::client::IntoClientRequest;
+
+    #[tokio::test]
+    async fn test_invalid_url() {
+        let url = Url::parse("ftp://localhost/engine.io/").unwrap();
+        assert!(matches!(
+            PreparedConnection::new(&url, None).await,
+            Err(Error::InvalidUrlScheme(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "websocket")]
+    async fn test_websocket_once() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            // a second connection is never opened
+            tokio::time::timeout(std::time::Duration::from_millis(500), listener.accept())
+                .await
+                .is_err()
+        });
+
+        let prepared = PreparedConnection::new(&url, None).await?;
+        let request = || format!("ws://{}/", url.authority()).into_client_request();
+        assert!(prepared.websocket(request()?, None).await.is_some());
+        // later websockets connect from scratch
+        assert!(prepared.websocket(request()?, None).await.is_none());
+        assert!(prepared.clone().websocket(request()?, None).await.is_none());
+        assert!(server.await.unwrap());
+        Ok(())
+    }
+}
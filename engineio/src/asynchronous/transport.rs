@@ -1,4 +1,5 @@
 use crate::error::Result;
+use crate::{CloseFrame, HttpVersion, TransportKind};
 use adler32::adler32;
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -6,7 +7,11 @@ use futures_util::Stream;
 use std::{pin::Pin, time::SystemTime};
 use url::Url;
 
-use super::async_transports::{PollingTransport, WebsocketSecureTransport, WebsocketTransport};
+#[cfg(any(test, feature = "test-support"))]
+use super::async_transports::{ChaosTransport, LoopbackTransport, MockTransport};
+use super::async_transports::{CustomTransport, PollingTransport};
+#[cfg(feature = "websocket")]
+use super::async_transports::{WebsocketSecureTransport, WebsocketTransport};
 
 #[async_trait]
 pub trait AsyncTransport: Stream<Item = Result<Bytes>> + Unpin {
@@ -32,13 +37,31 @@ pub trait AsyncTransport: Stream<Item = Result<Bytes>> + Unpin {
         url.query_pairs_mut().append_pair("t", &hash.to_string());
         Ok(url)
     }
+
+    /// Ends the stream of incoming data and closes the connection, called
+    /// once the client disconnects or gives up on the connection. Custom
+    /// transports whose stream only ends once the server closed the
+    /// connection should implement it, the default does nothing.
+    fn abort(&self) {}
 }
 
+/// The transports of an async client. The in-memory and fault-injecting
+/// transports for tests require the `test-support` feature.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum AsyncTransportType {
     Polling(PollingTransport),
+    #[cfg(feature = "websocket")]
     Websocket(WebsocketTransport),
+    #[cfg(feature = "websocket")]
     WebsocketSecure(WebsocketSecureTransport),
+    #[cfg(any(test, feature = "test-support"))]
+    Loopback(LoopbackTransport),
+    #[cfg(any(test, feature = "test-support"))]
+    Mock(MockTransport),
+    #[cfg(any(test, feature = "test-support"))]
+    Chaos(ChaosTransport),
+    Custom(CustomTransport),
 }
 
 impl From<PollingTransport> for AsyncTransportType {
@@ -47,33 +70,183 @@ impl From<PollingTransport> for AsyncTransportType {
     }
 }
 
+#[cfg(feature = "websocket")]
 impl From<WebsocketTransport> for AsyncTransportType {
     fn from(transport: WebsocketTransport) -> Self {
         AsyncTransportType::Websocket(transport)
     }
 }
 
+#[cfg(feature = "websocket")]
 impl From<WebsocketSecureTransport> for AsyncTransportType {
     fn from(transport: WebsocketSecureTransport) -> Self {
         AsyncTransportType::WebsocketSecure(transport)
     }
 }
 
+#[cfg(any(test, feature = "test-support"))]
+impl From<LoopbackTransport> for AsyncTransportType {
+    fn from(transport: LoopbackTransport) -> Self {
+        AsyncTransportType::Loopback(transport)
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl From<MockTransport> for AsyncTransportType {
+    fn from(transport: MockTransport) -> Self {
+        AsyncTransportType::Mock(transport)
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl From<ChaosTransport> for AsyncTransportType {
+    fn from(transport: ChaosTransport) -> Self {
+        AsyncTransportType::Chaos(transport)
+    }
+}
+
+impl From<CustomTransport> for AsyncTransportType {
+    fn from(transport: CustomTransport) -> Self {
+        AsyncTransportType::Custom(transport)
+    }
+}
+
 #[cfg(feature = "async")]
 impl AsyncTransportType {
     pub fn as_transport(&self) -> &(dyn AsyncTransport + Send) {
         match self {
             AsyncTransportType::Polling(transport) => transport,
+            #[cfg(feature = "websocket")]
             AsyncTransportType::Websocket(transport) => transport,
+            #[cfg(feature = "websocket")]
             AsyncTransportType::WebsocketSecure(transport) => transport,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(transport) => transport,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Mock(transport) => transport,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport,
+            AsyncTransportType::Custom(transport) => transport,
+        }
+    }
+
+    /// Returns which kind of transport this is.
+    pub fn kind(&self) -> TransportKind {
+        match self {
+            AsyncTransportType::Polling(_) => TransportKind::Polling,
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::Websocket(_) => TransportKind::Websocket,
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::WebsocketSecure(_) => TransportKind::WebsocketSecure,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(_) => TransportKind::Loopback,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Mock(_) => TransportKind::Mock,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport.inner().kind(),
+            AsyncTransportType::Custom(_) => TransportKind::Custom,
+        }
+    }
+
+    /// Ends the stream of incoming data. Only polling, in-memory and custom
+    /// transports need to be cancelled, websocket connections are closed by
+    /// the server on disconnect. Custom transports are ended through
+    /// [`AsyncTransport::abort`].
+    pub(crate) fn cancel(&self) {
+        match self {
+            AsyncTransportType::Polling(transport) => transport.cancel(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(transport) => transport.cancel(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Mock(transport) => transport.cancel(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport.cancel(),
+            AsyncTransportType::Custom(transport) => transport.abort(),
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::Websocket(_) | AsyncTransportType::WebsocketSecure(_) => {}
+        }
+    }
+
+    /// Ends the stream of incoming data. Unlike [`AsyncTransportType::cancel`],
+    /// websocket transports stop reading and sending keepalive frames right
+    /// away and close the websocket.
+    pub(crate) fn abort(&self) {
+        match self {
+            AsyncTransportType::Polling(transport) => transport.cancel(),
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::Websocket(transport) => transport.abort(),
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::WebsocketSecure(transport) => transport.abort(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(transport) => transport.cancel(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Mock(transport) => transport.cancel(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport.abort(),
+            AsyncTransportType::Custom(transport) => transport.abort(),
+        }
+    }
+
+    /// The close frame the server ended a websocket with, if any.
+    pub(crate) fn close_frame(&self) -> Option<CloseFrame> {
+        match self {
+            AsyncTransportType::Polling(_) | AsyncTransportType::Custom(_) => None,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport.inner().close_frame(),
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::Websocket(transport) => transport.close_frame(),
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::WebsocketSecure(transport) => transport.close_frame(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(_) | AsyncTransportType::Mock(_) => None,
+        }
+    }
+
+    /// Checks that the connection still works, see
+    /// `ClientBuilder::half_open_probe`. Websocket transports need their
+    /// stream to be polled meanwhile, as it reads the answer of the server.
+    pub(crate) async fn probe(&self) -> Result<()> {
+        match self {
+            AsyncTransportType::Polling(transport) => transport.probe().await,
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::Websocket(transport) => transport.probe().await,
+            #[cfg(feature = "websocket")]
+            AsyncTransportType::WebsocketSecure(transport) => transport.probe().await,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(transport) => transport.probe().await,
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Mock(_) => Ok(()),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport.probe().await,
+            AsyncTransportType::Custom(transport) => transport.probe().await,
+        }
+    }
+
+    /// Returns the HTTP version of the last response the polling transport
+    /// received, `None` for websocket and in-memory transports.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        match self {
+            AsyncTransportType::Polling(transport) => transport.http_version(),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => transport.inner().http_version(),
+            _ => None,
         }
     }
 
     pub fn as_pin_box(&mut self) -> Pin<Box<&mut (dyn AsyncTransport + Send)>> {
         match self {
             AsyncTransportType::Polling(transport) => Box::pin(transport),
+            #[cfg(feature = "websocket")]
             AsyncTransportType::Websocket(transport) => Box::pin(transport),
+            #[cfg(feature = "websocket")]
             AsyncTransportType::WebsocketSecure(transport) => Box::pin(transport),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Loopback(transport) => Box::pin(transport),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Mock(transport) => Box::pin(transport),
+            #[cfg(any(test, feature = "test-support"))]
+            AsyncTransportType::Chaos(transport) => Box::pin(transport),
+            AsyncTransportType::Custom(transport) => Box::pin(transport),
         }
     }
 }
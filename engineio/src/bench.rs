@@ -0,0 +1,17 @@
+//! Entry points of the benches into the internals of this crate. Not part of
+//! the public API, it may change with any release.
+use bytes::Bytes;
+
+use crate::error::Result;
+use crate::packet::{Packet, Payload};
+
+/// Encodes `packets` as one payload of the polling transport.
+pub fn encode_payload(packets: Vec<Packet>) -> Result<Bytes> {
+    Bytes::try_from(Payload::from(packets))
+}
+
+/// Decodes a payload of the polling transport and returns its number of
+/// packets.
+pub fn decode_payload(data: Bytes) -> Result<usize> {
+    Payload::try_from(data).map(|payload| payload.len())
+}
@@ -0,0 +1,299 @@
+#[cfg(feature = "websocket")]
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+use tokio::net::lookup_host;
+#[cfg(feature = "websocket")]
+use tokio::net::{TcpSocket, TcpStream};
+use url::{ParseError, Url};
+
+use crate::{error::Result, resolver::SharedResolver};
+
+/// The local end of the connections to the server, for hosts with several
+/// network interfaces that need to source their traffic from a specific one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LocalBinding {
+    pub(crate) address: Option<IpAddr>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub(crate) interface: Option<String>,
+    /// The zone of a link-local IPv6 address of the server, e.g. `eth0`.
+    pub(crate) zone: Option<String>,
+}
+
+impl LocalBinding {
+    pub(crate) fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let builder = builder.local_address(self.address);
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        let builder = match self.device() {
+            Some(interface) => builder.interface(interface),
+            None => builder,
+        };
+        builder
+    }
+
+    pub(crate) fn apply_blocking(
+        &self,
+        builder: reqwest::blocking::ClientBuilder,
+    ) -> reqwest::blocking::ClientBuilder {
+        let builder = builder.local_address(self.address);
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        let builder = match self.device() {
+            Some(interface) => builder.interface(interface),
+            None => builder,
+        };
+        builder
+    }
+
+    /// The interface the `reqwest` clients are bound to. `reqwest` drops the
+    /// zone of an IPv6 address, so they reach a link-local address of the
+    /// server only when bound to the interface the zone names.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    fn device(&self) -> Option<&str> {
+        self.interface.as_deref().or(self.zone.as_deref())
+    }
+
+    /// Looks up the addresses of `host`, by the `resolver` if given. An IPv6
+    /// address is scoped to the zone of the binding, which the system resolves
+    /// to the index of the interface.
+    pub(crate) async fn lookup(
+        &self,
+        host: &str,
+        port: u16,
+        resolver: Option<&SharedResolver>,
+    ) -> Result<Vec<SocketAddr>> {
+        if let (Some(zone), Some(ip)) = (&self.zone, host.strip_prefix('[')) {
+            let ip = ip.trim_end_matches(']');
+            return Ok(lookup_host((format!("{ip}%{zone}").as_str(), port))
+                .await?
+                .collect());
+        }
+        Ok(match resolver {
+            Some(resolver) => resolver.lookup(host, port).await?,
+            None => lookup_host((host, port)).await?.collect(),
+        })
+    }
+}
+
+/// Parses `url`, which may contain an IPv6 address with a zone like
+/// `http://[fe80::1%eth0]:3000/` or its percent-encoded form
+/// `http://[fe80::1%25eth0]:3000/` of RFC 6874. As [`Url`] rejects zones, the
+/// zone is split off and returned separately, to be passed to
+/// `ClientBuilder::ipv6_zone`.
+///
+/// # Example
+/// ```rust
+/// use rust_engineio::{split_zone, ClientBuilder};
+///
+/// let (url, zone) = split_zone("http://[fe80::1%eth0]:3000/").unwrap();
+/// assert_eq!(url.as_str(), "http://[fe80::1]:3000/");
+/// assert_eq!(zone.as_deref(), Some("eth0"));
+///
+/// let mut builder = ClientBuilder::new(url);
+/// if let Some(zone) = zone {
+///     builder = builder.ipv6_zone(zone);
+/// }
+/// ```
+pub fn split_zone(url: &str) -> std::result::Result<(Url, Option<String>), ParseError> {
+    let authority_start = url.find("://").map_or(0, |index| index + 3);
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |index| authority_start + index);
+    let authority = &url[authority_start..authority_end];
+
+    let separator = authority
+        .find('[')
+        .zip(authority.find(']'))
+        .and_then(|(open, close)| {
+            let separator = open + authority[open..close].find('%')?;
+            Some((authority_start + separator, authority_start + close))
+        });
+    let Some((separator, close)) = separator else {
+        return Ok((Url::parse(url)?, None));
+    };
+
+    let zone = &url[separator + 1..close];
+    let zone = match zone.strip_prefix("25") {
+        Some(decoded) if !decoded.is_empty() => decoded,
+        _ => zone,
+    };
+    if zone.is_empty() {
+        return Err(ParseError::InvalidIpv6Address);
+    }
+    let url = Url::parse(&format!("{}{}", &url[..separator], &url[close..]))?;
+    Ok((url, Some(zone.to_owned())))
+}
+
+/// The websocket transports open their connections themselves.
+#[cfg(feature = "websocket")]
+impl LocalBinding {
+    fn is_unbound(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Connects to the host of `url`, which is looked up by the `resolver` if
+    /// given. Returns `None` if neither a binding nor a resolver is configured
+    /// and the caller can connect on its own.
+    pub(crate) async fn connect(
+        &self,
+        url: &Url,
+        resolver: Option<&SharedResolver>,
+    ) -> Option<io::Result<TcpStream>> {
+        if self.is_unbound() && resolver.is_none() {
+            return None;
+        }
+        let host = url.host_str().unwrap_or_default();
+        let port = url.port_or_known_default().unwrap_or_default();
+        Some(match self.lookup(host, port, resolver).await {
+            Ok(addrs) => self.connect_to(&addrs).await,
+            Err(err) => Err(io::Error::other(err)),
+        })
+    }
+
+    /// Connects to the first of the `addrs` that is reachable from the local address.
+    pub(crate) async fn connect_to(&self, addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+        let mut last_error = None;
+        for addr in addrs {
+            // only addresses of the same family as the local address can be reached
+            if matches!(self.address, Some(local) if local.is_ipv4() != addr.is_ipv4()) {
+                continue;
+            }
+            match self.connect_one(*addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "no address of the server is reachable from the local address",
+            )
+        }))
+    }
+
+    async fn connect_one(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let socket = if addr.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        if let Some(address) = self.address {
+            socket.bind(SocketAddr::new(address, 0))?;
+        }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(interface) = &self.interface {
+            socket.bind_device(Some(interface.as_bytes()))?;
+        }
+        socket.connect(addr).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "websocket")]
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_split_zone() -> std::result::Result<(), ParseError> {
+        let (url, zone) = split_zone("http://[fe80::1%eth0]:3000/socket.io/?EIO=4")?;
+        assert_eq!(url.as_str(), "http://[fe80::1]:3000/socket.io/?EIO=4");
+        assert_eq!(zone.as_deref(), Some("eth0"));
+
+        let (url, zone) = split_zone("ws://[fe80::1%253]/")?;
+        assert_eq!(url.as_str(), "ws://[fe80::1]/");
+        assert_eq!(zone.as_deref(), Some("3"));
+
+        let (url, zone) = split_zone("http://[::1]:3000/?q=[a%b]")?;
+        assert_eq!(url.as_str(), "http://[::1]:3000/?q=[a%b]");
+        assert_eq!(zone, None);
+
+        assert_eq!(
+            split_zone("http://[fe80::1%]/").unwrap_err(),
+            ParseError::InvalidIpv6Address
+        );
+        assert!(split_zone("http://[fe80::1%eth0/").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn test_connect_from_local_address() -> io::Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?)).unwrap();
+
+        assert!(LocalBinding::default().connect(&url, None).await.is_none());
+
+        let binding = LocalBinding {
+            address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ..Default::default()
+        };
+        let stream = binding.connect(&url, None).await.unwrap()?;
+        assert_eq!(stream.local_addr()?.ip(), Ipv4Addr::LOCALHOST);
+
+        let (_, peer) = listener.accept().await?;
+        assert_eq!(peer, stream.local_addr()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn test_skip_other_address_family() {
+        let binding = LocalBinding {
+            address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ..Default::default()
+        };
+        let err = binding
+            .connect_to(&["[::1]:4200".parse().unwrap()])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrNotAvailable);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_with_zone() -> Result<()> {
+        let binding = LocalBinding {
+            zone: Some(String::from("1")),
+            ..Default::default()
+        };
+        let addrs = binding.lookup("[fe80::1]", 3000, None).await?;
+        assert!(matches!(
+            addrs[..],
+            [SocketAddr::V6(addr)] if addr.scope_id() == 1 && addr.port() == 3000
+        ));
+        Ok(())
+    }
+
+    /// Connects to a link-local address of this machine, if it has one.
+    #[cfg(all(feature = "websocket", target_os = "linux"))]
+    #[tokio::test]
+    async fn test_connect_with_zone() -> io::Result<()> {
+        let interfaces = std::fs::read_to_string("/proc/net/if_inet6").unwrap_or_default();
+        let link_local = interfaces.lines().find_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let ip = u128::from_str_radix(fields.first()?, 16).ok()?;
+            let ip = std::net::Ipv6Addr::from(ip);
+            let scope_id = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+            let interface = fields.last()?.to_string();
+            let is_link_local = ip.segments()[0] & 0xffc0 == 0xfe80;
+            is_link_local.then_some((ip, scope_id, interface))
+        });
+        let Some((ip, scope_id, interface)) = link_local else {
+            return Ok(());
+        };
+
+        let addr = std::net::SocketAddrV6::new(ip, 0, 0, scope_id);
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let port = listener.local_addr()?.port();
+        let (url, zone) = split_zone(&format!("http://[{ip}%{interface}]:{port}/")).unwrap();
+
+        let binding = LocalBinding {
+            zone,
+            ..Default::default()
+        };
+        let stream = binding.connect(&url, None).await.unwrap()?;
+        let (_, peer) = listener.accept().await?;
+        assert_eq!(peer, stream.local_addr()?);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Packet;
+use crate::{DataInfo, DisconnectDescription, Packet};
 use bytes::Bytes;
 use std::fmt::Debug;
 use std::ops::Deref;
@@ -72,12 +72,12 @@ impl Debug for OptionalCallback<Packet> {
 }
 
 #[cfg_attr(tarpaulin, ignore)]
-impl Debug for OptionalCallback<Bytes> {
+impl Debug for OptionalCallback<(Bytes, DataInfo)> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         f.write_fmt(format_args!(
             "Callback({:?})",
             if self.inner.is_some() {
-                "Fn(Bytes)"
+                "Fn(Bytes, DataInfo)"
             } else {
                 "None"
             }
@@ -91,3 +91,17 @@ impl<I> Deref for OptionalCallback<I> {
         self.inner.as_ref()
     }
 }
+
+#[cfg_attr(tarpaulin, ignore)]
+impl Debug for OptionalCallback<DisconnectDescription> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        f.write_fmt(format_args!(
+            "Callback({:?})",
+            if self.inner.is_some() {
+                "Fn(DisconnectDescription)"
+            } else {
+                "None"
+            }
+        ))
+    }
+}
@@ -1,18 +1,28 @@
 use super::super::socket::Socket as InnerSocket;
 use crate::callback::OptionalCallback;
 use crate::socket::DEFAULT_MAX_POLL_TIMEOUT;
-use crate::transport::Transport;
+use crate::transport::{HttpProtocol, PollRetry, Transport, TransportOptions};
 
-use crate::error::{Error, Result};
+#[cfg(feature = "websocket")]
+use crate::error::Error;
+use crate::error::Result;
 use crate::header::HeaderMap;
 use crate::packet::{HandshakePacket, Packet, PacketId};
-use crate::transports::{PollingTransport, WebsocketSecureTransport, WebsocketTransport};
-use crate::ENGINE_IO_VERSION;
+use crate::pool::{BufferPool, BufferPoolStats, DEFAULT_BUFFER_POOL_SIZE};
+use crate::resolver::{Resolver, SharedResolver};
+use crate::transports::PollingTransport;
+#[cfg(feature = "websocket")]
+use crate::transports::{WebsocketSecureTransport, WebsocketTransport};
+#[cfg(feature = "websocket")]
+use crate::upgrade::{SharedUpgradeStore, UpgradeStore};
+use crate::{DataInfo, DisconnectDescription, HttpVersion, TransportKind, ENGINE_IO_VERSION};
 use bytes::Bytes;
 use native_tls::TlsConnector;
-use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fmt::Debug;
+use std::net::IpAddr;
+#[cfg(feature = "websocket")]
+use std::time::Duration;
 use url::Url;
 
 /// An engine.io client that allows interaction with the connected engine.io
@@ -35,9 +45,17 @@ pub struct ClientBuilder {
     handshake: Option<HandshakePacket>,
     on_error: OptionalCallback<String>,
     on_open: OptionalCallback<()>,
-    on_close: OptionalCallback<()>,
-    on_data: OptionalCallback<Bytes>,
+    on_close: OptionalCallback<DisconnectDescription>,
+    on_data: OptionalCallback<(Bytes, DataInfo)>,
     on_packet: OptionalCallback<Packet>,
+    #[cfg(feature = "websocket")]
+    on_upgrade: OptionalCallback<()>,
+    #[cfg(feature = "websocket")]
+    websocket_ping_interval: Option<Duration>,
+    #[cfg(feature = "websocket")]
+    upgrade_store: Option<SharedUpgradeStore>,
+    buffer_pool_size: usize,
+    transport_options: TransportOptions,
 }
 
 impl ClientBuilder {
@@ -60,6 +78,14 @@ impl ClientBuilder {
             on_error: OptionalCallback::default(),
             on_open: OptionalCallback::default(),
             on_packet: OptionalCallback::default(),
+            #[cfg(feature = "websocket")]
+            on_upgrade: OptionalCallback::default(),
+            #[cfg(feature = "websocket")]
+            websocket_ping_interval: None,
+            #[cfg(feature = "websocket")]
+            upgrade_store: None,
+            buffer_pool_size: DEFAULT_BUFFER_POOL_SIZE,
+            transport_options: TransportOptions::default(),
         }
     }
 
@@ -69,9 +95,101 @@ impl ClientBuilder {
         self
     }
 
-    /// Specify transport's HTTP headers
-    pub fn headers(mut self, headers: HeaderMap) -> Self {
-        self.headers = Some(headers);
+    /// Specify transport's HTTP headers, either as a [`HeaderMap`] or as an
+    /// [`http::HeaderMap`]
+    pub fn headers<H: Into<HeaderMap>>(mut self, headers: H) -> Self {
+        self.headers = Some(headers.into());
+        self
+    }
+
+    /// Sends websocket `Ping` frames in the given interval if a websocket
+    /// transport is used. This is separate from the engine.io heartbeat and meant
+    /// for proxies that track the liveness of websocket connections on the
+    /// protocol layer. Intervals below 1 ms are raised to 1 ms. Disabled by
+    /// default.
+    #[cfg(feature = "websocket")]
+    pub fn websocket_ping_interval(mut self, interval: Duration) -> Self {
+        self.websocket_ping_interval = Some(interval);
+        self
+    }
+
+    /// Remembers the servers that accepted an upgrade to websocket in `store`
+    /// and lets `build` connect to them with a websocket right away, skipping
+    /// the polling handshake. See [`UpgradeStore`] for details.
+    #[cfg(feature = "websocket")]
+    pub fn remember_upgrade<S: UpgradeStore + 'static>(mut self, store: S) -> Self {
+        self.upgrade_store = Some(SharedUpgradeStore::new(store));
+        self
+    }
+
+    /// Sets the number of idle buffers that are kept for encoding outgoing
+    /// packets and reading incoming binary frames. Reusing buffers reduces the
+    /// pressure on the allocator at high message rates, `0` disables pooling.
+    /// Defaults to [`DEFAULT_BUFFER_POOL_SIZE`].
+    pub fn buffer_pool_size(mut self, size: usize) -> Self {
+        self.buffer_pool_size = size;
+        self
+    }
+
+    /// Sets the HTTP protocol version of the polling transport. Defaults to
+    /// [`HttpProtocol::Http1`]. Negotiating HTTP/2 via ALPN needs the `http2`
+    /// feature, and a custom [`TlsConnector`] needs to request the ALPN
+    /// protocols itself.
+    pub fn http_protocol(mut self, http_protocol: HttpProtocol) -> Self {
+        self.transport_options.http_protocol = http_protocol;
+        self
+    }
+
+    /// Sets how often a long-poll request of the polling transport is retried
+    /// when a gateway responds with a transient error. Defaults to
+    /// [`PollRetry::default`], use [`PollRetry::disabled`] to fail right away.
+    pub fn poll_retry(mut self, poll_retry: PollRetry) -> Self {
+        self.transport_options.poll_retry = poll_retry;
+        self
+    }
+
+    /// Limits the size of the payloads that are received from the server. A
+    /// larger payload fails with [`Error::PayloadTooLarge`] before it's read
+    /// completely, which protects against malicious or misconfigured servers.
+    /// Unlimited by default, except for websocket messages, which tungstenite
+    /// limits to 64 MiB.
+    pub fn max_payload_size(mut self, limit: usize) -> Self {
+        self.transport_options.max_payload_size = Some(limit);
+        self
+    }
+
+    /// Binds the connections to the server to the given local address, for
+    /// hosts with several network interfaces. Only addresses of the same family
+    /// as `address` are connected to.
+    pub fn local_address(mut self, address: IpAddr) -> Self {
+        self.transport_options.local_binding.address = Some(address);
+        self
+    }
+
+    /// Binds the connections to the server to the given network interface
+    /// (`SO_BINDTODEVICE`), which usually requires elevated privileges.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(mut self, interface: impl Into<String>) -> Self {
+        self.transport_options.local_binding.interface = Some(interface.into());
+        self
+    }
+
+    /// Sets the zone of a link-local IPv6 address of the server, e.g. `eth0`
+    /// for `http://[fe80::1%eth0]:3000/`, which [`url::Url`] can't hold, see
+    /// [`crate::split_zone`]. The websocket transports connect to the address
+    /// scoped to the zone. The polling transports are bound to the interface
+    /// the zone names instead, so they need its name rather than its index and
+    /// reach link-local addresses on Linux only.
+    pub fn ipv6_zone(mut self, zone: impl Into<String>) -> Self {
+        self.transport_options.local_binding.zone = Some(zone.into());
+        self
+    }
+
+    /// Resolves the host of the server with `resolver` instead of the DNS
+    /// resolver of the system, e.g. with a [`crate::DohResolver`]. Applies to the
+    /// polling and the websocket transports.
+    pub fn resolver<R: Resolver + 'static>(mut self, resolver: R) -> Self {
+        self.transport_options.resolver = Some(SharedResolver::new(resolver));
         self
     }
 
@@ -79,6 +197,18 @@ impl ClientBuilder {
     pub fn on_close<T>(mut self, callback: T) -> Self
     where
         T: Fn(()) + 'static + Sync + Send,
+    {
+        self.on_close = OptionalCallback::new(move |_| callback(()));
+        self
+    }
+
+    /// Registers the `on_close` callback along with a [`DisconnectDescription`]
+    /// that tells why the connection closed and what its state was, e.g. when
+    /// data was received the last time. Replaces the callback registered with
+    /// [`ClientBuilder::on_close`].
+    pub fn on_close_with_description<T>(mut self, callback: T) -> Self
+    where
+        T: Fn(DisconnectDescription) + 'static + Sync + Send,
     {
         self.on_close = OptionalCallback::new(callback);
         self
@@ -89,7 +219,18 @@ impl ClientBuilder {
     where
         T: Fn(Bytes) + 'static + Sync + Send,
     {
-        self.on_data = OptionalCallback::new(callback);
+        self.on_data = OptionalCallback::new(move |(data, _)| callback(data));
+        self
+    }
+
+    /// Registers the `on_data` callback along with a [`DataInfo`] that tells
+    /// whether the data was sent as text or binary and on which transport.
+    /// Replaces the callback registered with [`ClientBuilder::on_data`].
+    pub fn on_data_with_info<T>(mut self, callback: T) -> Self
+    where
+        T: Fn(Bytes, DataInfo) + 'static + Sync + Send,
+    {
+        self.on_data = OptionalCallback::new(move |(data, info)| callback(data, info));
         self
     }
 
@@ -120,6 +261,17 @@ impl ClientBuilder {
         self
     }
 
+    /// Registers the `on_upgrade` callback, which is called once the polling
+    /// transport of the handshake was upgraded to a websocket transport.
+    #[cfg(feature = "websocket")]
+    pub fn on_upgrade<T>(mut self, callback: T) -> Self
+    where
+        T: Fn(()) + 'static + Sync + Send,
+    {
+        self.on_upgrade = OptionalCallback::new(callback);
+        self
+    }
+
     /// Performs the handshake
     fn handshake_with_transport<T: Transport>(&mut self, transport: &T) -> Result<()> {
         // No need to handshake twice
@@ -129,8 +281,7 @@ impl ClientBuilder {
 
         let mut url = self.url.clone();
 
-        let handshake: HandshakePacket =
-            Packet::try_from(transport.poll(DEFAULT_MAX_POLL_TIMEOUT)?)?.try_into()?;
+        let handshake = HandshakePacket::from_response(transport.poll(DEFAULT_MAX_POLL_TIMEOUT)?)?;
 
         // update the base_url with the new sid
         url.query_pairs_mut().append_pair("sid", &handshake.sid[..]);
@@ -148,36 +299,44 @@ impl ClientBuilder {
         }
 
         // Start with polling transport
-        let transport = PollingTransport::new(
+        let transport = PollingTransport::with_options(
             self.url.clone(),
             self.tls_config.clone(),
             self.headers.clone().map(|v| v.try_into().unwrap()),
-        );
+            &self.transport_options,
+        )?;
 
         self.handshake_with_transport(&transport)
     }
 
-    /// Build websocket if allowed, if not fall back to polling
+    /// Build websocket if allowed, if not fall back to polling. Without the
+    /// `websocket` feature, this always builds a polling transport.
     pub fn build(mut self) -> Result<Client> {
+        #[cfg(feature = "websocket")]
+        if let Some(store) = self.upgrade_store.clone() {
+            return self.build_remembering_upgrade(store);
+        }
         self.handshake()?;
 
+        #[cfg(feature = "websocket")]
         if self.websocket_upgrade()? {
-            self.build_websocket_with_upgrade()
-        } else {
-            self.build_polling()
+            return self.build_websocket_with_upgrade();
         }
+        self.build_polling()
     }
 
     /// Build socket with polling transport
     pub fn build_polling(mut self) -> Result<Client> {
+        let buffer_pool = BufferPool::new(self.buffer_pool_size);
         self.handshake()?;
 
         // Make a polling transport with new sid
-        let transport = PollingTransport::new(
+        let transport = PollingTransport::with_options(
             self.url,
             self.tls_config,
             self.headers.map(|v| v.try_into().unwrap()),
-        );
+            &self.transport_options,
+        )?;
 
         // SAFETY: handshake function called previously.
         Ok(Client {
@@ -189,11 +348,13 @@ impl ClientBuilder {
                 self.on_error,
                 self.on_open,
                 self.on_packet,
-            ),
+            )
+            .with_buffer_pool(buffer_pool),
         })
     }
 
     /// Build socket with a polling transport then upgrade to websocket transport
+    #[cfg(feature = "websocket")]
     pub fn build_websocket_with_upgrade(mut self) -> Result<Client> {
         self.handshake()?;
 
@@ -205,7 +366,9 @@ impl ClientBuilder {
     }
 
     /// Build socket with only a websocket transport
+    #[cfg(feature = "websocket")]
     pub fn build_websocket(mut self) -> Result<Client> {
+        let buffer_pool = BufferPool::new(self.buffer_pool_size);
         // SAFETY: Already a Url
         let url = url::Url::parse(self.url.as_ref())?;
 
@@ -217,12 +380,20 @@ impl ClientBuilder {
 
         match url.scheme() {
             "http" | "ws" => {
-                let transport = WebsocketTransport::new(url, headers)?;
+                let mut transport =
+                    WebsocketTransport::with_options(url, headers, &self.transport_options)?;
                 if self.handshake.is_some() {
                     transport.upgrade()?;
+                    if let Some(on_upgrade) = self.on_upgrade.as_ref() {
+                        spawn_scoped!(on_upgrade(()));
+                    }
                 } else {
                     self.handshake_with_transport(&transport)?;
                 }
+                transport.set_buffer_pool(buffer_pool.clone());
+                if let Some(interval) = self.websocket_ping_interval {
+                    transport.start_keepalive(interval);
+                }
                 // NOTE: Although self.url contains the sid, it does not propagate to the transport
                 // SAFETY: handshake function called previously.
                 Ok(Client {
@@ -234,17 +405,29 @@ impl ClientBuilder {
                         self.on_error,
                         self.on_open,
                         self.on_packet,
-                    ),
+                    )
+                    .with_buffer_pool(buffer_pool),
                 })
             }
             "https" | "wss" => {
-                let transport =
-                    WebsocketSecureTransport::new(url, self.tls_config.clone(), headers)?;
+                let mut transport = WebsocketSecureTransport::with_options(
+                    url,
+                    self.tls_config.clone(),
+                    headers,
+                    &self.transport_options,
+                )?;
                 if self.handshake.is_some() {
                     transport.upgrade()?;
+                    if let Some(on_upgrade) = self.on_upgrade.as_ref() {
+                        spawn_scoped!(on_upgrade(()));
+                    }
                 } else {
                     self.handshake_with_transport(&transport)?;
                 }
+                transport.set_buffer_pool(buffer_pool.clone());
+                if let Some(interval) = self.websocket_ping_interval {
+                    transport.start_keepalive(interval);
+                }
                 // NOTE: Although self.url contains the sid, it does not propagate to the transport
                 // SAFETY: handshake function called previously.
                 Ok(Client {
@@ -256,7 +439,8 @@ impl ClientBuilder {
                         self.on_error,
                         self.on_open,
                         self.on_packet,
-                    ),
+                    )
+                    .with_buffer_pool(buffer_pool),
                 })
             }
             _ => Err(Error::InvalidUrlScheme(url.scheme().to_string())),
@@ -274,7 +458,30 @@ impl ClientBuilder {
         }
     }
 
+    /// Like `build`, connects to the servers of `store` with a websocket
+    /// right away and updates `store` with the outcome of the upgrade.
+    #[cfg(feature = "websocket")]
+    fn build_remembering_upgrade(mut self, store: SharedUpgradeStore) -> Result<Client> {
+        if self.handshake.is_none() && store.is_remembered(&self.url) {
+            match self.clone().build_websocket() {
+                Ok(client) => return Ok(client),
+                Err(_) => store.forget(&self.url),
+            }
+        }
+
+        self.handshake()?;
+        if self.websocket_upgrade()? {
+            let url = self.url.clone();
+            let client = self.build_websocket_with_upgrade()?;
+            store.remember(&url);
+            return Ok(client);
+        }
+        store.forget(&self.url);
+        self.build_polling()
+    }
+
     /// Checks the handshake to see if websocket upgrades are allowed
+    #[cfg(feature = "websocket")]
     fn websocket_upgrade(&mut self) -> Result<bool> {
         // SAFETY: handshake set by above function.
         Ok(self
@@ -303,6 +510,15 @@ impl Client {
         self.socket.disconnect()
     }
 
+    /// Closes the connection right away, without sending a close packet to the
+    /// server. Unlike [`Client::disconnect`], nothing is sent to the server
+    /// anymore once this returns, and the response of a running poll is
+    /// discarded. The server notices the closed connection by the missing
+    /// heartbeat.
+    pub fn abort(&self) {
+        self.socket.abort()
+    }
+
     /// Sends a packet to the server.
     pub fn emit(&self, packet: Packet) -> Result<()> {
         self.socket.emit(packet)
@@ -316,11 +532,9 @@ impl Client {
             // check for the appropriate action or callback
             self.socket.handle_packet(packet.clone());
             match packet.packet_id {
-                PacketId::MessageBinary => {
-                    self.socket.handle_data(packet.data.clone());
-                }
-                PacketId::Message => {
-                    self.socket.handle_data(packet.data.clone());
+                PacketId::MessageBinary | PacketId::Message => {
+                    self.socket
+                        .handle_data(packet.data.clone(), packet.packet_id);
                 }
                 PacketId::Close => {
                     self.socket.handle_close();
@@ -333,7 +547,7 @@ impl Client {
                 }
                 PacketId::Ping => {
                     self.socket.pinged()?;
-                    self.emit(Packet::new(PacketId::Pong, Bytes::new()))?;
+                    self.socket.pong()?;
                 }
                 PacketId::Pong => {
                     // this will never happen as the pong packet is
@@ -353,6 +567,28 @@ impl Client {
         self.socket.is_connected()
     }
 
+    /// Returns the handshake data the server sent when opening the connection,
+    /// including any extension fields.
+    pub fn handshake(&self) -> &HandshakePacket {
+        self.socket.handshake()
+    }
+
+    /// Returns statistics about the pool of buffers that packets are encoded with.
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.socket.buffer_pool_stats()
+    }
+
+    /// Returns the HTTP version that was negotiated with the server, `None` if
+    /// a websocket transport is used.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        self.socket.http_version()
+    }
+
+    /// Returns the transport of the connection.
+    pub fn transport_kind(&self) -> TransportKind {
+        self.socket.transport_kind()
+    }
+
     pub fn iter(&self) -> Iter {
         Iter { socket: self }
     }
@@ -378,6 +614,7 @@ impl<'a> Iterator for Iter<'a> {
 mod test {
 
     use crate::packet::PacketId;
+    use crate::Error;
 
     use super::*;
 
@@ -445,6 +682,7 @@ mod test {
 
         assert!(sut.poll().is_ok());
 
+        #[cfg(feature = "websocket")]
         assert!(builder(Url::parse("fake://fake.fake").unwrap())
             .build_websocket()
             .is_err());
@@ -522,6 +760,26 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "websocket")]
+    fn test_connection_websocket_ping() -> Result<()> {
+        let url = crate::test::engine_io_server()?;
+        let socket = builder(url)
+            .websocket_ping_interval(Duration::from_millis(10))
+            .build_websocket()?;
+        test_connection(socket)
+    }
+
+    #[test]
+    fn test_connection_http1() -> Result<()> {
+        let url = crate::test::engine_io_polling_server()?;
+        let socket = builder(url)
+            .http_protocol(HttpProtocol::Http1)
+            .build_polling()?;
+        assert_eq!(socket.http_version(), Some(HttpVersion::HTTP_11));
+        test_connection(socket)
+    }
+
     #[test]
     fn test_connection_dynamic() -> Result<()> {
         let url = crate::test::engine_io_server()?;
@@ -561,6 +819,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "websocket")]
     fn test_connection_wss() -> Result<()> {
         let url = crate::test::engine_io_polling_server()?;
         assert!(builder(url).build_websocket_with_upgrade().is_err());
@@ -598,6 +857,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "websocket")]
     fn test_connection_ws() -> Result<()> {
         let url = crate::test::engine_io_polling_server()?;
         assert!(builder(url.clone()).build_websocket().is_err());
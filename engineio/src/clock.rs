@@ -0,0 +1,48 @@
+//! Time keeping for the heartbeat that notices when the system was suspended.
+//!
+//! A heartbeat deadline that is only checked once the pending poll returns is
+//! pushed back by the time the machine was suspended, so the client would wait
+//! for the full remaining ping timeout after waking up although the server
+//! dropped the connection long ago. Therefore pending polls are interrupted
+//! regularly to check the deadline, which catches up as soon as the monotonic
+//! clock includes the suspended time. The wall clock is never consulted, as an
+//! NTP step would make every connection look timed out.
+//!
+//! The monotonic clock is read from tokio, so tests can pause and advance it.
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The interval in which a pending poll is interrupted in order to check whether
+/// the heartbeat expired while the system was suspended.
+pub(crate) const WAKE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A point in time taken from the monotonic clock.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timestamp {
+    monotonic: Instant,
+}
+
+impl Timestamp {
+    pub(crate) fn now() -> Self {
+        Timestamp {
+            monotonic: Instant::now(),
+        }
+    }
+
+    /// Returns the time that elapsed since this timestamp was taken.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.monotonic.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_elapsed_paused() {
+        let sut = Timestamp::now();
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(sut.elapsed() >= Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::PacketId;
+
+/// How a message passed to `on_data_with_info` arrived, so that users of
+/// plain engine.io can demultiplex the protocols they run on top of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DataInfo {
+    /// Either [`PacketId::Message`] for text or [`PacketId::MessageBinary`]
+    /// for binary data, which the polling transport sends base64 encoded.
+    pub packet_id: PacketId,
+    /// The transport the message arrived on.
+    pub transport: TransportKind,
+}
+
+impl DataInfo {
+    pub(crate) fn new(packet_id: PacketId, transport: TransportKind) -> Self {
+        DataInfo {
+            packet_id,
+            transport,
+        }
+    }
+
+    /// Whether the message was sent as binary data.
+    pub fn is_binary(&self) -> bool {
+        self.packet_id == PacketId::MessageBinary
+    }
+}
+
+/// The transport of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TransportKind {
+    /// HTTP long-polling.
+    Polling,
+    /// A websocket without TLS.
+    Websocket,
+    /// A websocket over TLS.
+    WebsocketSecure,
+    /// The in-memory transport of
+    /// [`crate::asynchronous::async_transports::loopback`].
+    #[cfg(any(test, feature = "test-support"))]
+    Loopback,
+    /// The scripted transport of
+    /// [`crate::asynchronous::async_transports::MockTransport`].
+    #[cfg(any(test, feature = "test-support"))]
+    Mock,
+    /// A transport implemented outside of this crate, see
+    /// [`crate::asynchronous::async_transports::CustomTransport`].
+    Custom,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        let info = DataInfo::new(PacketId::MessageBinary, TransportKind::Polling);
+        assert!(info.is_binary());
+        let info = DataInfo::new(PacketId::Message, TransportKind::Websocket);
+        assert!(!info.is_binary());
+    }
+}
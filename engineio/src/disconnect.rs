@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, TransportKind};
+
+/// Why a connection was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum CloseReason {
+    /// The client disconnected or aborted the connection.
+    ForcedClose,
+    /// The server sent a close packet.
+    ServerClose,
+    /// The server didn't send a ping in time.
+    PingTimeout,
+    /// The server didn't acknowledge a probe of the connection, see
+    /// `ClientBuilder::half_open_probe`.
+    HalfOpenConnection,
+    /// The transport ended without a close packet, e.g. the server closed the
+    /// websocket.
+    TransportClose,
+    /// The transport failed, e.g. the connection was reset.
+    TransportError,
+}
+
+/// A close frame the server sent to end a websocket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CloseFrame {
+    /// The close code, e.g. `1001` if the server is going away.
+    pub code: u16,
+    pub reason: String,
+}
+
+/// Describes how a connection ended, as passed to
+/// `on_close_with_description`. Gathered when the connection closes, so that
+/// an abnormal termination can be analyzed without enabling debug logging in
+/// advance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DisconnectDescription {
+    pub reason: CloseReason,
+    /// The transport the connection used when it closed.
+    pub transport: TransportKind,
+    /// The time since data was received the last time.
+    pub since_last_received: Duration,
+    /// The time since the server sent the last ping.
+    pub since_last_ping: Duration,
+    /// The time since the client sent the last pong.
+    pub since_last_pong: Duration,
+    /// The size of the packets that were being sent when the connection
+    /// closed.
+    pub bytes_in_flight: usize,
+    /// The close frame of the server, if a websocket was closed with one.
+    pub close_frame: Option<CloseFrame>,
+    /// The error that closed the connection, if any.
+    pub error: Option<String>,
+    /// The error code of the operating system, if an I/O error closed the
+    /// connection, e.g. `104` for a reset connection on Linux.
+    pub os_error: Option<i32>,
+}
+
+impl DisconnectDescription {
+    pub(crate) fn new(reason: CloseReason, transport: TransportKind) -> Self {
+        DisconnectDescription {
+            reason,
+            transport,
+            since_last_received: Duration::ZERO,
+            since_last_ping: Duration::ZERO,
+            since_last_pong: Duration::ZERO,
+            bytes_in_flight: 0,
+            close_frame: None,
+            error: None,
+            os_error: None,
+        }
+    }
+
+    /// Records the error that closed the connection, including the error code
+    /// of the operating system it was caused by.
+    pub(crate) fn with_error(mut self, error: &Error) -> Self {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+        while let Some(cause) = source {
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                if let Some(code) = io.raw_os_error() {
+                    self.os_error = Some(code);
+                    break;
+                }
+            }
+            source = cause.source();
+        }
+        self.error = Some(error.to_string());
+        self
+    }
+
+    /// Whether the connection ended without the client or the server closing
+    /// it deliberately.
+    pub fn is_abnormal(&self) -> bool {
+        !matches!(
+            self.reason,
+            CloseReason::ForcedClose | CloseReason::ServerClose
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_os_error() {
+        let reset = std::io::Error::from_raw_os_error(104);
+        let description =
+            DisconnectDescription::new(CloseReason::TransportError, TransportKind::Polling)
+                .with_error(&Error::IncompleteIo(reset));
+        assert_eq!(description.os_error, Some(104));
+        assert!(description.error.is_some());
+        assert!(description.is_abnormal());
+
+        let description =
+            DisconnectDescription::new(CloseReason::ServerClose, TransportKind::Websocket)
+                .with_error(&Error::PingTimeout());
+        assert_eq!(description.os_error, None);
+        assert!(!description.is_abnormal());
+    }
+}
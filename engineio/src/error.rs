@@ -1,9 +1,14 @@
+use crate::ENGINE_IO_VERSION;
 use base64::DecodeError;
 use reqwest::Error as ReqwestError;
+use serde::Deserialize;
 use serde_json::Error as JsonError;
 use std::io::Error as IoError;
 use std::str::Utf8Error;
 use thiserror::Error;
+#[cfg(feature = "websocket")]
+use tungstenite::error::CapacityError;
+#[cfg(feature = "websocket")]
 use tungstenite::Error as TungsteniteError;
 use url::ParseError as UrlParseError;
 
@@ -30,6 +35,7 @@ pub enum Error {
     InvalidUrlScheme(String),
     #[error("Error during connection via http: {0}")]
     IncompleteResponseFromReqwest(#[from] ReqwestError),
+    #[cfg(feature = "websocket")]
     #[error("Error with websocket connection: {0}")]
     WebsocketError(#[from] TungsteniteError),
     #[error("Network request returned with status code: {0}")]
@@ -38,6 +44,8 @@ pub enum Error {
     InvalidHandshake(String),
     #[error("Called an action before the connection was established")]
     IllegalActionBeforeOpen(),
+    #[error("Called an action after the transport was aborted")]
+    IllegalActionAfterAbort(),
     #[error("Error setting up the http request: {0}")]
     InvalidHttpConfiguration(#[from] http::Error),
     #[error("string is not json serializable: {0}")]
@@ -46,6 +54,8 @@ pub enum Error {
     InvalidPoisonedLock(),
     #[error("Got an IO-Error: {0}")]
     IncompleteIo(#[from] IoError),
+    #[error("Error during the TLS handshake: {0}")]
+    IncompleteTls(#[from] native_tls::Error),
     #[error("Server did not allow upgrading to websockets")]
     IllegalWebsocketUpgrade(),
     #[error("Invalid header name")]
@@ -54,16 +64,110 @@ pub enum Error {
     InvalidHeaderValueFromReqwest(#[from] reqwest::header::InvalidHeaderValue),
     #[error("The server did not send a PING packet in time")]
     PingTimeout(),
+    #[error("The connection is half-open, the server did not acknowledge a probe")]
+    HalfOpenConnection(),
+    #[error(
+        "The server does not support engine.io protocol version {client} (server version: {})",
+        .server.map_or_else(|| "unknown".to_owned(), |version| version.to_string())
+    )]
+    UnsupportedProtocolVersion { server: Option<i32>, client: i32 },
+    #[error("The server rejected the websocket upgrade probe")]
+    UpgradeRejected(),
+    #[error("Received a payload that exceeds the limit of {0} bytes")]
+    PayloadTooLarge(usize),
+    #[error("Could not resolve the host {0}")]
+    UnresolvableHost(String),
+    #[cfg(feature = "server")]
+    #[error("Received an invalid HTTP request: {0}")]
+    InvalidHttpRequest(String),
+    #[cfg(feature = "tower")]
+    #[error("Error in the http middleware: {0}")]
+    HttpMiddleware(tower::BoxError),
 }
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// Checks whether the response to an opening request indicates that the
+    /// server speaks another protocol version than this crate.
+    pub(crate) fn from_handshake_response(data: &[u8]) -> Option<Self> {
+        // engine.io v3 prefixes every packet of a payload with its length, e.g. `96:0{"sid":...`
+        let is_v3_payload = data
+            .iter()
+            .position(|&byte| byte == b':')
+            .is_some_and(|index| index > 0 && data[..index].iter().all(u8::is_ascii_digit));
+        if is_v3_payload {
+            return Some(Error::UnsupportedProtocolVersion {
+                server: Some(3),
+                client: ENGINE_IO_VERSION,
+            });
+        }
+
+        // a server rejecting the requested version responds with `{"code":5,"message":"Unsupported protocol version"}`
+        #[derive(Deserialize)]
+        struct ErrorResponse {
+            code: u8,
+        }
+        const UNSUPPORTED_PROTOCOL_VERSION: u8 = 5;
+
+        match serde_json::from_slice::<ErrorResponse>(data) {
+            Ok(response) if response.code == UNSUPPORTED_PROTOCOL_VERSION => {
+                Some(Error::UnsupportedProtocolVersion {
+                    server: None,
+                    client: ENGINE_IO_VERSION,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts an error of the websocket handshake, detecting servers that
+    /// reject the requested protocol version.
+    #[cfg(feature = "websocket")]
+    pub(crate) fn from_websocket_handshake(error: TungsteniteError) -> Self {
+        if let TungsteniteError::Http(response) = &error {
+            if let Some(error) = response
+                .body()
+                .as_deref()
+                .and_then(Self::from_handshake_response)
+            {
+                return error;
+            }
+        }
+        Error::from(error)
+    }
+
+    /// Converts an error reading from a websocket, detecting messages that
+    /// exceed the configured payload limit.
+    #[cfg(feature = "websocket")]
+    pub(crate) fn from_websocket_read(error: TungsteniteError) -> Self {
+        match error {
+            TungsteniteError::Capacity(CapacityError::MessageTooLong { max_size, .. }) => {
+                Error::PayloadTooLarge(max_size)
+            }
+            error => Error::from(error),
+        }
+    }
+}
+
 impl<T> From<std::sync::PoisonError<T>> for Error {
     fn from(_: std::sync::PoisonError<T>) -> Self {
         Self::InvalidPoisonedLock()
     }
 }
 
+impl From<rust_socketio_wire::engineio::Error> for Error {
+    fn from(err: rust_socketio_wire::engineio::Error) -> Self {
+        use rust_socketio_wire::engineio::Error as WireError;
+        match err {
+            WireError::IncompletePacket => Error::IncompletePacket(),
+            WireError::InvalidPacketId(id) => Error::InvalidPacketId(id),
+            WireError::InvalidBase64(err) => Error::InvalidBase64(err),
+            _ => Error::InvalidPacket(),
+        }
+    }
+}
+
 impl From<Error> for std::io::Error {
     fn from(err: Error) -> std::io::Error {
         std::io::Error::new(std::io::ErrorKind::Other, err)
@@ -88,4 +192,33 @@ mod tests {
             std::io::Error::new(std::io::ErrorKind::Other, Error::IllegalWebsocketUpgrade());
         assert!(matches!(_io_error, _error));
     }
+
+    #[test]
+    fn test_unsupported_protocol_version() {
+        assert!(matches!(
+            Error::from_handshake_response(
+                br#"96:0{"sid":"abc","upgrades":[],"pingInterval":25000,"pingTimeout":5000}"#
+            ),
+            Some(Error::UnsupportedProtocolVersion {
+                server: Some(3),
+                client: ENGINE_IO_VERSION
+            })
+        ));
+        assert!(matches!(
+            Error::from_handshake_response(
+                br#"{"code":5,"message":"Unsupported protocol version"}"#
+            ),
+            Some(Error::UnsupportedProtocolVersion { server: None, .. })
+        ));
+
+        // other errors and valid handshakes are left alone
+        assert!(
+            Error::from_handshake_response(br#"{"code":1,"message":"Session ID unknown"}"#)
+                .is_none()
+        );
+        assert!(Error::from_handshake_response(
+            br#"0{"sid":"abc","upgrades":[],"pingInterval":25000,"pingTimeout":5000}"#
+        )
+        .is_none());
+    }
 }
@@ -105,6 +105,36 @@ impl TryFrom<HeaderMap> for HttpHeaderMap {
     }
 }
 
+impl From<HttpHeaderMap> for HeaderMap {
+    /// The values of a repeated header are joined into one, separated by
+    /// commas as HTTP allows. Cookies are joined by semicolons instead, as a
+    /// request carries them in a single `Cookie` header (RFC 6265).
+    fn from(headers: HttpHeaderMap) -> Self {
+        let mut map = HeaderMap::new();
+        for key in headers.keys() {
+            let separator: &[u8] = if key == http::header::COOKIE {
+                b"; "
+            } else {
+                b", "
+            };
+            let mut value = Vec::new();
+            for (i, part) in headers.get_all(key).iter().enumerate() {
+                if i > 0 {
+                    value.extend_from_slice(separator);
+                }
+                value.extend_from_slice(part.as_bytes());
+            }
+            map.insert(
+                HeaderName::from(key.clone()),
+                HeaderValue {
+                    inner: Bytes::from(value),
+                },
+            );
+        }
+        map
+    }
+}
+
 impl IntoIterator for HeaderMap {
     type Item = (HeaderName, HeaderValue);
     type IntoIter = IntoIter;
@@ -137,3 +167,24 @@ impl Iterator for IntoIter {
         self.inner.next()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_http_header_map() {
+        let mut headers = HttpHeaderMap::new();
+        headers.insert("x-tenant", HttpHeaderValue::from_static("acme"));
+        headers.append("accept", HttpHeaderValue::from_static("text/plain"));
+        headers.append("accept", HttpHeaderValue::from_static("application/json"));
+        headers.append("cookie", HttpHeaderValue::from_static("session=abc"));
+        headers.append("cookie", HttpHeaderValue::from_static("theme=dark"));
+
+        let map = HeaderMap::from(headers);
+        let headers = HttpHeaderMap::try_from(map).unwrap();
+        assert_eq!(headers["x-tenant"], "acme");
+        assert_eq!(headers["accept"], "text/plain, application/json");
+        assert_eq!(headers["cookie"], "session=abc; theme=dark");
+    }
+}
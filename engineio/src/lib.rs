@@ -36,6 +36,9 @@
 //! * `build_websocket_with_upgrade`: Build socket with a polling transport then upgrade to websocket transport (if possible).
 //! * `build_websocket`: Build socket with only a websocket transport, crashes when websockets are not allowed.
 //!
+//! The websocket transports are behind the default `websocket` feature. Polling-only builds
+//! disable it to drop the `tungstenite` dependency, `build` then always uses polling.
+//!
 //!
 //! ## Current features
 //!
@@ -43,14 +46,17 @@
 //! This includes various transport options, the possibility of sending engine.io packets and registering the
 //! common engine.io event callbacks:
 //! * on_open
-//! * on_close
-//! * on_data
+//! * on_close (or on_close_with_description, which also tells why the connection closed)
+//! * on_data (or on_data_with_info, which also tells whether the data was binary)
 //! * on_error
 //! * on_packet
 //!
 //! It is also possible to pass in custom tls configurations via the `TlsConnector` as well
 //! as custom headers for the opening request.
 //!
+//! The `serde` feature derives `Serialize` and `Deserialize` for packets and close
+//! frames, to persist or replay them in other tooling.
+//!
 //! ## Async version
 //!
 //! The crate also ships with an asynchronous version that can be enabled with a feature flag.
@@ -80,14 +86,26 @@ macro_rules! spawn_scoped {
 }
 
 pub mod asynchronous;
+#[doc(hidden)]
+pub mod bench;
+mod binding;
 mod callback;
 pub mod client;
+mod clock;
+mod data;
+mod disconnect;
 /// Generic header map
 pub mod header;
 pub mod packet;
+mod pool;
+mod resolver;
+#[cfg(feature = "server")]
+pub mod server;
 pub(self) mod socket;
+mod timing;
 pub mod transport;
 pub mod transports;
+mod upgrade;
 
 pub const ENGINE_IO_VERSION: i32 = 4;
 
@@ -95,9 +113,21 @@ pub const ENGINE_IO_VERSION: i32 = 4;
 /// crate. Handles all kinds of errors.
 pub mod error;
 
+pub use binding::split_zone;
 pub use client::{Client, ClientBuilder};
+pub use data::{DataInfo, TransportKind};
+pub use disconnect::{CloseFrame, CloseReason, DisconnectDescription};
 pub use error::Error;
+/// The HTTP version that was negotiated with the server.
+pub use http::Version as HttpVersion;
 pub use packet::{Packet, PacketId};
+pub use pool::{BufferPoolStats, DEFAULT_BUFFER_POOL_SIZE};
+pub use resolver::{DohResolver, Resolver};
+pub use timing::EmitTimingStats;
+#[cfg(feature = "websocket")]
+pub use transport::WriteRetry;
+pub use transport::{HttpProtocol, PollRetry};
+pub use upgrade::{FileUpgradeStore, MemoryUpgradeStore, UpgradeStore};
 
 #[cfg(test)]
 pub(crate) mod test {
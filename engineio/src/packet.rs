@@ -1,15 +1,18 @@
-use base64::{engine::general_purpose, Engine as _};
 use bytes::{BufMut, Bytes, BytesMut};
+use rust_socketio_wire::engineio as wire;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::char;
+use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::convert::TryInto;
 use std::fmt::{Display, Formatter, Result as FmtResult, Write};
 use std::ops::Index;
 
 use crate::error::{Error, Result};
+use crate::pool::BufferPool;
 /// Enumeration of the `engine.io` `Packet` types.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PacketId {
     Open,
     Close,
@@ -25,10 +28,7 @@ pub enum PacketId {
 impl PacketId {
     /// Returns the byte that represents the [`PacketId`] as a [`char`].
     fn to_string_byte(self) -> u8 {
-        match self {
-            Self::MessageBinary => b'b',
-            _ => u8::from(self) + b'0',
-        }
+        wire::PacketId::from(self).to_byte()
     }
 }
 
@@ -40,16 +40,7 @@ impl Display for PacketId {
 
 impl From<PacketId> for u8 {
     fn from(packet_id: PacketId) -> Self {
-        match packet_id {
-            PacketId::Open => 0,
-            PacketId::Close => 1,
-            PacketId::Ping => 2,
-            PacketId::Pong => 3,
-            PacketId::Message => 4,
-            PacketId::MessageBinary => 4,
-            PacketId::Upgrade => 5,
-            PacketId::Noop => 6,
-        }
+        wire::PacketId::from(packet_id).number()
     }
 }
 
@@ -57,21 +48,43 @@ impl TryFrom<u8> for PacketId {
     type Error = Error;
     /// Converts a byte into the corresponding `PacketId`.
     fn try_from(b: u8) -> Result<PacketId> {
-        match b {
-            0 | b'0' => Ok(PacketId::Open),
-            1 | b'1' => Ok(PacketId::Close),
-            2 | b'2' => Ok(PacketId::Ping),
-            3 | b'3' => Ok(PacketId::Pong),
-            4 | b'4' => Ok(PacketId::Message),
-            5 | b'5' => Ok(PacketId::Upgrade),
-            6 | b'6' => Ok(PacketId::Noop),
-            _ => Err(Error::InvalidPacketId(b)),
+        Ok(wire::PacketId::from_byte(b)?.into())
+    }
+}
+
+impl From<PacketId> for wire::PacketId {
+    fn from(packet_id: PacketId) -> Self {
+        match packet_id {
+            PacketId::Open => wire::PacketId::Open,
+            PacketId::Close => wire::PacketId::Close,
+            PacketId::Ping => wire::PacketId::Ping,
+            PacketId::Pong => wire::PacketId::Pong,
+            PacketId::Message => wire::PacketId::Message,
+            PacketId::MessageBinary => wire::PacketId::MessageBinary,
+            PacketId::Upgrade => wire::PacketId::Upgrade,
+            PacketId::Noop => wire::PacketId::Noop,
+        }
+    }
+}
+
+impl From<wire::PacketId> for PacketId {
+    fn from(packet_id: wire::PacketId) -> Self {
+        match packet_id {
+            wire::PacketId::Open => PacketId::Open,
+            wire::PacketId::Close => PacketId::Close,
+            wire::PacketId::Ping => PacketId::Ping,
+            wire::PacketId::Pong => PacketId::Pong,
+            wire::PacketId::Message => PacketId::Message,
+            wire::PacketId::MessageBinary => PacketId::MessageBinary,
+            wire::PacketId::Upgrade => PacketId::Upgrade,
+            wire::PacketId::Noop => PacketId::Noop,
         }
     }
 }
 
 /// A `Packet` sent via the `engine.io` protocol.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Packet {
     pub packet_id: PacketId,
     pub data: Bytes,
@@ -81,11 +94,29 @@ pub struct Packet {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct HandshakePacket {
     pub sid: String,
+    #[serde(default)]
     pub upgrades: Vec<String>,
     #[serde(rename = "pingInterval")]
     pub ping_interval: u64,
     #[serde(rename = "pingTimeout")]
     pub ping_timeout: u64,
+    #[serde(flatten)]
+    pub(crate) extensions: HashMap<String, serde_json::Value>,
+}
+
+impl HandshakePacket {
+    /// All fields of the handshake that are not known to this crate, like
+    /// `maxPayload` or custom fields added by the server.
+    pub fn extensions(&self) -> &HashMap<String, serde_json::Value> {
+        &self.extensions
+    }
+
+    /// Parses the response of the server to the opening request.
+    pub(crate) fn from_response(data: Bytes) -> Result<Self> {
+        Packet::try_from(data.clone())
+            .and_then(HandshakePacket::try_from)
+            .map_err(|err| Error::from_handshake_response(&data).unwrap_or(err))
+    }
 }
 
 impl TryFrom<Packet> for HandshakePacket {
@@ -103,6 +134,21 @@ impl Packet {
             data: data.into(),
         }
     }
+
+    /// Encodes the packet into a buffer taken from the given pool.
+    pub(crate) fn encode(self, pool: &BufferPool) -> Bytes {
+        pool.encode(self.encoded_len(), |buffer| self.write_to(buffer))
+    }
+
+    fn encoded_len(&self) -> usize {
+        wire::encoded_len(self.packet_id.into(), &self.data)
+    }
+
+    fn write_to(self, buffer: &mut BytesMut) {
+        let start = buffer.len();
+        buffer.resize(start + self.encoded_len(), 0);
+        wire::encode_packet(self.packet_id.into(), &self.data, &mut buffer[start..]);
+    }
 }
 
 impl TryFrom<Bytes> for Packet {
@@ -111,31 +157,12 @@ impl TryFrom<Bytes> for Packet {
     fn try_from(
         bytes: Bytes,
     ) -> std::result::Result<Self, <Self as std::convert::TryFrom<Bytes>>::Error> {
-        if bytes.is_empty() {
-            return Err(Error::IncompletePacket());
-        }
-
-        let is_base64 = *bytes.first().ok_or(Error::IncompletePacket())? == b'b';
-
-        // only 'messages' packets could be encoded
-        let packet_id = if is_base64 {
-            PacketId::MessageBinary
-        } else {
-            (*bytes.first().ok_or(Error::IncompletePacket())?).try_into()?
-        };
-
-        if bytes.len() == 1 && packet_id == PacketId::Message {
-            return Err(Error::IncompletePacket());
-        }
-
-        let data: Bytes = bytes.slice(1..);
-
+        let (packet_id, data) = wire::decode_packet(&bytes)?;
         Ok(Packet {
-            packet_id,
-            data: if is_base64 {
-                Bytes::from(general_purpose::STANDARD.decode(data.as_ref())?)
-            } else {
-                data
+            packet_id: packet_id.into(),
+            data: match data {
+                Cow::Borrowed(data) => bytes.slice_ref(data),
+                Cow::Owned(data) => Bytes::from(data),
             },
         })
     }
@@ -144,37 +171,35 @@ impl TryFrom<Bytes> for Packet {
 impl From<Packet> for Bytes {
     /// Encodes a `Packet` into an `u8` byte stream.
     fn from(packet: Packet) -> Self {
-        let mut result = BytesMut::with_capacity(packet.data.len() + 1);
-        result.put_u8(packet.packet_id.to_string_byte());
-        if packet.packet_id == PacketId::MessageBinary {
-            result.extend(general_purpose::STANDARD.encode(packet.data).into_bytes());
-        } else {
-            result.put(packet.data);
-        }
+        let mut result = BytesMut::with_capacity(packet.encoded_len());
+        packet.write_to(&mut result);
         result.freeze()
     }
 }
 
+/// A chain of packets as sent by the polling transport.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct Payload(Vec<Packet>);
 
 impl Payload {
-    // see https://en.wikipedia.org/wiki/Delimiter#ASCII_delimited_text
-    const SEPARATOR: char = '\x1e';
-
-    #[cfg(test)]
     pub fn len(&self) -> usize {
         self.0.len()
     }
 }
 
+impl From<Vec<Packet>> for Payload {
+    fn from(packets: Vec<Packet>) -> Self {
+        Payload(packets)
+    }
+}
+
 impl TryFrom<Bytes> for Payload {
     type Error = Error;
     /// Decodes a `payload` which in the `engine.io` context means a chain of normal
     /// packets separated by a certain SEPARATOR, in this case the delimiter `\x30`.
     fn try_from(payload: Bytes) -> Result<Self> {
-        payload
-            .split(|&c| c as char == Self::SEPARATOR)
+        wire::split_payload(&payload)
             .map(|slice| Packet::try_from(payload.slice_ref(slice)))
             .collect::<Result<Vec<_>>>()
             .map(Self)
@@ -191,7 +216,7 @@ impl TryFrom<Payload> for Bytes {
         for packet in packets {
             // at the moment no base64 encoding is used
             buf.extend(Bytes::from(packet.clone()));
-            buf.put_u8(Payload::SEPARATOR as u8);
+            buf.put_u8(wire::SEPARATOR);
         }
 
         // remove the last separator
@@ -261,6 +286,11 @@ mod tests {
         assert_eq!(packet.data, Bytes::from_static(b"Hello"));
 
         assert_eq!(Bytes::from(packet), data);
+
+        // a binary websocket frame
+        let packet = Packet::try_from(Bytes::from_static(b"\x04Hello")).unwrap();
+        assert_eq!(packet.packet_id, PacketId::MessageBinary);
+        assert_eq!(packet.data, Bytes::from_static(b"Hello"));
     }
 
     #[test]
@@ -349,6 +379,7 @@ mod tests {
             ping_timeout: 1000,
             sid: "Test".to_owned(),
             upgrades: vec!["websocket".to_owned(), "test".to_owned()],
+            extensions: HashMap::new(),
         };
         let encoded: String = serde_json::to_string(&packet).unwrap();
 
@@ -358,4 +389,42 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn test_handshake_packet_extensions() {
+        let data = r#"{"sid":"Test","upgrades":["websocket"],"pingInterval":25000,"pingTimeout":20000,"maxPayload":1000000,"region":"eu"}"#;
+        let packet =
+            HandshakePacket::try_from(Packet::new(PacketId::Open, Bytes::from(data))).unwrap();
+
+        assert_eq!(packet.sid, "Test");
+        assert_eq!(packet.ping_interval, 25000);
+        assert_eq!(packet.extensions().len(), 2);
+        assert_eq!(
+            packet.extensions()["maxPayload"],
+            serde_json::json!(1000000)
+        );
+        assert_eq!(packet.extensions()["region"], serde_json::json!("eu"));
+
+        // a missing list of upgrades means that no upgrades are available
+        let data = r#"{"sid":"Test","pingInterval":25000,"pingTimeout":20000}"#;
+        let packet =
+            HandshakePacket::try_from(Packet::new(PacketId::Open, Bytes::from(data))).unwrap();
+        assert!(packet.upgrades.is_empty());
+        assert!(packet.extensions().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let packet = Packet::new(PacketId::MessageBinary, Bytes::from_static(&[1, 2, 3]));
+        let json = serde_json::to_string(&packet).unwrap();
+        assert_eq!(serde_json::from_str::<Packet>(&json).unwrap(), packet);
+
+        let payload = Payload::from(vec![packet.clone(), Packet::new(PacketId::Ping, "")]);
+        let json = serde_json::to_string(&payload).unwrap();
+        let payload: Payload = serde_json::from_str(&json).unwrap();
+        assert_eq!(payload.len(), 2);
+        assert_eq!(payload[0], packet);
+        assert_eq!(payload[1], Packet::new(PacketId::Ping, ""));
+    }
 }
@@ -0,0 +1,169 @@
+//! A pool of reusable buffers that packets are encoded into.
+//!
+//! Encoded packets are split off a pooled [`BytesMut`] and handed out as frozen
+//! [`Bytes`]. Once all handed out chunks of a buffer are dropped, the next
+//! `reserve` on the buffer reclaims the memory instead of allocating again.
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use bytes::{Bytes, BytesMut};
+
+/// The default number of idle buffers that are kept by the pool of a client.
+pub const DEFAULT_BUFFER_POOL_SIZE: usize = 16;
+
+/// The capacity new buffers are allocated with.
+const BUFFER_CAPACITY: usize = 8 * 1024;
+
+/// Statistics about the buffer pool of a client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// How often a pooled buffer was reused.
+    pub reused: u64,
+    /// How often a new buffer was allocated as the pool was empty.
+    pub allocated: u64,
+    /// The number of buffers that are currently idle in the pool.
+    pub idle: usize,
+}
+
+#[derive(Debug)]
+struct Inner {
+    buffers: Mutex<Vec<BytesMut>>,
+    size: usize,
+    reused: AtomicU64,
+    allocated: AtomicU64,
+}
+
+/// A pool of buffers, cloning it yields a handle to the same pool.
+#[derive(Debug, Clone)]
+pub(crate) struct BufferPool {
+    inner: Arc<Inner>,
+}
+
+impl BufferPool {
+    /// Creates a pool that keeps at most `size` idle buffers. A size of `0`
+    /// disables pooling.
+    pub(crate) fn new(size: usize) -> Self {
+        BufferPool {
+            inner: Arc::new(Inner {
+                buffers: Mutex::new(Vec::with_capacity(size)),
+                size,
+                reused: AtomicU64::new(0),
+                allocated: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Writes at least `len` bytes into a pooled buffer using `write` and
+    /// returns the written bytes. Without pooling, the buffer is allocated
+    /// with a capacity of exactly `len`.
+    pub(crate) fn encode<F>(&self, len: usize, write: F) -> Bytes
+    where
+        F: FnOnce(&mut BytesMut),
+    {
+        if self.inner.size == 0 {
+            self.inner.allocated.fetch_add(1, Ordering::Relaxed);
+            let mut buffer = BytesMut::with_capacity(len);
+            write(&mut buffer);
+            return buffer.freeze();
+        }
+
+        let mut buffer = self.take();
+        buffer.reserve(len);
+        write(&mut buffer);
+
+        let bytes = buffer.split().freeze();
+        self.put_back(buffer);
+        bytes
+    }
+
+    pub(crate) fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            reused: self.inner.reused.load(Ordering::Relaxed),
+            allocated: self.inner.allocated.load(Ordering::Relaxed),
+            idle: self.inner.buffers.lock().map_or(0, |buffers| buffers.len()),
+        }
+    }
+
+    fn take(&self) -> BytesMut {
+        // a poisoned lock just disables pooling
+        let buffer = self
+            .inner
+            .buffers
+            .lock()
+            .ok()
+            .and_then(|mut buffers| buffers.pop());
+
+        match buffer {
+            Some(buffer) => {
+                self.inner.reused.fetch_add(1, Ordering::Relaxed);
+                buffer
+            }
+            None => {
+                self.inner.allocated.fetch_add(1, Ordering::Relaxed);
+                BytesMut::with_capacity(BUFFER_CAPACITY)
+            }
+        }
+    }
+
+    fn put_back(&self, buffer: BytesMut) {
+        if let Ok(mut buffers) = self.inner.buffers.lock() {
+            if buffers.len() < self.inner.size {
+                buffers.push(buffer);
+            }
+        }
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool::new(DEFAULT_BUFFER_POOL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BufMut;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_reuses_buffers() {
+        let pool = BufferPool::new(1);
+
+        let first = pool.encode(3, |buffer| buffer.put_slice(b"foo"));
+        let second = pool.encode(3, |buffer| buffer.put_slice(b"bar"));
+        assert_eq!(first, Bytes::from_static(b"foo"));
+        assert_eq!(second, Bytes::from_static(b"bar"));
+
+        assert_eq!(
+            pool.stats(),
+            BufferPoolStats {
+                reused: 1,
+                allocated: 1,
+                idle: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_disabled_pool() {
+        let pool = BufferPool::new(0);
+
+        pool.encode(3, |buffer| {
+            assert_eq!(buffer.capacity(), 3);
+            buffer.put_slice(b"foo")
+        });
+        pool.encode(3, |buffer| buffer.put_slice(b"bar"));
+
+        assert_eq!(
+            pool.stats(),
+            BufferPoolStats {
+                reused: 0,
+                allocated: 2,
+                idle: 0,
+            }
+        );
+    }
+}
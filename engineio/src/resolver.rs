@@ -0,0 +1,364 @@
+use std::{
+    fmt::Debug,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures_util::future::join;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::ACCEPT,
+};
+use serde::Deserialize;
+use tokio::runtime::{Handle, Runtime};
+use url::Url;
+
+use crate::{error::Result, Error};
+
+/// Resolves the host name of the server in place of the DNS resolver of the
+/// system. A resolver is set via `ClientBuilder::resolver` and used by the
+/// polling and the websocket transports alike, e.g. to plug in a resolver
+/// crate like `hickory-resolver` (formerly `trust-dns`), to cache lookups or to
+/// map internal service names to their addresses.
+///
+/// # Example
+/// ```rust
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// use async_trait::async_trait;
+/// use rust_engineio::{Error, Resolver};
+///
+/// /// Resolves every host to the local machine.
+/// struct Localhost;
+///
+/// #[async_trait]
+/// impl Resolver for Localhost {
+///     async fn resolve(&self, _host: &str) -> Result<Vec<IpAddr>, Error> {
+///         Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Resolver: Send + Sync {
+    /// Returns the addresses of `host`, which is never an IP address.
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>>;
+}
+
+#[async_trait]
+impl<R: Resolver + ?Sized> Resolver for Arc<R> {
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>> {
+        (**self).resolve(host).await
+    }
+}
+
+/// A [`Resolver`] that looks up hosts via DNS-over-HTTPS, so that neither the
+/// network nor the DNS servers of the system see which hosts are connected to.
+/// It queries an endpoint that speaks the JSON format of DNS-over-HTTPS, as
+/// offered by Cloudflare and Google, for the IPv4 and the IPv6 addresses.
+#[derive(Debug, Clone)]
+pub struct DohResolver {
+    endpoint: Url,
+    client: reqwest::Client,
+}
+
+/// The record types of IPv4 and IPv6 addresses.
+const RECORD_A: u16 = 1;
+const RECORD_AAAA: u16 = 28;
+
+impl DohResolver {
+    /// Uses the DNS-over-HTTPS endpoint at `endpoint`, e.g.
+    /// `https://cloudflare-dns.com/dns-query`. The host of the endpoint itself
+    /// is resolved by the system, use an IP address to avoid that.
+    pub fn new(endpoint: Url) -> Self {
+        DohResolver {
+            endpoint,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Uses the endpoint of Cloudflare at `https://1.1.1.1/dns-query`.
+    pub fn cloudflare() -> Self {
+        // SAFETY: the url is valid
+        Self::new(Url::parse("https://1.1.1.1/dns-query").unwrap())
+    }
+
+    /// Uses the endpoint of Google at `https://8.8.8.8/resolve`.
+    pub fn google() -> Self {
+        // SAFETY: the url is valid
+        Self::new(Url::parse("https://8.8.8.8/resolve").unwrap())
+    }
+
+    /// Sends the queries with `client` instead of a default one, e.g. to
+    /// configure timeouts or a proxy.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn query(&self, host: &str, record_type: u16) -> Result<Vec<IpAddr>> {
+        let response = self
+            .client
+            .get(self.endpoint.clone())
+            .query(&[("name", host), ("type", &record_type.to_string())])
+            .header(ACCEPT, "application/dns-json")
+            .send()
+            .await?
+            .error_for_status()?;
+        parse_response(host, &response.bytes().await?, record_type)
+    }
+}
+
+#[async_trait]
+impl Resolver for DohResolver {
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>> {
+        let (v4, v6) = join(self.query(host, RECORD_A), self.query(host, RECORD_AAAA)).await;
+        let addrs = match (v4, v6) {
+            (Err(err), Err(_)) => return Err(err),
+            (v4, v6) => [v4.unwrap_or_default(), v6.unwrap_or_default()].concat(),
+        };
+        if addrs.is_empty() {
+            return Err(Error::UnresolvableHost(host.to_owned()));
+        }
+        Ok(addrs)
+    }
+}
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u16,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+/// Extracts the addresses of the given record type, skipping e.g. the CNAME
+/// records that lead to them.
+fn parse_response(host: &str, body: &[u8], record_type: u16) -> Result<Vec<IpAddr>> {
+    let response: DohResponse = serde_json::from_slice(body)?;
+    // any status but NOERROR, e.g. NXDOMAIN
+    if response.status != 0 {
+        return Err(Error::UnresolvableHost(host.to_owned()));
+    }
+    Ok(response
+        .answer
+        .iter()
+        .filter(|answer| answer.record_type == record_type)
+        .filter_map(|answer| answer.data.parse().ok())
+        .collect())
+}
+
+/// The resolver of the transports, which also serves the `reqwest` clients of
+/// the polling transports.
+#[derive(Clone)]
+pub(crate) struct SharedResolver {
+    resolver: Arc<dyn Resolver>,
+    /// Runs the lookups of the blocking transports, created with the first of
+    /// them. It lives as long as the resolver, so that the connections a
+    /// resolver like [`DohResolver`] keeps open stay usable between lookups.
+    runtime: Arc<BlockingRuntime>,
+}
+
+impl SharedResolver {
+    pub(crate) fn new<R: Resolver + 'static>(resolver: R) -> Self {
+        SharedResolver {
+            resolver: Arc::new(resolver),
+            runtime: Arc::default(),
+        }
+    }
+
+    /// Returns the addresses of `host` with the given `port`. IP addresses are
+    /// returned as they are.
+    pub(crate) async fn lookup(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        Self::resolve_addrs(&*self.resolver, host, port).await
+    }
+
+    /// Like [`SharedResolver::lookup`], for the blocking transports. The lookup
+    /// runs on the runtime of the resolver, so that it can also be called from
+    /// within an async context.
+    pub(crate) fn lookup_blocking(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let handle = self.runtime.handle()?;
+        let (tx, rx) = std::sync::mpsc::channel();
+        let resolver = self.resolver.clone();
+        let name = host.to_owned();
+        handle.spawn(async move {
+            let _ = tx.send(Self::resolve_addrs(&*resolver, &name, port).await);
+        });
+        rx.recv()
+            .unwrap_or_else(|_| Err(Error::UnresolvableHost(host.to_owned())))
+    }
+
+    /// Returns the addresses of `host` resolved by `resolver` with `port`.
+    async fn resolve_addrs(
+        resolver: &dyn Resolver,
+        host: &str,
+        port: u16,
+    ) -> Result<Vec<SocketAddr>> {
+        let ip = host.trim_start_matches('[').trim_end_matches(']');
+        let addrs = match ip.parse() {
+            Ok(ip) => vec![ip],
+            Err(_) => resolver.resolve(host).await?,
+        };
+        Ok(addrs
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect())
+    }
+}
+
+/// The runtime of the blocking lookups. It's shut down without waiting for
+/// its tasks, as the resolver may be dropped within an async context.
+#[derive(Default)]
+struct BlockingRuntime(Mutex<Option<Runtime>>);
+
+impl BlockingRuntime {
+    /// The handle of the runtime, which is created on the first call.
+    fn handle(&self) -> Result<Handle> {
+        let mut runtime = self.0.lock()?;
+        if let Some(runtime) = runtime.as_ref() {
+            return Ok(runtime.handle().clone());
+        }
+        let created = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("rust-engineio-resolver")
+            .enable_all()
+            .build()?;
+        Ok(runtime.insert(created).handle().clone())
+    }
+}
+
+impl Drop for BlockingRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.get_mut().ok().and_then(Option::take) {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Resolve for SharedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.resolver.clone();
+        Box::pin(async move {
+            let addrs = resolver.resolve(name.as_str()).await?;
+            // the port is replaced by the one of the request
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+impl Debug for SharedResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Resolver")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_response() -> Result<()> {
+        let body = br#"{"Status":0,"Answer":[
+            {"name":"example.com","type":5,"TTL":60,"data":"cdn.example.com."},
+            {"name":"cdn.example.com","type":1,"TTL":60,"data":"93.184.215.14"}
+        ]}"#;
+        assert_eq!(
+            parse_response("example.com", body, RECORD_A)?,
+            vec![IpAddr::V4(Ipv4Addr::new(93, 184, 215, 14))]
+        );
+        assert!(parse_response("example.com", body, RECORD_AAAA)?.is_empty());
+
+        let body = br#"{"Status":3}"#;
+        assert!(matches!(
+            parse_response("example.invalid", body, RECORD_A),
+            Err(Error::UnresolvableHost(_))
+        ));
+        Ok(())
+    }
+
+    struct Localhost;
+
+    #[async_trait]
+    impl Resolver for Localhost {
+        async fn resolve(&self, _host: &str) -> Result<Vec<IpAddr>> {
+            Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookup() -> Result<()> {
+        let resolver = SharedResolver::new(Localhost);
+        assert_eq!(
+            resolver.lookup("engineio.test", 4201).await?,
+            vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 4201))]
+        );
+        assert_eq!(
+            resolver.lookup("[::1]", 4201).await?,
+            vec![SocketAddr::from((Ipv6Addr::LOCALHOST, 4201))]
+        );
+        Ok(())
+    }
+
+    fn unresolvable_url() -> Result<Url> {
+        let mut url = crate::test::engine_io_server()?;
+        url.set_host(Some("engineio.test"))?;
+        Ok(url)
+    }
+
+    #[tokio::test]
+    async fn test_resolver_async() -> Result<()> {
+        let url = unresolvable_url()?;
+
+        let client = crate::asynchronous::ClientBuilder::new(url.clone())
+            .resolver(Localhost)
+            .build_polling()
+            .await?;
+        client.connect().await?;
+        client.disconnect().await?;
+
+        #[cfg(feature = "websocket")]
+        {
+            let client = crate::asynchronous::ClientBuilder::new(url)
+                .resolver(Localhost)
+                .build_websocket()
+                .await?;
+            client.connect().await?;
+            client.disconnect().await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lookup_blocking() -> Result<()> {
+        // a sync client may be used from within an async context
+        let resolver = SharedResolver::new(Localhost);
+        for _ in 0..2 {
+            assert_eq!(
+                resolver.lookup_blocking("engineio.test", 4201)?,
+                vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 4201))]
+            );
+        }
+        drop(resolver);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolver_blocking() -> Result<()> {
+        let client = crate::ClientBuilder::new(unresolvable_url()?)
+            .resolver(Localhost)
+            .build_polling()?;
+        client.connect()?;
+        client.disconnect()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use bytes::{Buf, Bytes, BytesMut};
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode, Uri};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{Role, WebSocketConfig},
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{error::Result, Error};
+
+/// The limit of the size of a request line along with its headers.
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// The limit of the number of headers of a request.
+const MAX_HEADERS: usize = 64;
+
+/// A HTTP/1.1 connection of a client, which serves just what the transports
+/// need: requests whose body has a `Content-Length`, and the websocket
+/// handshake.
+pub(super) struct Connection {
+    stream: TcpStream,
+    buffer: BytesMut,
+}
+
+impl Connection {
+    pub(super) fn new(stream: TcpStream) -> Self {
+        Connection {
+            stream,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Reads the next request, including a body of at most `max_body` bytes.
+    /// Returns `None` once the client closed the connection.
+    pub(super) async fn next_request(&mut self, max_body: usize) -> Result<Option<Request<Bytes>>> {
+        let (request, head_len, content_length) = loop {
+            if let Some(head) = parse_head(&self.buffer)? {
+                break head;
+            }
+            if self.buffer.len() > MAX_HEAD_SIZE {
+                return Err(Error::InvalidHttpRequest("head too large".to_owned()));
+            }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(Error::InvalidHttpRequest("incomplete head".to_owned()));
+            }
+        };
+        self.buffer.advance(head_len);
+
+        if content_length > max_body {
+            return Err(Error::PayloadTooLarge(max_body));
+        }
+        while self.buffer.len() < content_length {
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(Error::InvalidHttpRequest("incomplete body".to_owned()));
+            }
+        }
+        let body = self.buffer.split_to(content_length).freeze();
+        Ok(Some(request.map(|_| body)))
+    }
+
+    /// Sends a response with the given body.
+    pub(super) async fn respond(
+        &mut self,
+        status: StatusCode,
+        content_type: &str,
+        body: Bytes,
+    ) -> Result<()> {
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            status.as_str(),
+            status.canonical_reason().unwrap_or_default(),
+            content_type,
+            body.len()
+        );
+        self.stream.write_all(head.as_bytes()).await?;
+        self.stream.write_all(&body).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    /// Accepts the websocket handshake of `request`, see [`is_websocket`].
+    pub(super) async fn upgrade(
+        mut self,
+        request: &Request<Bytes>,
+        config: WebSocketConfig,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        // the client waits for the response before it sends frames
+        if !self.buffer.is_empty() {
+            return Err(Error::InvalidHttpRequest(
+                "data before the upgrade".to_owned(),
+            ));
+        }
+        let head = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(request)?
+        );
+        self.stream.write_all(head.as_bytes()).await?;
+        self.stream.flush().await?;
+
+        Ok(WebSocketStream::from_raw_socket(
+            MaybeTlsStream::Plain(self.stream),
+            Role::Server,
+            Some(config),
+        )
+        .await)
+    }
+}
+
+/// Whether `request` asks to open a websocket connection.
+pub(super) fn is_websocket(request: &Request<Bytes>) -> bool {
+    let header_is = |name: HeaderName, value: &str| {
+        request.headers().get_all(name).iter().any(|header| {
+            header
+                .to_str()
+                .unwrap_or_default()
+                .split(',')
+                .any(|token| token.trim().eq_ignore_ascii_case(value))
+        })
+    };
+    request.method() == Method::GET
+        && header_is(header::UPGRADE, "websocket")
+        && header_is(header::SEC_WEBSOCKET_VERSION, "13")
+        && request.headers().contains_key(header::SEC_WEBSOCKET_KEY)
+}
+
+/// The `Sec-WebSocket-Accept` header of the response to the websocket
+/// handshake of `request`.
+pub(super) fn accept_key<B>(request: &Request<B>) -> Result<String> {
+    let key = request
+        .headers()
+        .get(header::SEC_WEBSOCKET_KEY)
+        .ok_or_else(|| Error::InvalidHttpRequest("missing websocket key".to_owned()))?;
+    Ok(derive_accept_key(key.as_bytes()))
+}
+
+/// Whether the client wants the connection to be closed after the response.
+pub(super) fn wants_close(request: &Request<Bytes>) -> bool {
+    let close = request
+        .headers()
+        .get(header::CONNECTION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("close"));
+    close || request.version() < http::Version::HTTP_11
+}
+
+/// Parses the head of a request, returning it along with its length and the
+/// length of its body. Returns `None` if the head isn't complete yet.
+fn parse_head(buffer: &[u8]) -> Result<Option<(Request<()>, usize, usize)>> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Request::new(&mut headers);
+    let head_len = match parsed.parse(buffer) {
+        Ok(httparse::Status::Complete(len)) => len,
+        Ok(httparse::Status::Partial) => return Ok(None),
+        Err(err) => return Err(Error::InvalidHttpRequest(err.to_string())),
+    };
+
+    let mut header_map = HeaderMap::new();
+    for header in parsed.headers.iter() {
+        header_map.append(
+            HeaderName::from_bytes(header.name.as_bytes())
+                .map_err(|err| Error::InvalidHttpRequest(err.to_string()))?,
+            HeaderValue::from_bytes(header.value)
+                .map_err(|err| Error::InvalidHttpRequest(err.to_string()))?,
+        );
+    }
+    if header_map.contains_key(header::TRANSFER_ENCODING) {
+        return Err(Error::InvalidHttpRequest(
+            "chunked bodies are not supported".to_owned(),
+        ));
+    }
+    let content_length = match header_map.get(header::CONTENT_LENGTH) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| Error::InvalidHttpRequest("invalid content length".to_owned()))?,
+        None => 0,
+    };
+
+    let version = match parsed.version {
+        Some(0) => http::Version::HTTP_10,
+        _ => http::Version::HTTP_11,
+    };
+    let mut request = Request::builder()
+        .method(parsed.method.unwrap_or_default())
+        .uri(
+            parsed
+                .path
+                .unwrap_or("/")
+                .parse::<Uri>()
+                .map_err(http::Error::from)?,
+        )
+        .version(version)
+        .body(())?;
+    *request.headers_mut() = header_map;
+    Ok(Some((request, head_len, content_length)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_head() -> Result<()> {
+        let data = b"POST /engine.io/?EIO=4&transport=polling HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\n4test";
+        assert!(parse_head(&data[..20])?.is_none());
+
+        let (request, head_len, content_length) = parse_head(data)?.unwrap();
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.uri().query(), Some("EIO=4&transport=polling"));
+        assert_eq!(request.headers()[header::HOST], "localhost");
+        assert_eq!(&data[head_len..], b"4test");
+        assert_eq!(content_length, 5);
+        assert!(!wants_close(&request.map(|_| Bytes::new())));
+
+        assert!(parse_head(b"GET / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::{collections::VecDeque, time::Duration};
+
+use futures_util::future::{self, Either};
+use tokio::{sync::mpsc, time::Instant};
+
+use super::{ServerBuilder, ServerSocket};
+use crate::{Packet, PacketId};
+
+/// Pings the sessions of a server from a single task, instead of one timer
+/// per session, and closes the ones whose client doesn't answer in time.
+///
+/// All sessions of a server share the ping interval and timeout, so their
+/// deadlines are due in the order they're scheduled: the pending pings and
+/// the pending checks for pongs are plain queues, sorted by their deadline
+/// without any further bookkeeping.
+#[derive(Debug)]
+pub(super) struct Heartbeat {
+    sessions: mpsc::UnboundedSender<ServerSocket>,
+}
+
+impl Heartbeat {
+    /// Starts the task that pings the sessions. It ends once the heartbeat is
+    /// dropped and the last scheduled session is closed.
+    pub(super) fn start(options: &ServerBuilder) -> Self {
+        let (sessions, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(receiver, options.ping_interval, options.ping_timeout));
+        Heartbeat { sessions }
+    }
+
+    /// Pings `socket` in the ping interval from now on.
+    pub(super) fn schedule(&self, socket: ServerSocket) {
+        // fails only if the task panicked
+        let _ = self.sessions.send(socket);
+    }
+}
+
+async fn run(
+    mut sessions: mpsc::UnboundedReceiver<ServerSocket>,
+    interval: Duration,
+    timeout: Duration,
+) {
+    let mut pings: VecDeque<(Instant, ServerSocket)> = VecDeque::new();
+    // the number of the ping that has to be answered by the deadline
+    let mut checks: VecDeque<(Instant, (u64, ServerSocket))> = VecDeque::new();
+    let mut open = true;
+    loop {
+        let now = Instant::now();
+        while let Some((ping, socket)) = pop_due(&mut checks, now) {
+            if !socket.answered(ping) {
+                socket.abort();
+            }
+        }
+        while let Some(socket) = pop_due(&mut pings, now) {
+            if socket.is_closed() {
+                continue;
+            }
+            pings.push_back((now + interval, socket.clone()));
+            // a client that didn't answer the last ping yet is closed by its
+            // check instead
+            if let Some(ping) = socket.start_ping() {
+                checks.push_back((now + timeout, (ping, socket.clone())));
+                // sent on a task of its own, so that a client that doesn't
+                // read its pings can't delay the pings of the others
+                tokio::spawn(async move {
+                    let ping = socket.emit(Packet::new(PacketId::Ping, ""));
+                    if !matches!(tokio::time::timeout(timeout, ping).await, Ok(Ok(()))) {
+                        socket.abort();
+                    }
+                });
+            }
+        }
+
+        let next = [
+            pings.front().map(|(deadline, _)| *deadline),
+            checks.front().map(|(deadline, _)| *deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let sleep = match next {
+            Some(deadline) => Either::Left(tokio::time::sleep_until(deadline)),
+            None if !open => break,
+            None => Either::Right(future::pending()),
+        };
+        if !open {
+            sleep.await;
+            continue;
+        }
+        let received = sessions.recv();
+        tokio::pin!(sleep, received);
+        if let Either::Right((session, _)) = future::select(sleep, received).await {
+            match session {
+                Some(socket) => pings.push_back((Instant::now() + interval, socket)),
+                None => open = false,
+            }
+        }
+    }
+}
+
+/// Removes the first entry of `queue` if its deadline is due at `now`.
+fn pop_due<T>(queue: &mut VecDeque<(Instant, T)>, now: Instant) -> Option<T> {
+    match queue.front() {
+        Some((deadline, _)) if *deadline <= now => queue.pop_front().map(|(_, entry)| entry),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use http::Request;
+
+    use super::*;
+    use crate::error::Result;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat() -> Result<()> {
+        let options = Arc::new(
+            ServerBuilder::new().ping(Duration::from_millis(100), Duration::from_millis(50)),
+        );
+        let heartbeat = Heartbeat::start(&options);
+        let request = Request::get("/").body(()).unwrap();
+        let sockets: Vec<_> = (0..3)
+            .map(|_| ServerSocket::new(&request, options.clone()))
+            .collect();
+        for socket in &sockets {
+            heartbeat.schedule(socket.clone());
+        }
+
+        // the last client never answers
+        let ping = Bytes::from(Packet::new(PacketId::Ping, ""));
+        for _ in 0..3 {
+            for socket in &sockets[..2] {
+                assert_eq!(socket.poll().await?, ping);
+                socket.post(Bytes::from(Packet::new(PacketId::Pong, "")))?;
+            }
+        }
+        assert!(sockets[..2].iter().all(|socket| !socket.is_closed()));
+        assert!(sockets[2].is_closed());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stalled_client() -> Result<()> {
+        let options = Arc::new(
+            ServerBuilder::new().ping(Duration::from_millis(100), Duration::from_millis(50)),
+        );
+        let heartbeat = Heartbeat::start(&options);
+        let request = Request::get("/").body(()).unwrap();
+        let stalled = ServerSocket::new(&request, options.clone());
+        let socket = ServerSocket::new(&request, options.clone());
+
+        let start = Instant::now();
+        let guard = stalled.stall().await;
+        heartbeat.schedule(stalled.clone());
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        heartbeat.schedule(socket.clone());
+
+        // the ping of the stalled client doesn't delay the other one
+        let ping = Bytes::from(Packet::new(PacketId::Ping, ""));
+        assert_eq!(socket.poll().await?, ping);
+        assert!(start.elapsed() < Duration::from_millis(130));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(stalled.is_closed());
+        drop(guard);
+        Ok(())
+    }
+}
@@ -0,0 +1,780 @@
+//! An `engine.io` server, the counterpart of the clients of this crate and of
+//! the JavaScript client.
+//!
+//! The server speaks revision 4 of the protocol. Clients open a session with
+//! the HTTP long-polling transport and upgrade it to websocket, or connect via
+//! websocket directly. The server sends the heartbeat pings and closes the
+//! sessions whose client doesn't answer them in time.
+//!
+//! The HTTP part covers what the transports need, i.e. plain HTTP/1.1 with
+//! keep-alive and bodies with a `Content-Length`. Every path is served, TLS
+//! and CORS headers are left to a reverse proxy. Alternatively, the server is
+//! mounted into an existing `axum` or `hyper` application as a `tower`
+//! service or layer, see [`Server::service`] and [`Server::layer`] (behind the
+//! `hyper` feature flag).
+//!
+//! # Example
+//! ```rust
+//! use futures_util::StreamExt;
+//! use rust_engineio::{server::ServerBuilder, Packet, PacketId};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let server = ServerBuilder::new()
+//!         .bind("127.0.0.1:0")
+//!         .await
+//!         .expect("address in use");
+//!     println!("listening on {}", server.local_addr().unwrap());
+//!
+//!     tokio::spawn(async move {
+//!         while let Some(mut socket) = server.accept().await {
+//!             tokio::spawn(async move {
+//!                 // echoes the messages of the client
+//!                 while let Some(Ok(packet)) = socket.next().await {
+//!                     let _ = socket.emit(packet).await;
+//!                 }
+//!             });
+//!         }
+//!     });
+//! }
+//! ```
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_util::{future, StreamExt};
+use http::{Method, Request, StatusCode};
+use serde_json::json;
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+
+use crate::{
+    asynchronous::async_transports::AsyncWebsocketGeneralTransport, error::Result,
+    ENGINE_IO_VERSION,
+};
+use heartbeat::Heartbeat;
+
+mod connection;
+mod heartbeat;
+#[cfg(feature = "hyper")]
+mod service;
+mod socket;
+
+#[cfg(feature = "hyper")]
+pub use service::{EngineLayer, EngineRouter, EngineService};
+pub use socket::ServerSocket;
+
+/// The default limit of the size of a received packet, like the one of the
+/// JavaScript server.
+const DEFAULT_MAX_PAYLOAD: usize = 1_000_000;
+
+/// Configures and starts a [`Server`].
+#[derive(Debug, Clone)]
+pub struct ServerBuilder {
+    ping_interval: Duration,
+    ping_timeout: Duration,
+    max_payload: usize,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        ServerBuilder {
+            ping_interval: Duration::from_secs(25),
+            ping_timeout: Duration::from_secs(20),
+            max_payload: DEFAULT_MAX_PAYLOAD,
+        }
+    }
+}
+
+impl ServerBuilder {
+    /// Creates a builder with the defaults of the JavaScript server: a ping
+    /// every 25 seconds that must be answered within 20 seconds and packets of
+    /// at most 1 MB.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the interval of the heartbeat pings and the time the client has to
+    /// answer them before its session is closed.
+    pub fn ping(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.ping_interval = interval;
+        self.ping_timeout = timeout;
+        self
+    }
+
+    /// Limits the size of the packets a client may send, larger ones close the
+    /// connection. Via polling, the limit applies to the body of a request.
+    pub fn max_payload(mut self, bytes: usize) -> Self {
+        self.max_payload = bytes;
+        self
+    }
+
+    /// Listens for clients on `address`. The connections are served in the
+    /// background, the sessions are handed out by [`Server::accept`].
+    pub async fn bind<A: ToSocketAddrs>(self, address: A) -> Result<Server> {
+        let listener = TcpListener::bind(address).await?;
+        let mut server = self.build();
+        server.local_addr = Some(listener.local_addr()?);
+        let shared = server.shared.clone();
+        server.acceptor = Some(tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, shared.clone()));
+            }
+        }));
+        Ok(server)
+    }
+
+    /// Creates a server without a listener, whose clients connect through the
+    /// HTTP server of the application via [`Server::service`] instead.
+    pub fn build(self) -> Server {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Server {
+            local_addr: None,
+            shared: Arc::new(Shared {
+                options: Arc::new(self),
+                sessions: Mutex::default(),
+                heartbeat: OnceLock::new(),
+                accepted: Mutex::new(Some(tx)),
+                closing: AtomicBool::new(false),
+            }),
+            sockets: tokio::sync::Mutex::new(rx),
+            acceptor: None,
+        }
+    }
+}
+
+/// An `engine.io` server listening for clients, see the
+/// [module documentation](self). Stops listening when it's dropped, sessions
+/// that were opened or upgraded via websocket stay open.
+pub struct Server {
+    local_addr: Option<SocketAddr>,
+    shared: Arc<Shared>,
+    sockets: tokio::sync::Mutex<mpsc::UnboundedReceiver<ServerSocket>>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// The address the server listens on, e.g. to find out the port if it was
+    /// bound to port 0. `None` if the server was built without a listener.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Waits for the next client that opened a session. Returns `None` once
+    /// the server can't accept connections anymore.
+    pub async fn accept(&self) -> Option<ServerSocket> {
+        self.sockets.lock().await.recv().await
+    }
+
+    /// Serves the clients as a `tower` service, e.g. to mount the server into
+    /// the router of an `axum` application. The sessions are handed out by
+    /// [`Server::accept`] like the ones of the listener.
+    #[cfg(feature = "hyper")]
+    pub fn service(&self) -> EngineService {
+        EngineService::new(self.shared.clone())
+    }
+
+    /// Mounts the server at `/engine.io` of an application as a `tower`
+    /// layer, which passes the other requests on to the application, see
+    /// [`EngineLayer`].
+    #[cfg(feature = "hyper")]
+    pub fn layer(&self) -> EngineLayer {
+        EngineLayer::new(self.service())
+    }
+
+    /// Stops listening and rejects new sessions, [`Server::accept`] returns
+    /// `None` once the sessions opened before are handed out. The open
+    /// sessions stay, see [`Server::shutdown`].
+    pub fn stop_accepting(&self) {
+        self.shared.closing.store(true, Ordering::Release);
+        if let Some(acceptor) = &self.acceptor {
+            acceptor.abort();
+        }
+        if let Ok(mut accepted) = self.shared.accepted.lock() {
+            accepted.take();
+        }
+    }
+
+    /// Shuts the server down gracefully: it stops listening, rejects new
+    /// sessions and ends [`Server::accept`], then sends a `Close` packet to
+    /// every open session. Resolves once all sessions are closed, or once
+    /// `grace_period` is over, which closes the remaining ones without
+    /// waiting for their clients.
+    ///
+    /// A session is closed once its client received the `Close` packet, so
+    /// the packets emitted before shutting down are delivered as well.
+    pub async fn shutdown(&self, grace_period: Duration) {
+        self.stop_accepting();
+        let sessions: Vec<ServerSocket> = match self.shared.sessions.lock() {
+            Ok(sessions) => sessions.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        let draining = future::join_all(sessions.iter().map(ServerSocket::drain));
+        if tokio::time::timeout(grace_period, draining).await.is_err() {
+            for session in &sessions {
+                session.abort();
+            }
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(acceptor) = &self.acceptor {
+            acceptor.abort();
+        }
+    }
+}
+
+impl Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("local_addr", &self.local_addr)
+            .finish()
+    }
+}
+
+/// The state shared by the connections of a server.
+struct Shared {
+    options: Arc<ServerBuilder>,
+    /// The open sessions by their id, which the requests of the polling
+    /// transport and upgrades refer to.
+    sessions: Mutex<HashMap<String, ServerSocket>>,
+    /// Pings the sessions, started along with the first one.
+    heartbeat: OnceLock<Heartbeat>,
+    /// Hands out the new sessions, dropped once the server shuts down.
+    accepted: Mutex<Option<mpsc::UnboundedSender<ServerSocket>>>,
+    /// Set once the server shuts down, new sessions are rejected from then.
+    closing: AtomicBool,
+}
+
+impl Shared {
+    /// Hands out a new session, starts its heartbeat and keeps it until it's
+    /// closed.
+    fn register(self: &Arc<Self>, socket: ServerSocket) {
+        let accepted = match self.accepted.lock() {
+            Ok(accepted) => accepted.clone(),
+            Err(_) => None,
+        };
+        let Some(accepted) = accepted else {
+            // the server shut down while the session was opened
+            socket.abort();
+            return;
+        };
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(socket.sid().to_owned(), socket.clone());
+        }
+        self.heartbeat
+            .get_or_init(|| Heartbeat::start(&self.options))
+            .schedule(socket.clone());
+        let shared = self.clone();
+        let closed = socket.clone();
+        tokio::spawn(async move {
+            closed.closed().await;
+            if let Ok(mut sessions) = shared.sessions.lock() {
+                sessions.remove(closed.sid());
+            }
+        });
+        let _ = accepted.send(socket);
+    }
+
+    fn session(&self, sid: &str) -> Option<ServerSocket> {
+        self.sessions.lock().ok()?.get(sid).cloned()
+    }
+
+    fn websocket_config(&self) -> WebSocketConfig {
+        WebSocketConfig {
+            max_message_size: Some(self.options.max_payload),
+            max_frame_size: Some(self.options.max_payload),
+            ..Default::default()
+        }
+    }
+}
+
+/// The reasons to reject a request, with the codes of the JavaScript server.
+#[derive(Debug, Clone, Copy)]
+enum Rejection {
+    UnknownTransport = 0,
+    UnknownSid = 1,
+    BadHandshakeMethod = 2,
+    BadRequest = 3,
+    Forbidden = 4,
+    UnsupportedProtocolVersion = 5,
+}
+
+impl Rejection {
+    fn body(self) -> Bytes {
+        let message = match self {
+            Rejection::UnknownTransport => "Transport unknown",
+            Rejection::UnknownSid => "Session ID unknown",
+            Rejection::BadHandshakeMethod => "Bad handshake method",
+            Rejection::BadRequest => "Bad request",
+            Rejection::Forbidden => "Forbidden",
+            Rejection::UnsupportedProtocolVersion => "Unsupported protocol version",
+        };
+        Bytes::from(json!({ "code": self as u8, "message": message }).to_string())
+    }
+}
+
+/// What the server does with a request.
+enum Route {
+    Polling(Polling),
+    /// Opens a session via websocket, or upgrades the given one.
+    Websocket(Option<ServerSocket>),
+}
+
+/// The requests of the polling transport.
+enum Polling {
+    Open,
+    Poll(ServerSocket),
+    Post(ServerSocket),
+}
+
+/// Serves the requests of a connection until it's closed or upgraded to
+/// websocket.
+async fn serve_connection(stream: TcpStream, shared: Arc<Shared>) -> Result<()> {
+    let mut connection = connection::Connection::new(stream);
+    while let Some(request) = connection.next_request(shared.options.max_payload).await? {
+        let polling = match route(&request, &shared) {
+            Ok(Route::Polling(polling)) => polling,
+            Ok(Route::Websocket(upgrade)) => {
+                let websocket = connection
+                    .upgrade(&request, shared.websocket_config())
+                    .await?;
+                let (sender, receiver) = websocket.split();
+                let transport = AsyncWebsocketGeneralTransport::new(sender, receiver).await;
+                return open_websocket(upgrade, transport, &request, &shared).await;
+            }
+            Err(rejection) => {
+                connection
+                    .respond(
+                        StatusCode::BAD_REQUEST,
+                        "application/json",
+                        rejection.body(),
+                    )
+                    .await?;
+                continue;
+            }
+        };
+
+        match poll(polling, &request, &shared).await {
+            Ok(body) => {
+                connection
+                    .respond(StatusCode::OK, "text/plain; charset=UTF-8", body)
+                    .await?
+            }
+            Err(_) => {
+                let body = Rejection::BadRequest.body();
+                connection
+                    .respond(StatusCode::BAD_REQUEST, "application/json", body)
+                    .await?
+            }
+        }
+        if connection::wants_close(&request) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Answers a request of the polling transport, returning the body of the
+/// response.
+async fn poll(polling: Polling, request: &Request<Bytes>, shared: &Arc<Shared>) -> Result<Bytes> {
+    match polling {
+        Polling::Open => {
+            let socket = ServerSocket::new(request, shared.options.clone());
+            let handshake = Bytes::from(socket.handshake(&["websocket"])?);
+            shared.register(socket);
+            Ok(handshake)
+        }
+        Polling::Poll(socket) => socket.poll().await,
+        Polling::Post(socket) => socket
+            .post(request.body().clone())
+            .map(|_| Bytes::from("ok")),
+    }
+}
+
+/// Opens a session over an accepted websocket, or upgrades the session of
+/// `upgrade` to it.
+async fn open_websocket(
+    upgrade: Option<ServerSocket>,
+    transport: AsyncWebsocketGeneralTransport,
+    request: &Request<Bytes>,
+    shared: &Arc<Shared>,
+) -> Result<()> {
+    match upgrade {
+        Some(socket) => socket.upgrade(transport).await,
+        None => {
+            let socket = ServerSocket::new(request, shared.options.clone());
+            socket.open_websocket(transport).await?;
+            shared.register(socket);
+            Ok(())
+        }
+    }
+}
+
+/// Decides how to handle a request by its query.
+fn route(request: &Request<Bytes>, shared: &Shared) -> std::result::Result<Route, Rejection> {
+    let query = |key: &str| {
+        request.uri().query().and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.into_owned())
+        })
+    };
+    if query("EIO") != Some(ENGINE_IO_VERSION.to_string()) {
+        return Err(Rejection::UnsupportedProtocolVersion);
+    }
+    let session = match query("sid") {
+        Some(sid) => Some(shared.session(&sid).ok_or(Rejection::UnknownSid)?),
+        None if shared.closing.load(Ordering::Acquire) => return Err(Rejection::Forbidden),
+        None => None,
+    };
+
+    match (query("transport").as_deref(), session) {
+        (Some("websocket"), _) if !connection::is_websocket(request) => Err(Rejection::BadRequest),
+        (Some("websocket"), session) => Ok(Route::Websocket(session)),
+        (Some("polling"), None) if request.method() == Method::GET => {
+            Ok(Route::Polling(Polling::Open))
+        }
+        (Some("polling"), None) => Err(Rejection::BadHandshakeMethod),
+        (Some("polling"), Some(socket)) if request.method() == Method::GET => {
+            Ok(Route::Polling(Polling::Poll(socket)))
+        }
+        (Some("polling"), Some(socket)) if request.method() == Method::POST => {
+            Ok(Route::Polling(Polling::Post(socket)))
+        }
+        (Some("polling"), Some(_)) => Err(Rejection::BadRequest),
+        _ => Err(Rejection::UnknownTransport),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::FutureExt;
+
+    use crate::{
+        asynchronous::ClientBuilder, CloseReason, MemoryUpgradeStore, Packet, PacketId,
+        TransportKind, UpgradeStore,
+    };
+
+    #[tokio::test]
+    async fn test_echo() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+
+        let client = ClientBuilder::new(url.clone()).build_websocket().await?;
+        let mut socket = server.accept().await.unwrap();
+        assert!(socket.uri().path().starts_with("/engine.io/"));
+
+        client.connect().await?;
+        client.emit(Packet::new(PacketId::Message, "hello")).await?;
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hello"));
+        client
+            .emit(Packet::new(PacketId::MessageBinary, vec![1, 2, 3]))
+            .await?;
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::MessageBinary, vec![1, 2, 3]));
+
+        socket.emit(Packet::new(PacketId::Message, "hi")).await?;
+        let mut client_stream = client.clone();
+        let packet = client_stream.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hi"));
+
+        client.disconnect().await?;
+        assert!(socket.next().await.is_none());
+        assert!(socket.is_closed());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_polling() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+
+        let client = ClientBuilder::new(url).build_polling().await?;
+        let mut socket = server.accept().await.unwrap();
+        assert!(!socket.is_websocket().await);
+
+        client.connect().await?;
+        client.emit(Packet::new(PacketId::Message, "hello")).await?;
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hello"));
+        client
+            .emit(Packet::new(PacketId::MessageBinary, vec![1, 2, 3]))
+            .await?;
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::MessageBinary, vec![1, 2, 3]));
+
+        socket.emit(Packet::new(PacketId::Message, "hi")).await?;
+        socket
+            .emit(Packet::new(PacketId::MessageBinary, vec![4, 5]))
+            .await?;
+        let mut client_stream = client.clone();
+        let packet = client_stream.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hi"));
+        let packet = client_stream.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::MessageBinary, vec![4, 5]));
+
+        client.disconnect().await?;
+        assert!(socket.next().await.is_none());
+        assert!(socket.is_closed());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upgrade() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+
+        let client = ClientBuilder::new(url).build().await?;
+        let mut socket = server.accept().await.unwrap();
+
+        client.connect().await?;
+        client.emit(Packet::new(PacketId::Message, "hello")).await?;
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hello"));
+        // the message was sent after the upgrade
+        assert!(socket.is_websocket().await);
+        socket.emit(Packet::new(PacketId::Message, "hi")).await?;
+        let mut client_stream = client.clone();
+        let packet = client_stream.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hi"));
+
+        socket.close().await?;
+        assert!(socket.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remember_upgrade() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+        let store = MemoryUpgradeStore::new();
+        let origin = url.origin().ascii_serialization();
+
+        let client = ClientBuilder::new(url.clone())
+            .remember_upgrade(store.clone())
+            .build()
+            .await?;
+        client.connect().await?;
+        let _upgraded = server.accept().await.unwrap();
+        assert!(store.is_remembered(&origin));
+        client.disconnect().await?;
+
+        // handshakes over the websocket right away
+        let client = ClientBuilder::new(url)
+            .remember_upgrade(store.clone())
+            .build()
+            .await?;
+        client.connect().await?;
+        let socket = server.accept().await.unwrap();
+        assert!(socket.is_websocket().await);
+        assert!(store.is_remembered(&origin));
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejections() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let base = format!("http://{}/engine.io/", server.local_addr().unwrap());
+        let http = reqwest::Client::new();
+
+        let reject = |query: &'static str, method: reqwest::Method| {
+            let request = http.request(method, format!("{base}?{query}")).send();
+            async move {
+                let response = request.await?;
+                assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+                let body: serde_json::Value = serde_json::from_slice(&response.bytes().await?)?;
+                Ok::<_, crate::Error>(body["code"].as_u64())
+            }
+        };
+        let get = reqwest::Method::GET;
+        assert_eq!(
+            reject("EIO=3&transport=polling", get.clone()).await?,
+            Some(5)
+        );
+        assert_eq!(
+            reject("EIO=4&transport=carrier-pigeon", get.clone()).await?,
+            Some(0)
+        );
+        assert_eq!(
+            reject("EIO=4&transport=polling&sid=x", get.clone()).await?,
+            Some(1)
+        );
+        assert_eq!(
+            reject("EIO=4&transport=polling", reqwest::Method::POST).await?,
+            Some(2)
+        );
+        assert_eq!(reject("EIO=4&transport=websocket", get).await?, Some(3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_info() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = ClientBuilder::new(url)
+            .on_data_with_info(move |data, info| {
+                let _ = tx.send((data, info));
+                Box::pin(async {})
+            })
+            .build_websocket()
+            .await?;
+        let socket = server.accept().await.unwrap();
+        client.connect().await?;
+        let mut client_stream = client.clone();
+        tokio::spawn(async move { while client_stream.next().await.is_some() {} });
+
+        socket.emit(Packet::new(PacketId::Message, "text")).await?;
+        socket
+            .emit(Packet::new(PacketId::MessageBinary, vec![1, 2]))
+            .await?;
+        let (data, info) = rx.recv().await.unwrap();
+        assert_eq!(data, "text");
+        assert!(!info.is_binary());
+        assert_eq!(info.transport, crate::TransportKind::Websocket);
+        let (data, info) = rx.recv().await.unwrap();
+        assert_eq!(data, vec![1, 2]);
+        assert!(info.is_binary());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_description() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+        let connect = |url: url::Url| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let builder = ClientBuilder::new(url).on_close_with_description(move |description| {
+                let _ = tx.send(description);
+                async {}.boxed()
+            });
+            (builder, rx)
+        };
+
+        let (builder, mut closed) = connect(url.clone());
+        let mut client = builder.build_websocket().await?;
+        client.connect().await?;
+        let socket = server.accept().await.unwrap();
+        socket.close().await?;
+        while client.next().await.is_some() {}
+        let description = closed.recv().await.unwrap();
+        assert_eq!(description.reason, CloseReason::ServerClose);
+        assert_eq!(description.transport, TransportKind::Websocket);
+        assert!(!description.is_abnormal());
+
+        let (builder, mut closed) = connect(url);
+        let client = builder.build_polling().await?;
+        client.connect().await?;
+        client.disconnect().await?;
+        let description = closed.recv().await.unwrap();
+        assert_eq!(description.reason, CloseReason::ForcedClose);
+        assert_eq!(description.transport, TransportKind::Polling);
+        // reported once
+        client.abort();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(closed.try_recv().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() -> Result<()> {
+        let server = ServerBuilder::new().bind("127.0.0.1:0").await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+
+        let mut received = Vec::new();
+        for client in [
+            ClientBuilder::new(url.clone()).build_polling().await?,
+            ClientBuilder::new(url.clone()).build_websocket().await?,
+        ] {
+            client.connect().await?;
+            let socket = server.accept().await.unwrap();
+            socket.emit(Packet::new(PacketId::Message, "bye")).await?;
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let mut client_stream = client.clone();
+            tokio::spawn(async move {
+                let mut packets = Vec::new();
+                while let Some(Ok(packet)) = client_stream.next().await {
+                    packets.push(packet);
+                }
+                let _ = tx.send(packets);
+            });
+            received.push((socket, rx));
+        }
+
+        server.shutdown(Duration::from_secs(5)).await;
+        assert!(server.accept().await.is_none());
+        for (socket, rx) in received {
+            assert!(socket.is_closed());
+            // the packets emitted before are delivered along with the close
+            let packets = rx.await.unwrap();
+            assert!(packets.contains(&Packet::new(PacketId::Message, "bye")));
+        }
+        assert!(server.shared.sessions.lock().unwrap().is_empty());
+
+        // new sessions are rejected
+        let client = ClientBuilder::new(url).build_polling().await;
+        assert!(client.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ping_timeout() -> Result<()> {
+        let server = ServerBuilder::new()
+            .ping(Duration::from_millis(100), Duration::from_millis(100))
+            .bind("127.0.0.1:0")
+            .await?;
+        let url = url::Url::parse(&format!(
+            "http://{}/engine.io/",
+            server.local_addr().unwrap()
+        ))?;
+
+        // the client connects but never reads the pings, so it never answers
+        let client = ClientBuilder::new(url).build_websocket().await?;
+        let socket = server.accept().await.unwrap();
+        assert!(!socket.is_closed());
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(socket.is_closed());
+        drop(client);
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    convert::Infallible,
+    fmt::Debug,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::{future::BoxFuture, StreamExt};
+use http::{header, HeaderValue, Request, Response, StatusCode};
+use http_body::Body;
+use http_body_util::{BodyExt, Either, Full, Limited};
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::TokioIo;
+use tokio_tungstenite::{tungstenite::protocol::Role, WebSocketStream};
+use tower::{Layer, Service};
+
+use super::{connection, open_websocket, poll, route, Rejection, Route, Shared};
+use crate::asynchronous::async_transports::AsyncWebsocketGeneralTransport;
+
+/// Serves the clients of a [`super::Server`] as a `tower` service, see
+/// [`super::Server::service`]. Every path is served, so the service is meant
+/// to be nested under the path of the clients, e.g. `/engine.io/`.
+///
+/// Websockets are accepted via the connection upgrades of `hyper`, which
+/// `axum` passes on to its services as well. Behind other HTTP servers,
+/// clients are refused a websocket and stay on polling.
+///
+/// # Example
+/// ```rust,ignore
+/// use rust_engineio::server::ServerBuilder;
+///
+/// let server = ServerBuilder::new().build();
+/// let app = axum::Router::new().nest_service("/engine.io", server.service());
+/// ```
+#[derive(Clone)]
+pub struct EngineService {
+    shared: Arc<Shared>,
+}
+
+impl EngineService {
+    pub(super) fn new(shared: Arc<Shared>) -> Self {
+        EngineService { shared }
+    }
+}
+
+impl<B> Service<Request<B>> for EngineService
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let shared = self.shared.clone();
+        Box::pin(async move { Ok(serve_request(request, shared).await) })
+    }
+}
+
+impl Debug for EngineService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EngineService").finish()
+    }
+}
+
+/// Mounts a [`super::Server`] at a path of an application as a `tower` layer,
+/// see [`super::Server::layer`]. Requests to the path, e.g.
+/// `/engine.io/?EIO=4&transport=polling`, are served by the [`EngineService`],
+/// all others are passed on to the wrapped service.
+///
+/// # Example
+/// ```rust,ignore
+/// use rust_engineio::server::ServerBuilder;
+///
+/// let server = ServerBuilder::new().build();
+/// let app = axum::Router::new()
+///     .route("/", axum::routing::get(|| async { "hello" }))
+///     .layer(server.layer());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EngineLayer {
+    service: EngineService,
+    path: String,
+}
+
+impl EngineLayer {
+    pub(super) fn new(service: EngineService) -> Self {
+        EngineLayer {
+            service,
+            path: "/engine.io".to_owned(),
+        }
+    }
+
+    /// Sets the path the clients connect to, `/engine.io` by default like the
+    /// JavaScript server.
+    pub fn path<P: Into<String>>(mut self, path: P) -> Self {
+        let path = path.into();
+        self.path = format!("/{}", path.trim_matches('/'));
+        self
+    }
+}
+
+impl<S> Layer<S> for EngineLayer {
+    type Service = EngineRouter<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EngineRouter {
+            engine: self.service.clone(),
+            path: self.path.clone(),
+            inner,
+        }
+    }
+}
+
+/// Routes the requests to the path of an [`EngineLayer`] to the
+/// [`EngineService`], and all others to the wrapped service.
+#[derive(Debug, Clone)]
+pub struct EngineRouter<S> {
+    engine: EngineService,
+    path: String,
+    inner: S,
+}
+
+impl<S> EngineRouter<S> {
+    fn is_engine_path(&self, path: &str) -> bool {
+        match path.strip_prefix(self.path.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || self.path == "/",
+            None => false,
+        }
+    }
+}
+
+impl<S, B, ResBody> Service<Request<B>> for EngineRouter<S>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = Response<Either<Full<Bytes>, ResBody>>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if self.is_engine_path(request.uri().path()) {
+            let response = self.engine.call(request);
+            return Box::pin(async move {
+                match response.await {
+                    Ok(response) => Ok(response.map(Either::Left)),
+                    Err(never) => match never {},
+                }
+            });
+        }
+        let response = self.inner.call(request);
+        Box::pin(async move { Ok(response.await?.map(Either::Right)) })
+    }
+}
+
+async fn serve_request<B>(mut request: Request<B>, shared: Arc<Shared>) -> Response<Full<Bytes>>
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let on_upgrade = request.extensions_mut().remove::<OnUpgrade>();
+    let (parts, body) = request.into_parts();
+    let body = match Limited::new(body, shared.options.max_payload)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return rejected(Rejection::BadRequest),
+    };
+    let request = Request::from_parts(parts, body);
+
+    let upgrade = match route(&request, &shared) {
+        Ok(Route::Polling(polling)) => {
+            return match poll(polling, &request, &shared).await {
+                Ok(body) => respond(StatusCode::OK, "text/plain; charset=UTF-8", body),
+                Err(_) => rejected(Rejection::BadRequest),
+            };
+        }
+        Ok(Route::Websocket(upgrade)) => upgrade,
+        Err(rejection) => return rejected(rejection),
+    };
+    let (Some(on_upgrade), Ok(accept_key)) = (on_upgrade, connection::accept_key(&request)) else {
+        return rejected(Rejection::BadRequest);
+    };
+    let Ok(accept_key) = HeaderValue::from_str(&accept_key) else {
+        return rejected(Rejection::BadRequest);
+    };
+
+    // the connection is upgraded once the response is sent
+    tokio::spawn(async move {
+        let Ok(upgraded) = on_upgrade.await else {
+            return;
+        };
+        let websocket = WebSocketStream::from_raw_socket(
+            TokioIo::new(upgraded),
+            Role::Server,
+            Some(shared.websocket_config()),
+        )
+        .await;
+        let (sender, receiver) = websocket.split();
+        let transport = AsyncWebsocketGeneralTransport::new(sender, receiver).await;
+        let _ = open_websocket(upgrade, transport, &request, &shared).await;
+    });
+    let mut response = Response::new(Full::default());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept_key);
+    response
+}
+
+fn respond(status: StatusCode, content_type: &'static str, body: Bytes) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+fn rejected(rejection: Rejection) -> Response<Full<Bytes>> {
+    respond(
+        StatusCode::BAD_REQUEST,
+        "application/json",
+        rejection.body(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{server::ServerBuilder, Packet, PacketId};
+
+    async fn request(
+        service: &EngineService,
+        request: http::request::Builder,
+        body: &'static str,
+    ) -> (StatusCode, String) {
+        let request = request.body(Full::new(Bytes::from(body))).unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_service() -> crate::error::Result<()> {
+        let server = ServerBuilder::new().build();
+        assert_eq!(server.local_addr(), None);
+        let service = server.service();
+
+        let (status, handshake) =
+            request(&service, Request::get("/?EIO=4&transport=polling"), "").await;
+        assert_eq!(status, StatusCode::OK);
+        let mut socket = server.accept().await.unwrap();
+        assert!(handshake.contains(socket.sid()));
+
+        let path = format!("/?EIO=4&transport=polling&sid={}", socket.sid());
+        let (status, body) = request(&service, Request::post(&path), "4hello").await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "ok"));
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hello"));
+
+        socket.emit(Packet::new(PacketId::Message, "hi")).await?;
+        let (status, body) = request(&service, Request::get(&path), "").await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "4hi"));
+
+        // without the upgrades of hyper, the session stays on polling
+        let upgrade = Request::get(format!("/?EIO=4&transport=websocket&sid={}", socket.sid()))
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==");
+        let (status, _) = request(&service, upgrade, "").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = request(&service, Request::get("/?EIO=3"), "").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("Unsupported protocol version"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_poll() -> crate::error::Result<()> {
+        let server = ServerBuilder::new().build();
+        let service = server.service();
+        request(&service, Request::get("/?EIO=4&transport=polling"), "").await;
+        let socket = server.accept().await.unwrap();
+        let path = format!("/?EIO=4&transport=polling&sid={}", socket.sid());
+
+        // the request of a pending poll is dropped, e.g. as the client went away
+        let poll = request(&service, Request::get(&path), "");
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), poll)
+                .await
+                .is_err()
+        );
+
+        // the next poll isn't taken for an overlapping one
+        socket.emit(Packet::new(PacketId::Message, "hi")).await?;
+        let (status, body) = request(&service, Request::get(&path), "").await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "4hi"));
+        assert!(!socket.is_closed());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_layer() -> crate::error::Result<()> {
+        let server = ServerBuilder::new().build();
+        let app = tower::service_fn(|_: Request<Full<Bytes>>| async {
+            Ok::<_, Infallible>(Response::new(Full::new(Bytes::from("app"))))
+        });
+        let router = server.layer().path("/realtime/").layer(app);
+
+        let call = |path: &'static str| {
+            let request = Request::get(path).body(Full::default()).unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+        let handshake = call("/realtime/?EIO=4&transport=polling").await;
+        let socket = server.accept().await.unwrap();
+        assert!(handshake.contains(socket.sid()));
+        assert!(call("/realtime?EIO=3")
+            .await
+            .contains("Unsupported protocol version"));
+        assert_eq!(call("/").await, "app");
+        assert_eq!(call("/realtimes/?EIO=4").await, "app");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_websocket_upgrade() -> crate::error::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let server = ServerBuilder::new().build();
+        let service = server.service();
+
+        // hyper hands the upgraded connection out as `OnUpgrade` on both
+        // ends, so a client connection that a peer switches to a websocket
+        // stands in for the server of the application here
+        let (io, mut peer) = tokio::io::duplex(4096);
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(io))
+            .await
+            .unwrap();
+        tokio::spawn(connection.with_upgrades());
+        let peer = tokio::spawn(async move {
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(peer.read_u8().await.unwrap());
+            }
+            peer.write_all(
+                b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n",
+            )
+            .await
+            .unwrap();
+            WebSocketStream::from_raw_socket(peer, Role::Client, None).await
+        });
+        let upgrade = Request::get("/")
+            .header(header::CONNECTION, "Upgrade")
+            .header(header::UPGRADE, "websocket")
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let mut response = sender.send_request(upgrade).await.unwrap();
+        let on_upgrade = hyper::upgrade::on(&mut response);
+
+        let request = Request::get("/?EIO=4&transport=websocket")
+            .header(header::CONNECTION, "Upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .extension(on_upgrade)
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        let mut websocket = peer.await.unwrap();
+        let mut socket = server.accept().await.unwrap();
+        let Some(Ok(Message::Text(handshake))) = websocket.next().await else {
+            panic!("no handshake");
+        };
+        assert!(handshake.starts_with('0') && handshake.contains(socket.sid()));
+
+        futures_util::SinkExt::send(&mut websocket, Message::Text("4hello".to_owned())).await?;
+        let packet = socket.next().await.unwrap()?;
+        assert_eq!(packet, Packet::new(PacketId::Message, "hello"));
+
+        socket.emit(Packet::new(PacketId::Message, "hi")).await?;
+        let Some(Ok(Message::Text(message))) = websocket.next().await else {
+            panic!("no message");
+        };
+        assert_eq!(message, "4hi");
+        Ok(())
+    }
+}
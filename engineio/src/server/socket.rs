@@ -0,0 +1,454 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_util::{
+    future::{self, Either},
+    Stream,
+};
+use http::{HeaderMap, Request, Uri};
+use rand::{thread_rng, Rng};
+use tokio::sync::{mpsc, Notify};
+
+use super::ServerBuilder;
+use crate::{
+    asynchronous::async_transports::AsyncWebsocketGeneralTransport,
+    error::Result,
+    packet::{HandshakePacket, Payload},
+    Error, Packet, PacketId,
+};
+
+/// The time a client has to finish the upgrade to websocket once it sent the
+/// probe, like the `upgradeTimeout` of the JavaScript server.
+const UPGRADE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The session of a client. Received messages are read from the [`Stream`],
+/// which ends when the client closes the session or misses a ping. Clones
+/// share the session.
+#[derive(Clone)]
+pub struct ServerSocket {
+    inner: Arc<Session>,
+}
+
+struct Session {
+    sid: String,
+    uri: Uri,
+    headers: HeaderMap,
+    options: Arc<ServerBuilder>,
+    outgoing: tokio::sync::Mutex<Outgoing>,
+    /// Notified when packets are queued for the polling transport, or it has
+    /// to answer a poll for another reason.
+    writable: Notify,
+    /// Notified when the queued packets were handed to the client.
+    flushed: Notify,
+    /// Set while a poll is answered, clients must not poll concurrently.
+    polling: AtomicBool,
+    /// Set once the client probed a websocket connection, so that its pending
+    /// poll is answered with a noop.
+    upgrading: AtomicBool,
+    incoming: Mutex<Option<mpsc::UnboundedSender<Result<Packet>>>>,
+    received: Mutex<mpsc::UnboundedReceiver<Result<Packet>>>,
+    /// The number of pings sent, and of the last one the client answered.
+    pings: AtomicU64,
+    answered: AtomicU64,
+    closed: AtomicBool,
+    closed_notify: Notify,
+}
+
+/// Clears the flag of a running poll once it's dropped.
+struct PollingGuard<'a>(&'a AtomicBool);
+
+impl Drop for PollingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Where the packets for the client go: into the queue the polling transport
+/// answers the next poll with, or directly to the websocket.
+#[derive(Default)]
+struct Outgoing {
+    queue: Vec<Packet>,
+    websocket: Option<AsyncWebsocketGeneralTransport>,
+}
+
+impl ServerSocket {
+    /// Creates the session of a client that sent `request` to open it.
+    pub(super) fn new<B>(request: &Request<B>, options: Arc<ServerBuilder>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        ServerSocket {
+            inner: Arc::new(Session {
+                sid: session_id(),
+                uri: request.uri().clone(),
+                headers: request.headers().clone(),
+                options,
+                outgoing: Default::default(),
+                writable: Notify::new(),
+                flushed: Notify::new(),
+                polling: AtomicBool::new(false),
+                upgrading: AtomicBool::new(false),
+                incoming: Mutex::new(Some(tx)),
+                received: Mutex::new(rx),
+                pings: AtomicU64::new(0),
+                answered: AtomicU64::new(0),
+                closed: AtomicBool::new(false),
+                closed_notify: Notify::new(),
+            }),
+        }
+    }
+
+    /// The packet that opens the session, offering an upgrade if the client
+    /// connected via polling.
+    pub(super) fn handshake(&self, upgrades: &[&str]) -> Result<Packet> {
+        let options = &self.inner.options;
+        let handshake = HandshakePacket {
+            sid: self.inner.sid.clone(),
+            upgrades: upgrades.iter().map(|&upgrade| upgrade.to_owned()).collect(),
+            ping_interval: options.ping_interval.as_millis() as u64,
+            ping_timeout: options.ping_timeout.as_millis() as u64,
+            extensions: HashMap::from([(
+                "maxPayload".to_owned(),
+                serde_json::Value::from(options.max_payload),
+            )]),
+        };
+        Ok(Packet::new(
+            PacketId::Open,
+            serde_json::to_string(&handshake)?,
+        ))
+    }
+
+    /// Opens the session of a client that connected via websocket directly.
+    pub(super) async fn open_websocket(
+        &self,
+        transport: AsyncWebsocketGeneralTransport,
+    ) -> Result<()> {
+        self.inner.outgoing.lock().await.websocket = Some(transport.clone());
+        self.emit(self.handshake(&[])?).await?;
+        self.start_reading(transport);
+        Ok(())
+    }
+
+    /// Answers a poll of the client with the queued packets, waiting for
+    /// packets if there are none.
+    pub(super) async fn poll(&self) -> Result<Bytes> {
+        if self.inner.polling.swap(true, Ordering::AcqRel) {
+            self.abort();
+            return Err(Error::InvalidHttpRequest("overlapping polls".to_owned()));
+        }
+        // cleared on drop, as the poll is cancelled when the request is dropped
+        let _polling = PollingGuard(&self.inner.polling);
+        loop {
+            let writable = self.inner.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+
+            let mut outgoing = self.inner.outgoing.lock().await;
+            if outgoing.websocket.is_some() {
+                break Err(Error::InvalidHttpRequest(
+                    "the session was upgraded".to_owned(),
+                ));
+            }
+            if !outgoing.queue.is_empty() {
+                let packets = std::mem::take(&mut outgoing.queue);
+                self.inner.flushed.notify_waiters();
+                break Bytes::try_from(Payload::from(packets));
+            }
+            drop(outgoing);
+            if self.inner.upgrading.load(Ordering::Acquire) {
+                break Ok(Bytes::from(Packet::new(PacketId::Noop, "")));
+            }
+            if self.is_closed() {
+                break Err(Error::IllegalActionBeforeOpen());
+            }
+            writable.await;
+        }
+    }
+
+    /// Handles the packets the client sent via polling.
+    pub(super) fn post(&self, body: Bytes) -> Result<()> {
+        match Payload::try_from(body) {
+            Ok(packets) => {
+                for packet in packets {
+                    self.receive(packet);
+                }
+                Ok(())
+            }
+            Err(err) => {
+                self.abort();
+                Err(err)
+            }
+        }
+    }
+
+    /// Upgrades the session of a client that connected via polling to the
+    /// websocket `transport`: the client probes the websocket, then asks to
+    /// switch to it once its polling transport is idle.
+    pub(super) async fn upgrade(&self, transport: AsyncWebsocketGeneralTransport) -> Result<()> {
+        let upgrade = async {
+            let probe = Packet::try_from(
+                transport
+                    .poll_next()
+                    .await?
+                    .ok_or(Error::UpgradeRejected())?,
+            )?;
+            if probe != Packet::new(PacketId::Ping, "probe") {
+                return Err(Error::UpgradeRejected());
+            }
+            transport
+                .emit(Bytes::from(Packet::new(PacketId::Pong, "probe")), false)
+                .await?;
+            self.inner.upgrading.store(true, Ordering::Release);
+            self.inner.writable.notify_waiters();
+
+            loop {
+                let packet = Packet::try_from(
+                    transport
+                        .poll_next()
+                        .await?
+                        .ok_or(Error::UpgradeRejected())?,
+                )?;
+                match packet.packet_id {
+                    PacketId::Upgrade => return Ok(()),
+                    PacketId::Noop => (),
+                    _ => return Err(Error::UpgradeRejected()),
+                }
+            }
+        };
+        let result = tokio::time::timeout(UPGRADE_TIMEOUT, upgrade)
+            .await
+            .unwrap_or(Err(Error::UpgradeRejected()));
+        self.inner.upgrading.store(false, Ordering::Release);
+        result?;
+
+        let mut outgoing = self.inner.outgoing.lock().await;
+        for packet in std::mem::take(&mut outgoing.queue) {
+            send(&transport, packet).await?;
+        }
+        outgoing.websocket = Some(transport.clone());
+        drop(outgoing);
+        self.inner.flushed.notify_waiters();
+        // a poll that is still pending must not wait any longer
+        self.inner.writable.notify_waiters();
+        self.start_reading(transport);
+        Ok(())
+    }
+
+    /// Reads the packets the client sends via websocket until the session is
+    /// closed.
+    fn start_reading(&self, transport: AsyncWebsocketGeneralTransport) {
+        let socket = self.clone();
+        tokio::spawn(async move {
+            let read = async {
+                while let Ok(Some(data)) = transport.poll_next().await {
+                    match Packet::try_from(data) {
+                        Ok(packet) => socket.receive(packet),
+                        Err(err) => {
+                            socket.fail(err);
+                            break;
+                        }
+                    }
+                }
+            };
+            let closed = socket.closed();
+            tokio::pin!(read, closed);
+            match future::select(read, closed).await {
+                Either::Left(_) => socket.abort(),
+                Either::Right(_) => {
+                    transport.close().await;
+                    // the connection closes once the last handle is dropped
+                    socket.inner.outgoing.lock().await.websocket.take();
+                }
+            }
+        });
+    }
+
+    /// Counts a ping about to be sent and returns its number, unless the
+    /// client didn't answer the last one yet.
+    pub(super) fn start_ping(&self) -> Option<u64> {
+        let pings = self.inner.pings.load(Ordering::Acquire);
+        if self.inner.answered.load(Ordering::Acquire) < pings {
+            return None;
+        }
+        self.inner.pings.store(pings + 1, Ordering::Release);
+        Some(pings + 1)
+    }
+
+    /// Whether the client answered the ping with the number `ping`.
+    pub(super) fn answered(&self, ping: u64) -> bool {
+        self.inner.answered.load(Ordering::Acquire) >= ping
+    }
+
+    /// Handles a packet the client sent.
+    fn receive(&self, packet: Packet) {
+        match packet.packet_id {
+            PacketId::Message | PacketId::MessageBinary => {
+                if let Ok(incoming) = self.inner.incoming.lock() {
+                    if let Some(incoming) = incoming.as_ref() {
+                        let _ = incoming.send(Ok(packet));
+                    }
+                }
+            }
+            PacketId::Pong => {
+                let pings = self.inner.pings.load(Ordering::Acquire);
+                self.inner.answered.store(pings, Ordering::Release);
+            }
+            PacketId::Close => self.abort(),
+            // pings of old clients, upgrades and noops
+            _ => (),
+        }
+    }
+
+    /// Passes an error on to the stream and closes the session.
+    fn fail(&self, err: Error) {
+        if let Ok(incoming) = self.inner.incoming.lock() {
+            if let Some(incoming) = incoming.as_ref() {
+                let _ = incoming.send(Err(err));
+            }
+        }
+        self.abort();
+    }
+
+    /// The id of the session.
+    pub fn sid(&self) -> &str {
+        &self.inner.sid
+    }
+
+    /// The URI the client requested to open the session, including the query.
+    pub fn uri(&self) -> &Uri {
+        &self.inner.uri
+    }
+
+    /// The headers of the request that opened the session.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.inner.headers
+    }
+
+    /// Whether the session was closed.
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::Acquire)
+    }
+
+    /// Whether the session was opened or upgraded to websocket and isn't
+    /// closed yet.
+    pub async fn is_websocket(&self) -> bool {
+        self.inner.outgoing.lock().await.websocket.is_some()
+    }
+
+    /// Waits until the session is closed.
+    pub(super) async fn closed(&self) {
+        let closed = self.inner.closed_notify.notified();
+        tokio::pin!(closed);
+        closed.as_mut().enable();
+        if !self.is_closed() {
+            closed.await;
+        }
+    }
+
+    /// Sends a packet to the client. Via websocket, [`PacketId::MessageBinary`]
+    /// packets are sent as binary frames and all others as text frames. Via
+    /// polling, the packets are queued until the client polls.
+    pub async fn emit(&self, packet: Packet) -> Result<()> {
+        if self.is_closed() {
+            return Err(Error::IllegalActionBeforeOpen());
+        }
+        let mut outgoing = self.inner.outgoing.lock().await;
+        match &outgoing.websocket {
+            Some(transport) => send(transport, packet).await,
+            None => {
+                outgoing.queue.push(packet);
+                drop(outgoing);
+                self.inner.writable.notify_waiters();
+                Ok(())
+            }
+        }
+    }
+
+    /// Blocks the packets for the client while the returned guard is held,
+    /// like a websocket whose client doesn't read.
+    #[cfg(test)]
+    pub(super) async fn stall(&self) -> impl Sized + '_ {
+        self.inner.outgoing.lock().await
+    }
+
+    /// Closes the session, the client doesn't reconnect.
+    pub async fn close(&self) -> Result<()> {
+        let result = self.emit(Packet::new(PacketId::Close, "")).await;
+        self.abort();
+        result
+    }
+
+    /// Closes the session once the client received the packets queued for it
+    /// and the `Close` packet that follows them.
+    pub(super) async fn drain(&self) {
+        if self.emit(Packet::new(PacketId::Close, "")).await.is_ok() {
+            loop {
+                let flushed = self.inner.flushed.notified();
+                tokio::pin!(flushed);
+                flushed.as_mut().enable();
+                if self.is_closed() || self.inner.outgoing.lock().await.queue.is_empty() {
+                    break;
+                }
+                flushed.await;
+            }
+        }
+        self.abort();
+    }
+
+    /// Closes the session without notifying the client.
+    pub(super) fn abort(&self) {
+        if self.inner.closed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        if let Ok(mut incoming) = self.inner.incoming.lock() {
+            incoming.take();
+        }
+        self.inner.closed_notify.notify_waiters();
+        self.inner.writable.notify_waiters();
+        self.inner.flushed.notify_waiters();
+    }
+}
+
+impl Stream for ServerSocket {
+    type Item = Result<Packet>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.inner.received.lock() {
+            Ok(mut received) => received.poll_recv(cx),
+            Err(_) => Poll::Ready(Some(Err(Error::InvalidPoisonedLock()))),
+        }
+    }
+}
+
+impl Debug for ServerSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerSocket")
+            .field("sid", &self.inner.sid)
+            .field("uri", &self.inner.uri)
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// Sends a packet via websocket.
+async fn send(transport: &AsyncWebsocketGeneralTransport, packet: Packet) -> Result<()> {
+    match packet.packet_id {
+        PacketId::MessageBinary => transport.emit(packet.data, true).await,
+        _ => transport.emit(Bytes::from(packet), false).await,
+    }
+}
+
+/// Creates the id of a new session. It's the only credential of the polling
+/// requests of the session, so it's drawn from a cryptographically secure
+/// random number generator.
+fn session_id() -> String {
+    format!("{:032x}", thread_rng().gen::<u128>())
+}
@@ -1,5 +1,10 @@
 use crate::callback::OptionalCallback;
+use crate::clock::Timestamp;
+#[cfg(feature = "websocket")]
+use crate::clock::WAKE_CHECK_INTERVAL;
+use crate::pool::{BufferPool, BufferPoolStats};
 use crate::transport::TransportType;
+use crate::{CloseReason, DataInfo, DisconnectDescription, HttpVersion, TransportKind};
 
 use crate::error::{Error, Result};
 use crate::packet::{HandshakePacket, Packet, PacketId, Payload};
@@ -9,7 +14,10 @@ use std::sync::RwLock;
 use std::time::Duration;
 use std::{fmt::Debug, sync::atomic::Ordering};
 use std::{
-    sync::{atomic::AtomicBool, Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc, Mutex,
+    },
     time::Instant,
 };
 
@@ -23,26 +31,33 @@ pub const DEFAULT_MAX_POLL_TIMEOUT: Duration = Duration::from_secs(45);
 #[derive(Clone)]
 pub struct Socket {
     transport: Arc<TransportType>,
-    on_close: OptionalCallback<()>,
-    on_data: OptionalCallback<Bytes>,
+    on_close: OptionalCallback<DisconnectDescription>,
+    on_data: OptionalCallback<(Bytes, DataInfo)>,
     on_error: OptionalCallback<String>,
     on_open: OptionalCallback<()>,
     on_packet: OptionalCallback<Packet>,
     connected: Arc<AtomicBool>,
-    last_ping: Arc<Mutex<Instant>>,
+    /// Whether the close of the connection was reported to `on_close`.
+    close_reported: Arc<AtomicBool>,
+    last_ping: Arc<Mutex<Timestamp>>,
     last_pong: Arc<Mutex<Instant>>,
+    /// When data was received from the server the last time.
+    last_received: Arc<Mutex<Instant>>,
+    /// The size of the packets that are being sent.
+    bytes_in_flight: Arc<AtomicUsize>,
     connection_data: Arc<HandshakePacket>,
     /// Since we get packets in payloads it's possible to have a state where only some of the packets have been consumed.
     remaining_packets: Arc<RwLock<Option<crate::packet::IntoIter>>>,
     max_ping_timeout: u64,
+    buffer_pool: BufferPool,
 }
 
 impl Socket {
     pub(crate) fn new(
         transport: TransportType,
         handshake: HandshakePacket,
-        on_close: OptionalCallback<()>,
-        on_data: OptionalCallback<Bytes>,
+        on_close: OptionalCallback<DisconnectDescription>,
+        on_data: OptionalCallback<(Bytes, DataInfo)>,
         on_error: OptionalCallback<String>,
         on_open: OptionalCallback<()>,
         on_packet: OptionalCallback<Packet>,
@@ -57,37 +72,47 @@ impl Socket {
             on_packet,
             transport: Arc::new(transport),
             connected: Arc::new(AtomicBool::default()),
-            last_ping: Arc::new(Mutex::new(Instant::now())),
+            close_reported: Arc::new(AtomicBool::default()),
+            last_ping: Arc::new(Mutex::new(Timestamp::now())),
             last_pong: Arc::new(Mutex::new(Instant::now())),
+            last_received: Arc::new(Mutex::new(Instant::now())),
+            bytes_in_flight: Arc::new(AtomicUsize::new(0)),
             connection_data: Arc::new(handshake),
             remaining_packets: Arc::new(RwLock::new(None)),
             max_ping_timeout,
+            buffer_pool: BufferPool::default(),
         }
     }
 
+    /// Sets the pool that outgoing packets are encoded with.
+    pub(crate) fn with_buffer_pool(mut self, buffer_pool: BufferPool) -> Self {
+        self.buffer_pool = buffer_pool;
+        self
+    }
+
     /// Opens the connection to a specified server. The first Pong packet is sent
     /// to the server to trigger the Ping-cycle.
     pub fn connect(&self) -> Result<()> {
         // SAFETY: Has valid handshake due to type
         self.connected.store(true, Ordering::Release);
+        self.close_reported.store(false, Ordering::Release);
 
         if let Some(on_open) = self.on_open.as_ref() {
             spawn_scoped!(on_open(()));
         }
 
         // set the last ping to now and set the connected state
-        *self.last_ping.lock()? = Instant::now();
+        *self.last_ping.lock()? = Timestamp::now();
+        *self.last_received.lock()? = Instant::now();
 
         // emit a pong packet to keep trigger the ping cycle on the server
-        self.emit(Packet::new(PacketId::Pong, Bytes::new()))?;
+        self.pong()?;
 
         Ok(())
     }
 
     pub fn disconnect(&self) -> Result<()> {
-        if let Some(on_close) = self.on_close.as_ref() {
-            spawn_scoped!(on_close(()));
-        }
+        self.report_close(CloseReason::ForcedClose, None);
 
         // will not succeed when connection to the server is interrupted
         let _ = self.emit(Packet::new(PacketId::Close, Bytes::new()));
@@ -97,6 +122,14 @@ impl Socket {
         Ok(())
     }
 
+    /// Closes the connection right away without sending a close packet. The
+    /// response of a running poll is discarded, nothing is sent afterwards.
+    pub fn abort(&self) {
+        self.connected.store(false, Ordering::Release);
+        self.transport.abort();
+        self.report_close(CloseReason::ForcedClose, None);
+    }
+
     /// Sends a packet to the server.
     pub fn emit(&self, packet: Packet) -> Result<()> {
         if !self.connected.load(Ordering::Acquire) {
@@ -112,10 +145,15 @@ impl Socket {
         let data: Bytes = if is_binary {
             packet.data
         } else {
-            packet.into()
+            packet.encode(&self.buffer_pool)
         };
 
-        if let Err(error) = self.transport.as_transport().emit(data, is_binary) {
+        let len = data.len();
+        self.bytes_in_flight.fetch_add(len, Ordering::AcqRel);
+        let result = self.transport.as_transport().emit(data, is_binary);
+        self.bytes_in_flight.fetch_sub(len, Ordering::AcqRel);
+
+        if let Err(error) = result {
             self.call_error_callback(error.to_string());
             return Err(error);
         }
@@ -139,14 +177,32 @@ impl Socket {
                 // Iterator has run out of packets, get a new payload.
                 // Make sure that payload is received within time_to_next_ping, as otherwise the heart
                 // stopped beating and we disconnect.
-                let data = self
-                    .transport
-                    .as_transport()
-                    .poll(Duration::from_millis(self.time_to_next_ping()?))?;
+                let time_to_next_ping = Duration::from_millis(self.time_to_next_ping()?);
+                // aborting a long polling request makes the server close the session, so
+                // only other transports are interrupted to check for a suspended system
+                let timeout = match self.transport.as_ref() {
+                    TransportType::Polling(_) => time_to_next_ping,
+                    #[cfg(feature = "websocket")]
+                    _ => time_to_next_ping.min(WAKE_CHECK_INTERVAL),
+                };
+                let data = match self.transport.as_transport().poll(timeout) {
+                    // the heartbeat is still within its deadline, keep waiting
+                    Err(Error::PingTimeout()) if self.time_to_next_ping()? > 0 => continue,
+                    Err(error @ Error::PingTimeout()) => {
+                        self.report_close(CloseReason::PingTimeout, Some(&error));
+                        return Err(error);
+                    }
+                    result => result?,
+                };
 
+                // the connection was aborted while polling
+                if !self.connected.load(Ordering::Acquire) {
+                    return Ok(None);
+                }
                 if data.is_empty() {
                     continue;
                 }
+                *self.last_received.lock()? = Instant::now();
 
                 let payload = Payload::try_from(data)?;
                 let mut iter = payload.into_iter();
@@ -161,6 +217,43 @@ impl Socket {
         }
     }
 
+    /// Answers a ping of the server.
+    pub(crate) fn pong(&self) -> Result<()> {
+        self.emit(Packet::new(PacketId::Pong, Bytes::new()))?;
+        *self.last_pong.lock()? = Instant::now();
+        Ok(())
+    }
+
+    /// Calls the close callback with a description of the connection, once per
+    /// connection. Failed polls aren't reported, as the next poll may succeed.
+    fn report_close(&self, reason: CloseReason, error: Option<&Error>) {
+        if self.close_reported.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let Some(on_close) = self.on_close.as_ref() else {
+            return;
+        };
+        let elapsed = |instant: &Mutex<Instant>| {
+            instant
+                .lock()
+                .map(|instant| instant.elapsed())
+                .unwrap_or_default()
+        };
+        let mut description = DisconnectDescription::new(reason, self.transport.kind());
+        if let Some(error) = error {
+            description = description.with_error(error);
+        }
+        description.since_last_received = elapsed(&self.last_received);
+        description.since_last_pong = elapsed(&self.last_pong);
+        description.since_last_ping = self
+            .last_ping
+            .lock()
+            .map(|ping| ping.elapsed())
+            .unwrap_or_default();
+        description.bytes_in_flight = self.bytes_in_flight.load(Ordering::Acquire);
+        spawn_scoped!(on_close(description));
+    }
+
     /// Calls the error callback with a given message.
     #[inline]
     fn call_error_callback(&self, text: String) {
@@ -174,8 +267,25 @@ impl Socket {
         Ok(self.connected.load(Ordering::Acquire))
     }
 
+    /// Returns the handshake data the server sent when opening the connection.
+    pub(crate) fn handshake(&self) -> &HandshakePacket {
+        &self.connection_data
+    }
+
+    pub(crate) fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.buffer_pool.stats()
+    }
+
+    pub(crate) fn http_version(&self) -> Option<HttpVersion> {
+        self.transport.http_version()
+    }
+
+    pub(crate) fn transport_kind(&self) -> TransportKind {
+        self.transport.kind()
+    }
+
     pub(crate) fn pinged(&self) -> Result<()> {
-        *self.last_ping.lock()? = Instant::now();
+        *self.last_ping.lock()? = Timestamp::now();
         Ok(())
     }
 
@@ -183,17 +293,8 @@ impl Socket {
     /// This is used to detect whether we have been disconnected from the server.
     /// See https://socket.io/docs/v4/how-it-works/#disconnection-detection
     fn time_to_next_ping(&self) -> Result<u64> {
-        match Instant::now().checked_duration_since(*self.last_ping.lock()?) {
-            Some(since_last_ping) => {
-                let since_last_ping = since_last_ping.as_millis() as u64;
-                if since_last_ping > self.max_ping_timeout {
-                    Ok(0)
-                } else {
-                    Ok(self.max_ping_timeout - since_last_ping)
-                }
-            }
-            None => Ok(0),
-        }
+        let since_last_ping = self.last_ping.lock()?.elapsed().as_millis() as u64;
+        Ok(self.max_ping_timeout.saturating_sub(since_last_ping))
     }
 
     pub(crate) fn handle_packet(&self, packet: Packet) {
@@ -202,16 +303,15 @@ impl Socket {
         }
     }
 
-    pub(crate) fn handle_data(&self, data: Bytes) {
+    pub(crate) fn handle_data(&self, data: Bytes, packet_id: PacketId) {
         if let Some(on_data) = self.on_data.as_ref() {
-            spawn_scoped!(on_data(data));
+            let info = DataInfo::new(packet_id, self.transport.kind());
+            spawn_scoped!(on_data((data, info)));
         }
     }
 
     pub(crate) fn handle_close(&self) {
-        if let Some(on_close) = self.on_close.as_ref() {
-            spawn_scoped!(on_close(()));
-        }
+        self.report_close(CloseReason::ServerClose, None);
 
         self.connected.store(false, Ordering::Release);
     }
@@ -0,0 +1,96 @@
+//! Timings of the emits of a client, to tell a slow transport from emits that
+//! queue up behind each other.
+//!
+//! An emit first waits for the transport, which is locked by the emit before
+//! it and while the client reconnects, and then writes the packet. A long
+//! queue wait with short writes points at head-of-line blocking, e.g. a
+//! large emit or a stalled reconnect holding the transport, long writes at a
+//! slow network or server.
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// The timings of the emits of a client, see
+/// `asynchronous::ClientBuilder::emit_timing`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmitTimingStats {
+    /// The number of timed emits.
+    pub emits: u64,
+    /// The time the emits waited for the transport, in total.
+    pub queue_wait: Duration,
+    /// The longest time an emit waited for the transport.
+    pub max_queue_wait: Duration,
+    /// The time the transport took to write the emits, in total.
+    pub write: Duration,
+    /// The longest time the transport took to write an emit.
+    pub max_write: Duration,
+}
+
+impl EmitTimingStats {
+    /// The average time an emit waited for the transport.
+    pub fn mean_queue_wait(&self) -> Duration {
+        mean(self.queue_wait, self.emits)
+    }
+
+    /// The average time the transport took to write an emit.
+    pub fn mean_write(&self) -> Duration {
+        mean(self.write, self.emits)
+    }
+}
+
+fn mean(total: Duration, count: u64) -> Duration {
+    match u32::try_from(count) {
+        Ok(0) => Duration::ZERO,
+        Ok(count) => total / count,
+        Err(_) => Duration::from_secs_f64(total.as_secs_f64() / count as f64),
+    }
+}
+
+/// Records the timings of emits. Cloning yields a handle to the same records.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EmitTimer {
+    stats: Arc<Mutex<EmitTimingStats>>,
+}
+
+impl EmitTimer {
+    /// Records an emit that waited `queue_wait` for the transport, which
+    /// then took `write` to write it.
+    pub(crate) fn record(&self, queue_wait: Duration, write: Duration) {
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.emits += 1;
+        stats.queue_wait += queue_wait;
+        stats.max_queue_wait = stats.max_queue_wait.max(queue_wait);
+        stats.write += write;
+        stats.max_write = stats.max_write.max(write);
+    }
+
+    /// The timings up to now.
+    pub(crate) fn stats(&self) -> EmitTimingStats {
+        *self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let timer = EmitTimer::default();
+        assert_eq!(timer.stats().mean_queue_wait(), Duration::ZERO);
+
+        timer
+            .clone()
+            .record(Duration::from_millis(30), Duration::from_millis(2));
+        timer.record(Duration::from_millis(10), Duration::from_millis(4));
+        let stats = timer.stats();
+        assert_eq!(stats.emits, 2);
+        assert_eq!(stats.queue_wait, Duration::from_millis(40));
+        assert_eq!(stats.max_queue_wait, Duration::from_millis(30));
+        assert_eq!(stats.mean_queue_wait(), Duration::from_millis(20));
+        assert_eq!(stats.write, Duration::from_millis(6));
+        assert_eq!(stats.max_write, Duration::from_millis(4));
+        assert_eq!(stats.mean_write(), Duration::from_millis(3));
+    }
+}
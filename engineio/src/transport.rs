@@ -1,8 +1,16 @@
-use super::transports::{PollingTransport, WebsocketSecureTransport, WebsocketTransport};
-use crate::error::Result;
+use super::transports::PollingTransport;
+#[cfg(feature = "websocket")]
+use super::transports::{WebsocketSecureTransport, WebsocketTransport};
+use crate::binding::LocalBinding;
+use crate::error::{Error, Result};
+use crate::resolver::SharedResolver;
+use crate::{HttpVersion, TransportKind};
 use adler32::adler32;
 use bytes::Bytes;
+use reqwest::StatusCode;
 use std::time::{Duration, SystemTime};
+#[cfg(feature = "websocket")]
+use tungstenite::protocol::WebSocketConfig;
 use url::Url;
 
 pub trait Transport {
@@ -35,7 +43,9 @@ pub trait Transport {
 #[derive(Debug)]
 pub enum TransportType {
     Polling(PollingTransport),
+    #[cfg(feature = "websocket")]
     WebsocketSecure(WebsocketSecureTransport),
+    #[cfg(feature = "websocket")]
     Websocket(WebsocketTransport),
 }
 
@@ -45,12 +55,14 @@ impl From<PollingTransport> for TransportType {
     }
 }
 
+#[cfg(feature = "websocket")]
 impl From<WebsocketSecureTransport> for TransportType {
     fn from(transport: WebsocketSecureTransport) -> Self {
         TransportType::WebsocketSecure(transport)
     }
 }
 
+#[cfg(feature = "websocket")]
 impl From<WebsocketTransport> for TransportType {
     fn from(transport: WebsocketTransport) -> Self {
         TransportType::Websocket(transport)
@@ -61,10 +73,233 @@ impl TransportType {
     pub fn as_transport(&self) -> &dyn Transport {
         match self {
             TransportType::Polling(transport) => transport,
+            #[cfg(feature = "websocket")]
             TransportType::Websocket(transport) => transport,
+            #[cfg(feature = "websocket")]
             TransportType::WebsocketSecure(transport) => transport,
         }
     }
+
+    /// Returns which kind of transport this is.
+    pub fn kind(&self) -> TransportKind {
+        match self {
+            TransportType::Polling(_) => TransportKind::Polling,
+            #[cfg(feature = "websocket")]
+            TransportType::Websocket(_) => TransportKind::Websocket,
+            #[cfg(feature = "websocket")]
+            TransportType::WebsocketSecure(_) => TransportKind::WebsocketSecure,
+        }
+    }
+
+    /// Returns the HTTP version of the last response the polling transport
+    /// received, `None` for websocket transports.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        match self {
+            TransportType::Polling(transport) => transport.http_version(),
+            #[cfg(feature = "websocket")]
+            _ => None,
+        }
+    }
+
+    /// Stops the transport without closing the connection. A running long
+    /// polling request can't be interrupted, the socket discards its response,
+    /// but no further requests are sent.
+    pub(crate) fn abort(&self) {
+        match self {
+            TransportType::Polling(transport) => transport.abort(),
+            #[cfg(feature = "websocket")]
+            TransportType::Websocket(transport) => transport.abort(),
+            #[cfg(feature = "websocket")]
+            TransportType::WebsocketSecure(transport) => transport.abort(),
+        }
+    }
+}
+
+/// The HTTP protocol version that is used by the polling transport.
+///
+/// Some gateways mishandle HTTP/2 request bodies, which is why HTTP/1.1 is
+/// the default. Negotiating HTTP/2 over TLS needs ALPN, which is only
+/// available with the `http2` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpProtocol {
+    /// Negotiate the version with the server via ALPN. Without the `http2`
+    /// feature, no protocols are offered and the server picks HTTP/1.1.
+    Auto,
+    /// Only use HTTP/1.1. This is the default.
+    #[default]
+    Http1,
+    /// Only use HTTP/2. Over TLS, only `h2` is offered via ALPN, which needs
+    /// the `http2` feature. Plain connections assume that the server speaks
+    /// HTTP/2 without an upgrade.
+    Http2,
+}
+
+/// Retry policy for the long-poll `GET` requests of the polling transport.
+///
+/// Gateways in front of the server occasionally answer with `502 Bad Gateway`
+/// or `503 Service Unavailable` for a short time. As a `GET` only fetches the
+/// pending packets of the session, it's retried with an exponential backoff
+/// instead of failing the whole session. Requests that send packets to the
+/// server are never retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollRetry {
+    max_retries: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl PollRetry {
+    /// Retries a request at most `max_retries` times, waiting `initial_delay`
+    /// before the first retry and doubling the delay for every further retry.
+    pub fn new(max_retries: u32, initial_delay: Duration) -> Self {
+        PollRetry {
+            max_retries,
+            initial_delay,
+            max_delay: initial_delay.saturating_mul(1 << max_retries.min(16)),
+        }
+    }
+
+    /// Never retries a request.
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Caps the delay between two retries.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Whether a response with the given status is worth retrying.
+    pub(crate) fn is_transient(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE
+        )
+    }
+
+    /// Returns how long to wait before the next retry, or `None` if the retries
+    /// are exhausted. `attempt` counts the retries that were already made.
+    pub(crate) fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let delay = self
+            .initial_delay
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_delay);
+        Some(delay)
+    }
+}
+
+impl Default for PollRetry {
+    /// Retries a request up to three times, starting with a delay of 100ms.
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(100))
+    }
+}
+
+/// Retry policy for the writes of the async websocket transport.
+///
+/// A write can fail for a moment while the connection is otherwise healthy,
+/// e.g. when the write buffer is full or the socket times out under
+/// backpressure. Such writes are retried with an exponential backoff before
+/// the emit fails and the connection is considered broken. A frame is only
+/// sent again if the websocket didn't queue it yet, a queued frame is only
+/// flushed again, so that no frame is sent twice.
+#[cfg(feature = "websocket")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteRetry {
+    backoff: PollRetry,
+}
+
+#[cfg(feature = "websocket")]
+impl WriteRetry {
+    /// Retries a write at most `max_retries` times, waiting `initial_delay`
+    /// before the first retry and doubling the delay for every further retry.
+    pub fn new(max_retries: u32, initial_delay: Duration) -> Self {
+        WriteRetry {
+            backoff: PollRetry::new(max_retries, initial_delay),
+        }
+    }
+
+    /// Never retries a write.
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Caps the delay between two retries.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.backoff = self.backoff.max_delay(max_delay);
+        self
+    }
+
+    /// Whether a write that failed with `error` is worth retrying.
+    pub(crate) fn is_transient(error: &tungstenite::Error) -> bool {
+        match error {
+            tungstenite::Error::WriteBufferFull(_) => true,
+            tungstenite::Error::Io(error) => matches!(
+                error.kind(),
+                std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
+
+    /// Returns how long to wait before the next retry, or `None` if the retries
+    /// are exhausted. `attempt` counts the retries that were already made.
+    pub(crate) fn delay(&self, attempt: u32) -> Option<Duration> {
+        self.backoff.delay(attempt)
+    }
+}
+
+#[cfg(feature = "websocket")]
+impl Default for WriteRetry {
+    /// Retries a write up to three times, starting with a delay of 10ms.
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(10))
+    }
+}
+
+/// The options of the transports that are configured on the client builders.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TransportOptions {
+    pub(crate) http_protocol: HttpProtocol,
+    pub(crate) poll_retry: PollRetry,
+    pub(crate) local_binding: LocalBinding,
+    /// Resolves the host of the server instead of the system, if set.
+    pub(crate) resolver: Option<SharedResolver>,
+    /// The maximum size of a payload received from the server, unlimited if `None`.
+    pub(crate) max_payload_size: Option<usize>,
+    /// How failed writes of the async websocket transport are retried.
+    #[cfg(feature = "websocket")]
+    pub(crate) write_retry: WriteRetry,
+    /// The middleware that wraps the requests of the async polling transport.
+    #[cfg(feature = "tower")]
+    pub(crate) http_layer: Option<crate::asynchronous::HttpLayer>,
+}
+
+impl TransportOptions {
+    /// Fails with [`Error::PayloadTooLarge`] if `size` bytes exceed the maximum payload size.
+    pub(crate) fn check_payload_size(max_payload_size: Option<usize>, size: usize) -> Result<()> {
+        match max_payload_size {
+            Some(limit) if size > limit => Err(Error::PayloadTooLarge(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The configuration of websocket connections, which limits the size of
+    /// incoming messages and frames to the maximum payload size.
+    #[cfg(feature = "websocket")]
+    pub(crate) fn websocket_config(&self) -> Option<WebSocketConfig> {
+        self.max_payload_size.map(|limit| WebSocketConfig {
+            max_message_size: Some(limit),
+            max_frame_size: Some(limit),
+            ..Default::default()
+        })
+    }
 }
 
 impl std::fmt::Debug for dyn Transport {
@@ -72,3 +307,80 @@ impl std::fmt::Debug for dyn Transport {
         f.write_fmt(format_args!("Transport(base_url: {:?})", self.base_url(),))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_write_retry_is_transient() {
+        use std::io::{Error as IoError, ErrorKind};
+        use tungstenite::Message;
+
+        let io = |kind| tungstenite::Error::Io(IoError::from(kind));
+        assert!(WriteRetry::is_transient(&io(ErrorKind::TimedOut)));
+        assert!(WriteRetry::is_transient(
+            &tungstenite::Error::WriteBufferFull(Message::text("full"))
+        ));
+        assert!(!WriteRetry::is_transient(&io(ErrorKind::BrokenPipe)));
+        assert!(!WriteRetry::is_transient(
+            &tungstenite::Error::ConnectionClosed
+        ));
+        assert_eq!(WriteRetry::disabled().delay(0), None);
+    }
+
+    #[test]
+    fn test_poll_retry_delay() {
+        let retry =
+            PollRetry::new(3, Duration::from_millis(100)).max_delay(Duration::from_millis(300));
+
+        assert_eq!(retry.delay(0), Some(Duration::from_millis(100)));
+        assert_eq!(retry.delay(1), Some(Duration::from_millis(200)));
+        // capped by the maximum delay
+        assert_eq!(retry.delay(2), Some(Duration::from_millis(300)));
+        // retries are exhausted
+        assert_eq!(retry.delay(3), None);
+        assert_eq!(PollRetry::disabled().delay(0), None);
+    }
+
+    #[test]
+    fn test_poll_retry_transient() {
+        assert!(PollRetry::is_transient(StatusCode::BAD_GATEWAY));
+        assert!(PollRetry::is_transient(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!PollRetry::is_transient(StatusCode::OK));
+        assert!(!PollRetry::is_transient(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_max_payload_size() {
+        assert!(TransportOptions::check_payload_size(None, usize::MAX).is_ok());
+        assert!(TransportOptions::check_payload_size(Some(10), 10).is_ok());
+        assert!(matches!(
+            TransportOptions::check_payload_size(Some(10), 11),
+            Err(Error::PayloadTooLarge(10))
+        ));
+    }
+
+    #[test]
+    fn test_http_protocol_default() {
+        assert_eq!(HttpProtocol::default(), HttpProtocol::Http1);
+        assert_eq!(
+            TransportOptions::default().http_protocol,
+            HttpProtocol::Http1
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "websocket")]
+    fn test_websocket_config() {
+        assert!(TransportOptions::default().websocket_config().is_none());
+        let options = TransportOptions {
+            max_payload_size: Some(1024),
+            ..Default::default()
+        };
+        let config = options.websocket_config().unwrap();
+        assert_eq!(config.max_message_size, Some(1024));
+        assert_eq!(config.max_frame_size, Some(1024));
+    }
+}
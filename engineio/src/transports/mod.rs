@@ -1,7 +1,16 @@
 mod polling;
+#[cfg(feature = "websocket")]
 mod websocket;
+#[cfg(feature = "websocket")]
 mod websocket_secure;
 
 pub use self::polling::PollingTransport;
+#[cfg(feature = "websocket")]
 pub use self::websocket::WebsocketTransport;
+#[cfg(feature = "websocket")]
 pub use self::websocket_secure::WebsocketSecureTransport;
+
+/// How long aborting a blocking websocket transport waits for the close frame
+/// to be sent.
+#[cfg(feature = "websocket")]
+const ABORT_CLOSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
@@ -1,12 +1,15 @@
 use crate::error::{Error, Result};
-use crate::transport::Transport;
+use crate::transport::{HttpProtocol, PollRetry, Transport, TransportOptions};
+use crate::HttpVersion;
 use base64::{engine::general_purpose, Engine as _};
 use bytes::{BufMut, Bytes, BytesMut};
 use native_tls::TlsConnector;
 use reqwest::{
-    blocking::{Client, ClientBuilder},
+    blocking::{Client, ClientBuilder, Response},
     header::HeaderMap,
 };
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use url::Url;
@@ -15,6 +18,10 @@ use url::Url;
 pub struct PollingTransport {
     client: Arc<Client>,
     base_url: Arc<RwLock<Url>>,
+    http_version: Arc<RwLock<Option<HttpVersion>>>,
+    retry: PollRetry,
+    max_payload_size: Option<usize>,
+    aborted: Arc<AtomicBool>,
 }
 
 impl PollingTransport {
@@ -24,26 +31,111 @@ impl PollingTransport {
         tls_config: Option<TlsConnector>,
         opening_headers: Option<HeaderMap>,
     ) -> Self {
-        let client = match (tls_config, opening_headers) {
-            (Some(config), Some(map)) => ClientBuilder::new()
-                .use_preconfigured_tls(config)
-                .default_headers(map)
-                .build()
-                .unwrap(),
-            (Some(config), None) => ClientBuilder::new()
-                .use_preconfigured_tls(config)
-                .build()
-                .unwrap(),
-            (None, Some(map)) => ClientBuilder::new().default_headers(map).build().unwrap(),
-            (None, None) => Client::new(),
-        };
+        Self::with_http_protocol(
+            base_url,
+            tls_config,
+            opening_headers,
+            HttpProtocol::default(),
+        )
+    }
+
+    /// Creates an instance of `PollingTransport` that uses the given HTTP protocol version.
+    pub fn with_http_protocol(
+        base_url: Url,
+        tls_config: Option<TlsConnector>,
+        opening_headers: Option<HeaderMap>,
+        http_protocol: HttpProtocol,
+    ) -> Self {
+        Self::with_options(
+            base_url,
+            tls_config,
+            opening_headers,
+            &TransportOptions {
+                http_protocol,
+                ..Default::default()
+            },
+        )
+        // SAFETY: only a resolver can fail, and none is configured
+        .unwrap()
+    }
+
+    /// Creates an instance of `PollingTransport` with the given transport
+    /// `options`. If they contain a resolver, the host is resolved right away,
+    /// as the blocking `reqwest` client doesn't accept a resolver.
+    pub(crate) fn with_options(
+        base_url: Url,
+        tls_config: Option<TlsConnector>,
+        opening_headers: Option<HeaderMap>,
+        options: &TransportOptions,
+    ) -> Result<Self> {
+        let mut builder = options.local_binding.apply_blocking(ClientBuilder::new());
+        if let (Some(resolver), Some(host)) = (&options.resolver, base_url.host_str()) {
+            let port = base_url.port_or_known_default().unwrap_or_default();
+            builder = builder.resolve_to_addrs(host, &resolver.lookup_blocking(host, port)?);
+        }
+        if let Some(config) = tls_config {
+            builder = builder.use_preconfigured_tls(config);
+        }
+        if let Some(map) = opening_headers {
+            builder = builder.default_headers(map);
+        }
+        let client = match options.http_protocol {
+            HttpProtocol::Auto => builder,
+            HttpProtocol::Http1 => builder.http1_only(),
+            HttpProtocol::Http2 => builder.http2_prior_knowledge(),
+        }
+        .build()
+        .unwrap();
 
         let mut url = base_url;
         url.query_pairs_mut().append_pair("transport", "polling");
 
-        PollingTransport {
+        Ok(PollingTransport {
             client: Arc::new(client),
             base_url: Arc::new(RwLock::new(url)),
+            http_version: Arc::new(RwLock::new(None)),
+            retry: options.poll_retry,
+            max_payload_size: options.max_payload_size,
+            aborted: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Returns the HTTP version of the last response received from the server.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        self.http_version.read().ok().and_then(|version| *version)
+    }
+
+    /// Reads the body of a response, failing once it exceeds the maximum payload size.
+    fn read_body(&self, response: Response) -> Result<Bytes> {
+        let Some(limit) = self.max_payload_size else {
+            return Ok(response.bytes()?);
+        };
+        if let Some(length) = response.content_length() {
+            TransportOptions::check_payload_size(self.max_payload_size, length as usize)?;
+        }
+        let mut body = Vec::new();
+        response.take(limit as u64 + 1).read_to_end(&mut body)?;
+        TransportOptions::check_payload_size(self.max_payload_size, body.len())?;
+        Ok(Bytes::from(body))
+    }
+
+    /// Stops the transport. A running request can't be interrupted, but no
+    /// further requests are sent and `poll`/`emit` fail right away.
+    pub(crate) fn abort(&self) {
+        self.aborted.store(true, Ordering::Release);
+    }
+
+    /// Returns the url to send the next request to, unless the transport was aborted.
+    fn request_address(&self) -> Result<Url> {
+        if self.aborted.load(Ordering::Acquire) {
+            return Err(Error::IllegalActionAfterAbort());
+        }
+        self.address()
+    }
+
+    fn record_version(&self, response: &Response) {
+        if let Ok(mut version) = self.http_version.write() {
+            *version = Some(response.version());
         }
     }
 }
@@ -62,13 +154,13 @@ impl Transport for PollingTransport {
         } else {
             data
         };
-        let status = self
+        let response = self
             .client
-            .post(self.address()?)
+            .post(self.request_address()?)
             .body(data_to_send)
-            .send()?
-            .status()
-            .as_u16();
+            .send()?;
+        self.record_version(&response);
+        let status = response.status().as_u16();
 
         if status != 200 {
             let error = Error::IncompleteHttp(status);
@@ -79,12 +171,25 @@ impl Transport for PollingTransport {
     }
 
     fn poll(&self, timeout: Duration) -> Result<Bytes> {
-        Ok(self
-            .client
-            .get(self.address()?)
-            .timeout(timeout)
-            .send()?
-            .bytes()?)
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .client
+                .get(self.request_address()?)
+                .timeout(timeout)
+                .send()?;
+            self.record_version(&response);
+
+            let status = response.status();
+            if !PollRetry::is_transient(status) {
+                return self.read_body(response);
+            }
+            let Some(delay) = self.retry.delay(attempt) else {
+                return Err(Error::IncompleteHttp(status.as_u16()));
+            };
+            attempt += 1;
+            std::thread::sleep(delay);
+        }
     }
 
     fn base_url(&self) -> Result<Url> {
@@ -107,6 +212,8 @@ impl Transport for PollingTransport {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
     use std::str::FromStr;
     #[test]
     fn polling_transport_base_url() -> Result<()> {
@@ -132,13 +239,114 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn polling_abort() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        let transport = PollingTransport::new(url, None, None);
+        transport.abort();
+
+        assert!(matches!(
+            transport.poll(Duration::from_secs(5)),
+            Err(Error::IllegalActionAfterAbort())
+        ));
+        assert!(matches!(
+            transport.emit(Bytes::from_static(b"2"), false),
+            Err(Error::IllegalActionAfterAbort())
+        ));
+        listener.set_nonblocking(true)?;
+        assert!(listener.accept().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn polling_payload_too_large() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let body = "4".repeat(100);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        });
+
+        let options = TransportOptions {
+            max_payload_size: Some(10),
+            ..Default::default()
+        };
+        let transport = PollingTransport::with_options(url, None, None, &options)?;
+        assert!(matches!(
+            transport.poll(Duration::from_secs(5)),
+            Err(Error::PayloadTooLarge(10))
+        ));
+        Ok(())
+    }
+
+    /// Answers one request per connection with the given statuses in order.
+    fn gateway(
+        listener: TcpListener,
+        statuses: &'static [&'static str],
+    ) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: 2\r\n\r\n4a"
+                );
+            }
+        })
+    }
+
+    #[test]
+    fn polling_retry() -> Result<()> {
+        let options = TransportOptions {
+            poll_retry: PollRetry::new(2, Duration::from_millis(1)),
+            ..Default::default()
+        };
+
+        // the first two polls fail at the gateway, the third one reaches the server
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        let server = gateway(
+            listener,
+            &["503 Service Unavailable", "502 Bad Gateway", "200 OK"],
+        );
+        let transport = PollingTransport::with_options(url, None, None, &options)?;
+        assert_eq!(
+            transport.poll(Duration::from_secs(5))?,
+            Bytes::from_static(b"4a")
+        );
+        server.join().unwrap();
+
+        // the error of the last retry is returned once the retries are exhausted
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        let server = gateway(listener, &["503 Service Unavailable"; 3]);
+        let transport = PollingTransport::with_options(url, None, None, &options)?;
+        assert!(matches!(
+            transport.poll(Duration::from_secs(5)),
+            Err(Error::IncompleteHttp(503))
+        ));
+        server.join().unwrap();
+        Ok(())
+    }
+
     #[test]
     fn transport_debug() -> Result<()> {
         let mut url = crate::test::engine_io_server()?;
         let transport =
             PollingTransport::new(Url::from_str(&url.to_string()[..]).unwrap(), None, None);
         url.query_pairs_mut().append_pair("transport", "polling");
-        assert_eq!(format!("PollingTransport {{ client: {:?}, base_url: RwLock {{ data: {:?}, poisoned: false, .. }} }}", transport.client, url), format!("{:?}", transport));
+        assert_eq!(format!("PollingTransport {{ client: {:?}, base_url: RwLock {{ data: {:?}, poisoned: false, .. }}, http_version: RwLock {{ data: None, poisoned: false, .. }}, retry: {:?}, max_payload_size: None, aborted: false }}", transport.client, url, PollRetry::default()), format!("{:?}", transport));
         let test: Box<dyn Transport> = Box::new(transport);
         assert_eq!(
             format!("Transport(base_url: Ok({:?}))", url),
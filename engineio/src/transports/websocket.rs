@@ -1,9 +1,11 @@
+use super::ABORT_CLOSE_TIMEOUT;
 use crate::{
     asynchronous::{
         async_transports::WebsocketTransport as AsyncWebsocketTransport, transport::AsyncTransport,
     },
     error::Result,
-    transport::Transport,
+    pool::BufferPool,
+    transport::{Transport, TransportOptions},
     Error,
 };
 use bytes::Bytes;
@@ -21,11 +23,22 @@ pub struct WebsocketTransport {
 impl WebsocketTransport {
     /// Creates an instance of `WebsocketTransport`.
     pub fn new(base_url: Url, headers: Option<HeaderMap>) -> Result<Self> {
+        Self::with_options(base_url, headers, &TransportOptions::default())
+    }
+
+    /// Creates an instance of `WebsocketTransport` with the given transport `options`.
+    pub(crate) fn with_options(
+        base_url: Url,
+        headers: Option<HeaderMap>,
+        options: &TransportOptions,
+    ) -> Result<Self> {
         let runtime = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()?;
 
-        let inner = runtime.block_on(AsyncWebsocketTransport::new(base_url, headers))?;
+        let inner = runtime.block_on(AsyncWebsocketTransport::with_options(
+            base_url, headers, None, options,
+        ))?;
 
         Ok(WebsocketTransport {
             runtime: Arc::new(runtime),
@@ -38,6 +51,31 @@ impl WebsocketTransport {
     pub(crate) fn upgrade(&self) -> Result<()> {
         self.runtime.block_on(async { self.inner.upgrade().await })
     }
+
+    /// Sets the pool that incoming binary frames are copied into. Only takes
+    /// effect before the transport is cloned.
+    pub(crate) fn set_buffer_pool(&mut self, buffer_pool: BufferPool) {
+        if let Some(inner) = Arc::get_mut(&mut self.inner) {
+            inner.set_buffer_pool(buffer_pool);
+        }
+    }
+
+    /// Starts sending websocket `Ping` frames in the given interval. As the
+    /// transport uses its own single threaded runtime, the frames are sent
+    /// while the client is polling for new packets.
+    pub(crate) fn start_keepalive(&self, interval: Duration) {
+        let _guard = self.runtime.enter();
+        self.inner.start_keepalive(interval)
+    }
+
+    /// Stops the transport and closes the websocket, waiting at most
+    /// one second for the close frame to be sent.
+    pub(crate) fn abort(&self) {
+        self.inner.abort();
+        self.runtime.block_on(async {
+            let _ = tokio::time::timeout(ABORT_CLOSE_TIMEOUT, self.inner.close()).await;
+        });
+    }
 }
 
 impl Transport for WebsocketTransport {
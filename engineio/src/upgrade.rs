@@ -0,0 +1,220 @@
+#[cfg(feature = "websocket")]
+use std::fmt::Debug;
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+#[cfg(feature = "websocket")]
+use url::Url;
+
+use crate::error::Result;
+
+/// Remembers the servers that accepted an upgrade to websocket, like the
+/// `rememberUpgrade` option of the JavaScript client. A store is set via
+/// `ClientBuilder::remember_upgrade`, after which `build` connects to a
+/// remembered server with a websocket right away, handshaking over the
+/// websocket instead of polling first. Servers are identified by the origin of
+/// their url, e.g. `https://example.com:443`.
+///
+/// If the direct websocket fails, the server is forgotten and `build` falls
+/// back to the polling handshake. [`MemoryUpgradeStore`] keeps the servers for
+/// the lifetime of the process, [`FileUpgradeStore`] persists them across
+/// runs, e.g. for command line tools.
+pub trait UpgradeStore: Send + Sync {
+    /// Whether the server at `origin` accepted an upgrade before.
+    fn is_remembered(&self, origin: &str) -> bool;
+
+    /// Called after the server at `origin` accepted an upgrade.
+    fn remember(&self, origin: &str);
+
+    /// Called after the server at `origin` refused a websocket.
+    fn forget(&self, origin: &str);
+}
+
+impl<S: UpgradeStore + ?Sized> UpgradeStore for Arc<S> {
+    fn is_remembered(&self, origin: &str) -> bool {
+        (**self).is_remembered(origin)
+    }
+
+    fn remember(&self, origin: &str) {
+        (**self).remember(origin)
+    }
+
+    fn forget(&self, origin: &str) {
+        (**self).forget(origin)
+    }
+}
+
+/// An [`UpgradeStore`] that keeps the servers in memory. Clones share the
+/// servers, so that one store serves every client of a process.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUpgradeStore {
+    origins: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl MemoryUpgradeStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UpgradeStore for MemoryUpgradeStore {
+    fn is_remembered(&self, origin: &str) -> bool {
+        self.origins
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(origin)
+    }
+
+    fn remember(&self, origin: &str) {
+        self.origins
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(origin.to_owned());
+    }
+
+    fn forget(&self, origin: &str) {
+        self.origins
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(origin);
+    }
+}
+
+/// An [`UpgradeStore`] that persists the servers in a file, one origin per
+/// line. The file is read once on creation and rewritten on every change.
+/// Failing to write it only costs the polling handshake of the next run, so
+/// write errors are ignored.
+#[derive(Debug)]
+pub struct FileUpgradeStore {
+    path: PathBuf,
+    origins: Mutex<BTreeSet<String>>,
+}
+
+impl FileUpgradeStore {
+    /// Loads the servers from the file at `path`, which doesn't need to exist
+    /// yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let origins = match fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => BTreeSet::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(FileUpgradeStore {
+            path,
+            origins: Mutex::new(origins),
+        })
+    }
+
+    fn update(&self, change: impl FnOnce(&mut BTreeSet<String>) -> bool) {
+        let mut origins = self.origins.lock().unwrap_or_else(PoisonError::into_inner);
+        if change(&mut origins) {
+            let contents: String = origins.iter().map(|origin| format!("{origin}\n")).collect();
+            let _ = fs::write(&self.path, contents);
+        }
+    }
+}
+
+impl UpgradeStore for FileUpgradeStore {
+    fn is_remembered(&self, origin: &str) -> bool {
+        self.origins
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(origin)
+    }
+
+    fn remember(&self, origin: &str) {
+        self.update(|origins| origins.insert(origin.to_owned()));
+    }
+
+    fn forget(&self, origin: &str) {
+        self.update(|origins| origins.remove(origin));
+    }
+}
+
+/// The upgrade store of a client builder.
+#[cfg(feature = "websocket")]
+#[derive(Clone)]
+pub(crate) struct SharedUpgradeStore(Arc<dyn UpgradeStore>);
+
+#[cfg(feature = "websocket")]
+impl SharedUpgradeStore {
+    pub(crate) fn new<S: UpgradeStore + 'static>(store: S) -> Self {
+        SharedUpgradeStore(Arc::new(store))
+    }
+
+    /// The key of the server at `url`.
+    pub(crate) fn origin(url: &Url) -> String {
+        url.origin().ascii_serialization()
+    }
+
+    pub(crate) fn is_remembered(&self, url: &Url) -> bool {
+        self.0.is_remembered(&Self::origin(url))
+    }
+
+    pub(crate) fn remember(&self, url: &Url) {
+        self.0.remember(&Self::origin(url))
+    }
+
+    pub(crate) fn forget(&self, url: &Url) {
+        self.0.forget(&Self::origin(url))
+    }
+}
+
+#[cfg(feature = "websocket")]
+impl Debug for SharedUpgradeStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UpgradeStore")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryUpgradeStore::new();
+        let shared = store.clone();
+        assert!(!store.is_remembered("https://example.com"));
+        shared.remember("https://example.com");
+        assert!(store.is_remembered("https://example.com"));
+        shared.forget("https://example.com");
+        assert!(!store.is_remembered("https://example.com"));
+    }
+
+    #[test]
+    #[cfg(feature = "websocket")]
+    fn test_file_store() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("engineio-upgrades-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let url = Url::parse("https://example.com/engine.io/?EIO=4&sid=abc")?;
+
+        let store = SharedUpgradeStore::new(FileUpgradeStore::open(&path)?);
+        assert!(!store.is_remembered(&url));
+        store.remember(&url);
+        store.remember(&Url::parse("http://localhost:4201/")?);
+        assert!(store.is_remembered(&url));
+
+        // survives a restart
+        let reopened = FileUpgradeStore::open(&path)?;
+        assert!(reopened.is_remembered("https://example.com"));
+        assert!(reopened.is_remembered("http://localhost:4201"));
+        reopened.forget("https://example.com");
+        assert!(!FileUpgradeStore::open(&path)?.is_remembered("https://example.com"));
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Measures the encoding of emitted text events with and without the stack
+//! buffer of `small_event_threshold`, for events below and above the
+//! threshold. The client is connected via the in-memory loopback transport,
+//! so the benches neither need a server nor depend on the network.
+use std::{sync::Arc, time::Duration};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+use rust_socketio::asynchronous::{loopback, Client, ClientBuilder};
+use serde_json::json;
+use tokio::runtime::{Builder, Runtime};
+
+/// The threshold of the clients that encode small events on the stack.
+const THRESHOLD: usize = 256;
+
+/// Connects a client whose emits are drained by the loopback server.
+fn client(runtime: &Runtime, threshold: Option<usize>) -> Client {
+    runtime.block_on(async {
+        let (transport, server) = loopback();
+        server
+            .open(Duration::from_secs(300), Duration::from_secs(300))
+            .unwrap();
+        server
+            .send(EnginePacket::new(
+                EnginePacketId::Message,
+                "0{\"sid\":\"bench\"}",
+            ))
+            .unwrap();
+        let mut builder = ClientBuilder::new("http://localhost/").loopback(transport);
+        if let Some(threshold) = threshold {
+            builder = builder.small_event_threshold(threshold);
+        }
+        let client = builder.connect().await.unwrap();
+        while !client.is_connected() {
+            tokio::task::yield_now().await;
+        }
+
+        let server = Arc::new(server);
+        tokio::spawn(async move { while server.receive().await.is_some() {} });
+        client
+    })
+}
+
+fn criterion_emit(c: &mut Criterion) {
+    let runtime = Builder::new_multi_thread().enable_all().build().unwrap();
+    let small = json!({"id": 1, "name": "small event"});
+    let large = json!({"id": 1, "name": "x".repeat(4 * THRESHOLD)});
+
+    for (name, threshold) in [("regular", None), ("stack", Some(THRESHOLD))] {
+        let client = client(&runtime, threshold);
+        c.bench_function(&format!("socket io emit small event {name}"), |b| {
+            b.to_async(&runtime).iter(|| async {
+                client
+                    .emit("small", black_box(small.clone()))
+                    .await
+                    .unwrap()
+            })
+        });
+        c.bench_function(&format!("socket io emit large event {name}"), |b| {
+            b.to_async(&runtime).iter(|| async {
+                client
+                    .emit("large", black_box(large.clone()))
+                    .await
+                    .unwrap()
+            })
+        });
+    }
+}
+
+criterion_group!(benches, criterion_emit);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+//! Measures the state reads and emits of a client while other tasks emit
+//! concurrently. The client is connected via the in-memory loopback
+//! transport, so the benches neither need a server nor depend on the network.
+use std::{sync::Arc, time::Duration};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+use rust_socketio::asynchronous::{loopback, Client, ClientBuilder};
+use serde_json::json;
+use tokio::{
+    runtime::{Builder, Runtime},
+    task::JoinHandle,
+};
+
+/// The number of tasks that emit in the background.
+const EMITTERS: usize = 4;
+
+/// Connects a client and starts the tasks that emit while it's measured.
+fn loaded_client(runtime: &Runtime) -> (Client, Vec<JoinHandle<()>>) {
+    runtime.block_on(async {
+        let (transport, server) = loopback();
+        server
+            .open(Duration::from_secs(300), Duration::from_secs(300))
+            .unwrap();
+        server
+            .send(EnginePacket::new(
+                EnginePacketId::Message,
+                "0{\"sid\":\"bench\"}",
+            ))
+            .unwrap();
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .connect()
+            .await
+            .unwrap();
+        while !client.is_connected() {
+            tokio::task::yield_now().await;
+        }
+
+        let server = Arc::new(server);
+        let mut tasks = vec![tokio::spawn(async move {
+            while server.receive().await.is_some() {}
+        })];
+        for _ in 0..EMITTERS {
+            let client = client.clone();
+            tasks.push(tokio::spawn(async move {
+                while client.emit("load", json!(1)).await.is_ok() {}
+            }));
+        }
+        (client, tasks)
+    })
+}
+
+fn criterion_state(c: &mut Criterion) {
+    let runtime = Builder::new_multi_thread().enable_all().build().unwrap();
+    let (client, tasks) = loaded_client(&runtime);
+
+    c.bench_function("socket io snapshot under concurrent emits", |b| {
+        b.iter(|| black_box(client.snapshot()))
+    });
+    c.bench_function("socket io namespaces under concurrent emits", |b| {
+        b.iter(|| black_box(client.namespaces()))
+    });
+    c.bench_function("socket io emit under concurrent emits", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { client.emit("measured", black_box(json!(1))).await.unwrap() })
+    });
+
+    for task in tasks {
+        task.abort();
+    }
+}
+
+criterion_group!(benches, criterion_state);
+criterion_main!(benches);
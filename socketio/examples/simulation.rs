@@ -0,0 +1,70 @@
+use futures_util::FutureExt;
+use rust_socketio::{asynchronous::ClientBuilder, simulation::MockServer, ReconnectEvent};
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+// the time is paused, so the simulated hours pass in an instant
+#[tokio::main(flavor = "current_thread", start_paused = true)]
+async fn main() {
+    let server = MockServer::new().ping(Duration::from_secs(10), Duration::from_secs(5));
+    let start = Instant::now();
+
+    let client = ClientBuilder::new("http://simulated/")
+        .reconnect_delay(500, 8000)
+        .half_open_probe(Duration::from_secs(5), Duration::from_secs(2))
+        .on_reconnect_event(move |event| {
+            async move {
+                match event {
+                    ReconnectEvent::Attempt(attempt) => {
+                        log(start, &format!("reconnect attempt {attempt}"))
+                    }
+                    ReconnectEvent::Reconnected(_) => log(start, "reconnected"),
+                    _ => {}
+                }
+            }
+            .boxed()
+        })
+        .http_layer(server.clone())
+        .connect()
+        .await
+        .expect("Connection failed");
+
+    // the network fails
+    server.disconnect();
+    sleep(Duration::from_secs(60)).await;
+
+    // the server hangs, the client notices once the ping timeout passed
+    server.stop_pings();
+    sleep(Duration::from_secs(60)).await;
+
+    // a NAT gateway drops the connection silently, the client notices once a
+    // probe isn't answered
+    server.half_open();
+    sleep(Duration::from_secs(60)).await;
+
+    // the server restarts and is down for a while, a storm of reconnects
+    server.refuse_connections(8);
+    server.disconnect();
+    sleep(Duration::from_secs(3600)).await;
+
+    client
+        .emit("status", json!("still here"))
+        .await
+        .expect("Server unreachable");
+    sleep(Duration::from_secs(1)).await;
+    log(
+        start,
+        &format!(
+            "{} handshakes, received {:?}",
+            server.handshakes(),
+            server.received()
+        ),
+    );
+
+    client.disconnect().await.expect("Disconnect failed");
+}
+
+fn log(start: Instant, message: &str) {
+    println!("{:>7.1}s: {message}", start.elapsed().as_secs_f32());
+}
@@ -0,0 +1,87 @@
+use crate::{Event, Payload};
+
+/// The size up to which the payload of a request is retained for its ack by
+/// default, see [`crate::ClientBuilder::ack_request_limit`].
+pub(crate) const DEFAULT_ACK_REQUEST_LIMIT: usize = 64 * 1024;
+
+/// The emit that requested an ack, as passed to the callbacks of
+/// `emit_with_ack_request` alongside the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AckRequest {
+    event: Event,
+    payload: Option<Payload>,
+}
+
+impl AckRequest {
+    /// Retains the given emit, its payload only if it's at most `limit` bytes
+    /// large.
+    pub(crate) fn retain(event: Event, payload: &Payload, limit: usize) -> Self {
+        let payload = (size(payload) <= limit).then(|| payload.clone());
+        AckRequest { event, payload }
+    }
+
+    /// The event that was emitted.
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// The payload that was emitted, `None` if it was larger than the limit
+    /// set via `ClientBuilder::ack_request_limit`.
+    pub fn payload(&self) -> Option<&Payload> {
+        self.payload.as_ref()
+    }
+}
+
+/// Where the async client runs the callback of an ack, see
+/// `asynchronous::ClientBuilder::ack_dispatch`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AckDispatch {
+    /// Runs the callback on the task that receives the packets, as soon as
+    /// the ack arrives. An ack that is awaited, like the one of
+    /// [`crate::DynClient::emit_with_ack`], resumes the awaiting task right
+    /// away, without a hop through another task. The packets behind the ack
+    /// wait until the callback is done, so it should be short.
+    #[default]
+    Inline,
+    /// Spawns a task for the callback, so that slow callbacks don't hold up
+    /// the packets behind the ack, at the cost of scheduling the task.
+    Spawn,
+}
+
+/// The size of the payload as sent, without the packet framing.
+fn size(payload: &Payload) -> usize {
+    match payload {
+        Payload::Binary(data) => data.len(),
+        Payload::Text(values) => values
+            .iter()
+            .map(|value| serde_json::to_vec(value).map_or(0, |encoded| encoded.len()))
+            .sum(),
+        #[allow(deprecated)]
+        Payload::String(string) => string.len(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_retain() {
+        let payload = Payload::Text(vec![json!({"id": 1})]);
+        let request = AckRequest::retain(Event::from("get"), &payload, 8);
+        assert_eq!(request.event(), &Event::from("get"));
+        assert_eq!(request.payload(), Some(&payload));
+
+        let request = AckRequest::retain(Event::from("get"), &payload, 7);
+        assert_eq!(request.event(), &Event::from("get"));
+        assert_eq!(request.payload(), None);
+
+        let binary = Payload::Binary(Bytes::from_static(&[1, 2, 3]));
+        assert!(AckRequest::retain(Event::from("put"), &binary, 3)
+            .payload()
+            .is_some());
+    }
+}
@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use crate::asynchronous::client::callback::Callback;
+use crate::{emit::AckFailure, Event, Payload};
 use tokio::time::Instant;
 
 use super::callback::DynAsyncCallback;
@@ -15,4 +16,46 @@ pub(crate) struct Ack {
     pub timeout: Duration,
     pub time_started: Instant,
     pub callback: Callback<DynAsyncCallback>,
+    pub event: Event,
+    // the payload to emit again after a reconnect instead of failing the ack
+    pub resend: Option<Payload>,
+    // the sequence number the emit keeps when it's resent
+    pub sequence: Option<u64>,
+    // completes the ack instead of the error callbacks if it can't be received
+    pub on_failure: Option<AckFailure>,
+}
+
+impl Ack {
+    /// Whether the server took longer than the timeout to answer. The time is
+    /// read from tokio, so tests can pause and advance it.
+    pub(crate) fn timed_out(&self) -> bool {
+        self.time_started.elapsed() >= self.timeout
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timed_out() {
+        let ack = Ack {
+            id: 0,
+            timeout: Duration::from_secs(5),
+            time_started: Instant::now(),
+            callback: Callback::<DynAsyncCallback>::new(|_, _| async {}.boxed()),
+            event: Event::from("test"),
+            resend: None,
+            sequence: None,
+            on_failure: None,
+        };
+        assert!(!ack.timed_out());
+
+        tokio::time::advance(Duration::from_millis(4999)).await;
+        assert!(!ack.timed_out());
+        tokio::time::advance(Duration::from_millis(1)).await;
+        assert!(ack.timed_out());
+    }
 }
@@ -1,50 +1,149 @@
 use futures_util::future::BoxFuture;
-use log::trace;
+use log::{trace, warn};
 use native_tls::TlsConnector;
+use rand::{thread_rng, Rng};
+#[cfg(any(test, feature = "test-support"))]
+use rust_engineio::asynchronous::async_transports::{Chaos, LoopbackTransport, MockTransport};
+#[cfg(feature = "tower")]
+use rust_engineio::asynchronous::{HttpRequest, HttpResponse, HttpService};
+#[cfg(feature = "websocket")]
+use rust_engineio::UpgradeStore;
 use rust_engineio::{
-    asynchronous::ClientBuilder as EngineIoClientBuilder,
+    asynchronous::{
+        transport::AsyncTransport, Client as EngineClient, ClientBuilder as EngineIoClientBuilder,
+        PreparedConnection,
+    },
     header::{HeaderMap, HeaderValue},
+    split_zone, Resolver,
 };
-use std::collections::HashMap;
+use std::{net::IpAddr, sync::Arc, time::Duration};
+#[cfg(feature = "tower")]
+use tower::{BoxError, Layer, Service};
 use url::Url;
 
-use crate::{error::Result, Event, Payload, TransportType};
+#[cfg(feature = "websocket")]
+use crate::WriteRetry;
+use crate::{
+    bearer::AUTHORIZATION, error::Result, AckDispatch, BackoffStrategy, BearerAuth, BufferOverflow,
+    EmitPolicy, EngineEvent, Error, Event, Failover, HttpProtocol, NamespaceStatus, Payload,
+    PayloadCodec, PollRetry, QueryValue, ReconnectEvent, RedactedView, ServerAddresses,
+    TransportKind, TransportType, UnhandledPolicy, Utf8Policy,
+};
 
 use super::{
+    bus::EventBus,
     callback::{
-        Callback, DynAsyncAnyCallback, DynAsyncCallback, DynAsyncReconnectSettingsCallback,
+        AfterConnectHook, BeforeConnectHook, Callback, DynAsyncAnyCallback, DynAsyncCallback,
+        DynAsyncErrorCallback, DynAsyncReconnectSettingsCallback,
     },
     client::{Client, ReconnectSettings},
+    dispatcher::Dispatcher,
+    watchdog::{AsyncOverloadHandler, OverloadPolicy},
 };
+use crate::ack::DEFAULT_ACK_REQUEST_LIMIT;
 use crate::asynchronous::socket::Socket as InnerSocket;
+use crate::codec::Codecs;
+use crate::emit::{Coalescer, SequenceCounter, DEFAULT_EMIT_QUEUE_CAPACITY};
+use crate::engine_event::{AsyncEngineEventHandler, AsyncTransportChangeHandler};
+use crate::failover::Endpoints;
+use crate::handlers::EventHandlers;
+use crate::idle::{ActivityTracker, AsyncBeforeIdleCloseHandler};
+use crate::namespace::AsyncNamespaceStateHandler;
+use crate::reconnect::AsyncReconnectEventHandler;
+use crate::redact::Redaction;
+use crate::schema::EventSchemas;
+
+/// Builds the engine.io client over a loopback, mock or custom transport.
+type InMemoryTransport =
+    Arc<dyn Fn(EngineIoClientBuilder) -> BoxFuture<'static, Result<EngineClient>> + Send + Sync>;
 
 /// A builder class for a `socket.io` socket. This handles setting up the client and
 /// configuring the callback, the namespace and metadata of the socket. If no
 /// namespace is specified, the default namespace `/` is taken. The `connect` method
 /// acts the `build` method and returns a connected [`Client`].
 pub struct ClientBuilder {
-    pub(crate) address: String,
-    pub(crate) on: HashMap<Event, Callback<DynAsyncCallback>>,
+    pub(crate) endpoints: Endpoints,
+    pub(crate) on: EventHandlers<Callback<DynAsyncCallback>>,
     pub(crate) on_any: Option<Callback<DynAsyncAnyCallback>>,
+    pub(crate) on_unhandled: Option<Callback<DynAsyncAnyCallback>>,
+    pub(crate) on_error: Option<Callback<DynAsyncErrorCallback>>,
     pub(crate) on_reconnect: Option<Callback<DynAsyncReconnectSettingsCallback>>,
+    pub(crate) codecs: Codecs,
+    pub(crate) schemas: EventSchemas,
     pub(crate) namespace: String,
     tls_config: Option<TlsConnector>,
     opening_headers: Option<HeaderMap>,
-    transport_type: TransportType,
+    query: Vec<(String, QueryValue)>,
+    transports: Vec<TransportType>,
+    #[cfg(feature = "websocket")]
+    websocket_ping_interval: Option<Duration>,
+    #[cfg(feature = "websocket")]
+    websocket_write_retry: Option<WriteRetry>,
+    #[cfg(feature = "websocket")]
+    upgrade_store: Option<Arc<dyn UpgradeStore>>,
+    half_open_probe: Option<(Duration, Duration)>,
+    #[cfg(any(test, feature = "test-support"))]
+    chaos: Option<Chaos>,
+    buffer_pool_size: Option<usize>,
+    emit_timing: bool,
+    http_protocol: HttpProtocol,
+    poll_retry: Option<PollRetry>,
+    max_payload_size: Option<usize>,
+    #[cfg(feature = "tower")]
+    http_layers: Vec<Arc<dyn Fn(EngineIoClientBuilder) -> EngineIoClientBuilder + Send + Sync>>,
+    in_memory: Option<InMemoryTransport>,
+    local_address: Option<IpAddr>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    interface: Option<String>,
+    resolver: Option<Arc<dyn Resolver>>,
+    prepared: Option<PreparedConnection>,
     pub(crate) auth: Option<serde_json::Value>,
+    pub(crate) bearer_auth: Option<BearerAuth>,
+    pub(crate) origin: Option<String>,
+    pub(crate) sequence: Option<SequenceCounter>,
+    pub(crate) dispatcher: Option<Dispatcher>,
+    pub(crate) event_buses: Vec<EventBus>,
+    pub(crate) redaction: Redaction,
+    pub(crate) small_event_threshold: Option<usize>,
+    pub(crate) ack_request_limit: usize,
+    pub(crate) ack_dispatch: AckDispatch,
+    pub(crate) callback_watchdog: Option<(usize, OverloadPolicy)>,
+    pub(crate) on_overload: Option<AsyncOverloadHandler>,
+    on_engineio: Option<AsyncEngineEventHandler>,
+    pub(crate) on_namespace_state_change: Option<AsyncNamespaceStateHandler>,
+    pub(crate) on_reconnect_event: Option<AsyncReconnectEventHandler>,
+    pub(crate) on_transport_change: Option<AsyncTransportChangeHandler>,
+    pub(crate) before_connect: Vec<BeforeConnectHook>,
+    pub(crate) after_connect: Vec<AfterConnectHook>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) activity: Option<ActivityTracker>,
+    pub(crate) on_before_idle_close: Option<AsyncBeforeIdleCloseHandler>,
     pub(crate) reconnect: bool,
     pub(crate) reconnect_on_disconnect: bool,
     // None implies infinite attempts
     pub(crate) max_reconnect_attempts: Option<u8>,
     pub(crate) reconnect_delay_min: u64,
     pub(crate) reconnect_delay_max: u64,
+    pub(crate) backoff: Option<Arc<dyn BackoffStrategy>>,
+    pub(crate) emit_policy: EmitPolicy,
+    pub(crate) resend_on_reconnect: bool,
+    pub(crate) coalescer: Coalescer,
+    pub(crate) emit_buffer_limit: Option<(usize, BufferOverflow)>,
+    pub(crate) emit_queue_capacity: usize,
+    pub(crate) unhandled_policy: UnhandledPolicy,
+    pub(crate) utf8_policy: Utf8Policy,
 }
 
 impl ClientBuilder {
     /// Create as client builder from a URL. URLs must be in the form
     /// `[ws or wss or http or https]://[domain]:[port]/[path]`. The
     /// path of the URL is optional and if no port is given, port 80
-    /// will be used.
+    /// will be used. Link-local IPv6 addresses may carry a zone, like
+    /// `http://[fe80::1%eth0]:3000`.
+    ///
+    /// A list of URLs lets the client fail over to the next server if the
+    /// handshake with one fails, see [`ServerAddresses`] and
+    /// [`ClientBuilder::failover`].
     /// # Example
     /// ```rust
     /// use rust_socketio::{Payload, asynchronous::{ClientBuilder, Client}};
@@ -80,24 +179,156 @@ impl ClientBuilder {
     ///     assert!(result.is_ok());
     /// }
     /// ```
-    pub fn new<T: Into<String>>(address: T) -> Self {
+    pub fn new<T: Into<ServerAddresses>>(address: T) -> Self {
         Self {
-            address: address.into(),
-            on: HashMap::new(),
+            endpoints: Endpoints::new(address.into()),
+            on: EventHandlers::default(),
             on_any: None,
+            on_unhandled: None,
+            on_error: None,
             on_reconnect: None,
+            codecs: Codecs::default(),
+            schemas: EventSchemas::default(),
             namespace: "/".to_owned(),
             tls_config: None,
             opening_headers: None,
-            transport_type: TransportType::Any,
+            query: Vec::new(),
+            transports: vec![TransportType::Any],
+            #[cfg(feature = "websocket")]
+            websocket_ping_interval: None,
+            #[cfg(feature = "websocket")]
+            websocket_write_retry: None,
+            #[cfg(feature = "websocket")]
+            upgrade_store: None,
+            half_open_probe: None,
+            #[cfg(any(test, feature = "test-support"))]
+            chaos: None,
+            buffer_pool_size: None,
+            emit_timing: false,
+            http_protocol: HttpProtocol::default(),
+            poll_retry: None,
+            max_payload_size: None,
+            #[cfg(feature = "tower")]
+            http_layers: Vec::new(),
+            in_memory: None,
+            local_address: None,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            interface: None,
+            resolver: None,
+            prepared: None,
             auth: None,
+            bearer_auth: None,
+            origin: None,
+            sequence: None,
+            dispatcher: None,
+            event_buses: Vec::new(),
+            redaction: Redaction::default(),
+            small_event_threshold: None,
+            ack_request_limit: DEFAULT_ACK_REQUEST_LIMIT,
+            ack_dispatch: AckDispatch::default(),
+            callback_watchdog: None,
+            on_overload: None,
+            on_engineio: None,
+            on_namespace_state_change: None,
+            on_reconnect_event: None,
+            on_transport_change: None,
+            before_connect: Vec::new(),
+            after_connect: Vec::new(),
+            idle_timeout: None,
+            activity: None,
+            on_before_idle_close: None,
             reconnect: true,
             reconnect_on_disconnect: false,
             // None implies infinite attempts
             max_reconnect_attempts: None,
             reconnect_delay_min: 1000,
             reconnect_delay_max: 5000,
+            backoff: None,
+            emit_policy: EmitPolicy::default(),
+            resend_on_reconnect: false,
+            coalescer: Coalescer::default(),
+            emit_buffer_limit: None,
+            emit_queue_capacity: DEFAULT_EMIT_QUEUE_CAPACITY,
+            unhandled_policy: UnhandledPolicy::default(),
+            utf8_policy: Utf8Policy::default(),
+        }
+    }
+
+    /// Applies a preset for low-latency traffic that tolerates losses, e.g.
+    /// telemetry or cursor positions, which are worthless once they're stale:
+    /// - emits fail right away while the client reconnects
+    ///   ([`EmitPolicy::FailFast`]) and pending acks aren't resent,
+    /// - the client reconnects quickly, every 100ms to 1s without a limit,
+    /// - long-poll requests and failed websocket writes aren't retried and,
+    ///   with the `websocket` feature, the websocket transport is used right
+    ///   away instead of upgrading a polling connection.
+    ///
+    /// Settings made after the preset override it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .realtime()
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn realtime(mut self) -> Self {
+        self.emit_policy = EmitPolicy::FailFast;
+        self.resend_on_reconnect = false;
+        self.reconnect = true;
+        self.reconnect_on_disconnect = false;
+        self.max_reconnect_attempts = None;
+        self.poll_retry = Some(PollRetry::disabled());
+        #[cfg(feature = "websocket")]
+        {
+            self.transports = vec![TransportType::Websocket];
+            self.websocket_write_retry = Some(WriteRetry::disabled());
         }
+        self.reconnect_delay(100, 1000)
+    }
+
+    /// Applies a preset for command channels that must not lose emits:
+    /// - emits are buffered while the client reconnects and sent afterwards
+    ///   ([`EmitPolicy::Buffer`]),
+    /// - emits whose ack is pending when the connection drops are resent after
+    ///   the reconnect (see [`ClientBuilder::resend_on_reconnect`]),
+    /// - the client reconnects without a limit, also after the server closed
+    ///   the connection, backing off from 500ms to 30s,
+    /// - long-poll requests are retried on transient gateway errors and the
+    ///   transport falls back to polling if websockets are unavailable.
+    ///
+    /// As resent emits may arrive twice, combine the preset with
+    /// [`ClientBuilder::sequence_numbers`] to let the server drop duplicates.
+    /// Settings made after the preset override it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .reliable()
+    ///         // settings after the preset override it
+    ///         .reconnect_delay(200, 10_000)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn reliable(mut self) -> Self {
+        self.emit_policy = EmitPolicy::Buffer;
+        self.resend_on_reconnect = true;
+        self.reconnect = true;
+        self.reconnect_on_disconnect = true;
+        self.max_reconnect_attempts = None;
+        self.poll_retry = Some(PollRetry::new(5, Duration::from_millis(200)));
+        self.transports = vec![TransportType::Any];
+        self.reconnect_delay(500, 30_000)
     }
 
     /// Sets the target namespace of the client. The namespace should start
@@ -116,7 +347,8 @@ impl ClientBuilder {
 
     /// Registers a new callback for a certain [`crate::event::Event`]. The event could either be
     /// one of the common events like `message`, `error`, `open`, `close` or a custom
-    /// event defined by a string, e.g. `onPayment` or `foo`.
+    /// event defined by a string, e.g. `onPayment` or `foo`. Several callbacks can be
+    /// registered for the same event, they are called in the order of registration.
     ///
     /// # Example
     /// ```rust
@@ -191,8 +423,84 @@ impl ClientBuilder {
             + Send
             + Sync,
     {
-        self.on
-            .insert(event.into(), Callback::<DynAsyncCallback>::new(callback));
+        self.on.add(
+            event.into(),
+            Callback::<DynAsyncCallback>::new(callback),
+            false,
+        );
+        self
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] that is removed
+    /// after it was called once.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, Payload};
+    /// use futures_util::FutureExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .once("welcome", |payload: Payload, _| {
+    ///             async move { println!("Welcome: {:#?}", payload) }.boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    #[cfg(feature = "async-callbacks")]
+    pub fn once<T: Into<Event>, F>(mut self, event: T, callback: F) -> Self
+    where
+        F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
+            + 'static
+            + Send
+            + Sync,
+    {
+        self.on.add(
+            event.into(),
+            Callback::<DynAsyncCallback>::new(callback),
+            true,
+        );
+        self
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] that receives
+    /// protobuf messages of type `M`, as sent by [`Client::emit_proto`]. Available
+    /// with the `prost` feature.
+    ///
+    /// The binary attachment of the event is decoded as `M`, the type name that
+    /// is sent along isn't checked. Attachments that fail to decode are reported
+    /// to the callbacks of [`crate::event::Event::Error`].
+    #[cfg(all(feature = "async-callbacks", feature = "prost"))]
+    pub fn on_proto<M>(
+        self,
+        event: impl Into<Event>,
+        mut callback: impl FnMut(M, Client) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    ) -> Self
+    where
+        M: prost::Message + Default,
+    {
+        self.on(
+            event,
+            move |payload: Payload, socket: Client| match crate::proto::decode::<M>(&payload) {
+                Ok(message) => callback(message, socket),
+                Err(err) => Box::pin(async move {
+                    let _ = socket.error(&err).await;
+                }),
+            },
+        )
+    }
+
+    /// Removes all callbacks that were registered for a certain [`crate::event::Event`].
+    pub fn off<T: Into<Event>>(mut self, event: T) -> Self {
+        self.on.remove(&event.into());
+        self
+    }
+
+    /// Removes the callbacks of all events. The `on_any` callback is kept.
+    pub fn off_all(mut self) -> Self {
+        self.on.clear();
         self
     }
 
@@ -262,6 +570,96 @@ impl ClientBuilder {
         self
     }
 
+    /// Registers a callback for all [`crate::event::Event::Custom`] and
+    /// [`crate::event::Event::Message`] that are received without a handler,
+    /// i.e. that neither have a callback of their own nor are caught by
+    /// [`ClientBuilder::on_any`]. This helps to detect events the server added
+    /// or renamed. See [`ClientBuilder::unhandled_policy`] for what happens to
+    /// these events besides.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use futures_util::future::FutureExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let client = ClientBuilder::new("http://localhost:4200/")
+    ///         .on_unhandled(|event, _payload, _client| {
+    ///             async move { eprintln!("no handler for {event}") }.boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn on_unhandled<F>(mut self, callback: F) -> Self
+    where
+        F: for<'a> FnMut(Event, Payload, Client) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_unhandled = Some(Callback::<DynAsyncAnyCallback>::new(callback));
+        self
+    }
+
+    /// Registers a callback for the errors of the client, which receives them
+    /// as typed [`crate::Error`] values that can be matched on. It's called
+    /// before the callbacks of [`Event::Error`], which receive the errors as
+    /// strings. Errors the server sends in a `ConnectError` packet are only
+    /// passed to the latter.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::{asynchronous::ClientBuilder, Error};
+    ///
+    /// let builder = ClientBuilder::new("http://localhost:4200/").on_error(|err, _client| {
+    ///     let retry = matches!(err, Error::Disconnected(_));
+    ///     async move { println!("ack lost, retry: {retry}") }.boxed()
+    /// });
+    /// ```
+    pub fn on_error<F>(mut self, callback: F) -> Self
+    where
+        F: for<'a> FnMut(&'a Error, Client) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_error = Some(Callback::<DynAsyncErrorCallback>::new(callback));
+        self
+    }
+
+    /// Sets the [`UnhandledPolicy`] that defines what happens to received events
+    /// without a handler, see [`ClientBuilder::on_unhandled`].
+    /// Defaults to [`UnhandledPolicy::Ignore`].
+    pub fn unhandled_policy(mut self, policy: UnhandledPolicy) -> Self {
+        self.unhandled_policy = policy;
+        self
+    }
+
+    /// Sets the [`Utf8Policy`] that defines what happens to received text
+    /// packets that aren't valid UTF-8. Defaults to [`Utf8Policy::Reject`].
+    /// The policy only applies to polling, see [`Utf8Policy`].
+    pub fn utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Registers a codec for the payloads of a certain [`crate::event::Event`], which
+    /// are then sent and received as binary attachments instead of JSON. See
+    /// [`PayloadCodec`] for an example.
+    pub fn codec<T: Into<Event>, C>(mut self, event: T, codec: C) -> Self
+    where
+        C: PayloadCodec + 'static,
+    {
+        self.codecs.insert(event.into(), Arc::new(codec));
+        self
+    }
+
+    /// Sets the versioned schemas of events, which tag emits with their version
+    /// and migrate and validate received payloads. Received events that don't
+    /// match their schema are reported as [`Error::InvalidSchemaPayload`] and
+    /// dropped. See [`EventSchemas`] for an example.
+    pub fn schemas(mut self, schemas: EventSchemas) -> Self {
+        self.schemas = schemas;
+        self
+    }
+
     /// Uses a preconfigured TLS connector for secure communication. This configures
     /// both the `polling` as well as the `websocket` transport type.
     /// # Example
@@ -322,6 +720,60 @@ impl ClientBuilder {
         self
     }
 
+    /// Adds the headers of an [`http::HeaderMap`] to the headers of the
+    /// opening request, see [`ClientBuilder::opening_header`]. Headers that
+    /// were set before are replaced, the values of a repeated header are
+    /// joined into one, separated by commas, or by semicolons for `Cookie`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http::{header, HeaderMap, HeaderValue};
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut headers = HeaderMap::new();
+    ///     headers.insert(header::USER_AGENT, HeaderValue::from_static("dashboard/1.0"));
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .opening_headers(headers)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn opening_headers(mut self, headers: http::HeaderMap) -> Self {
+        let map = self.opening_headers.get_or_insert_with(HeaderMap::default);
+        for (key, value) in HeaderMap::from(headers) {
+            map.insert(key, value);
+        }
+        self
+    }
+
+    /// Appends a query parameter to the URL of the server, e.g. a room or a
+    /// client version that the server reads from the handshake. The value is
+    /// converted via [`QueryValue`] and percent-encoded, so that numbers,
+    /// booleans, spaces and unicode arrive as they were given. Parameters in
+    /// the URL passed to [`ClientBuilder::new`] are kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // connects to /socket.io/?room=caf%C3%A9+1&limit=20&compact=true&EIO=4...
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .query("room", "café 1")
+    ///         .query("limit", 20)
+    ///         .query("compact", true)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn query<K: Into<String>, V: Into<QueryValue>>(mut self, key: K, value: V) -> Self {
+        self.query.push((key.into(), value.into()));
+        self
+    }
+
     /// Sets authentification data sent in the opening request.
     /// # Example
     /// ```rust
@@ -345,120 +797,1340 @@ impl ClientBuilder {
         self
     }
 
-    /// Specifies which EngineIO [`TransportType`] to use.
+    /// Authenticates the connections with a bearer token in the `Authorization`
+    /// header of every request and websocket upgrade. The client reconnects
+    /// once the token is rotated, see [`BearerAuth`].
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, BearerAuth};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .bearer_auth(BearerAuth::new(|| String::from("secret-token")))
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn bearer_auth(mut self, auth: BearerAuth) -> Self {
+        self.bearer_auth = Some(auth);
+        self
+    }
+
+    /// If set to `true`, every text payload emitted by this client is tagged with a
+    /// random id that identifies the client. Events that carry the client's own tag
+    /// (e.g. broadcasts the server sends back to all room members including the sender)
+    /// are dropped instead of being passed to the registered callbacks. Tags of other
+    /// clients are removed from the payload before the callbacks are called.
+    /// Binary payloads can't be tagged and are therefore never suppressed,
+    /// neither are emits that request an ack, as the tag would take the place
+    /// of the ack callback in the handler of the server. Defaults to `false`.
     ///
     /// # Example
     /// ```rust
-    /// use rust_socketio::{asynchronous::ClientBuilder, TransportType};
+    /// use rust_socketio::asynchronous::ClientBuilder;
     ///
     /// #[tokio::main]
     /// async fn main() {
     ///     let socket = ClientBuilder::new("http://localhost:4200/")
-    ///         // Use websockets to handshake and connect.
-    ///         .transport_type(TransportType::Websocket)
+    ///         .suppress_echo(true)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn suppress_echo(mut self, suppress_echo: bool) -> Self {
+        self.origin = if suppress_echo {
+            Some(format!("{:016x}", thread_rng().gen::<u64>()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Tags every text payload emitted by this client with a sequence number,
+    /// so that the server can detect lost and duplicated emits, e.g. around a
+    /// reconnect. The number is carried in a trailing argument
+    /// `{"__rust_socketio_seq": n}`. Numbers are assigned in the order the
+    /// emits are sent, so buffered emits get theirs once they're flushed after
+    /// the reconnect, while emits that are resent (see
+    /// [`crate::EmitOptions::resend_on_reconnect`]) keep theirs. The first emit
+    /// gets `next`, pass the value of `Client::next_sequence` that was
+    /// persisted by a previous run to continue its numbers. Disabled by default.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // continue the numbers of the previous run
+    ///     let next = 1337;
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .sequence_numbers(next)
     ///         .connect()
     ///         .await
-    ///         .expect("connection failed");
+    ///         .expect("Connection failed");
+    ///
+    ///     // ... persist `socket.next_sequence()` on shutdown
     /// }
     /// ```
-    pub fn transport_type(mut self, transport_type: TransportType) -> Self {
-        self.transport_type = transport_type;
+    pub fn sequence_numbers(mut self, next: u64) -> Self {
+        self.sequence = Some(SequenceCounter::new(next));
+        self
+    }
 
+    /// Calls the callbacks of this client on the given [`Dispatcher`], which
+    /// preserves the arrival order of events across all clients sharing it, e.g.
+    /// clients connected to different namespaces. By default every client calls
+    /// its callbacks independently, in parallel to other clients.
+    pub fn dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = Some(dispatcher);
         self
     }
 
-    /// If set to `false` do not try to reconnect on network errors. Defaults to
-    /// `true`
-    pub fn reconnect(mut self, reconnect: bool) -> Self {
-        self.reconnect = reconnect;
+    /// Forwards the events this client receives to the subscribers of the given
+    /// [`EventBus`], in addition to the callbacks. Can be called several times,
+    /// e.g. with differently filtered buses.
+    pub fn event_bus(mut self, bus: EventBus) -> Self {
+        self.event_buses.push(bus);
         self
     }
 
-    /// If set to `true` try to reconnect when the server disconnects the
-    /// client. Defaults to `false`
-    pub fn reconnect_on_disconnect(mut self, reconnect_on_disconnect: bool) -> Self {
-        self.reconnect_on_disconnect = reconnect_on_disconnect;
+    /// Registers a hook that decides what the logs show of the payloads this
+    /// client sends and receives. Without a hook, only the shape of a payload is
+    /// logged, see [`RedactedView::summary`].
+    pub fn redact<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Event, &Payload) -> RedactedView + 'static + Send + Sync,
+    {
+        self.redaction = Redaction::new(Arc::new(hook));
         self
     }
 
-    /// Sets the minimum and maximum delay between reconnection attempts
-    pub fn reconnect_delay(mut self, min: u64, max: u64) -> Self {
-        self.reconnect_delay_min = min;
-        self.reconnect_delay_max = max;
+    /// Encodes text events whose encoded packet is at most `bytes` long in a
+    /// buffer on the stack, instead of building the intermediate strings of the
+    /// regular packet encoding. This saves allocations for clients that emit
+    /// many small events. Events with binary payloads or acks are always encoded
+    /// the regular way. Disabled by default.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         // most events of this client are small status updates
+    ///         .small_event_threshold(128)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn small_event_threshold(mut self, bytes: usize) -> Self {
+        self.small_event_threshold = Some(bytes);
         self
     }
 
-    /// Sets the maximum number of times to attempt reconnections. Defaults to
-    /// an infinite number of attempts
-    pub fn max_reconnect_attempts(mut self, reconnect_attempts: u8) -> Self {
-        self.max_reconnect_attempts = Some(reconnect_attempts);
+    /// Limits the size of the payloads that are retained for the callbacks of
+    /// `emit_with_ack_request`. The payload of a larger request isn't passed
+    /// to its callback, only its event. Defaults to 64 KiB.
+    pub fn ack_request_limit(mut self, bytes: usize) -> Self {
+        self.ack_request_limit = bytes;
         self
     }
 
-    /// Connects the socket to a certain endpoint. This returns a connected
-    /// [`Client`] instance. This method returns an [`std::result::Result::Err`]
-    /// value if something goes wrong during connection. Also starts a separate
-    /// thread to start polling for packets. Used with callbacks.
+    /// Sets where the callbacks of acks run. Defaults to
+    /// [`AckDispatch::Inline`], which answers request/response flows with the
+    /// least latency, [`AckDispatch::Spawn`] keeps slow callbacks from
+    /// delaying the events received after the ack.
+    ///
     /// # Example
     /// ```rust
-    /// use rust_socketio::{asynchronous::ClientBuilder, Payload};
-    /// use serde_json::json;
-    /// use futures_util::future::FutureExt;
+    /// use rust_socketio::{asynchronous::ClientBuilder, AckDispatch};
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut socket = ClientBuilder::new("http://localhost:4200/")
-    ///         .namespace("/admin")
-    ///         .on("error", |err, _| async move { eprintln!("Error: {:#?}", err) }.boxed())
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .ack_dispatch(AckDispatch::Spawn)
     ///         .connect()
-    ///         .await
-    ///         .expect("connection failed");
-    ///
-    ///     // use the socket
-    ///     let json_payload = json!({"token": 123});
+    ///         .await;
+    /// }
+    /// ```
+    pub fn ack_dispatch(mut self, dispatch: AckDispatch) -> Self {
+        self.ack_dispatch = dispatch;
+        self
+    }
+
+    /// Watches the callbacks of this client that run on tasks of their own,
+    /// i.e. the ones queued on a [`Dispatcher`] and the acks of
+    /// [`AckDispatch::Spawn`]. Once more than `high_water` of them are in
+    /// flight, e.g. as a handler stalls on an external dependency, `policy`
+    /// keeps them from piling up. Callbacks that run inline are not counted,
+    /// as they hold up reading from the transport anyway.
     ///
-    ///     let result = socket.emit("foo", json_payload).await;
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::{
+    ///     asynchronous::{ClientBuilder, Dispatcher, OverloadPolicy},
+    ///     AckDispatch,
+    /// };
     ///
-    ///     assert!(result.is_ok());
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .dispatcher(Dispatcher::new())
+    ///         .ack_dispatch(AckDispatch::Spawn)
+    ///         .callback_watchdog(1000, OverloadPolicy::DropVolatile)
+    ///         .on_overload(|in_flight| {
+    ///             async move { eprintln!("{in_flight} callbacks in flight") }.boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
     /// }
     /// ```
-    pub async fn connect(self) -> Result<Client> {
-        let mut socket = self.connect_manual().await?;
-        socket.poll_stream().await?;
-
-        Ok(socket)
+    pub fn callback_watchdog(mut self, high_water: usize, policy: OverloadPolicy) -> Self {
+        self.callback_watchdog = Some((high_water, policy));
+        self
     }
 
-    /// Creates a new Socket that can be used for reconnections
-    pub(crate) async fn inner_create(&self) -> Result<InnerSocket> {
-        let mut url = Url::parse(&self.address)?;
-
-        if url.path() == "/" {
-            url.set_path("/socket.io/");
-        }
-
-        let mut builder = EngineIoClientBuilder::new(url);
+    /// Registers a handler that is called with the number of in-flight
+    /// callbacks whenever the client becomes overloaded, see
+    /// [`ClientBuilder::callback_watchdog`].
+    pub fn on_overload<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(usize) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_overload = Some(Arc::new(handler));
+        self
+    }
 
+    /// Registers a handler for the events of the underlying `engine.io`
+    /// connection, e.g. to observe the transport upgrade or the pings of the
+    /// server. The handler is called for every packet the connection receives,
+    /// including the ones carrying `socket.io` packets. Pings are answered by
+    /// the connection itself, so there is no event for the pong.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::{asynchronous::ClientBuilder, EngineEvent};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .on_engineio(|event| {
+    ///             async move {
+    ///                 if event == EngineEvent::Upgrade {
+    ///                     println!("upgraded to websocket");
+    ///                 }
+    ///             }
+    ///             .boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn on_engineio<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(EngineEvent) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_engineio = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called whenever the state of a namespace of
+    /// the client changes, e.g. once the server accepted or refused the
+    /// connection. The current states are returned by `Client::namespaces`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::{asynchronous::ClientBuilder, NamespaceState};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .namespace("/admin")
+    ///         .on_namespace_state_change(|nsp, status| {
+    ///             async move {
+    ///                 if status.state == NamespaceState::Failed {
+    ///                     println!("{nsp} failed: {:?}", status.last_error);
+    ///                 }
+    ///             }
+    ///             .boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn on_namespace_state_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(String, NamespaceStatus) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_namespace_state_change = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called with the progress of the client
+    /// while it reconnects: before every attempt, after every failed one and
+    /// once it reconnected.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::{asynchronous::ClientBuilder, ReconnectEvent};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .on_reconnect_event(|event| {
+    ///             async move {
+    ///                 if let ReconnectEvent::Error(err) = event {
+    ///                     println!("failed to reconnect: {err}");
+    ///                 }
+    ///             }
+    ///             .boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn on_reconnect_event<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(ReconnectEvent) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_reconnect_event = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called with the transport the client is
+    /// connected over whenever it changes, i.e. on the first connect and when
+    /// a reconnect ends up on another transport than before, e.g. because the
+    /// client fell back to polling. As the `engine.io` connection is upgraded
+    /// while it's opened, the handler is called with the transport after the
+    /// upgrade. The current transport is returned by `Client::snapshot`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .on_transport_change(|transport| {
+    ///             async move { println!("connected over {transport:?}") }.boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn on_transport_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(TransportKind) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.on_transport_change = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a hook that is awaited before every connect and reconnect,
+    /// before the connection to the server is opened, e.g. to fetch a fresh
+    /// token that the closure of [`BearerAuth::new`] returns. Several hooks
+    /// are awaited in the order they were registered.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let token = Arc::new(Mutex::new(String::new()));
+    ///     let token_clone = token.clone();
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .before_connect(move || {
+    ///             let token = token_clone.clone();
+    ///             async move {
+    ///                 // e.g. requested from an identity provider
+    ///                 *token.lock().unwrap() = String::from("fresh-token");
+    ///             }
+    ///             .boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn before_connect<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.before_connect.push(Arc::new(hook));
+        self
+    }
+
+    /// Registers a hook that is awaited after every connect and reconnect,
+    /// once the server accepted the connection to the namespace, e.g. to
+    /// subscribe to server-side topics again. The hooks are awaited in the
+    /// order they were registered, after the emits buffered while reconnecting
+    /// were sent and before the callbacks of [`Event::Connect`] are called.
+    /// Packets are handled once the hooks returned, so a hook must not wait
+    /// for a response of the server.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use serde_json::json;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .after_connect(|client| {
+    ///             async move {
+    ///                 for topic in ["prices", "news"] {
+    ///                     let _ = client.emit("subscribe", json!(topic)).await;
+    ///                 }
+    ///             }
+    ///             .boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn after_connect<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(Client) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+    {
+        self.after_connect.push(Arc::new(hook));
+        self
+    }
+
+    /// Emits the given subscription events again after every connect and
+    /// reconnect, once the server accepted the connection to the namespace,
+    /// so a server that lost its state after a restart or failover still
+    /// delivers the subscribed topics. The events are emitted in the given
+    /// order by an [`after_connect`](ClientBuilder::after_connect) hook, which
+    /// reports failed emits to the error callback.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use serde_json::json;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .resubscribe_on_connect(vec![
+    ///             ("subscribe", json!("prices")),
+    ///             ("subscribe", json!("news")),
+    ///         ])
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn resubscribe_on_connect<E, D>(
+        self,
+        subscriptions: impl IntoIterator<Item = (E, D)>,
+    ) -> Self
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let subscriptions: Arc<Vec<(Event, Payload)>> = Arc::new(
+            subscriptions
+                .into_iter()
+                .map(|(event, payload)| (event.into(), payload.into()))
+                .collect(),
+        );
+        self.after_connect(move |client| {
+            let subscriptions = subscriptions.clone();
+            Box::pin(async move {
+                for (event, payload) in subscriptions.iter() {
+                    if let Err(err) = client.emit(event.clone(), payload.clone()).await {
+                        let _ = client.error(&err).await;
+                    }
+                }
+            })
+        })
+    }
+
+    /// Disconnects the client once no `socket.io` packet was sent or received
+    /// for `timeout`, e.g. to save the connection time billed by a hosted
+    /// server. The heartbeats of `engine.io` don't count as traffic. The
+    /// client doesn't reconnect after closing an idle connection. Disabled by
+    /// default.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self.activity = Some(ActivityTracker::new());
+        self
+    }
+
+    /// Registers a handler that is called before an idle connection is closed
+    /// (see [`ClientBuilder::idle_timeout`]) with the time since the last
+    /// packet. Returning `false` keeps the connection open for another period
+    /// of the timeout.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .idle_timeout(Duration::from_secs(300))
+    ///         .on_before_idle_close(|idle| {
+    ///             async move {
+    ///                 println!("closing the connection after {idle:?} without traffic");
+    ///                 true
+    ///             }
+    ///             .boxed()
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn on_before_idle_close<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Duration) -> BoxFuture<'static, bool> + 'static + Send + Sync,
+    {
+        self.on_before_idle_close = Some(Arc::new(handler));
+        self
+    }
+
+    /// Specifies which EngineIO [`TransportType`] to use.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, TransportType};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         // Use websockets to handshake and connect.
+    ///         .transport_type(TransportType::Websocket)
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    /// }
+    /// ```
+    pub fn transport_type(mut self, transport_type: TransportType) -> Self {
+        self.transports = vec![transport_type];
+
+        self
+    }
+
+    /// Specifies the EngineIO [`TransportType`]s to try in order. If the
+    /// handshake with one of them fails, the client falls back to the next one,
+    /// on the first connect as well as on reconnects, and fails with the error
+    /// of the last one if none connects. An empty list is the same as
+    /// [`TransportType::Any`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, TransportType};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         // handshake with websockets, fall back to polling if they're blocked
+    ///         .transports([TransportType::Websocket, TransportType::Polling])
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    /// }
+    /// ```
+    pub fn transports(mut self, transports: impl IntoIterator<Item = TransportType>) -> Self {
+        self.transports = transports.into_iter().collect();
+        if self.transports.is_empty() {
+            self.transports.push(TransportType::Any);
+        }
+        self
+    }
+
+    /// Sets the order in which the client tries the servers if it was created
+    /// with several URLs. Defaults to [`Failover::Priority`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, Failover};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new(vec![
+    ///         "http://localhost:4200/",
+    ///         "http://localhost:4201/",
+    ///     ])
+    ///     .failover(Failover::RoundRobin)
+    ///     .connect()
+    ///     .await;
+    /// }
+    /// ```
+    pub fn failover(mut self, failover: Failover) -> Self {
+        self.endpoints.set_failover(failover);
+        self
+    }
+
+    /// Sends websocket `Ping` frames in the given interval while connected via
+    /// websocket. This is independent of the `engine.io` heartbeat and keeps
+    /// proxies happy that track the liveness of a websocket connection on the
+    /// protocol layer. Intervals below 1 ms are raised to 1 ms. Disabled by
+    /// default.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, TransportType};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .transport_type(TransportType::Websocket)
+    ///         .websocket_ping_interval(Duration::from_secs(10))
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    #[cfg(feature = "websocket")]
+    pub fn websocket_ping_interval(mut self, interval: Duration) -> Self {
+        self.websocket_ping_interval = Some(interval);
+        self
+    }
+
+    /// Sets how often the websocket transport retries a write that failed for
+    /// a moment, e.g. because of backpressure, before the emit fails and the
+    /// client reconnects. A frame is never sent twice. Defaults to
+    /// [`WriteRetry::default`].
+    #[cfg(feature = "websocket")]
+    pub fn websocket_write_retry(mut self, write_retry: WriteRetry) -> Self {
+        self.websocket_write_retry = Some(write_retry);
+        self
+    }
+
+    /// Remembers the servers that accepted an upgrade to websocket in `store`,
+    /// like `rememberUpgrade` of the JavaScript client. With
+    /// [`TransportType::Any`], connects and reconnects to a remembered server
+    /// then skip the polling handshake and open a websocket right away.
+    /// [`crate::FileUpgradeStore`] persists the servers across runs, e.g. for
+    /// command line tools.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, FileUpgradeStore};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let upgrades = FileUpgradeStore::open("/tmp/socketio-upgrades").unwrap();
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .remember_upgrade(upgrades)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    #[cfg(feature = "websocket")]
+    pub fn remember_upgrade<S: UpgradeStore + 'static>(mut self, store: S) -> Self {
+        self.upgrade_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Injects faults like latency, dropped packets and random disconnects
+    /// into every connection once its handshake is done, to verify how the
+    /// handlers, the heartbeat and the reconnects cope with an unreliable
+    /// network. See [`Chaos`] for the faults.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::{Chaos, ClientBuilder};
+    /// use std::time::Duration;
+    ///
+    /// let builder = ClientBuilder::new("http://localhost:4200/").chaos(
+    ///     Chaos::new()
+    ///         .latency(Duration::from_millis(50), Duration::from_millis(500))
+    ///         .drop_rate(0.01)
+    ///         .disconnect_rate(0.001),
+    /// );
+    /// ```
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn chaos(mut self, chaos: Chaos) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Probes the connection once no data was received for `idle`, so that a
+    /// half-open connection, e.g. one that a NAT gateway dropped silently, is
+    /// detected within seconds instead of after the ping interval and ping
+    /// timeout of the server. If the server doesn't acknowledge the probe
+    /// within `timeout`, the connection is closed, which is reported as
+    /// [`crate::EngineEvent::Error`], and the client reconnects. Choose `idle`
+    /// well below the ping interval of the server. Disabled by default.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .half_open_probe(Duration::from_secs(5), Duration::from_secs(2))
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn half_open_probe(mut self, idle: Duration, timeout: Duration) -> Self {
+        self.half_open_probe = Some((idle, timeout));
+        self
+    }
+
+    /// Sets the number of idle buffers that are kept for encoding outgoing
+    /// packets and reading incoming binary frames, `0` disables pooling.
+    /// Defaults to [`rust_engineio::DEFAULT_BUFFER_POOL_SIZE`].
+    pub fn buffer_pool_size(mut self, size: usize) -> Self {
+        self.buffer_pool_size = Some(size);
+        self
+    }
+
+    /// Times the emits of the client: how long each waited for the transport,
+    /// which the emit before it and reconnects hold, and how long the
+    /// transport took to write it, see [`Client::emit_timing_stats`]. Tells a
+    /// slow transport from emits that queue up behind each other. Disabled by
+    /// default.
+    pub fn emit_timing(mut self, enabled: bool) -> Self {
+        self.emit_timing = enabled;
+        self
+    }
+
+    /// Sets the HTTP protocol version of the polling transport, e.g. to force
+    /// HTTP/2 for servers that support it. Defaults to [`HttpProtocol::Http1`],
+    /// negotiating HTTP/2 over TLS needs the `http2` feature.
+    pub fn http_protocol(mut self, http_protocol: HttpProtocol) -> Self {
+        self.http_protocol = http_protocol;
+        self
+    }
+
+    /// Sets how often a long-poll request of the polling transport is retried
+    /// when a gateway responds with `502` or `503`, which keeps the session alive
+    /// across brief outages. Defaults to [`PollRetry::default`].
+    pub fn poll_retry(mut self, poll_retry: PollRetry) -> Self {
+        self.poll_retry = Some(poll_retry);
+        self
+    }
+
+    /// Limits the size of the payloads that are received from the server, larger
+    /// payloads fail with [`rust_engineio::Error::PayloadTooLarge`] before they're
+    /// read completely. Protects against malicious or misconfigured servers.
+    /// For the transports of [`ClientBuilder::transport_factory`], the limit
+    /// applies to the data after they decompressed it.
+    pub fn max_payload_size(mut self, limit: usize) -> Self {
+        self.max_payload_size = Some(limit);
+        self
+    }
+
+    /// Wraps the HTTP requests of the polling transport, including the
+    /// handshake, in the given `tower` layer, e.g. to add retries, rate
+    /// limiting, authentication or tracing. Layers set by later calls wrap the
+    /// earlier ones. Websocket connections aren't affected.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::{ClientBuilder, HttpRequest};
+    /// use tower::util::MapRequestLayer;
+    ///
+    /// let builder = ClientBuilder::new("http://localhost:4200/")
+    ///     .http_layer(MapRequestLayer::new(|mut request: HttpRequest| {
+    ///         request
+    ///             .headers_mut()
+    ///             .insert("authorization", "Bearer token".parse().unwrap());
+    ///         request
+    ///     }));
+    /// ```
+    #[cfg(feature = "tower")]
+    pub fn http_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<HttpService> + Clone + Send + Sync + 'static,
+        L::Service: Service<HttpRequest, Response = HttpResponse> + Clone + Send + 'static,
+        <L::Service as Service<HttpRequest>>::Future: Send + 'static,
+        <L::Service as Service<HttpRequest>>::Error: Into<BoxError>,
+    {
+        self.http_layers
+            .push(Arc::new(move |builder| builder.http_layer(layer.clone())));
+        self
+    }
+
+    /// Connects over the client end of a [`loopback`] pair instead of the
+    /// network, the test plays the server through the [`LoopbackServer`]. The
+    /// server handle has to answer each handshake, including the ones of
+    /// reconnects, with [`LoopbackServer::open`]. The address of the builder
+    /// and the transport type are ignored. Replaces the transport set via
+    /// [`ClientBuilder::mock_transport`] or [`ClientBuilder::transport_factory`]
+    /// before, only the last of these calls takes effect.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use rust_socketio::asynchronous::{loopback, ClientBuilder};
+    /// use rust_engineio::{Packet, PacketId};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let (transport, server) = loopback();
+    ///     server.open(Duration::from_secs(25), Duration::from_secs(20)).unwrap();
+    ///     server.send(Packet::new(PacketId::Message, "0{\"sid\":\"1\"}")).unwrap();
+    ///
+    ///     let client = ClientBuilder::new("http://localhost/")
+    ///         .loopback(transport)
+    ///         .connect()
+    ///         .await
+    ///         .expect("Connection failed");
+    ///     client.emit("status", "online").await.unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`loopback`]: crate::asynchronous::loopback
+    /// [`LoopbackServer`]: crate::asynchronous::LoopbackServer
+    /// [`LoopbackServer::open`]: crate::asynchronous::LoopbackServer::open
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn loopback(self, transport: LoopbackTransport) -> Self {
+        self.in_memory(
+            "loopback",
+            Arc::new(move |builder| {
+                let transport = transport.clone();
+                Box::pin(async move { Ok(builder.build_loopback(transport).await?) })
+            }),
+        )
+    }
+
+    /// Connects over a [`MockTransport`] instead of the network, which plays
+    /// the traffic of the server from its script. Reconnects read the next
+    /// handshake from the same script. The address of the builder and the
+    /// transport type are ignored. Replaces the transport set via
+    /// [`ClientBuilder::loopback`] or [`ClientBuilder::transport_factory`]
+    /// before, only the last of these calls takes effect.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use rust_socketio::asynchronous::{ClientBuilder, MockTransport};
+    /// use rust_engineio::{Packet, PacketId};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let transport = MockTransport::new()
+    ///         .open(Duration::from_secs(25), Duration::from_secs(20))
+    ///         .expect(Packet::new(PacketId::Message, "0"))
+    ///         .receive(Packet::new(PacketId::Message, "0{\"sid\":\"1\"}"))
+    ///         .expect(Packet::new(PacketId::Message, "2[\"status\",\"online\"]"));
+    ///
+    ///     let client = ClientBuilder::new("http://localhost/")
+    ///         .mock_transport(transport)
+    ///         .connect()
+    ///         .await
+    ///         .expect("Connection failed");
+    ///     client.emit("status", "online").await.unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`MockTransport`]: crate::asynchronous::MockTransport
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn mock_transport(self, transport: MockTransport) -> Self {
+        self.in_memory(
+            "mock_transport",
+            Arc::new(move |builder| {
+                let transport = transport.clone();
+                Box::pin(async move { Ok(builder.build_mock(transport).await?) })
+            }),
+        )
+    }
+
+    /// Connects with a transport of `factory` instead of the built-in ones,
+    /// e.g. over QUIC or a serial line. `factory` is called with the
+    /// `engine.io` URL of the server on every connect and reconnect, and the
+    /// transport type is ignored. See [`CustomTransport`] for what the
+    /// transport has to implement. Replaces the transport set via `loopback`
+    /// or `mock_transport` before, only the last of these calls takes effect.
+    ///
+    /// [`CustomTransport`]: crate::asynchronous::CustomTransport
+    pub fn transport_factory<F, T>(self, factory: F) -> Self
+    where
+        F: Fn(Url) -> BoxFuture<'static, std::result::Result<T, rust_engineio::Error>>
+            + Send
+            + Sync
+            + 'static,
+        T: AsyncTransport + Clone + Send + Sync + 'static,
+    {
+        let factory = Arc::new(factory);
+        self.in_memory(
+            "transport_factory",
+            Arc::new(move |builder| {
+                let factory = factory.clone();
+                Box::pin(async move {
+                    Ok(builder
+                        .transport_factory(move |url| factory(url))
+                        .build()
+                        .await?)
+                })
+            }),
+        )
+    }
+
+    /// Connects over `transport` instead of the network. Warns if it replaces
+    /// the transport of an earlier call, as only one of them can be used.
+    fn in_memory(mut self, method: &str, transport: InMemoryTransport) -> Self {
+        if self.in_memory.replace(transport).is_some() {
+            warn!("ClientBuilder::{method} replaces the transport that was set before");
+        }
+        self
+    }
+
+    /// Binds the connections to the server to the given local address, for
+    /// hosts with several network interfaces. Applies to the polling and the
+    /// websocket transport.
+    pub fn local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// Binds the connections to the server to the given network interface
+    /// (`SO_BINDTODEVICE`), which usually requires elevated privileges.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(mut self, interface: impl Into<String>) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+
+    /// Resolves the host of the server with `resolver` instead of the DNS
+    /// resolver of the system, e.g. with a [`crate::DohResolver`] for
+    /// DNS-over-HTTPS. Applies to the polling and the websocket transport and
+    /// to every reconnect.
+    pub fn resolver<R: Resolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Resolves the address of the server and establishes the TCP and TLS
+    /// connection ahead of time, without starting the handshake. A later call to
+    /// [`ClientBuilder::connect`] then completes with less latency. The resolved
+    /// addresses are reused for reconnects, the established connection is only
+    /// used by the first websocket transport (see [`PreparedConnection`]). With
+    /// several URLs, the connection to the server that is tried first is
+    /// prepared.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::{asynchronous::ClientBuilder, TransportType};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let builder = ClientBuilder::new("http://localhost:4200/")
+    ///         .transport_type(TransportType::Websocket)
+    ///         .prepare()
+    ///         .await
+    ///         .expect("Server unreachable");
+    ///
+    ///     // ... other startup work ...
+    ///
+    ///     let socket = builder.connect().await.expect("Connection failed");
+    /// }
+    /// ```
+    pub async fn prepare(mut self) -> Result<Self> {
+        let address = self.endpoints.first().unwrap_or_default();
+        let builder = self.engine_builder(address)?.prepare().await?;
+        self.prepared = builder.prepared_connection().cloned();
+        Ok(self)
+    }
+
+    /// If set to `false` do not try to reconnect on network errors. Defaults to
+    /// `true`
+    pub fn reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// If set to `true` try to reconnect when the server disconnects the
+    /// client. Defaults to `false`
+    pub fn reconnect_on_disconnect(mut self, reconnect_on_disconnect: bool) -> Self {
+        self.reconnect_on_disconnect = reconnect_on_disconnect;
+        self
+    }
+
+    /// Sets the minimum and maximum delay between reconnection attempts, for an
+    /// exponential backoff. Replaces a strategy set via [`ClientBuilder::backoff`].
+    pub fn reconnect_delay(mut self, min: u64, max: u64) -> Self {
+        self.reconnect_delay_min = min;
+        self.reconnect_delay_max = max;
+        self.backoff = None;
+        self
+    }
+
+    /// Sets the [`BackoffStrategy`] that decides how long the client waits
+    /// between its attempts to reconnect and when it gives up, e.g. a
+    /// [`crate::FixedBackoff`], an [`crate::ExponentialBackoff`] or a closure.
+    /// Replaces the delays set via [`ClientBuilder::reconnect_delay`], the
+    /// limit of [`ClientBuilder::max_reconnect_attempts`] still applies.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, ExponentialBackoff};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .reconnect(true)
+    ///         .backoff(
+    ///             ExponentialBackoff::new(Duration::from_millis(200), Duration::from_secs(30))
+    ///                 .jitter(0.3),
+    ///         )
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn backoff<B: BackoffStrategy + 'static>(mut self, strategy: B) -> Self {
+        self.backoff = Some(Arc::new(strategy));
+        self
+    }
+
+    /// Sets the maximum number of times to attempt reconnections. Defaults to
+    /// an infinite number of attempts
+    pub fn max_reconnect_attempts(mut self, reconnect_attempts: u8) -> Self {
+        self.max_reconnect_attempts = Some(reconnect_attempts);
+        self
+    }
+
+    /// Sets the [`EmitPolicy`] that defines what happens to emits while the client
+    /// is reconnecting. The policy can be overridden for a single emit via
+    /// [`Client::emit_opts`]. Defaults to [`EmitPolicy::FailFast`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, EmitPolicy};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_policy(EmitPolicy::Block(Duration::from_secs(5)))
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn emit_policy(mut self, emit_policy: EmitPolicy) -> Self {
+        self.emit_policy = emit_policy;
+        self
+    }
+
+    /// Sends emits with an ack again after a reconnect if the connection
+    /// dropped before their ack was received, instead of failing the ack. Can
+    /// be overridden for a single emit via
+    /// [`crate::EmitOptions::resend_on_reconnect`]. Defaults to `false`.
+    pub fn resend_on_reconnect(mut self, resend: bool) -> Self {
+        self.resend_on_reconnect = resend;
+        self
+    }
+
+    /// Buffers at most `max` emits while the client is reconnecting,
+    /// `overflow` decides what happens to the emits beyond. Without a limit,
+    /// which is the default, the buffer grows until the client reconnected or
+    /// gave up. Emits that are dropped are reported to the callbacks of
+    /// [`Event::Error`] as [`Error::EmitDropped`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, BufferOverflow, EmitPolicy};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // keeps the latest 100 emits while reconnecting
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_policy(EmitPolicy::Buffer)
+    ///         .emit_buffer_limit(100, BufferOverflow::DropOldest)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn emit_buffer_limit(mut self, max: usize, overflow: BufferOverflow) -> Self {
+        self.emit_buffer_limit = Some((max, overflow));
+        self
+    }
+
+    /// Sets how many emits [`Client::try_emit`] queues until they're sent,
+    /// 256 by default. `try_emit` fails with [`crate::TryEmitError::Full`]
+    /// while the queue is full. The queue holds at least one emit.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_queue_capacity(1024)
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn emit_queue_capacity(mut self, capacity: usize) -> Self {
+        self.emit_queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Keeps at most one emit of `event` in the buffer while the client is
+    /// reconnecting: a buffered emit is replaced by the next one, so that only
+    /// the latest is sent after the reconnect, e.g. for cursor positions or
+    /// other state of which only the current value matters. Only applies to
+    /// emits without an ack that are buffered via [`EmitPolicy::Buffer`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, EmitPolicy};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_policy(EmitPolicy::Buffer)
+    ///         .coalesce("cursor")
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn coalesce<E: Into<Event>>(mut self, event: E) -> Self {
+        self.coalescer.insert(event.into(), None);
+        self
+    }
+
+    /// Like [`ClientBuilder::coalesce`], but merges the payload of the
+    /// buffered emit with the one of the next emit via `merge`, which receives
+    /// the older payload first.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, EmitPolicy, Payload};
+    /// use serde_json::Value;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // sums up the scroll deltas that were buffered
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_policy(EmitPolicy::Buffer)
+    ///         .coalesce_with("scroll", |older, newer| match (older, newer) {
+    ///             (Payload::Text(older), Payload::Text(newer)) => {
+    ///                 let delta = |values: &[Value]| values.first().and_then(Value::as_i64).unwrap_or(0);
+    ///                 Payload::Text(vec![(delta(&older) + delta(&newer)).into()])
+    ///             }
+    ///             (_, newer) => newer,
+    ///         })
+    ///         .connect()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn coalesce_with<E, F>(mut self, event: E, merge: F) -> Self
+    where
+        E: Into<Event>,
+        F: Fn(Payload, Payload) -> Payload + Send + Sync + 'static,
+    {
+        self.coalescer.insert(event.into(), Some(Arc::new(merge)));
+        self
+    }
+
+    /// Connects the socket to a certain endpoint. This returns a connected
+    /// [`Client`] instance. This method returns an [`std::result::Result::Err`]
+    /// value if something goes wrong during connection. Also starts a separate
+    /// thread to start polling for packets. Used with callbacks.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{asynchronous::ClientBuilder, Payload};
+    /// use serde_json::json;
+    /// use futures_util::future::FutureExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .namespace("/admin")
+    ///         .on("error", |err, _| async move { eprintln!("Error: {:#?}", err) }.boxed())
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     // use the socket
+    ///     let json_payload = json!({"token": 123});
+    ///
+    ///     let result = socket.emit("foo", json_payload).await;
+    ///
+    ///     assert!(result.is_ok());
+    /// }
+    /// ```
+    pub async fn connect(self) -> Result<Client> {
+        let mut socket = self.connect_manual().await?;
+        socket.poll_stream().await?;
+
+        Ok(socket)
+    }
+
+    /// Creates the engine.io builder for the server at `address` with the
+    /// options of this builder.
+    fn engine_builder(&self, address: &str) -> Result<EngineIoClientBuilder> {
+        // `Url` rejects the zone of a link-local IPv6 address, like `%eth0`
+        let (mut url, zone) = split_zone(address)?;
+
+        if url.path() == "/" {
+            url.set_path("/socket.io/");
+        }
+        if !self.query.is_empty() {
+            let mut pairs = url.query_pairs_mut();
+            for (key, value) in &self.query {
+                pairs.append_pair(key, value.as_str());
+            }
+        }
+
+        let url_host = url.host_str().map(ToOwned::to_owned);
+        let mut builder = EngineIoClientBuilder::new(url);
+        if let Some(zone) = zone {
+            builder = builder.ipv6_zone(zone);
+        }
+
+        if let Some(handler) = &self.on_engineio {
+            builder = Self::forward_engine_events(builder, handler.clone());
+        }
         if let Some(tls_config) = &self.tls_config {
             builder = builder.tls_config(tls_config.to_owned());
         }
-        if let Some(headers) = &self.opening_headers {
-            builder = builder.headers(headers.to_owned());
+        let mut headers = self.opening_headers.clone();
+        if let Some(auth) = &self.bearer_auth {
+            headers
+                .get_or_insert_with(HeaderMap::default)
+                .insert(AUTHORIZATION.to_owned(), auth.header());
+        }
+        if let Some(headers) = headers {
+            builder = builder.headers(headers);
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(interval) = self.websocket_ping_interval {
+            builder = builder.websocket_ping_interval(interval);
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(write_retry) = self.websocket_write_retry {
+            builder = builder.websocket_write_retry(write_retry);
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(store) = &self.upgrade_store {
+            builder = builder.remember_upgrade(store.clone());
+        }
+        if let Some((idle, timeout)) = self.half_open_probe {
+            builder = builder.half_open_probe(idle, timeout);
+        }
+        #[cfg(any(test, feature = "test-support"))]
+        if let Some(chaos) = &self.chaos {
+            builder = builder.chaos(chaos.clone());
+        }
+        if let Some(size) = self.buffer_pool_size {
+            builder = builder.buffer_pool_size(size);
+        }
+        builder = builder.emit_timing(self.emit_timing);
+        builder = builder.http_protocol(self.http_protocol);
+        if let Some(poll_retry) = self.poll_retry {
+            builder = builder.poll_retry(poll_retry);
+        }
+        if let Some(limit) = self.max_payload_size {
+            builder = builder.max_payload_size(limit);
+        }
+        #[cfg(feature = "tower")]
+        for layer in &self.http_layers {
+            builder = layer(builder);
+        }
+        if let Some(address) = self.local_address {
+            builder = builder.local_address(address);
+        }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(interface) = &self.interface {
+            builder = builder.interface(interface.to_owned());
+        }
+        if let Some(resolver) = &self.resolver {
+            builder = builder.resolver(resolver.clone());
+        }
+        // the connection is only prepared for one of the servers
+        if let Some(prepared) = &self.prepared {
+            if Some(prepared.host()) == url_host.as_deref() {
+                builder = builder.prepared(prepared.clone());
+            }
         }
 
-        let engine_client = match self.transport_type {
-            TransportType::Any => builder.build_with_fallback().await?,
-            TransportType::Polling => builder.build_polling().await?,
-            TransportType::Websocket => builder.build_websocket().await?,
-            TransportType::WebsocketUpgrade => builder.build_websocket_with_upgrade().await?,
-        };
+        Ok(builder)
+    }
+
+    /// Registers `handler` for all events of the `engine.io` connection.
+    fn forward_engine_events(
+        builder: EngineIoClientBuilder,
+        handler: AsyncEngineEventHandler,
+    ) -> EngineIoClientBuilder {
+        let (on_open, on_close, on_error, on_packet) = (
+            handler.clone(),
+            handler.clone(),
+            handler.clone(),
+            handler.clone(),
+        );
+        let builder = builder
+            .on_open(move |_| on_open(EngineEvent::Open))
+            .on_close_with_description(move |description| on_close(EngineEvent::Close(description)))
+            .on_error(move |error| on_error(EngineEvent::Error(error)))
+            .on_packet(move |packet| on_packet(EngineEvent::from(packet)));
+        #[cfg(feature = "websocket")]
+        let builder = builder.on_upgrade(move |_| handler(EngineEvent::Upgrade));
+        builder
+    }
+
+    /// Connects to the engine.io server at `address`.
+    async fn engine_client(&self, address: &str) -> Result<EngineClient> {
+        let builder = self.engine_builder(address)?;
+        if let Some(build) = &self.in_memory {
+            return build(builder).await;
+        }
 
-        let inner_socket = InnerSocket::new(engine_client)?;
+        let mut result = Err(Error::InvalidUrl(url::ParseError::EmptyHost));
+        for transport_type in &self.transports {
+            let builder = builder.clone();
+            let connected = match transport_type {
+                TransportType::Any => builder.build_with_fallback().await,
+                TransportType::Polling => builder.build_polling().await,
+                #[cfg(feature = "websocket")]
+                TransportType::Websocket => builder.build_websocket().await,
+                #[cfg(feature = "websocket")]
+                TransportType::WebsocketUpgrade => builder.build_websocket_with_upgrade().await,
+            };
+            result = connected.map_err(Error::from);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Creates a new Socket that can be used for reconnections, to the first
+    /// of the servers that accepts the handshake in the order of the
+    /// [`Failover`]. Fails with the error of the last server if none does.
+    pub(crate) async fn inner_create(&self) -> Result<InnerSocket> {
+        let mut result = Err(Error::InvalidUrl(url::ParseError::EmptyHost));
+        for (index, address) in self.endpoints.candidates() {
+            result = self.engine_client(address).await;
+            if result.is_ok() {
+                self.endpoints.connected(index);
+                break;
+            }
+        }
+
+        let inner_socket = InnerSocket::new(result?)?
+            .with_small_event_threshold(self.small_event_threshold)
+            .with_utf8_policy(self.utf8_policy);
         Ok(inner_socket)
     }
 
     //TODO: 0.3.X stabilize
     pub(crate) async fn connect_manual(self) -> Result<Client> {
+        for hook in &self.before_connect {
+            hook().await;
+        }
         let inner_socket = self.inner_create().await?;
 
         let socket = Client::new(inner_socket, self)?;
@@ -467,3 +2139,58 @@ impl ClientBuilder {
         Ok(socket)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_realtime() {
+        let builder = ClientBuilder::new("http://localhost:4200/").realtime();
+        assert_eq!(builder.emit_policy, EmitPolicy::FailFast);
+        assert!(!builder.resend_on_reconnect);
+        assert!(builder.reconnect);
+        assert!(!builder.reconnect_on_disconnect);
+        assert_eq!(builder.max_reconnect_attempts, None);
+        assert_eq!(
+            (builder.reconnect_delay_min, builder.reconnect_delay_max),
+            (100, 1000)
+        );
+        assert_eq!(builder.poll_retry, Some(PollRetry::disabled()));
+        #[cfg(feature = "websocket")]
+        assert!(builder.transports == [TransportType::Websocket]);
+        #[cfg(feature = "websocket")]
+        assert_eq!(builder.websocket_write_retry, Some(WriteRetry::disabled()));
+    }
+
+    #[test]
+    fn test_reliable() {
+        let builder = ClientBuilder::new("http://localhost:4200/").reliable();
+        assert_eq!(builder.emit_policy, EmitPolicy::Buffer);
+        assert!(builder.resend_on_reconnect);
+        assert!(builder.reconnect);
+        assert!(builder.reconnect_on_disconnect);
+        assert_eq!(builder.max_reconnect_attempts, None);
+        assert_eq!(
+            (builder.reconnect_delay_min, builder.reconnect_delay_max),
+            (500, 30_000)
+        );
+        assert_eq!(
+            builder.poll_retry,
+            Some(PollRetry::new(5, Duration::from_millis(200)))
+        );
+        assert!(builder.transports == [TransportType::Any]);
+
+        // settings after the preset override it
+        let builder = ClientBuilder::new("http://localhost:4200/")
+            .reliable()
+            .emit_policy(EmitPolicy::FailFast)
+            .reconnect_delay(200, 10_000);
+        assert_eq!(builder.emit_policy, EmitPolicy::FailFast);
+        assert_eq!(
+            (builder.reconnect_delay_min, builder.reconnect_delay_max),
+            (200, 10_000)
+        );
+        assert!(builder.resend_on_reconnect);
+    }
+}
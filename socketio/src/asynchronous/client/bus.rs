@@ -0,0 +1,126 @@
+use std::{fmt::Debug, sync::Arc};
+
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use super::stream::EventStream;
+use crate::{Event, Payload};
+
+/// Forwards the events a client receives into a [`tokio::sync::broadcast`]
+/// channel, so that several parts of an application can subscribe to them
+/// without registering competing callbacks on the client. Register the bus via
+/// [`crate::asynchronous::ClientBuilder::event_bus`]; changes of the connection
+/// state are published by [`crate::asynchronous::Client::watch_state`].
+///
+/// Subscribers that fall behind by more than the capacity of the channel miss
+/// the oldest events, see [`broadcast::error::RecvError::Lagged`].
+///
+/// # Example
+/// ```no_run
+/// use rust_socketio::asynchronous::{ClientBuilder, EventBus};
+/// use rust_socketio::Event;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let bus = EventBus::new(64).filter(|event| *event == Event::from("price"));
+///     let mut prices = bus.subscribe();
+///     let mut audit = bus.subscribe();
+///
+///     let client = ClientBuilder::new("http://localhost:4200/")
+///         .event_bus(bus)
+///         .connect()
+///         .await
+///         .expect("Connection failed");
+///
+///     tokio::spawn(async move {
+///         while let Ok((_, payload)) = audit.recv().await {
+///             println!("audit: {payload:?}");
+///         }
+///     });
+///     while let Ok((event, payload)) = prices.recv().await {
+///         println!("{event}: {payload:?}");
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct EventBus {
+    sender: Sender<(Event, Payload)>,
+    filter: Option<EventFilter>,
+}
+
+type EventFilter = Arc<dyn Fn(&Event) -> bool + Send + Sync>;
+
+impl EventBus {
+    /// Creates a bus that buffers up to `capacity` events for each subscriber.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        EventBus {
+            sender,
+            filter: None,
+        }
+    }
+
+    /// Only forwards the events for which `filter` returns true. All events are
+    /// forwarded by default, including [`Event::Connect`], [`Event::Close`] and
+    /// [`Event::Error`].
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Subscribes to the events that are forwarded from now on.
+    pub fn subscribe(&self) -> Receiver<(Event, Payload)> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribes to the events that are forwarded from now on as an
+    /// [`EventStream`], which can be filtered and split by event.
+    pub fn stream(&self) -> EventStream {
+        EventStream::new(self.subscribe())
+    }
+
+    /// Forwards an event to the subscribers, unless it's filtered out. Events
+    /// without subscribers are dropped.
+    pub(crate) fn forward(&self, event: &Event, payload: &Payload) {
+        if self.filter.as_ref().is_some_and(|filter| !filter(event)) {
+            return;
+        }
+        let _ = self.sender.send((event.clone(), payload.clone()));
+    }
+}
+
+impl Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.sender.receiver_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_forward() {
+        let bus = EventBus::new(8).filter(|event| matches!(event, Event::Custom(_)));
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+
+        bus.forward(&Event::Connect, &Payload::from("{}"));
+        bus.forward(&Event::from("price"), &Payload::from("1"));
+
+        for receiver in [&mut first, &mut second] {
+            assert_eq!(
+                receiver.recv().await.unwrap(),
+                (Event::from("price"), Payload::from("1"))
+            );
+            assert!(receiver.try_recv().is_err());
+        }
+    }
+}
@@ -2,9 +2,10 @@ use futures_util::future::BoxFuture;
 use std::{
     fmt::Debug,
     ops::{Deref, DerefMut},
+    sync::Arc,
 };
 
-use crate::{Event, Payload};
+use crate::{Error, Event, Payload};
 
 use super::client::{Client, ReconnectSettings};
 
@@ -16,9 +17,18 @@ pub(crate) type DynAsyncAnyCallback = Box<
     dyn for<'a> FnMut(Event, Payload, Client) -> BoxFuture<'static, ()> + 'static + Send + Sync,
 >;
 
+pub(crate) type DynAsyncErrorCallback =
+    Box<dyn for<'a> FnMut(&'a Error, Client) -> BoxFuture<'static, ()> + 'static + Send + Sync>;
+
 pub(crate) type DynAsyncReconnectSettingsCallback =
     Box<dyn for<'a> FnMut() -> BoxFuture<'static, ReconnectSettings> + 'static + Send + Sync>;
 
+/// Called before every connect and reconnect, see `ClientBuilder::before_connect`.
+pub(crate) type BeforeConnectHook = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Called once the namespace is connected, see `ClientBuilder::after_connect`.
+pub(crate) type AfterConnectHook = Arc<dyn Fn(Client) -> BoxFuture<'static, ()> + Send + Sync>;
+
 pub(crate) struct Callback<T> {
     inner: T,
 }
@@ -53,6 +63,10 @@ impl Callback<DynAsyncCallback> {
             inner: Box::new(callback),
         }
     }
+
+    pub(crate) fn into_inner(self) -> DynAsyncCallback {
+        self.inner
+    }
 }
 
 impl Deref for Callback<DynAsyncAnyCallback> {
@@ -81,6 +95,32 @@ impl Callback<DynAsyncAnyCallback> {
     }
 }
 
+impl Deref for Callback<DynAsyncErrorCallback> {
+    type Target =
+        dyn for<'a> FnMut(&'a Error, Client) -> BoxFuture<'static, ()> + 'static + Sync + Send;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.as_ref()
+    }
+}
+
+impl DerefMut for Callback<DynAsyncErrorCallback> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner.as_mut()
+    }
+}
+
+impl Callback<DynAsyncErrorCallback> {
+    pub(crate) fn new<T>(callback: T) -> Self
+    where
+        T: for<'a> FnMut(&'a Error, Client) -> BoxFuture<'static, ()> + 'static + Sync + Send,
+    {
+        Callback {
+            inner: Box::new(callback),
+        }
+    }
+}
+
 impl Deref for Callback<DynAsyncReconnectSettingsCallback> {
     type Target =
         dyn for<'a> FnMut() -> BoxFuture<'static, ReconnectSettings> + 'static + Sync + Send;
@@ -1,12 +1,26 @@
-use std::{ops::DerefMut, pin::Pin, sync::Arc};
+use std::{
+    collections::HashMap,
+    ops::DerefMut,
+    pin::{pin, Pin},
+    sync::{Arc, OnceLock},
+};
 
-use backoff::{backoff::Backoff, ExponentialBackoffBuilder};
-use futures_util::{future::BoxFuture, stream, Stream, StreamExt};
-use log::trace;
+use backoff::{
+    backoff::Backoff, exponential::ExponentialBackoff, Clock, ExponentialBackoffBuilder,
+};
+use futures_util::{
+    future::{select, BoxFuture, Either},
+    stream, FutureExt, Stream, StreamExt,
+};
+use log::{trace, warn};
 use rand::{thread_rng, Rng};
+use serde::Serialize;
 use serde_json::Value;
 use tokio::{
-    sync::RwLock,
+    sync::{
+        mpsc::{self, error::TrySendError},
+        watch, RwLock,
+    },
     time::{sleep, Duration, Instant},
 };
 
@@ -14,12 +28,27 @@ use super::{
     ack::Ack,
     builder::ClientBuilder,
     callback::{Callback, DynAsyncCallback},
+    dispatcher::Dispatcher,
+    watchdog::CallbackWatchdog,
 };
 use crate::{
     asynchronous::socket::Socket as InnerSocket,
+    dyn_client::{ack_channel, receive_ack},
+    emit::{
+        AckFailure, Admission, BufferedEmit, Coalescer, ConnectionState, EmitGate, PendingStats,
+        SequenceCounter,
+    },
+    engine_event::AsyncTransportChangeHandler,
     error::{Error, Result},
+    idle::{ActivityTracker, AsyncBeforeIdleCloseHandler},
+    namespace::{AsyncNamespaceStateHandler, NamespaceTracker},
     packet::{Packet, PacketId},
-    Event, Payload,
+    recovery::SessionRecovery,
+    redact::Redaction,
+    uptime::UptimeTracker,
+    AckDispatch, AckRequest, BufferPoolStats, ClientSnapshot, EmitOptions, EmitTimingStats, Event,
+    HttpVersion, NamespaceState, NamespaceStatus, Payload, PayloadCodec, ReconnectEvent,
+    ServerAddresses, TryEmitError, UnhandledPolicy, UptimeStats,
 };
 
 #[derive(Default)]
@@ -31,6 +60,8 @@ enum DisconnectReason {
     Manual,
     /// The server disconnected
     Server,
+    /// The user requested a reconnect
+    Requested,
 }
 
 /// Settings that can be updated before reconnecting to a server
@@ -60,6 +91,37 @@ impl ReconnectSettings {
     }
 }
 
+/// Reads the time of the reconnect backoff from tokio, so that tests can pause
+/// and advance it.
+#[derive(Debug, Default)]
+struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> std::time::Instant {
+        Instant::now().into_std()
+    }
+}
+
+/// The exponential backoff between reconnect attempts.
+fn reconnect_backoff(min: Duration, max: Duration) -> ExponentialBackoff<TokioClock> {
+    let defaults = ExponentialBackoffBuilder::new()
+        .with_initial_interval(min)
+        .with_max_interval(max)
+        .build();
+    let mut backoff = ExponentialBackoff {
+        current_interval: defaults.current_interval,
+        initial_interval: defaults.initial_interval,
+        randomization_factor: defaults.randomization_factor,
+        multiplier: defaults.multiplier,
+        max_interval: defaults.max_interval,
+        start_time: defaults.start_time,
+        max_elapsed_time: defaults.max_elapsed_time,
+        clock: TokioClock,
+    };
+    backoff.reset();
+    backoff
+}
+
 /// A socket which handles communication with the server. It's initialized with
 /// a specific address as well as an optional namespace to connect to. If `None`
 /// is given the client will connect to the default namespace `"/"`.
@@ -72,8 +134,34 @@ pub struct Client {
     nsp: String,
     // Data send in the opening packet (commonly used as for auth)
     auth: Option<serde_json::Value>,
+    // Tag attached to emitted payloads in order to detect our own echoes
+    origin: Option<String>,
+    // Numbers the emitted payloads, if enabled
+    sequence: Option<SequenceCounter>,
+    // The session that the server restores after a reconnect, if supported
+    recovery: SessionRecovery,
+    // Keeps only the latest buffered emit of some events
+    coalescer: Coalescer,
+    // Records the traffic for closing idle connections, if enabled
+    activity: Option<ActivityTracker>,
     builder: Arc<RwLock<ClientBuilder>>,
     disconnect_reason: Arc<RwLock<DisconnectReason>>,
+    // Decides what happens to emits while the client is reconnecting
+    gate: Arc<EmitGate<BufferedEmit<DynAsyncCallback>>>,
+    // Holds the emits of `try_emit` until they're sent, once the stream is polled
+    emit_queue: Arc<OnceLock<mpsc::Sender<(Event, Payload)>>>,
+    // Calls the callbacks in order with other clients, if shared
+    dispatcher: Option<Dispatcher>,
+    // Counts the callbacks that run on tasks of their own, if enabled
+    watchdog: Option<CallbackWatchdog>,
+    // Decides what the logs show of payloads
+    redaction: Redaction,
+    // Records the connects and disconnects for the uptime statistics
+    uptime: UptimeTracker,
+    // Records the state of the namespace for diagnostics
+    namespaces: NamespaceTracker,
+    on_namespace_state_change: Option<AsyncNamespaceStateHandler>,
+    on_transport_change: Option<AsyncTransportChangeHandler>,
 }
 
 impl Client {
@@ -87,8 +175,24 @@ impl Client {
             nsp: builder.namespace.to_owned(),
             outstanding_acks: Arc::new(RwLock::new(Vec::new())),
             auth: builder.auth.clone(),
+            origin: builder.origin.clone(),
+            sequence: builder.sequence.clone(),
+            recovery: SessionRecovery::default(),
+            coalescer: builder.coalescer.clone(),
+            activity: builder.activity.clone(),
+            dispatcher: builder.dispatcher.clone(),
+            watchdog: builder.callback_watchdog.map(|(high_water, policy)| {
+                CallbackWatchdog::new(high_water, policy, builder.on_overload.clone())
+            }),
+            redaction: builder.redaction.clone(),
+            on_namespace_state_change: builder.on_namespace_state_change.clone(),
+            on_transport_change: builder.on_transport_change.clone(),
+            gate: Arc::new(EmitGate::new(builder.emit_buffer_limit)),
+            emit_queue: Arc::new(OnceLock::new()),
             builder: Arc::new(RwLock::new(builder)),
             disconnect_reason: Arc::new(RwLock::new(DisconnectReason::default())),
+            uptime: UptimeTracker::default(),
+            namespaces: NamespaceTracker::default(),
         })
     }
 
@@ -96,24 +200,34 @@ impl Client {
     /// called to interact with the server.
     pub(crate) async fn connect(&self) -> Result<()> {
         // Connect the underlying socket
-        self.socket.read().await.connect().await?;
+        let socket = self.socket.read().await;
+        socket.connect().await?;
+        let transport = socket.transport_kind();
+        drop(socket);
+        if self.gate.snapshot().set_transport(transport) != Some(transport) {
+            if let Some(handler) = &self.on_transport_change {
+                handler(transport).await;
+            }
+        }
 
         // construct the opening packet
-        let auth = self.auth.as_ref().map(|data| data.to_string());
+        let auth = self.recovery.auth(self.auth.as_ref());
         let open_packet = Packet::new(PacketId::Connect, self.nsp.clone(), auth, None, 0, None);
 
         self.socket.read().await.send(open_packet).await?;
+        self.set_namespace_state(NamespaceState::Pending, None)
+            .await;
 
         Ok(())
     }
 
-    pub(crate) async fn reconnect(&mut self) -> Result<()> {
+    async fn do_reconnect(&mut self) -> Result<()> {
         let mut builder = self.builder.write().await;
 
         if let Some(config) = builder.on_reconnect.as_mut() {
             let reconnect_settings = config().await;
             if let Some(address) = reconnect_settings.address {
-                builder.address = address;
+                builder.endpoints.replace(ServerAddresses::from(address));
             }
 
             if let Some(auth) = reconnect_settings.auth {
@@ -121,6 +235,9 @@ impl Client {
             }
         }
 
+        for hook in &builder.before_connect {
+            hook().await;
+        }
         let socket = builder.inner_create().await?;
 
         // New inner socket that can be connected
@@ -136,20 +253,125 @@ impl Client {
         Ok(())
     }
 
+    /// Stops reconnecting, drops the buffered emits and fails their acks.
+    async fn give_up_reconnecting(&self) {
+        if let Ok(dropped) = self.gate.set_state(ConnectionState::Disconnected) {
+            self.fail_dropped(dropped).await;
+        }
+    }
+
+    /// Disconnects the client once it's idle for `timeout`, unless the handler
+    /// registered via [`ClientBuilder::on_before_idle_close`] keeps it open.
+    fn watch_idle(
+        &self,
+        timeout: Duration,
+        activity: ActivityTracker,
+        on_before_close: Option<AsyncBeforeIdleCloseHandler>,
+    ) {
+        let client = self.clone();
+        tokio::runtime::Handle::current().spawn(async move {
+            loop {
+                let idle = activity.idle_for();
+                if idle < timeout {
+                    sleep(timeout - idle).await;
+                    continue;
+                }
+                match client.gate.state() {
+                    Ok(ConnectionState::Connected) => {}
+                    // the idle time starts over once the client reconnected
+                    Ok(ConnectionState::Reconnecting) => {
+                        activity.touch();
+                        continue;
+                    }
+                    _ => return,
+                }
+                let close = match &on_before_close {
+                    Some(handler) => handler(idle).await,
+                    None => true,
+                };
+                if close {
+                    let _ = client.disconnect().await;
+                    return;
+                }
+                activity.touch();
+            }
+        });
+    }
+
+    /// Sends the emits queued via [`Client::try_emit`] in order, until the
+    /// client is disconnected for good.
+    fn send_queued_emits(&self, mut queued: mpsc::Receiver<(Event, Payload)>) {
+        let client = self.clone();
+        let mut state = self.gate.watch_state();
+        tokio::runtime::Handle::current().spawn(async move {
+            loop {
+                let disconnected = state.wait_for(|state| *state == ConnectionState::Disconnected);
+                let (event, payload) = match select(pin!(queued.recv()), pin!(disconnected)).await {
+                    Either::Left((Some(emit), _)) => emit,
+                    _ => return,
+                };
+                if let Err(err) = client.emit(event, payload).await {
+                    let _ = client.error(&err).await;
+                }
+            }
+        });
+    }
+
+    /// Reconnects to present the rotated token of the [`crate::BearerAuth`].
+    /// Returns `false` once the client is disconnected for good.
+    async fn reconnect_with_rotated_token(&self) -> bool {
+        if !self.builder.read().await.reconnect {
+            // the rotated token is used on the next connect
+            return self.is_alive();
+        }
+        self.reconnect().await.is_ok()
+    }
+
+    /// Whether the client may still connect again, i.e. it wasn't
+    /// disconnected for good.
+    fn is_alive(&self) -> bool {
+        !matches!(self.gate.state(), Ok(ConnectionState::Disconnected))
+    }
+
     /// Drives the stream using a thread so messages are processed
     pub(crate) async fn poll_stream(&mut self) -> Result<()> {
         let builder = self.builder.read().await;
         let reconnect_delay_min = builder.reconnect_delay_min;
         let reconnect_delay_max = builder.reconnect_delay_max;
         let max_reconnect_attempts = builder.max_reconnect_attempts;
+        let strategy = builder.backoff.clone();
         let reconnect = builder.reconnect;
         let reconnect_on_disconnect = builder.reconnect_on_disconnect;
+        let idle = builder.idle_timeout.zip(self.activity.clone());
+        let on_before_idle_close = builder.on_before_idle_close.clone();
+        let bearer_auth = builder.bearer_auth.clone();
+        let on_reconnect_event = builder.on_reconnect_event.clone();
+        let emit_queue_capacity = builder.emit_queue_capacity;
         drop(builder);
 
+        let (queue, queued) = mpsc::channel(emit_queue_capacity);
+        if self.emit_queue.set(queue).is_ok() {
+            self.send_queued_emits(queued);
+        }
+
+        if let Some((timeout, activity)) = idle {
+            self.watch_idle(timeout, activity, on_before_idle_close);
+        }
+        if let Some(auth) = bearer_auth {
+            let client = self.clone();
+            let alive = self.clone();
+            let runtime = tokio::runtime::Handle::current();
+            // the token is watched on a thread of its own, see `BearerAuth::watch`
+            auth.watch(
+                move || alive.is_alive(),
+                move || runtime.block_on(client.reconnect_with_rotated_token()),
+            );
+        }
+
         let mut client_clone = self.clone();
 
         tokio::runtime::Handle::current().spawn(async move {
-            loop {
+            'poll: loop {
                 let mut stream = client_clone.as_stream().await;
                 // Consume the stream until it returns None and the stream is closed.
                 while let Some(item) = stream.next().await {
@@ -160,35 +382,82 @@ impl Client {
 
                 // Drop the stream so we can once again use `socket_clone` as mutable
                 drop(stream);
-
-                let should_reconnect = match *(client_clone.disconnect_reason.read().await) {
-                    DisconnectReason::Unknown => reconnect,
-                    DisconnectReason::Manual => false,
-                    DisconnectReason::Server => reconnect_on_disconnect,
-                };
+                client_clone.uptime.disconnected();
+                client_clone
+                    .set_namespace_state(NamespaceState::Disconnected, None)
+                    .await;
+
+                let (should_reconnect, connection_dropped) =
+                    match *(client_clone.disconnect_reason.read().await) {
+                        DisconnectReason::Unknown => (reconnect, true),
+                        DisconnectReason::Manual => (false, false),
+                        DisconnectReason::Server => (reconnect_on_disconnect, false),
+                        DisconnectReason::Requested => (true, true),
+                    };
 
                 if should_reconnect {
+                    let _ = client_clone.gate.set_state(ConnectionState::Reconnecting);
+                    // only emits whose connection dropped are resent, the server
+                    // won't answer acks of a connection it closed on purpose
+                    client_clone
+                        .close_outstanding_acks(connection_dropped)
+                        .await;
                     let mut reconnect_attempts = 0;
-                    let mut backoff = ExponentialBackoffBuilder::new()
-                        .with_initial_interval(Duration::from_millis(reconnect_delay_min))
-                        .with_max_interval(Duration::from_millis(reconnect_delay_max))
-                        .build();
+                    let mut attempt: u32 = 0;
+                    let mut backoff = reconnect_backoff(
+                        Duration::from_millis(reconnect_delay_min),
+                        Duration::from_millis(reconnect_delay_max),
+                    );
 
                     loop {
                         if let Some(max_reconnect_attempts) = max_reconnect_attempts {
                             reconnect_attempts += 1;
                             if reconnect_attempts > max_reconnect_attempts {
                                 trace!("Max reconnect attempts reached without success");
-                                break;
+                                client_clone.give_up_reconnecting().await;
+                                // the poll task ends, like the sync client's poll thread
+                                break 'poll;
                             }
                         }
-                        match client_clone.reconnect().await {
+                        // counted like the sync client, the strategy is asked
+                        // before every attempt, including the first
+                        attempt = attempt.saturating_add(1);
+                        if let Some(strategy) = &strategy {
+                            let Some(delay) = strategy.delay(attempt) else {
+                                trace!("The backoff strategy gave up reconnecting");
+                                client_clone.give_up_reconnecting().await;
+                                break 'poll;
+                            };
+                            trace!("Waiting for {}ms before reconnecting", delay.as_millis());
+                            sleep(delay).await;
+                        }
+                        if let Some(handler) = &on_reconnect_event {
+                            handler(ReconnectEvent::Attempt(attempt)).await;
+                        }
+                        match client_clone.do_reconnect().await {
                             Ok(_) => {
                                 trace!("Reconnected after {reconnect_attempts} attempts");
+                                if let Some(handler) = &on_reconnect_event {
+                                    handler(ReconnectEvent::Reconnected(attempt)).await;
+                                }
+                                // the buffered emits are flushed once the server
+                                // acknowledged the namespace, see `dispatch`
                                 break;
                             }
                             Err(e) => {
                                 trace!("Failed to reconnect: {e:?}");
+                                if let Some(handler) = &on_reconnect_event {
+                                    handler(ReconnectEvent::Error(e.to_string())).await;
+                                }
+                                client_clone
+                                    .set_namespace_state(
+                                        NamespaceState::Failed,
+                                        Some(e.to_string()),
+                                    )
+                                    .await;
+                                if strategy.is_some() {
+                                    continue;
+                                }
                                 if let Some(delay) = backoff.next_backoff() {
                                     let delay_ms = delay.as_millis();
                                     trace!("Waiting for {delay_ms}ms before reconnecting");
@@ -198,6 +467,11 @@ impl Client {
                         }
                     }
                 } else {
+                    if let Ok(dropped) = client_clone.gate.set_state(ConnectionState::Disconnected)
+                    {
+                        client_clone.fail_dropped(dropped).await;
+                    }
+                    client_clone.close_outstanding_acks(false).await;
                     break;
                 }
             }
@@ -244,11 +518,155 @@ impl Client {
         E: Into<Event>,
         D: Into<Payload>,
     {
-        self.socket
-            .read()
-            .await
-            .emit(&self.nsp, event.into(), data.into())
-            .await
+        self.emit_opts(event, data, EmitOptions::default()).await
+    }
+
+    /// Queues a message for the server without awaiting it, e.g. from a `Drop`
+    /// impl or from synchronous code that runs outside of the runtime. The
+    /// queued emits are sent in order by a task of the client, like
+    /// [`Client::emit`], which reports failures to the callbacks of
+    /// [`Event::Error`]. Fails with [`TryEmitError::Full`] if the queue holds
+    /// [`ClientBuilder::emit_queue_capacity`] emits already, and with
+    /// [`TryEmitError::Disconnected`] if the client is disconnected for good.
+    /// Both hand back the event and payload.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::asynchronous::{Client, ClientBuilder};
+    /// use serde_json::json;
+    ///
+    /// struct Session {
+    ///     socket: Client,
+    /// }
+    ///
+    /// impl Drop for Session {
+    ///     fn drop(&mut self) {
+    ///         // a `Drop` impl can't await the emit
+    ///         let _ = self.socket.try_emit("leave", json!({"room": "lobby"}));
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     drop(Session { socket });
+    /// }
+    /// ```
+    pub fn try_emit<E, D>(&self, event: E, data: D) -> std::result::Result<(), TryEmitError>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let (event, payload) = (event.into(), data.into());
+        let queue = match self.emit_queue.get() {
+            Some(queue) if self.gate.state().ok() != Some(ConnectionState::Disconnected) => queue,
+            _ => return Err(TryEmitError::Disconnected(event, payload)),
+        };
+        queue.try_send((event, payload)).map_err(|err| match err {
+            TrySendError::Full((event, payload)) => TryEmitError::Full(event, payload),
+            TrySendError::Closed((event, payload)) => TryEmitError::Disconnected(event, payload),
+        })
+    }
+
+    /// Sends a message to the server like [`Client::emit`], but allows to
+    /// override the settings of the client for this single emit, e.g. the
+    /// [`crate::EmitPolicy`] that applies while the client is reconnecting.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{asynchronous::ClientBuilder, EmitOptions, EmitPolicy};
+    /// use serde_json::json;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     let options = EmitOptions::new().policy(EmitPolicy::Buffer);
+    ///     let result = socket.emit_opts("foo", json!({"token": 123}), options).await;
+    ///
+    ///     assert!(result.is_ok());
+    /// }
+    /// ```
+    pub async fn emit_opts<E, D>(&self, event: E, data: D, options: EmitOptions) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.send(
+            BufferedEmit {
+                event: event.into(),
+                payload: data.into(),
+                ack: None,
+                resend_on_reconnect: false,
+                deadline: options.deadline,
+                sequence: None,
+                on_failure: None,
+            },
+            options,
+        )
+        .await
+    }
+
+    /// Sends `data` to the server as the single argument of `event`, serialized
+    /// via `serde`, see [`Payload::json`]. Behaves like [`Client::emit`]
+    /// otherwise.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Login<'a> {
+    ///     user: &'a str,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     socket.emit_json("login", &Login { user: "alice" }).await.unwrap();
+    /// }
+    /// ```
+    pub async fn emit_json<E, T>(&self, event: E, data: &T) -> Result<()>
+    where
+        E: Into<Event>,
+        T: Serialize + ?Sized,
+    {
+        self.emit(event, Payload::json(data)?).await
+    }
+
+    /// Sends a protobuf message to the server as binary attachment of `event`,
+    /// with the full type name of the message as argument before it. Available
+    /// with the `prost` feature, see [`crate::asynchronous::ClientBuilder::on_proto`]
+    /// for the receiving side.
+    ///
+    /// Unlike [`Client::emit`], the message isn't subject to the
+    /// [`crate::EmitPolicy`] and fails while reconnecting.
+    #[cfg(feature = "prost")]
+    pub async fn emit_proto<M>(&self, event: impl Into<Event>, message: &M) -> Result<()>
+    where
+        M: prost::Message + prost::Name,
+    {
+        let event = event.into();
+        let message = Payload::Binary(message.encode_to_vec().into());
+        self.redaction
+            .trace("Emitting", &self.nsp, &event, &message);
+        let attachment = self.builder.read().await.codecs.encode(&event, message)?;
+        let (args, _) = self.tag_payload(crate::proto::args::<M>(), None, false);
+        let packet = crate::proto::packet(event, args, attachment, &self.nsp)?;
+        self.touch();
+        self.socket.read().await.send(packet).await
     }
 
     /// Disconnects this client from the server by sending a `socket.io` closing
@@ -286,6 +704,11 @@ impl Client {
     /// ```
     pub async fn disconnect(&self) -> Result<()> {
         *(self.disconnect_reason.write().await) = DisconnectReason::Manual;
+        self.uptime.disconnected();
+        self.set_namespace_state(NamespaceState::Disconnected, None)
+            .await;
+        let dropped = self.gate.set_state(ConnectionState::Disconnected)?;
+        self.fail_dropped(dropped).await;
 
         let disconnect_packet =
             Packet::new(PacketId::Disconnect, self.nsp.clone(), None, None, 0, None);
@@ -296,172 +719,883 @@ impl Client {
         Ok(())
     }
 
-    /// Sends a message to the server but `alloc`s an `ack` to check whether the
-    /// server responded in a given time span. This message takes an event, which
-    /// could either be one of the common events like "message" or "error" or a
-    /// custom event like "foo", as well as a data parameter. But be careful,
-    /// in case you send a [`Payload::String`], the string needs to be valid JSON.
-    /// It's even recommended to use a library like serde_json to serialize the data properly.
-    /// It also requires a timeout `Duration` in which the client needs to answer.
-    /// If the ack is acked in the correct time span, the specified callback is
-    /// called. The callback consumes a [`Payload`] which represents the data send
-    /// by the server.
+    /// Closes the connection right away, without sending a `Disconnect` packet
+    /// or anything else to the server. Use this instead of
+    /// [`Client::disconnect`] if no more bytes must be sent, e.g. after the
+    /// credentials of the connection were revoked. Like on a disconnect,
+    /// buffered emits are dropped, pending acks are failed and the client
+    /// doesn't reconnect.
+    pub async fn abort(&self) -> Result<()> {
+        *(self.disconnect_reason.write().await) = DisconnectReason::Manual;
+        self.uptime.disconnected();
+        self.set_namespace_state(NamespaceState::Disconnected, None)
+            .await;
+        let dropped = self.gate.set_state(ConnectionState::Disconnected)?;
+        self.fail_dropped(dropped).await;
+
+        self.socket.read().await.abort();
+        self.close_outstanding_acks(false).await;
+
+        self.callback(&Event::Close, "").await
+    }
+
+    /// Closes the current connection and connects again with the configuration
+    /// of the [`ClientBuilder`], e.g. if the application detected a stale
+    /// connection on its own. The new connection is established in the
+    /// background like any other reconnect, with the configured backoff and
+    /// the [`ClientBuilder::on_reconnect`] handler, even if reconnecting is
+    /// disabled. Emits made in the meantime follow the [`crate::EmitPolicy`], use
+    /// [`Client::watch_state`] to wait for the reconnect.
+    ///
+    /// Fails if the client is disconnected for good, does nothing if it's
+    /// reconnecting already.
     ///
-    /// Please note that the requirements on the provided callbacks are similar to the ones
-    /// for [`crate::asynchronous::ClientBuilder::on`].
     /// # Example
-    /// ```
-    /// use rust_socketio::{asynchronous::{ClientBuilder, Client}, Payload};
-    /// use serde_json::json;
-    /// use std::time::Duration;
-    /// use std::thread::sleep;
-    /// use futures_util::FutureExt;
+    /// ```no_run
+    /// use rust_socketio::{asynchronous::ClientBuilder, ConnectionState};
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut socket = ClientBuilder::new("http://localhost:4200/")
-    ///         .on("foo", |payload: Payload, _| async move { println!("Received: {:#?}", payload) }.boxed())
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
     ///         .connect()
     ///         .await
     ///         .expect("connection failed");
     ///
-    ///     let ack_callback = |message: Payload, socket: Client| {
-    ///         async move {
-    ///             match message {
-    ///                 Payload::Text(values) => println!("{:#?}", values),
-    ///                 Payload::Binary(bytes) => println!("Received bytes: {:#?}", bytes),
-    ///                 // This is deprecated use Payload::Text instead
-    ///                 Payload::String(str) => println!("{}", str),
-    ///             }
-    ///         }.boxed()
-    ///     };
-    ///
-    ///
-    ///     let payload = json!({"token": 123});
-    ///     socket.emit_with_ack("foo", payload, Duration::from_secs(2), ack_callback).await.unwrap();
-    ///
-    ///     sleep(Duration::from_secs(2));
+    ///     let mut state = socket.watch_state();
+    ///     socket.reconnect().await.expect("client is disconnected");
+    ///     state
+    ///         .wait_for(|state| *state == ConnectionState::Connected)
+    ///         .await
+    ///         .expect("client was dropped");
     /// }
     /// ```
-    #[inline]
-    pub async fn emit_with_ack<F, E, D>(
-        &self,
-        event: E,
-        data: D,
-        timeout: Duration,
-        callback: F,
-    ) -> Result<()>
-    where
-        F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
-            + 'static
-            + Send
-            + Sync,
-        E: Into<Event>,
-        D: Into<Payload>,
-    {
-        let id = thread_rng().gen_range(0..999);
-        let socket_packet =
-            Packet::new_from_payload(data.into(), event.into(), &self.nsp, Some(id))?;
+    pub async fn reconnect(&self) -> Result<()> {
+        match self.gate.state()? {
+            ConnectionState::Connected => {}
+            ConnectionState::Reconnecting => return Ok(()),
+            ConnectionState::Disconnected => return Err(Error::IllegalActionBeforeOpen()),
+        }
+        *(self.disconnect_reason.write().await) = DisconnectReason::Requested;
+        self.gate.set_state(ConnectionState::Reconnecting)?;
 
-        let ack = Ack {
-            id,
-            time_started: Instant::now(),
-            timeout,
-            callback: Callback::<DynAsyncCallback>::new(callback),
-        };
+        let disconnect_packet =
+            Packet::new(PacketId::Disconnect, self.nsp.clone(), None, None, 0, None);
+        let socket = self.socket.read().await;
+        // the connection is closed anyway
+        let _ = socket.send(disconnect_packet).await;
+        socket.disconnect().await
+    }
 
-        // add the ack to the tuple of outstanding acks
-        self.outstanding_acks.write().await.push(ack);
+    /// Returns statistics about the pool of buffers that packets are encoded with.
+    pub async fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.socket.read().await.buffer_pool_stats()
+    }
 
-        self.socket.read().await.send(socket_packet).await
+    /// Returns how long the emits of the current connection waited for the
+    /// transport and how long the transport took to write them, `None` unless
+    /// enabled via [`ClientBuilder::emit_timing`]. Waiting for a slow socket
+    /// lock while reconnecting isn't included.
+    pub async fn emit_timing_stats(&self) -> Option<EmitTimingStats> {
+        self.socket.read().await.emit_timing_stats()
     }
 
-    async fn callback<P: Into<Payload>>(&self, event: &Event, payload: P) -> Result<()> {
-        let mut builder = self.builder.write().await;
-        let payload = payload.into();
+    /// Returns the HTTP version that was negotiated with the server, `None` if
+    /// a websocket transport is used.
+    pub async fn http_version(&self) -> Option<HttpVersion> {
+        self.socket.read().await.http_version()
+    }
 
-        if let Some(callback) = builder.on.get_mut(event) {
-            callback(payload.clone(), self.clone()).await;
-        }
+    /// Returns the events that handlers are registered for, together with the
+    /// number of handlers per event. The `on_any` handler is not included.
+    pub async fn handlers(&self) -> HashMap<Event, usize> {
+        self.builder.read().await.on.counts()
+    }
 
-        // Call on_any for all common and custom events.
-        match event {
-            Event::Message | Event::Custom(_) => {
-                if let Some(callback) = builder.on_any.as_mut() {
-                    callback(event.clone(), payload, self.clone()).await;
-                }
-            }
-            _ => (),
-        }
+    /// Returns a [`watch`] channel that holds the current [`ConnectionState`]
+    /// of the client, so that several parts of an application can follow
+    /// disconnects and reconnects without registering callbacks.
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.gate.watch_state()
+    }
 
-        Ok(())
+    /// Returns when the client connected and reconnected, how long it was
+    /// connected and disconnected in total and how often it disconnected. The
+    /// client counts as connected once the server accepted the connection to
+    /// its namespace.
+    pub fn uptime_stats(&self) -> UptimeStats {
+        self.uptime.stats()
     }
 
-    /// Handles the incoming acks and classifies what callbacks to call and how.
-    #[inline]
-    async fn handle_ack(&self, socket_packet: &Packet) -> Result<()> {
-        let mut to_be_removed = Vec::new();
-        if let Some(id) = socket_packet.id {
-            for (index, ack) in self.outstanding_acks.write().await.iter_mut().enumerate() {
-                if ack.id == id {
-                    to_be_removed.push(index);
-
-                    if ack.time_started.elapsed() < ack.timeout {
-                        if let Some(ref payload) = socket_packet.data {
-                            ack.callback.deref_mut()(
-                                Payload::from(payload.to_owned()),
-                                self.clone(),
-                            )
-                            .await;
-                        }
-                        if let Some(ref attachments) = socket_packet.attachments {
-                            if let Some(payload) = attachments.get(0) {
-                                ack.callback.deref_mut()(
-                                    Payload::Binary(payload.to_owned()),
-                                    self.clone(),
-                                )
-                                .await;
-                            }
-                        }
-                    } else {
-                        trace!("Received an Ack that is now timed out (elapsed time was longer than specified duration)");
-                    }
-                }
-            }
-            for index in to_be_removed {
-                self.outstanding_acks.write().await.remove(index);
-            }
-        }
-        Ok(())
+    /// Returns the sequence number the next emit gets, `None` if sequence
+    /// numbers aren't enabled via [`ClientBuilder::sequence_numbers`]. Persist
+    /// it on shutdown in order to continue the numbers after a restart.
+    pub fn next_sequence(&self) -> Option<u64> {
+        self.sequence.as_ref().map(SequenceCounter::peek)
     }
 
-    /// Handles a binary event.
-    #[inline]
-    async fn handle_binary_event(&self, packet: &Packet) -> Result<()> {
-        let event = if let Some(string_data) = &packet.data {
-            string_data.replace('\"', "").into()
-        } else {
-            Event::Message
-        };
+    /// Whether the server restored the session on the last connect, i.e. the
+    /// client rejoined its rooms and received the events it missed while it was
+    /// disconnected. Requires a server with connection state recovery enabled
+    /// (socket.io 4.6 or later), otherwise it's always `false` and the
+    /// application has to resynchronize its state after a reconnect.
+    ///
+    /// The server appends an offset to the events it broadcasts while
+    /// recovery is enabled, which the handlers receive as the last argument.
+    pub fn recovered(&self) -> bool {
+        self.recovery.recovered()
+    }
 
-        if let Some(attachments) = &packet.attachments {
-            if let Some(binary_payload) = attachments.get(0) {
-                self.callback(&event, Payload::Binary(binary_payload.to_owned()))
-                    .await?;
-            }
-        }
-        Ok(())
+    /// Returns the state of the namespaces of the client together with their
+    /// last error, e.g. whether the server accepted the connection yet or why
+    /// it refused it.
+    pub fn namespaces(&self) -> HashMap<String, NamespaceStatus> {
+        self.namespaces.snapshot()
     }
 
-    /// A method that parses a packet and eventually calls the corresponding
-    /// callback with the supplied data.
-    async fn handle_event(&self, packet: &Packet) -> Result<()> {
-        let Some(ref data) = packet.data else {
-            return Ok(());
-        };
+    /// Returns the connection state, the state of the namespace and the
+    /// transport of the client at once, without taking a lock, see
+    /// [`ClientSnapshot`].
+    pub fn snapshot(&self) -> ClientSnapshot {
+        self.gate.snapshot().load()
+    }
 
-        // a socketio message always comes in one of the following two flavors (both JSON):
-        // 1: `["event", "msg", ...]`
+    /// Whether the client is connected and the server accepted its namespace,
+    /// read without taking a lock.
+    pub fn is_connected(&self) -> bool {
+        self.snapshot().is_connected()
+    }
+
+    /// Returns how many emits are buffered until the client reconnected and
+    /// how many acks are outstanding, see [`PendingStats`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::{asynchronous::ClientBuilder, EmitPolicy};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_policy(EmitPolicy::Buffer)
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     let pending = socket.pending().await.expect("client is intact");
+    ///     if pending.buffered > 0 {
+    ///         println!("{} updates pending", pending.buffered);
+    ///     }
+    /// }
+    /// ```
+    pub async fn pending(&self) -> Result<PendingStats> {
+        let (buffered, buffered_bytes) = self.gate.buffered()?;
+        Ok(PendingStats {
+            buffered,
+            buffered_bytes,
+            acks: self.outstanding_acks.read().await.len(),
+        })
+    }
+
+    /// Records the state of the namespace of the client and calls the handler
+    /// registered via [`ClientBuilder::on_namespace_state_change`] if it changed.
+    async fn set_namespace_state(&self, state: NamespaceState, error: Option<String>) {
+        self.gate.snapshot().set_namespace(state);
+        let Some(status) = self.namespaces.update(&self.nsp, state, error) else {
+            return;
+        };
+        if let Some(handler) = &self.on_namespace_state_change {
+            handler(self.nsp.clone(), status).await;
+        }
+    }
+
+    /// Registers a new callback for a certain [`crate::event::Event`] on the
+    /// connected client. The callback is called after the callbacks that were
+    /// registered before, it's kept across reconnects. This may be called from
+    /// within a callback that was registered via `on` or `once`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::{asynchronous::ClientBuilder, Payload};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     socket
+    ///         .on("test", |payload: Payload, _| {
+    ///             async move { println!("Received: {:#?}", payload) }.boxed()
+    ///         })
+    ///         .await;
+    ///     socket.off("test").await;
+    /// }
+    /// ```
+    #[cfg(feature = "async-callbacks")]
+    pub async fn on<T: Into<Event>, F>(&self, event: T, callback: F)
+    where
+        F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
+            + 'static
+            + Send
+            + Sync,
+    {
+        self.builder.write().await.on.add(
+            event.into(),
+            Callback::<DynAsyncCallback>::new(callback),
+            false,
+        );
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] on the
+    /// connected client that is removed after it was called once.
+    #[cfg(feature = "async-callbacks")]
+    pub async fn once<T: Into<Event>, F>(&self, event: T, callback: F)
+    where
+        F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
+            + 'static
+            + Send
+            + Sync,
+    {
+        self.builder.write().await.on.add(
+            event.into(),
+            Callback::<DynAsyncCallback>::new(callback),
+            true,
+        );
+    }
+
+    /// Removes all callbacks that are registered for a certain [`crate::event::Event`].
+    pub async fn off<T: Into<Event>>(&self, event: T) {
+        self.builder.write().await.on.remove(&event.into());
+    }
+
+    /// Removes the callbacks of all events. The `on_any` callback is kept.
+    pub async fn off_all(&self) {
+        self.builder.write().await.on.clear();
+    }
+
+    /// Registers a codec for the payloads of a certain [`crate::event::Event`] on
+    /// the connected client, replacing a previously registered one. The codec is
+    /// kept across reconnects. See [`PayloadCodec`] for details.
+    pub async fn set_codec<T: Into<Event>, C>(&self, event: T, codec: C)
+    where
+        C: PayloadCodec + 'static,
+    {
+        self.builder
+            .write()
+            .await
+            .codecs
+            .insert(event.into(), Arc::new(codec));
+    }
+
+    /// Sends a message to the server like [`Client::emit`], but drops it if it
+    /// can't be sent until `deadline`, e.g. because it's buffered while the
+    /// client reconnects. See [`EmitOptions::deadline`].
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{asynchronous::ClientBuilder, EmitPolicy};
+    /// use serde_json::json;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .emit_policy(EmitPolicy::Buffer)
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     // a position update is worthless after a second
+    ///     let deadline = Instant::now() + Duration::from_secs(1);
+    ///     let result = socket
+    ///         .emit_until("position", json!({"x": 1, "y": 2}), deadline)
+    ///         .await;
+    ///
+    ///     assert!(result.is_ok());
+    /// }
+    /// ```
+    pub async fn emit_until<E, D>(
+        &self,
+        event: E,
+        data: D,
+        deadline: std::time::Instant,
+    ) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.emit_opts(event, data, EmitOptions::new().deadline(deadline))
+            .await
+    }
+
+    /// Sends a message to the server but `alloc`s an `ack` to check whether the
+    /// server responded in a given time span. This message takes an event, which
+    /// could either be one of the common events like "message" or "error" or a
+    /// custom event like "foo", as well as a data parameter. But be careful,
+    /// in case you send a [`Payload::String`], the string needs to be valid JSON.
+    /// It's even recommended to use a library like serde_json to serialize the data properly.
+    /// It also requires a timeout `Duration` in which the client needs to answer.
+    /// If the ack is acked in the correct time span, the specified callback is
+    /// called. The callback consumes a [`Payload`] which represents the data send
+    /// by the server. If the connection closes before, the callback isn't called
+    /// and [`Error::Disconnected`] is reported to the callbacks of
+    /// [`Event::Error`], use [`Client::emit_with_ack_result`] to receive the
+    /// error instead.
+    ///
+    /// Please note that the requirements on the provided callbacks are similar to the ones
+    /// for [`crate::asynchronous::ClientBuilder::on`].
+    /// # Example
+    /// ```
+    /// use rust_socketio::{asynchronous::{ClientBuilder, Client}, Payload};
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    /// use std::thread::sleep;
+    /// use futures_util::FutureExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .on("foo", |payload: Payload, _| async move { println!("Received: {:#?}", payload) }.boxed())
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     let ack_callback = |message: Payload, socket: Client| {
+    ///         async move {
+    ///             match message {
+    ///                 Payload::Text(values) => println!("{:#?}", values),
+    ///                 Payload::Binary(bytes) => println!("Received bytes: {:#?}", bytes),
+    ///                 // This is deprecated use Payload::Text instead
+    ///                 Payload::String(str) => println!("{}", str),
+    ///             }
+    ///         }.boxed()
+    ///     };
+    ///
+    ///
+    ///     let payload = json!({"token": 123});
+    ///     socket.emit_with_ack("foo", payload, Duration::from_secs(2), ack_callback).await.unwrap();
+    ///
+    ///     sleep(Duration::from_secs(2));
+    /// }
+    /// ```
+    #[inline]
+    pub async fn emit_with_ack<F, E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
+            + 'static
+            + Send
+            + Sync,
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.emit_with_ack_opts(event, data, timeout, callback, EmitOptions::default())
+            .await
+    }
+
+    /// Sends a message to the server and requests an ack like
+    /// [`Client::emit_with_ack`], but allows to override the settings of the
+    /// client for this single emit. Set [`EmitOptions::resend_on_reconnect`] in
+    /// order to send the emit again after a reconnect if the connection drops
+    /// before the ack was received.
+    pub async fn emit_with_ack_opts<F, E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+        callback: F,
+        options: EmitOptions,
+    ) -> Result<()>
+    where
+        F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
+            + 'static
+            + Send
+            + Sync,
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let resend_on_reconnect = match options.resend_on_reconnect {
+            Some(resend) => resend,
+            None => self.builder.read().await.resend_on_reconnect,
+        };
+        self.send(
+            BufferedEmit {
+                event: event.into(),
+                payload: data.into(),
+                ack: Some((timeout, Box::new(callback))),
+                resend_on_reconnect,
+                deadline: options.deadline,
+                sequence: None,
+                on_failure: None,
+            },
+            options,
+        )
+        .await
+    }
+
+    /// Sends a message to the server and requests an ack like
+    /// [`Client::emit_with_ack`], but also passes the emit that requested the
+    /// ack to the callback, which saves correlating the response with the
+    /// request. The payload is only retained up to the size set via
+    /// [`ClientBuilder::ack_request_limit`].
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{asynchronous::{Client, ClientBuilder}, AckRequest, Payload};
+    /// use serde_json::json;
+    /// use futures_util::FutureExt;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     let result = socket
+    ///         .emit_with_ack_request(
+    ///             "get_user",
+    ///             json!({"id": 42}),
+    ///             Duration::from_secs(2),
+    ///             |request: AckRequest, response: Payload, _: Client| {
+    ///                 async move { println!("{:?} answered with {:?}", request.payload(), response) }
+    ///                     .boxed()
+    ///             },
+    ///         )
+    ///         .await;
+    ///
+    ///     assert!(result.is_ok());
+    /// }
+    /// ```
+    pub async fn emit_with_ack_request<F, E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(AckRequest, Payload, Client) -> BoxFuture<'static, ()> + 'static + Send + Sync,
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let (event, payload) = (event.into(), data.into());
+        let limit = self.builder.read().await.ack_request_limit;
+        let request = AckRequest::retain(event.clone(), &payload, limit);
+        self.emit_with_ack(event, payload, timeout, move |response, client| {
+            callback(request.clone(), response, client)
+        })
+        .await
+    }
+
+    /// Sends a message to the server, requests an ack like
+    /// [`Client::emit_with_ack`] and waits for it. Unlike the callback of
+    /// [`Client::emit_with_ack`], the result tells why an ack wasn't received:
+    /// [`Error::AckTimeout`] if the server didn't answer within `timeout`, and
+    /// [`Error::Disconnected`] as soon as the connection the ack was requested
+    /// over closes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::asynchronous::ClientBuilder;
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let socket = ClientBuilder::new("http://localhost:4200/")
+    ///         .connect()
+    ///         .await
+    ///         .expect("connection failed");
+    ///
+    ///     let ack = socket
+    ///         .emit_with_ack_result("get_user", json!({"id": 42}), Duration::from_secs(2))
+    ///         .await;
+    ///     println!("the server answered {ack:?}");
+    /// }
+    /// ```
+    pub async fn emit_with_ack_result<E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+    ) -> Result<Payload>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let event = event.into();
+        let (tx, rx) = ack_channel();
+        let failed = tx.clone();
+        self.send(
+            BufferedEmit {
+                event: event.clone(),
+                payload: data.into(),
+                ack: Some((
+                    timeout,
+                    Box::new(move |payload, _| {
+                        tx.send(Ok(payload));
+                        async {}.boxed()
+                    }),
+                )),
+                resend_on_reconnect: self.builder.read().await.resend_on_reconnect,
+                deadline: None,
+                sequence: None,
+                on_failure: Some(AckFailure::new(move |err| failed.send(Err(err)))),
+            },
+            EmitOptions::default(),
+        )
+        .await?;
+        receive_ack(event, timeout, rx).await
+    }
+
+    async fn send(&self, emit: BufferedEmit<DynAsyncCallback>, options: EmitOptions) -> Result<()> {
+        if options.volatile && self.gate.state()? != ConnectionState::Connected {
+            self.fail_dropped(vec![emit]).await;
+            return Ok(());
+        }
+        let policy = match options.policy {
+            Some(policy) => policy,
+            None => self.builder.read().await.emit_policy,
+        };
+        let emit = self.gate.coalesce(&self.coalescer, policy, emit)?;
+
+        match self.gate.admit_async(policy, emit).await? {
+            Admission::Send(emit) => self.send_raw(emit).await,
+            Admission::Buffered(Some(dropped)) => {
+                self.report_overflow(dropped).await;
+                Ok(())
+            }
+            Admission::Buffered(None) => Ok(()),
+        }
+    }
+
+    /// Reports an emit that was dropped as the buffer was full, and fails its
+    /// ack.
+    async fn report_overflow(&self, emit: BufferedEmit<DynAsyncCallback>) {
+        if let Err(e) = self.error(&Error::EmitDropped(emit.event.clone())).await {
+            trace!("Failed to report dropped emit: {e:?}");
+        }
+        self.fail_dropped(vec![emit]).await;
+    }
+
+    async fn send_raw(&self, emit: BufferedEmit<DynAsyncCallback>) -> Result<()> {
+        if emit.expired() {
+            let err = Error::EmitExpired(emit.event);
+            self.error(&err).await?;
+            return Err(err);
+        }
+        self.redaction
+            .trace("Emitting", &self.nsp, &emit.event, &emit.payload);
+        let resend = (emit.ack.is_some() && emit.resend_on_reconnect).then(|| emit.payload.clone());
+        let payload = {
+            let builder = self.builder.read().await;
+            let payload = builder.codecs.encode(&emit.event, emit.payload)?;
+            builder.schemas.tag(&emit.event, payload)
+        };
+        let (payload, sequence) = self.tag_payload(payload, emit.sequence, emit.ack.is_some());
+        self.touch();
+        let Some((timeout, callback)) = emit.ack else {
+            return self
+                .socket
+                .read()
+                .await
+                .emit(&self.nsp, emit.event, payload)
+                .await;
+        };
+
+        let id = thread_rng().gen_range(0..999);
+        let socket_packet =
+            Packet::new_from_payload(payload, emit.event.clone(), &self.nsp, Some(id))?;
+
+        let ack = Ack {
+            id,
+            time_started: Instant::now(),
+            timeout,
+            callback: Callback::<DynAsyncCallback>::new(callback),
+            event: emit.event,
+            resend,
+            sequence,
+            on_failure: emit.on_failure,
+        };
+
+        // add the ack to the tuple of outstanding acks
+        self.outstanding_acks.write().await.push(ack);
+
+        self.socket.read().await.send(socket_packet).await
+    }
+
+    /// Marks the client as connected and sends the emits that were buffered
+    /// while reconnecting. Called once the server acknowledged the namespace,
+    /// as it drops the events that arrive before.
+    async fn flush_buffered_emits(&self) {
+        if !matches!(self.gate.state(), Ok(ConnectionState::Reconnecting)) {
+            return;
+        }
+        let buffered = match self.gate.set_state(ConnectionState::Connected) {
+            Ok(buffered) => buffered,
+            Err(e) => {
+                trace!("Failed to update the connection state: {e:?}");
+                return;
+            }
+        };
+
+        for emit in buffered {
+            if let Err(e) = self.send_raw(emit).await {
+                trace!("Failed to send buffered emit: {e:?}");
+            }
+        }
+    }
+
+    /// Removes the outstanding acks, as the connection they were requested over
+    /// closed. If `resend` is set, the emits that are flagged to be resent after
+    /// a reconnect are buffered until the client reconnected, all other acks are
+    /// failed.
+    async fn close_outstanding_acks(&self, resend: bool) {
+        let acks = std::mem::take(&mut *self.outstanding_acks.write().await);
+        for ack in acks {
+            match ack.resend {
+                Some(payload) if resend => {
+                    let emit = BufferedEmit {
+                        event: ack.event,
+                        payload,
+                        ack: Some((ack.timeout, ack.callback.into_inner())),
+                        resend_on_reconnect: true,
+                        deadline: None,
+                        sequence: ack.sequence,
+                        on_failure: ack.on_failure,
+                    };
+                    match self.gate.rebuffer(emit) {
+                        Ok(Some(dropped)) => self.report_overflow(dropped).await,
+                        Ok(None) => {}
+                        Err(e) => trace!("Failed to buffer emit for resending: {e:?}"),
+                    }
+                }
+                _ => self.fail_ack(ack.event, ack.on_failure).await,
+            }
+        }
+    }
+
+    /// Fails the acks of emits that were dropped before they were sent.
+    async fn fail_dropped(&self, dropped: Vec<BufferedEmit<DynAsyncCallback>>) {
+        for emit in dropped.into_iter().filter(|emit| emit.ack.is_some()) {
+            self.fail_ack(emit.event, emit.on_failure).await;
+        }
+    }
+
+    /// Fails an ack that can't be received anymore, or reports it to the
+    /// `error` callbacks if the ack can't be failed itself.
+    async fn fail_ack(&self, event: Event, on_failure: Option<AckFailure>) {
+        let err = Error::Disconnected(event);
+        match on_failure {
+            Some(on_failure) => on_failure.fail(err),
+            None => {
+                if let Err(e) = self.error(&err).await {
+                    trace!("Failed to report failed ack: {e:?}");
+                }
+            }
+        }
+    }
+
+    /// Records a sent or received packet for closing idle connections.
+    fn touch(&self) {
+        if let Some(activity) = &self.activity {
+            activity.touch();
+        }
+    }
+
+    /// Tags the payload with its sequence number if sequence numbers are
+    /// enabled, and with the origin of this client if echo suppression is
+    /// enabled. `sequence` is the number of an emit that is resent. Returns the
+    /// sequence number the payload was tagged with.
+    ///
+    /// Emits that request an `ack` aren't tagged with the origin, as a server
+    /// handler like `(data, callback)` would receive the tag in place of the
+    /// ack callback.
+    #[inline]
+    fn tag_payload(
+        &self,
+        payload: Payload,
+        sequence: Option<u64>,
+        ack: bool,
+    ) -> (Payload, Option<u64>) {
+        let sequence = match (&self.sequence, &payload) {
+            (Some(counter), Payload::Text(_)) => Some(counter.assign(sequence)),
+            _ => None,
+        };
+        let payload = match sequence {
+            Some(sequence) => payload.with_sequence(sequence),
+            None => payload,
+        };
+        let payload = match self.origin {
+            Some(ref origin) if !ack => payload.with_origin(origin),
+            _ => payload,
+        };
+        (payload, sequence)
+    }
+
+    pub(crate) async fn callback<P: Into<Payload>>(&self, event: &Event, payload: P) -> Result<()> {
+        let payload = payload.into();
+        self.redaction.trace("Received", &self.nsp, event, &payload);
+
+        for bus in &self.builder.read().await.event_buses {
+            bus.forward(event, &payload);
+        }
+
+        // the builder isn't locked while the handlers are called, so that they
+        // can register and remove handlers themselves
+        let handlers = self.builder.write().await.on.fire(event);
+        let handled = !handlers.is_empty();
+        for handler in handlers {
+            let future = {
+                let mut callback = handler.callback.lock()?;
+                callback(payload.clone(), self.clone())
+            };
+            future.await;
+        }
+
+        // Call on_any for all common and custom events.
+        match event {
+            Event::Message | Event::Custom(_) => {
+                let mut builder = self.builder.write().await;
+                if let Some(callback) = builder.on_any.as_mut() {
+                    callback(event.clone(), payload, self.clone()).await;
+                } else if !handled {
+                    drop(builder);
+                    self.unhandled(event, payload).await?;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Reports an error to the `on_error` callback and, as a string, to the
+    /// callbacks of [`Event::Error`].
+    pub(crate) async fn error(&self, err: &Error) -> Result<()> {
+        let future = self
+            .builder
+            .write()
+            .await
+            .on_error
+            .as_mut()
+            .map(|callback| callback(err, self.clone()));
+        if let Some(future) = future {
+            future.await;
+        }
+        self.callback(&Event::Error, err.to_string()).await
+    }
+
+    /// Handles a received event without a handler as configured.
+    async fn unhandled(&self, event: &Event, payload: Payload) -> Result<()> {
+        let mut builder = self.builder.write().await;
+        if let Some(callback) = builder.on_unhandled.as_mut() {
+            callback(event.clone(), payload, self.clone()).await;
+        }
+        let policy = builder.unhandled_policy;
+        drop(builder);
+
+        match policy {
+            UnhandledPolicy::Ignore => Ok(()),
+            UnhandledPolicy::Warn => {
+                warn!(
+                    "Received event {event} on namespace {}, which has no handler",
+                    self.nsp
+                );
+                Ok(())
+            }
+            UnhandledPolicy::Error => {
+                let err = Error::UnhandledEvent(event.clone());
+                Box::pin(self.error(&err)).await
+            }
+        }
+    }
+
+    /// Handles the incoming acks and classifies what callbacks to call and how.
+    #[inline]
+    async fn handle_ack(&self, socket_packet: &Packet) -> Result<()> {
+        let Some(id) = socket_packet.id else {
+            return Ok(());
+        };
+        let acks: Vec<Ack> = {
+            let mut outstanding_acks = self.outstanding_acks.write().await;
+            let (acks, others) = outstanding_acks.drain(..).partition(|ack| ack.id == id);
+            *outstanding_acks = others;
+            acks
+        };
+
+        let mut payloads = Vec::new();
+        if let Some(ref payload) = socket_packet.data {
+            payloads.push(Payload::from(payload.to_owned()));
+        }
+        if let Some(payload) = socket_packet.attachments.as_ref().and_then(|a| a.first()) {
+            payloads.push(Payload::Binary(payload.to_owned()));
+        }
+        let dispatch = self.builder.read().await.ack_dispatch;
+        for mut ack in acks {
+            if ack.timed_out() {
+                trace!("Received an Ack that is now timed out (elapsed time was longer than specified duration)");
+                continue;
+            }
+            let (payloads, client) = (payloads.clone(), self.clone());
+            let call = async move {
+                for payload in payloads {
+                    ack.callback.deref_mut()(payload, client.clone()).await;
+                }
+            };
+            match (dispatch, &self.watchdog) {
+                (AckDispatch::Inline, _) => call.await,
+                (AckDispatch::Spawn, Some(watchdog)) => {
+                    tokio::spawn(watchdog.track(call.boxed()));
+                }
+                (AckDispatch::Spawn, None) => {
+                    tokio::spawn(call);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a binary event.
+    #[inline]
+    async fn handle_binary_event(&self, packet: &Packet) -> Result<()> {
+        let event = packet.binary_event();
+        if self.origin.is_some() && packet.binary_origin() == self.origin {
+            // this is an echo of one of our own protobuf messages
+            return Ok(());
+        }
+
+        if let Some(attachments) = &packet.attachments {
+            if let Some(binary_payload) = attachments.get(0) {
+                let payload = self
+                    .builder
+                    .read()
+                    .await
+                    .codecs
+                    .decode(&event, Payload::Binary(binary_payload.to_owned()))?;
+                self.callback(&event, payload).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A method that parses a packet and eventually calls the corresponding
+    /// callback with the supplied data.
+    async fn handle_event(&self, packet: &Packet) -> Result<()> {
+        let Some(ref data) = packet.data else {
+            return Ok(());
+        };
+
+        // a socketio message always comes in one of the following two flavors (both JSON):
+        // 1: `["event", "msg", ...]`
         // 2: `["msg"]`
         // in case 2, the message is ment for the default message event, in case 1 the event
         // is specified
@@ -479,8 +1613,16 @@ impl Client {
                 },
             };
 
+            self.recovery.received(payloads);
+            let mut payloads = payloads.to_vec();
+            if self.origin.is_some() && Payload::take_origin(&mut payloads) == self.origin {
+                // this is an echo of one of our own emits
+                return Ok(());
+            }
+            let payloads = self.builder.read().await.schemas.check(&event, payloads)?;
+
             // call the correct callback
-            self.callback(&event, payloads.to_vec()).await?;
+            self.callback(&event, payloads).await?;
         }
 
         Ok(())
@@ -495,21 +1637,23 @@ impl Client {
             match packet.packet_type {
                 PacketId::Ack | PacketId::BinaryAck => {
                     if let Err(err) = self.handle_ack(packet).await {
-                        self.callback(&Event::Error, err.to_string()).await?;
+                        self.error(&err).await?;
                         return Err(err);
                     }
                 }
                 PacketId::BinaryEvent => {
                     if let Err(err) = self.handle_binary_event(packet).await {
-                        self.callback(&Event::Error, err.to_string()).await?;
+                        self.error(&err).await?;
                     }
                 }
                 PacketId::Connect => {
-                    *(self.disconnect_reason.write().await) = DisconnectReason::default();
+                    let hooks = self.builder.read().await.after_connect.clone();
+                    for hook in hooks {
+                        hook(self.clone()).await;
+                    }
                     self.callback(&Event::Connect, "").await?;
                 }
                 PacketId::Disconnect => {
-                    *(self.disconnect_reason.write().await) = DisconnectReason::Server;
                     self.callback(&Event::Close, "").await?;
                 }
                 PacketId::ConnectError => {
@@ -525,11 +1669,73 @@ impl Client {
                 }
                 PacketId::Event => {
                     if let Err(err) = self.handle_event(packet).await {
-                        self.callback(&Event::Error, err.to_string()).await?;
+                        self.error(&err).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a packet right away, or queues it on the dispatcher if the client
+    /// shares one with other clients. The reason of a disconnect is recorded in
+    /// any case before the stream continues, as it decides about reconnecting.
+    async fn dispatch(&self, packet: &Packet) -> Result<()> {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.wait_for_capacity().await;
+        }
+        if packet.nsp == self.nsp {
+            self.touch();
+            match packet.packet_type {
+                PacketId::Connect => {
+                    self.recovery.connected(packet.data.as_deref());
+                    *(self.disconnect_reason.write().await) = DisconnectReason::default();
+                    self.uptime.connected();
+                    self.set_namespace_state(NamespaceState::Connected, None)
+                        .await;
+                    self.flush_buffered_emits().await;
+                }
+                PacketId::Disconnect => {
+                    *(self.disconnect_reason.write().await) = DisconnectReason::Server;
+                    self.uptime.disconnected();
+                    self.set_namespace_state(NamespaceState::Disconnected, None)
+                        .await;
+                }
+                PacketId::ConnectError => {
+                    let error = packet.data.clone().unwrap_or_default();
+                    self.set_namespace_state(NamespaceState::Failed, Some(error))
+                        .await;
+                    if matches!(self.gate.state(), Ok(ConnectionState::Reconnecting)) {
+                        // the namespace was refused, the buffered emits can't be delivered
+                        self.give_up_reconnecting().await;
                     }
                 }
+                _ => {}
+            }
+        }
+
+        if let Some(watchdog) = &self.watchdog {
+            if watchdog.drops(packet) {
+                trace!("Dropped an event while the callbacks are overloaded");
+                return Ok(());
+            }
+        }
+
+        let Some(dispatcher) = &self.dispatcher else {
+            return self.handle_socketio_packet(packet).await;
+        };
+        let client = self.clone();
+        let packet = packet.clone();
+        let job = async move {
+            if let Err(e) = client.handle_socketio_packet(&packet).await {
+                trace!("Failed to handle dispatched packet: {e:?}");
             }
         }
+        .boxed();
+        match &self.watchdog {
+            Some(watchdog) => dispatcher.dispatch(watchdog.track(job)),
+            None => dispatcher.dispatch(job),
+        }
         Ok(())
     }
 
@@ -547,12 +1753,12 @@ impl Client {
                 None => None,
                 Some(Err(err)) => {
                     // call the error callback
-                    match self.callback(&Event::Error, err.to_string()).await {
+                    match self.error(&err).await {
                         Err(callback_err) => Some((Err(callback_err), socket)),
                         Ok(_) => Some((Err(err), socket)),
                     }
                 }
-                Some(Ok(packet)) => match self.handle_socketio_packet(&packet).await {
+                Some(Ok(packet)) => match self.dispatch(&packet).await {
                     Err(callback_err) => Some((Err(callback_err), socket)),
                     Ok(_) => Some((Ok(packet), socket)),
                 },
@@ -562,6 +1768,30 @@ impl Client {
     }
 }
 
+#[async_trait::async_trait]
+impl crate::DynClient for Client {
+    async fn emit(&self, event: Event, payload: Payload) -> Result<()> {
+        Client::emit(self, event, payload).await
+    }
+
+    async fn emit_with_ack(
+        &self,
+        event: Event,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<Payload> {
+        Client::emit_with_ack_result(self, event, payload, timeout).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Client::disconnect(self).await
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.gate.snapshot().connection()
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -579,19 +1809,22 @@ mod test {
     use serde_json::json;
     use serial_test::serial;
     use tokio::{
-        sync::mpsc,
+        sync::{mpsc, watch},
         time::{sleep, timeout},
     };
 
+    use super::reconnect_backoff;
     use crate::{
         asynchronous::{
             client::{builder::ClientBuilder, client::Client},
-            ReconnectSettings,
+            EventBus, ReconnectSettings,
         },
         error::Result,
         packet::{Packet, PacketId},
-        Payload, TransportType,
+        AckDispatch, BearerAuth, CodecError, ConnectionState, Error, Event, NamespaceState,
+        Payload, PayloadCodec, ReconnectEvent, TransportType,
     };
+    use backoff::backoff::Backoff;
 
     #[tokio::test]
     async fn socket_io_integration() -> Result<()> {
@@ -649,37 +1882,342 @@ mod test {
     }
 
     #[tokio::test]
-    async fn socket_io_async_callback() -> Result<()> {
-        // Test whether asynchronous callbacks are fully executed.
+    async fn socket_io_spawned_ack() -> Result<()> {
+        let url = crate::test::socket_io_server();
+
+        let socket = ClientBuilder::new(url)
+            .ack_dispatch(AckDispatch::Spawn)
+            .connect()
+            .await?;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut tx = Some(tx);
+        socket
+            .emit_with_ack(
+                "test",
+                json!({"token": 123_i32}),
+                Duration::from_secs(1),
+                move |message: Payload, _| {
+                    if let Some(tx) = tx.take() {
+                        let _ = tx.send(message);
+                    }
+                    async {}.boxed()
+                },
+            )
+            .await?;
+        let ack = timeout(Duration::from_secs(2), rx).await;
+        assert!(matches!(ack, Ok(Ok(Payload::Text(_)))));
+
+        assert!(socket.disconnect().await.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_async_callback() -> Result<()> {
+        // Test whether asynchronous callbacks are fully executed.
+        let url = crate::test::socket_io_server();
+
+        // This synchronization mechanism is used to let the test know that the end of the
+        // async callback was reached.
+        let notify = Arc::new(tokio::sync::Notify::new());
+        let notify_clone = notify.clone();
+
+        let socket = ClientBuilder::new(url)
+            .on("test", move |_, _| {
+                let cl = notify_clone.clone();
+                async move {
+                    sleep(Duration::from_secs(1)).await;
+                    // The async callback should be awaited and not aborted.
+                    // Thus, the notification should be called.
+                    cl.notify_one();
+                }
+                .boxed()
+            })
+            .connect()
+            .await?;
+
+        let payload = json!({"token": 123_i32});
+        let result = socket.emit("test", Payload::from(payload)).await;
+
+        assert!(result.is_ok());
+        // If the timeout did not trigger, the async callback was fully executed.
+        let timeout = timeout(Duration::from_secs(5), notify.notified()).await;
+        assert!(timeout.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_register_after_connect() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let socket = ClientBuilder::new(url).connect().await?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let once_tx = tx.clone();
+        socket
+            .once("test-received", move |_, _| {
+                once_tx.send("once").unwrap();
+                async {}.boxed()
+            })
+            .await;
+        socket
+            .on("test-received", move |_, client: Client| {
+                tx.send("on").unwrap();
+                // handlers can be removed from within a callback
+                async move { client.off("test-received").await }.boxed()
+            })
+            .await;
+        assert_eq!(
+            socket.handlers().await.get(&Event::from("test-received")),
+            Some(&2)
+        );
+
+        socket.emit("test", json!("first")).await?;
+        assert_eq!(
+            timeout(Duration::from_secs(5), rx.recv()).await.unwrap(),
+            Some("once")
+        );
+        assert_eq!(
+            timeout(Duration::from_secs(5), rx.recv()).await.unwrap(),
+            Some("on")
+        );
+
+        socket.emit("test", json!("second")).await?;
+        assert!(timeout(Duration::from_secs(1), rx.recv()).await.is_err());
+        assert!(socket.handlers().await.is_empty());
+
+        socket.disconnect().await?;
+        Ok(())
+    }
+
+    /// Sends the text arguments of an event as a comma separated list.
+    struct CsvCodec;
+
+    impl PayloadCodec for CsvCodec {
+        fn encode(&self, payload: Payload) -> std::result::Result<Bytes, CodecError> {
+            match payload {
+                Payload::Text(values) => Ok(Bytes::from(
+                    values
+                        .iter()
+                        .map(|value| value.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )),
+                _ => Err("only text payloads are supported".into()),
+            }
+        }
+
+        fn decode(&self, data: Bytes) -> std::result::Result<Payload, CodecError> {
+            let values = std::str::from_utf8(&data)?
+                .split(',')
+                .map(serde_json::from_str)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(Payload::Text(values))
+        }
+    }
+
+    #[tokio::test]
+    async fn socket_io_codec_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let socket = ClientBuilder::new(url)
+            .codec("binary", CsvCodec)
+            .on("binary-received", move |payload, _| {
+                tx.send(payload).unwrap();
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        socket.set_codec("binary-received", CsvCodec).await;
+
+        // the server echoes the binary attachment, which is decoded again
+        socket
+            .emit("binary", Payload::Text(vec![json!(1), json!("two")]))
+            .await?;
+        let payload = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert_eq!(payload, Some(Payload::Text(vec![json!(1), json!("two")])));
+
+        socket.disconnect().await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "prost")]
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Ping {
+        #[prost(string, tag = "1")]
+        text: String,
+    }
+
+    #[cfg(feature = "prost")]
+    impl prost::Name for Ping {
+        const NAME: &'static str = "Ping";
+        const PACKAGE: &'static str = "test";
+    }
+
+    #[cfg(feature = "prost")]
+    #[tokio::test]
+    async fn socket_io_proto_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let socket = ClientBuilder::new(url)
+            .on_proto::<Ping>("proto-received", move |ping, _| {
+                tx.send(ping).unwrap();
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+
+        // the server echoes the type name and the attachment
+        let ping = Ping {
+            text: "hello".to_owned(),
+        };
+        socket.emit_proto("proto", &ping).await?;
+        let received = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert_eq!(received, Some(ping));
+
+        socket.disconnect().await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "prost")]
+    #[tokio::test]
+    async fn test_proto_tagged() -> Result<()> {
+        use crate::asynchronous::loopback;
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        /// Reverses the bytes of binary payloads.
+        struct ReverseCodec;
+
+        impl PayloadCodec for ReverseCodec {
+            fn encode(&self, payload: Payload) -> std::result::Result<Bytes, CodecError> {
+                match payload {
+                    Payload::Binary(data) => Ok(data.iter().rev().copied().collect()),
+                    _ => Err("only binary payloads are supported".into()),
+                }
+            }
+
+            fn decode(&self, data: Bytes) -> std::result::Result<Payload, CodecError> {
+                Ok(Payload::Binary(data.iter().rev().copied().collect()))
+            }
+        }
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let echo = tx.clone();
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .codec("proto", ReverseCodec)
+            .suppress_echo(true)
+            .sequence_numbers(5)
+            .on_proto::<Ping>("proto", move |ping, _| {
+                let _ = echo.send(ping.text);
+                async {}.boxed()
+            })
+            .on("greeting", move |_, _| {
+                let _ = tx.send("greeting".to_owned());
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+
+        // the messages of the client, without the pongs of the heartbeat
+        let next = || async {
+            loop {
+                let packet = server.receive().await.unwrap();
+                if packet.packet_id != EnginePacketId::Pong {
+                    return packet;
+                }
+            }
+        };
+        assert_eq!(next().await.data, Bytes::from("0"));
+
+        let ping = Ping {
+            text: "hello".to_owned(),
+        };
+        client.emit_proto("proto", &ping).await?;
+        let event = next().await;
+        let attachment = next().await;
+        let data = String::from_utf8(event.data.to_vec()).unwrap();
+        assert!(data.starts_with(
+            "51-[\"proto\",\"test.Ping\",{\"__rust_socketio_seq\":5},{\"__rust_socketio_origin\":"
+        ));
+        let encoded = prost::Message::encode_to_vec(&ping);
+        let reversed: Vec<u8> = encoded.iter().rev().copied().collect();
+        assert_eq!(attachment.data, Bytes::from(reversed));
+
+        // the echo of the message is dropped, a regular event is not
+        server.send(event)?;
+        server.send(attachment)?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "2[\"greeting\",\"hello\"]",
+        ))?;
+        assert_eq!(rx.recv().await, Some("greeting".to_owned()));
+
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_ack_failed_on_disconnect() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let socket = ClientBuilder::new(url)
+            .on(Event::Error, move |payload, _| {
+                tx.send(payload).unwrap();
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+
+        // the server never answers the ack of this event
+        socket
+            .emit_with_ack("no-ack", json!(""), Duration::from_secs(30), |_, _| {
+                async { panic!("the ack must not be received") }.boxed()
+            })
+            .await?;
+        socket.disconnect().await?;
+
+        let payload = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        let err = crate::Error::Disconnected(Event::from("no-ack")).to_string();
+        assert_eq!(payload, Some(Payload::from(err)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_abort_integration() -> Result<()> {
         let url = crate::test::socket_io_server();
-
-        // This synchronization mechanism is used to let the test know that the end of the
-        // async callback was reached.
-        let notify = Arc::new(tokio::sync::Notify::new());
-        let notify_clone = notify.clone();
+        let (tx, mut rx) = mpsc::unbounded_channel();
 
         let socket = ClientBuilder::new(url)
-            .on("test", move |_, _| {
-                let cl = notify_clone.clone();
-                async move {
-                    sleep(Duration::from_secs(1)).await;
-                    // The async callback should be awaited and not aborted.
-                    // Thus, the notification should be called.
-                    cl.notify_one();
-                }
-                .boxed()
+            .on(Event::Error, move |payload, _| {
+                tx.send(payload).unwrap();
+                async {}.boxed()
             })
             .connect()
             .await?;
 
-        let payload = json!({"token": 123_i32});
-        let result = socket.emit("test", Payload::from(payload)).await;
-
-        assert!(result.is_ok());
-        // If the timeout did not trigger, the async callback was fully executed.
-        let timeout = timeout(Duration::from_secs(5), notify.notified()).await;
-        assert!(timeout.is_ok());
+        socket
+            .emit_with_ack("no-ack", json!(""), Duration::from_secs(30), |_, _| {
+                async { panic!("the ack must not be received") }.boxed()
+            })
+            .await?;
+        socket.abort().await?;
 
+        let payload = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        let err = crate::Error::Disconnected(Event::from("no-ack")).to_string();
+        assert_eq!(payload, Some(Payload::from(err)));
+        assert!(socket.emit("foo", json!("")).await.is_err());
         Ok(())
     }
 
@@ -705,9 +2243,19 @@ mod test {
             .on("message", |payload, _| {
                 async move { println!("{:#?}", payload) }.boxed()
             })
+            .once("message", |payload, _| {
+                async move { println!("First: {:#?}", payload) }.boxed()
+            })
+            .on("removed", |_, _| async {}.boxed())
+            .off("removed")
             .connect()
             .await?;
 
+        let handlers = socket.handlers().await;
+        assert_eq!(handlers.len(), 2);
+        assert_eq!(handlers.get(&Event::from("test")), Some(&1));
+        assert_eq!(handlers.get(&Event::Message), Some(&2));
+
         assert!(socket.emit("message", json!("Hello World")).await.is_ok());
 
         assert!(socket
@@ -740,6 +2288,8 @@ mod test {
         static CONNECT_NUM: AtomicUsize = AtomicUsize::new(0);
         static MESSAGE_NUM: AtomicUsize = AtomicUsize::new(0);
         static ON_RECONNECT_CALLED: AtomicUsize = AtomicUsize::new(0);
+        static ATTEMPT_NUM: AtomicUsize = AtomicUsize::new(0);
+        static RECONNECT_NUM: AtomicUsize = AtomicUsize::new(0);
 
         let url = crate::test::socket_io_restart_server();
 
@@ -774,6 +2324,21 @@ mod test {
                 }
                 .boxed()
             })
+            .on_reconnect_event(|event| {
+                async move {
+                    match event {
+                        ReconnectEvent::Attempt(_) => {
+                            ATTEMPT_NUM.fetch_add(1, Ordering::Release);
+                        }
+                        ReconnectEvent::Reconnected(attempts) => {
+                            assert_eq!(attempts as usize, load(&ATTEMPT_NUM));
+                            RECONNECT_NUM.fetch_add(1, Ordering::Release);
+                        }
+                        _ => {}
+                    }
+                }
+                .boxed()
+            })
             .on("message", |_, _socket| {
                 async move {
                     // test the iterator implementation and make sure there is a constant
@@ -794,6 +2359,19 @@ mod test {
         assert_eq!(load(&CONNECT_NUM), 1, "should connect once");
         assert_eq!(load(&MESSAGE_NUM), 1, "should receive one");
 
+        // buffered emits are flushed once the namespace is connected again
+        let mut state = socket.watch_state();
+        let socket_clone = socket.clone();
+        let flushed = tokio::spawn(async move {
+            state
+                .wait_for(|state| *state == ConnectionState::Reconnecting)
+                .await?;
+            state
+                .wait_for(|state| *state == ConnectionState::Connected)
+                .await?;
+            Ok::<_, watch::error::RecvError>(socket_clone.namespaces())
+        });
+
         let r = socket.emit("restart_server", json!("")).await;
         assert!(r.is_ok(), "should emit restart success");
 
@@ -811,6 +2389,18 @@ mod test {
             load(&ON_RECONNECT_CALLED) > 1,
             "should call on_reconnect at least once"
         );
+        assert_eq!(
+            load(&ATTEMPT_NUM),
+            load(&ON_RECONNECT_CALLED),
+            "should announce every attempt"
+        );
+        assert_eq!(load(&RECONNECT_NUM), 1, "should reconnect once");
+        let namespaces = timeout(Duration::from_secs(1), flushed)
+            .await
+            .expect("should flush the buffered emits")
+            .unwrap()
+            .unwrap();
+        assert_eq!(namespaces["/"].state, NamespaceState::Connected);
 
         socket.disconnect().await?;
         Ok(())
@@ -896,6 +2486,177 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn socket_io_event_bus_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let bus = EventBus::new(8).filter(|event| *event == Event::from("test"));
+        let mut events = bus.subscribe();
+
+        let socket = ClientBuilder::new(url)
+            .auth(json!({ "password": "123" }))
+            .event_bus(bus)
+            .connect()
+            .await?;
+        let mut state = socket.watch_state();
+
+        let (event, _) = timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event, Event::from("test"));
+
+        socket.disconnect().await?;
+        timeout(Duration::from_secs(5), state.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*state.borrow(), ConnectionState::Disconnected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_sequence_numbers_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, mut rx) = mpsc::channel(1);
+        let socket = ClientBuilder::new(url)
+            .sequence_numbers(5)
+            .on("proto-received", move |payload, _| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send(payload).await;
+                }
+                .boxed()
+            })
+            .connect()
+            .await?;
+        assert_eq!(socket.next_sequence(), Some(5));
+
+        // the server echoes the first two arguments, i.e. the data and the tag
+        socket.emit("proto", json!("a")).await?;
+        let payload = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            payload,
+            Payload::Text(vec![json!("a"), json!({ Payload::SEQUENCE_KEY: 5 })])
+        );
+        assert_eq!(socket.next_sequence(), Some(6));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_namespaces_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, mut rx) = mpsc::channel(4);
+        let socket = ClientBuilder::new(url)
+            .namespace("/unknown")
+            .on_namespace_state_change(move |nsp, status| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send((nsp, status.state)).await;
+                }
+                .boxed()
+            })
+            .reconnect(false)
+            .connect()
+            .await?;
+
+        for expected in [NamespaceState::Pending, NamespaceState::Failed] {
+            let (nsp, state) = timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!((nsp.as_str(), state), ("/unknown", expected));
+        }
+        let status = &socket.namespaces()["/unknown"];
+        assert_eq!(status.state, NamespaceState::Failed);
+        assert!(status
+            .last_error
+            .as_ref()
+            .is_some_and(|error| error.contains("Invalid namespace")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_idle_timeout_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, mut rx) = mpsc::channel(4);
+        let socket = ClientBuilder::new(url)
+            .idle_timeout(Duration::from_millis(300))
+            .on_before_idle_close(move |idle| {
+                let tx = tx.clone();
+                async move {
+                    assert!(idle >= Duration::from_millis(300));
+                    // keep the connection open the first time
+                    tx.send(()).await.is_err()
+                }
+                .boxed()
+            })
+            .connect()
+            .await?;
+
+        timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert!(socket.uptime_stats().connected);
+        drop(rx);
+
+        sleep(Duration::from_millis(800)).await;
+        assert!(!socket.uptime_stats().connected);
+        assert_eq!(socket.namespaces()["/"].state, NamespaceState::Disconnected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_bearer_auth_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let socket = ClientBuilder::new(url)
+            .bearer_auth(BearerAuth::from_receiver("first", rx))
+            .reconnect_delay(10, 10)
+            .connect()
+            .await?;
+        sleep(Duration::from_millis(300)).await;
+        assert_eq!(socket.uptime_stats().disconnects, 0);
+
+        // rotating the token reconnects
+        tx.send(String::from("second")).unwrap();
+        sleep(Duration::from_millis(1000)).await;
+        let stats = socket.uptime_stats();
+        assert!(stats.connected);
+        assert_eq!(stats.disconnects, 1);
+
+        socket.emit("test", json!("still connected")).await?;
+        socket.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_manual_reconnect_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let socket = ClientBuilder::new(url).reconnect(false).connect().await?;
+        sleep(Duration::from_millis(300)).await;
+
+        let mut state = socket.watch_state();
+        socket.reconnect().await?;
+        assert_eq!(*state.borrow_and_update(), ConnectionState::Reconnecting);
+        timeout(
+            Duration::from_secs(5),
+            state.wait_for(|state| *state == ConnectionState::Connected),
+        )
+        .await
+        .expect("should reconnect")
+        .unwrap();
+        assert_eq!(socket.uptime_stats().disconnects, 1);
+
+        socket.emit("test", json!("reconnected")).await?;
+        socket.disconnect().await?;
+        assert!(matches!(
+            socket.reconnect().await,
+            Err(Error::IllegalActionBeforeOpen())
+        ));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn socket_io_auth_builder_integration() -> Result<()> {
         let url = crate::test::socket_io_auth_server();
@@ -937,6 +2698,7 @@ mod test {
     }
 
     #[tokio::test]
+    #[cfg(feature = "websocket")]
     async fn socket_io_websocket_integration() -> Result<()> {
         let url = crate::test::socket_io_server();
         let socket = ClientBuilder::new(url.clone())
@@ -947,6 +2709,20 @@ mod test {
     }
 
     #[tokio::test]
+    #[cfg(feature = "websocket")]
+    async fn socket_io_prepared_websocket_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let socket = ClientBuilder::new(url)
+            .transport_type(TransportType::Websocket)
+            .prepare()
+            .await?
+            .connect_manual()
+            .await?;
+        test_socketio_socket(socket, "/".to_owned()).await
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "websocket")]
     async fn socket_io_websocket_upgrade_integration() -> Result<()> {
         let url = crate::test::socket_io_server();
         let socket = ClientBuilder::new(url)
@@ -1147,7 +2923,400 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_loopback() -> Result<()> {
+        use crate::asynchronous::loopback;
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .on("greeting", move |payload, _| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send(payload);
+                }
+                .boxed()
+            })
+            .connect()
+            .await?;
+
+        // the messages of the client, without the pongs of the heartbeat
+        let next = || async {
+            loop {
+                let packet = server.receive().await.unwrap();
+                if packet.packet_id == EnginePacketId::Message {
+                    return packet.data;
+                }
+            }
+        };
+        assert_eq!(next().await, Bytes::from("0"));
+
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "2[\"greeting\",\"hello\"]",
+        ))?;
+        assert_eq!(rx.recv().await, Some(Payload::Text(vec![json!("hello")])));
+        let snapshot = client.snapshot();
+        assert!(snapshot.is_connected());
+        assert_eq!(snapshot.transport, Some(crate::TransportKind::Loopback));
+
+        client.emit("status", json!("online")).await?;
+        assert_eq!(next().await, Bytes::from("2[\"status\",\"online\"]"));
+
+        client.disconnect().await?;
+        assert!(!client.is_connected());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ack_emit_not_tagged() -> Result<()> {
+        use crate::asynchronous::loopback;
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .suppress_echo(true)
+            .connect()
+            .await?;
+
+        // the messages of the client, without the pongs of the heartbeat
+        let next = || async {
+            loop {
+                let packet = server.receive().await.unwrap();
+                if packet.packet_id == EnginePacketId::Message {
+                    return String::from_utf8(packet.data.to_vec()).unwrap();
+                }
+            }
+        };
+        assert_eq!(next().await, "0");
+
+        client.emit("status", json!("online")).await?;
+        assert!(next()
+            .await
+            .starts_with("2[\"status\",\"online\",{\"__rust_socketio_origin\":"));
+
+        // a server handler `(data, callback)` gets the ack callback second
+        client
+            .emit_with_ack("echo", json!("hi"), Duration::from_secs(5), |_, _| {
+                async {}.boxed()
+            })
+            .await?;
+        let data = next().await;
+        assert!(data.ends_with("[\"echo\",\"hi\"]"), "{data}");
+
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ack_failed_on_disconnect() -> Result<()> {
+        use crate::asynchronous::loopback;
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .on(Event::Error, move |payload, _| {
+                let _ = tx.send(payload);
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        while !client.is_connected() {
+            tokio::task::yield_now().await;
+        }
+
+        client
+            .emit_with_ack("callback", json!(1), Duration::from_secs(30), |_, _| {
+                async { panic!("the ack must not be received") }.boxed()
+            })
+            .await?;
+        let result = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .emit_with_ack_result("result", json!(2), Duration::from_secs(30))
+                    .await
+            }
+        });
+        while client.pending().await?.acks < 2 {
+            tokio::task::yield_now().await;
+        }
+        client.disconnect().await?;
+
+        let result = timeout(Duration::from_secs(5), result)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(result, Err(Error::Disconnected(event)) if event == Event::from("result"))
+        );
+        // only the ack with a callback is reported to the error callbacks
+        let err = Error::Disconnected(Event::from("callback")).to_string();
+        assert_eq!(rx.recv().await, Some(Payload::from(err)));
+        assert!(rx.try_recv().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_last_transport_wins() -> Result<()> {
+        use crate::asynchronous::{loopback, MockTransport};
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+
+        // the mock transport has no handshake to connect with
+        let client = ClientBuilder::new("http://localhost/")
+            .mock_transport(MockTransport::new())
+            .loopback(transport)
+            .connect()
+            .await?;
+        while !client.is_connected() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            client.snapshot().transport,
+            Some(crate::TransportKind::Loopback)
+        );
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transport_factory() -> Result<()> {
+        use crate::asynchronous::loopback;
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = ClientBuilder::new("http://localhost:4200/")
+            .transport_factory(move |url| {
+                let _ = tx.send(url);
+                let transport = transport.clone();
+                async move { Ok(transport) }.boxed()
+            })
+            .connect()
+            .await?;
+        let url = rx.recv().await.unwrap();
+        assert_eq!(url.port(), Some(4200));
+        assert_eq!(url.path(), "/socket.io/");
+
+        while !client.is_connected() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            client.snapshot().transport,
+            Some(crate::TransportKind::Custom)
+        );
+        client.emit("status", json!("online")).await?;
+        loop {
+            let packet = server.receive().await.unwrap();
+            if packet.packet_id == EnginePacketId::Message && packet.data != "0" {
+                assert_eq!(packet.data, Bytes::from("2[\"status\",\"online\"]"));
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_schemas() -> Result<()> {
+        use crate::{asynchronous::loopback, EventSchemas};
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Order {
+            id: u32,
+            items: Vec<String>,
+        }
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "0{\"sid\":\"1\"}",
+        ))?;
+
+        // version 1 had a single `item`
+        let migrate = |mut order: serde_json::Value| {
+            let item = order.as_object_mut().and_then(|order| order.remove("item"));
+            order["items"] = json!([item]);
+            Ok(order)
+        };
+        let schemas = EventSchemas::new()
+            .event::<Order>("order", 2)
+            .migrate("order", 1, migrate);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (errors, mut errors_rx) = mpsc::unbounded_channel();
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .schemas(schemas)
+            .on("order", move |payload, _| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send(payload);
+                }
+                .boxed()
+            })
+            .on_error(move |err, _| {
+                if let Error::InvalidSchemaPayload { version, .. } = err {
+                    let _ = errors.send(*version);
+                }
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+
+        let next = || async {
+            loop {
+                let packet = server.receive().await.unwrap();
+                if packet.packet_id == EnginePacketId::Message && packet.data != "0" {
+                    return packet.data;
+                }
+            }
+        };
+        client
+            .emit("order", json!({"id": 1, "items": ["tea"]}))
+            .await?;
+        assert_eq!(
+            next().await,
+            Bytes::from(
+                "2[\"order\",{\"id\":1,\"items\":[\"tea\"]},{\"__rust_socketio_version\":2}]"
+            )
+        );
+
+        // old versions are migrated before they're passed to the callbacks
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "2[\"order\",{\"id\":1,\"item\":\"tea\"},{\"__rust_socketio_version\":1}]",
+        ))?;
+        assert_eq!(
+            rx.recv().await,
+            Some(Payload::Text(vec![json!({"id": 1, "items": ["tea"]})]))
+        );
+
+        // payloads that don't match the schema are reported and dropped
+        server.send(EnginePacket::new(
+            EnginePacketId::Message,
+            "2[\"order\",{\"id\":\"one\"}]",
+        ))?;
+        assert_eq!(errors_rx.recv().await, Some(2));
+        assert!(rx.try_recv().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_emit_timing() -> Result<()> {
+        use crate::asynchronous::loopback;
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .emit_timing(true)
+            .connect()
+            .await?;
+        let connected = client.emit_timing_stats().await.unwrap();
+
+        client.emit("status", json!("online")).await?;
+        let stats = client.emit_timing_stats().await.unwrap();
+        assert_eq!(stats.emits, connected.emits + 1);
+        assert!(stats.max_queue_wait >= connected.max_queue_wait);
+
+        let (transport, server) = loopback();
+        server.open(Duration::from_secs(25), Duration::from_secs(20))?;
+        let client = ClientBuilder::new("http://localhost/")
+            .loopback(transport)
+            .connect()
+            .await?;
+        assert_eq!(client.emit_timing_stats().await, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_reconnect() -> Result<()> {
+        use crate::asynchronous::MockTransport;
+        use rust_engineio::{Packet as EnginePacket, PacketId as EnginePacketId};
+
+        let message = |data: &str| EnginePacket::new(EnginePacketId::Message, data.to_owned());
+        let transport = MockTransport::new()
+            .open(Duration::from_secs(25), Duration::from_secs(20))
+            .expect(message("0"))
+            .receive(message("0{\"sid\":\"1\"}"))
+            .close()
+            .open(Duration::from_secs(25), Duration::from_secs(20))
+            .expect(message("0"))
+            .receive(message("0{\"sid\":\"2\"}"))
+            .receive(message("2[\"greeting\",\"again\"]"));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _client = ClientBuilder::new("http://localhost/")
+            .mock_transport(transport.clone())
+            .reconnect_delay(10, 10)
+            .on("greeting", move |payload, _| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send(payload);
+                }
+                .boxed()
+            })
+            .connect()
+            .await?;
+
+        assert_eq!(
+            timeout(Duration::from_secs(5), rx.recv()).await.unwrap(),
+            Some(Payload::Text(vec![json!("again")]))
+        );
+        transport.assert_done();
+        Ok(())
+    }
+
     fn load(num: &AtomicUsize) -> usize {
         num.load(Ordering::Acquire)
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_backoff_paused() {
+        let mut backoff = reconnect_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        let delay = backoff.next_backoff().unwrap();
+        // the randomization factor spreads the delay by 50% in both directions
+        assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+
+        // the backoff gives up after the maximum elapsed time
+        tokio::time::advance(Duration::from_secs(15 * 60)).await;
+        assert_eq!(backoff.next_backoff(), None);
+    }
 }
@@ -0,0 +1,112 @@
+use std::fmt::Debug;
+
+use futures_util::future::BoxFuture;
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+/// Calls the callbacks of several clients one after another, in the order their
+/// packets arrived.
+///
+/// By default every client calls its callbacks on its own polling task, so the
+/// callbacks of clients connected to different namespaces run in parallel and
+/// their relative order is undefined. Clients that share a dispatcher (see
+/// [`crate::asynchronous::ClientBuilder::dispatcher`]) hand their packets to a
+/// single task instead, which preserves the global arrival order across all of
+/// them.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::FutureExt;
+/// use rust_socketio::asynchronous::{ClientBuilder, Dispatcher};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let dispatcher = Dispatcher::new();
+///     let orders = ClientBuilder::new("http://localhost:4200/")
+///         .namespace("/orders")
+///         .dispatcher(dispatcher.clone())
+///         .on("created", |payload, _| async move { println!("order: {payload:?}") }.boxed())
+///         .connect()
+///         .await;
+///     let payments = ClientBuilder::new("http://localhost:4200/")
+///         .namespace("/payments")
+///         .dispatcher(dispatcher)
+///         .on("received", |payload, _| async move { println!("payment: {payload:?}") }.boxed())
+///         .connect()
+///         .await;
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Dispatcher {
+    sender: UnboundedSender<BoxFuture<'static, ()>>,
+}
+
+impl Dispatcher {
+    /// Creates a dispatcher and spawns the task that calls the callbacks. The
+    /// task stops once all clients and clones of the dispatcher are dropped.
+    ///
+    /// # Panics
+    /// Panics if called outside of a tokio runtime.
+    pub fn new() -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<BoxFuture<'static, ()>>();
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                job.await;
+            }
+        });
+        Dispatcher { sender }
+    }
+
+    /// Queues a job behind all jobs that were dispatched before. The job is
+    /// dropped if the dispatcher task panicked in a callback.
+    pub(crate) fn dispatch(&self, job: BoxFuture<'static, ()>) {
+        let _ = self.sender.send(job);
+    }
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Dispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Dispatcher")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use futures_util::FutureExt;
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dispatch_order() {
+        let dispatcher = Dispatcher::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        // a slow job doesn't let later jobs overtake it
+        for (i, delay) in [(0, 50), (1, 0), (2, 10)] {
+            let calls = calls.clone();
+            dispatcher.dispatch(
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    calls.lock().unwrap().push(i);
+                }
+                .boxed(),
+            );
+        }
+        let (done_tx, done_rx) = oneshot::channel();
+        dispatcher.dispatch(async move { done_tx.send(()).unwrap() }.boxed());
+        done_rx.await.unwrap();
+
+        assert_eq!(*calls.lock().unwrap(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,65 @@
+use futures_util::{future::BoxFuture, FutureExt};
+
+use super::{builder::ClientBuilder, client::Client};
+use crate::error::Result;
+
+/// Builds connected clients from a [`ClientBuilder`]. Applications that get
+/// their dependencies injected can depend on this trait instead of connecting
+/// clients themselves, which allows to swap in a mock factory in tests.
+///
+/// # Example
+/// ```rust
+/// use futures_util::{future::BoxFuture, FutureExt};
+/// use rust_socketio::asynchronous::{ClientBuilder, DefaultClientFactory, SocketIoClientFactory};
+///
+/// struct Notifier<F: SocketIoClientFactory> {
+///     factory: F,
+/// }
+///
+/// impl<F: SocketIoClientFactory> Notifier<F> {
+///     async fn start(&self) -> Result<F::Client, rust_socketio::Error> {
+///         self.factory
+///             .connect(ClientBuilder::new("http://localhost:4200/").namespace("/notify"))
+///             .await
+///     }
+/// }
+///
+/// // in tests
+/// struct MockFactory;
+///
+/// impl SocketIoClientFactory for MockFactory {
+///     type Client = ();
+///
+///     fn connect(&self, _builder: ClientBuilder) -> BoxFuture<'_, Result<(), rust_socketio::Error>> {
+///         async { Ok(()) }.boxed()
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     // in production
+///     let notifier = Notifier { factory: DefaultClientFactory };
+///
+///     let notifier = Notifier { factory: MockFactory };
+///     assert!(notifier.start().await.is_ok());
+/// }
+/// ```
+pub trait SocketIoClientFactory: Send + Sync {
+    /// The client that is built.
+    type Client;
+
+    /// Connects a client as configured by `builder`.
+    fn connect(&self, builder: ClientBuilder) -> BoxFuture<'_, Result<Self::Client>>;
+}
+
+/// The factory that connects a [`Client`] via [`ClientBuilder::connect`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultClientFactory;
+
+impl SocketIoClientFactory for DefaultClientFactory {
+    type Client = Client;
+
+    fn connect(&self, builder: ClientBuilder) -> BoxFuture<'_, Result<Client>> {
+        builder.connect().boxed()
+    }
+}
@@ -1,5 +1,10 @@
 mod ack;
 pub(crate) mod builder;
+pub(crate) mod bus;
 #[cfg(feature = "async-callbacks")]
 mod callback;
 pub(crate) mod client;
+pub(crate) mod dispatcher;
+pub(crate) mod factory;
+pub(crate) mod stream;
+pub(crate) mod watchdog;
@@ -0,0 +1,322 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Debug,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_stream::stream;
+use futures_util::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::{
+    broadcast::{error::RecvError, Receiver},
+    mpsc::{self, UnboundedReceiver},
+};
+
+use crate::{Error, Event, Payload};
+
+/// The events forwarded by an [`super::bus::EventBus`] as a [`Stream`],
+/// created by [`super::bus::EventBus::stream`]. The combinators split it into
+/// sub-streams of single events and typed payloads, so that reactive pipelines
+/// don't need to match on every event in one loop. Events missed because the
+/// stream fell behind the capacity of the bus are skipped.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use rust_socketio::asynchronous::{ClientBuilder, EventBus};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Price {
+///     symbol: String,
+///     value: f64,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let bus = EventBus::new(64);
+///     let mut prices = bus.stream().filter_event("price").map_payload::<Price>();
+///     let mut by_event = bus.stream().split_by_event();
+///
+///     let client = ClientBuilder::new("http://localhost:4200/")
+///         .event_bus(bus)
+///         .connect()
+///         .await
+///         .expect("Connection failed");
+///
+///     tokio::spawn(async move {
+///         while let Some((event, mut payloads)) = by_event.next().await {
+///             tokio::spawn(async move {
+///                 while let Some(payload) = payloads.next().await {
+///                     println!("{event}: {payload:?}");
+///                 }
+///             });
+///         }
+///     });
+///     while let Some(Ok(price)) = prices.next().await {
+///         println!("{}: {}", price.symbol, price.value);
+///     }
+/// }
+/// ```
+pub struct EventStream {
+    inner: BoxStream<'static, (Event, Payload)>,
+}
+
+impl EventStream {
+    pub(crate) fn new(mut receiver: Receiver<(Event, Payload)>) -> Self {
+        let inner = stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(item) => yield item,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+        EventStream {
+            inner: Box::pin(inner),
+        }
+    }
+
+    /// The payloads of `event` only.
+    pub fn filter_event<E: Into<Event>>(self, event: E) -> PayloadStream {
+        let event = event.into();
+        let inner = self.inner.filter_map(move |(received, payload)| {
+            let matches = received == event;
+            async move { matches.then_some(payload) }
+        });
+        PayloadStream {
+            inner: Box::pin(inner),
+        }
+    }
+
+    /// Splits the stream into a sub-stream per event, which is yielded along
+    /// with the event when it's received for the first time.
+    ///
+    /// The events are distributed by a task of their own, so the sub-streams
+    /// progress independently. Payloads are buffered until their sub-stream
+    /// takes them, drop the sub-streams that aren't needed.
+    ///
+    /// # Panics
+    /// Panics if called outside of a `tokio` runtime.
+    pub fn split_by_event(mut self) -> SplitByEvent {
+        let (groups, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut senders = HashMap::new();
+            while let Some((event, payload)) = self.inner.next().await {
+                let sender = match senders.entry(event.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let (sender, receiver) = mpsc::unbounded_channel();
+                        let _ = groups.send((event, PayloadStream::from_receiver(receiver)));
+                        entry.insert(sender)
+                    }
+                };
+                let _ = sender.send(payload);
+
+                senders.retain(|_, sender| !sender.is_closed());
+                // nobody listens anymore
+                if groups.is_closed() && senders.is_empty() {
+                    return;
+                }
+            }
+        });
+        SplitByEvent { receiver }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = (Event, Payload);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream").finish_non_exhaustive()
+    }
+}
+
+/// The payloads of a single event, see [`EventStream::filter_event`] and
+/// [`EventStream::split_by_event`].
+pub struct PayloadStream {
+    inner: BoxStream<'static, Payload>,
+}
+
+impl PayloadStream {
+    fn from_receiver(receiver: UnboundedReceiver<Payload>) -> Self {
+        let inner = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|payload| (payload, receiver))
+        });
+        PayloadStream {
+            inner: Box::pin(inner),
+        }
+    }
+
+    /// Deserializes the payloads into `T`. A payload with a single value is
+    /// deserialized from that value, one with several values from the array
+    /// of them, e.g. into a tuple. Binary payloads fail with
+    /// [`Error::InvalidJson`].
+    pub fn map_payload<T>(self) -> TypedStream<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        TypedStream {
+            inner: Box::pin(self.inner.map(deserialize)),
+        }
+    }
+}
+
+impl Stream for PayloadStream {
+    type Item = Payload;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl Debug for PayloadStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PayloadStream").finish_non_exhaustive()
+    }
+}
+
+/// The deserialized payloads of an event, see [`PayloadStream::map_payload`].
+pub struct TypedStream<T> {
+    inner: BoxStream<'static, Result<T, Error>>,
+}
+
+impl<T> Stream for TypedStream<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl<T> Debug for TypedStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedStream").finish_non_exhaustive()
+    }
+}
+
+/// The sub-streams of the events, see [`EventStream::split_by_event`].
+pub struct SplitByEvent {
+    receiver: UnboundedReceiver<(Event, PayloadStream)>,
+}
+
+impl Stream for SplitByEvent {
+    type Item = (Event, PayloadStream);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Debug for SplitByEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SplitByEvent").finish_non_exhaustive()
+    }
+}
+
+/// Deserializes a payload into `T`, see [`PayloadStream::map_payload`].
+pub(crate) fn deserialize<T: DeserializeOwned>(payload: Payload) -> Result<T, Error> {
+    let value = match payload {
+        Payload::Text(mut values) if values.len() == 1 => values.remove(0),
+        Payload::Text(values) => Value::Array(values),
+        #[allow(deprecated)]
+        Payload::String(string) => serde_json::from_str(&string)?,
+        Payload::Binary(_) => {
+            return Err(Error::InvalidJson(serde::de::Error::custom(
+                "binary payloads can't be deserialized",
+            )))
+        }
+    };
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use serde_json::json;
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::asynchronous::EventBus;
+
+    fn price(value: f64) -> Payload {
+        json!({ "symbol": "ACME", "value": value }).into()
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_map() {
+        let bus = EventBus::new(8);
+        let mut values = bus
+            .stream()
+            .filter_event("price")
+            .map_payload::<HashMap<String, Value>>();
+
+        bus.forward(&Event::from("volume"), &Payload::from(json!(10)));
+        bus.forward(&Event::from("price"), &price(1.5));
+        bus.forward(
+            &Event::from("price"),
+            &Payload::Binary(Bytes::from_static(b"1")),
+        );
+        drop(bus);
+
+        let first = values.next().await.unwrap().unwrap();
+        assert_eq!(first["value"], json!(1.5));
+        assert!(matches!(
+            values.next().await,
+            Some(Err(Error::InvalidJson(_)))
+        ));
+        assert!(values.next().await.is_none());
+    }
+
+    #[test]
+    fn test_deserialize_several_values() {
+        let payload = Payload::Text(vec![json!("ACME"), json!(2)]);
+        assert_eq!(
+            deserialize::<(String, u8)>(payload).unwrap(),
+            (String::from("ACME"), 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_by_event() {
+        let bus = EventBus::new(8);
+        let mut groups = bus.stream().split_by_event();
+
+        bus.forward(&Event::from("price"), &price(1.0));
+        bus.forward(&Event::from("volume"), &Payload::from(json!(10)));
+        bus.forward(&Event::from("price"), &price(2.0));
+        drop(bus);
+
+        let (event, prices) = timeout(Duration::from_secs(1), groups.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event, Event::from("price"));
+        let (event, volumes) = groups.next().await.unwrap();
+        assert_eq!(event, Event::from("volume"));
+        assert!(groups.next().await.is_none());
+
+        assert_eq!(
+            prices.collect::<Vec<_>>().await,
+            vec![price(1.0), price(2.0)]
+        );
+        assert_eq!(
+            volumes.collect::<Vec<_>>().await,
+            vec![Payload::from(json!(10))]
+        );
+    }
+}
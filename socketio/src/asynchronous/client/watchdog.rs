@@ -0,0 +1,205 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use tokio::sync::Notify;
+
+use crate::packet::{Packet, PacketId};
+
+/// What a client does while more of its callbacks are in flight than the
+/// high-water mark of [`crate::asynchronous::ClientBuilder::callback_watchdog`].
+///
+/// The client is overloaded from the moment the number of in-flight callbacks
+/// exceeds the high-water mark until it dropped to half of it. The handler of
+/// [`crate::asynchronous::ClientBuilder::on_overload`] is called every time
+/// the client becomes overloaded, whatever the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverloadPolicy {
+    /// Drops the received events that don't request an ack. Events with an
+    /// ack, acks and the packets of the connection are still handled.
+    DropVolatile,
+    /// Stops reading from the transport until the callbacks caught up. The
+    /// heartbeat isn't answered meanwhile, so the server closes the connection
+    /// if the callbacks take longer than its ping timeout.
+    PauseReads,
+    /// Only calls the handler of `on_overload`.
+    Notify,
+}
+
+/// Called with the number of in-flight callbacks when a client becomes
+/// overloaded.
+pub(crate) type AsyncOverloadHandler = Arc<dyn Fn(usize) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Counts the callbacks of a client that run on tasks of their own, i.e. the
+/// ones queued on a [`crate::asynchronous::Dispatcher`] and the spawned acks
+/// of [`crate::AckDispatch::Spawn`], and applies the [`OverloadPolicy`].
+#[derive(Clone)]
+pub(crate) struct CallbackWatchdog {
+    high_water: usize,
+    policy: OverloadPolicy,
+    on_overload: Option<AsyncOverloadHandler>,
+    in_flight: Arc<AtomicUsize>,
+    overloaded: Arc<AtomicBool>,
+    /// Notified once the client isn't overloaded anymore.
+    recovered: Arc<Notify>,
+}
+
+impl CallbackWatchdog {
+    pub(crate) fn new(
+        high_water: usize,
+        policy: OverloadPolicy,
+        on_overload: Option<AsyncOverloadHandler>,
+    ) -> Self {
+        CallbackWatchdog {
+            high_water,
+            policy,
+            on_overload,
+            in_flight: Arc::default(),
+            overloaded: Arc::default(),
+            recovered: Arc::default(),
+        }
+    }
+
+    /// Counts `callback` as in flight until it completes or is dropped.
+    pub(crate) fn track(&self, callback: BoxFuture<'static, ()>) -> BoxFuture<'static, ()> {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::AcqRel) + 1;
+        if in_flight > self.high_water && !self.overloaded.swap(true, Ordering::AcqRel) {
+            if let Some(on_overload) = &self.on_overload {
+                tokio::spawn(on_overload(in_flight));
+            }
+        }
+        let guard = InFlight(self.clone());
+        async move {
+            callback.await;
+            drop(guard);
+        }
+        .boxed()
+    }
+
+    /// Whether `packet` is dropped instead of handled.
+    pub(crate) fn drops(&self, packet: &Packet) -> bool {
+        self.policy == OverloadPolicy::DropVolatile
+            && self.is_overloaded()
+            && matches!(packet.packet_type, PacketId::Event | PacketId::BinaryEvent)
+            && packet.id.is_none()
+    }
+
+    /// Waits until the client isn't overloaded anymore if reads are paused.
+    pub(crate) async fn wait_for_capacity(&self) {
+        if self.policy != OverloadPolicy::PauseReads {
+            return;
+        }
+        loop {
+            let recovered = self.recovered.notified();
+            tokio::pin!(recovered);
+            recovered.as_mut().enable();
+            if !self.is_overloaded() {
+                return;
+            }
+            recovered.await;
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    fn is_overloaded(&self) -> bool {
+        self.overloaded.load(Ordering::Acquire)
+    }
+}
+
+/// Counts a callback as in flight while it's alive.
+struct InFlight(CallbackWatchdog);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let watchdog = &self.0;
+        let in_flight = watchdog.in_flight.fetch_sub(1, Ordering::AcqRel) - 1;
+        if in_flight <= watchdog.high_water / 2 && watchdog.overloaded.swap(false, Ordering::AcqRel)
+        {
+            watchdog.recovered.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::sync::{mpsc, oneshot};
+
+    use super::*;
+
+    fn event(id: Option<i32>) -> Packet {
+        Packet::new(
+            PacketId::Event,
+            "/".to_owned(),
+            Some(r#"["a"]"#.to_owned()),
+            id,
+            0,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_overload() {
+        let (overload_tx, mut overload_rx) = mpsc::unbounded_channel();
+        let on_overload: AsyncOverloadHandler = Arc::new(move |in_flight| {
+            let _ = overload_tx.send(in_flight);
+            async {}.boxed()
+        });
+        let watchdog = CallbackWatchdog::new(2, OverloadPolicy::DropVolatile, Some(on_overload));
+
+        let mut stalled = Vec::new();
+        let mut releases = Vec::new();
+        for _ in 0..3 {
+            let (release_tx, release_rx) = oneshot::channel::<()>();
+            stalled.push(tokio::spawn(
+                watchdog.track(
+                    async move {
+                        let _ = release_rx.await;
+                    }
+                    .boxed(),
+                ),
+            ));
+            releases.push(release_tx);
+        }
+        assert_eq!(watchdog.in_flight(), 3);
+        assert_eq!(overload_rx.recv().await, Some(3));
+        assert!(watchdog.drops(&event(None)));
+        assert!(!watchdog.drops(&event(Some(1))));
+
+        // recovers at half the high-water mark
+        for (release, task) in releases.drain(..2).zip(stalled.drain(..2)) {
+            release.send(()).unwrap();
+            task.await.unwrap();
+        }
+        assert_eq!(watchdog.in_flight(), 1);
+        assert!(!watchdog.drops(&event(None)));
+    }
+
+    #[tokio::test]
+    async fn test_pause_reads() {
+        let watchdog = CallbackWatchdog::new(0, OverloadPolicy::PauseReads, None);
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        let stalled = tokio::spawn(
+            watchdog.track(
+                async move {
+                    let _ = release_rx.await;
+                }
+                .boxed(),
+            ),
+        );
+
+        let paused = tokio::time::timeout(Duration::from_millis(50), watchdog.wait_for_capacity());
+        assert!(paused.await.is_err());
+        release_tx.send(()).unwrap();
+        stalled.await.unwrap();
+        watchdog.wait_for_capacity().await;
+        assert!(!watchdog.drops(&event(None)));
+    }
+}
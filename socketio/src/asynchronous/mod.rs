@@ -4,7 +4,22 @@ mod socket;
 
 #[cfg(feature = "async")]
 pub use client::builder::ClientBuilder;
+pub use client::bus::EventBus;
 pub use client::client::{Client, ReconnectSettings};
+pub use client::dispatcher::Dispatcher;
+pub use client::factory::{DefaultClientFactory, SocketIoClientFactory};
+#[cfg(feature = "server")]
+pub(crate) use client::stream::deserialize;
+pub use client::stream::{EventStream, PayloadStream, SplitByEvent, TypedStream};
+pub use client::watchdog::OverloadPolicy;
+pub use rust_engineio::asynchronous::async_transports::CustomTransport;
+#[cfg(any(test, feature = "test-support"))]
+pub use rust_engineio::asynchronous::async_transports::{
+    loopback, Chaos, LoopbackServer, LoopbackTransport, MockTransport,
+};
+pub use rust_engineio::asynchronous::transport::AsyncTransport;
+#[cfg(feature = "tower")]
+pub use rust_engineio::asynchronous::{HttpRequest, HttpResponse, HttpService};
 
 // re-export the macro
 pub use crate::{async_any_callback, async_callback};
@@ -2,13 +2,14 @@ use super::generator::StreamGenerator;
 use crate::{
     error::Result,
     packet::{Packet, PacketId},
-    Error, Event, Payload,
+    Error, Event, Payload, Utf8Policy,
 };
-use async_stream::try_stream;
+use async_stream::stream;
 use bytes::Bytes;
 use futures_util::{Stream, StreamExt};
 use rust_engineio::{
-    asynchronous::Client as EngineClient, Packet as EnginePacket, PacketId as EnginePacketId,
+    asynchronous::Client as EngineClient, BufferPoolStats, EmitTimingStats, HttpVersion,
+    Packet as EnginePacket, PacketId as EnginePacketId, TransportKind,
 };
 use std::{
     fmt::Debug,
@@ -23,6 +24,7 @@ use std::{
 pub(crate) struct Socket {
     engine_client: Arc<EngineClient>,
     connected: Arc<AtomicBool>,
+    small_event_threshold: Option<usize>,
     generator: StreamGenerator<Packet>,
 }
 
@@ -33,10 +35,33 @@ impl Socket {
         Ok(Socket {
             engine_client: Arc::new(engine_client.clone()),
             connected: connected.clone(),
-            generator: StreamGenerator::new(Self::stream(engine_client, connected)),
+            small_event_threshold: None,
+            generator: StreamGenerator::new(Self::stream(
+                engine_client,
+                connected,
+                Utf8Policy::default(),
+            )),
         })
     }
 
+    /// Encodes text events up to `threshold` bytes on the stack, see
+    /// [`Packet::encode_small_event`].
+    pub(super) fn with_small_event_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.small_event_threshold = threshold;
+        self
+    }
+
+    /// Decodes received text packets that aren't valid UTF-8 according to
+    /// `policy`. Must be called before the packets are polled.
+    pub(super) fn with_utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.generator = StreamGenerator::new(Self::stream(
+            (*self.engine_client).clone(),
+            self.connected.clone(),
+            policy,
+        ));
+        self
+    }
+
     /// Connects to the server. This includes a connection of the underlying
     /// engine.io client and afterwards an opening socket.io request.
     pub async fn connect(&self) -> Result<()> {
@@ -61,6 +86,24 @@ impl Socket {
         Ok(())
     }
 
+    /// Closes the connection right away without sending a `Disconnect` or any
+    /// other packet.
+    pub fn abort(&self) {
+        self.engine_client.abort();
+        self.connected.store(false, Ordering::Release);
+    }
+
+    /// Sends an already encoded `socket.io` packet without attachments.
+    async fn send_encoded(&self, encoded: Bytes) -> Result<()> {
+        if !self.is_engineio_connected() || !self.connected.load(Ordering::Acquire) {
+            return Err(Error::IllegalActionBeforeOpen());
+        }
+
+        let engine_packet = EnginePacket::new(EnginePacketId::Message, encoded);
+        self.engine_client.emit(engine_packet).await?;
+        Ok(())
+    }
+
     /// Sends a `socket.io` packet to the server using the `engine.io` client.
     pub async fn send(&self, packet: Packet) -> Result<()> {
         if !self.is_engineio_connected() || !self.connected.load(Ordering::Acquire) {
@@ -84,26 +127,52 @@ impl Socket {
     /// Emits to certain event with given data. The data needs to be JSON,
     /// otherwise this returns an `InvalidJson` error.
     pub async fn emit(&self, nsp: &str, event: Event, data: Payload) -> Result<()> {
+        if let (Some(threshold), Payload::Text(values)) = (self.small_event_threshold, &data) {
+            if let Some(encoded) = Packet::encode_small_event(nsp, &event, values, threshold) {
+                return self.send_encoded(encoded).await;
+            }
+        }
+
         let socket_packet = Packet::new_from_payload(data, event, nsp, None)?;
 
         self.send(socket_packet).await
     }
 
+    /// The stream of received packets, which ends after the first error unless
+    /// a packet that isn't valid UTF-8 is rejected on its own.
     fn stream(
         client: EngineClient,
         is_connected: Arc<AtomicBool>,
+        utf8_policy: Utf8Policy,
     ) -> Pin<Box<impl Stream<Item = Result<Packet>> + Send>> {
-        Box::pin(try_stream! {
+        Box::pin(stream! {
                 for await received_data in client.clone() {
-                    let packet = received_data?;
+                    let packet = match received_data {
+                        Ok(packet) => packet,
+                        Err(err) => {
+                            yield Err(err.into());
+                            return;
+                        }
+                    };
 
                     if packet.packet_id == EnginePacketId::Message
                         || packet.packet_id == EnginePacketId::MessageBinary
                     {
-                        let packet = Self::handle_engineio_packet(packet, client.clone()).await?;
-                        Self::handle_socketio_packet(&packet, is_connected.clone());
-
-                        yield packet;
+                        match Self::handle_engineio_packet(packet, client.clone(), utf8_policy).await {
+                            Ok(packet) => {
+                                Self::handle_socketio_packet(&packet, is_connected.clone());
+                                yield Ok(packet);
+                            }
+                            Err(err @ Error::InvalidUtf8Packet { .. })
+                                if utf8_policy == Utf8Policy::Reject =>
+                            {
+                                yield Err(err);
+                            }
+                            Err(err) => {
+                                yield Err(err);
+                                return;
+                            }
+                        }
                     }
                 }
         })
@@ -130,8 +199,9 @@ impl Socket {
     async fn handle_engineio_packet(
         packet: EnginePacket,
         mut client: EngineClient,
+        utf8_policy: Utf8Policy,
     ) -> Result<Packet> {
-        let mut socket_packet = Packet::try_from(&packet.data)?;
+        let mut socket_packet = Packet::decode(&packet.data, utf8_policy)?;
 
         // Only handle attachments if there are any
         if socket_packet.attachment_count > 0 {
@@ -164,6 +234,22 @@ impl Socket {
     fn is_engineio_connected(&self) -> bool {
         self.engine_client.is_connected()
     }
+
+    pub(crate) fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.engine_client.buffer_pool_stats()
+    }
+
+    pub(crate) fn emit_timing_stats(&self) -> Option<EmitTimingStats> {
+        self.engine_client.emit_timing_stats()
+    }
+
+    pub(crate) fn http_version(&self) -> Option<HttpVersion> {
+        self.engine_client.http_version()
+    }
+
+    pub(crate) fn transport_kind(&self) -> TransportKind {
+        self.engine_client.transport_kind()
+    }
 }
 
 impl Stream for Socket {
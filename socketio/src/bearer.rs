@@ -0,0 +1,254 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
+
+/// The header the token is sent in.
+pub(crate) const AUTHORIZATION: &str = "Authorization";
+
+/// How often a watched channel checks whether its client is still alive.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Authenticates the connections of a client with a bearer token, which is
+/// sent in the `Authorization` header of every request and websocket upgrade.
+/// Set via `ClientBuilder::bearer_auth`.
+///
+/// The token is taken from a closure, which is called on every connect and
+/// reconnect, or from a channel, which delivers the rotated tokens. Once the
+/// token is rotated, the client reconnects to present the new one, unless
+/// reconnecting is disabled, in which case the new token is used on the next
+/// connect.
+///
+/// # Example
+/// ```no_run
+/// use std::{sync::mpsc, time::Duration};
+///
+/// use rust_socketio::{BearerAuth, ClientBuilder};
+///
+/// // fetched on every connect and checked for a new token every 5 minutes
+/// let auth = BearerAuth::new(|| std::fs::read_to_string("/run/secrets/token").unwrap())
+///     .refresh_every(Duration::from_secs(300));
+/// let socket = ClientBuilder::new("http://localhost:4200/")
+///     .bearer_auth(auth)
+///     .connect()
+///     .expect("connection failed");
+///
+/// // rotated by sending the new token
+/// let (tx, rx) = mpsc::channel();
+/// let socket = ClientBuilder::new("http://localhost:4200/")
+///     .bearer_auth(BearerAuth::from_receiver("initial-token", rx))
+///     .connect()
+///     .expect("connection failed");
+/// tx.send(String::from("rotated-token")).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct BearerAuth {
+    source: TokenSource,
+    /// The token of the current connection.
+    current: Arc<Mutex<String>>,
+    refresh_interval: Option<Duration>,
+}
+
+#[derive(Clone)]
+enum TokenSource {
+    Fn(Arc<dyn Fn() -> String + Send + Sync>),
+    /// Taken by the first client that watches it.
+    Channel(Arc<Mutex<Option<Receiver<String>>>>),
+}
+
+impl BearerAuth {
+    /// Takes the token from `source`, which is called on every connect and
+    /// reconnect.
+    pub fn new<F>(source: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        BearerAuth {
+            source: TokenSource::Fn(Arc::new(source)),
+            current: Arc::default(),
+            refresh_interval: None,
+        }
+    }
+
+    /// Starts with `token` and takes the rotated tokens from `receiver`. Only
+    /// the first client that is connected with this auth receives them.
+    pub fn from_receiver<T: Into<String>>(token: T, receiver: Receiver<String>) -> Self {
+        BearerAuth {
+            source: TokenSource::Channel(Arc::new(Mutex::new(Some(receiver)))),
+            current: Arc::new(Mutex::new(token.into())),
+            refresh_interval: None,
+        }
+    }
+
+    /// Calls the closure of [`BearerAuth::new`] in the given interval and
+    /// reconnects if it returns a new token. Has no effect on tokens from a
+    /// channel, which are applied as they arrive.
+    pub fn refresh_every(mut self, interval: Duration) -> Self {
+        self.refresh_interval = Some(interval);
+        self
+    }
+
+    /// Returns the token for a new connection.
+    pub(crate) fn token(&self) -> String {
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        if let TokenSource::Fn(source) = &self.source {
+            *current = source();
+        }
+        current.clone()
+    }
+
+    /// The value of the `Authorization` header for a new connection.
+    pub(crate) fn header(&self) -> String {
+        format!("Bearer {}", self.token())
+    }
+
+    /// Calls `on_rotate` in a thread of its own whenever the token is rotated,
+    /// until it returns `false`. The thread also ends once `alive` returns
+    /// `false`, which is checked on every refresh and at least every
+    /// [`WATCH_INTERVAL`] for channels.
+    pub(crate) fn watch<A, F>(&self, alive: A, mut on_rotate: F)
+    where
+        A: Fn() -> bool + Send + 'static,
+        F: FnMut() -> bool + Send + 'static,
+    {
+        let current = self.current.clone();
+        match &self.source {
+            TokenSource::Channel(receiver) => {
+                let Some(receiver) = receiver
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take()
+                else {
+                    return;
+                };
+                std::thread::spawn(move || loop {
+                    let token = match receiver.recv_timeout(WATCH_INTERVAL) {
+                        Ok(token) => token,
+                        Err(RecvTimeoutError::Timeout) if alive() => continue,
+                        // the sender or the client is gone
+                        Err(_) => return,
+                    };
+                    if !alive() {
+                        return;
+                    }
+                    let mut current = current.lock().unwrap_or_else(PoisonError::into_inner);
+                    if *current == token {
+                        continue;
+                    }
+                    *current = token;
+                    drop(current);
+                    if !on_rotate() {
+                        return;
+                    }
+                });
+            }
+            TokenSource::Fn(source) => {
+                let Some(interval) = self.refresh_interval else {
+                    return;
+                };
+                let source = source.clone();
+                std::thread::spawn(move || loop {
+                    std::thread::sleep(interval);
+                    if !alive() {
+                        return;
+                    }
+                    let token = source();
+                    let rotated = *current.lock().unwrap_or_else(PoisonError::into_inner) != token;
+                    if rotated && !on_rotate() {
+                        return;
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl Debug for BearerAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never show the token
+        f.debug_struct("BearerAuth")
+            .field("refresh_interval", &self.refresh_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_token_from_fn() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let auth = BearerAuth::new(move || {
+            format!("token-{}", calls_clone.fetch_add(1, Ordering::SeqCst))
+        });
+        assert_eq!(auth.header(), "Bearer token-0");
+        assert_eq!(auth.clone().header(), "Bearer token-1");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_rotate_via_channel() {
+        let (tx, rx) = mpsc::channel();
+        let auth = BearerAuth::from_receiver("first", rx);
+        let (rotated_tx, rotated_rx) = mpsc::channel();
+        auth.watch(|| true, move || rotated_tx.send(()).is_ok());
+        // only one client receives the rotated tokens
+        auth.watch(|| true, || panic!("the channel is watched twice"));
+
+        tx.send(String::from("first")).unwrap();
+        tx.send(String::from("second")).unwrap();
+        rotated_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(auth.header(), "Bearer second");
+        assert!(rotated_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_refresh_fn() {
+        let token = Arc::new(Mutex::new(String::from("first")));
+        let token_clone = token.clone();
+        let auth = BearerAuth::new(move || token_clone.lock().unwrap().clone())
+            .refresh_every(Duration::from_millis(10));
+        assert_eq!(auth.header(), "Bearer first");
+
+        let (rotated_tx, rotated_rx) = mpsc::channel();
+        auth.watch(|| true, move || rotated_tx.send(()).is_ok());
+        *token.lock().unwrap() = String::from("second");
+        rotated_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(auth.header(), "Bearer second");
+    }
+
+    #[test]
+    fn test_watch_ends_with_client() {
+        let auth =
+            BearerAuth::new(|| String::from("token")).refresh_every(Duration::from_millis(10));
+        let (alive_tx, alive_rx) = mpsc::channel();
+        auth.watch(
+            move || {
+                let _ = alive_tx.send(());
+                false
+            },
+            || panic!("the client is gone"),
+        );
+        alive_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        // the thread ended and dropped the closure
+        assert!(alive_rx.recv_timeout(Duration::from_secs(1)).is_err());
+
+        let (tx, rx) = mpsc::channel();
+        let auth = BearerAuth::from_receiver("first", rx);
+        auth.watch(|| false, || panic!("the client is gone"));
+        tx.send(String::from("second")).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(auth.header(), "Bearer first");
+    }
+}
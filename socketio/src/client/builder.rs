@@ -1,29 +1,56 @@
 use super::super::{event::Event, payload::Payload};
 use super::callback::Callback;
 use super::client::Client;
+use super::Dispatcher;
 use crate::RawClient;
 use native_tls::TlsConnector;
+use rand::{thread_rng, Rng};
 use rust_engineio::client::ClientBuilder as EngineIoClientBuilder;
 use rust_engineio::header::{HeaderMap, HeaderValue};
-use url::Url;
+#[cfg(feature = "websocket")]
+use rust_engineio::UpgradeStore;
+use rust_engineio::{split_zone, Client as EngineClient, Resolver};
 
-use crate::client::callback::{SocketAnyCallback, SocketCallback};
+use crate::ack::DEFAULT_ACK_REQUEST_LIMIT;
+use crate::client::callback::{SocketAnyCallback, SocketCallback, SocketErrorCallback};
+use crate::codec::Codecs;
+use crate::emit::{Coalescer, SequenceCounter};
+use crate::engine_event::{EngineEventHandler, TransportChangeHandler};
 use crate::error::Result;
-use std::collections::HashMap;
+use crate::failover::Endpoints;
+use crate::handlers::EventHandlers;
+use crate::idle::{ActivityTracker, BeforeIdleCloseHandler};
+use crate::namespace::NamespaceStateHandler;
+use crate::reconnect::ReconnectEventHandler;
+use crate::recovery::SessionRecovery;
+use crate::redact::Redaction;
+use crate::{
+    bearer::AUTHORIZATION, BackoffStrategy, BearerAuth, BufferOverflow, EmitPolicy, EngineEvent,
+    Error, EventSchemas, Failover, HttpProtocol, NamespaceStatus, PayloadCodec, PollRetry,
+    QueryValue, ReconnectEvent, RedactedView, ServerAddresses, TransportKind, UnhandledPolicy,
+    Utf8Policy,
+};
+use std::net::IpAddr;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use crate::socket::Socket as InnerSocket;
 
 /// Flavor of Engine.IO transport.
 #[derive(Clone, Eq, PartialEq)]
 pub enum TransportType {
-    /// Handshakes with polling, upgrades if possible
+    /// Handshakes with polling, upgrades if possible. Without the `websocket`
+    /// feature, this is the same as [`TransportType::Polling`].
     Any,
     /// Handshakes with websocket. Does not use polling.
+    #[cfg(feature = "websocket")]
     Websocket,
     /// Handshakes with polling, errors if upgrade fails
+    #[cfg(feature = "websocket")]
     WebsocketUpgrade,
-    /// Handshakes with polling
+    /// Handshakes with polling and never probes or upgrades to a websocket,
+    /// even if the server offers one, e.g. for deployments behind proxies
+    /// that break websockets. Reconnects stay on polling as well.
     Polling,
 }
 
@@ -33,27 +60,72 @@ pub enum TransportType {
 /// acts the `build` method and returns a connected [`Client`].
 #[derive(Clone)]
 pub struct ClientBuilder {
-    pub(crate) address: String,
-    on: Arc<Mutex<HashMap<Event, Callback<SocketCallback>>>>,
+    pub(crate) endpoints: Endpoints,
+    on: Arc<Mutex<EventHandlers<Callback<SocketCallback>>>>,
     on_any: Arc<Mutex<Option<Callback<SocketAnyCallback>>>>,
-    namespace: String,
+    on_unhandled: Arc<Mutex<Option<Callback<SocketAnyCallback>>>>,
+    on_error: Arc<Mutex<Option<Callback<SocketErrorCallback>>>>,
+    codecs: Arc<Mutex<Codecs>>,
+    schemas: EventSchemas,
+    pub(crate) namespace: String,
     tls_config: Option<TlsConnector>,
     opening_headers: Option<HeaderMap>,
-    transport_type: TransportType,
+    query: Vec<(String, QueryValue)>,
+    transports: Vec<TransportType>,
+    #[cfg(feature = "websocket")]
+    websocket_ping_interval: Option<Duration>,
+    #[cfg(feature = "websocket")]
+    upgrade_store: Option<Arc<dyn UpgradeStore>>,
+    buffer_pool_size: Option<usize>,
+    http_protocol: HttpProtocol,
+    poll_retry: Option<PollRetry>,
+    max_payload_size: Option<usize>,
+    local_address: Option<IpAddr>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    interface: Option<String>,
+    resolver: Option<Arc<dyn Resolver>>,
     auth: Option<serde_json::Value>,
+    pub(crate) bearer_auth: Option<BearerAuth>,
+    origin: Option<String>,
+    pub(crate) sequence: Option<SequenceCounter>,
+    pub(crate) recovery: SessionRecovery,
+    dispatcher: Option<Dispatcher>,
+    redaction: Redaction,
+    small_event_threshold: Option<usize>,
+    pub(crate) ack_request_limit: usize,
+    on_engineio: Option<EngineEventHandler>,
+    pub(crate) on_namespace_state_change: Option<NamespaceStateHandler>,
+    pub(crate) on_reconnect_event: Option<ReconnectEventHandler>,
+    pub(crate) on_transport_change: Option<TransportChangeHandler>,
+    pub(crate) resubscriptions: Vec<(Event, Payload)>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) activity: Option<ActivityTracker>,
+    pub(crate) on_before_idle_close: Option<BeforeIdleCloseHandler>,
     pub(crate) reconnect: bool,
     pub(crate) reconnect_on_disconnect: bool,
     // None reconnect attempts represent infinity.
     pub(crate) max_reconnect_attempts: Option<u8>,
     pub(crate) reconnect_delay_min: u64,
     pub(crate) reconnect_delay_max: u64,
+    pub(crate) backoff: Option<Arc<dyn BackoffStrategy>>,
+    pub(crate) emit_policy: EmitPolicy,
+    pub(crate) resend_on_reconnect: bool,
+    pub(crate) coalescer: Coalescer,
+    pub(crate) emit_buffer_limit: Option<(usize, BufferOverflow)>,
+    unhandled_policy: UnhandledPolicy,
+    pub(crate) utf8_policy: Utf8Policy,
 }
 
 impl ClientBuilder {
     /// Create as client builder from a URL. URLs must be in the form
     /// `[ws or wss or http or https]://[domain]:[port]/[path]`. The
     /// path of the URL is optional and if no port is given, port 80
-    /// will be used.
+    /// will be used. Link-local IPv6 addresses may carry a zone, like
+    /// `http://[fe80::1%eth0]:3000`.
+    ///
+    /// A list of URLs lets the client fail over to the next server if the
+    /// handshake with one fails, see [`ServerAddresses`] and
+    /// [`ClientBuilder::failover`].
     /// # Example
     /// ```rust
     /// use rust_socketio::{ClientBuilder, Payload, RawClient};
@@ -82,25 +154,133 @@ impl ClientBuilder {
     ///
     /// assert!(result.is_ok());
     /// ```
-    pub fn new<T: Into<String>>(address: T) -> Self {
+    pub fn new<T: Into<ServerAddresses>>(address: T) -> Self {
         Self {
-            address: address.into(),
-            on: Arc::new(Mutex::new(HashMap::new())),
+            endpoints: Endpoints::new(address.into()),
+            on: Arc::new(Mutex::new(EventHandlers::default())),
             on_any: Arc::new(Mutex::new(None)),
+            on_unhandled: Arc::new(Mutex::new(None)),
+            on_error: Arc::new(Mutex::new(None)),
+            codecs: Arc::new(Mutex::new(Codecs::default())),
+            schemas: EventSchemas::default(),
             namespace: "/".to_owned(),
             tls_config: None,
             opening_headers: None,
-            transport_type: TransportType::Any,
+            query: Vec::new(),
+            transports: vec![TransportType::Any],
+            #[cfg(feature = "websocket")]
+            websocket_ping_interval: None,
+            #[cfg(feature = "websocket")]
+            upgrade_store: None,
+            buffer_pool_size: None,
+            http_protocol: HttpProtocol::default(),
+            poll_retry: None,
+            max_payload_size: None,
+            local_address: None,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            interface: None,
+            resolver: None,
             auth: None,
+            bearer_auth: None,
+            origin: None,
+            sequence: None,
+            recovery: SessionRecovery::default(),
+            dispatcher: None,
+            redaction: Redaction::default(),
+            small_event_threshold: None,
+            ack_request_limit: DEFAULT_ACK_REQUEST_LIMIT,
+            on_engineio: None,
+            on_namespace_state_change: None,
+            on_reconnect_event: None,
+            on_transport_change: None,
+            resubscriptions: Vec::new(),
+            idle_timeout: None,
+            activity: None,
+            on_before_idle_close: None,
             reconnect: true,
             reconnect_on_disconnect: false,
             // None means infinity
             max_reconnect_attempts: None,
             reconnect_delay_min: 1000,
             reconnect_delay_max: 5000,
+            backoff: None,
+            emit_policy: EmitPolicy::default(),
+            resend_on_reconnect: false,
+            coalescer: Coalescer::default(),
+            emit_buffer_limit: None,
+            unhandled_policy: UnhandledPolicy::default(),
+            utf8_policy: Utf8Policy::default(),
         }
     }
 
+    /// Applies a preset for low-latency traffic that tolerates losses, e.g.
+    /// telemetry or cursor positions, which are worthless once they're stale:
+    /// - emits fail right away while the client reconnects
+    ///   ([`EmitPolicy::FailFast`]) and pending acks aren't resent,
+    /// - the client reconnects quickly, every 100ms to 1s without a limit,
+    /// - long-poll requests aren't retried and, with the `websocket` feature,
+    ///   the websocket transport is used right away instead of upgrading a
+    ///   polling connection.
+    ///
+    /// Settings made after the preset override it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .realtime()
+    ///     .connect();
+    /// ```
+    pub fn realtime(mut self) -> Self {
+        self.emit_policy = EmitPolicy::FailFast;
+        self.resend_on_reconnect = false;
+        self.reconnect = true;
+        self.reconnect_on_disconnect = false;
+        self.max_reconnect_attempts = None;
+        self.poll_retry = Some(PollRetry::disabled());
+        #[cfg(feature = "websocket")]
+        {
+            self.transports = vec![TransportType::Websocket];
+        }
+        self.reconnect_delay(100, 1000)
+    }
+
+    /// Applies a preset for command channels that must not lose emits:
+    /// - emits are buffered while the client reconnects and sent afterwards
+    ///   ([`EmitPolicy::Buffer`]),
+    /// - emits whose ack is pending when the connection drops are resent after
+    ///   the reconnect (see [`ClientBuilder::resend_on_reconnect`]),
+    /// - the client reconnects without a limit, also after the server closed
+    ///   the connection, backing off from 500ms to 30s,
+    /// - long-poll requests are retried on transient gateway errors and the
+    ///   transport falls back to polling if websockets are unavailable.
+    ///
+    /// As resent emits may arrive twice, combine the preset with
+    /// [`ClientBuilder::sequence_numbers`] to let the server drop duplicates.
+    /// Settings made after the preset override it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .reliable()
+    ///     // settings after the preset override it
+    ///     .reconnect_delay(200, 10_000)
+    ///     .connect();
+    /// ```
+    pub fn reliable(mut self) -> Self {
+        self.emit_policy = EmitPolicy::Buffer;
+        self.resend_on_reconnect = true;
+        self.reconnect = true;
+        self.reconnect_on_disconnect = true;
+        self.max_reconnect_attempts = None;
+        self.poll_retry = Some(PollRetry::new(5, Duration::from_millis(200)));
+        self.transports = vec![TransportType::Any];
+        self.reconnect_delay(500, 30_000)
+    }
+
     /// Sets the target namespace of the client. The namespace should start
     /// with a leading `/`. Valid examples are e.g. `/admin`, `/foo`.
     pub fn namespace<T: Into<String>>(mut self, namespace: T) -> Self {
@@ -134,10 +314,37 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the minimum and maximum delay between reconnection attempts, for an
+    /// exponential backoff. Replaces a strategy set via [`ClientBuilder::backoff`].
     pub fn reconnect_delay(mut self, min: u64, max: u64) -> Self {
         self.reconnect_delay_min = min;
         self.reconnect_delay_max = max;
+        self.backoff = None;
+
+        self
+    }
 
+    /// Sets the [`BackoffStrategy`] that decides how long the client waits
+    /// between its attempts to reconnect and when it gives up, e.g. a
+    /// [`crate::FixedBackoff`], an [`crate::ExponentialBackoff`] or a closure.
+    /// Replaces the delays set via [`ClientBuilder::reconnect_delay`], the
+    /// limit of [`ClientBuilder::max_reconnect_attempts`] still applies.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, ExponentialBackoff};
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .reconnect(true)
+    ///     .backoff(
+    ///         ExponentialBackoff::new(Duration::from_millis(200), Duration::from_secs(30))
+    ///             .jitter(0.3),
+    ///     )
+    ///     .connect();
+    /// ```
+    pub fn backoff<B: BackoffStrategy + 'static>(mut self, strategy: B) -> Self {
+        self.backoff = Some(Arc::new(strategy));
         self
     }
 
@@ -146,9 +353,109 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the [`EmitPolicy`] that defines what happens to emits while the client
+    /// is reconnecting. The policy can be overridden for a single emit via
+    /// [`Client::emit_opts`].
+    /// Defaults to [`EmitPolicy::FailFast`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, EmitPolicy};
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .emit_policy(EmitPolicy::Block(Duration::from_secs(5)))
+    ///     .connect();
+    /// ```
+    pub fn emit_policy(mut self, emit_policy: EmitPolicy) -> Self {
+        self.emit_policy = emit_policy;
+        self
+    }
+
+    /// Sends emits with an ack again after a reconnect if the connection
+    /// dropped before their ack was received, instead of failing the ack. Can
+    /// be overridden for a single emit via
+    /// [`crate::EmitOptions::resend_on_reconnect`]. Defaults to `false`.
+    pub fn resend_on_reconnect(mut self, resend: bool) -> Self {
+        self.resend_on_reconnect = resend;
+        self
+    }
+
+    /// Buffers at most `max` emits while the client is reconnecting,
+    /// `overflow` decides what happens to the emits beyond. Without a limit,
+    /// which is the default, the buffer grows until the client reconnected or
+    /// gave up. Emits that are dropped are reported to the callbacks of
+    /// [`Event::Error`] as [`Error::EmitDropped`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{BufferOverflow, ClientBuilder, EmitPolicy};
+    ///
+    /// // keeps the latest 100 emits while reconnecting
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .emit_policy(EmitPolicy::Buffer)
+    ///     .emit_buffer_limit(100, BufferOverflow::DropOldest)
+    ///     .connect();
+    /// ```
+    pub fn emit_buffer_limit(mut self, max: usize, overflow: BufferOverflow) -> Self {
+        self.emit_buffer_limit = Some((max, overflow));
+        self
+    }
+
+    /// Keeps at most one emit of `event` in the buffer while the client is
+    /// reconnecting: a buffered emit is replaced by the next one, so that only
+    /// the latest is sent after the reconnect, e.g. for cursor positions or
+    /// other state of which only the current value matters. Only applies to
+    /// emits without an ack that are buffered via [`EmitPolicy::Buffer`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, EmitPolicy};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .emit_policy(EmitPolicy::Buffer)
+    ///     .coalesce("cursor")
+    ///     .connect();
+    /// ```
+    pub fn coalesce<E: Into<Event>>(mut self, event: E) -> Self {
+        self.coalescer.insert(event.into(), None);
+        self
+    }
+
+    /// Like [`ClientBuilder::coalesce`], but merges the payload of the
+    /// buffered emit with the one of the next emit via `merge`, which receives
+    /// the older payload first.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, EmitPolicy, Payload};
+    /// use serde_json::Value;
+    ///
+    /// // sums up the scroll deltas that were buffered
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .emit_policy(EmitPolicy::Buffer)
+    ///     .coalesce_with("scroll", |older, newer| match (older, newer) {
+    ///         (Payload::Text(older), Payload::Text(newer)) => {
+    ///             let delta = |values: &[Value]| values.first().and_then(Value::as_i64).unwrap_or(0);
+    ///             Payload::Text(vec![(delta(&older) + delta(&newer)).into()])
+    ///         }
+    ///         (_, newer) => newer,
+    ///     })
+    ///     .connect();
+    /// ```
+    pub fn coalesce_with<E, F>(mut self, event: E, merge: F) -> Self
+    where
+        E: Into<Event>,
+        F: Fn(Payload, Payload) -> Payload + Send + Sync + 'static,
+    {
+        self.coalescer.insert(event.into(), Some(Arc::new(merge)));
+        self
+    }
+
     /// Registers a new callback for a certain [`crate::event::Event`]. The event could either be
     /// one of the common events like `message`, `error`, `open`, `close` or a custom
-    /// event defined by a string, e.g. `onPayment` or `foo`.
+    /// event defined by a string, e.g. `onPayment` or `foo`. Several callbacks can be
+    /// registered for the same event, they are called in the order of registration.
     ///
     /// # Example
     /// ```rust
@@ -176,7 +483,92 @@ impl ClientBuilder {
     {
         let callback = Callback::<SocketCallback>::new(callback);
         // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
-        self.on.lock().unwrap().insert(event.into(), callback);
+        self.on.lock().unwrap().add(event.into(), callback, false);
+        self
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] that is removed
+    /// after it was called once.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Payload};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .once("welcome", |payload: Payload, _| println!("Welcome: {:#?}", payload))
+    ///     .connect();
+    /// ```
+    // While present implementation doesn't require mut, it's reasonable to require mutability.
+    #[allow(unused_mut)]
+    pub fn once<T: Into<Event>, F>(mut self, event: T, callback: F) -> Self
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+    {
+        let callback = Callback::<SocketCallback>::new(callback);
+        // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
+        self.on.lock().unwrap().add(event.into(), callback, true);
+        self
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] that receives
+    /// protobuf messages of type `M`, as sent by [`RawClient::emit_proto`].
+    /// Available with the `prost` feature.
+    ///
+    /// The binary attachment of the event is decoded as `M`, the type name that
+    /// is sent along isn't checked. Attachments that fail to decode are reported
+    /// to the callbacks of [`crate::event::Event::Error`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// #[derive(Clone, PartialEq, prost::Message)]
+    /// struct Position {
+    ///     #[prost(double, tag = "1")]
+    ///     latitude: f64,
+    ///     #[prost(double, tag = "2")]
+    ///     longitude: f64,
+    /// }
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .on_proto::<Position>("position", |position, _| println!("{position:?}"))
+    ///     .connect();
+    /// ```
+    #[cfg(feature = "prost")]
+    pub fn on_proto<M>(
+        self,
+        event: impl Into<Event>,
+        mut callback: impl FnMut(M, RawClient) + 'static + Send,
+    ) -> Self
+    where
+        M: prost::Message + Default,
+    {
+        self.on(
+            event,
+            move |payload: Payload, socket: RawClient| match crate::proto::decode::<M>(&payload) {
+                Ok(message) => callback(message, socket),
+                Err(err) => {
+                    let _ = socket.error(&err);
+                }
+            },
+        )
+    }
+
+    /// Removes all callbacks that were registered for a certain [`crate::event::Event`].
+    // While present implementation doesn't require mut, it's reasonable to require mutability.
+    #[allow(unused_mut)]
+    pub fn off<T: Into<Event>>(mut self, event: T) -> Self {
+        // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
+        self.on.lock().unwrap().remove(&event.into());
+        self
+    }
+
+    /// Removes the callbacks of all events. The `on_any` callback is kept.
+    // While present implementation doesn't require mut, it's reasonable to require mutability.
+    #[allow(unused_mut)]
+    pub fn off_all(mut self) -> Self {
+        // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
+        self.on.lock().unwrap().clear();
         self
     }
 
@@ -208,6 +600,123 @@ impl ClientBuilder {
         self
     }
 
+    /// Registers a callback for all [`crate::event::Event::Custom`] and
+    /// [`crate::event::Event::Message`] that are received without a handler,
+    /// i.e. that neither have a callback of their own nor are caught by
+    /// [`ClientBuilder::on_any`]. This helps to detect events the server added
+    /// or renamed. See [`ClientBuilder::unhandled_policy`] for what happens to
+    /// these events besides.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Payload};
+    ///
+    /// let client = ClientBuilder::new("http://localhost:4200/")
+    ///     .on("foo", |payload, _client| println!("foo: {payload:?}"))
+    ///     .on_unhandled(|event, _payload, _client| eprintln!("no handler for {event}"))
+    ///     .connect();
+    /// ```
+    // While present implementation doesn't require mut, it's reasonable to require mutability.
+    #[allow(unused_mut)]
+    pub fn on_unhandled<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(Event, Payload, RawClient) + 'static + Send,
+    {
+        let callback = Some(Callback::<SocketAnyCallback>::new(callback));
+        // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
+        *self.on_unhandled.lock().unwrap() = callback;
+        self
+    }
+
+    /// Registers a callback for the errors of the client, which receives them
+    /// as typed [`crate::Error`] values that can be matched on. It's called
+    /// before the callbacks of [`crate::event::Event::Error`], which receive the
+    /// errors as strings. Errors the server sends in a `ConnectError` packet
+    /// are only passed to the latter.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Error};
+    ///
+    /// let client = ClientBuilder::new("http://localhost:4200/")
+    ///     .on_error(|err, _client| match err {
+    ///         Error::Disconnected(event) => eprintln!("no ack for {event}"),
+    ///         err => eprintln!("error: {err}"),
+    ///     })
+    ///     .connect();
+    /// ```
+    // While present implementation doesn't require mut, it's reasonable to require mutability.
+    #[allow(unused_mut)]
+    pub fn on_error<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Error, RawClient) + 'static + Send,
+    {
+        let callback = Some(Callback::<SocketErrorCallback>::new(callback));
+        // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
+        *self.on_error.lock().unwrap() = callback;
+        self
+    }
+
+    /// Sets the [`UnhandledPolicy`] that defines what happens to received events
+    /// without a handler, see [`ClientBuilder::on_unhandled`].
+    /// Defaults to [`UnhandledPolicy::Ignore`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, UnhandledPolicy};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .unhandled_policy(UnhandledPolicy::Warn)
+    ///     .connect();
+    /// ```
+    pub fn unhandled_policy(mut self, policy: UnhandledPolicy) -> Self {
+        self.unhandled_policy = policy;
+        self
+    }
+
+    /// Sets the [`Utf8Policy`] that defines what happens to received text
+    /// packets that aren't valid UTF-8. Defaults to [`Utf8Policy::Reject`].
+    /// The policy only applies to polling, see [`Utf8Policy`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Utf8Policy};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .utf8_policy(Utf8Policy::Lossy)
+    ///     .connect();
+    /// ```
+    pub fn utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Registers a codec for the payloads of a certain [`crate::event::Event`], which
+    /// are then sent and received as binary attachments instead of JSON. See
+    /// [`PayloadCodec`] for an example.
+    // While present implementation doesn't require mut, it's reasonable to require mutability.
+    #[allow(unused_mut)]
+    pub fn codec<T: Into<Event>, C>(mut self, event: T, codec: C) -> Self
+    where
+        C: PayloadCodec + 'static,
+    {
+        // SAFETY: Lock is held for such amount of time no code paths lead to a panic while lock is held
+        self.codecs
+            .lock()
+            .unwrap()
+            .insert(event.into(), Arc::new(codec));
+        self
+    }
+
+    /// Sets the versioned schemas of events, which tag emits with their version
+    /// and migrate and validate received payloads. Received events that don't
+    /// match their schema are reported as [`Error::InvalidSchemaPayload`] and
+    /// dropped. See [`EventSchemas`] for an example.
+    pub fn schemas(mut self, schemas: EventSchemas) -> Self {
+        self.schemas = schemas;
+        self
+    }
+
     /// Uses a preconfigured TLS connector for secure communication. This configures
     /// both the `polling` as well as the `websocket` transport type.
     /// # Example
@@ -261,6 +770,52 @@ impl ClientBuilder {
         self
     }
 
+    /// Adds the headers of an [`http::HeaderMap`] to the headers of the
+    /// opening request, see [`ClientBuilder::opening_header`]. Headers that
+    /// were set before are replaced, the values of a repeated header are
+    /// joined into one, separated by commas, or by semicolons for `Cookie`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http::{header, HeaderMap, HeaderValue};
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(header::USER_AGENT, HeaderValue::from_static("dashboard/1.0"));
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .opening_headers(headers)
+    ///     .connect();
+    /// ```
+    pub fn opening_headers(mut self, headers: http::HeaderMap) -> Self {
+        let map = self.opening_headers.get_or_insert_with(HeaderMap::default);
+        for (key, value) in HeaderMap::from(headers) {
+            map.insert(key, value);
+        }
+        self
+    }
+
+    /// Appends a query parameter to the URL of the server, e.g. a room or a
+    /// client version that the server reads from the handshake. The value is
+    /// converted via [`QueryValue`] and percent-encoded, so that numbers,
+    /// booleans, spaces and unicode arrive as they were given. Parameters in
+    /// the URL passed to [`ClientBuilder::new`] are kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// // connects to /socket.io/?room=caf%C3%A9+1&limit=20&compact=true&EIO=4...
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .query("room", "café 1")
+    ///     .query("limit", 20)
+    ///     .query("compact", true)
+    ///     .connect();
+    /// ```
+    pub fn query<K: Into<String>, V: Into<QueryValue>>(mut self, key: K, value: V) -> Self {
+        self.query.push((key.into(), value.into()));
+        self
+    }
+
     /// Sets data sent in the opening request.
     /// # Example
     /// ```rust
@@ -281,6 +836,312 @@ impl ClientBuilder {
         self
     }
 
+    /// Authenticates the connections with a bearer token in the `Authorization`
+    /// header of every request and websocket upgrade. The client reconnects
+    /// once the token is rotated, see [`BearerAuth`].
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{BearerAuth, ClientBuilder};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .bearer_auth(BearerAuth::new(|| String::from("secret-token")))
+    ///     .connect();
+    /// ```
+    pub fn bearer_auth(mut self, auth: BearerAuth) -> Self {
+        self.bearer_auth = Some(auth);
+        self
+    }
+
+    /// If set to `true`, every text payload emitted by this client is tagged with a
+    /// random id that identifies the client. Events that carry the client's own tag
+    /// (e.g. broadcasts the server sends back to all room members including the sender)
+    /// are dropped instead of being passed to the registered callbacks. Tags of other
+    /// clients are removed from the payload before the callbacks are called.
+    /// Binary payloads can't be tagged and are therefore never suppressed,
+    /// neither are emits that request an ack, as the tag would take the place
+    /// of the ack callback in the handler of the server. Defaults to `false`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .suppress_echo(true)
+    ///     .connect();
+    /// ```
+    pub fn suppress_echo(mut self, suppress_echo: bool) -> Self {
+        self.origin = if suppress_echo {
+            Some(format!("{:016x}", thread_rng().gen::<u64>()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Tags every text payload emitted by this client with a sequence number,
+    /// so that the server can detect lost and duplicated emits, e.g. around a
+    /// reconnect. The number is carried in a trailing argument
+    /// `{"__rust_socketio_seq": n}`. Numbers are assigned in the order the
+    /// emits are sent, so buffered emits get theirs once they're flushed after
+    /// the reconnect, while emits that are resent (see
+    /// [`crate::EmitOptions::resend_on_reconnect`]) keep theirs. The first emit
+    /// gets `next`, pass the value of `Client::next_sequence` that was
+    /// persisted by a previous run to continue its numbers. Disabled by default.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// // continue the numbers of the previous run
+    /// let next = 1337;
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .sequence_numbers(next)
+    ///     .connect()
+    ///     .expect("Connection failed");
+    ///
+    /// // ... persist `socket.next_sequence()` on shutdown
+    /// ```
+    pub fn sequence_numbers(mut self, next: u64) -> Self {
+        self.sequence = Some(SequenceCounter::new(next));
+        self
+    }
+
+    /// Calls the callbacks of this client on the given [`Dispatcher`], which
+    /// preserves the arrival order of events across all clients sharing it, e.g.
+    /// clients connected to different namespaces. By default every client calls
+    /// its callbacks independently, in parallel to other clients.
+    pub fn dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = Some(dispatcher);
+        self
+    }
+
+    /// Registers a hook that decides what the logs show of the payloads this
+    /// client sends and receives. Without a hook, only the shape of a payload is
+    /// logged, see [`RedactedView::summary`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Event, Payload, RedactedView};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .redact(|event: &Event, payload: &Payload| match (event.as_str(), payload) {
+    ///         // the credentials of a login must never be logged
+    ///         ("login", _) => RedactedView::new("<redacted>"),
+    ///         (_, Payload::Text(values)) => RedactedView::new(format!("{values:?}")),
+    ///         (_, payload) => RedactedView::summary(payload),
+    ///     })
+    ///     .connect();
+    /// ```
+    pub fn redact<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Event, &Payload) -> RedactedView + 'static + Send + Sync,
+    {
+        self.redaction = Redaction::new(Arc::new(hook));
+        self
+    }
+
+    /// Encodes text events whose encoded packet is at most `bytes` long in a
+    /// buffer on the stack, instead of building the intermediate strings of the
+    /// regular packet encoding. This saves allocations for clients that emit
+    /// many small events. Events with binary payloads or acks are always encoded
+    /// the regular way. Disabled by default.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     // most events of this client are small status updates
+    ///     .small_event_threshold(128)
+    ///     .connect();
+    /// ```
+    pub fn small_event_threshold(mut self, bytes: usize) -> Self {
+        self.small_event_threshold = Some(bytes);
+        self
+    }
+
+    /// Limits the size of the payloads that are retained for the callbacks of
+    /// `emit_with_ack_request`. The payload of a larger request isn't passed
+    /// to its callback, only its event. Defaults to 64 KiB.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     // requests are small, responses are large
+    ///     .ack_request_limit(1024)
+    ///     .connect();
+    /// ```
+    pub fn ack_request_limit(mut self, bytes: usize) -> Self {
+        self.ack_request_limit = bytes;
+        self
+    }
+
+    /// Registers a handler for the events of the underlying `engine.io`
+    /// connection, e.g. to observe the transport upgrade or the pings of the
+    /// server. The handler is called for every packet the connection receives,
+    /// including the ones carrying `socket.io` packets. Pings are answered by
+    /// the connection itself, so there is no event for the pong.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, EngineEvent};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .on_engineio(|event| match event {
+    ///         EngineEvent::Upgrade => println!("upgraded to websocket"),
+    ///         EngineEvent::Ping => println!("ping"),
+    ///         _ => {}
+    ///     })
+    ///     .connect();
+    /// ```
+    pub fn on_engineio<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(EngineEvent) + 'static + Send + Sync,
+    {
+        self.on_engineio = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called whenever the state of a namespace of
+    /// the client changes, e.g. once the server accepted or refused the
+    /// connection. The current states are returned by `Client::namespaces`.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, NamespaceState};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .namespace("/admin")
+    ///     .on_namespace_state_change(|nsp, status| {
+    ///         if status.state == NamespaceState::Failed {
+    ///             println!("{nsp} failed: {:?}", status.last_error);
+    ///         }
+    ///     })
+    ///     .connect();
+    /// ```
+    pub fn on_namespace_state_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str, &NamespaceStatus) + 'static + Send + Sync,
+    {
+        self.on_namespace_state_change = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called with the progress of the client
+    /// while it reconnects: before every attempt, after every failed one and
+    /// once it reconnected.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, ReconnectEvent};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .on_reconnect_event(|event| {
+    ///         if let ReconnectEvent::Error(err) = event {
+    ///             println!("failed to reconnect: {err}");
+    ///         }
+    ///     })
+    ///     .connect();
+    /// ```
+    pub fn on_reconnect_event<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ReconnectEvent) + 'static + Send + Sync,
+    {
+        self.on_reconnect_event = Some(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called with the transport the client is
+    /// connected over whenever it changes, i.e. on the first connect and when
+    /// a reconnect ends up on another transport than before, e.g. because the
+    /// client fell back to polling. As the `engine.io` connection is upgraded
+    /// while it's opened, the handler is called with the transport after the
+    /// upgrade. The current transport is returned by `Client::snapshot`.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .on_transport_change(|transport| println!("connected over {transport:?}"))
+    ///     .connect();
+    /// ```
+    pub fn on_transport_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(TransportKind) + 'static + Send + Sync,
+    {
+        self.on_transport_change = Some(Arc::new(handler));
+        self
+    }
+
+    /// Emits the given subscription events again after every connect and
+    /// reconnect, once the server accepted the connection to the namespace,
+    /// so a server that lost its state after a restart or failover still
+    /// delivers the subscribed topics. The events are emitted in the given
+    /// order, failed emits are reported to the error callback. Calling it
+    /// again adds further subscriptions.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    /// use serde_json::json;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .resubscribe_on_connect(vec![
+    ///         ("subscribe", json!("prices")),
+    ///         ("subscribe", json!("news")),
+    ///     ])
+    ///     .connect();
+    /// ```
+    pub fn resubscribe_on_connect<E, D>(
+        mut self,
+        subscriptions: impl IntoIterator<Item = (E, D)>,
+    ) -> Self
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.resubscriptions.extend(
+            subscriptions
+                .into_iter()
+                .map(|(event, payload)| (event.into(), payload.into())),
+        );
+        self
+    }
+
+    /// Disconnects the client once no `socket.io` packet was sent or received
+    /// for `timeout`, e.g. to save the connection time billed by a hosted
+    /// server. The heartbeats of `engine.io` don't count as traffic. The
+    /// client doesn't reconnect after closing an idle connection. Disabled by
+    /// default.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self.activity = Some(ActivityTracker::new());
+        self
+    }
+
+    /// Registers a handler that is called before an idle connection is closed
+    /// (see [`ClientBuilder::idle_timeout`]) with the time since the last
+    /// packet. Returning `false` keeps the connection open for another period
+    /// of the timeout.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .idle_timeout(Duration::from_secs(300))
+    ///     .on_before_idle_close(|idle| {
+    ///         println!("closing the connection after {idle:?} without traffic");
+    ///         true
+    ///     })
+    ///     .connect();
+    /// ```
+    pub fn on_before_idle_close<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Duration) -> bool + 'static + Send + Sync,
+    {
+        self.on_before_idle_close = Some(Arc::new(handler));
+        self
+    }
+
     /// Specifies which EngineIO [`TransportType`] to use.
     /// # Example
     /// ```rust
@@ -301,8 +1162,150 @@ impl ClientBuilder {
     /// assert!(result.is_ok());
     /// ```
     pub fn transport_type(mut self, transport_type: TransportType) -> Self {
-        self.transport_type = transport_type;
+        self.transports = vec![transport_type];
+
+        self
+    }
+
+    /// Specifies the EngineIO [`TransportType`]s to try in order. If the
+    /// handshake with one of them fails, the client falls back to the next one,
+    /// on the first connect as well as on reconnects, and fails with the error
+    /// of the last one if none connects. An empty list is the same as
+    /// [`TransportType::Any`].
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, TransportType};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     // handshake with websockets, fall back to polling if they're blocked
+    ///     .transports([TransportType::Websocket, TransportType::Polling])
+    ///     .connect()
+    ///     .expect("connection failed");
+    /// ```
+    pub fn transports(mut self, transports: impl IntoIterator<Item = TransportType>) -> Self {
+        self.transports = transports.into_iter().collect();
+        if self.transports.is_empty() {
+            self.transports.push(TransportType::Any);
+        }
+        self
+    }
+
+    /// Sets the order in which the client tries the servers if it was created
+    /// with several URLs. Defaults to [`Failover::Priority`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Failover};
+    ///
+    /// let socket = ClientBuilder::new(vec![
+    ///     "http://localhost:4200/",
+    ///     "http://localhost:4201/",
+    /// ])
+    /// .failover(Failover::RoundRobin)
+    /// .connect();
+    /// ```
+    pub fn failover(mut self, failover: Failover) -> Self {
+        self.endpoints.set_failover(failover);
+        self
+    }
+
+    /// Sends websocket `Ping` frames in the given interval while connected via
+    /// websocket. This is independent of the `engine.io` heartbeat and keeps
+    /// proxies happy that track the liveness of a websocket connection on the
+    /// protocol layer. Intervals below 1 ms are raised to 1 ms. Disabled by
+    /// default.
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, TransportType};
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .transport_type(TransportType::Websocket)
+    ///     .websocket_ping_interval(Duration::from_secs(10))
+    ///     .connect();
+    /// ```
+    #[cfg(feature = "websocket")]
+    pub fn websocket_ping_interval(mut self, interval: Duration) -> Self {
+        self.websocket_ping_interval = Some(interval);
+        self
+    }
+
+    /// Remembers the servers that accepted an upgrade to websocket in `store`,
+    /// like `rememberUpgrade` of the JavaScript client. With
+    /// [`TransportType::Any`], connects and reconnects to a remembered server
+    /// then skip the polling handshake and open a websocket right away.
+    /// [`crate::FileUpgradeStore`] persists the servers across runs, e.g. for
+    /// command line tools.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, FileUpgradeStore};
+    ///
+    /// let upgrades = FileUpgradeStore::open("/tmp/socketio-upgrades").unwrap();
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .remember_upgrade(upgrades)
+    ///     .connect();
+    /// ```
+    #[cfg(feature = "websocket")]
+    pub fn remember_upgrade<S: UpgradeStore + 'static>(mut self, store: S) -> Self {
+        self.upgrade_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Sets the number of idle buffers that are kept for encoding outgoing
+    /// packets and reading incoming binary frames, `0` disables pooling.
+    /// Defaults to [`rust_engineio::DEFAULT_BUFFER_POOL_SIZE`].
+    pub fn buffer_pool_size(mut self, size: usize) -> Self {
+        self.buffer_pool_size = Some(size);
+        self
+    }
+
+    /// Sets the HTTP protocol version of the polling transport, e.g. to force
+    /// HTTP/2 for servers that support it. Defaults to [`HttpProtocol::Http1`],
+    /// negotiating HTTP/2 over TLS needs the `http2` feature.
+    pub fn http_protocol(mut self, http_protocol: HttpProtocol) -> Self {
+        self.http_protocol = http_protocol;
+        self
+    }
+
+    /// Sets how often a long-poll request of the polling transport is retried
+    /// when a gateway responds with `502` or `503`, which keeps the session alive
+    /// across brief outages. Defaults to [`PollRetry::default`].
+    pub fn poll_retry(mut self, poll_retry: PollRetry) -> Self {
+        self.poll_retry = Some(poll_retry);
+        self
+    }
+
+    /// Limits the size of the payloads that are received from the server, larger
+    /// payloads fail with [`rust_engineio::Error::PayloadTooLarge`] before they're
+    /// read completely. Protects against malicious or misconfigured servers.
+    pub fn max_payload_size(mut self, limit: usize) -> Self {
+        self.max_payload_size = Some(limit);
+        self
+    }
 
+    /// Binds the connections to the server to the given local address, for
+    /// hosts with several network interfaces. Applies to the polling and the
+    /// websocket transport.
+    pub fn local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// Binds the connections to the server to the given network interface
+    /// (`SO_BINDTODEVICE`), which usually requires elevated privileges.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(mut self, interface: impl Into<String>) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+
+    /// Resolves the host of the server with `resolver` instead of the DNS
+    /// resolver of the system, e.g. with a [`crate::DohResolver`] for
+    /// DNS-over-HTTPS. Applies to the polling and the websocket transport and
+    /// to every reconnect.
+    pub fn resolver<R: Resolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Arc::new(resolver));
         self
     }
 
@@ -329,45 +1332,218 @@ impl ClientBuilder {
     ///
     /// assert!(result.is_ok());
     /// ```
-    pub fn connect(self) -> Result<Client> {
+    pub fn connect(mut self) -> Result<Client> {
+        self.recovery = SessionRecovery::default();
         Client::new(self)
     }
 
-    pub fn connect_raw(self) -> Result<RawClient> {
+    pub fn connect_raw(mut self) -> Result<RawClient> {
+        self.recovery = SessionRecovery::default();
+        self.reconnect_raw()
+    }
+
+    /// Connects like [`ClientBuilder::connect_raw`], but recovers the session
+    /// of the previous connection of this builder, for the reconnects of a
+    /// [`Client`].
+    pub(crate) fn reconnect_raw(self) -> Result<RawClient> {
+        let inner_socket = self.inner_create()?;
+
+        let socket = RawClient::new(
+            inner_socket,
+            &self.namespace,
+            self.on,
+            self.on_any,
+            self.codecs,
+            self.auth,
+            self.origin,
+        )?
+        .with_sequence(self.sequence)
+        .with_recovery(self.recovery)
+        .with_activity(self.activity)
+        .with_dispatcher(self.dispatcher)
+        .with_redaction(self.redaction)
+        .with_schemas(self.schemas)
+        .with_unhandled(self.unhandled_policy, self.on_unhandled)
+        .with_on_error(self.on_error);
+        socket.connect()?;
+
+        Ok(socket)
+    }
+
+    /// Connects to the engine.io server at `address`.
+    fn engine_client(&self, address: &str) -> Result<EngineClient> {
         // Parse url here rather than in new to keep new returning Self.
-        let mut url = Url::parse(&self.address)?;
+        // `Url` rejects the zone of a link-local IPv6 address, like `%eth0`
+        let (mut url, zone) = split_zone(address)?;
 
         if url.path() == "/" {
             url.set_path("/socket.io/");
         }
+        if !self.query.is_empty() {
+            let mut pairs = url.query_pairs_mut();
+            for (key, value) in &self.query {
+                pairs.append_pair(key, value.as_str());
+            }
+        }
 
         let mut builder = EngineIoClientBuilder::new(url);
+        if let Some(zone) = zone {
+            builder = builder.ipv6_zone(zone);
+        }
 
-        if let Some(tls_config) = self.tls_config {
-            builder = builder.tls_config(tls_config);
+        if let Some(handler) = &self.on_engineio {
+            builder = Self::forward_engine_events(builder, handler.clone());
         }
-        if let Some(headers) = self.opening_headers {
+        if let Some(tls_config) = &self.tls_config {
+            builder = builder.tls_config(tls_config.to_owned());
+        }
+        let mut headers = self.opening_headers.clone();
+        if let Some(auth) = &self.bearer_auth {
+            headers
+                .get_or_insert_with(HeaderMap::default)
+                .insert(AUTHORIZATION.to_owned(), auth.header());
+        }
+        if let Some(headers) = headers {
             builder = builder.headers(headers);
         }
+        #[cfg(feature = "websocket")]
+        if let Some(interval) = self.websocket_ping_interval {
+            builder = builder.websocket_ping_interval(interval);
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(store) = &self.upgrade_store {
+            builder = builder.remember_upgrade(store.clone());
+        }
+        if let Some(size) = self.buffer_pool_size {
+            builder = builder.buffer_pool_size(size);
+        }
+        builder = builder.http_protocol(self.http_protocol);
+        if let Some(poll_retry) = self.poll_retry {
+            builder = builder.poll_retry(poll_retry);
+        }
+        if let Some(limit) = self.max_payload_size {
+            builder = builder.max_payload_size(limit);
+        }
+        if let Some(address) = self.local_address {
+            builder = builder.local_address(address);
+        }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(interface) = &self.interface {
+            builder = builder.interface(interface.to_owned());
+        }
+        if let Some(resolver) = &self.resolver {
+            builder = builder.resolver(resolver.clone());
+        }
 
-        let engine_client = match self.transport_type {
-            TransportType::Any => builder.build_with_fallback()?,
-            TransportType::Polling => builder.build_polling()?,
-            TransportType::Websocket => builder.build_websocket()?,
-            TransportType::WebsocketUpgrade => builder.build_websocket_with_upgrade()?,
-        };
+        let mut result = Err(Error::InvalidUrl(url::ParseError::EmptyHost));
+        for transport_type in &self.transports {
+            let builder = builder.clone();
+            let connected = match transport_type {
+                TransportType::Any => builder.build_with_fallback(),
+                TransportType::Polling => builder.build_polling(),
+                #[cfg(feature = "websocket")]
+                TransportType::Websocket => builder.build_websocket(),
+                #[cfg(feature = "websocket")]
+                TransportType::WebsocketUpgrade => builder.build_websocket_with_upgrade(),
+            };
+            result = connected.map_err(Error::from);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
 
-        let inner_socket = InnerSocket::new(engine_client)?;
+    /// Creates the socket to the first of the servers that accepts the
+    /// handshake, in the order of the [`Failover`]. Fails with the error of
+    /// the last server if none does.
+    fn inner_create(&self) -> Result<InnerSocket> {
+        let mut result = Err(Error::InvalidUrl(url::ParseError::EmptyHost));
+        for (index, address) in self.endpoints.candidates() {
+            result = self.engine_client(address);
+            if result.is_ok() {
+                self.endpoints.connected(index);
+                break;
+            }
+        }
 
-        let socket = RawClient::new(
-            inner_socket,
-            &self.namespace,
-            self.on,
-            self.on_any,
-            self.auth,
-        )?;
-        socket.connect()?;
+        let inner_socket = InnerSocket::new(result?)?
+            .with_small_event_threshold(self.small_event_threshold)
+            .with_utf8_policy(self.utf8_policy);
+        Ok(inner_socket)
+    }
 
-        Ok(socket)
+    /// Registers `handler` for all events of the `engine.io` connection.
+    fn forward_engine_events(
+        builder: EngineIoClientBuilder,
+        handler: EngineEventHandler,
+    ) -> EngineIoClientBuilder {
+        let (on_open, on_close, on_error, on_packet) = (
+            handler.clone(),
+            handler.clone(),
+            handler.clone(),
+            handler.clone(),
+        );
+        let builder = builder
+            .on_open(move |_| on_open(EngineEvent::Open))
+            .on_close_with_description(move |description| on_close(EngineEvent::Close(description)))
+            .on_error(move |error| on_error(EngineEvent::Error(error)))
+            .on_packet(move |packet| on_packet(EngineEvent::from(packet)));
+        #[cfg(feature = "websocket")]
+        let builder = builder.on_upgrade(move |_| handler(EngineEvent::Upgrade));
+        builder
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_realtime() {
+        let builder = ClientBuilder::new("http://localhost:4200/").realtime();
+        assert_eq!(builder.emit_policy, EmitPolicy::FailFast);
+        assert!(!builder.resend_on_reconnect);
+        assert!(builder.reconnect);
+        assert!(!builder.reconnect_on_disconnect);
+        assert_eq!(builder.max_reconnect_attempts, None);
+        assert_eq!(
+            (builder.reconnect_delay_min, builder.reconnect_delay_max),
+            (100, 1000)
+        );
+        assert_eq!(builder.poll_retry, Some(PollRetry::disabled()));
+        #[cfg(feature = "websocket")]
+        assert!(builder.transports == [TransportType::Websocket]);
+    }
+
+    #[test]
+    fn test_reliable() {
+        let builder = ClientBuilder::new("http://localhost:4200/").reliable();
+        assert_eq!(builder.emit_policy, EmitPolicy::Buffer);
+        assert!(builder.resend_on_reconnect);
+        assert!(builder.reconnect);
+        assert!(builder.reconnect_on_disconnect);
+        assert_eq!(builder.max_reconnect_attempts, None);
+        assert_eq!(
+            (builder.reconnect_delay_min, builder.reconnect_delay_max),
+            (500, 30_000)
+        );
+        assert_eq!(
+            builder.poll_retry,
+            Some(PollRetry::new(5, Duration::from_millis(200)))
+        );
+        assert!(builder.transports == [TransportType::Any]);
+
+        // settings after the preset override it
+        let builder = ClientBuilder::new("http://localhost:4200/")
+            .reliable()
+            .emit_policy(EmitPolicy::FailFast)
+            .reconnect_delay(200, 10_000);
+        assert_eq!(builder.emit_policy, EmitPolicy::FailFast);
+        assert_eq!(
+            (builder.reconnect_delay_min, builder.reconnect_delay_max),
+            (200, 10_000)
+        );
+        assert!(builder.resend_on_reconnect);
     }
 }
@@ -4,10 +4,11 @@ use std::{
 };
 
 use super::RawClient;
-use crate::{Event, Payload};
+use crate::{Error, Event, Payload};
 
 pub(crate) type SocketCallback = Box<dyn FnMut(Payload, RawClient) + 'static + Send>;
 pub(crate) type SocketAnyCallback = Box<dyn FnMut(Event, Payload, RawClient) + 'static + Send>;
+pub(crate) type SocketErrorCallback = Box<dyn FnMut(&Error, RawClient) + 'static + Send>;
 
 pub(crate) struct Callback<T> {
     inner: T,
@@ -44,6 +45,10 @@ impl Callback<SocketCallback> {
             inner: Box::new(callback),
         }
     }
+
+    pub(crate) fn into_inner(self) -> SocketCallback {
+        self.inner
+    }
 }
 
 // SocketAnyCallback implementations
@@ -78,3 +83,36 @@ impl Callback<SocketAnyCallback> {
         }
     }
 }
+
+// SocketErrorCallback implementations
+
+impl Debug for Callback<SocketErrorCallback> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+impl Deref for Callback<SocketErrorCallback> {
+    type Target = dyn FnMut(&Error, RawClient) + 'static + Send;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.as_ref()
+    }
+}
+
+impl DerefMut for Callback<SocketErrorCallback> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner.as_mut()
+    }
+}
+
+impl Callback<SocketErrorCallback> {
+    pub(crate) fn new<T>(callback: T) -> Self
+    where
+        T: FnMut(&Error, RawClient) + 'static + Send,
+    {
+        Callback {
+            inner: Box::new(callback),
+        }
+    }
+}
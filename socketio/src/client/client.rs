@@ -1,40 +1,91 @@
 use std::{
-    sync::{Arc, Mutex, RwLock},
-    time::Duration,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, RwLock,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
-use super::{ClientBuilder, RawClient};
+use super::{callback::SocketCallback, ClientBuilder, RawClient};
+#[cfg(feature = "async")]
+use crate::dyn_client::blocking;
 use crate::{
+    emit::{
+        AckFailure, Admission, BufferedEmit, ConnectionState, EmitGate, PendingStats,
+        SequenceCounter,
+    },
     error::Result,
+    idle::{ActivityTracker, BeforeIdleCloseHandler},
+    namespace::NamespaceTracker,
     packet::{Packet, PacketId},
-    Error,
+    uptime::UptimeTracker,
+    AckRequest, BufferPoolStats, ClientSnapshot, EmitOptions, Error, HttpVersion, NamespaceState,
+    NamespaceStatus, PayloadCodec, ReconnectEvent, ServerAddresses, UptimeStats, Utf8Policy,
 };
 pub(crate) use crate::{event::Event, payload::Payload};
 use backoff::ExponentialBackoff;
 use backoff::{backoff::Backoff, ExponentialBackoffBuilder};
+use log::trace;
+use serde::Serialize;
 
 #[derive(Clone)]
 pub struct Client {
     builder: Arc<Mutex<ClientBuilder>>,
     client: Arc<RwLock<RawClient>>,
     backoff: ExponentialBackoff,
+    gate: Arc<EmitGate<BufferedEmit<SocketCallback>>>,
+    uptime: UptimeTracker,
+    namespaces: NamespaceTracker,
+    poll_thread: Arc<Mutex<Option<JoinHandle<Result<()>>>>>,
+    // Set to reconnect once the connection closed, e.g. to rotate the token
+    reconnect_requested: Arc<AtomicBool>,
 }
 
 impl Client {
     pub(crate) fn new(builder: ClientBuilder) -> Result<Self> {
         let builder_clone = builder.clone();
-        let client = builder_clone.connect_raw()?;
+        let client = builder_clone.reconnect_raw()?;
         let backoff = ExponentialBackoffBuilder::new()
             .with_initial_interval(Duration::from_millis(builder.reconnect_delay_min))
             .with_max_interval(Duration::from_millis(builder.reconnect_delay_max))
             .build();
 
+        let idle = builder.idle_timeout.zip(builder.activity.clone());
+        let on_before_idle_close = builder.on_before_idle_close.clone();
+        let bearer_auth = builder.bearer_auth.clone();
+
+        let gate = Arc::new(EmitGate::new(builder.emit_buffer_limit));
+        let transport = client.transport_kind();
+        gate.snapshot().set_transport(transport);
+        if let Some(handler) = &builder.on_transport_change {
+            handler(transport);
+        }
         let s = Self {
+            gate,
             builder: Arc::new(Mutex::new(builder)),
             client: Arc::new(RwLock::new(client)),
             backoff,
+            uptime: UptimeTracker::default(),
+            namespaces: NamespaceTracker::default(),
+            poll_thread: Default::default(),
+            reconnect_requested: Default::default(),
         };
-        s.poll_callback();
+        s.set_namespace_state(NamespaceState::Pending, None);
+        s.poll_callback()?;
+        if let Some((timeout, activity)) = idle {
+            s.watch_idle(timeout, activity, on_before_idle_close);
+        }
+        if let Some(auth) = bearer_auth {
+            let client = s.clone();
+            let alive = s.clone();
+            auth.watch(
+                move || alive.is_alive(),
+                move || client.reconnect_with_rotated_token(),
+            );
+        }
 
         Ok(s)
     }
@@ -42,7 +93,10 @@ impl Client {
     /// Updates the URL the client will connect to when reconnecting.
     /// This is especially useful for updating query parameters.
     pub fn set_reconnect_url<T: Into<String>>(&self, address: T) -> Result<()> {
-        self.builder.lock()?.address = address.into();
+        self.builder
+            .lock()?
+            .endpoints
+            .replace(ServerAddresses::from(address.into()));
         Ok(())
     }
 
@@ -76,9 +130,73 @@ impl Client {
         E: Into<Event>,
         D: Into<Payload>,
     {
-        let client = self.client.read()?;
-        // TODO(#230): like js client, buffer emit, resend after reconnect
-        client.emit(event, data)
+        self.emit_opts(event, data, EmitOptions::default())
+    }
+
+    /// Sends a message to the server like [`Client::emit`], but allows to
+    /// override the settings of the client for this single emit, e.g. the
+    /// [`crate::EmitPolicy`] that applies while the client is reconnecting.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{ClientBuilder, EmitOptions, EmitPolicy};
+    /// use serde_json::json;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// let options = EmitOptions::new().policy(EmitPolicy::Buffer);
+    /// let result = socket.emit_opts("foo", json!({"token": 123}), options);
+    ///
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn emit_opts<E, D>(&self, event: E, data: D, options: EmitOptions) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.send(
+            BufferedEmit {
+                event: event.into(),
+                payload: data.into(),
+                ack: None,
+                resend_on_reconnect: false,
+                deadline: options.deadline,
+                sequence: None,
+                on_failure: None,
+            },
+            options,
+        )
+    }
+
+    /// Sends a message to the server like [`Client::emit`], but drops it if it
+    /// can't be sent until `deadline`, e.g. because it's buffered while the
+    /// client reconnects. See [`EmitOptions::deadline`].
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{ClientBuilder, EmitPolicy};
+    /// use serde_json::json;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .emit_policy(EmitPolicy::Buffer)
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// // a position update is worthless after a second
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    /// let result = socket.emit_until("position", json!({"x": 1, "y": 2}), deadline);
+    ///
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn emit_until<E, D>(&self, event: E, data: D, deadline: Instant) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.emit_opts(event, data, EmitOptions::new().deadline(deadline))
     }
 
     /// Sends a message to the server but `alloc`s an `ack` to check whether the
@@ -90,7 +208,10 @@ impl Client {
     /// It also requires a timeout `Duration` in which the client needs to answer.
     /// If the ack is acked in the correct time span, the specified callback is
     /// called. The callback consumes a [`Payload`] which represents the data send
-    /// by the server.
+    /// by the server. If the connection closes before, the callback isn't called
+    /// and [`Error::Disconnected`] is reported to the callbacks of
+    /// [`Event::Error`], use [`Client::emit_with_ack_result`] to receive the
+    /// error instead.
     ///
     /// # Example
     /// ```
@@ -130,9 +251,281 @@ impl Client {
         E: Into<Event>,
         D: Into<Payload>,
     {
+        self.emit_with_ack_opts(event, data, timeout, callback, EmitOptions::default())
+    }
+
+    /// Sends a message to the server and requests an ack like
+    /// [`Client::emit_with_ack`], but allows to override the settings of the
+    /// client for this single emit. Set [`EmitOptions::resend_on_reconnect`] in
+    /// order to send the emit again after a reconnect if the connection drops
+    /// before the ack was received.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{ClientBuilder, EmitOptions};
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .reconnect(true)
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// let options = EmitOptions::new().resend_on_reconnect(true);
+    /// let result = socket.emit_with_ack_opts(
+    ///     "foo",
+    ///     json!({"token": 123}),
+    ///     Duration::from_secs(2),
+    ///     |payload, _| println!("Ack: {:#?}", payload),
+    ///     options,
+    /// );
+    ///
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn emit_with_ack_opts<F, E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+        callback: F,
+        options: EmitOptions,
+    ) -> Result<()>
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let resend_on_reconnect = match options.resend_on_reconnect {
+            Some(resend) => resend,
+            None => self.builder.lock()?.resend_on_reconnect,
+        };
+        self.send(
+            BufferedEmit {
+                event: event.into(),
+                payload: data.into(),
+                ack: Some((timeout, Box::new(callback))),
+                resend_on_reconnect,
+                deadline: options.deadline,
+                sequence: None,
+                on_failure: None,
+            },
+            options,
+        )
+    }
+
+    /// Sends a message to the server and requests an ack like
+    /// [`Client::emit_with_ack`], but also passes the emit that requested the
+    /// ack to the callback, which saves correlating the response with the
+    /// request. The payload is only retained up to the size set via
+    /// [`ClientBuilder::ack_request_limit`].
+    ///
+    /// # Example
+    /// ```
+    /// use rust_socketio::{AckRequest, ClientBuilder, Payload, RawClient};
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// let result = socket.emit_with_ack_request(
+    ///     "get_user",
+    ///     json!({"id": 42}),
+    ///     Duration::from_secs(2),
+    ///     |request: AckRequest, response: Payload, _: RawClient| {
+    ///         println!("{:?} answered with {:?}", request.payload(), response)
+    ///     },
+    /// );
+    ///
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn emit_with_ack_request<F, E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(AckRequest, Payload, RawClient) + 'static + Send,
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let (event, payload) = (event.into(), data.into());
+        let limit = self.builder.lock()?.ack_request_limit;
+        let request = AckRequest::retain(event.clone(), &payload, limit);
+        self.emit_with_ack(event, payload, timeout, move |response, client| {
+            callback(request.clone(), response, client)
+        })
+    }
+
+    /// Sends a message to the server, requests an ack like
+    /// [`Client::emit_with_ack`] and blocks until it's received. Unlike the
+    /// callback of [`Client::emit_with_ack`], the result tells why an ack
+    /// wasn't received: [`Error::AckTimeout`] if the server didn't answer
+    /// within `timeout`, and [`Error::Disconnected`] as soon as the connection
+    /// the ack was requested over closes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::ClientBuilder;
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// let ack = socket.emit_with_ack_result("get_user", json!({"id": 42}), Duration::from_secs(2));
+    /// println!("the server answered {ack:?}");
+    /// ```
+    pub fn emit_with_ack_result<E, D>(
+        &self,
+        event: E,
+        data: D,
+        timeout: Duration,
+    ) -> Result<Payload>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let event = event.into();
+        let (tx, rx) = mpsc::sync_channel(1);
+        let failed = tx.clone();
+        self.send(
+            BufferedEmit {
+                event: event.clone(),
+                payload: data.into(),
+                ack: Some((
+                    timeout,
+                    Box::new(move |payload, _| {
+                        let _ = tx.try_send(Ok(payload));
+                    }),
+                )),
+                resend_on_reconnect: self.builder.lock()?.resend_on_reconnect,
+                deadline: None,
+                sequence: None,
+                on_failure: Some(AckFailure::new(move |err| {
+                    let _ = failed.try_send(Err(err));
+                })),
+            },
+            EmitOptions::default(),
+        )?;
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(Error::AckTimeout(event)),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Disconnected(event)),
+        }
+    }
+
+    fn send(&self, emit: BufferedEmit<SocketCallback>, options: EmitOptions) -> Result<()> {
+        if options.volatile && self.gate.state()? != ConnectionState::Connected {
+            return self.fail_dropped(vec![emit]);
+        }
+        let builder = self.builder.lock()?;
+        let policy = options.policy.unwrap_or(builder.emit_policy);
+        let emit = self.gate.coalesce(&builder.coalescer, policy, emit)?;
+        drop(builder);
+
+        match self.gate.admit(policy, emit)? {
+            Admission::Send(emit) => self.send_raw(emit),
+            Admission::Buffered(Some(dropped)) => self.report_overflow(dropped),
+            Admission::Buffered(None) => Ok(()),
+        }
+    }
+
+    /// Reports an emit that was dropped as the buffer was full, and fails its
+    /// ack.
+    fn report_overflow(&self, emit: BufferedEmit<SocketCallback>) -> Result<()> {
+        self.client
+            .read()?
+            .error(&Error::EmitDropped(emit.event.clone()))?;
+        self.fail_dropped(vec![emit])
+    }
+
+    fn send_raw(&self, emit: BufferedEmit<SocketCallback>) -> Result<()> {
         let client = self.client.read()?;
-        // TODO(#230): like js client, buffer emit, resend after reconnect
-        client.emit_with_ack(event, data, timeout, callback)
+        if emit.expired() {
+            let err = Error::EmitExpired(emit.event);
+            client.error(&err)?;
+            return Err(err);
+        }
+        client.send_with_ack(emit)
+    }
+
+    /// Marks the client as connected and sends the emits that were buffered
+    /// while reconnecting. Called once the server acknowledged the namespace,
+    /// as it drops the events that arrive before.
+    fn flush_buffered_emits(&self) -> Result<()> {
+        if self.gate.state()? != ConnectionState::Reconnecting {
+            return Ok(());
+        }
+        for emit in self.gate.set_state(ConnectionState::Connected)? {
+            // a failing emit must not prevent the others from being sent
+            let _ = self.send_raw(emit);
+        }
+        Ok(())
+    }
+
+    /// Emits the subscriptions of [`ClientBuilder::resubscribe_on_connect`]
+    /// after the server acknowledged the namespace.
+    fn resubscribe(&self) -> Result<()> {
+        let subscriptions = self.builder.lock()?.resubscriptions.clone();
+        for (event, payload) in subscriptions {
+            if let Err(err) = self.emit(event, payload) {
+                self.client.read()?.error(&err)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails the acks of emits that were dropped before they were sent.
+    fn fail_dropped(&self, dropped: Vec<BufferedEmit<SocketCallback>>) -> Result<()> {
+        let client = self.client.read()?;
+        for emit in dropped.into_iter().filter(|emit| emit.ack.is_some()) {
+            client.fail_ack(emit.event, emit.on_failure)?;
+        }
+        Ok(())
+    }
+
+    /// Sends `data` to the server as the single argument of `event`, serialized
+    /// via `serde`, see [`Payload::json`]. Behaves like [`Client::emit`]
+    /// otherwise.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::ClientBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Login<'a> {
+    ///     user: &'a str,
+    /// }
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// socket.emit_json("login", &Login { user: "alice" }).unwrap();
+    /// ```
+    pub fn emit_json<E, T>(&self, event: E, data: &T) -> Result<()>
+    where
+        E: Into<Event>,
+        T: Serialize + ?Sized,
+    {
+        self.emit(event, Payload::json(data)?)
+    }
+
+    /// Sends a protobuf message to the server like [`RawClient::emit_proto`].
+    /// Available with the `prost` feature. Unlike [`Client::emit`], the message
+    /// isn't subject to the [`crate::EmitPolicy`] and fails while reconnecting.
+    #[cfg(feature = "prost")]
+    pub fn emit_proto<M>(&self, event: impl Into<Event>, message: &M) -> Result<()>
+    where
+        M: prost::Message + prost::Name,
+    {
+        self.client.read()?.emit_proto(event, message)
     }
 
     /// Disconnects this client from the server by sending a `socket.io` closing
@@ -161,15 +554,269 @@ impl Client {
     ///
     /// ```
     pub fn disconnect(&self) -> Result<()> {
+        self.uptime.disconnected();
+        self.set_namespace_state(NamespaceState::Disconnected, None);
+        let dropped = self.gate.set_state(ConnectionState::Disconnected)?;
+        self.fail_dropped(dropped)?;
         let client = self.client.read()?;
         client.disconnect()
     }
 
-    fn reconnect(&mut self) -> Result<()> {
+    /// Closes the connection right away, without sending a `Disconnect` packet
+    /// or anything else to the server, see [`RawClient::abort`]. Like
+    /// [`Client::disconnect`], buffered emits are dropped, pending acks are
+    /// failed and the client doesn't reconnect.
+    pub fn abort(&self) -> Result<()> {
+        self.uptime.disconnected();
+        self.set_namespace_state(NamespaceState::Disconnected, None);
+        let dropped = self.gate.set_state(ConnectionState::Disconnected)?;
+        self.fail_dropped(dropped)?;
+        let client = self.client.read()?;
+        client.abort()
+    }
+
+    /// Returns statistics about the pool of buffers that packets are encoded with.
+    pub fn buffer_pool_stats(&self) -> Result<BufferPoolStats> {
+        Ok(self.client.read()?.buffer_pool_stats())
+    }
+
+    /// Returns the HTTP version that was negotiated with the server, `None` if
+    /// a websocket transport is used.
+    pub fn http_version(&self) -> Result<Option<HttpVersion>> {
+        Ok(self.client.read()?.http_version())
+    }
+
+    /// Returns the events that handlers are registered for, together with the
+    /// number of handlers per event. The `on_any` handler is not included.
+    pub fn handlers(&self) -> Result<HashMap<Event, usize>> {
+        self.client.read()?.handlers()
+    }
+
+    /// Returns when the client connected and reconnected, how long it was
+    /// connected and disconnected in total and how often it disconnected. The
+    /// client counts as connected once the server accepted the connection to
+    /// its namespace.
+    pub fn uptime_stats(&self) -> UptimeStats {
+        self.uptime.stats()
+    }
+
+    /// Returns the sequence number the next emit gets, `None` if sequence
+    /// numbers aren't enabled via [`ClientBuilder::sequence_numbers`]. Persist
+    /// it on shutdown in order to continue the numbers after a restart.
+    pub fn next_sequence(&self) -> Result<Option<u64>> {
+        Ok(self
+            .builder
+            .lock()?
+            .sequence
+            .as_ref()
+            .map(SequenceCounter::peek))
+    }
+
+    /// Whether the server restored the session on the last connect, i.e. the
+    /// client rejoined its rooms and received the events it missed while it was
+    /// disconnected. Requires a server with connection state recovery enabled
+    /// (socket.io 4.6 or later), otherwise it's always `false` and the
+    /// application has to resynchronize its state after a reconnect.
+    ///
+    /// The server appends an offset to the events it broadcasts while
+    /// recovery is enabled, which the handlers receive as the last argument.
+    pub fn recovered(&self) -> Result<bool> {
+        Ok(self.builder.lock()?.recovery.recovered())
+    }
+
+    /// Returns the state of the namespaces of the client together with their
+    /// last error, e.g. whether the server accepted the connection yet or why
+    /// it refused it.
+    pub fn namespaces(&self) -> HashMap<String, NamespaceStatus> {
+        self.namespaces.snapshot()
+    }
+
+    /// Returns the connection state, the state of the namespace and the
+    /// transport of the client at once, without taking a lock, see
+    /// [`ClientSnapshot`].
+    pub fn snapshot(&self) -> ClientSnapshot {
+        self.gate.snapshot().load()
+    }
+
+    /// Whether the client is connected and the server accepted its namespace,
+    /// read without taking a lock.
+    pub fn is_connected(&self) -> bool {
+        self.snapshot().is_connected()
+    }
+
+    /// Returns how many emits are buffered until the client reconnected and
+    /// how many acks are outstanding, see [`PendingStats`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::{ClientBuilder, EmitPolicy};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .emit_policy(EmitPolicy::Buffer)
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// let pending = socket.pending().expect("client is intact");
+    /// if pending.buffered > 0 {
+    ///     println!("{} updates pending", pending.buffered);
+    /// }
+    /// ```
+    pub fn pending(&self) -> Result<PendingStats> {
+        let (buffered, buffered_bytes) = self.gate.buffered()?;
+        Ok(PendingStats {
+            buffered,
+            buffered_bytes,
+            acks: self.client.read()?.outstanding_acks()?,
+        })
+    }
+
+    /// Blocks until the background thread that receives the packets of the
+    /// client stopped. It stops after [`Client::disconnect`] or
+    /// [`Client::abort`], which yields `Ok(())`, or unexpectedly if the
+    /// connection closed and the client didn't or couldn't reconnect, which
+    /// yields the error that stopped it. Handlers aren't called anymore once
+    /// the thread stopped.
+    ///
+    /// The thread is shared by all clones of the client, only the first call
+    /// of `join` or [`Client::detach`] waits for it, later calls return
+    /// `Ok(())` right away.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::ClientBuilder;
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .on("message", |payload, _| println!("Received: {:?}", payload))
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// // receive events until the connection is lost for good
+    /// if let Err(err) = socket.join() {
+    ///     eprintln!("connection lost: {err}");
+    /// }
+    /// ```
+    pub fn join(&self) -> Result<()> {
+        // don't hold the lock while waiting, so that clones can detach
+        let handle = self.poll_thread.lock()?.take();
+        match handle {
+            Some(handle) => handle.join().map_err(|_| Error::PollThreadPanicked)?,
+            None => Ok(()),
+        }
+    }
+
+    /// Detaches the background thread that receives the packets of the client,
+    /// so that [`Client::join`] returns right away. The thread keeps running
+    /// until the connection closed.
+    pub fn detach(&self) -> Result<()> {
+        self.poll_thread.lock()?.take();
+        Ok(())
+    }
+
+    /// Registers a new callback for a certain [`crate::event::Event`] on the
+    /// connected client. The callback is called after the callbacks that were
+    /// registered before, it's kept across reconnects.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Payload};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// socket
+    ///     .on("test", |payload: Payload, _| println!("Received: {:#?}", payload))
+    ///     .expect("registration failed");
+    /// socket.off("test").expect("removal failed");
+    /// ```
+    pub fn on<T: Into<Event>, F>(&self, event: T, callback: F) -> Result<()>
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+    {
+        self.client.read()?.on(event, callback)
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] on the
+    /// connected client that is removed after it was called once.
+    pub fn once<T: Into<Event>, F>(&self, event: T, callback: F) -> Result<()>
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+    {
+        self.client.read()?.once(event, callback)
+    }
+
+    /// Removes all callbacks that are registered for a certain [`crate::event::Event`].
+    pub fn off<T: Into<Event>>(&self, event: T) -> Result<()> {
+        self.client.read()?.off(event)
+    }
+
+    /// Removes the callbacks of all events. The `on_any` callback is kept.
+    pub fn off_all(&self) -> Result<()> {
+        self.client.read()?.off_all()
+    }
+
+    /// Registers a codec for the payloads of a certain [`crate::event::Event`] on
+    /// the connected client, replacing a previously registered one. The codec is
+    /// kept across reconnects.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use bytes::Bytes;
+    /// use rust_socketio::{ClientBuilder, CodecError, Payload, PayloadCodec};
+    ///
+    /// struct RawCodec;
+    ///
+    /// impl PayloadCodec for RawCodec {
+    ///     fn encode(&self, payload: Payload) -> Result<Bytes, CodecError> {
+    ///         match payload {
+    ///             Payload::Binary(data) => Ok(data),
+    ///             _ => Err("only binary payloads are supported".into()),
+    ///         }
+    ///     }
+    ///
+    ///     fn decode(&self, data: Bytes) -> Result<Payload, CodecError> {
+    ///         Ok(Payload::Binary(data))
+    ///     }
+    /// }
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect()
+    ///     .expect("connection failed");
+    ///
+    /// socket.set_codec("telemetry", RawCodec).expect("registration failed");
+    /// ```
+    pub fn set_codec<T: Into<Event>, C>(&self, event: T, codec: C) -> Result<()>
+    where
+        C: PayloadCodec + 'static,
+    {
+        self.client.read()?.set_codec(event, codec)
+    }
+
+    /// Reconnects to the server, `resend` are the emits whose acks were pending
+    /// when the connection dropped and that are sent again after reconnecting.
+    /// Returns the last error if the client gave up.
+    fn reconnect(&mut self, resend: Vec<BufferedEmit<SocketCallback>>) -> Result<()> {
+        self.gate.set_state(ConnectionState::Reconnecting)?;
+        for emit in resend {
+            // a full buffer must not stop the client from reconnecting
+            match self.gate.rebuffer(emit) {
+                Ok(Some(dropped)) => {
+                    if let Err(e) = self.report_overflow(dropped) {
+                        trace!("Failed to report dropped emit: {e:?}");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => trace!("Failed to buffer emit for resending: {e:?}"),
+            }
+        }
+
+        let mut last_error = Error::StoppedEngineIoSocket;
         let mut reconnect_attempts = 0;
-        let (reconnect, max_reconnect_attempts) = {
+        let mut attempt: u32 = 0;
+        let (reconnect, max_reconnect_attempts, strategy) = {
             let builder = self.builder.lock()?;
-            (builder.reconnect, builder.max_reconnect_attempts)
+            let strategy = builder.backoff.clone();
+            (builder.reconnect, builder.max_reconnect_attempts, strategy)
         };
 
         if reconnect {
@@ -181,24 +828,86 @@ impl Client {
                     }
                 }
 
-                if let Some(backoff) = self.backoff.next_backoff() {
-                    std::thread::sleep(backoff);
+                attempt = attempt.saturating_add(1);
+                let delay = match &strategy {
+                    Some(strategy) => match strategy.delay(attempt) {
+                        Some(delay) => Some(delay),
+                        // the strategy gave up
+                        None => break,
+                    },
+                    None => self.backoff.next_backoff(),
+                };
+                if let Some(delay) = delay {
+                    std::thread::sleep(delay);
                 }
 
-                if self.do_reconnect().is_ok() {
-                    break;
+                self.reconnect_callback(ReconnectEvent::Attempt(attempt));
+                match self.do_reconnect() {
+                    Ok(()) => {
+                        self.reconnect_callback(ReconnectEvent::Reconnected(attempt));
+                        self.set_namespace_state(NamespaceState::Pending, None);
+                        // the buffered emits are flushed once the server
+                        // acknowledged the namespace, see `poll_callback`
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        self.reconnect_callback(ReconnectEvent::Error(err.to_string()));
+                        self.set_namespace_state(NamespaceState::Failed, Some(err.to_string()));
+                        last_error = err;
+                    }
                 }
             }
         }
 
-        Ok(())
+        let dropped = self.gate.set_state(ConnectionState::Disconnected)?;
+        self.fail_dropped(dropped)?;
+        Err(last_error)
+    }
+
+    /// Calls the handler registered via [`ClientBuilder::on_reconnect_event`].
+    fn reconnect_callback(&self, event: ReconnectEvent) {
+        let handler = self
+            .builder
+            .lock()
+            .ok()
+            .and_then(|builder| builder.on_reconnect_event.clone());
+        if let Some(handler) = handler {
+            handler(&event);
+        }
+    }
+
+    /// Records the state of the namespace of the client and calls the handler
+    /// registered via [`ClientBuilder::on_namespace_state_change`] if it changed.
+    fn set_namespace_state(&self, state: NamespaceState, error: Option<String>) {
+        self.gate.snapshot().set_namespace(state);
+        let Ok(builder) = self.builder.lock() else {
+            return;
+        };
+        let nsp = builder.namespace.clone();
+        let handler = builder.on_namespace_state_change.clone();
+        drop(builder);
+
+        if let (Some(status), Some(handler)) = (self.namespaces.update(&nsp, state, error), handler)
+        {
+            handler(&nsp, &status);
+        }
     }
 
     fn do_reconnect(&self) -> Result<()> {
         let builder = self.builder.lock()?;
-        let new_client = builder.clone().connect_raw()?;
+        let new_client = builder.clone().reconnect_raw()?;
+        let transport = new_client.transport_kind();
+        let handler = builder.on_transport_change.clone();
+        drop(builder);
         let mut client = self.client.write()?;
         *client = new_client;
+        drop(client);
+
+        if self.gate.snapshot().set_transport(transport) != Some(transport) {
+            if let Some(handler) = handler {
+                handler(transport);
+            }
+        }
 
         Ok(())
     }
@@ -209,36 +918,180 @@ impl Client {
         }
     }
 
-    fn poll_callback(&self) {
+    /// Disconnects the client once it's idle for `timeout`, unless the handler
+    /// registered via [`ClientBuilder::on_before_idle_close`] keeps it open.
+    fn watch_idle(
+        &self,
+        timeout: Duration,
+        activity: ActivityTracker,
+        on_before_close: Option<BeforeIdleCloseHandler>,
+    ) {
+        let client = self.clone();
+        std::thread::spawn(move || loop {
+            let idle = activity.idle_for();
+            if idle < timeout {
+                std::thread::sleep(timeout - idle);
+                continue;
+            }
+            match client.gate.state() {
+                Ok(ConnectionState::Connected) => {}
+                // the idle time starts over once the client reconnected
+                Ok(ConnectionState::Reconnecting) => {
+                    activity.touch();
+                    continue;
+                }
+                _ => return,
+            }
+            if on_before_close.as_ref().is_none_or(|handler| handler(idle)) {
+                let _ = client.disconnect();
+                return;
+            }
+            activity.touch();
+        });
+    }
+
+    /// Reconnects to present the rotated token of the [`crate::BearerAuth`].
+    /// Returns `false` once the client is disconnected for good.
+    fn reconnect_with_rotated_token(&self) -> bool {
+        match self.gate.state() {
+            Ok(ConnectionState::Connected) => {}
+            // the next attempt uses the rotated token
+            Ok(ConnectionState::Reconnecting) => return true,
+            _ => return false,
+        }
+        if !self.builder.lock().is_ok_and(|builder| builder.reconnect) {
+            return true;
+        }
+        self.reconnect_requested.store(true, Ordering::SeqCst);
+        if let Ok(client) = self.client.read() {
+            let _ = client.disconnect_for_reconnect();
+        }
+        true
+    }
+
+    /// Whether the client may still connect again, i.e. it wasn't
+    /// disconnected for good.
+    fn is_alive(&self) -> bool {
+        !matches!(self.gate.state(), Ok(ConnectionState::Disconnected))
+    }
+
+    fn poll_callback(&self) -> Result<()> {
         let mut self_clone = self.clone();
+        let nsp = self
+            .builder
+            .lock()
+            .ok()
+            .map(|builder| builder.namespace.clone());
         // Use thread to consume items in iterator in order to call callbacks
-        std::thread::spawn(move || {
+        let handle = std::thread::spawn(move || {
             // tries to restart a poll cycle whenever a 'normal' error occurs,
             // it just panics on network errors, in case the poll cycle returned
             // `Result::Ok`, the server receives a close frame so it's safe to
             // terminate
             for packet in self_clone.iter() {
-                let should_reconnect = match packet {
-                    Err(Error::IncompleteResponseFromEngineIo(_)) => {
-                        //TODO: 0.3.X handle errors
-                        //TODO: logging error
-                        true
+                if let (Ok(packet), Some(nsp)) = (&packet, &nsp) {
+                    match packet.packet_type {
+                        PacketId::Connect if &packet.nsp == nsp => {
+                            self_clone.uptime.connected();
+                            self_clone.set_namespace_state(NamespaceState::Connected, None);
+                            self_clone.flush_buffered_emits()?;
+                            self_clone.resubscribe()?;
+                        }
+                        PacketId::Disconnect if &packet.nsp == nsp => {
+                            self_clone.uptime.disconnected();
+                            self_clone.set_namespace_state(NamespaceState::Disconnected, None);
+                        }
+                        PacketId::ConnectError if &packet.nsp == nsp => {
+                            self_clone.set_namespace_state(
+                                NamespaceState::Failed,
+                                Some(packet.data.clone().unwrap_or_default()),
+                            );
+                            if self_clone.gate.state()? == ConnectionState::Reconnecting {
+                                // the namespace was refused, the buffered emits can't be delivered
+                                let dropped =
+                                    self_clone.gate.set_state(ConnectionState::Disconnected)?;
+                                self_clone.fail_dropped(dropped)?;
+                            }
+                        }
+                        _ => {}
                     }
-                    Ok(Packet {
-                        packet_type: PacketId::Disconnect,
-                        ..
-                    }) => match self_clone.builder.lock() {
-                        Ok(builder) => builder.reconnect_on_disconnect,
-                        Err(_) => false,
-                    },
-                    _ => false,
-                };
+                }
+                let reconnect_requested = matches!(packet, Err(Error::StoppedEngineIoSocket))
+                    && self_clone.reconnect_requested.swap(false, Ordering::SeqCst);
+                if let (Err(Error::StoppedEngineIoSocket), false) = (&packet, reconnect_requested) {
+                    // the connection closed and isn't reconnected, which is only
+                    // expected after `Client::disconnect` or `Client::abort`
+                    return match self_clone.gate.state()? {
+                        ConnectionState::Disconnected => Ok(()),
+                        _ => Err(Error::StoppedEngineIoSocket),
+                    };
+                }
+                let should_reconnect = reconnect_requested
+                    || match packet {
+                        Err(Error::IncompleteResponseFromEngineIo(_)) => {
+                            //TODO: 0.3.X handle errors
+                            //TODO: logging error
+                            true
+                        }
+                        Err(Error::InvalidUtf8Packet { .. }) => match self_clone.builder.lock() {
+                            Ok(builder) => builder.utf8_policy == Utf8Policy::Terminate,
+                            Err(_) => false,
+                        },
+                        Ok(Packet {
+                            packet_type: PacketId::Disconnect,
+                            ..
+                        }) => match self_clone.builder.lock() {
+                            Ok(builder) => builder.reconnect_on_disconnect,
+                            Err(_) => false,
+                        },
+                        _ => false,
+                    };
                 if should_reconnect {
-                    let _ = self_clone.disconnect();
-                    let _ = self_clone.reconnect();
+                    self_clone.uptime.disconnected();
+                    self_clone.set_namespace_state(NamespaceState::Disconnected, None);
+                    // don't use `Client::disconnect`, as emits are allowed while reconnecting
+                    let mut resend = Vec::new();
+                    if let Ok(client) = self_clone.client.read() {
+                        // take the emits to resend before disconnecting fails all acks
+                        resend = client.close_outstanding_acks(true).unwrap_or_default();
+                        let _ = client.disconnect();
+                    }
+                    self_clone.reconnect(resend)?;
                 }
             }
+            // the lock of the client was poisoned
+            Err(Error::InvalidPoisonedLock())
         });
+        *self.poll_thread.lock()? = Some(handle);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl crate::DynClient for Client {
+    async fn emit(&self, event: Event, payload: Payload) -> Result<()> {
+        let client = self.clone();
+        blocking(move || client.emit(event, payload)).await
+    }
+
+    async fn emit_with_ack(
+        &self,
+        event: Event,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<Payload> {
+        let client = self.clone();
+        blocking(move || client.emit_with_ack_result(event, payload, timeout)).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        let client = self.clone();
+        blocking(move || client.disconnect()).await
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.gate.snapshot().connection()
     }
 }
 
@@ -276,7 +1129,7 @@ mod test {
 
     use super::*;
     use crate::error::Result;
-    use crate::ClientBuilder;
+    use crate::{BearerAuth, ClientBuilder, NamespaceState};
     use serde_json::json;
     use serial_test::serial;
     use std::time::{Duration, SystemTime};
@@ -340,7 +1193,181 @@ mod test {
         assert_eq!(load(&MESSAGE_NUM), 2, "should receive two messages");
         assert_eq!(load(&CLOSE_NUM), 1, "should close once");
 
+        let stats = socket.uptime_stats();
+        assert!(stats.connected);
+        assert_eq!(stats.disconnects, 1);
+        assert!(stats.connected_at.is_some() && stats.last_reconnect_at.is_some());
+
+        socket.disconnect()?;
+        assert!(!socket.uptime_stats().connected);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(reconnect)]
+    fn socket_io_reconnect_state_integration() -> Result<()> {
+        static NAMESPACE_CONNECT_NUM: AtomicUsize = AtomicUsize::new(0);
+        static ATTEMPT_NUM: AtomicUsize = AtomicUsize::new(0);
+        static RECONNECT_NUM: AtomicUsize = AtomicUsize::new(0);
+
+        let url = crate::test::socket_io_restart_server();
+
+        let socket = ClientBuilder::new(url)
+            .reconnect(true)
+            .max_reconnect_attempts(100)
+            .reconnect_delay(100, 100)
+            .on_namespace_state_change(|_, status| {
+                if status.state == NamespaceState::Connected {
+                    NAMESPACE_CONNECT_NUM.fetch_add(1, Ordering::Release);
+                }
+            })
+            .on_reconnect_event(|event| match event {
+                ReconnectEvent::Attempt(_) => {
+                    ATTEMPT_NUM.fetch_add(1, Ordering::Release);
+                }
+                ReconnectEvent::Reconnected(attempts) => {
+                    assert_eq!(*attempts as usize, load(&ATTEMPT_NUM));
+                    RECONNECT_NUM.fetch_add(1, Ordering::Release);
+                }
+                _ => {}
+            })
+            .connect()?;
+
+        // waiting for the namespace to connect
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        assert_eq!(load(&NAMESPACE_CONNECT_NUM), 1, "should connect namespace");
+
+        socket.emit("restart_server", json!(""))?;
+
+        // waiting for server to restart
+        for _ in 0..10 {
+            std::thread::sleep(std::time::Duration::from_millis(400));
+            if load(&NAMESPACE_CONNECT_NUM) == 2 {
+                break;
+            }
+        }
+
+        assert!(load(&ATTEMPT_NUM) >= 1, "should attempt to reconnect");
+        assert_eq!(load(&RECONNECT_NUM), 1, "should reconnect once");
+        assert_eq!(
+            load(&NAMESPACE_CONNECT_NUM),
+            2,
+            "should connect namespace twice"
+        );
+        assert_eq!(socket.namespaces()["/"].state, NamespaceState::Connected);
+
+        socket.disconnect()?;
+        assert_eq!(socket.namespaces()["/"].state, NamespaceState::Disconnected);
+        Ok(())
+    }
+
+    #[test]
+    fn socket_io_resubscribe_integration() -> Result<()> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let url = crate::test::socket_io_server();
+        let socket = ClientBuilder::new(url)
+            .resubscribe_on_connect(vec![("test", json!("resubscribed"))])
+            .on("test-received", move |payload, _| {
+                let _ = tx.send(payload);
+            })
+            .connect()?;
+
+        let payload = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(payload, Payload::from(json!("resubscribed")));
+
+        socket.disconnect()?;
+        Ok(())
+    }
+
+    #[test]
+    fn socket_io_ack_result_disconnect_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let socket = ClientBuilder::new(url).connect()?;
+
+        // the server never answers the ack of this event
+        let handle = std::thread::spawn({
+            let socket = socket.clone();
+            move || socket.emit_with_ack_result("no-ack", json!(""), Duration::from_secs(30))
+        });
+        while socket.pending()?.acks == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
         socket.disconnect()?;
+
+        let result = handle.join().unwrap();
+        assert!(
+            matches!(result, Err(Error::Disconnected(event)) if event == Event::from("no-ack"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn socket_io_idle_timeout_integration() -> Result<()> {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let url = crate::test::socket_io_server();
+        let socket = ClientBuilder::new(url)
+            .idle_timeout(Duration::from_millis(300))
+            .on_before_idle_close(|idle| {
+                assert!(idle >= Duration::from_millis(300));
+                // keep the connection open the first time
+                CALLS.fetch_add(1, Ordering::AcqRel) > 0
+            })
+            .connect()?;
+
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(load(&CALLS), 1);
+        assert!(socket.uptime_stats().connected);
+
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(load(&CALLS), 2);
+        assert!(!socket.uptime_stats().connected);
+        socket.join()?;
+        Ok(())
+    }
+
+    #[test]
+    fn socket_io_bearer_auth_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let socket = ClientBuilder::new(url)
+            .bearer_auth(BearerAuth::from_receiver("first", rx))
+            .reconnect_delay(10, 10)
+            .connect()?;
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(socket.uptime_stats().disconnects, 0);
+
+        // rotating the token reconnects
+        tx.send(String::from("second")).unwrap();
+        std::thread::sleep(Duration::from_millis(1000));
+        let stats = socket.uptime_stats();
+        assert!(stats.connected);
+        assert_eq!(stats.disconnects, 1);
+
+        socket.emit("test", json!("still connected"))?;
+        socket.disconnect()?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial(reconnect)]
+    fn socket_io_join_integration() -> Result<()> {
+        let url = crate::test::socket_io_restart_server();
+        let socket = ClientBuilder::new(url).reconnect(false).connect()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let socket_clone = socket.clone();
+        std::thread::spawn(move || tx.send(socket_clone.join()));
+
+        std::thread::sleep(Duration::from_millis(500));
+        socket.emit("restart_server", json!(""))?;
+
+        let result = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("the poll thread should stop");
+        assert!(result.is_err(), "the connection closed unexpectedly");
+        // the thread was joined already
+        socket.join()?;
         Ok(())
     }
 
@@ -429,6 +1456,11 @@ mod test {
             builder: Arc::new(Mutex::new(builder)),
             client,
             backoff: Default::default(),
+            gate: Arc::new(EmitGate::new(None)),
+            uptime: Default::default(),
+            namespaces: Default::default(),
+            poll_thread: Default::default(),
+            reconnect_requested: Default::default(),
         };
         let socket_clone = socket.clone();
 
@@ -453,7 +1485,7 @@ mod test {
         drop(lock);
 
         let _ = socket.disconnect();
-        socket.reconnect()?;
+        socket.reconnect(Vec::new())?;
 
         // waiting for client to emit messages
         std::thread::sleep(Duration::from_millis(100));
@@ -0,0 +1,109 @@
+use std::{
+    fmt::Debug,
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Calls the callbacks of several clients one after another, in the order their
+/// packets arrived.
+///
+/// By default every client calls its callbacks on its own polling thread, so the
+/// callbacks of clients connected to different namespaces run in parallel and
+/// their relative order is undefined. Clients that share a dispatcher (see
+/// [`crate::ClientBuilder::dispatcher`]) hand their packets to a single thread
+/// instead, which preserves the global arrival order across all of them.
+///
+/// # Example
+/// ```no_run
+/// use rust_socketio::{client::Dispatcher, ClientBuilder};
+///
+/// let dispatcher = Dispatcher::new();
+/// let orders = ClientBuilder::new("http://localhost:4200/")
+///     .namespace("/orders")
+///     .dispatcher(dispatcher.clone())
+///     .on("created", |payload, _| println!("order: {payload:?}"))
+///     .connect();
+/// let payments = ClientBuilder::new("http://localhost:4200/")
+///     .namespace("/payments")
+///     .dispatcher(dispatcher)
+///     .on("received", |payload, _| println!("payment: {payload:?}"))
+///     .connect();
+/// ```
+#[derive(Clone)]
+pub struct Dispatcher {
+    sender: Sender<Job>,
+}
+
+impl Dispatcher {
+    /// Creates a dispatcher and spawns the thread that calls the callbacks. The
+    /// thread stops once all clients and clones of the dispatcher are dropped.
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in receiver {
+                job();
+            }
+        });
+        Dispatcher { sender }
+    }
+
+    /// Queues a job behind all jobs that were dispatched before. The job is
+    /// dropped if the dispatcher thread panicked in a callback.
+    pub(crate) fn dispatch<F: FnOnce() + Send + 'static>(&self, job: F) {
+        let _ = self.sender.send(Box::new(job));
+    }
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Dispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Dispatcher")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_dispatch_order() {
+        let dispatcher = Dispatcher::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done_rx) = mpsc::channel();
+
+        // jobs of different producers are run one after another in arrival order
+        let handles: Vec<_> = (0..2)
+            .map(|producer| {
+                let dispatcher = dispatcher.clone();
+                let calls = calls.clone();
+                thread::spawn(move || {
+                    for i in 0..3 {
+                        let calls = calls.clone();
+                        dispatcher.dispatch(move || calls.lock().unwrap().push((producer, i)));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        dispatcher.dispatch(move || done_tx.send(()).unwrap());
+        done_rx.recv().unwrap();
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 6);
+        for producer in 0..2 {
+            let order: Vec<_> = calls.iter().filter(|(p, _)| *p == producer).collect();
+            assert_eq!(order, vec![&(producer, 0), &(producer, 1), &(producer, 2)]);
+        }
+    }
+}
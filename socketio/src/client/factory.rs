@@ -0,0 +1,59 @@
+use super::{Client, ClientBuilder};
+use crate::error::Result;
+
+/// Builds connected clients from a [`ClientBuilder`]. Applications that get
+/// their dependencies injected can depend on this trait instead of connecting
+/// clients themselves, which allows to swap in a mock factory in tests.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::client::{DefaultClientFactory, SocketIoClientFactory};
+/// use rust_socketio::ClientBuilder;
+///
+/// struct Notifier<F: SocketIoClientFactory> {
+///     factory: F,
+/// }
+///
+/// impl<F: SocketIoClientFactory> Notifier<F> {
+///     fn start(&self) -> Result<F::Client, rust_socketio::Error> {
+///         self.factory
+///             .connect(ClientBuilder::new("http://localhost:4200/").namespace("/notify"))
+///     }
+/// }
+///
+/// // in production
+/// let notifier = Notifier { factory: DefaultClientFactory };
+///
+/// // in tests
+/// struct MockFactory;
+///
+/// impl SocketIoClientFactory for MockFactory {
+///     type Client = ();
+///
+///     fn connect(&self, _builder: ClientBuilder) -> Result<(), rust_socketio::Error> {
+///         Ok(())
+///     }
+/// }
+///
+/// let notifier = Notifier { factory: MockFactory };
+/// assert!(notifier.start().is_ok());
+/// ```
+pub trait SocketIoClientFactory: Send + Sync {
+    /// The client that is built.
+    type Client;
+
+    /// Connects a client as configured by `builder`.
+    fn connect(&self, builder: ClientBuilder) -> Result<Self::Client>;
+}
+
+/// The factory that connects a [`Client`] via [`ClientBuilder::connect`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultClientFactory;
+
+impl SocketIoClientFactory for DefaultClientFactory {
+    type Client = Client;
+
+    fn connect(&self, builder: ClientBuilder) -> Result<Client> {
+        builder.connect()
+    }
+}
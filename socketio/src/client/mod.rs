@@ -1,9 +1,13 @@
 mod builder;
+mod dispatcher;
+mod factory;
 mod raw_client;
 
 pub use builder::ClientBuilder;
 pub use builder::TransportType;
 pub use client::Client;
+pub use dispatcher::Dispatcher;
+pub use factory::{DefaultClientFactory, SocketIoClientFactory};
 pub use raw_client::RawClient;
 
 /// Internal callback type
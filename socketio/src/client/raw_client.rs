@@ -1,12 +1,22 @@
 use super::callback::Callback;
+use crate::codec::Codecs;
+use crate::emit::{AckFailure, BufferedEmit, SequenceCounter};
 use crate::packet::{Packet, PacketId};
-use crate::Error;
 pub(crate) use crate::{event::Event, payload::Payload};
+use crate::{
+    BufferPoolStats, Error, EventSchemas, HttpVersion, PayloadCodec, TransportKind, UnhandledPolicy,
+};
 use rand::{thread_rng, Rng};
+use serde::Serialize;
 use serde_json::Value;
 
-use crate::client::callback::{SocketAnyCallback, SocketCallback};
+use crate::client::callback::{SocketAnyCallback, SocketCallback, SocketErrorCallback};
+use crate::client::Dispatcher;
 use crate::error::Result;
+use crate::handlers::EventHandlers;
+use crate::idle::ActivityTracker;
+use crate::recovery::SessionRecovery;
+use crate::redact::Redaction;
 use std::collections::HashMap;
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex};
@@ -25,6 +35,13 @@ pub struct Ack {
     timeout: Duration,
     time_started: Instant,
     callback: Callback<SocketCallback>,
+    event: Event,
+    // the payload to emit again after a reconnect instead of failing the ack
+    resend: Option<Payload>,
+    // the sequence number the emit keeps when it's resent
+    sequence: Option<u64>,
+    // completes the ack instead of the error callbacks if it can't be received
+    on_failure: Option<AckFailure>,
 }
 
 /// A socket which handles communication with the server. It's initialized with
@@ -34,13 +51,33 @@ pub struct Ack {
 pub struct RawClient {
     /// The inner socket client to delegate the methods to.
     socket: InnerSocket,
-    on: Arc<Mutex<HashMap<Event, Callback<SocketCallback>>>>,
+    on: Arc<Mutex<EventHandlers<Callback<SocketCallback>>>>,
     on_any: Arc<Mutex<Option<Callback<SocketAnyCallback>>>>,
+    codecs: Arc<Mutex<Codecs>>,
     outstanding_acks: Arc<Mutex<Vec<Ack>>>,
     // namespace, for multiplexing messages
     nsp: String,
     // Data send in the opening packet (commonly used as for auth)
     auth: Option<Value>,
+    // Tag attached to emitted payloads in order to detect our own echoes
+    origin: Option<String>,
+    // Numbers the emitted payloads, if enabled
+    sequence: Option<SequenceCounter>,
+    // The session that the server restores after a reconnect, if supported
+    recovery: SessionRecovery,
+    // Records the traffic for closing idle connections, if enabled
+    activity: Option<ActivityTracker>,
+    // Calls the callbacks in order with other clients, if shared
+    dispatcher: Option<Dispatcher>,
+    // Decides what the logs show of payloads
+    redaction: Redaction,
+    // Versions, migrates and validates the payloads of events
+    schemas: EventSchemas,
+    // Handles received events without a handler
+    unhandled_policy: UnhandledPolicy,
+    on_unhandled: Arc<Mutex<Option<Callback<SocketAnyCallback>>>>,
+    // Receives the errors of the client as typed values
+    on_error: Arc<Mutex<Option<Callback<SocketErrorCallback>>>>,
 }
 
 impl RawClient {
@@ -51,20 +88,90 @@ impl RawClient {
     pub(crate) fn new<T: Into<String>>(
         socket: InnerSocket,
         namespace: T,
-        on: Arc<Mutex<HashMap<Event, Callback<SocketCallback>>>>,
+        on: Arc<Mutex<EventHandlers<Callback<SocketCallback>>>>,
         on_any: Arc<Mutex<Option<Callback<SocketAnyCallback>>>>,
+        codecs: Arc<Mutex<Codecs>>,
         auth: Option<Value>,
+        origin: Option<String>,
     ) -> Result<Self> {
         Ok(RawClient {
             socket,
             nsp: namespace.into(),
             on,
             on_any,
+            codecs,
             outstanding_acks: Arc::new(Mutex::new(Vec::new())),
             auth,
+            origin,
+            sequence: None,
+            recovery: SessionRecovery::default(),
+            activity: None,
+            dispatcher: None,
+            redaction: Redaction::default(),
+            schemas: EventSchemas::default(),
+            unhandled_policy: UnhandledPolicy::default(),
+            on_unhandled: Arc::new(Mutex::new(None)),
+            on_error: Arc::new(Mutex::new(None)),
         })
     }
 
+    /// Calls the callbacks on the given dispatcher instead of the polling thread.
+    pub(crate) fn with_dispatcher(mut self, dispatcher: Option<Dispatcher>) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
+
+    /// Tags the emitted payloads with the sequence numbers of the given counter.
+    pub(crate) fn with_sequence(mut self, sequence: Option<SequenceCounter>) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Recovers the session of the given handle when connecting.
+    pub(crate) fn with_recovery(mut self, recovery: SessionRecovery) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Records the sent and received packets on the given tracker.
+    pub(crate) fn with_activity(mut self, activity: Option<ActivityTracker>) -> Self {
+        self.activity = activity;
+        self
+    }
+
+    /// Logs payloads as decided by the given redaction.
+    pub(crate) fn with_redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Versions, migrates and validates the payloads of events with the given
+    /// schemas.
+    pub(crate) fn with_schemas(mut self, schemas: EventSchemas) -> Self {
+        self.schemas = schemas;
+        self
+    }
+
+    /// Handles received events without a handler as given.
+    pub(crate) fn with_unhandled(
+        mut self,
+        policy: UnhandledPolicy,
+        on_unhandled: Arc<Mutex<Option<Callback<SocketAnyCallback>>>>,
+    ) -> Self {
+        self.unhandled_policy = policy;
+        self.on_unhandled = on_unhandled;
+        self
+    }
+
+    /// Passes the errors of the client to the given callback as typed values.
+    pub(crate) fn with_on_error(
+        mut self,
+        on_error: Arc<Mutex<Option<Callback<SocketErrorCallback>>>>,
+    ) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
     /// Connects the client to a server. Afterwards the `emit_*` methods can be
     /// called to interact with the server. Attention: it's not allowed to add a
     /// callback after a call to this method.
@@ -72,7 +179,7 @@ impl RawClient {
         // Connect the underlying socket
         self.socket.connect()?;
 
-        let auth = self.auth.as_ref().map(|data| data.to_string());
+        let auth = self.recovery.auth(self.auth.as_ref());
 
         // construct the opening packet
         let open_packet = Packet::new(PacketId::Connect, self.nsp.clone(), auth, None, 0, None);
@@ -113,7 +220,15 @@ impl RawClient {
         E: Into<Event>,
         D: Into<Payload>,
     {
-        self.socket.emit(&self.nsp, event.into(), data.into())
+        let event = event.into();
+        let payload = data.into();
+        self.redaction
+            .trace("Emitting", &self.nsp, &event, &payload);
+        let payload = self.codecs.lock()?.encode(&event, payload)?;
+        let payload = self.schemas.tag(&event, payload);
+        let (payload, _) = self.tag_payload(payload, None, false);
+        self.touch();
+        self.socket.emit(&self.nsp, event, payload)
     }
 
     /// Disconnects this client from the server by sending a `socket.io` closing
@@ -148,6 +263,30 @@ impl RawClient {
         // TODO: logging
         let _ = self.socket.send(disconnect_packet);
         self.socket.disconnect()?;
+        self.close_outstanding_acks(false)?;
+
+        let _ = self.callback(&Event::Close, ""); // trigger on_close
+        Ok(())
+    }
+
+    /// Disconnects from the server like [`RawClient::disconnect`], but keeps
+    /// the pending acks, so that their emits can be resent after reconnecting.
+    pub(crate) fn disconnect_for_reconnect(&self) -> Result<()> {
+        let disconnect_packet =
+            Packet::new(PacketId::Disconnect, self.nsp.clone(), None, None, 0, None);
+
+        let _ = self.socket.send(disconnect_packet);
+        self.socket.disconnect()
+    }
+
+    /// Closes the connection right away, without sending a `Disconnect` packet
+    /// or anything else to the server. Use this instead of
+    /// [`RawClient::disconnect`] if no more bytes must be sent, e.g. after the
+    /// credentials of the connection were revoked. Pending acks are failed like
+    /// on a disconnect.
+    pub fn abort(&self) -> Result<()> {
+        self.socket.abort();
+        self.close_outstanding_acks(false)?;
 
         let _ = self.callback(&Event::Close, ""); // trigger on_close
         Ok(())
@@ -203,34 +342,200 @@ impl RawClient {
         E: Into<Event>,
         D: Into<Payload>,
     {
+        self.send_with_ack(BufferedEmit {
+            event: event.into(),
+            payload: data.into(),
+            ack: Some((timeout, Box::new(callback))),
+            resend_on_reconnect: false,
+            deadline: None,
+            sequence: None,
+            on_failure: None,
+        })
+    }
+
+    /// Sends an emit and registers its ack if it requests one. Emits that are
+    /// flagged to be resent after a reconnect keep their payload for that.
+    pub(crate) fn send_with_ack(&self, emit: BufferedEmit<SocketCallback>) -> Result<()> {
+        let BufferedEmit {
+            event,
+            payload,
+            ack: Some((timeout, callback)),
+            resend_on_reconnect,
+            sequence,
+            on_failure,
+            ..
+        } = emit
+        else {
+            return self.emit(emit.event, emit.payload);
+        };
+        self.redaction
+            .trace("Emitting", &self.nsp, &event, &payload);
+        let resend = resend_on_reconnect.then(|| payload.clone());
+        let payload = self.codecs.lock()?.encode(&event, payload)?;
+        let payload = self.schemas.tag(&event, payload);
+        let (payload, sequence) = self.tag_payload(payload, sequence, true);
         let id = thread_rng().gen_range(0..999);
-        let socket_packet =
-            Packet::new_from_payload(data.into(), event.into(), &self.nsp, Some(id))?;
+        let socket_packet = Packet::new_from_payload(payload, event.clone(), &self.nsp, Some(id))?;
 
         let ack = Ack {
             id,
             time_started: Instant::now(),
             timeout,
             callback: Callback::<SocketCallback>::new(callback),
+            event,
+            resend,
+            sequence,
+            on_failure,
         };
 
         // add the ack to the tuple of outstanding acks
         self.outstanding_acks.lock()?.push(ack);
 
+        self.touch();
         self.socket.send(socket_packet)?;
         Ok(())
     }
 
+    /// Sends `data` to the server as the single argument of `event`, serialized
+    /// via `serde`, see [`Payload::json`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_socketio::ClientBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Login<'a> {
+    ///     user: &'a str,
+    /// }
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect_raw()
+    ///     .expect("connection failed");
+    ///
+    /// socket.emit_json("login", &Login { user: "alice" }).unwrap();
+    /// ```
+    pub fn emit_json<E, T>(&self, event: E, data: &T) -> Result<()>
+    where
+        E: Into<Event>,
+        T: Serialize + ?Sized,
+    {
+        self.emit(event, Payload::json(data)?)
+    }
+
+    /// Sends a protobuf message to the server as binary attachment of `event`,
+    /// with the full type name of the message as argument before it. Available
+    /// with the `prost` feature, see [`crate::ClientBuilder::on_proto`] for the
+    /// receiving side.
+    #[cfg(feature = "prost")]
+    pub fn emit_proto<M>(&self, event: impl Into<Event>, message: &M) -> Result<()>
+    where
+        M: prost::Message + prost::Name,
+    {
+        let event = event.into();
+        let message = Payload::Binary(message.encode_to_vec().into());
+        self.redaction
+            .trace("Emitting", &self.nsp, &event, &message);
+        let attachment = self.codecs.lock()?.encode(&event, message)?;
+        let (args, _) = self.tag_payload(crate::proto::args::<M>(), None, false);
+        let packet = crate::proto::packet(event, args, attachment, &self.nsp)?;
+        self.touch();
+        self.socket.send(packet)
+    }
+
+    /// Returns statistics about the pool of buffers that packets are encoded with.
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.socket.buffer_pool_stats()
+    }
+
+    /// Returns the HTTP version that was negotiated with the server, `None` if
+    /// a websocket transport is used.
+    pub fn http_version(&self) -> Option<HttpVersion> {
+        self.socket.http_version()
+    }
+
+    /// Returns the transport of the connection.
+    pub fn transport_kind(&self) -> TransportKind {
+        self.socket.transport_kind()
+    }
+
+    /// Returns the events that handlers are registered for, together with the
+    /// number of handlers per event. The `on_any` handler is not included.
+    pub fn handlers(&self) -> Result<HashMap<Event, usize>> {
+        Ok(self.on.lock()?.counts())
+    }
+
+    /// Registers a new callback for a certain [`crate::event::Event`] on the
+    /// connected client. The callback is called after the callbacks that were
+    /// registered before, it's kept across reconnects. This may be called from
+    /// within a callback.
+    pub fn on<T: Into<Event>, F>(&self, event: T, callback: F) -> Result<()>
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+    {
+        let callback = Callback::<SocketCallback>::new(callback);
+        self.on.lock()?.add(event.into(), callback, false);
+        Ok(())
+    }
+
+    /// Registers a callback for a certain [`crate::event::Event`] on the
+    /// connected client that is removed after it was called once.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::{ClientBuilder, Payload};
+    ///
+    /// let socket = ClientBuilder::new("http://localhost:4200/")
+    ///     .connect_raw()
+    ///     .expect("connection failed");
+    ///
+    /// socket
+    ///     .once("welcome", |payload: Payload, _| println!("Welcome: {:#?}", payload))
+    ///     .expect("registration failed");
+    /// ```
+    pub fn once<T: Into<Event>, F>(&self, event: T, callback: F) -> Result<()>
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+    {
+        let callback = Callback::<SocketCallback>::new(callback);
+        self.on.lock()?.add(event.into(), callback, true);
+        Ok(())
+    }
+
+    /// Removes all callbacks that are registered for a certain [`crate::event::Event`].
+    pub fn off<T: Into<Event>>(&self, event: T) -> Result<()> {
+        self.on.lock()?.remove(&event.into());
+        Ok(())
+    }
+
+    /// Removes the callbacks of all events. The `on_any` callback is kept.
+    pub fn off_all(&self) -> Result<()> {
+        self.on.lock()?.clear();
+        Ok(())
+    }
+
+    /// Registers a codec for the payloads of a certain [`crate::event::Event`] on
+    /// the connected client, replacing a previously registered one. See
+    /// [`PayloadCodec`] for details.
+    pub fn set_codec<T: Into<Event>, C>(&self, event: T, codec: C) -> Result<()>
+    where
+        C: PayloadCodec + 'static,
+    {
+        self.codecs.lock()?.insert(event.into(), Arc::new(codec));
+        Ok(())
+    }
+
     pub(crate) fn poll(&self) -> Result<Option<Packet>> {
         loop {
             match self.socket.poll() {
                 Err(err) => {
-                    self.callback(&Event::Error, err.to_string())?;
+                    self.error(&err)?;
                     return Err(err);
                 }
                 Ok(Some(packet)) => {
                     if packet.nsp == self.nsp {
-                        self.handle_socketio_packet(&packet)?;
+                        self.touch();
+                        self.dispatch(&packet)?;
                         return Ok(Some(packet));
                     } else {
                         // Not our namespace continue polling
@@ -241,35 +546,162 @@ impl RawClient {
         }
     }
 
+    /// The number of emits whose ack wasn't received yet.
+    pub(crate) fn outstanding_acks(&self) -> Result<usize> {
+        Ok(self.outstanding_acks.lock()?.len())
+    }
+
+    /// Removes the outstanding acks, as the connection they were requested over
+    /// closed. If `resend` is set, the emits that are flagged to be resent after
+    /// a reconnect are returned, all other acks are failed.
+    pub(crate) fn close_outstanding_acks(
+        &self,
+        resend: bool,
+    ) -> Result<Vec<BufferedEmit<SocketCallback>>> {
+        let acks = std::mem::take(&mut *self.outstanding_acks.lock()?);
+        let mut emits = Vec::new();
+        for ack in acks {
+            match ack.resend {
+                Some(payload) if resend => emits.push(BufferedEmit {
+                    event: ack.event,
+                    payload,
+                    ack: Some((ack.timeout, ack.callback.into_inner())),
+                    resend_on_reconnect: true,
+                    deadline: None,
+                    sequence: ack.sequence,
+                    on_failure: ack.on_failure,
+                }),
+                _ => self.fail_ack(ack.event, ack.on_failure)?,
+            }
+        }
+        Ok(emits)
+    }
+
+    /// Fails an ack that can't be received anymore, or reports it to the
+    /// `error` callbacks if the ack can't be failed itself.
+    pub(crate) fn fail_ack(&self, event: Event, on_failure: Option<AckFailure>) -> Result<()> {
+        match on_failure {
+            Some(on_failure) => {
+                on_failure.fail(Error::Disconnected(event));
+                Ok(())
+            }
+            None => self.error(&Error::Disconnected(event)),
+        }
+    }
+
+    /// Reports an error to the `on_error` callback and, as a string, to the
+    /// callbacks of [`Event::Error`].
+    pub(crate) fn error(&self, err: &Error) -> Result<()> {
+        if let Some(callback) = self.on_error.lock()?.deref_mut() {
+            callback(err, self.clone());
+        }
+        self.callback(&Event::Error, err.to_string())
+    }
+
+    /// Handles a packet right away, or queues it on the dispatcher if the client
+    /// shares one with other clients.
+    fn dispatch(&self, packet: &Packet) -> Result<()> {
+        let Some(dispatcher) = &self.dispatcher else {
+            return self.handle_socketio_packet(packet);
+        };
+        let client = self.clone();
+        let packet = packet.clone();
+        dispatcher.dispatch(move || {
+            // errors are reported to the `error` callbacks already
+            let _ = client.handle_socketio_packet(&packet);
+        });
+        Ok(())
+    }
+
+    /// Records a sent or received packet for closing idle connections.
+    #[inline]
+    fn touch(&self) {
+        if let Some(activity) = &self.activity {
+            activity.touch();
+        }
+    }
+
+    /// Tags the payload with its sequence number if sequence numbers are
+    /// enabled, and with the origin of this client if echo suppression is
+    /// enabled. `sequence` is the number of an emit that is resent. Returns the
+    /// sequence number the payload was tagged with.
+    ///
+    /// Emits that request an `ack` aren't tagged with the origin, as a server
+    /// handler like `(data, callback)` would receive the tag in place of the
+    /// ack callback.
+    #[inline]
+    fn tag_payload(
+        &self,
+        payload: Payload,
+        sequence: Option<u64>,
+        ack: bool,
+    ) -> (Payload, Option<u64>) {
+        let sequence = match (&self.sequence, &payload) {
+            (Some(counter), Payload::Text(_)) => Some(counter.assign(sequence)),
+            _ => None,
+        };
+        let payload = match sequence {
+            Some(sequence) => payload.with_sequence(sequence),
+            None => payload,
+        };
+        let payload = match self.origin {
+            Some(ref origin) if !ack => payload.with_origin(origin),
+            _ => payload,
+        };
+        (payload, sequence)
+    }
+
     #[cfg(test)]
     pub(crate) fn iter(&self) -> Iter {
         Iter { socket: self }
     }
 
-    fn callback<P: Into<Payload>>(&self, event: &Event, payload: P) -> Result<()> {
-        let mut on = self.on.lock()?;
-        let mut on_any = self.on_any.lock()?;
-        let lock = on.deref_mut();
-        let on_any_lock = on_any.deref_mut();
-
+    pub(crate) fn callback<P: Into<Payload>>(&self, event: &Event, payload: P) -> Result<()> {
         let payload = payload.into();
-
-        if let Some(callback) = lock.get_mut(event) {
+        self.redaction.trace("Received", &self.nsp, event, &payload);
+
+        // the registry isn't locked while the handlers are called, so that they
+        // can register and remove handlers themselves
+        let handlers = self.on.lock()?.fire(event);
+        let handled = !handlers.is_empty();
+        for handler in handlers {
+            let mut callback = handler.callback.lock()?;
             callback(payload.clone(), self.clone());
         }
+
         match event {
             Event::Message | Event::Custom(_) => {
-                if let Some(callback) = on_any_lock {
+                let mut on_any = self.on_any.lock()?;
+                if let Some(callback) = on_any.deref_mut() {
                     callback(event.clone(), payload, self.clone())
+                } else if !handled {
+                    drop(on_any);
+                    self.unhandled(event, payload)?;
                 }
             }
             _ => {}
         }
-        drop(on);
-        drop(on_any);
         Ok(())
     }
 
+    /// Handles a received event without a handler as configured.
+    fn unhandled(&self, event: &Event, payload: Payload) -> Result<()> {
+        if let Some(callback) = self.on_unhandled.lock()?.deref_mut() {
+            callback(event.clone(), payload, self.clone());
+        }
+        match self.unhandled_policy {
+            UnhandledPolicy::Ignore => Ok(()),
+            UnhandledPolicy::Warn => {
+                log::warn!(
+                    "Received event {event} on namespace {}, which has no handler",
+                    self.nsp
+                );
+                Ok(())
+            }
+            UnhandledPolicy::Error => self.error(&Error::UnhandledEvent(event.clone())),
+        }
+    }
+
     /// Handles the incoming acks and classifies what callbacks to call and how.
     #[inline]
     fn handle_ack(&self, socket_packet: &Packet) -> Result<()> {
@@ -304,15 +736,19 @@ impl RawClient {
     /// Handles a binary event.
     #[inline]
     fn handle_binary_event(&self, packet: &Packet) -> Result<()> {
-        let event = if let Some(string_data) = &packet.data {
-            string_data.replace('\"', "").into()
-        } else {
-            Event::Message
-        };
+        let event = packet.binary_event();
+        if self.origin.is_some() && packet.binary_origin() == self.origin {
+            // this is an echo of one of our own protobuf messages
+            return Ok(());
+        }
 
         if let Some(attachments) = &packet.attachments {
             if let Some(binary_payload) = attachments.first() {
-                self.callback(&event, Payload::Binary(binary_payload.to_owned()))?;
+                let payload = self
+                    .codecs
+                    .lock()?
+                    .decode(&event, Payload::Binary(binary_payload.to_owned()))?;
+                self.callback(&event, payload)?;
             }
         }
         Ok(())
@@ -343,8 +779,16 @@ impl RawClient {
                 },
             };
 
+            self.recovery.received(payloads);
+            let mut payloads = payloads.to_vec();
+            if self.origin.is_some() && Payload::take_origin(&mut payloads) == self.origin {
+                // this is an echo of one of our own emits
+                return Ok(());
+            }
+            let payloads = self.schemas.check(&event, payloads)?;
+
             // call the correct callback
-            self.callback(&event, payloads.to_vec())?;
+            self.callback(&event, payloads)?;
         }
 
         Ok(())
@@ -359,19 +803,21 @@ impl RawClient {
             match packet.packet_type {
                 PacketId::Ack | PacketId::BinaryAck => {
                     if let Err(err) = self.handle_ack(packet) {
-                        self.callback(&Event::Error, err.to_string())?;
+                        self.error(&err)?;
                         return Err(err);
                     }
                 }
                 PacketId::BinaryEvent => {
                     if let Err(err) = self.handle_binary_event(packet) {
-                        self.callback(&Event::Error, err.to_string())?;
+                        self.error(&err)?;
                     }
                 }
                 PacketId::Connect => {
+                    self.recovery.connected(packet.data.as_deref());
                     self.callback(&Event::Connect, "")?;
                 }
                 PacketId::Disconnect => {
+                    self.close_outstanding_acks(false)?;
                     self.callback(&Event::Close, "")?;
                 }
                 PacketId::ConnectError => {
@@ -386,7 +832,7 @@ impl RawClient {
                 }
                 PacketId::Event => {
                     if let Err(err) = self.handle_event(packet) {
-                        self.callback(&Event::Error, err.to_string())?;
+                        self.error(&err)?;
                     }
                 }
             }
@@ -526,8 +972,16 @@ mod test {
             .opening_header("accept-encoding", "application/json")
             .on("test", |str, _| println!("Received: {:#?}", str))
             .on("message", |payload, _| println!("{:#?}", payload))
+            .once("message", |payload, _| println!("First: {:#?}", payload))
+            .on("removed", |_, _| {})
+            .off("removed")
             .connect_raw()?;
 
+        let handlers = socket.handlers()?;
+        assert_eq!(handlers.len(), 2);
+        assert_eq!(handlers.get(&Event::from("test")), Some(&1));
+        assert_eq!(handlers.get(&Event::Message), Some(&2));
+
         assert!(socket.emit("message", json!("Hello World")).is_ok());
 
         assert!(socket.emit("binary", Bytes::from_static(&[46, 88])).is_ok());
@@ -547,6 +1001,54 @@ mod test {
         test_socketio_socket(socket, "/admin".to_owned())
     }
 
+    #[test]
+    fn socket_io_ack_failed_on_disconnect() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, rx) = mpsc::channel();
+
+        let socket = ClientBuilder::new(url)
+            .on(Event::Error, move |payload, _| tx.send(payload).unwrap())
+            .connect_raw()?;
+
+        // the server never answers the ack of this event
+        socket.emit_with_ack("no-ack", json!(""), Duration::from_secs(30), |_, _| {
+            panic!("the ack must not be received")
+        })?;
+        socket.disconnect()?;
+
+        let err = Error::Disconnected(Event::from("no-ack")).to_string();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Payload::from(err)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn socket_io_on_error_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        let (tx, rx) = mpsc::channel();
+
+        let socket = ClientBuilder::new(url)
+            .on_error(move |err, _| {
+                if let Error::Disconnected(event) = err {
+                    tx.send(event.clone()).unwrap();
+                }
+            })
+            .connect_raw()?;
+
+        socket.emit_with_ack("no-ack", json!(""), Duration::from_secs(30), |_, _| {
+            panic!("the ack must not be received")
+        })?;
+        socket.disconnect()?;
+
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Event::from("no-ack")
+        );
+        Ok(())
+    }
+
     #[test]
     fn socket_io_on_any_integration() -> Result<()> {
         let url = crate::test::socket_io_server();
@@ -580,6 +1082,37 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn socket_io_on_unhandled_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+
+        let (tx, rx) = mpsc::sync_channel(4);
+        let error_tx = tx.clone();
+
+        let _socket = ClientBuilder::new(url)
+            .namespace("/")
+            .auth(json!({ "password": "123" }))
+            .on("auth", |_payload, _client| {})
+            .on("message", |_payload, _client| {})
+            .on(Event::Error, move |payload, _client| {
+                if let Payload::Text(values) = payload {
+                    error_tx.send(values[0].to_string()).unwrap();
+                }
+            })
+            .on_unhandled(move |event, _payload, _client| {
+                tx.send(String::from(event)).unwrap();
+            })
+            .unhandled_policy(UnhandledPolicy::Error)
+            .connect()?;
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), "test");
+        let error = rx.recv_timeout(timeout).unwrap();
+        assert!(error.contains("Received event test, which has no handler"));
+
+        Ok(())
+    }
+
     #[test]
     fn socket_io_auth_builder_integration() -> Result<()> {
         let url = crate::test::socket_io_auth_server();
@@ -624,6 +1157,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "websocket")]
     fn socket_io_websocket_integration() -> Result<()> {
         let url = crate::test::socket_io_server();
         let socket = ClientBuilder::new(url)
@@ -633,6 +1167,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "websocket")]
     fn socket_io_websocket_upgrade_integration() -> Result<()> {
         let url = crate::test::socket_io_server();
         let socket = ClientBuilder::new(url)
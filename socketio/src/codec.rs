@@ -0,0 +1,147 @@
+//! Codecs that send the payload of specific events as a binary attachment in a
+//! custom format, e.g. protobuf, instead of JSON.
+//!
+//! A codec is registered per event on the client or its builder. Emits of the
+//! event are encoded by the codec and sent as a binary attachment. Binary payloads
+//! received for the event are decoded before they're passed to the callbacks.
+//! Events without a codec keep the default JSON parser.
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use bytes::Bytes;
+
+use crate::{
+    error::{Error, Result},
+    Event, Payload,
+};
+
+/// The error a [`PayloadCodec`] fails with.
+pub type CodecError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Encodes and decodes the payloads of an event.
+///
+/// # Example
+/// ```rust
+/// use bytes::Bytes;
+/// use rust_socketio::{ClientBuilder, CodecError, Payload, PayloadCodec};
+///
+/// /// Sends the text arguments of an event as UTF-8 lines.
+/// struct LinesCodec;
+///
+/// impl PayloadCodec for LinesCodec {
+///     fn encode(&self, payload: Payload) -> Result<Bytes, CodecError> {
+///         match payload {
+///             Payload::Text(values) => Ok(Bytes::from(
+///                 values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join("\n"),
+///             )),
+///             payload => Err(format!("unsupported payload {payload:?}").into()),
+///         }
+///     }
+///
+///     fn decode(&self, data: Bytes) -> Result<Payload, CodecError> {
+///         let values = std::str::from_utf8(&data)?
+///             .lines()
+///             .map(serde_json::from_str)
+///             .collect::<Result<Vec<_>, _>>()?;
+///         Ok(Payload::Text(values))
+///     }
+/// }
+///
+/// let socket = ClientBuilder::new("http://localhost:4200/")
+///     .codec("telemetry", LinesCodec)
+///     .connect();
+/// ```
+pub trait PayloadCodec: Send + Sync {
+    /// Encodes the payload of an emit into the binary attachment that is sent.
+    fn encode(&self, payload: Payload) -> std::result::Result<Bytes, CodecError>;
+
+    /// Decodes a received binary attachment into the payload that is passed to
+    /// the callbacks.
+    fn decode(&self, data: Bytes) -> std::result::Result<Payload, CodecError>;
+}
+
+/// The codecs that are registered per event.
+#[derive(Clone, Default)]
+pub(crate) struct Codecs {
+    codecs: HashMap<Event, Arc<dyn PayloadCodec>>,
+}
+
+impl Codecs {
+    pub(crate) fn insert(&mut self, event: Event, codec: Arc<dyn PayloadCodec>) {
+        self.codecs.insert(event, codec);
+    }
+
+    /// Encodes the payload of an emit if a codec is registered for `event`.
+    pub(crate) fn encode(&self, event: &Event, payload: Payload) -> Result<Payload> {
+        match self.codecs.get(event) {
+            Some(codec) => Ok(Payload::Binary(
+                codec.encode(payload).map_err(Error::InvalidCodecPayload)?,
+            )),
+            None => Ok(payload),
+        }
+    }
+
+    /// Decodes a received payload if a codec is registered for `event`. Text
+    /// payloads are passed on unchanged.
+    pub(crate) fn decode(&self, event: &Event, payload: Payload) -> Result<Payload> {
+        match (self.codecs.get(event), payload) {
+            (Some(codec), Payload::Binary(data)) => {
+                codec.decode(data).map_err(Error::InvalidCodecPayload)
+            }
+            (_, payload) => Ok(payload),
+        }
+    }
+}
+
+impl Debug for Codecs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.codecs.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    struct LengthCodec;
+
+    impl PayloadCodec for LengthCodec {
+        fn encode(&self, payload: Payload) -> std::result::Result<Bytes, CodecError> {
+            match payload {
+                Payload::Text(values) => Ok(Bytes::from(vec![values.len() as u8])),
+                _ => Err("only text payloads are supported".into()),
+            }
+        }
+
+        fn decode(&self, data: Bytes) -> std::result::Result<Payload, CodecError> {
+            Ok(Payload::Text(vec![json!(data[0])]))
+        }
+    }
+
+    #[test]
+    fn test_codecs() -> Result<()> {
+        let mut codecs = Codecs::default();
+        codecs.insert(Event::from("count"), Arc::new(LengthCodec));
+
+        let count = Event::from("count");
+        assert_eq!(
+            codecs.encode(&count, Payload::Text(vec![json!(1), json!(2)]))?,
+            Payload::Binary(Bytes::from_static(&[2]))
+        );
+        assert_eq!(
+            codecs.decode(&count, Payload::Binary(Bytes::from_static(&[2])))?,
+            Payload::Text(vec![json!(2)])
+        );
+        assert!(matches!(
+            codecs.encode(&count, Payload::Binary(Bytes::new())),
+            Err(Error::InvalidCodecPayload(_))
+        ));
+
+        // text payloads and other events are left alone
+        let text = Payload::Text(vec![json!("foo")]);
+        assert_eq!(codecs.decode(&count, text.clone())?, text);
+        assert_eq!(codecs.encode(&Event::Message, text.clone())?, text);
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+//! Builders in the shape of upstream `rust_socketio` 0.6, for code that is
+//! switched to this crate with a change of the dependency only.
+//!
+//! The builders of this crate take the address as [`crate::ServerAddresses`],
+//! which breaks callers that pass their own `Into<String>` types. Importing the
+//! builders of this module instead, e.g. `use rust_socketio::compat::ClientBuilder;`,
+//! keeps such code compiling. Every other type is the same as upstream and
+//! re-exported here, and [`ClientBuilder::into_inner`] leads to the settings
+//! only this crate has.
+
+use native_tls::TlsConnector;
+use rust_engineio::header::HeaderValue;
+
+use crate::client::Client;
+use crate::error::Result;
+
+pub use crate::{Error, Event, Payload, RawClient, TransportType};
+
+/// [`crate::ClientBuilder`] with the constructor of upstream
+/// `rust_socketio`, which takes the address as any `Into<String>`.
+pub struct ClientBuilder(crate::ClientBuilder);
+
+impl ClientBuilder {
+    /// See [`crate::ClientBuilder::new`].
+    pub fn new<T: Into<String>>(address: T) -> Self {
+        ClientBuilder(crate::ClientBuilder::new(address.into()))
+    }
+
+    /// See [`crate::ClientBuilder::namespace`].
+    pub fn namespace<T: Into<String>>(self, namespace: T) -> Self {
+        ClientBuilder(self.0.namespace(namespace))
+    }
+
+    /// See [`crate::ClientBuilder::reconnect`].
+    pub fn reconnect(self, reconnect: bool) -> Self {
+        ClientBuilder(self.0.reconnect(reconnect))
+    }
+
+    /// See [`crate::ClientBuilder::reconnect_on_disconnect`].
+    pub fn reconnect_on_disconnect(self, reconnect_on_disconnect: bool) -> Self {
+        ClientBuilder(self.0.reconnect_on_disconnect(reconnect_on_disconnect))
+    }
+
+    /// See [`crate::ClientBuilder::reconnect_delay`].
+    pub fn reconnect_delay(self, min: u64, max: u64) -> Self {
+        ClientBuilder(self.0.reconnect_delay(min, max))
+    }
+
+    /// See [`crate::ClientBuilder::max_reconnect_attempts`].
+    pub fn max_reconnect_attempts(self, reconnect_attempts: u8) -> Self {
+        ClientBuilder(self.0.max_reconnect_attempts(reconnect_attempts))
+    }
+
+    /// See [`crate::ClientBuilder::on`].
+    pub fn on<T: Into<Event>, F>(self, event: T, callback: F) -> Self
+    where
+        F: FnMut(Payload, RawClient) + 'static + Send,
+    {
+        ClientBuilder(self.0.on(event, callback))
+    }
+
+    /// See [`crate::ClientBuilder::on_any`].
+    pub fn on_any<F>(self, callback: F) -> Self
+    where
+        F: FnMut(Event, Payload, RawClient) + 'static + Send,
+    {
+        ClientBuilder(self.0.on_any(callback))
+    }
+
+    /// See [`crate::ClientBuilder::tls_config`].
+    pub fn tls_config(self, tls_config: TlsConnector) -> Self {
+        ClientBuilder(self.0.tls_config(tls_config))
+    }
+
+    /// See [`crate::ClientBuilder::opening_header`].
+    pub fn opening_header<T: Into<HeaderValue>, K: Into<String>>(self, key: K, val: T) -> Self {
+        ClientBuilder(self.0.opening_header(key, val))
+    }
+
+    /// See [`crate::ClientBuilder::auth`].
+    pub fn auth(self, auth: serde_json::Value) -> Self {
+        ClientBuilder(self.0.auth(auth))
+    }
+
+    /// See [`crate::ClientBuilder::transport_type`].
+    pub fn transport_type(self, transport_type: TransportType) -> Self {
+        ClientBuilder(self.0.transport_type(transport_type))
+    }
+
+    /// See [`crate::ClientBuilder::connect`].
+    pub fn connect(self) -> Result<Client> {
+        self.0.connect()
+    }
+
+    /// See [`crate::ClientBuilder::connect_raw`].
+    pub fn connect_raw(self) -> Result<RawClient> {
+        self.0.connect_raw()
+    }
+
+    /// The builder of this crate, for the settings upstream doesn't have.
+    pub fn into_inner(self) -> crate::ClientBuilder {
+        self.0
+    }
+}
+
+impl From<ClientBuilder> for crate::ClientBuilder {
+    fn from(builder: ClientBuilder) -> Self {
+        builder.into_inner()
+    }
+}
+
+/// The async builder in the shape of upstream `rust_socketio`.
+#[cfg(feature = "async")]
+pub mod asynchronous {
+    use super::*;
+    use crate::asynchronous::{Client, ReconnectSettings};
+    use futures_util::future::BoxFuture;
+
+    /// [`crate::asynchronous::ClientBuilder`] with the constructor of upstream
+    /// `rust_socketio`, which takes the address as any `Into<String>`.
+    pub struct ClientBuilder(crate::asynchronous::ClientBuilder);
+
+    impl ClientBuilder {
+        /// See [`crate::asynchronous::ClientBuilder::new`].
+        pub fn new<T: Into<String>>(address: T) -> Self {
+            ClientBuilder(crate::asynchronous::ClientBuilder::new(address.into()))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::namespace`].
+        pub fn namespace<T: Into<String>>(self, namespace: T) -> Self {
+            ClientBuilder(self.0.namespace(namespace))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::on`].
+        pub fn on<T: Into<Event>, F>(self, event: T, callback: F) -> Self
+        where
+            F: for<'a> std::ops::FnMut(Payload, Client) -> BoxFuture<'static, ()>
+                + 'static
+                + Send
+                + Sync,
+        {
+            ClientBuilder(self.0.on(event, callback))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::on_reconnect`].
+        pub fn on_reconnect<F>(self, callback: F) -> Self
+        where
+            F: for<'a> std::ops::FnMut() -> BoxFuture<'static, ReconnectSettings>
+                + 'static
+                + Send
+                + Sync,
+        {
+            ClientBuilder(self.0.on_reconnect(callback))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::on_any`].
+        pub fn on_any<F>(self, callback: F) -> Self
+        where
+            F: for<'a> FnMut(Event, Payload, Client) -> BoxFuture<'static, ()>
+                + 'static
+                + Send
+                + Sync,
+        {
+            ClientBuilder(self.0.on_any(callback))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::tls_config`].
+        pub fn tls_config(self, tls_config: TlsConnector) -> Self {
+            ClientBuilder(self.0.tls_config(tls_config))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::opening_header`].
+        pub fn opening_header<T: Into<HeaderValue>, K: Into<String>>(self, key: K, val: T) -> Self {
+            ClientBuilder(self.0.opening_header(key, val))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::auth`].
+        pub fn auth<T: Into<serde_json::Value>>(self, auth: T) -> Self {
+            ClientBuilder(self.0.auth(auth))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::transport_type`].
+        pub fn transport_type(self, transport_type: TransportType) -> Self {
+            ClientBuilder(self.0.transport_type(transport_type))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::reconnect`].
+        pub fn reconnect(self, reconnect: bool) -> Self {
+            ClientBuilder(self.0.reconnect(reconnect))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::reconnect_on_disconnect`].
+        pub fn reconnect_on_disconnect(self, reconnect_on_disconnect: bool) -> Self {
+            ClientBuilder(self.0.reconnect_on_disconnect(reconnect_on_disconnect))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::reconnect_delay`].
+        pub fn reconnect_delay(self, min: u64, max: u64) -> Self {
+            ClientBuilder(self.0.reconnect_delay(min, max))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::max_reconnect_attempts`].
+        pub fn max_reconnect_attempts(self, reconnect_attempts: u8) -> Self {
+            ClientBuilder(self.0.max_reconnect_attempts(reconnect_attempts))
+        }
+
+        /// See [`crate::asynchronous::ClientBuilder::connect`].
+        pub async fn connect(self) -> Result<Client> {
+            self.0.connect().await
+        }
+
+        /// The builder of this crate, for the settings upstream doesn't have.
+        pub fn into_inner(self) -> crate::asynchronous::ClientBuilder {
+            self.0
+        }
+    }
+
+    impl From<ClientBuilder> for crate::asynchronous::ClientBuilder {
+        fn from(builder: ClientBuilder) -> Self {
+            builder.into_inner()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// An address type of an application, which upstream accepted.
+    struct Address(&'static str);
+
+    impl From<Address> for String {
+        fn from(address: Address) -> Self {
+            address.0.to_owned()
+        }
+    }
+
+    #[test]
+    fn test_upstream_address() {
+        let builder = ClientBuilder::new(Address("http://localhost:4200/"))
+            .namespace("/admin")
+            .reconnect_delay(10, 20)
+            .into_inner();
+        let addresses: Vec<_> = builder.endpoints.candidates().map(|(_, a)| a).collect();
+        assert_eq!(addresses, ["http://localhost:4200/"]);
+
+        #[cfg(feature = "async")]
+        {
+            let builder =
+                asynchronous::ClientBuilder::new(Address("http://localhost:4201/")).into_inner();
+            let addresses: Vec<_> = builder.endpoints.candidates().map(|(_, a)| a).collect();
+            assert_eq!(addresses, ["http://localhost:4201/"]);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::sync::oneshot;
+
+use crate::{
+    error::{Error, Result},
+    ConnectionState, Event, Payload,
+};
+
+/// An object-safe handle to a connected client, implemented by the sync
+/// [`crate::client::Client`] as well as the async
+/// [`crate::asynchronous::Client`], so that libraries built on top of this
+/// crate can accept either of them as `Arc<dyn DynClient>`.
+///
+/// The methods of the sync client block, they're run on the blocking thread
+/// pool of `tokio`. Building and dropping the sync client blocks as well, so
+/// it has to happen outside of the async tasks, e.g. in
+/// `tokio::task::spawn_blocking`. Acks are returned by
+/// [`DynClient::emit_with_ack`] instead of being passed to a callback.
+///
+/// # Example
+/// ```no_run
+/// use std::{sync::Arc, time::Duration};
+///
+/// use rust_socketio::{asynchronous, ClientBuilder, DynClient, Error};
+/// use serde_json::json;
+///
+/// async fn report(client: Arc<dyn DynClient>) -> Result<(), Error> {
+///     let ack = client
+///         .emit_with_ack("status".into(), json!("ok").into(), Duration::from_secs(2))
+///         .await?;
+///     println!("the server answered {ack:?}");
+///     client.disconnect().await
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     // the sync client blocks, so it lives on the blocking thread pool
+///     let runtime = tokio::runtime::Handle::current();
+///     tokio::task::spawn_blocking(move || {
+///         let sync_client = Arc::new(ClientBuilder::new("http://localhost:4200/").connect()?);
+///         runtime.block_on(report(sync_client.clone()))
+///     })
+///     .await
+///     .expect("the sync client panicked")?;
+///
+///     let async_client = asynchronous::ClientBuilder::new("http://localhost:4200/")
+///         .connect()
+///         .await?;
+///     report(Arc::new(async_client)).await
+/// }
+/// ```
+#[async_trait]
+pub trait DynClient: Send + Sync {
+    /// Sends `payload` to the server as `event`, like `Client::emit`.
+    async fn emit(&self, event: Event, payload: Payload) -> Result<()>;
+
+    /// Sends `payload` to the server as `event` and returns the payload of the
+    /// ack. Fails with [`Error::AckTimeout`] if the ack isn't received within
+    /// `timeout`, and with [`Error::Disconnected`] if the connection closes
+    /// before.
+    async fn emit_with_ack(
+        &self,
+        event: Event,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<Payload>;
+
+    /// Disconnects from the server, like `Client::disconnect`.
+    async fn disconnect(&self) -> Result<()>;
+
+    /// The current state of the connection.
+    fn state(&self) -> ConnectionState;
+}
+
+#[async_trait]
+impl<C: DynClient + ?Sized> DynClient for Arc<C> {
+    async fn emit(&self, event: Event, payload: Payload) -> Result<()> {
+        (**self).emit(event, payload).await
+    }
+
+    async fn emit_with_ack(
+        &self,
+        event: Event,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<Payload> {
+        (**self).emit_with_ack(event, payload, timeout).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        (**self).disconnect().await
+    }
+
+    fn state(&self) -> ConnectionState {
+        (**self).state()
+    }
+}
+
+/// Runs a blocking call of the sync client on the blocking thread pool.
+pub(crate) async fn blocking<T, F>(call: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(call).await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Creates the channel that the ack callback passes the payload of the ack to,
+/// or the failure of the ack. The sender is shared by the callback and the
+/// failure, which may be called more than once between them.
+pub(crate) fn ack_channel() -> (AckSender, oneshot::Receiver<Result<Payload>>) {
+    let (tx, rx) = oneshot::channel();
+    (AckSender(Arc::new(Mutex::new(Some(tx)))), rx)
+}
+
+#[derive(Clone)]
+pub(crate) struct AckSender(Arc<Mutex<Option<oneshot::Sender<Result<Payload>>>>>);
+
+impl AckSender {
+    pub(crate) fn send(&self, result: Result<Payload>) {
+        let tx = self.0.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if let Some(tx) = tx {
+            let _ = tx.send(result);
+        }
+    }
+}
+
+/// Waits for the payload of an ack. Fails if the ack was failed, or if the
+/// sender was dropped without being called.
+pub(crate) async fn receive_ack(
+    event: Event,
+    timeout: Duration,
+    ack: oneshot::Receiver<Result<Payload>>,
+) -> Result<Payload> {
+    match tokio::time::timeout(timeout, ack).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(Error::Disconnected(event)),
+        Err(_) => Err(Error::AckTimeout(event)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_receive_ack() {
+        let (tx, rx) = ack_channel();
+        tx.send(Ok(json!("first").into()));
+        tx.send(Ok(json!("second").into()));
+        let payload = receive_ack("get".into(), Duration::from_secs(1), rx).await;
+        assert_eq!(payload.ok(), Some(json!("first").into()));
+
+        let (tx, rx) = ack_channel();
+        tx.clone().send(Err(Error::Disconnected("get".into())));
+        tx.send(Ok(json!("late").into()));
+        let payload = receive_ack("get".into(), Duration::from_secs(1), rx).await;
+        assert!(matches!(payload, Err(Error::Disconnected(_))));
+
+        let (tx, rx) = ack_channel();
+        drop(tx);
+        let payload = receive_ack("get".into(), Duration::from_secs(1), rx).await;
+        assert!(matches!(payload, Err(Error::Disconnected(_))));
+
+        let (_tx, rx) = ack_channel();
+        let payload = receive_ack("get".into(), Duration::from_millis(10), rx).await;
+        assert!(matches!(payload, Err(Error::AckTimeout(_))));
+    }
+
+    async fn exercise(client: &dyn DynClient) -> Result<()> {
+        assert_eq!(client.state(), ConnectionState::Connected);
+        client.emit("message".into(), json!("hello").into()).await?;
+        let ack = client
+            .emit_with_ack("test".into(), json!("ping").into(), Duration::from_secs(2))
+            .await?;
+        assert_eq!(ack, json!("woot").into());
+
+        client.disconnect().await?;
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn socket_io_dyn_client_integration() -> Result<()> {
+        let url = crate::test::socket_io_server();
+        // the sync client is built, used and dropped outside of the runtime
+        let runtime = tokio::runtime::Handle::current();
+        let sync_url = url.clone();
+        tokio::task::spawn_blocking(move || {
+            let client: Arc<dyn DynClient> =
+                Arc::new(crate::ClientBuilder::new(sync_url).connect()?);
+            runtime.block_on(exercise(&client))
+        })
+        .await
+        .unwrap()?;
+
+        let client = crate::asynchronous::ClientBuilder::new(url)
+            .connect()
+            .await?;
+        exercise(&client).await
+    }
+}
@@ -0,0 +1,870 @@
+//! Defines how emits behave while the connection to the server is not available.
+//!
+//! The behavior depends on the state of the connection as well as on the
+//! [`EmitPolicy`] that is configured for the client (see
+//! [`crate::ClientBuilder::emit_policy`]) or passed along with a single emit
+//! via [`EmitOptions`]:
+//!
+//! | Connection state                    | `FailFast` | `Buffer`                       | `Block(deadline)`                      |
+//! |-------------------------------------|------------|--------------------------------|----------------------------------------|
+//! | connected                           | sent       | sent                           | sent                                   |
+//! | reconnecting                        | error      | buffered, sent after reconnect | sent after reconnect, error after deadline |
+//! | disconnected (manually or given up) | error      | error                          | error                                  |
+//!
+//! Buffered emits are dropped once the client is disconnected for good, i.e.
+//! when [`crate::client::Client::disconnect`] is called or all reconnect attempts
+//! failed. Errors are always reported as [`Error::IllegalActionBeforeOpen`].
+//!
+//! "After reconnect" means once the server acknowledged the namespace again, as
+//! it drops the events that arrive before. Buffered emits are dropped as well if
+//! the server refuses the namespace after a reconnect.
+//!
+//! The connection state is mirrored into the [`crate::ClientSnapshot`] of the
+//! client, so that emits pass the gate without a lock while it's connected.
+//!
+//! Acks can't be received anymore once the connection they were requested over
+//! closes. Pending acks are therefore failed right away when the connection
+//! closes, instead of waiting for their timeout: the failure is reported to the
+//! callbacks of [`Event::Error`] as [`Error::Disconnected`], the ack callback
+//! isn't called. Acks requested via `Client::emit_with_ack_result` are failed
+//! with [`Error::Disconnected`] themselves instead. The same applies to
+//! buffered emits with an ack that are dropped. Emits flagged via [`EmitOptions::resend_on_reconnect`] are buffered
+//! instead if the connection dropped and the client reconnects, and are sent
+//! again with a new ack once the connection is reestablished.
+//!
+//! [`EmitOptions::volatile`] emits are dropped without an error if the client
+//! isn't connected, instead of following the table above.
+//!
+//! An emit can carry a deadline via [`EmitOptions::deadline`], e.g. for
+//! position updates that are worthless once they are stale. An emit that is
+//! still buffered or waiting for the reconnect when its deadline passes is
+//! dropped instead of being sent, which is reported to the callbacks of
+//! [`Event::Error`] as [`Error::EmitExpired`].
+//!
+//! With [`crate::ClientBuilder::sequence_numbers`], every text payload carries a
+//! sequence number in a trailing object `{"__rust_socketio_seq": n}`. Numbers
+//! are assigned in the order the emits are sent, buffered emits get theirs when
+//! they're flushed after the reconnect. Emits that are resent after a reconnect
+//! keep their number, so that the server can detect duplicates as well as gaps.
+//!
+//! The buffer is unbounded by default. [`crate::ClientBuilder::emit_buffer_limit`]
+//! caps the number of buffered emits, the [`BufferOverflow`] decides what
+//! happens to an emit that would exceed it: the oldest buffered emit or the new
+//! one is dropped, which is reported to the callbacks of [`Event::Error`] as
+//! [`Error::EmitDropped`], or the new one is rejected with
+//! [`Error::EmitBufferFull`]. The emits resent after a reconnect count towards
+//! the limit as well.
+//!
+//! Events registered via [`crate::ClientBuilder::coalesce`] keep at most one
+//! emit in the buffer, e.g. for cursor positions where only the latest value
+//! matters: a buffered emit of the event is replaced by the next one, or merged
+//! with it via [`crate::ClientBuilder::coalesce_with`]. Emits with an ack are
+//! never coalesced, as their acks would be lost.
+//!
+//! `try_emit` of the async client queues an emit without awaiting it, e.g. from
+//! a `Drop` impl or from code that runs outside of the runtime. A task of the
+//! client sends the queued emits in order like `emit`, so the table above
+//! applies to them once they're dequeued. The queue holds
+//! [`crate::asynchronous::ClientBuilder::emit_queue_capacity`] emits; while it's
+//! full or once the client is disconnected, `try_emit` fails with a
+//! [`TryEmitError`] that hands the emit back.
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{Error, Result},
+    snapshot::SnapshotCell,
+    Event, Payload,
+};
+
+/// Describes what happens to an emit while the client is reconnecting to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmitPolicy {
+    /// Return an error right away. This is the default.
+    #[default]
+    FailFast,
+    /// Buffer the emit and send it as soon as the connection is reestablished.
+    Buffer,
+    /// Wait at most for the given duration until the connection is reestablished
+    /// and send the emit afterwards. Returns an error if the deadline expires.
+    Block(Duration),
+}
+
+/// Describes what happens to an emit that would be buffered while the buffer
+/// already holds the maximum number of emits, see
+/// [`crate::ClientBuilder::emit_buffer_limit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferOverflow {
+    /// Drop the oldest buffered emit to make room for the new one.
+    DropOldest,
+    /// Drop the new emit, keep the buffered ones.
+    DropNewest,
+    /// Reject the new emit with [`Error::EmitBufferFull`].
+    Error,
+}
+
+/// The capacity of the queue of `try_emit` by default, see
+/// [`crate::asynchronous::ClientBuilder::emit_queue_capacity`].
+#[cfg(feature = "async")]
+pub(crate) const DEFAULT_EMIT_QUEUE_CAPACITY: usize = 256;
+
+/// The reason why `try_emit` couldn't queue an emit. Both variants hold the
+/// event and payload of the emit, so that it can be retried or sent otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TryEmitError {
+    /// The queue is full, as the client didn't keep up with sending.
+    #[error("The emit queue is full, couldn't queue event {0}")]
+    Full(Event, Payload),
+    /// The client is disconnected and doesn't reconnect anymore.
+    #[error("The client is disconnected, couldn't queue event {0}")]
+    Disconnected(Event, Payload),
+}
+
+impl TryEmitError {
+    /// Returns the event and payload of the emit that wasn't queued.
+    pub fn into_inner(self) -> (Event, Payload) {
+        match self {
+            TryEmitError::Full(event, payload) | TryEmitError::Disconnected(event, payload) => {
+                (event, payload)
+            }
+        }
+    }
+}
+
+/// Options for a single emit. Every option that is not set falls back to the
+/// setting of the client.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::{EmitOptions, EmitPolicy};
+///
+/// let options = EmitOptions::new().policy(EmitPolicy::Buffer);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EmitOptions {
+    pub(crate) policy: Option<EmitPolicy>,
+    pub(crate) resend_on_reconnect: Option<bool>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) volatile: bool,
+}
+
+impl EmitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the [`EmitPolicy`] of the client for this emit.
+    pub fn policy(mut self, policy: EmitPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Sends the emit again after a reconnect if the connection dropped before
+    /// its ack was received, instead of failing the ack. Only applies to emits
+    /// with an ack, overrides the setting of the client (see
+    /// [`crate::ClientBuilder::resend_on_reconnect`]).
+    pub fn resend_on_reconnect(mut self, resend: bool) -> Self {
+        self.resend_on_reconnect = Some(resend);
+        self
+    }
+
+    /// Drops the emit if it couldn't be sent until `deadline`, e.g. because it
+    /// was buffered while the client was reconnecting. Without a deadline, an
+    /// emit is sent no matter how late.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Drops the emit if it couldn't be sent within `timeout`, the relative
+    /// form of [`EmitOptions::deadline`].
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Drops the emit silently if the client isn't connected, regardless of
+    /// the [`EmitPolicy`], e.g. for frequent updates that are superseded by
+    /// the next one anyway. The ack of a dropped emit is failed.
+    pub fn volatile(mut self, volatile: bool) -> Self {
+        self.volatile = volatile;
+        self
+    }
+}
+
+/// The outgoing data of a client that wasn't sent or answered yet, as returned
+/// by `Client::pending`, e.g. to shed load or to show how many updates are
+/// waiting to be synced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PendingStats {
+    /// The emits that are buffered until the client reconnected.
+    pub buffered: usize,
+    /// The size of the payloads of the buffered emits in bytes, with text
+    /// payloads counted as JSON.
+    pub buffered_bytes: usize,
+    /// The emits that were sent and whose ack wasn't received yet.
+    pub acks: usize,
+}
+
+/// The state of a client's connection, as it decides what happens to emits. The
+/// async client publishes changes via
+/// [`crate::asynchronous::Client::watch_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The client is connected to the server.
+    Connected,
+    /// The connection dropped and the client tries to reconnect.
+    Reconnecting,
+    /// The client was disconnected and doesn't reconnect anymore.
+    Disconnected,
+}
+
+/// An emit that waits for the connection to be reestablished. `C` is the type of
+/// the ack callback, which differs between the sync and the async client.
+pub(crate) struct BufferedEmit<C> {
+    pub(crate) event: Event,
+    pub(crate) payload: Payload,
+    pub(crate) ack: Option<(Duration, C)>,
+    pub(crate) resend_on_reconnect: bool,
+    pub(crate) deadline: Option<Instant>,
+    /// The sequence number of an emit that is resent, `None` for a new emit.
+    pub(crate) sequence: Option<u64>,
+    /// Completes the ack if it can't be received anymore.
+    pub(crate) on_failure: Option<AckFailure>,
+}
+
+/// Completes an ack with the error why it can't be received anymore, instead
+/// of reporting the error to the callbacks of [`Event::Error`].
+pub(crate) struct AckFailure(Box<dyn FnOnce(Error) + Send + Sync>);
+
+impl AckFailure {
+    pub(crate) fn new(fail: impl FnOnce(Error) + Send + Sync + 'static) -> Self {
+        AckFailure(Box::new(fail))
+    }
+
+    pub(crate) fn fail(self, err: Error) {
+        (self.0)(err)
+    }
+}
+
+impl std::fmt::Debug for AckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AckFailure")
+    }
+}
+
+impl<C> BufferedEmit<C> {
+    /// Whether the deadline of the emit passed, so that it must not be sent
+    /// anymore.
+    pub(crate) fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Merges a buffered payload with a newer one of the same event.
+pub(crate) type MergeFn = Arc<dyn Fn(Payload, Payload) -> Payload + Send + Sync>;
+
+/// The events whose buffered emits are coalesced, together with the function
+/// that merges them. Without a function, the newer payload replaces the older.
+#[derive(Clone, Default)]
+pub(crate) struct Coalescer {
+    events: Arc<HashMap<Event, Option<MergeFn>>>,
+}
+
+impl Coalescer {
+    pub(crate) fn insert(&mut self, event: Event, merge: Option<MergeFn>) {
+        Arc::make_mut(&mut self.events).insert(event, merge);
+    }
+}
+
+/// Hands out the sequence numbers of the emits of a client. Cloning yields a
+/// handle to the same counter, so that it survives reconnects.
+#[derive(Debug, Clone)]
+pub(crate) struct SequenceCounter {
+    next: Arc<AtomicU64>,
+}
+
+impl SequenceCounter {
+    pub(crate) fn new(next: u64) -> Self {
+        SequenceCounter {
+            next: Arc::new(AtomicU64::new(next)),
+        }
+    }
+
+    /// Returns the sequence number of an emit, which is `sequence` if the emit
+    /// is resent and the next number otherwise.
+    pub(crate) fn assign(&self, sequence: Option<u64>) -> u64 {
+        sequence.unwrap_or_else(|| self.next.fetch_add(1, Ordering::SeqCst))
+    }
+
+    /// The number the next emit gets.
+    pub(crate) fn peek(&self) -> u64 {
+        self.next.load(Ordering::SeqCst)
+    }
+}
+
+/// The outcome of passing an emit through the [`EmitGate`].
+pub(crate) enum Admission<T> {
+    /// The emit should be sent right away.
+    Send(T),
+    /// The emit was buffered and is handed out again once the client reconnected.
+    /// Holds the emit that was dropped instead if the buffer was full, which is
+    /// either the oldest buffered one or the new one, see [`BufferOverflow`].
+    Buffered(Option<T>),
+}
+
+struct GateState<T> {
+    state: ConnectionState,
+    buffer: VecDeque<T>,
+    limit: Option<(usize, BufferOverflow)>,
+}
+
+/// Decides for every emit whether it is sent, buffered or rejected, depending on
+/// the [`ConnectionState`] and the [`EmitPolicy`].
+pub(crate) struct EmitGate<T> {
+    inner: Mutex<GateState<T>>,
+    state_changed: Condvar,
+    #[cfg(feature = "async")]
+    state_notify: tokio::sync::Notify,
+    #[cfg(feature = "async")]
+    state_watch: tokio::sync::watch::Sender<ConnectionState>,
+    /// The connection state for reads without the lock, updated along with
+    /// `inner`.
+    snapshot: SnapshotCell,
+}
+
+impl<T> EmitGate<T> {
+    /// Creates a gate whose buffer holds at most `limit.0` emits, if a limit
+    /// is given.
+    pub(crate) fn new(limit: Option<(usize, BufferOverflow)>) -> Self {
+        EmitGate {
+            inner: Mutex::new(GateState {
+                state: ConnectionState::Connected,
+                buffer: VecDeque::new(),
+                limit,
+            }),
+            state_changed: Condvar::new(),
+            #[cfg(feature = "async")]
+            state_notify: tokio::sync::Notify::new(),
+            #[cfg(feature = "async")]
+            state_watch: tokio::sync::watch::Sender::new(ConnectionState::Connected),
+            snapshot: SnapshotCell::new(ConnectionState::Connected),
+        }
+    }
+
+    /// Updates the connection state. Returns the buffered emits that need to be
+    /// sent if the client is connected again. Buffered emits are dropped if the
+    /// client got disconnected, they're returned so that their acks can be failed.
+    pub(crate) fn set_state(&self, state: ConnectionState) -> Result<Vec<T>> {
+        let mut inner = self.inner.lock()?;
+        inner.state = state;
+        self.snapshot.set_connection(state);
+
+        let buffered = match state {
+            ConnectionState::Connected | ConnectionState::Disconnected => {
+                inner.buffer.drain(..).collect()
+            }
+            ConnectionState::Reconnecting => Vec::new(),
+        };
+        drop(inner);
+
+        self.state_changed.notify_all();
+        #[cfg(feature = "async")]
+        {
+            self.state_notify.notify_waiters();
+            self.state_watch.send_replace(state);
+        }
+
+        Ok(buffered)
+    }
+
+    /// The current connection state.
+    pub(crate) fn state(&self) -> Result<ConnectionState> {
+        Ok(self.snapshot.connection())
+    }
+
+    /// The snapshot of the client, which the gate keeps the connection state
+    /// of up to date.
+    pub(crate) fn snapshot(&self) -> &SnapshotCell {
+        &self.snapshot
+    }
+
+    /// Subscribes to the changes of the connection state.
+    #[cfg(feature = "async")]
+    pub(crate) fn watch_state(&self) -> tokio::sync::watch::Receiver<ConnectionState> {
+        self.state_watch.subscribe()
+    }
+
+    /// Passes an emit through the gate, blocks the current thread if the policy
+    /// requires to wait for a reconnect.
+    pub(crate) fn admit(&self, policy: EmitPolicy, item: T) -> Result<Admission<T>> {
+        if self.snapshot.connection() == ConnectionState::Connected {
+            return Ok(Admission::Send(item));
+        }
+        let mut inner = self.inner.lock()?;
+
+        if let (ConnectionState::Reconnecting, EmitPolicy::Block(deadline)) = (inner.state, policy)
+        {
+            let (guard, _) = self
+                .state_changed
+                .wait_timeout_while(inner, deadline, |inner| {
+                    inner.state == ConnectionState::Reconnecting
+                })?;
+            inner = guard;
+        }
+
+        Self::decide(&mut inner, policy, item)
+    }
+
+    /// Passes an emit through the gate, waits asynchronously if the policy
+    /// requires to wait for a reconnect.
+    #[cfg(feature = "async")]
+    pub(crate) async fn admit_async(&self, policy: EmitPolicy, item: T) -> Result<Admission<T>> {
+        if self.snapshot.connection() == ConnectionState::Connected {
+            return Ok(Admission::Send(item));
+        }
+        if let EmitPolicy::Block(deadline) = policy {
+            let started = tokio::time::Instant::now();
+            loop {
+                // create the notification before checking the state, so that no
+                // state change is missed in between
+                let notified = self.state_notify.notified();
+                if self.snapshot.connection() != ConnectionState::Reconnecting {
+                    break;
+                }
+
+                let Some(remaining) = deadline.checked_sub(started.elapsed()) else {
+                    break;
+                };
+                if tokio::time::timeout(remaining, notified).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        let mut inner = self.inner.lock()?;
+        Self::decide(&mut inner, policy, item)
+    }
+
+    /// Buffers an emit whose ack was pending when the connection dropped, so
+    /// that it's resent once the client reconnected. Unlike [`Self::admit`], a
+    /// full buffer isn't an error, even with [`BufferOverflow::Error`]: the
+    /// emit that doesn't fit is returned instead, as it is if the client isn't
+    /// reconnecting anymore.
+    pub(crate) fn rebuffer(&self, item: T) -> Result<Option<T>> {
+        let mut inner = self.inner.lock()?;
+        if inner.state != ConnectionState::Reconnecting {
+            return Ok(Some(item));
+        }
+        let dropped = match inner.limit {
+            Some((max, overflow)) if inner.buffer.len() >= max => match overflow {
+                BufferOverflow::DropOldest if max > 0 => inner.buffer.pop_front(),
+                _ => return Ok(Some(item)),
+            },
+            _ => None,
+        };
+        inner.buffer.push_back(item);
+        Ok(dropped)
+    }
+
+    fn decide(inner: &mut GateState<T>, policy: EmitPolicy, item: T) -> Result<Admission<T>> {
+        match (inner.state, policy) {
+            (ConnectionState::Connected, _) => Ok(Admission::Send(item)),
+            (ConnectionState::Reconnecting, EmitPolicy::Buffer) => {
+                let dropped = match inner.limit {
+                    Some((max, overflow)) if inner.buffer.len() >= max => match overflow {
+                        BufferOverflow::Error => return Err(Error::EmitBufferFull()),
+                        BufferOverflow::DropNewest => return Ok(Admission::Buffered(Some(item))),
+                        BufferOverflow::DropOldest => match inner.buffer.pop_front() {
+                            Some(oldest) => Some(oldest),
+                            // nothing can be buffered at all
+                            None => return Ok(Admission::Buffered(Some(item))),
+                        },
+                    },
+                    _ => None,
+                };
+                inner.buffer.push_back(item);
+                Ok(Admission::Buffered(dropped))
+            }
+            _ => Err(Error::IllegalActionBeforeOpen()),
+        }
+    }
+}
+
+impl<C> EmitGate<BufferedEmit<C>> {
+    /// The number of buffered emits and the size of their payloads.
+    pub(crate) fn buffered(&self) -> Result<(usize, usize)> {
+        let inner = self.inner.lock()?;
+        let bytes = inner.buffer.iter().map(|emit| emit.payload.size()).sum();
+        Ok((inner.buffer.len(), bytes))
+    }
+
+    /// Takes the buffered emit of the event out of the buffer if the event is
+    /// coalesced and `emit` would be buffered as well, and merges its payload
+    /// into `emit`. Returns the emit to pass through the gate.
+    pub(crate) fn coalesce(
+        &self,
+        coalescer: &Coalescer,
+        policy: EmitPolicy,
+        mut emit: BufferedEmit<C>,
+    ) -> Result<BufferedEmit<C>> {
+        let Some(merge) = coalescer.events.get(&emit.event) else {
+            return Ok(emit);
+        };
+        if emit.ack.is_some() || policy != EmitPolicy::Buffer {
+            return Ok(emit);
+        }
+
+        let mut inner = self.inner.lock()?;
+        if inner.state != ConnectionState::Reconnecting {
+            return Ok(emit);
+        }
+        let position = inner
+            .buffer
+            .iter()
+            .position(|buffered| buffered.event == emit.event && buffered.ack.is_none());
+        if let Some(buffered) = position.and_then(|position| inner.buffer.remove(position)) {
+            if let Some(merge) = merge {
+                emit.payload = merge(buffered.payload, emit.payload);
+            }
+        }
+        Ok(emit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    fn sent(admission: Result<Admission<u8>>) -> Option<u8> {
+        match admission {
+            Ok(Admission::Send(item)) => Some(item),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_rebuffer_never_fails() -> Result<()> {
+        let gate = EmitGate::new(Some((1, BufferOverflow::Error)));
+        assert_eq!(gate.rebuffer(1)?, Some(1));
+
+        gate.set_state(ConnectionState::Reconnecting)?;
+        assert_eq!(gate.rebuffer(2)?, None);
+        assert_eq!(gate.rebuffer(3)?, Some(3));
+        assert!(gate.admit(EmitPolicy::Buffer, 4).is_err());
+
+        let gate = EmitGate::new(Some((1, BufferOverflow::DropOldest)));
+        gate.set_state(ConnectionState::Reconnecting)?;
+        assert_eq!(gate.rebuffer(1)?, None);
+        assert_eq!(gate.rebuffer(2)?, Some(1));
+        assert_eq!(gate.set_state(ConnectionState::Connected)?, vec![2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_connected_always_sends() -> Result<()> {
+        let gate = EmitGate::new(None);
+
+        assert_eq!(sent(gate.admit(EmitPolicy::FailFast, 1)), Some(1));
+        assert_eq!(sent(gate.admit(EmitPolicy::Buffer, 2)), Some(2));
+        assert_eq!(
+            sent(gate.admit(EmitPolicy::Block(Duration::from_secs(1)), 3)),
+            Some(3)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconnecting_fail_fast() -> Result<()> {
+        let gate = EmitGate::new(None);
+        gate.set_state(ConnectionState::Reconnecting)?;
+
+        assert!(matches!(
+            gate.admit(EmitPolicy::FailFast, 1),
+            Err(Error::IllegalActionBeforeOpen())
+        ));
+        assert!(gate.set_state(ConnectionState::Connected)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconnecting_buffer_flushed_on_connect() -> Result<()> {
+        let gate = EmitGate::new(None);
+        gate.set_state(ConnectionState::Reconnecting)?;
+
+        assert!(matches!(
+            gate.admit(EmitPolicy::Buffer, 1)?,
+            Admission::Buffered(None)
+        ));
+        assert!(matches!(
+            gate.admit(EmitPolicy::Buffer, 2)?,
+            Admission::Buffered(None)
+        ));
+
+        assert_eq!(gate.set_state(ConnectionState::Connected)?, vec![1, 2]);
+        assert_eq!(sent(gate.admit(EmitPolicy::Buffer, 3)), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconnecting_buffer_dropped_on_disconnect() -> Result<()> {
+        let gate = EmitGate::new(None);
+        gate.set_state(ConnectionState::Reconnecting)?;
+        assert!(matches!(
+            gate.admit(EmitPolicy::Buffer, 1)?,
+            Admission::Buffered(None)
+        ));
+
+        assert_eq!(gate.set_state(ConnectionState::Disconnected)?, vec![1]);
+        assert!(gate.set_state(ConnectionState::Connected)?.is_empty());
+        Ok(())
+    }
+
+    fn dropped(admission: Result<Admission<u8>>) -> Option<u8> {
+        match admission {
+            Ok(Admission::Buffered(dropped)) => dropped,
+            _ => panic!("the emit wasn't buffered"),
+        }
+    }
+
+    #[test]
+    fn test_buffer_overflow() -> Result<()> {
+        let gate = EmitGate::new(Some((2, BufferOverflow::DropOldest)));
+        gate.set_state(ConnectionState::Reconnecting)?;
+        for value in 1..=2 {
+            assert_eq!(dropped(gate.admit(EmitPolicy::Buffer, value)), None);
+        }
+        assert_eq!(dropped(gate.admit(EmitPolicy::Buffer, 3)), Some(1));
+        assert_eq!(gate.set_state(ConnectionState::Connected)?, vec![2, 3]);
+
+        let gate = EmitGate::new(Some((2, BufferOverflow::DropNewest)));
+        gate.set_state(ConnectionState::Reconnecting)?;
+        for value in 1..=2 {
+            assert_eq!(dropped(gate.admit(EmitPolicy::Buffer, value)), None);
+        }
+        assert_eq!(dropped(gate.admit(EmitPolicy::Buffer, 3)), Some(3));
+        assert_eq!(gate.set_state(ConnectionState::Connected)?, vec![1, 2]);
+
+        let gate = EmitGate::new(Some((1, BufferOverflow::Error)));
+        gate.set_state(ConnectionState::Reconnecting)?;
+        assert_eq!(dropped(gate.admit(EmitPolicy::Buffer, 1)), None);
+        assert!(matches!(
+            gate.admit(EmitPolicy::Buffer, 2),
+            Err(Error::EmitBufferFull())
+        ));
+        assert_eq!(gate.set_state(ConnectionState::Connected)?, vec![1]);
+
+        let gate = EmitGate::new(Some((0, BufferOverflow::DropOldest)));
+        gate.set_state(ConnectionState::Reconnecting)?;
+        assert_eq!(dropped(gate.admit(EmitPolicy::Buffer, 1)), Some(1));
+        assert!(gate.set_state(ConnectionState::Connected)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconnecting_block() -> Result<()> {
+        let gate = Arc::new(EmitGate::new(None));
+        gate.set_state(ConnectionState::Reconnecting)?;
+
+        // the deadline expires
+        assert!(gate
+            .admit(EmitPolicy::Block(Duration::from_millis(10)), 1)
+            .is_err());
+
+        // the client reconnects in time
+        let gate_clone = gate.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            gate_clone.set_state(ConnectionState::Connected).unwrap();
+        });
+        assert_eq!(
+            sent(gate.admit(EmitPolicy::Block(Duration::from_secs(5)), 2)),
+            Some(2)
+        );
+        handle.join().unwrap();
+
+        // the client gives up reconnecting while waiting
+        gate.set_state(ConnectionState::Reconnecting)?;
+        let gate_clone = gate.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            gate_clone.set_state(ConnectionState::Disconnected).unwrap();
+        });
+        assert!(gate
+            .admit(EmitPolicy::Block(Duration::from_secs(5)), 3)
+            .is_err());
+        handle.join().unwrap();
+        Ok(())
+    }
+
+    fn emit(event: &str, value: u8, ack: bool) -> BufferedEmit<()> {
+        BufferedEmit {
+            event: event.into(),
+            payload: Payload::Text(vec![value.into()]),
+            ack: ack.then_some((Duration::from_secs(1), ())),
+            resend_on_reconnect: false,
+            deadline: None,
+            sequence: None,
+            on_failure: None,
+        }
+    }
+
+    fn admit_coalesced(
+        gate: &EmitGate<BufferedEmit<()>>,
+        coalescer: &Coalescer,
+        emit: BufferedEmit<()>,
+    ) -> Result<()> {
+        let emit = gate.coalesce(coalescer, EmitPolicy::Buffer, emit)?;
+        assert!(matches!(
+            gate.admit(EmitPolicy::Buffer, emit)?,
+            Admission::Buffered(None)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_coalesce() -> Result<()> {
+        let mut coalescer = Coalescer::default();
+        coalescer.insert("cursor".into(), None);
+        coalescer.insert(
+            "count".into(),
+            Some(Arc::new(|older, newer| match (older, newer) {
+                (Payload::Text(older), Payload::Text(newer)) => {
+                    Payload::Text(vec![(older[0].as_u64().unwrap()
+                        + newer[0].as_u64().unwrap())
+                    .into()])
+                }
+                (_, newer) => newer,
+            })),
+        );
+
+        let gate = EmitGate::new(None);
+        // connected, nothing is buffered
+        let sent = gate.coalesce(&coalescer, EmitPolicy::Buffer, emit("cursor", 1, false))?;
+        assert!(matches!(sent.payload, Payload::Text(ref values) if values[0] == 1));
+
+        gate.set_state(ConnectionState::Reconnecting)?;
+        admit_coalesced(&gate, &coalescer, emit("cursor", 1, false))?;
+        admit_coalesced(&gate, &coalescer, emit("count", 1, false))?;
+        admit_coalesced(&gate, &coalescer, emit("chat", 1, false))?;
+        admit_coalesced(&gate, &coalescer, emit("cursor", 2, false))?;
+        admit_coalesced(&gate, &coalescer, emit("count", 2, false))?;
+        admit_coalesced(&gate, &coalescer, emit("chat", 2, false))?;
+        admit_coalesced(&gate, &coalescer, emit("cursor", 3, true))?;
+
+        let buffered: Vec<_> = gate
+            .set_state(ConnectionState::Connected)?
+            .into_iter()
+            .map(|emit| match emit.payload {
+                Payload::Text(values) => (emit.event.to_string(), values[0].as_u64().unwrap()),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            buffered,
+            vec![
+                ("chat".to_owned(), 1),
+                ("cursor".to_owned(), 2),
+                ("count".to_owned(), 3),
+                ("chat".to_owned(), 2),
+                ("cursor".to_owned(), 3),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_counter() {
+        let counter = SequenceCounter::new(41);
+        let handle = counter.clone();
+        assert_eq!(counter.assign(None), 41);
+        assert_eq!(handle.assign(Some(7)), 7);
+        assert_eq!(handle.assign(None), 42);
+        assert_eq!(counter.peek(), 43);
+    }
+
+    #[test]
+    fn test_expired() {
+        let emit = |deadline| BufferedEmit::<()> {
+            event: Event::from("position"),
+            payload: Payload::from("{}"),
+            ack: None,
+            resend_on_reconnect: false,
+            deadline,
+            sequence: None,
+            on_failure: None,
+        };
+
+        assert!(!emit(None).expired());
+        assert!(!emit(Some(Instant::now() + Duration::from_secs(60))).expired());
+        assert!(emit(Some(Instant::now())).expired());
+    }
+
+    #[test]
+    fn test_disconnected_always_fails() -> Result<()> {
+        let gate = EmitGate::new(None);
+        gate.set_state(ConnectionState::Disconnected)?;
+
+        assert!(gate.admit(EmitPolicy::FailFast, 1).is_err());
+        assert!(gate.admit(EmitPolicy::Buffer, 2).is_err());
+        assert!(gate
+            .admit(EmitPolicy::Block(Duration::from_secs(5)), 3)
+            .is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnecting_block_async() -> Result<()> {
+        let gate = Arc::new(EmitGate::new(None));
+        gate.set_state(ConnectionState::Reconnecting)?;
+
+        assert!(gate
+            .admit_async(EmitPolicy::Block(Duration::from_millis(10)), 1)
+            .await
+            .is_err());
+
+        let gate_clone = gate.clone();
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            gate_clone.set_state(ConnectionState::Connected).unwrap();
+        });
+        assert_eq!(
+            sent(
+                gate.admit_async(EmitPolicy::Block(Duration::from_secs(5)), 2)
+                    .await
+            ),
+            Some(2)
+        );
+        handle.await.unwrap();
+
+        gate.set_state(ConnectionState::Reconnecting)?;
+        assert!(matches!(
+            gate.admit_async(EmitPolicy::Buffer, 3).await?,
+            Admission::Buffered(None)
+        ));
+        assert_eq!(gate.set_state(ConnectionState::Connected)?, vec![3]);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_watch_state() -> Result<()> {
+        let gate = EmitGate::<()>::new(None);
+        let mut state = gate.watch_state();
+        assert_eq!(*state.borrow_and_update(), ConnectionState::Connected);
+
+        gate.set_state(ConnectionState::Reconnecting)?;
+        state.changed().await.unwrap();
+        assert_eq!(*state.borrow_and_update(), ConnectionState::Reconnecting);
+        Ok(())
+    }
+}
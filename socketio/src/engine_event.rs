@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+#[cfg(feature = "async")]
+use futures_util::future::BoxFuture;
+use rust_engineio::{
+    DisconnectDescription, Packet as EnginePacket, PacketId as EnginePacketId, TransportKind,
+};
+
+/// An event of the underlying `engine.io` connection, for advanced users that
+/// need to observe the transport below the `socket.io` protocol. Handlers are
+/// registered via `ClientBuilder::on_engineio`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EngineEvent {
+    /// The `engine.io` connection was opened.
+    Open,
+    /// The polling transport of the handshake was upgraded to a websocket
+    /// transport.
+    Upgrade,
+    /// The server sent a ping, which the client answers with a pong right away.
+    Ping,
+    /// The client received a packet other than a ping, e.g. a message carrying
+    /// a `socket.io` packet.
+    Packet(EnginePacket),
+    /// The `engine.io` connection reported an error.
+    Error(String),
+    /// The `engine.io` connection was closed, with a description of why and
+    /// what the state of the transport was, e.g. for post-mortems of
+    /// abnormal terminations.
+    Close(DisconnectDescription),
+}
+
+impl From<EnginePacket> for EngineEvent {
+    fn from(packet: EnginePacket) -> Self {
+        match packet.packet_id {
+            EnginePacketId::Ping => EngineEvent::Ping,
+            _ => EngineEvent::Packet(packet),
+        }
+    }
+}
+
+pub(crate) type EngineEventHandler = Arc<dyn Fn(EngineEvent) + Send + Sync>;
+
+#[cfg(feature = "async")]
+pub(crate) type AsyncEngineEventHandler =
+    Arc<dyn Fn(EngineEvent) -> BoxFuture<'static, ()> + Send + Sync>;
+
+pub(crate) type TransportChangeHandler = Arc<dyn Fn(TransportKind) + Send + Sync>;
+
+#[cfg(feature = "async")]
+pub(crate) type AsyncTransportChangeHandler =
+    Arc<dyn Fn(TransportKind) -> BoxFuture<'static, ()> + Send + Sync>;
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_from_engine_packet() {
+        let ping = EnginePacket::new(EnginePacketId::Ping, Bytes::new());
+        assert_eq!(EngineEvent::from(ping), EngineEvent::Ping);
+
+        let message = EnginePacket::new(EnginePacketId::Message, Bytes::from_static(b"2[\"a\"]"));
+        assert_eq!(
+            EngineEvent::from(message.clone()),
+            EngineEvent::Packet(message)
+        );
+    }
+}
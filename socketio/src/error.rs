@@ -22,12 +22,20 @@ pub enum Error {
     InvalidPacket(),
     #[error("An error occurred while decoding the utf-8 text: {0}")]
     InvalidUtf8(#[from] Utf8Error),
+    #[error("Received a text packet that isn't valid utf-8: {source}")]
+    InvalidUtf8Packet {
+        /// The received packet, for diagnostics.
+        data: bytes::Bytes,
+        source: Utf8Error,
+    },
     #[error("An error occurred while encoding/decoding base64: {0}")]
     InvalidBase64(#[from] DecodeError),
     #[error("Invalid Url during parsing")]
     InvalidUrl(#[from] UrlParseError),
     #[error("Invalid Url Scheme: {0}")]
     InvalidUrlScheme(String),
+    #[error("Invalid Url template: {0}")]
+    InvalidUrlTemplate(String),
     #[error("Got illegal handshake response: {0}")]
     InvalidHandshake(String),
     #[error("Called an action before the connection was established")]
@@ -46,6 +54,37 @@ pub enum Error {
     InvalidAttachmentPacketType(u8),
     #[error("Underlying Engine.IO connection has closed")]
     StoppedEngineIoSocket,
+    #[error("The connection closed before the ack of event {0} was received")]
+    Disconnected(crate::Event),
+    #[error("The ack of event {0} wasn't received within its timeout")]
+    AckTimeout(crate::Event),
+    #[error("The emit of event {0} was dropped as its deadline passed before it was sent")]
+    EmitExpired(crate::Event),
+    #[error("The emit of event {0} was dropped as the emit buffer was full")]
+    EmitDropped(crate::Event),
+    #[error("The emit buffer is full")]
+    EmitBufferFull(),
+    #[error("Received event {0}, which has no handler")]
+    UnhandledEvent(crate::Event),
+    #[error("The payload codec failed: {0}")]
+    InvalidCodecPayload(#[source] crate::CodecError),
+    #[error(
+        "The payload of event {event} doesn't match its schema at version {version}: {source}"
+    )]
+    InvalidSchemaPayload {
+        event: crate::Event,
+        version: u32,
+        #[source]
+        source: crate::CodecError,
+    },
+    #[error("The thread that receives the packets of the client panicked")]
+    PollThreadPanicked,
+    #[cfg(feature = "redis")]
+    #[error("Redis replied with an error: {0}")]
+    InvalidRedisReply(String),
+    #[cfg(feature = "prost")]
+    #[error("Invalid protobuf message: {0}")]
+    InvalidProtobuf(#[from] prost::DecodeError),
 }
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
@@ -56,6 +95,18 @@ impl<T> From<std::sync::PoisonError<T>> for Error {
     }
 }
 
+impl From<rust_socketio_wire::socketio::Error> for Error {
+    fn from(err: rust_socketio_wire::socketio::Error) -> Self {
+        use rust_socketio_wire::socketio::Error as WireError;
+        match err {
+            WireError::IncompletePacket => Error::IncompletePacket(),
+            WireError::InvalidPacketId(id) => Error::InvalidPacketId(id),
+            WireError::InvalidJson(err) => Error::InvalidJson(err),
+            _ => Error::InvalidPacket(),
+        }
+    }
+}
+
 impl From<Error> for std::io::Error {
     fn from(err: Error) -> std::io::Error {
         std::io::Error::new(std::io::ErrorKind::Other, err)
@@ -57,3 +57,17 @@ impl Display for Event {
         f.write_str(self.as_str())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_names_are_custom() {
+        // the progress of reconnecting is reported via `ReconnectEvent`, so
+        // server events of the same names reach their handlers
+        for name in ["reconnect", "reconnect_attempt", "reconnect_error"] {
+            assert_eq!(Event::from(name), Event::Custom(String::from(name)));
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use url::Url;
+
+/// The URLs of the servers a client connects to, see `ClientBuilder::new`.
+/// Besides a single URL, a list of URLs converts into `ServerAddresses`: if
+/// the handshake with a server fails, the client fails over to the next one,
+/// in the order given by [`Failover`].
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::{ClientBuilder, Failover};
+///
+/// let socket = ClientBuilder::new([
+///     "http://primary.example.com:4200",
+///     "http://backup.example.com:4200",
+/// ])
+/// .failover(Failover::Priority)
+/// .connect();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServerAddresses(Vec<String>);
+
+impl ServerAddresses {
+    /// The URLs, in the order they were given.
+    pub fn as_slice(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl From<String> for ServerAddresses {
+    fn from(address: String) -> Self {
+        ServerAddresses(vec![address])
+    }
+}
+
+impl From<&String> for ServerAddresses {
+    fn from(address: &String) -> Self {
+        ServerAddresses(vec![address.clone()])
+    }
+}
+
+impl From<&str> for ServerAddresses {
+    fn from(address: &str) -> Self {
+        ServerAddresses(vec![address.to_owned()])
+    }
+}
+
+impl From<Url> for ServerAddresses {
+    fn from(address: Url) -> Self {
+        ServerAddresses(vec![address.into()])
+    }
+}
+
+impl<T: Into<String>> From<Vec<T>> for ServerAddresses {
+    fn from(addresses: Vec<T>) -> Self {
+        ServerAddresses(addresses.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<String>, const N: usize> From<[T; N]> for ServerAddresses {
+    fn from(addresses: [T; N]) -> Self {
+        ServerAddresses(addresses.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<String>> From<&[T]> for ServerAddresses {
+    fn from(addresses: &[T]) -> Self {
+        ServerAddresses(addresses.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// The order in which a client tries the [`ServerAddresses`] when it connects
+/// or reconnects. Each attempt tries every address once, the backoff between
+/// reconnect attempts applies to the attempts as a whole.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Failover {
+    /// Tries the addresses in the given order, so that the client returns to
+    /// the first one as soon as it's reachable again.
+    #[default]
+    Priority,
+    /// Starts with the address after the one of the previous connection, so
+    /// that the clients spread over the servers as they reconnect.
+    RoundRobin,
+}
+
+/// The addresses of a builder along with the one to start with. The position
+/// is shared by the clients of a builder, so that it survives the reconnects.
+#[derive(Debug, Clone)]
+pub(crate) struct Endpoints {
+    addresses: Vec<String>,
+    failover: Failover,
+    next: Arc<AtomicUsize>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(addresses: ServerAddresses) -> Self {
+        Endpoints {
+            addresses: addresses.0,
+            failover: Failover::default(),
+            next: Arc::default(),
+        }
+    }
+
+    pub(crate) fn set_failover(&mut self, failover: Failover) {
+        self.failover = failover;
+    }
+
+    /// Replaces the addresses, e.g. by the one of the reconnect settings.
+    pub(crate) fn replace(&mut self, addresses: ServerAddresses) {
+        self.addresses = addresses.0;
+        self.next.store(0, Ordering::Relaxed);
+    }
+
+    /// The addresses in the order to try them, along with their index for
+    /// [`Endpoints::connected`].
+    pub(crate) fn candidates(&self) -> impl Iterator<Item = (usize, &str)> {
+        let start = match self.failover {
+            Failover::Priority => 0,
+            Failover::RoundRobin => self.next.load(Ordering::Relaxed),
+        };
+        let len = self.addresses.len();
+        (0..len).map(move |offset| {
+            let index = (start + offset) % len;
+            (index, self.addresses[index].as_str())
+        })
+    }
+
+    /// The address that is tried first.
+    pub(crate) fn first(&self) -> Option<&str> {
+        self.candidates().next().map(|(_, address)| address)
+    }
+
+    /// Records that the client connected to the address at `index`.
+    pub(crate) fn connected(&self, index: usize) {
+        self.next
+            .store((index + 1) % self.addresses.len(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn order(endpoints: &Endpoints) -> Vec<&str> {
+        endpoints.candidates().map(|(_, address)| address).collect()
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(ServerAddresses::from("a").as_slice(), ["a"]);
+        assert_eq!(ServerAddresses::from(["a", "b"]).as_slice(), ["a", "b"]);
+        assert_eq!(
+            ServerAddresses::from(vec![String::from("a")]).as_slice(),
+            ["a"]
+        );
+        let url = Url::parse("http://localhost:4200/").unwrap();
+        assert_eq!(
+            ServerAddresses::from(url).as_slice(),
+            ["http://localhost:4200/"]
+        );
+    }
+
+    #[test]
+    fn test_failover_order() {
+        let mut endpoints = Endpoints::new(ServerAddresses::from(["a", "b", "c"]));
+        assert_eq!(order(&endpoints), ["a", "b", "c"]);
+        endpoints.connected(1);
+        assert_eq!(order(&endpoints), ["a", "b", "c"]);
+
+        endpoints.set_failover(Failover::RoundRobin);
+        assert_eq!(order(&endpoints), ["c", "a", "b"]);
+        endpoints.connected(2);
+        assert_eq!(order(&endpoints), ["a", "b", "c"]);
+        assert_eq!(endpoints.clone().first(), Some("a"));
+
+        endpoints.replace(ServerAddresses::from("d"));
+        assert_eq!(order(&endpoints), ["d"]);
+        endpoints.replace(ServerAddresses::from(Vec::<String>::new()));
+        assert_eq!(endpoints.first(), None);
+    }
+}
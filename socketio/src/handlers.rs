@@ -0,0 +1,127 @@
+//! Stores the handlers that are registered for events. Every event can have
+//! several handlers, which are called in the order they were registered.
+//! Handlers registered via `once` are removed after their first call.
+//!
+//! Handlers are shared, so that they can be called without holding the lock on
+//! the registry. This allows callbacks to register and remove handlers.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::Event;
+
+#[derive(Debug)]
+pub(crate) struct Handler<C> {
+    pub(crate) callback: Mutex<C>,
+    once: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct EventHandlers<C> {
+    handlers: HashMap<Event, Vec<Arc<Handler<C>>>>,
+}
+
+impl<C> Default for EventHandlers<C> {
+    fn default() -> Self {
+        EventHandlers {
+            handlers: HashMap::new(),
+        }
+    }
+}
+
+impl<C> EventHandlers<C> {
+    /// Appends a handler for `event`, which is removed after its first call if
+    /// `once` is set.
+    pub(crate) fn add(&mut self, event: Event, callback: C, once: bool) {
+        self.handlers
+            .entry(event)
+            .or_default()
+            .push(Arc::new(Handler {
+                callback: Mutex::new(callback),
+                once,
+            }));
+    }
+
+    /// Removes all handlers of `event`.
+    pub(crate) fn remove(&mut self, event: &Event) {
+        self.handlers.remove(event);
+    }
+
+    /// Removes the handlers of all events.
+    pub(crate) fn clear(&mut self) {
+        self.handlers.clear();
+    }
+
+    /// The handlers of `event` in the order they were registered. The `once`
+    /// handlers among them are removed, as they are about to be called.
+    pub(crate) fn fire(&mut self, event: &Event) -> Vec<Arc<Handler<C>>> {
+        let Some(handlers) = self.handlers.get_mut(event) else {
+            return Vec::new();
+        };
+        let fired = handlers.clone();
+        handlers.retain(|handler| !handler.once);
+        if handlers.is_empty() {
+            self.handlers.remove(event);
+        }
+        fired
+    }
+
+    /// The number of handlers per event.
+    pub(crate) fn counts(&self) -> HashMap<Event, usize> {
+        self.handlers
+            .iter()
+            .map(|(event, handlers)| (event.clone(), handlers.len()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fire(handlers: &mut EventHandlers<u8>, event: &str) -> Vec<u8> {
+        handlers
+            .fire(&Event::from(event))
+            .iter()
+            .map(|handler| *handler.callback.lock().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_registration_order() {
+        let mut handlers = EventHandlers::default();
+        handlers.add(Event::from("foo"), 1, false);
+        handlers.add(Event::from("foo"), 2, false);
+        handlers.add(Event::from("bar"), 3, false);
+
+        assert_eq!(fire(&mut handlers, "foo"), vec![1, 2]);
+        assert_eq!(fire(&mut handlers, "foo"), vec![1, 2]);
+        assert_eq!(
+            handlers.counts(),
+            HashMap::from([(Event::from("foo"), 2), (Event::from("bar"), 1)])
+        );
+
+        handlers.remove(&Event::from("foo"));
+        assert!(fire(&mut handlers, "foo").is_empty());
+        assert_eq!(fire(&mut handlers, "bar"), vec![3]);
+
+        handlers.clear();
+        assert!(fire(&mut handlers, "bar").is_empty());
+        assert!(handlers.counts().is_empty());
+    }
+
+    #[test]
+    fn test_once() {
+        let mut handlers = EventHandlers::default();
+        handlers.add(Event::from("foo"), 1, true);
+        handlers.add(Event::from("foo"), 2, false);
+        handlers.add(Event::from("bar"), 3, true);
+
+        assert_eq!(fire(&mut handlers, "foo"), vec![1, 2]);
+        assert_eq!(fire(&mut handlers, "foo"), vec![2]);
+        assert_eq!(fire(&mut handlers, "bar"), vec![3]);
+        assert!(fire(&mut handlers, "bar").is_empty());
+        assert_eq!(handlers.counts(), HashMap::from([(Event::from("foo"), 1)]));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "async")]
+use futures_util::future::BoxFuture;
+
+/// Records when a client last sent or received a `socket.io` packet, so that
+/// idle connections can be closed. The heartbeats of `engine.io` don't count.
+/// Cloning yields a handle to the same record, so that it survives reconnects.
+#[derive(Debug, Clone)]
+pub(crate) struct ActivityTracker {
+    last_activity: Arc<Mutex<Instant>>,
+}
+
+impl ActivityTracker {
+    pub(crate) fn new() -> Self {
+        ActivityTracker {
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Records a packet that was sent or received right now.
+    pub(crate) fn touch(&self) {
+        *self
+            .last_activity
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    /// The time since the last packet.
+    pub(crate) fn idle_for(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .elapsed()
+    }
+}
+
+/// Decides whether an idle connection is closed, gets the time since the last
+/// packet. Returning `false` keeps the connection open.
+pub(crate) type BeforeIdleCloseHandler = Arc<dyn Fn(Duration) -> bool + Send + Sync>;
+
+#[cfg(feature = "async")]
+pub(crate) type AsyncBeforeIdleCloseHandler =
+    Arc<dyn Fn(Duration) -> BoxFuture<'static, bool> + Send + Sync>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_activity_tracker() {
+        let tracker = ActivityTracker::new();
+        let handle = tracker.clone();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(handle.idle_for() >= Duration::from_millis(20));
+
+        tracker.touch();
+        assert!(handle.idle_for() < Duration::from_millis(20));
+    }
+}
@@ -75,6 +75,25 @@
 //! handling).
 //! - send JSON data to the server and receive an `ack`.
 //! - send and handle Binary data.
+//! - send and handle protobuf messages (via `prost`, behind the `prost` feature flag).
+//! - dispatch the events of several namespaces in their global arrival order.
+//! - build without websocket support for polling-only environments (by disabling the default
+//! `websocket` feature flag, which drops `tungstenite`).
+//! - wrap the HTTP requests of the async polling transport in `tower` middleware (behind the
+//! `tower` feature flag).
+//! - simulate disconnects, ping timeouts and reconnect storms in virtual time against an
+//! in-memory server, and test clients over loopback, scripted or fault-injecting transports
+//! (behind the `test-support` feature flag).
+//! - serve socket.io clients from a Rust backend (behind the `server` feature
+//! flag), broadcast across the servers of a cluster via Redis (behind the
+//! `redis` feature flag), and mount the server into an `axum` or `hyper`
+//! application (behind the `hyper` feature flag).
+//! - declare versioned schemas for the payloads of events, which migrate and validate
+//! received payloads.
+//! - persist, log and replay payloads and `engine.io` packets with `serde` (behind the
+//! `serde` feature flag).
+//! - parse and encode packets in `no_std` environments, like embedded gateways, with the
+//! `rust_socketio_wire` crate, which holds the wire format this crate uses.
 #![cfg_attr(
     feature = "async",
     doc = r#"
@@ -166,17 +185,41 @@ async fn main() {
 #![warn(clippy::perf)]
 #![warn(clippy::correctness)]
 
+pub(crate) mod ack;
+pub(crate) mod bearer;
 /// Defines client only structs
 pub mod client;
+pub(crate) mod codec;
+pub mod compat;
+#[cfg(feature = "async")]
+pub(crate) mod dyn_client;
+pub(crate) mod emit;
+pub(crate) mod engine_event;
 /// Deprecated import since 0.3.0-alpha-2, use Event in the crate root instead.
 /// Defines the events that could be sent or received.
 pub mod event;
+pub(crate) mod failover;
+pub(crate) mod handlers;
+pub(crate) mod idle;
+pub(crate) mod namespace;
 pub(crate) mod packet;
 /// Deprecated import since 0.3.0-alpha-2, use Event in the crate root instead.
 /// Defines the types of payload (binary or string), that
 /// could be sent or received.
 pub mod payload;
+#[cfg(feature = "prost")]
+pub(crate) mod proto;
+pub(crate) mod query;
+pub(crate) mod reconnect;
+pub(crate) mod recovery;
+pub(crate) mod redact;
+pub(crate) mod schema;
+pub(crate) mod snapshot;
 pub(self) mod socket;
+pub(crate) mod unhandled;
+pub(crate) mod uptime;
+pub(crate) mod url_builder;
+pub(crate) mod utf8;
 
 /// Deprecated import since 0.3.0-alpha-2, use Error in the crate root instead.
 /// Contains the error type which will be returned with every result in this
@@ -189,10 +232,46 @@ pub mod error;
 /// ([`crate::asynchronous::ClientBuilder`]) that allows for configuring a client.
 pub mod asynchronous;
 
+#[cfg(feature = "test-support")]
+pub mod simulation;
+
+#[cfg(feature = "server")]
+pub mod server;
+
 pub use error::Error;
 
+pub use ack::{AckDispatch, AckRequest};
+pub use bearer::BearerAuth;
+
 pub use {event::Event, payload::Payload};
 
+pub use codec::{CodecError, PayloadCodec};
+#[cfg(feature = "async")]
+pub use dyn_client::DynClient;
+pub use emit::{
+    BufferOverflow, ConnectionState, EmitOptions, EmitPolicy, PendingStats, TryEmitError,
+};
+pub use engine_event::EngineEvent;
+pub use failover::{Failover, ServerAddresses};
+pub use namespace::{NamespaceState, NamespaceStatus};
+pub use query::QueryValue;
+pub use reconnect::{BackoffStrategy, ExponentialBackoff, FixedBackoff, ReconnectEvent};
+pub use redact::RedactedView;
+pub use schema::EventSchemas;
+pub use snapshot::ClientSnapshot;
+pub use unhandled::UnhandledPolicy;
+pub use uptime::UptimeStats;
+pub use url_builder::UrlBuilder;
+pub use utf8::Utf8Policy;
+
+#[cfg(feature = "websocket")]
+pub use rust_engineio::WriteRetry;
+pub use rust_engineio::{
+    BufferPoolStats, CloseFrame, CloseReason, DisconnectDescription, DohResolver, EmitTimingStats,
+    FileUpgradeStore, HttpProtocol, HttpVersion, MemoryUpgradeStore, PollRetry, Resolver,
+    TransportKind, UpgradeStore,
+};
+
 pub use client::{ClientBuilder, RawClient, TransportType};
 
 // TODO: 0.4.0 remove
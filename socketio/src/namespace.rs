@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+#[cfg(feature = "async")]
+use futures_util::future::BoxFuture;
+
+/// The state of the connection of a client to a namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamespaceState {
+    /// The client sent a `Connect` packet and waits for the server to accept it.
+    Pending,
+    /// The server accepted the connection to the namespace.
+    Connected,
+    /// The server refused the connection with a `ConnectError` packet, or the
+    /// client failed to reconnect.
+    Failed,
+    /// The connection to the namespace was closed by either side or dropped.
+    Disconnected,
+}
+
+/// The state of a namespace together with the last error, as returned by
+/// `Client::namespaces`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceStatus {
+    /// The current state of the connection to the namespace.
+    pub state: NamespaceState,
+    /// The last error of the namespace, e.g. the message of a `ConnectError`
+    /// packet. It's kept after the namespace connected again.
+    pub last_error: Option<String>,
+}
+
+/// Records the state of the namespaces of a client. Cloning yields a handle to
+/// the same records.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NamespaceTracker {
+    namespaces: Arc<Mutex<HashMap<String, NamespaceStatus>>>,
+}
+
+impl NamespaceTracker {
+    /// Records the state of a namespace and replaces its last error if `error`
+    /// is given. Returns the new status if the state changed.
+    pub(crate) fn update(
+        &self,
+        nsp: &str,
+        state: NamespaceState,
+        error: Option<String>,
+    ) -> Option<NamespaceStatus> {
+        let mut namespaces = self
+            .namespaces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let previous = namespaces.get(nsp).map(|status| status.state);
+        let status = namespaces.entry(nsp.to_owned()).or_insert(NamespaceStatus {
+            state,
+            last_error: None,
+        });
+        if error.is_some() {
+            status.last_error = error;
+        }
+        status.state = state;
+        (previous != Some(state)).then(|| status.clone())
+    }
+
+    /// The status of all namespaces the client connected to.
+    pub(crate) fn snapshot(&self) -> HashMap<String, NamespaceStatus> {
+        self.namespaces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+pub(crate) type NamespaceStateHandler = Arc<dyn Fn(&str, &NamespaceStatus) + Send + Sync>;
+
+#[cfg(feature = "async")]
+pub(crate) type AsyncNamespaceStateHandler =
+    Arc<dyn Fn(String, NamespaceStatus) -> BoxFuture<'static, ()> + Send + Sync>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let tracker = NamespaceTracker::default();
+        let pending = tracker.update("/admin", NamespaceState::Pending, None);
+        assert_eq!(
+            pending.map(|status| status.state),
+            Some(NamespaceState::Pending)
+        );
+        assert_eq!(
+            tracker.update("/admin", NamespaceState::Pending, None),
+            None
+        );
+
+        let failed = tracker.update("/admin", NamespaceState::Failed, Some("denied".into()));
+        assert_eq!(
+            failed.and_then(|status| status.last_error).as_deref(),
+            Some("denied")
+        );
+
+        tracker.update("/admin", NamespaceState::Connected, None);
+        assert_eq!(
+            tracker.snapshot().get("/admin"),
+            Some(&NamespaceStatus {
+                state: NamespaceState::Connected,
+                last_error: Some("denied".into()),
+            })
+        );
+    }
+}
@@ -1,10 +1,14 @@
 use crate::error::{Error, Result};
-use crate::{Event, Payload};
+use crate::{Event, Payload, Utf8Policy};
 use bytes::Bytes;
+use rust_socketio_wire::socketio as wire;
 use serde::de::IgnoredAny;
+use serde_json::Value;
+use smallvec::SmallVec;
 
+use std::borrow::Cow;
 use std::convert::TryFrom;
-use std::fmt::Write;
+use std::io::{self, Write};
 use std::str::from_utf8 as str_from_utf8;
 
 /// An enumeration of the different `Packet` types in the `socket.io` protocol.
@@ -90,6 +94,98 @@ impl Packet {
     }
 }
 
+/// The bytes of a small event that are kept on the stack while encoding it, see
+/// [`Packet::encode_small_event`]. Larger thresholds still work, the buffer
+/// then moves to the heap for events above this size.
+const SMALL_EVENT_INLINE_CAPACITY: usize = 256;
+
+/// A buffer that fails writes which would grow it beyond its limit.
+struct BoundedBuffer {
+    bytes: SmallVec<[u8; SMALL_EVENT_INLINE_CAPACITY]>,
+    limit: usize,
+}
+
+impl BoundedBuffer {
+    fn new(limit: usize) -> Self {
+        BoundedBuffer {
+            bytes: SmallVec::new(),
+            limit,
+        }
+    }
+}
+
+impl io::Write for BoundedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.bytes.len() + buf.len() > self.limit {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Packet {
+    /// Encodes a text event without an ack id directly into a buffer on the
+    /// stack. This skips the `Packet` with its intermediate `String`s that
+    /// [`Packet::new_from_payload`] and the encoding via `Bytes::from` build, so
+    /// the only allocation left is the returned `Bytes`.
+    ///
+    /// Returns `None` if the encoded event is longer than `threshold` bytes; such
+    /// events are encoded the regular way. The encoding stops as soon as the
+    /// threshold is passed, so large arguments aren't serialized twice.
+    pub(crate) fn encode_small_event(
+        nsp: &str,
+        event: &Event,
+        data: &[Value],
+        threshold: usize,
+    ) -> Option<Bytes> {
+        let mut buffer = BoundedBuffer::new(threshold);
+        buffer.write_all(&[PacketId::Event as u8 + b'0']).ok()?;
+        if nsp != "/" {
+            buffer.write_all(nsp.as_bytes()).ok()?;
+            buffer.write_all(b",").ok()?;
+        }
+
+        buffer.write_all(b"[").ok()?;
+        serde_json::to_writer(&mut buffer, event.as_str()).ok()?;
+        for value in data {
+            buffer.write_all(b",").ok()?;
+            serde_json::to_writer(&mut buffer, value).ok()?;
+        }
+        buffer.write_all(b"]").ok()?;
+
+        Some(Bytes::copy_from_slice(&buffer.bytes))
+    }
+
+    /// The event of a binary event packet, which is its first argument, e.g. `foo`
+    /// for `["foo",{"_placeholder":true,"num":0}]`. Further text arguments before
+    /// the attachment, like the type name of a protobuf message, are skipped.
+    pub(crate) fn binary_event(&self) -> Event {
+        let Some(data) = &self.data else {
+            return Event::Message;
+        };
+        match serde_json::from_str::<Vec<serde_json::Value>>(&format!("[{data}]")) {
+            Ok(args) => match args.first() {
+                Some(serde_json::Value::String(event)) => Event::from(event.as_str()),
+                _ => Event::Message,
+            },
+            Err(_) => data.replace('"', "").into(),
+        }
+    }
+
+    /// The origin a binary event packet was tagged with, see
+    /// [`Payload::take_origin`]. Only protobuf messages carry tags.
+    pub(crate) fn binary_origin(&self) -> Option<String> {
+        let data = self.data.as_ref()?;
+        let mut args = serde_json::from_str::<Vec<Value>>(&format!("[{data}]")).ok()?;
+        Payload::take_origin(&mut args)
+    }
+}
+
 impl Default for Packet {
     fn default() -> Self {
         Self {
@@ -113,15 +209,34 @@ impl TryFrom<u8> for PacketId {
 impl TryFrom<char> for PacketId {
     type Error = Error;
     fn try_from(b: char) -> Result<Self> {
-        match b {
-            '0' => Ok(PacketId::Connect),
-            '1' => Ok(PacketId::Disconnect),
-            '2' => Ok(PacketId::Event),
-            '3' => Ok(PacketId::Ack),
-            '4' => Ok(PacketId::ConnectError),
-            '5' => Ok(PacketId::BinaryEvent),
-            '6' => Ok(PacketId::BinaryAck),
-            _ => Err(Error::InvalidPacketId(b)),
+        Ok(wire::PacketId::from_char(b)?.into())
+    }
+}
+
+impl From<PacketId> for wire::PacketId {
+    fn from(packet_id: PacketId) -> Self {
+        match packet_id {
+            PacketId::Connect => wire::PacketId::Connect,
+            PacketId::Disconnect => wire::PacketId::Disconnect,
+            PacketId::Event => wire::PacketId::Event,
+            PacketId::Ack => wire::PacketId::Ack,
+            PacketId::ConnectError => wire::PacketId::ConnectError,
+            PacketId::BinaryEvent => wire::PacketId::BinaryEvent,
+            PacketId::BinaryAck => wire::PacketId::BinaryAck,
+        }
+    }
+}
+
+impl From<wire::PacketId> for PacketId {
+    fn from(packet_id: wire::PacketId) -> Self {
+        match packet_id {
+            wire::PacketId::Connect => PacketId::Connect,
+            wire::PacketId::Disconnect => PacketId::Disconnect,
+            wire::PacketId::Event => PacketId::Event,
+            wire::PacketId::Ack => PacketId::Ack,
+            wire::PacketId::ConnectError => PacketId::ConnectError,
+            wire::PacketId::BinaryEvent => PacketId::BinaryEvent,
+            wire::PacketId::BinaryAck => PacketId::BinaryAck,
         }
     }
 }
@@ -158,43 +273,15 @@ impl From<&Packet> for Bytes {
     /// The binary payload of a packet is not put at the end of the
     /// stream as it gets handled and send by it's own logic via the socket.
     fn from(packet: &Packet) -> Bytes {
-        // first the packet type
+        let encoded = wire::Packet {
+            packet_type: packet.packet_type.into(),
+            nsp: &packet.nsp,
+            data: packet.data.as_deref().map(Cow::Borrowed),
+            id: packet.id,
+            attachment_count: packet.attachment_count,
+        };
         let mut buffer = String::new();
-        buffer.push((packet.packet_type as u8 + b'0') as char);
-
-        // eventually a number of attachments, followed by '-'
-        if let PacketId::BinaryAck | PacketId::BinaryEvent = packet.packet_type {
-            let _ = write!(buffer, "{}-", packet.attachment_count);
-        }
-
-        // if the namespace is different from the default one append it as well,
-        // followed by ','
-        if packet.nsp != "/" {
-            buffer.push_str(&packet.nsp);
-            buffer.push(',');
-        }
-
-        // if an id is present append it...
-        if let Some(id) = packet.id {
-            let _ = write!(buffer, "{id}");
-        }
-
-        if packet.attachments.is_some() {
-            let num = packet.attachment_count - 1;
-
-            // check if an event type is present
-            if let Some(event_type) = packet.data.as_ref() {
-                let _ = write!(
-                    buffer,
-                    "[{event_type},{{\"_placeholder\":true,\"num\":{num}}}]",
-                );
-            } else {
-                let _ = write!(buffer, "[{{\"_placeholder\":true,\"num\":{num}}}]");
-            }
-        } else if let Some(data) = packet.data.as_ref() {
-            buffer.push_str(data);
-        }
-
+        wire::encode_packet(&encoded, packet.attachments.is_some(), &mut buffer);
         Bytes::from(buffer)
     }
 }
@@ -216,64 +303,37 @@ impl TryFrom<&Bytes> for Packet {
     /// this member. This is done because the attachment is usually
     /// send in another packet.
     fn try_from(payload: &Bytes) -> Result<Packet> {
-        let mut payload = str_from_utf8(&payload).map_err(Error::InvalidUtf8)?;
-        let mut packet = Packet::default();
-
-        // packet_type
-        let id_char = payload.chars().next().ok_or(Error::IncompletePacket())?;
-        packet.packet_type = PacketId::try_from(id_char)?;
-        payload = &payload[id_char.len_utf8()..];
-
-        // attachment_count
-        if let PacketId::BinaryAck | PacketId::BinaryEvent = packet.packet_type {
-            let (prefix, rest) = payload.split_once('-').ok_or(Error::IncompletePacket())?;
-            payload = rest;
-            packet.attachment_count = prefix.parse().map_err(|_| Error::InvalidPacket())?;
-        }
-
-        // namespace
-        if payload.starts_with('/') {
-            let (prefix, rest) = payload.split_once(',').ok_or(Error::IncompletePacket())?;
-            payload = rest;
-            packet.nsp.clear(); // clearing the default
-            packet.nsp.push_str(prefix);
-        }
-
-        // id
-        let Some((non_digit_idx, _)) = payload.char_indices().find(|(_, c)| !c.is_ascii_digit())
-        else {
-            return Ok(packet);
-        };
-
-        if non_digit_idx > 0 {
-            let (prefix, rest) = payload.split_at(non_digit_idx);
-            payload = rest;
-            packet.id = Some(prefix.parse().map_err(|_| Error::InvalidPacket())?);
-        }
-
-        // validate json
-        serde_json::from_str::<IgnoredAny>(payload).map_err(Error::InvalidJson)?;
-
-        match packet.packet_type {
-            PacketId::BinaryAck | PacketId::BinaryEvent => {
-                if payload.starts_with('[') && payload.ends_with(']') {
-                    payload = &payload[1..payload.len() - 1];
-                }
-
-                let mut str = payload.replace("{\"_placeholder\":true,\"num\":0}", "");
-
-                if str.ends_with(',') {
-                    str.pop();
-                }
+        Packet::decode(payload, Utf8Policy::Reject)
+    }
+}
 
-                if !str.is_empty() {
-                    packet.data = Some(str);
-                }
+impl Packet {
+    /// Decodes a packet like `Packet::try_from`, handling text that isn't
+    /// valid UTF-8 according to `policy`.
+    pub(crate) fn decode(payload: &Bytes, policy: Utf8Policy) -> Result<Packet> {
+        let text = match str_from_utf8(payload) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(_) if policy == Utf8Policy::Lossy => String::from_utf8_lossy(payload),
+            Err(source) => {
+                return Err(Error::InvalidUtf8Packet {
+                    data: payload.clone(),
+                    source,
+                })
             }
-            _ => packet.data = Some(payload.to_string()),
-        }
+        };
+        Self::parse(&text)
+    }
 
-        Ok(packet)
+    fn parse(payload: &str) -> Result<Packet> {
+        let packet = wire::decode_packet(payload)?;
+        Ok(Packet {
+            packet_type: packet.packet_type.into(),
+            nsp: packet.nsp.to_owned(),
+            data: packet.data.map(Cow::into_owned),
+            id: packet.id,
+            attachment_count: packet.attachment_count,
+            attachments: None,
+        })
     }
 }
 
@@ -608,6 +668,21 @@ mod test {
         assert!(matches!(Error::InvalidPacketId(42 as char), _sut))
     }
 
+    #[test]
+    fn binary_event() {
+        let packet = |data: Option<&str>| Packet {
+            packet_type: PacketId::BinaryEvent,
+            data: data.map(str::to_owned),
+            ..Default::default()
+        };
+        assert_eq!(packet(None).binary_event(), Event::Message);
+        assert_eq!(packet(Some("\"foo\"")).binary_event(), Event::from("foo"));
+        assert_eq!(
+            packet(Some("\"foo\",\"pkg.Type\"")).binary_event(),
+            Event::from("foo")
+        );
+    }
+
     #[test]
     fn new_from_payload_binary() {
         let payload = Payload::Binary(Bytes::from_static(&[0, 4, 9]));
@@ -651,6 +726,53 @@ mod test {
         )
     }
 
+    #[test]
+    fn bounded_buffer() {
+        let mut buffer = BoundedBuffer::new(4);
+        buffer.write_all(b"abc").unwrap();
+        assert!(buffer.write_all(b"de").is_err());
+        buffer.write_all(b"d").unwrap();
+        assert_eq!(buffer.bytes.as_slice(), b"abcd");
+    }
+
+    #[test]
+    fn encode_small_event() {
+        let data = vec![
+            serde_json::json!("String test"),
+            serde_json::json!({"type":"object"}),
+        ];
+        for nsp in ["/", "/admin"] {
+            let packet =
+                Packet::new_from_payload(Payload::Text(data.clone()), "event".into(), nsp, None)
+                    .unwrap();
+            assert_eq!(
+                Packet::encode_small_event(nsp, &"event".into(), &data, 1024),
+                Some(Bytes::from(&packet))
+            );
+        }
+
+        // events above the threshold are left to the regular encoding
+        let encoded = Packet::encode_small_event("/", &"event".into(), &data, 1024).unwrap();
+        assert_eq!(
+            Packet::encode_small_event("/", &"event".into(), &data, encoded.len()),
+            Some(encoded.clone())
+        );
+        assert_eq!(
+            Packet::encode_small_event("/", &"event".into(), &data, encoded.len() - 1),
+            None
+        );
+
+        // events that don't fit on the stack are encoded on the heap if the threshold allows it
+        let large = vec![serde_json::json!("x".repeat(SMALL_EVENT_INLINE_CAPACITY))];
+        let packet =
+            Packet::new_from_payload(Payload::Text(large.clone()), "event".into(), "/", None)
+                .unwrap();
+        assert_eq!(
+            Packet::encode_small_event("/", &"event".into(), &large, usize::MAX),
+            Some(Bytes::from(&packet))
+        );
+    }
+
     #[test]
     fn new_from_payload_json() {
         let payload = Payload::Text(vec![
@@ -671,4 +793,17 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn test_decode_invalid_utf8() {
+        // "café" encoded as Latin-1
+        let payload = Bytes::from_static(b"2[\"menu\",\"caf\xe9\"]");
+
+        let err = Packet::decode(&payload, Utf8Policy::Reject).unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8Packet { ref data, .. } if *data == payload));
+        assert!(Packet::decode(&payload, Utf8Policy::Terminate).is_err());
+
+        let packet = Packet::decode(&payload, Utf8Policy::Lossy).unwrap();
+        assert_eq!(packet.data, Some("[\"menu\",\"caf\u{fffd}\"]".to_owned()));
+    }
 }
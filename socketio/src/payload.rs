@@ -1,4 +1,9 @@
 use bytes::Bytes;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Result;
 
 /// A type which represents a `payload` in the `socket.io` context.
 /// A payload could either be of the type `Payload::Binary`, which holds
@@ -6,6 +11,11 @@ use bytes::Bytes;
 /// `Payload::String` which holds a [`std::string::String`]. The enum is
 /// used for both representing data that's send and data that's received.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "SerdePayload", into = "SerdePayload")
+)]
 pub enum Payload {
     Binary(Bytes),
     Text(Vec<serde_json::Value>),
@@ -15,6 +25,39 @@ pub enum Payload {
 }
 
 impl Payload {
+    /// The key of the trailing object that carries the id of the emitting client
+    /// when echo suppression is enabled.
+    pub(crate) const ORIGIN_KEY: &'static str = "__rust_socketio_origin";
+
+    /// The key of the trailing object that carries the sequence number of an
+    /// emit when sequence numbers are enabled.
+    pub(crate) const SEQUENCE_KEY: &'static str = "__rust_socketio_seq";
+
+    /// Serializes `value` as the single argument of a text payload. This allows
+    /// to send any type that implements `serde::Serialize`, e.g. a struct that
+    /// derives it, without encoding it by hand. Fails with
+    /// [`crate::Error::InvalidJson`] if the value can't be represented as JSON.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::Payload;
+    /// use serde::Serialize;
+    /// use serde_json::json;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Order {
+    ///     id: u32,
+    ///     items: Vec<String>,
+    /// }
+    ///
+    /// let order = Order { id: 7, items: vec!["tea".to_owned()] };
+    /// let payload = Payload::json(&order).unwrap();
+    /// assert_eq!(payload, Payload::Text(vec![json!({"id": 7, "items": ["tea"]})]));
+    /// ```
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<Self> {
+        Ok(Self::Text(vec![serde_json::to_value(value)?]))
+    }
+
     pub(crate) fn string_to_value(string: String) -> serde_json::Value {
         if let Ok(value) = serde_json::from_str::<serde_json::Value>(&string) {
             value
@@ -22,6 +65,62 @@ impl Payload {
             serde_json::Value::String(string)
         }
     }
+
+    /// Tags a `Payload::Text` with the given origin by appending an additional
+    /// argument. Binary payloads can't carry a tag and are returned unchanged.
+    pub(crate) fn with_origin(self, origin: &str) -> Self {
+        match self {
+            Payload::Text(mut values) => {
+                values.push(serde_json::json!({ Self::ORIGIN_KEY: origin }));
+                Payload::Text(values)
+            }
+            payload => payload,
+        }
+    }
+
+    /// Tags a `Payload::Text` with the given sequence number by appending an
+    /// additional argument. Binary payloads are returned unchanged.
+    pub(crate) fn with_sequence(self, sequence: u64) -> Self {
+        match self {
+            Payload::Text(mut values) => {
+                values.push(serde_json::json!({ Self::SEQUENCE_KEY: sequence }));
+                Payload::Text(values)
+            }
+            payload => payload,
+        }
+    }
+
+    /// Removes a trailing origin tag from the given event arguments and
+    /// returns the origin it contained, if any.
+    pub(crate) fn take_origin(values: &mut Vec<serde_json::Value>) -> Option<String> {
+        let origin = values
+            .last()
+            .and_then(|value| value.as_object())
+            .filter(|object| object.len() == 1)
+            .and_then(|object| object.get(Self::ORIGIN_KEY))
+            .and_then(|origin| origin.as_str())
+            .map(ToOwned::to_owned);
+
+        if origin.is_some() {
+            values.pop();
+        }
+
+        origin
+    }
+
+    /// The size of the data in bytes, with the arguments of a text payload
+    /// counted as JSON.
+    pub(crate) fn size(&self) -> usize {
+        match self {
+            Payload::Binary(bytes) => bytes.len(),
+            Payload::Text(values) => values
+                .iter()
+                .map(|value| serde_json::to_vec(value).map_or(0, |json| json.len()))
+                .sum(),
+            #[allow(deprecated)]
+            Payload::String(string) => string.len(),
+        }
+    }
 }
 
 impl From<&str> for Payload {
@@ -72,12 +171,54 @@ impl From<Bytes> for Payload {
     }
 }
 
+/// Mirrors [`Payload`] for its serde derives, which would refer to the
+/// deprecated variant otherwise.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Payload")]
+enum SerdePayload {
+    Binary(Bytes),
+    Text(Vec<serde_json::Value>),
+    String(String),
+}
+
+#[cfg(feature = "serde")]
+#[allow(deprecated)]
+impl From<Payload> for SerdePayload {
+    fn from(payload: Payload) -> Self {
+        match payload {
+            Payload::Binary(data) => SerdePayload::Binary(data),
+            Payload::Text(values) => SerdePayload::Text(values),
+            Payload::String(string) => SerdePayload::String(string),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[allow(deprecated)]
+impl From<SerdePayload> for Payload {
+    fn from(payload: SerdePayload) -> Self {
+        match payload {
+            SerdePayload::Binary(data) => Payload::Binary(data),
+            SerdePayload::Text(values) => Payload::Text(values),
+            SerdePayload::String(string) => Payload::String(string),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
 
     use super::*;
 
+    #[test]
+    fn test_size() {
+        assert_eq!(Payload::from(json!({ "a": 1 })).size(), 7);
+        assert_eq!(Payload::Text(vec![json!("ab"), json!(12)]).size(), 6);
+        assert_eq!(Payload::Binary(Bytes::from_static(b"abc")).size(), 3);
+    }
+
     #[test]
     fn test_from_string() {
         let sut = Payload::from("foo ™");
@@ -140,6 +281,29 @@ mod tests {
         assert_eq!(Payload::Text(vec![json]), sut);
     }
 
+    #[test]
+    fn test_json() {
+        #[derive(Serialize)]
+        struct Greeting<'a> {
+            text: &'a str,
+            count: u8,
+        }
+
+        let sut = Payload::json(&Greeting {
+            text: "hi",
+            count: 2,
+        })
+        .unwrap();
+        assert_eq!(Payload::Text(vec![json!({"text": "hi", "count": 2})]), sut);
+
+        // maps need string keys to be represented as JSON
+        let map = std::collections::HashMap::from([((1, 2), "pair")]);
+        assert!(matches!(
+            Payload::json(&map),
+            Err(crate::Error::InvalidJson(_))
+        ));
+    }
+
     #[test]
     fn test_from_binary() {
         let sut = Payload::from(vec![1, 2, 3]);
@@ -151,4 +315,57 @@ mod tests {
         let sut = Payload::from(Bytes::from_static(&[1, 2, 3]));
         assert_eq!(Payload::Binary(Bytes::from_static(&[1, 2, 3])), sut);
     }
+
+    #[test]
+    fn test_sequence_tag() {
+        let sut = Payload::from(json!({"foo": "bar"}))
+            .with_sequence(7)
+            .with_origin("abc");
+        assert_eq!(
+            sut,
+            Payload::Text(vec![
+                json!({"foo": "bar"}),
+                json!({ Payload::SEQUENCE_KEY: 7 }),
+                json!({ Payload::ORIGIN_KEY: "abc" }),
+            ])
+        );
+
+        let sut = Payload::from(vec![1, 2, 3]).with_sequence(7);
+        assert_eq!(sut, Payload::from(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_origin_tag() {
+        let sut = Payload::from(json!({"foo": "bar"})).with_origin("abc");
+        let Payload::Text(mut values) = sut else {
+            panic!("expected a text payload");
+        };
+        assert_eq!(values.len(), 2);
+
+        assert_eq!(Payload::take_origin(&mut values), Some("abc".to_owned()));
+        assert_eq!(values, vec![json!({"foo": "bar"})]);
+
+        // untagged arguments stay untouched
+        assert_eq!(Payload::take_origin(&mut values), None);
+        assert_eq!(values, vec![json!({"foo": "bar"})]);
+
+        let sut = Payload::from(vec![1, 2, 3]).with_origin("abc");
+        assert_eq!(Payload::Binary(Bytes::from_static(&[1, 2, 3])), sut);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        for payload in [
+            Payload::from(json!({"foo": "bar"})),
+            Payload::from(vec![1, 2, 3]),
+        ] {
+            let json = serde_json::to_string(&payload).unwrap();
+            assert_eq!(serde_json::from_str::<Payload>(&json).unwrap(), payload);
+        }
+        assert_eq!(
+            serde_json::to_value(Payload::from(json!("text"))).unwrap(),
+            json!({"Text": ["text"]})
+        );
+    }
 }
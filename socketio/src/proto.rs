@@ -0,0 +1,106 @@
+//! Helpers to send and receive protobuf messages via [`prost`], available with
+//! the `prost` feature.
+//!
+//! A message is framed as binary event, with the full protobuf type name of the
+//! message as argument before its encoded attachment:
+//! `["event","package.Type",{"_placeholder":true,"num":0}]`. Servers and other
+//! clients can use the type name to pick the decoder of the attachment. Like
+//! any other emit, the attachment goes through the codec of the event, and the
+//! sequence number and origin tags follow the type name.
+use prost::{Message, Name};
+
+use crate::{
+    error::{Error, Result},
+    packet::{Packet, PacketId},
+    Event, Payload,
+};
+
+/// The arguments of a protobuf message before its attachment, which the
+/// client tags like the arguments of any other emit.
+pub(crate) fn args<M: Name>() -> Payload {
+    Payload::Text(vec![serde_json::Value::String(M::full_name())])
+}
+
+/// Builds the binary event packet of a protobuf message from its tagged
+/// `args` and the `attachment` the payload codec of the event returned.
+pub(crate) fn packet(
+    event: Event,
+    args: Payload,
+    attachment: Payload,
+    nsp: &str,
+) -> Result<Packet> {
+    let (Payload::Text(args), Payload::Binary(attachment)) = (args, attachment) else {
+        return Err(Error::InvalidPacket());
+    };
+    let mut values = vec![serde_json::Value::String(event.into())];
+    values.extend(args);
+    let args = serde_json::Value::Array(values).to_string();
+
+    Ok(Packet::new(
+        PacketId::BinaryEvent,
+        nsp.to_owned(),
+        // the packet encoding adds the brackets around the arguments
+        Some(args[1..args.len() - 1].to_owned()),
+        None,
+        1,
+        Some(vec![attachment]),
+    ))
+}
+
+/// Decodes the attachment of a received binary event as message `M`.
+pub(crate) fn decode<M: Message + Default>(payload: &Payload) -> Result<M> {
+    match payload {
+        Payload::Binary(data) => Ok(M::decode(data.clone())?),
+        _ => Err(Error::InvalidPacket()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Ping {
+        #[prost(string, tag = "1")]
+        text: String,
+    }
+
+    impl Name for Ping {
+        const NAME: &'static str = "Ping";
+        const PACKAGE: &'static str = "test";
+    }
+
+    #[test]
+    fn test_packet() -> Result<()> {
+        let ping = Ping {
+            text: "hello".to_owned(),
+        };
+        let attachment = Payload::Binary(Bytes::from(ping.encode_to_vec()));
+        let packet = packet(Event::from("ping"), args::<Ping>(), attachment, "/admin")?;
+        assert_eq!(
+            Bytes::from(&packet),
+            Bytes::from_static(
+                b"51-/admin,[\"ping\",\"test.Ping\",{\"_placeholder\":true,\"num\":0}]"
+            )
+        );
+
+        // the receiving side routes the packet to the event and decodes the attachment
+        let mut received = Packet::try_from(Bytes::from(&packet))?;
+        assert_eq!(received.binary_event(), Event::from("ping"));
+        received.attachments = packet.attachments;
+        let attachment = received.attachments.unwrap().remove(0);
+        assert_eq!(decode::<Ping>(&Payload::Binary(attachment))?, ping);
+
+        assert!(matches!(
+            decode::<Ping>(&Payload::Binary(Bytes::from_static(&[0xff]))),
+            Err(Error::InvalidProtobuf(_))
+        ));
+        assert!(matches!(
+            decode::<Ping>(&Payload::Text(vec![])),
+            Err(Error::InvalidPacket())
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The value of a query parameter, see `ClientBuilder::query` and
+/// [`crate::UrlBuilder::query`]. Numbers and booleans are converted the way
+/// servers parse them, e.g. `true` or `1.5`, and the value is percent-encoded
+/// when it's appended to the URL, so that spaces, `&` or unicode arrive as they
+/// were given.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::{QueryValue, UrlBuilder};
+///
+/// let url = UrlBuilder::new("localhost:4200")
+///     .secure(false)
+///     .query("name", "Zoë & co")
+///     .query("limit", 20)
+///     .query("compact", true)
+///     .query("since", QueryValue::display(chrono_like::Date(2024, 1, 31)))
+///     .build()
+///     .expect("invalid url");
+/// assert_eq!(
+///     url.query(),
+///     Some("name=Zo%C3%AB+%26+co&limit=20&compact=true&since=2024-01-31")
+/// );
+/// # mod chrono_like {
+/// #     pub struct Date(pub u16, pub u8, pub u8);
+/// #     impl std::fmt::Display for Date {
+/// #         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// #             write!(f, "{}-{:02}-{:02}", self.0, self.1, self.2)
+/// #         }
+/// #     }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryValue(String);
+
+impl QueryValue {
+    /// Takes the value from the [`Display`] implementation of `value`, for
+    /// types that don't convert into a `QueryValue` themselves.
+    pub fn display<T: Display>(value: T) -> Self {
+        QueryValue(value.to_string())
+    }
+
+    /// The value before it's percent-encoded.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for QueryValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for QueryValue {
+    fn from(value: String) -> Self {
+        QueryValue(value)
+    }
+}
+
+impl From<&String> for QueryValue {
+    fn from(value: &String) -> Self {
+        QueryValue(value.clone())
+    }
+}
+
+impl From<&str> for QueryValue {
+    fn from(value: &str) -> Self {
+        QueryValue(value.to_owned())
+    }
+}
+
+macro_rules! from_display {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for QueryValue {
+                fn from(value: $ty) -> Self {
+                    QueryValue::display(value)
+                }
+            }
+        )*
+    };
+}
+
+from_display!(bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(QueryValue::from(true).as_str(), "true");
+        assert_eq!(QueryValue::from(-3i64).as_str(), "-3");
+        assert_eq!(QueryValue::from(1.5f64).as_str(), "1.5");
+        assert_eq!(QueryValue::from(2.0f32).as_str(), "2");
+        assert_eq!(QueryValue::from("a b").as_str(), "a b");
+        assert_eq!(QueryValue::display('ë').as_str(), "ë");
+    }
+}
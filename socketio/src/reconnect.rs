@@ -0,0 +1,161 @@
+use std::{sync::Arc, time::Duration};
+
+#[cfg(feature = "async")]
+use futures_util::future::BoxFuture;
+use rand::{thread_rng, Rng};
+
+/// Decides how long a client waits between its attempts to reconnect, set via
+/// `ClientBuilder::backoff`. Closures that take the number of the attempt
+/// implement this trait as well.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::ClientBuilder;
+/// use std::time::Duration;
+///
+/// // wait 1s, 2s and 3s, then give up
+/// let builder = ClientBuilder::new("http://localhost:4200/").backoff(|attempt: u32| {
+///     (attempt <= 3).then(|| Duration::from_secs(attempt.into()))
+/// });
+/// ```
+pub trait BackoffStrategy: Send + Sync {
+    /// Returns the delay before the given attempt, counted from 1 for the
+    /// first attempt after the connection dropped. Returning `None` makes the
+    /// client give up reconnecting.
+    fn delay(&self, attempt: u32) -> Option<Duration>;
+}
+
+impl<F> BackoffStrategy for F
+where
+    F: Fn(u32) -> Option<Duration> + Send + Sync,
+{
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        self(attempt)
+    }
+}
+
+/// Waits the same time before every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedBackoff {
+    delay: Duration,
+}
+
+impl FixedBackoff {
+    /// Waits `delay` before every attempt and never gives up, unless limited
+    /// via `ClientBuilder::max_reconnect_attempts`.
+    pub fn new(delay: Duration) -> Self {
+        FixedBackoff { delay }
+    }
+}
+
+impl BackoffStrategy for FixedBackoff {
+    fn delay(&self, _attempt: u32) -> Option<Duration> {
+        Some(self.delay)
+    }
+}
+
+/// Multiplies the delay with every attempt, starting at `initial` and capped at
+/// `max`. The delays are randomized by the jitter, so that clients that lost
+/// their connection at the same time don't reconnect at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl ExponentialBackoff {
+    /// Doubles the delay with every attempt, without a jitter.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        ExponentialBackoff {
+            initial,
+            max,
+            multiplier: 2.0,
+            jitter: 0.0,
+        }
+    }
+
+    /// Sets the factor the delay grows by with every attempt. Defaults to 2.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Randomizes every delay by up to the given fraction in both directions,
+    /// e.g. by up to 50% for `0.5`. Defaults to 0.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl BackoffStrategy for ExponentialBackoff {
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = Duration::try_from_secs_f64(secs)
+            .unwrap_or(self.max)
+            .min(self.max);
+        if self.jitter == 0.0 {
+            return Some(delay);
+        }
+        let factor = 1.0 + thread_rng().gen_range(-self.jitter..=self.jitter);
+        Some(delay.mul_f64(factor))
+    }
+}
+
+/// The progress of a client that reconnects, passed to the handler registered
+/// via `ClientBuilder::on_reconnect_event`. Events the server emits under the
+/// same names are received as [`crate::Event::Custom`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReconnectEvent {
+    /// The client is about to attempt to reconnect for the given time, counted
+    /// from 1.
+    Attempt(u32),
+    /// An attempt to reconnect failed with the given error.
+    Error(String),
+    /// The client reconnected after the given number of attempts.
+    Reconnected(u32),
+}
+
+pub(crate) type ReconnectEventHandler = Arc<dyn Fn(&ReconnectEvent) + Send + Sync>;
+
+#[cfg(feature = "async")]
+pub(crate) type AsyncReconnectEventHandler =
+    Arc<dyn Fn(ReconnectEvent) -> BoxFuture<'static, ()> + Send + Sync>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exponential() {
+        let backoff = ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(1));
+        let delays: Vec<_> = (1..=5)
+            .filter_map(|attempt| backoff.delay(attempt))
+            .collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000].map(Duration::from_millis)
+        );
+        assert_eq!(backoff.delay(u32::MAX), Some(Duration::from_secs(1)));
+
+        let backoff = backoff.multiplier(3.0).jitter(0.5);
+        for _ in 0..100 {
+            let delay = backoff.delay(2).unwrap();
+            assert!(delay >= Duration::from_millis(150) && delay <= Duration::from_millis(450));
+        }
+    }
+
+    #[test]
+    fn test_fixed_and_custom() {
+        let fixed = FixedBackoff::new(Duration::from_millis(250));
+        assert_eq!(fixed.delay(1), fixed.delay(100));
+
+        let custom = |attempt: u32| (attempt <= 2).then_some(Duration::ZERO);
+        assert_eq!(custom.delay(2), Some(Duration::ZERO));
+        assert_eq!(custom.delay(3), None);
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde_json::{Map, Value};
+
+/// The state of a session that a server with connection state recovery (since
+/// socket.io 4.6) restores after a reconnect, so that the client receives the
+/// events it missed while it was disconnected.
+///
+/// The server sends the private id of the session along with the accepted
+/// connection and appends an offset to the events it broadcasts. Both are sent
+/// back in the opening packet of a reconnect, merged into the auth data. Every
+/// client gets a handle of its own when it connects, which survives its
+/// reconnects.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SessionRecovery {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    pid: Option<String>,
+    offset: Option<String>,
+    recovered: bool,
+}
+
+impl SessionRecovery {
+    /// The data of the opening packet: `auth`, with the id and the offset of
+    /// the session added if there is one to recover. The fields of `auth` take
+    /// precedence, data that isn't an object is sent unchanged.
+    pub(crate) fn auth(&self, auth: Option<&Value>) -> Option<String> {
+        let state = self.lock();
+        let Some(pid) = &state.pid else {
+            return auth.map(Value::to_string);
+        };
+
+        let mut data = Map::new();
+        data.insert(String::from("pid"), Value::from(pid.as_str()));
+        if let Some(offset) = &state.offset {
+            data.insert(String::from("offset"), Value::from(offset.as_str()));
+        }
+        match auth {
+            Some(Value::Object(auth)) => data.extend(auth.clone()),
+            Some(auth) => return Some(auth.to_string()),
+            None => {}
+        }
+        Some(Value::Object(data).to_string())
+    }
+
+    /// Records the data of a `CONNECT` packet, i.e. `{"sid": .., "pid": ..}`.
+    pub(crate) fn connected(&self, data: Option<&str>) {
+        let pid = data
+            .and_then(|data| serde_json::from_str::<Value>(data).ok())
+            .and_then(|data| data.get("pid")?.as_str().map(ToOwned::to_owned));
+
+        let mut state = self.lock();
+        state.recovered = pid.is_some() && state.pid == pid;
+        if !state.recovered {
+            state.offset = None;
+        }
+        state.pid = pid;
+    }
+
+    /// Records the offset of a received event, which the server appends as
+    /// the last argument. The argument is passed on to the handlers as is, as
+    /// events sent to the socket directly carry no offset.
+    pub(crate) fn received(&self, args: &[Value]) {
+        let mut state = self.lock();
+        if state.pid.is_none() {
+            return;
+        }
+        if let Some(Value::String(offset)) = args.last() {
+            state.offset = Some(offset.clone());
+        }
+    }
+
+    /// Whether the server restored the session on the last connect.
+    pub(crate) fn recovered(&self) -> bool {
+        self.lock().recovered
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_recover_session() {
+        let recovery = SessionRecovery::default();
+        let auth = json!({ "token": "abc" });
+        assert_eq!(recovery.auth(Some(&auth)), Some(auth.to_string()));
+
+        recovery.connected(Some(r#"{"sid":"a","pid":"p1"}"#));
+        assert!(!recovery.recovered());
+        recovery.received(&[json!("hello"), json!("offset-1")]);
+        recovery.received(&[json!({ "no": "offset" })]);
+
+        let data: Value = serde_json::from_str(&recovery.auth(Some(&auth)).unwrap()).unwrap();
+        assert_eq!(
+            data,
+            json!({ "pid": "p1", "offset": "offset-1", "token": "abc" })
+        );
+
+        recovery.connected(Some(r#"{"sid":"b","pid":"p1"}"#));
+        assert!(recovery.recovered());
+
+        // the server started a new session
+        recovery.connected(Some(r#"{"sid":"c","pid":"p2"}"#));
+        assert!(!recovery.recovered());
+        assert_eq!(
+            recovery.auth(None),
+            Some(json!({ "pid": "p2" }).to_string())
+        );
+
+        // the server doesn't support recovery
+        recovery.connected(Some(r#"{"sid":"d"}"#));
+        assert!(!recovery.recovered());
+        assert_eq!(recovery.auth(None), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Controls what the logs show of the payloads that are sent and received.
+//!
+//! Every emit and every received event is logged at `trace` level together with
+//! a [`RedactedView`] of its payload. Without a hook, the view only describes
+//! the shape of the payload, so that no payload data ends up in the logs. A hook
+//! registered via [`crate::ClientBuilder::redact`] decides per event what is
+//! shown instead, e.g. the payload with secrets masked.
+use std::{
+    fmt::{Debug, Display, Formatter},
+    sync::Arc,
+};
+
+use crate::{Event, Payload};
+
+/// The part of a payload that may appear in logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactedView(String);
+
+impl RedactedView {
+    /// A view that is shown as the given text.
+    pub fn new<T: Into<String>>(view: T) -> Self {
+        RedactedView(view.into())
+    }
+
+    /// The default view, which only describes the shape of the payload, e.g.
+    /// `2 text arguments` or `binary, 12 bytes`.
+    pub fn summary(payload: &Payload) -> Self {
+        match payload {
+            Payload::Binary(data) => RedactedView(format!("binary, {} bytes", data.len())),
+            Payload::Text(values) => RedactedView(format!("{} text arguments", values.len())),
+            #[allow(deprecated)]
+            Payload::String(string) => RedactedView(format!("string, {} bytes", string.len())),
+        }
+    }
+}
+
+impl Display for RedactedView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub(crate) type RedactHook = Arc<dyn Fn(&Event, &Payload) -> RedactedView + Send + Sync>;
+
+/// The redaction of a client, i.e. its hook if one is registered.
+#[derive(Clone, Default)]
+pub(crate) struct Redaction {
+    hook: Option<RedactHook>,
+}
+
+impl Redaction {
+    pub(crate) fn new(hook: RedactHook) -> Self {
+        Redaction { hook: Some(hook) }
+    }
+
+    /// The view of `payload` that may be logged.
+    pub(crate) fn view(&self, event: &Event, payload: &Payload) -> RedactedView {
+        match &self.hook {
+            Some(hook) => hook(event, payload),
+            None => RedactedView::summary(payload),
+        }
+    }
+
+    /// Logs an event that is sent or received, `action` describes which. The
+    /// hook is only called if the log is enabled.
+    pub(crate) fn trace(&self, action: &str, nsp: &str, event: &Event, payload: &Payload) {
+        if log::log_enabled!(log::Level::Trace) {
+            let view = self.view(event, payload);
+            log::trace!("{action} event {event} on namespace {nsp}: {view}");
+        }
+    }
+}
+
+impl Debug for Redaction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Redaction")
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        let payload = Payload::Text(vec![json!({"user": "alice", "password": "secret"})]);
+
+        let view = Redaction::default().view(&Event::from("login"), &payload);
+        assert_eq!(view.to_string(), "1 text arguments");
+        assert_eq!(
+            RedactedView::summary(&Payload::Binary(Bytes::from_static(&[1, 2, 3]))).to_string(),
+            "binary, 3 bytes"
+        );
+
+        let redaction = Redaction::new(Arc::new(|event, payload| match (event, payload) {
+            (Event::Custom(event), Payload::Text(values)) if event == "login" => {
+                RedactedView::new(format!("user {}", values[0]["user"]))
+            }
+            (_, payload) => RedactedView::summary(payload),
+        }));
+        let view = redaction.view(&Event::from("login"), &payload);
+        assert_eq!(view.to_string(), "user \"alice\"");
+        assert!(!view.to_string().contains("secret"));
+    }
+}
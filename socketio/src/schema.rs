@@ -0,0 +1,291 @@
+//! Versioned schemas for the payloads of events, to evolve the contract of an
+//! event between clients and servers that are deployed independently.
+//!
+//! An event is registered with its current version and the type of its
+//! payload. Text emits of the event carry the version as a trailing argument.
+//! Received text payloads are migrated from their version to the current one
+//! and validated against the payload type before they're passed to the
+//! callbacks. Payloads without a version are taken to be of the current
+//! version, e.g. the ones of servers that don't tag them. Binary payloads and
+//! events without a schema are left alone.
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    codec::CodecError,
+    error::{Error, Result},
+    Event, Payload,
+};
+
+type Validator = dyn Fn(&Value) -> std::result::Result<(), CodecError> + Send + Sync;
+type Migration = dyn Fn(Value) -> std::result::Result<Value, CodecError> + Send + Sync;
+
+/// The schemas of events, which are registered on a client builder.
+///
+/// The payload is handled as one JSON value: a single argument is the value
+/// itself, several arguments are an array.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::{ClientBuilder, EventSchemas};
+/// use serde::{Deserialize, Serialize};
+/// use serde_json::json;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Order {
+///     id: u32,
+///     items: Vec<String>,
+/// }
+///
+/// let schemas = EventSchemas::new()
+///     .event::<Order>("order", 2)
+///     // version 1 had a single `item`
+///     .migrate("order", 1, |mut order| {
+///         let item = order.as_object_mut().and_then(|fields| fields.remove("item"));
+///         order["items"] = json!([item]);
+///         Ok(order)
+///     });
+///
+/// let socket = ClientBuilder::new("http://localhost:4200/")
+///     .schemas(schemas)
+///     .connect();
+/// ```
+#[derive(Clone, Default)]
+pub struct EventSchemas {
+    schemas: HashMap<Event, Schema>,
+}
+
+#[derive(Clone)]
+struct Schema {
+    version: u32,
+    validator: Arc<Validator>,
+    migrations: HashMap<u32, Arc<Migration>>,
+}
+
+impl EventSchemas {
+    /// The key of the trailing object that carries the schema version of a
+    /// payload.
+    pub(crate) const VERSION_KEY: &'static str = "__rust_socketio_version";
+
+    /// Creates a registry without any schemas.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `event` with its current `version`, whose payloads have to
+    /// deserialize into `T`. Replaces a previously registered schema of the
+    /// event including its migrations.
+    pub fn event<T: DeserializeOwned>(mut self, event: impl Into<Event>, version: u32) -> Self {
+        self.schemas.insert(
+            event.into(),
+            Schema {
+                version,
+                validator: Arc::new(|value| {
+                    T::deserialize(value)?;
+                    Ok(())
+                }),
+                migrations: HashMap::new(),
+            },
+        );
+        self
+    }
+
+    /// Registers the migration of the payloads of `event` from version `from`
+    /// to `from + 1`. Payloads of older versions are migrated step by step, so
+    /// a migration is needed for every version between the oldest one that is
+    /// accepted and the current one. Has no effect if `event` has no schema.
+    pub fn migrate<F>(mut self, event: impl Into<Event>, from: u32, migration: F) -> Self
+    where
+        F: Fn(Value) -> std::result::Result<Value, CodecError> + Send + Sync + 'static,
+    {
+        if let Some(schema) = self.schemas.get_mut(&event.into()) {
+            schema.migrations.insert(from, Arc::new(migration));
+        }
+        self
+    }
+
+    /// The current version of the schema of `event`, if it has one.
+    pub fn version(&self, event: &Event) -> Option<u32> {
+        self.schemas.get(event).map(|schema| schema.version)
+    }
+
+    /// Tags a text payload of `event` with the current version of its schema.
+    pub(crate) fn tag(&self, event: &Event, payload: Payload) -> Payload {
+        match (self.schemas.get(event), payload) {
+            (Some(schema), Payload::Text(mut values)) => {
+                values.push(serde_json::json!({ Self::VERSION_KEY: schema.version }));
+                Payload::Text(values)
+            }
+            (_, payload) => payload,
+        }
+    }
+
+    /// Migrates the received arguments of `event` to the current version of its
+    /// schema and validates them. Fails with [`Error::InvalidSchemaPayload`] if
+    /// there's no migration from their version or they don't match the schema.
+    pub(crate) fn check(&self, event: &Event, mut values: Vec<Value>) -> Result<Vec<Value>> {
+        let Some(schema) = self.schemas.get(event) else {
+            return Ok(values);
+        };
+        let version = Self::take_version(&mut values).unwrap_or(schema.version);
+        let invalid = |source: CodecError| Error::InvalidSchemaPayload {
+            event: event.clone(),
+            version,
+            source,
+        };
+        if version > schema.version {
+            return Err(invalid(
+                format!("the current version is {}", schema.version).into(),
+            ));
+        }
+
+        let single = values.len() == 1;
+        let mut value = if single {
+            values.remove(0)
+        } else {
+            Value::Array(values)
+        };
+        for from in version..schema.version {
+            let migration = schema
+                .migrations
+                .get(&from)
+                .ok_or_else(|| invalid(format!("no migration from version {from}").into()))?;
+            value = migration(value).map_err(invalid)?;
+        }
+        (schema.validator)(&value).map_err(invalid)?;
+
+        Ok(match value {
+            Value::Array(values) if !single => values,
+            value => vec![value],
+        })
+    }
+
+    /// Removes a trailing version tag from the given event arguments and
+    /// returns the version it contained, if any.
+    fn take_version(values: &mut Vec<Value>) -> Option<u32> {
+        let version = values
+            .last()
+            .and_then(|value| value.as_object())
+            .filter(|object| object.len() == 1)
+            .and_then(|object| object.get(Self::VERSION_KEY))
+            .and_then(|version| version.as_u64())
+            .and_then(|version| u32::try_from(version).ok());
+
+        if version.is_some() {
+            values.pop();
+        }
+
+        version
+    }
+}
+
+impl Debug for EventSchemas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.schemas
+                    .iter()
+                    .map(|(event, schema)| (event, schema.version)),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Order {
+        id: u32,
+        items: Vec<String>,
+    }
+
+    fn schemas() -> EventSchemas {
+        EventSchemas::new()
+            .event::<Order>("order", 3)
+            .migrate("order", 1, |mut order| {
+                let item = order
+                    .as_object_mut()
+                    .and_then(|fields| fields.remove("item"));
+                order["items"] = json!([item]);
+                Ok(order)
+            })
+            .migrate("order", 2, Ok)
+    }
+
+    #[test]
+    fn test_tag() {
+        let schemas = schemas();
+        let order = Event::from("order");
+        assert_eq!(schemas.version(&order), Some(3));
+
+        let sut = schemas.tag(&order, Payload::from(json!({"id": 1})));
+        assert_eq!(
+            sut,
+            Payload::Text(vec![
+                json!({"id": 1}),
+                json!({ EventSchemas::VERSION_KEY: 3 })
+            ])
+        );
+
+        // binary payloads and other events are left alone
+        let binary = Payload::from(vec![1, 2, 3]);
+        assert_eq!(schemas.tag(&order, binary.clone()), binary);
+        let text = Payload::from(json!({"id": 1}));
+        assert_eq!(schemas.tag(&Event::Message, text.clone()), text);
+    }
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let schemas = schemas();
+        let order = Event::from("order");
+        let current = json!({"id": 1, "items": ["tea"]});
+
+        assert_eq!(
+            schemas.check(
+                &order,
+                vec![current.clone(), json!({ EventSchemas::VERSION_KEY: 3 })]
+            )?,
+            vec![current.clone()]
+        );
+        // untagged payloads are of the current version
+        assert_eq!(
+            schemas.check(&order, vec![current.clone()])?,
+            vec![current.clone()]
+        );
+        // old versions are migrated step by step
+        assert_eq!(
+            schemas.check(
+                &order,
+                vec![
+                    json!({"id": 1, "item": "tea"}),
+                    json!({ EventSchemas::VERSION_KEY: 1 })
+                ]
+            )?,
+            vec![current.clone()]
+        );
+
+        for values in [
+            vec![json!({"id": "one"})],
+            vec![current.clone(), json!({ EventSchemas::VERSION_KEY: 0 })],
+            vec![current.clone(), json!({ EventSchemas::VERSION_KEY: 4 })],
+        ] {
+            assert!(matches!(
+                schemas.check(&order, values),
+                Err(Error::InvalidSchemaPayload { .. })
+            ));
+        }
+
+        // other events are left alone
+        let values = vec![json!(1), json!({ EventSchemas::VERSION_KEY: 1 })];
+        assert_eq!(schemas.check(&Event::Message, values.clone())?, values);
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures_util::{stream::BoxStream, StreamExt};
+use rand::{thread_rng, Rng};
+use tokio::sync::broadcast;
+
+use super::Registered;
+use crate::{error::Result, Event, Payload};
+
+/// Connects the servers of a cluster, so that broadcasts reach the sockets
+/// connected to the other servers, like the adapters of the JavaScript server.
+/// An adapter is set via [`super::ServerBuilder::adapter`], without one the
+/// broadcasts and rooms are kept in the memory of the server.
+///
+/// Adapters only move [`ClusterMessage`]s between the servers: every
+/// broadcast, and every change of the rooms of the sockets a broadcast
+/// targets, is published to all servers, which apply it to the sockets
+/// connected to them. The rooms of a socket are kept by the server it's
+/// connected to. [`MemoryAdapter`] connects the servers of one process,
+/// [`super::RedisAdapter`] the ones sharing a Redis server (behind the
+/// `redis` feature flag).
+#[async_trait]
+pub trait Adapter: Send + Sync {
+    /// Publishes `message` to the servers of the cluster. Whether the
+    /// publishing server receives the message as well doesn't matter.
+    async fn publish(&self, message: ClusterMessage) -> Result<()>;
+
+    /// The messages published by the servers of the cluster. Called once as
+    /// the server starts, the server stops receiving broadcasts of the other
+    /// servers once the stream ends.
+    async fn subscribe(&self) -> Result<BoxStream<'static, ClusterMessage>>;
+}
+
+#[async_trait]
+impl<A: Adapter + ?Sized> Adapter for Arc<A> {
+    async fn publish(&self, message: ClusterMessage) -> Result<()> {
+        (**self).publish(message).await
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, ClusterMessage>> {
+        (**self).subscribe().await
+    }
+}
+
+/// A broadcast published by a server of the cluster, see [`Adapter`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ClusterMessage {
+    /// The id of the server that published the message.
+    pub node: String,
+    /// The namespace of the targeted sockets.
+    pub nsp: String,
+    /// The targeted rooms, all sockets of the namespace if empty.
+    pub rooms: Vec<String>,
+    /// The rooms whose sockets are left out.
+    pub except: Vec<String>,
+    pub operation: Operation,
+}
+
+impl ClusterMessage {
+    /// Creates a message that targets all sockets of `nsp`.
+    pub fn new<I, N>(node: I, nsp: N, operation: Operation) -> Self
+    where
+        I: Into<String>,
+        N: Into<String>,
+    {
+        ClusterMessage {
+            node: node.into(),
+            nsp: nsp.into(),
+            rooms: Vec::new(),
+            except: Vec::new(),
+            operation,
+        }
+    }
+}
+
+/// What a [`ClusterMessage`] does to the sockets it targets.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Sends the payload as the event.
+    Emit(Event, Payload),
+    /// Makes the sockets join the room.
+    Join(String),
+    /// Makes the sockets leave the room.
+    Leave(String),
+    /// Disconnects the sockets from the namespace, and closes the connection
+    /// of their clients if `true`.
+    Disconnect(bool),
+}
+
+/// An [`Adapter`] that connects the servers of one process, e.g. to test a
+/// cluster. Clones share the servers.
+#[derive(Debug, Clone)]
+pub struct MemoryAdapter {
+    messages: broadcast::Sender<ClusterMessage>,
+}
+
+impl MemoryAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for MemoryAdapter {
+    fn default() -> Self {
+        MemoryAdapter {
+            messages: broadcast::channel(1024).0,
+        }
+    }
+}
+
+#[async_trait]
+impl Adapter for MemoryAdapter {
+    async fn publish(&self, message: ClusterMessage) -> Result<()> {
+        // fails only if no server subscribed
+        let _ = self.messages.send(message);
+        Ok(())
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, ClusterMessage>> {
+        let mut messages = self.messages.subscribe();
+        Ok(stream! {
+            loop {
+                match messages.recv().await {
+                    Ok(message) => yield message,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Skipped {skipped} broadcasts of the other servers");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+        .boxed())
+    }
+}
+
+/// The adapter of a server, along with the id that tells the broadcasts of
+/// the server apart from the ones of the other servers.
+#[derive(Clone)]
+pub(super) struct Cluster {
+    node: String,
+    adapter: Arc<dyn Adapter>,
+}
+
+impl Cluster {
+    pub(super) fn new(adapter: Arc<dyn Adapter>) -> Self {
+        Cluster {
+            node: format!("{:016x}", thread_rng().gen::<u64>()),
+            adapter,
+        }
+    }
+
+    pub(super) async fn subscribe(&self) -> Result<BoxStream<'static, ClusterMessage>> {
+        self.adapter.subscribe().await
+    }
+
+    /// Publishes a broadcast of this server to the other servers.
+    pub(super) async fn publish(
+        &self,
+        nsp: &str,
+        rooms: &[String],
+        except: &[String],
+        operation: Operation,
+    ) -> Result<()> {
+        let message = ClusterMessage {
+            node: self.node.clone(),
+            nsp: nsp.to_owned(),
+            rooms: rooms.to_vec(),
+            except: except.to_vec(),
+            operation,
+        };
+        self.adapter.publish(message).await
+    }
+
+    /// Applies the broadcasts of the other servers to the sockets connected
+    /// to this server, until the subscription ends.
+    pub(super) async fn receive(
+        self,
+        mut messages: BoxStream<'static, ClusterMessage>,
+        namespaces: Arc<HashMap<String, Registered>>,
+    ) {
+        while let Some(message) = messages.next().await {
+            if message.node == self.node {
+                continue;
+            }
+            let Some((_, namespace)) = namespaces.get(&message.nsp) else {
+                continue;
+            };
+            let applied = namespace
+                .apply(&message.rooms, &message.except, &message.operation)
+                .await;
+            if let Err(err) = applied {
+                log::warn!("Ignoring a broadcast of another server: {err}");
+            }
+        }
+        log::warn!("The subscription of the adapter ended");
+    }
+}
+
+impl Debug for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cluster").field("node", &self.node).finish()
+    }
+}
@@ -0,0 +1,473 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use async_stream::stream;
+use futures_util::{stream, FutureExt, StreamExt};
+use serde_json::{json, Map, Value};
+use tokio::sync::mpsc;
+
+use super::{
+    middleware::Middleware, Broadcast, ConnectError, ConnectionHandler, Namespace, ServerSocket,
+};
+use crate::Payload;
+
+/// What the dashboard may do to the sockets, unless it's read-only.
+const FEATURES: [&str; 7] = [
+    "EMIT",
+    "JOIN",
+    "LEAVE",
+    "DISCONNECT",
+    "MJOIN",
+    "MLEAVE",
+    "MDISCONNECT",
+];
+
+/// Instruments the server for the [socket.io Admin UI](https://socket.io/docs/v4/admin-ui/),
+/// like `instrument` of `@socket.io/admin-ui`, see
+/// [`super::ServerBuilder::admin_ui`].
+///
+/// The dashboard connects to the namespace `/admin`, lists the sockets and
+/// rooms of the other namespaces along with the statistics of the server, and
+/// can make sockets join or leave rooms, disconnect them or emit to them.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::server::{AdminUi, ServerBuilder};
+///
+/// let builder = ServerBuilder::new().admin_ui(AdminUi::new("admin", "secret"));
+/// ```
+#[derive(Clone)]
+pub struct AdminUi {
+    nsp: String,
+    credentials: Option<(String, String)>,
+    read_only: bool,
+    server_id: Option<String>,
+    stats_interval: Duration,
+}
+
+impl AdminUi {
+    /// Instruments the server for dashboards that log in with `username` and
+    /// `password`, reporting the statistics every 2 seconds like the
+    /// JavaScript server. Unlike the JavaScript server, the password isn't a
+    /// bcrypt hash.
+    pub fn new<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self::with_credentials(Some((username.into(), password.into())))
+    }
+
+    /// Instruments the server without authentication, so that any client
+    /// that connects to the namespace of the dashboard can act on the sockets
+    /// unless it's [`read_only`](AdminUi::read_only). Only meant for servers
+    /// that can't be reached from untrusted networks.
+    pub fn no_auth() -> Self {
+        Self::with_credentials(None)
+    }
+
+    fn with_credentials(credentials: Option<(String, String)>) -> Self {
+        AdminUi {
+            nsp: "/admin".to_owned(),
+            credentials,
+            read_only: false,
+            server_id: None,
+            stats_interval: Duration::from_secs(2),
+        }
+    }
+
+    /// Sets the namespace the dashboard connects to, `/admin` by default.
+    pub fn namespace<N: Into<String>>(mut self, namespace: N) -> Self {
+        let mut nsp = namespace.into();
+        if !nsp.starts_with('/') {
+            nsp = "/".to_owned() + &nsp;
+        }
+        self.nsp = nsp;
+        self
+    }
+
+    /// Only lets the dashboard watch the sockets, not act on them.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Sets the id the dashboard tells the servers of a cluster apart by,
+    /// `{hostname}#{pid}` by default.
+    pub fn server_id<I: Into<String>>(mut self, id: I) -> Self {
+        self.server_id = Some(id.into());
+        self
+    }
+
+    /// Sets how often the statistics of the server are reported.
+    pub fn stats_interval(mut self, interval: Duration) -> Self {
+        self.stats_interval = interval;
+        self
+    }
+
+    pub(super) fn name(&self) -> &str {
+        &self.nsp
+    }
+}
+
+impl Debug for AdminUi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminUi")
+            .field("nsp", &self.nsp)
+            .field("username", &self.credentials.as_ref().map(|(name, _)| name))
+            .field("read_only", &self.read_only)
+            .field("server_id", &self.server_id)
+            .field("stats_interval", &self.stats_interval)
+            .finish()
+    }
+}
+
+/// A change of the sockets of a monitored namespace.
+#[derive(Debug)]
+pub(super) enum Change {
+    Connected(ServerSocket),
+    Disconnected {
+        nsp: String,
+        id: String,
+        reason: String,
+    },
+    Joined {
+        nsp: String,
+        room: String,
+        id: String,
+    },
+    Left {
+        nsp: String,
+        room: String,
+        id: String,
+    },
+}
+
+/// Passes the changes of a namespace on to the dashboards.
+#[derive(Debug, Clone)]
+pub(super) struct Monitor {
+    changes: mpsc::UnboundedSender<Change>,
+}
+
+impl Monitor {
+    pub(super) fn channel() -> (Self, mpsc::UnboundedReceiver<Change>) {
+        let (changes, receiver) = mpsc::unbounded_channel();
+        (Monitor { changes }, receiver)
+    }
+
+    pub(super) fn report(&self, change: Change) {
+        // fails only once the server stopped
+        let _ = self.changes.send(change);
+    }
+}
+
+/// The namespace of the dashboards and the namespaces they watch.
+pub(super) struct Admin {
+    options: AdminUi,
+    namespace: Namespace,
+    monitored: Vec<Namespace>,
+    started: Instant,
+}
+
+impl Admin {
+    pub(super) fn new(options: AdminUi, namespace: Namespace, monitored: Vec<Namespace>) -> Self {
+        Admin {
+            options,
+            namespace,
+            monitored,
+            started: Instant::now(),
+        }
+    }
+
+    /// Checks the credentials of the dashboards, the connections to other
+    /// namespaces pass.
+    pub(super) fn middleware(&self) -> Middleware {
+        let nsp = self.options.nsp.clone();
+        let credentials = self.options.credentials.clone();
+        Arc::new(move |handshake| {
+            let result = match &credentials {
+                Some((username, password)) if handshake.namespace() == nsp => {
+                    let auth = handshake.auth().cloned().unwrap_or_default();
+                    match (auth["username"].as_str(), auth["password"].as_str()) {
+                        // both are compared in full, so that the time taken
+                        // doesn't tell which part of the credentials matched
+                        (Some(name), Some(secret))
+                            if constant_time_eq(name, username)
+                                & constant_time_eq(secret, password) =>
+                        {
+                            Ok(())
+                        }
+                        _ => Err(ConnectError::new("invalid credentials")),
+                    }
+                }
+                _ => Ok(()),
+            };
+            async move { result }.boxed()
+        })
+    }
+
+    /// Sends a dashboard that connected the features, sockets and statistics
+    /// of the server, and registers the handlers of its actions.
+    pub(super) fn on_connection(self: &Arc<Self>) -> ConnectionHandler {
+        let admin = self.clone();
+        Arc::new(move |socket: ServerSocket| {
+            let admin = admin.clone();
+            async move {
+                let features: &[&str] = match admin.options.read_only {
+                    true => &[],
+                    false => &FEATURES,
+                };
+                if !admin.options.read_only {
+                    admin.register_actions(&socket);
+                }
+                let mut sockets = Vec::new();
+                for namespace in &admin.monitored {
+                    for socket in namespace.sockets() {
+                        sockets.push(serialize(&socket).await);
+                    }
+                }
+                let stats = admin.stats().await;
+                // the dashboard disconnects meanwhile at worst
+                let _ = socket
+                    .emit("config", json!({ "supportedFeatures": features }))
+                    .await;
+                let _ = socket.emit("all_sockets", Value::Array(sockets)).await;
+                let _ = socket.emit("server_stats", stats).await;
+            }
+            .boxed()
+        })
+    }
+
+    fn register_actions(self: &Arc<Self>, socket: &ServerSocket) {
+        let admin = self.clone();
+        socket.on("_join", move |payload, _, _| {
+            let target = admin.target(&payload, 2);
+            async move {
+                if let (Some(target), Some(room)) = (target, arg(&payload, 1)) {
+                    log_failure(target.join(room).await);
+                }
+            }
+            .boxed()
+        });
+        let admin = self.clone();
+        socket.on("_leave", move |payload, _, _| {
+            let target = admin.target(&payload, 2);
+            async move {
+                if let (Some(target), Some(room)) = (target, arg(&payload, 1)) {
+                    log_failure(target.leave(room).await);
+                }
+            }
+            .boxed()
+        });
+        let admin = self.clone();
+        socket.on("_disconnect", move |payload, _, _| {
+            let target = admin.target(&payload, 2);
+            let close =
+                matches!(&payload, Payload::Text(args) if args.get(1) == Some(&json!(true)));
+            async move {
+                if let Some(target) = target {
+                    log_failure(target.disconnect(close).await);
+                }
+            }
+            .boxed()
+        });
+        let admin = self.clone();
+        socket.on("_emit", move |payload, _, _| {
+            let target = admin.target(&payload, 1);
+            async move {
+                let Payload::Text(args) = payload else {
+                    return;
+                };
+                if let (Some(target), Some(Value::String(event))) = (target, args.get(2)) {
+                    let data = Payload::Text(args.get(3..).unwrap_or_default().to_vec());
+                    log_failure(target.emit(event.as_str(), data).await);
+                }
+            }
+            .boxed()
+        });
+    }
+
+    /// The sockets an action of the dashboard targets: the namespace is the
+    /// first argument, the filter the one at `filter`, which is a room, a list
+    /// of rooms or `null` for all sockets.
+    fn target(&self, payload: &Payload, filter: usize) -> Option<Broadcast> {
+        let nsp = arg(payload, 0)?;
+        let namespace = self
+            .monitored
+            .iter()
+            .find(|namespace| namespace.name() == nsp)?;
+        let Payload::Text(args) = payload else {
+            return None;
+        };
+        let rooms = match args.get(filter) {
+            Some(Value::String(room)) => vec![room.as_str()],
+            Some(Value::Array(rooms)) => rooms.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        Some(
+            rooms
+                .into_iter()
+                .fold(Broadcast::new(namespace.clone()), Broadcast::to),
+        )
+    }
+
+    /// Passes the changes of the monitored namespaces on to the dashboards and
+    /// reports the statistics, until the server stops.
+    pub(super) async fn run(self: Arc<Self>, mut changes: mpsc::UnboundedReceiver<Change>) {
+        let interval = self.options.stats_interval;
+        let ticks = stream! {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                yield None;
+            }
+        };
+        let changes = stream! {
+            while let Some(change) = changes.recv().await {
+                yield Some(change);
+            }
+        };
+        let mut events = stream::select(ticks.boxed(), changes.boxed());
+        while let Some(event) = events.next().await {
+            let (event, args) = match event {
+                None => ("server_stats", vec![self.stats().await]),
+                Some(Change::Connected(socket)) => (
+                    "socket_connected",
+                    vec![serialize(&socket).await, json!(socket.namespace())],
+                ),
+                Some(Change::Disconnected { nsp, id, reason }) => (
+                    "socket_disconnected",
+                    vec![json!(nsp), json!(id), json!(reason)],
+                ),
+                Some(Change::Joined { nsp, room, id }) => {
+                    ("room_joined", vec![json!(nsp), json!(room), json!(id)])
+                }
+                Some(Change::Left { nsp, room, id }) => {
+                    ("room_left", vec![json!(nsp), json!(room), json!(id)])
+                }
+            };
+            log_failure(self.namespace.emit(event, Payload::Text(args)).await);
+        }
+    }
+
+    /// The statistics of the server, as `server_stats` of the JavaScript
+    /// server reports them.
+    async fn stats(&self) -> Value {
+        let hostname = hostname();
+        let server_id = match &self.options.server_id {
+            Some(id) => id.clone(),
+            None => format!("{hostname}#{}", std::process::id()),
+        };
+        let namespaces = self.monitored.iter().chain([&self.namespace]);
+        let mut sessions = HashMap::new();
+        let mut counts = Vec::new();
+        for namespace in namespaces {
+            let sockets = namespace.sockets();
+            counts.push(json!({ "name": namespace.name(), "socketsCount": sockets.len() }));
+            for socket in sockets {
+                let session = &socket.inner.session;
+                sessions.insert(session.sid().to_owned(), session.clone());
+            }
+        }
+        let mut polling = 0;
+        for session in sessions.values() {
+            if !session.is_websocket().await {
+                polling += 1;
+            }
+        }
+        json!({
+            "serverId": server_id,
+            "hostname": hostname,
+            "pid": std::process::id(),
+            "uptime": self.started.elapsed().as_secs_f64(),
+            "clientsCount": sessions.len(),
+            "pollingClientsCount": polling,
+            "aggregatedEvents": [],
+            "namespaces": counts,
+        })
+    }
+}
+
+impl Debug for Admin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Admin")
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+/// A socket as the dashboard lists it.
+async fn serialize(socket: &ServerSocket) -> Value {
+    let handshake = socket.handshake();
+    let headers: Map<String, Value> = handshake
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), json!(value.to_str().ok()?))))
+        .collect();
+    let query: Map<String, Value> =
+        url::form_urlencoded::parse(handshake.uri().query().unwrap_or_default().as_bytes())
+            .map(|(key, value)| (key.into_owned(), json!(value)))
+            .collect();
+    // the address of the peer isn't known, only the one of a proxy in front
+    let address = ["cf-connecting-ip", "x-forwarded-for"]
+        .iter()
+        .find_map(|name| headers.get(*name).cloned())
+        .unwrap_or_default();
+    let issued = handshake
+        .issued()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |issued| issued.as_millis() as u64);
+    let session = &socket.inner.session;
+    let transport = match session.is_websocket().await {
+        true => "websocket",
+        false => "polling",
+    };
+    json!({
+        "id": socket.id(),
+        "clientId": session.sid(),
+        "transport": transport,
+        "nsp": socket.namespace(),
+        "data": {},
+        "handshake": {
+            "address": address,
+            "headers": headers,
+            "query": query,
+            "issued": issued,
+            "url": handshake.uri().to_string(),
+            "auth": handshake.auth().cloned().unwrap_or_else(|| json!({})),
+        },
+        "rooms": socket.rooms(),
+    })
+}
+
+/// The string argument at `index` of an action of the dashboard.
+fn arg(payload: &Payload, index: usize) -> Option<String> {
+    match payload {
+        Payload::Text(args) => args.get(index)?.as_str().map(str::to_owned),
+        _ => None,
+    }
+}
+
+fn log_failure(result: crate::error::Result<()>) {
+    if let Err(err) = result {
+        log::warn!("The admin UI failed to reach the sockets: {err}");
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_owned())
+}
+
+/// Compares two strings in a time that only depends on their lengths.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
@@ -0,0 +1,140 @@
+use std::{future::Future, marker::PhantomData};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use serde::de::DeserializeOwned;
+
+use super::{AckResponder, ServerSocket};
+use crate::{asynchronous::deserialize, Error, Payload};
+
+/// What a handler registered via [`ServerSocket::handle`] is called with: the
+/// payload of the event, the socket and the ack the client may have
+/// requested.
+#[derive(Debug)]
+pub struct EventParts {
+    payload: Payload,
+    socket: ServerSocket,
+    ack: Option<AckResponder>,
+}
+
+impl EventParts {
+    /// The payload of the event.
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    /// The socket that received the event.
+    pub fn socket(&self) -> &ServerSocket {
+        &self.socket
+    }
+}
+
+/// An argument of a handler registered via [`ServerSocket::handle`], which is
+/// extracted from the event before the handler is called, like the
+/// extractors of `axum`.
+///
+/// Implemented by [`Data`] for the deserialized payload, by [`Payload`] for
+/// the raw one, by [`ServerSocket`] for the socket and by [`AckResponder`]
+/// for the ack.
+pub trait FromEvent: Sized {
+    /// Extracts the argument. The handler isn't called if this fails.
+    fn from_event(parts: &mut EventParts) -> Result<Self, Error>;
+}
+
+/// The payload of an event deserialized into `T`. A payload with a single
+/// value is deserialized from that value, one with several values from the
+/// array of them, e.g. into a tuple.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Data<T>(pub T);
+
+impl<T: DeserializeOwned> FromEvent for Data<T> {
+    fn from_event(parts: &mut EventParts) -> Result<Self, Error> {
+        deserialize(parts.payload.clone()).map(Data)
+    }
+}
+
+impl FromEvent for Payload {
+    fn from_event(parts: &mut EventParts) -> Result<Self, Error> {
+        Ok(parts.payload.clone())
+    }
+}
+
+impl FromEvent for ServerSocket {
+    fn from_event(parts: &mut EventParts) -> Result<Self, Error> {
+        Ok(parts.socket.clone())
+    }
+}
+
+impl FromEvent for AckResponder {
+    /// Only the first ack argument of a handler answers the client.
+    fn from_event(parts: &mut EventParts) -> Result<Self, Error> {
+        Ok(parts.ack.take().unwrap_or_else(|| AckResponder {
+            socket: parts.socket.clone(),
+            id: None,
+        }))
+    }
+}
+
+/// A function whose arguments are all [`FromEvent`], see
+/// [`ServerSocket::handle`]. `Args` is the tuple of the argument types.
+pub trait Handler<Args>: Send + Sync + 'static {
+    /// Extracts the arguments and calls the function.
+    fn call(&self, parts: EventParts) -> Result<BoxFuture<'static, ()>, Error>;
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),*) => {
+        impl<F, Fut, $($arg,)*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = ()> + Send + 'static,
+            $($arg: FromEvent,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, mut parts: EventParts) -> Result<BoxFuture<'static, ()>, Error> {
+                $(let $arg = $arg::from_event(&mut parts)?;)*
+                Ok(self($($arg),*).boxed())
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(A);
+impl_handler!(A, B);
+impl_handler!(A, B, C);
+impl_handler!(A, B, C, D);
+
+/// Adapts a [`Handler`] to the handlers of [`ServerSocket::on`].
+pub(super) struct Extracting<H, Args> {
+    handler: H,
+    args: PhantomData<fn() -> Args>,
+}
+
+impl<H: Handler<Args>, Args> Extracting<H, Args> {
+    pub(super) fn new(handler: H) -> Self {
+        Extracting {
+            handler,
+            args: PhantomData,
+        }
+    }
+
+    pub(super) fn call(
+        &self,
+        payload: Payload,
+        socket: ServerSocket,
+        ack: AckResponder,
+    ) -> BoxFuture<'static, ()> {
+        let parts = EventParts {
+            payload,
+            socket,
+            ack: Some(ack),
+        };
+        match self.handler.call(parts) {
+            Ok(future) => future,
+            Err(err) => {
+                log::warn!("Skipping an event whose arguments can't be extracted: {err}");
+                Box::pin(async {})
+            }
+        }
+    }
+}
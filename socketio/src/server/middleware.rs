@@ -0,0 +1,106 @@
+use std::{fmt::Display, sync::Arc, time::SystemTime};
+
+use futures_util::future::BoxFuture;
+use http::{HeaderMap, Uri};
+use serde_json::{json, Value};
+
+/// Called for every connection to a namespace before it's accepted.
+pub(super) type Middleware = Arc<
+    dyn Fn(Handshake) -> BoxFuture<'static, std::result::Result<(), ConnectError>> + Send + Sync,
+>;
+
+/// What a client sent to connect to a namespace, as passed to the middlewares
+/// of [`super::ServerBuilder::middleware`] and kept by the
+/// [`super::ServerSocket`].
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    pub(super) nsp: String,
+    pub(super) auth: Option<Value>,
+    pub(super) uri: Uri,
+    pub(super) headers: HeaderMap,
+    pub(super) issued: SystemTime,
+}
+
+impl Handshake {
+    /// The namespace the client connects to.
+    pub fn namespace(&self) -> &str {
+        &self.nsp
+    }
+
+    /// The auth data the client sent along with its connection.
+    pub fn auth(&self) -> Option<&Value> {
+        self.auth.as_ref()
+    }
+
+    /// The URI the client requested to open its session, including the query.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The headers of the request that opened the session of the client.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// When the client connected to the namespace.
+    pub fn issued(&self) -> SystemTime {
+        self.issued
+    }
+
+    /// The decoded value of the query parameter `key`, if the client sent it.
+    pub fn query(&self, key: &str) -> Option<String> {
+        url::form_urlencoded::parse(self.uri.query()?.as_bytes())
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+/// Refuses a connection in a middleware. The client receives a
+/// `CONNECT_ERROR` packet with the message and the data, like the `Error` a
+/// middleware of the JavaScript server passes to `next`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectError {
+    message: String,
+    data: Option<Value>,
+}
+
+impl ConnectError {
+    /// Refuses the connection with `message`.
+    pub fn new<M: Into<String>>(message: M) -> Self {
+        ConnectError {
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Sends `data` along with the message, e.g. to tell the client how to
+    /// recover.
+    pub fn with_data<D: Into<Value>>(mut self, data: D) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// The message the client receives.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The data the client receives along with the message.
+    pub fn data(&self) -> Option<&Value> {
+        self.data.as_ref()
+    }
+
+    /// The payload of the `CONNECT_ERROR` packet.
+    pub(super) fn to_json(&self) -> Value {
+        match &self.data {
+            Some(data) => json!({ "message": self.message, "data": data }),
+            None => json!({ "message": self.message }),
+        }
+    }
+}
+
+impl Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
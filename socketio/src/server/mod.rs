@@ -0,0 +1,1775 @@
+//! A `socket.io` server, so that Rust backends can serve socket.io clients
+//! without a Node.js server in between.
+//!
+//! The server accepts the `engine.io` sessions of [`rust_engineio::server`],
+//! via polling or websocket. Clients connect to the namespaces registered via
+//! [`ServerBuilder::namespace`], whose handler then registers the event
+//! handlers of the new [`ServerSocket`]. Connections to other namespaces are
+//! refused.
+//!
+//! Middlewares registered via [`ServerBuilder::middleware`] run before a
+//! connection is accepted, e.g. to check the credentials of the client, and
+//! can refuse it with a [`ConnectError`]. The [`Handshake`] they check stays
+//! available to the handlers via [`ServerSocket::handshake`].
+//!
+//! Sockets can [`ServerSocket::join`] rooms, [`Server::of`] and
+//! [`ServerSocket::to`] then broadcast events to the sockets in a room, like
+//! `io.to(room).emit(..)` of the JavaScript server. The rooms are kept in
+//! memory, so they span the sockets of one server process only, unless the
+//! servers of a cluster are connected via an [`Adapter`], e.g. the
+//! [`RedisAdapter`] (behind the `redis` feature flag), which also connects
+//! them to the JavaScript servers using the Redis adapter.
+//!
+//! The server can be watched and managed from the dashboard of the socket.io
+//! Admin UI, see [`ServerBuilder::admin_ui`].
+//!
+//! Instead of listening on an address of its own, the server can be mounted
+//! into the router of an `axum` or `hyper` application via
+//! [`ServerBuilder::build`] and [`Server::layer`] or [`Server::service`]
+//! (behind the `hyper` feature flag).
+//!
+//! Instead of taking the raw payload, event handlers registered via
+//! [`ServerSocket::handle`] declare what they need as arguments, e.g. the
+//! payload deserialized via [`Data`], the socket and the [`AckResponder`].
+//!
+//! Every event is handled on its own task, so that handlers can wait for the
+//! acks of their own emits. The connection handler runs before the events of
+//! the client are dispatched, it must not wait for acks.
+//!
+//! # Example
+//! ```rust
+//! use futures_util::FutureExt;
+//! use rust_socketio::{server::ServerBuilder, Payload};
+//! use serde_json::json;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let server = ServerBuilder::new()
+//!         .namespace("/", |socket| {
+//!             async move {
+//!                 let _ = socket.emit("welcome", json!({ "id": socket.id() })).await;
+//!                 socket.on("echo", |payload, _, ack| {
+//!                     async move {
+//!                         let _ = ack.send(payload).await;
+//!                     }
+//!                     .boxed()
+//!                 });
+//!             }
+//!             .boxed()
+//!         })
+//!         .bind("127.0.0.1:0")
+//!         .await
+//!         .expect("address in use");
+//!     println!("listening on {}", server.local_addr().unwrap());
+//!     tokio::spawn(server.serve());
+//! }
+//! ```
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    net::SocketAddr,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use futures_util::{
+    future::{self, BoxFuture},
+    stream::BoxStream,
+    StreamExt,
+};
+use http::{HeaderMap, Uri};
+use rand::{thread_rng, Rng};
+use rust_engineio::{
+    server::{
+        Server as EngineServer, ServerBuilder as EngineServerBuilder, ServerSocket as EngineSocket,
+    },
+    Packet as EnginePacket, PacketId as EnginePacketId,
+};
+use serde_json::{json, Value};
+use tokio::{
+    net::ToSocketAddrs,
+    sync::{mpsc, oneshot},
+};
+
+use crate::{
+    error::Result,
+    packet::{Packet, PacketId},
+    Error, Event, Payload, Utf8Policy,
+};
+
+mod adapter;
+mod admin;
+mod extract;
+mod middleware;
+#[cfg(feature = "redis")]
+mod msgpack;
+#[cfg(feature = "redis")]
+mod redis;
+mod rooms;
+
+use adapter::Cluster;
+pub use adapter::{Adapter, ClusterMessage, MemoryAdapter, Operation};
+pub use admin::AdminUi;
+use admin::{Admin, Change, Monitor};
+use extract::Extracting;
+pub use extract::{Data, EventParts, FromEvent, Handler};
+use middleware::Middleware;
+pub use middleware::{ConnectError, Handshake};
+#[cfg(feature = "redis")]
+pub use redis::RedisAdapter;
+pub use rooms::{Broadcast, Namespace};
+#[cfg(feature = "hyper")]
+pub use rust_engineio::server::{EngineLayer, EngineRouter, EngineService};
+
+/// Called for every socket that connects to a namespace.
+type ConnectionHandler = Arc<dyn Fn(ServerSocket) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// The handler of a registered namespace and its sockets.
+type Registered = (ConnectionHandler, Namespace);
+
+/// Called for the events of a socket.
+type EventHandler =
+    Arc<dyn Fn(Payload, ServerSocket, AckResponder) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Configures and starts a [`Server`], see the [module documentation](self).
+#[derive(Default)]
+pub struct ServerBuilder {
+    engine: EngineServerBuilder,
+    namespaces: HashMap<String, ConnectionHandler>,
+    middlewares: Vec<Middleware>,
+    adapter: Option<Arc<dyn Adapter>>,
+    admin: Option<AdminUi>,
+    shutdown_notice: Option<(Event, Payload)>,
+}
+
+impl ServerBuilder {
+    /// Creates a builder without namespaces and with the heartbeat defaults
+    /// of the JavaScript server.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the interval of the heartbeat pings and the time the client has to
+    /// answer them before it's disconnected.
+    pub fn ping(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.engine = self.engine.ping(interval, timeout);
+        self
+    }
+
+    /// Limits the size of the packets a client may send, larger ones close the
+    /// connection. Defaults to 1 MB.
+    pub fn max_payload(mut self, bytes: usize) -> Self {
+        self.engine = self.engine.max_payload(bytes);
+        self
+    }
+
+    /// Accepts connections to `namespace`. `on_connection` is called with
+    /// every socket that connects and registers its event handlers. It runs
+    /// on its own task once it awaits, so it may await round-trips to the
+    /// client, like [`ServerSocket::emit_with_ack`].
+    pub fn namespace<N, F>(mut self, namespace: N, on_connection: F) -> Self
+    where
+        N: Into<String>,
+        F: Fn(ServerSocket) -> BoxFuture<'static, ()> + Send + Sync + 'static,
+    {
+        let mut nsp = namespace.into();
+        if !nsp.starts_with('/') {
+            nsp = "/".to_owned() + &nsp;
+        }
+        self.namespaces.insert(nsp, Arc::new(on_connection));
+        self
+    }
+
+    /// Adds a middleware that runs for every connection to a namespace before
+    /// it's accepted, like `io.use` of the JavaScript server. The middlewares
+    /// run in the order they were added, the first one that returns an error
+    /// refuses the connection and the client receives the [`ConnectError`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_util::FutureExt;
+    /// use rust_socketio::server::{ConnectError, ServerBuilder};
+    ///
+    /// let builder = ServerBuilder::new().middleware(|handshake| {
+    ///     let token = handshake.auth().and_then(|auth| auth["token"].as_str());
+    ///     let result = match token {
+    ///         Some("secret") => Ok(()),
+    ///         _ => Err(ConnectError::new("not authorized")),
+    ///     };
+    ///     async move { result }.boxed()
+    /// });
+    /// ```
+    pub fn middleware<F>(mut self, middleware: F) -> Self
+    where
+        F: Fn(Handshake) -> BoxFuture<'static, std::result::Result<(), ConnectError>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Connects the server to the other servers of a cluster via `adapter`,
+    /// so that broadcasts reach the sockets connected to any of them, see
+    /// [`Adapter`].
+    pub fn adapter<A: Adapter + 'static>(mut self, adapter: A) -> Self {
+        self.adapter = Some(Arc::new(adapter));
+        self
+    }
+
+    /// Instruments the server for the socket.io Admin UI, whose dashboard
+    /// then connects to the namespace of `admin_ui`, see [`AdminUi`].
+    pub fn admin_ui(mut self, admin_ui: AdminUi) -> Self {
+        self.admin = Some(admin_ui);
+        self
+    }
+
+    /// Sends `data` as `event` to every socket when the server shuts down via
+    /// [`Server::shutdown`], before they're disconnected, e.g. to tell the
+    /// clients about a maintenance.
+    pub fn shutdown_notice<E, D>(mut self, event: E, data: D) -> Self
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.shutdown_notice = Some((event.into(), data.into()));
+        self
+    }
+
+    /// Listens for clients on `address`, they're served once
+    /// [`Server::serve`] is called. Fails if the adapter fails to subscribe to
+    /// the broadcasts of the other servers.
+    pub async fn bind<A: ToSocketAddrs>(mut self, address: A) -> Result<Server> {
+        let engine = std::mem::take(&mut self.engine).bind(address).await?;
+        self.start(engine).await
+    }
+
+    /// Creates a server without a listener, whose clients connect through the
+    /// HTTP server of the application via [`Server::service`] instead. They're
+    /// served once [`Server::serve`] is called.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let server = ServerBuilder::new().namespace("/", on_connection).build().await?;
+    /// let app = axum::Router::new().nest_service("/socket.io", server.service());
+    /// tokio::spawn(server.serve());
+    /// ```
+    #[cfg(feature = "hyper")]
+    pub async fn build(mut self) -> Result<Server> {
+        let engine = std::mem::take(&mut self.engine).build();
+        self.start(engine).await
+    }
+
+    async fn start(mut self, engine: EngineServer) -> Result<Server> {
+        let cluster = self.adapter.map(Cluster::new);
+        let subscription = match &cluster {
+            Some(cluster) => Some((cluster.clone(), cluster.subscribe().await?)),
+            None => None,
+        };
+        let (monitor, changes) = Monitor::channel();
+        let mut namespaces: HashMap<String, Registered> = self
+            .namespaces
+            .into_iter()
+            .map(|(nsp, on_connection)| {
+                let mut namespace = Namespace::new(nsp.clone(), cluster.clone());
+                if self.admin.is_some() {
+                    namespace = namespace.monitored(monitor.clone());
+                }
+                (nsp, (on_connection, namespace))
+            })
+            .collect();
+        let admin = self.admin.map(|options| {
+            let namespace = Namespace::new(options.name().to_owned(), cluster.clone());
+            namespaces.remove(options.name());
+            let monitored = namespaces.values().map(|(_, ns)| ns.clone()).collect();
+            let admin = Arc::new(Admin::new(options, namespace.clone(), monitored));
+            self.middlewares.push(admin.middleware());
+            namespaces.insert(
+                namespace.name().to_owned(),
+                (admin.on_connection(), namespace),
+            );
+            (admin, changes)
+        });
+        Ok(Server {
+            engine: Arc::new(engine),
+            namespaces: Arc::new(namespaces),
+            middlewares: Arc::new(self.middlewares),
+            background: Arc::new(Mutex::new(Some(Background {
+                subscription,
+                admin,
+            }))),
+            shutdown_notice: self.shutdown_notice,
+        })
+    }
+}
+
+/// A `socket.io` server listening for clients, see the
+/// [module documentation](self). Clones share the server, e.g. to shut it
+/// down via [`Server::shutdown`] while it's served.
+#[derive(Clone)]
+pub struct Server {
+    engine: Arc<EngineServer>,
+    namespaces: Arc<HashMap<String, Registered>>,
+    middlewares: Arc<Vec<Middleware>>,
+    /// Taken by the first call of [`Server::serve`].
+    background: Arc<Mutex<Option<Background>>>,
+    shutdown_notice: Option<(Event, Payload)>,
+}
+
+/// What [`Server::serve`] runs besides accepting the clients.
+struct Background {
+    /// The broadcasts of the other servers of the cluster.
+    subscription: Option<(Cluster, BoxStream<'static, ClusterMessage>)>,
+    /// The admin UI and the changes of the sockets it reports.
+    admin: Option<(Arc<Admin>, mpsc::UnboundedReceiver<Change>)>,
+}
+
+impl Server {
+    /// The address the server listens on, e.g. to find out the port if it was
+    /// bound to port 0. `None` if the server was built without a listener.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.engine.local_addr()
+    }
+
+    /// Serves the `engine.io` endpoints of the clients as a `tower` service,
+    /// e.g. to mount the server into the router of an `axum` application,
+    /// see [`EngineService`]. The clients are served once [`Server::serve`]
+    /// is called, along with the ones of the listener.
+    #[cfg(feature = "hyper")]
+    pub fn service(&self) -> EngineService {
+        self.engine.service()
+    }
+
+    /// Mounts the server at `/socket.io` of an application as a `tower`
+    /// layer, which serves the polling and websocket requests of the clients
+    /// and passes all others on to the application, see [`EngineLayer`].
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let server = ServerBuilder::new().namespace("/", on_connection).build().await?;
+    /// let app = axum::Router::new()
+    ///     .route("/", axum::routing::get(|| async { "hello" }))
+    ///     .layer(server.layer());
+    /// tokio::spawn(server.serve());
+    /// ```
+    #[cfg(feature = "hyper")]
+    pub fn layer(&self) -> EngineLayer {
+        self.engine.layer().path("/socket.io")
+    }
+
+    /// The registered namespace `namespace`, to broadcast to its sockets. The
+    /// namespace stays usable once the server is served.
+    pub fn of(&self, namespace: &str) -> Option<Namespace> {
+        let mut nsp = namespace.to_owned();
+        if !nsp.starts_with('/') {
+            nsp = "/".to_owned() + &nsp;
+        }
+        self.namespaces
+            .get(&nsp)
+            .map(|(_, namespace)| namespace.clone())
+    }
+
+    /// Targets the sockets of the main namespace `/` in `room`, see
+    /// [`Namespace::to`]. Reaches no socket if `/` isn't registered.
+    pub fn to<R: Into<String>>(&self, room: R) -> Broadcast {
+        self.of("/")
+            .unwrap_or_else(|| Namespace::new("/".to_owned(), None))
+            .to(room)
+    }
+
+    /// Serves the clients, each on its own task, applies the broadcasts of
+    /// the other servers of the cluster and reports to the admin UI. Only
+    /// returns once the server can't accept connections anymore, e.g. after
+    /// [`Server::shutdown`].
+    pub async fn serve(self) -> Result<()> {
+        let Server {
+            engine,
+            namespaces,
+            middlewares,
+            background,
+            ..
+        } = self;
+        let (subscription, admin) = match background.lock().ok().and_then(|mut b| b.take()) {
+            Some(background) => (background.subscription, background.admin),
+            None => (None, None),
+        };
+        let receiving = {
+            let namespaces = namespaces.clone();
+            async move {
+                if let Some((cluster, messages)) = subscription {
+                    cluster.receive(messages, namespaces).await;
+                }
+                future::pending::<()>().await
+            }
+        };
+        let reporting = async move {
+            if let Some((admin, changes)) = admin {
+                admin.run(changes).await;
+            }
+            future::pending::<()>().await
+        };
+        let accepting = async move {
+            while let Some(session) = engine.accept().await {
+                tokio::spawn(Self::handle_session(
+                    session,
+                    namespaces.clone(),
+                    middlewares.clone(),
+                ));
+            }
+        };
+        let background = future::join(receiving, reporting);
+        future::select(pin!(accepting), pin!(background)).await;
+        Ok(())
+    }
+
+    /// Shuts the server down gracefully: it stops accepting connections,
+    /// sends the event of [`ServerBuilder::shutdown_notice`] to every socket
+    /// and disconnects it, then closes the `engine.io` sessions. Resolves
+    /// once the clients received all of it, or once `grace_period` is over,
+    /// which drops the remaining connections. [`Server::serve`] returns then.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let server = ServerBuilder::new()
+    ///     .namespace("/", on_connection)
+    ///     .shutdown_notice("maintenance", json!({ "back_in": 300 }))
+    ///     .bind("127.0.0.1:4200")
+    ///     .await?;
+    /// tokio::spawn(server.clone().serve());
+    /// tokio::signal::ctrl_c().await?;
+    /// server.shutdown(Duration::from_secs(10)).await;
+    /// ```
+    pub async fn shutdown(&self, grace_period: Duration) {
+        self.engine.stop_accepting();
+        let sockets: Vec<ServerSocket> = self
+            .namespaces
+            .values()
+            .flat_map(|(_, namespace)| namespace.sockets())
+            .collect();
+        let notifying = future::join_all(sockets.iter().map(|socket| async move {
+            if let Some((event, data)) = &self.shutdown_notice {
+                let _ = socket.emit(event.clone(), data.clone()).await;
+            }
+            let _ = socket.disconnect().await;
+        }));
+        let deadline = tokio::time::Instant::now() + grace_period;
+        let _ = tokio::time::timeout_at(deadline, notifying).await;
+        self.engine
+            .shutdown(deadline.saturating_duration_since(tokio::time::Instant::now()))
+            .await;
+    }
+
+    /// Dispatches the packets of a client to the sockets of its namespaces.
+    async fn handle_session(
+        mut session: EngineSocket,
+        namespaces: Arc<HashMap<String, Registered>>,
+        middlewares: Arc<Vec<Middleware>>,
+    ) {
+        let mut sockets: HashMap<String, ServerSocket> = HashMap::new();
+        while let Some(Ok(packet)) = session.next().await {
+            if packet.packet_id != EnginePacketId::Message {
+                // attachments are read along with their packet
+                continue;
+            }
+            let Ok(mut packet) = Packet::decode(&packet.data, Utf8Policy::default()) else {
+                break;
+            };
+            if packet.attachment_count > 0 {
+                let mut attachments = Vec::new();
+                while attachments.len() < usize::from(packet.attachment_count) {
+                    match session.next().await {
+                        Some(Ok(attachment)) => attachments.push(attachment.data),
+                        _ => break,
+                    }
+                }
+                packet.attachments = Some(attachments);
+            }
+
+            match packet.packet_type {
+                PacketId::Connect => {
+                    let Some((on_connection, namespace)) = namespaces.get(&packet.nsp) else {
+                        refuse(
+                            &session,
+                            &packet.nsp,
+                            ConnectError::new("Invalid namespace"),
+                        )
+                        .await;
+                        continue;
+                    };
+                    let handshake = Handshake {
+                        nsp: packet.nsp.clone(),
+                        auth: packet
+                            .data
+                            .as_ref()
+                            .and_then(|data| serde_json::from_str(data).ok()),
+                        uri: session.uri().clone(),
+                        headers: session.headers().clone(),
+                        issued: SystemTime::now(),
+                    };
+                    if let Err(error) = Self::run_middlewares(&middlewares, &handshake).await {
+                        refuse(&session, &packet.nsp, error).await;
+                        continue;
+                    }
+                    let socket =
+                        ServerSocket::connect(&session, handshake, on_connection, namespace).await;
+                    if let Some(socket) = socket {
+                        sockets.insert(packet.nsp, socket);
+                    }
+                }
+                PacketId::Disconnect => {
+                    if let Some(socket) = sockets.remove(&packet.nsp) {
+                        socket.closed("client namespace disconnect");
+                    }
+                }
+                PacketId::Event | PacketId::BinaryEvent => {
+                    if let Some(socket) = sockets.get(&packet.nsp) {
+                        socket.dispatch(packet);
+                    }
+                }
+                PacketId::Ack | PacketId::BinaryAck => {
+                    if let Some(socket) = sockets.get(&packet.nsp) {
+                        socket.resolve_ack(packet);
+                    }
+                }
+                PacketId::ConnectError => (),
+            }
+            sockets.retain(|_, socket| socket.is_connected());
+        }
+
+        for socket in sockets.values() {
+            socket.closed("transport close");
+        }
+    }
+
+    async fn run_middlewares(
+        middlewares: &[Middleware],
+        handshake: &Handshake,
+    ) -> std::result::Result<(), ConnectError> {
+        for middleware in middlewares {
+            middleware(handshake.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("local_addr", &self.local_addr())
+            .field("namespaces", &self.namespaces.keys())
+            .finish()
+    }
+}
+
+/// A client connected to a namespace of the [`Server`]. Clones share the
+/// socket.
+#[derive(Clone)]
+pub struct ServerSocket {
+    inner: Arc<SocketInner>,
+}
+
+struct SocketInner {
+    id: String,
+    namespace: Namespace,
+    handshake: Handshake,
+    session: EngineSocket,
+    handlers: RwLock<HashMap<Event, EventHandler>>,
+    acks: Mutex<HashMap<i32, oneshot::Sender<Payload>>>,
+    next_ack_id: AtomicI32,
+    connected: AtomicBool,
+}
+
+impl ServerSocket {
+    /// Accepts the connection to a namespace and calls its handler.
+    async fn connect(
+        session: &EngineSocket,
+        handshake: Handshake,
+        on_connection: &ConnectionHandler,
+        namespace: &Namespace,
+    ) -> Option<Self> {
+        let socket = ServerSocket {
+            inner: Arc::new(SocketInner {
+                id: format!("{:016x}", thread_rng().gen::<u64>()),
+                namespace: namespace.clone(),
+                handshake,
+                session: session.clone(),
+                handlers: RwLock::default(),
+                acks: Mutex::default(),
+                next_ack_id: AtomicI32::new(0),
+                connected: AtomicBool::new(true),
+            }),
+        };
+        let accepted = Packet::new(
+            PacketId::Connect,
+            socket.namespace().to_owned(),
+            Some(json!({ "sid": socket.id() }).to_string()),
+            None,
+            0,
+            None,
+        );
+        send(session, accepted).await.ok()?;
+        namespace.rooms().insert(&socket);
+        namespace.report(Change::Connected(socket.clone()));
+        // the handler runs on its own task, so that the packets of the client,
+        // like the ack of an emit of the handler, are handled meanwhile. It's
+        // polled once right away, so that the event handlers it registers
+        // before its first await receive the first events.
+        let mut handler = on_connection(socket.clone());
+        if futures_util::poll!(handler.as_mut()).is_pending() {
+            tokio::spawn(handler);
+        }
+        Some(socket)
+    }
+
+    /// The id of the socket, which is different for every namespace the
+    /// client connects to.
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    /// The namespace the socket is connected to.
+    pub fn namespace(&self) -> &str {
+        self.inner.namespace.name()
+    }
+
+    /// The auth data the client sent along with its connection.
+    pub fn auth(&self) -> Option<&Value> {
+        self.inner.handshake.auth()
+    }
+
+    /// What the client sent to connect to the namespace, as passed to the
+    /// middlewares, like `socket.handshake` of the JavaScript server.
+    pub fn handshake(&self) -> &Handshake {
+        &self.inner.handshake
+    }
+
+    /// The URI the client requested to open its session, including the query.
+    pub fn uri(&self) -> &Uri {
+        self.inner.session.uri()
+    }
+
+    /// The headers of the request that opened the session of the client.
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.session.headers()
+    }
+
+    /// Whether the client is still connected to the namespace.
+    pub fn is_connected(&self) -> bool {
+        self.inner.connected.load(Ordering::Acquire)
+    }
+
+    /// Joins `room` of the namespace, so that broadcasts to it reach the
+    /// socket. The socket leaves its rooms once it disconnects.
+    pub fn join<R: Into<String>>(&self, room: R) {
+        self.inner.namespace.join(self.id(), room.into());
+    }
+
+    /// Leaves `room` of the namespace.
+    pub fn leave(&self, room: &str) {
+        self.inner.namespace.leave(self.id(), room);
+    }
+
+    /// The rooms the socket is in, including the one of its id.
+    pub fn rooms(&self) -> Vec<String> {
+        self.inner.namespace.rooms().rooms_of(self.id())
+    }
+
+    /// Targets the other sockets in `room`, like `socket.to(room)` of the
+    /// JavaScript server.
+    pub fn to<R: Into<String>>(&self, room: R) -> Broadcast {
+        self.broadcast().to(room)
+    }
+
+    /// Targets all other sockets of the namespace.
+    pub fn broadcast(&self) -> Broadcast {
+        self.inner.namespace.except(self.id())
+    }
+
+    /// Registers the handler of `event`, replacing the previous one. The
+    /// handler receives an [`AckResponder`] to answer the ack the client may
+    /// have requested. The handler of [`Event::Close`] is called once the
+    /// client disconnected, with the reason as payload.
+    pub fn on<E, F>(&self, event: E, handler: F)
+    where
+        E: Into<Event>,
+        F: Fn(Payload, ServerSocket, AckResponder) -> BoxFuture<'static, ()>
+            + Send
+            + Sync
+            + 'static,
+    {
+        if let Ok(mut handlers) = self.inner.handlers.write() {
+            handlers.insert(event.into(), Arc::new(handler));
+        }
+    }
+
+    /// Registers a handler of `event` whose arguments are extracted from the
+    /// event, replacing the previous one, like the handlers of `axum`. Any
+    /// [`FromEvent`] can be an argument: [`Data`] deserializes the payload,
+    /// the [`ServerSocket`] and the [`AckResponder`] are passed as they are.
+    /// Events whose payload can't be deserialized are skipped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rust_socketio::server::{AckResponder, Data, ServerSocket};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Message {
+    ///     room: String,
+    ///     text: String,
+    /// }
+    ///
+    /// fn register(socket: &ServerSocket) {
+    ///     socket.handle(
+    ///         "message",
+    ///         |Data(message): Data<Message>, socket: ServerSocket, ack: AckResponder| async move {
+    ///             let _ = socket.to(message.room).emit("message", message.text).await;
+    ///             let _ = ack.send("delivered").await;
+    ///         },
+    ///     );
+    /// }
+    /// ```
+    pub fn handle<E, H, Args>(&self, event: E, handler: H)
+    where
+        E: Into<Event>,
+        H: Handler<Args>,
+        Args: 'static,
+    {
+        let handler = Extracting::new(handler);
+        self.on(event, move |payload, socket, ack| {
+            handler.call(payload, socket, ack)
+        });
+    }
+
+    /// Sends `data` to the client as `event`.
+    pub async fn emit<E, D>(&self, event: E, data: D) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let packet = Packet::new_from_payload(data.into(), event.into(), self.namespace(), None)?;
+        self.send(packet).await
+    }
+
+    /// Sends `data` to the client as `event` and returns the payload of the
+    /// ack. Fails with [`Error::AckTimeout`] if the ack isn't received within
+    /// `timeout`, and with [`Error::Disconnected`] if the client disconnects
+    /// before.
+    pub async fn emit_with_ack<E, D>(&self, event: E, data: D, timeout: Duration) -> Result<Payload>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        let event = event.into();
+        let mut packet =
+            Packet::new_from_payload(data.into(), event.clone(), self.namespace(), None)?;
+        let id = self.inner.next_ack_id.fetch_add(1, Ordering::Relaxed);
+        packet.id = Some(id);
+
+        let (tx, rx) = oneshot::channel();
+        self.inner.acks.lock()?.insert(id, tx);
+        if let Err(err) = self.send(packet).await {
+            self.inner.acks.lock()?.remove(&id);
+            return Err(err);
+        }
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(payload)) => Ok(payload),
+            Ok(Err(_)) => Err(Error::Disconnected(event)),
+            Err(_) => {
+                self.inner.acks.lock()?.remove(&id);
+                Err(Error::AckTimeout(event))
+            }
+        }
+    }
+
+    /// Disconnects the client from the namespace.
+    pub async fn disconnect(&self) -> Result<()> {
+        let packet = Packet::new(
+            PacketId::Disconnect,
+            self.namespace().to_owned(),
+            None,
+            None,
+            0,
+            None,
+        );
+        let result = self.send(packet).await;
+        self.closed("server namespace disconnect");
+        result
+    }
+
+    /// Disconnects the client on behalf of [`Broadcast::disconnect`], closing
+    /// its connection with `close`.
+    async fn disconnect_by_server(&self, close: bool) -> Result<()> {
+        if !close {
+            return self.disconnect().await;
+        }
+        let result = self.inner.session.close().await;
+        self.closed("forced server close");
+        Ok(result?)
+    }
+
+    async fn send(&self, packet: Packet) -> Result<()> {
+        if !self.is_connected() {
+            return Err(Error::IllegalActionBeforeOpen());
+        }
+        send(&self.inner.session, packet).await
+    }
+
+    /// Calls the handler of an event on its own task.
+    fn dispatch(&self, packet: Packet) {
+        let (event, payload) = match packet.packet_type {
+            PacketId::BinaryEvent => {
+                let attachment = packet.attachments.as_ref().and_then(|a| a.first());
+                let Some(data) = attachment else {
+                    return;
+                };
+                (packet.binary_event(), Payload::Binary(data.clone()))
+            }
+            _ => {
+                let Some(Ok(Value::Array(mut args))) =
+                    packet.data.as_deref().map(serde_json::from_str::<Value>)
+                else {
+                    return;
+                };
+                match args.first() {
+                    Some(Value::String(event)) if args.len() > 1 => {
+                        let event = Event::from(event.as_str());
+                        (event, Payload::Text(args.split_off(1)))
+                    }
+                    _ => (Event::Message, Payload::Text(args)),
+                }
+            }
+        };
+
+        let handler = match self.inner.handlers.read() {
+            Ok(handlers) => handlers.get(&event).cloned(),
+            Err(_) => None,
+        };
+        if let Some(handler) = handler {
+            let ack = AckResponder {
+                socket: self.clone(),
+                id: packet.id,
+            };
+            tokio::spawn(handler(payload, self.clone(), ack));
+        }
+    }
+
+    /// Passes the payload of an ack to the emit that awaits it.
+    fn resolve_ack(&self, packet: Packet) {
+        let Some(id) = packet.id else {
+            return;
+        };
+        let Some(tx) = self
+            .inner
+            .acks
+            .lock()
+            .ok()
+            .and_then(|mut acks| acks.remove(&id))
+        else {
+            return;
+        };
+        let payload = match packet.packet_type {
+            PacketId::BinaryAck => match packet.attachments.and_then(|a| a.into_iter().next()) {
+                Some(data) => Payload::Binary(data),
+                None => return,
+            },
+            _ => match packet.data.as_deref().map(serde_json::from_str::<Value>) {
+                Some(Ok(Value::Array(args))) => Payload::Text(args),
+                _ => Payload::Text(Vec::new()),
+            },
+        };
+        let _ = tx.send(payload);
+    }
+
+    /// Marks the socket as disconnected, fails the pending acks and calls the
+    /// handler of [`Event::Close`].
+    fn closed(&self, reason: &str) {
+        if !self.inner.connected.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        self.inner.namespace.rooms().remove(self.id());
+        self.inner.namespace.report(Change::Disconnected {
+            nsp: self.namespace().to_owned(),
+            id: self.id().to_owned(),
+            reason: reason.to_owned(),
+        });
+        if let Ok(mut acks) = self.inner.acks.lock() {
+            acks.clear();
+        }
+        let handler = match self.inner.handlers.read() {
+            Ok(handlers) => handlers.get(&Event::Close).cloned(),
+            Err(_) => None,
+        };
+        if let Some(handler) = handler {
+            let ack = AckResponder {
+                socket: self.clone(),
+                id: None,
+            };
+            tokio::spawn(handler(Payload::from(reason), self.clone(), ack));
+        }
+    }
+}
+
+impl Debug for ServerSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerSocket")
+            .field("id", &self.inner.id)
+            .field("nsp", &self.namespace())
+            .field("connected", &self.is_connected())
+            .finish()
+    }
+}
+
+/// Answers the ack a client requested for an event, see [`ServerSocket::on`].
+#[derive(Debug)]
+pub struct AckResponder {
+    socket: ServerSocket,
+    id: Option<i32>,
+}
+
+impl AckResponder {
+    /// Whether the client requested an ack for the event.
+    pub fn is_requested(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// Sends `data` as the ack. Does nothing if the client didn't request an
+    /// ack.
+    pub async fn send<D: Into<Payload>>(self, data: D) -> Result<()> {
+        let Some(id) = self.id else {
+            return Ok(());
+        };
+        let nsp = self.socket.namespace().to_owned();
+        let packet = match data.into() {
+            Payload::Binary(data) => Packet::new(
+                PacketId::BinaryAck,
+                nsp,
+                None,
+                Some(id),
+                1,
+                Some(vec![data]),
+            ),
+            Payload::Text(args) => Packet::new(
+                PacketId::Ack,
+                nsp,
+                Some(Value::Array(args).to_string()),
+                Some(id),
+                0,
+                None,
+            ),
+            #[allow(deprecated)]
+            Payload::String(data) => Packet::new(
+                PacketId::Ack,
+                nsp,
+                Some(Value::Array(vec![Payload::string_to_value(data)]).to_string()),
+                Some(id),
+                0,
+                None,
+            ),
+        };
+        self.socket.send(packet).await
+    }
+}
+
+/// Refuses the connection to `nsp` with a `CONNECT_ERROR` packet.
+async fn refuse(session: &EngineSocket, nsp: &str, error: ConnectError) {
+    let refusal = Packet::new(
+        PacketId::ConnectError,
+        nsp.to_owned(),
+        Some(error.to_json().to_string()),
+        None,
+        0,
+        None,
+    );
+    let _ = send(session, refusal).await;
+}
+
+/// Sends a `socket.io` packet with its attachments.
+async fn send(session: &EngineSocket, packet: Packet) -> Result<()> {
+    session
+        .emit(EnginePacket::new(
+            EnginePacketId::Message,
+            Bytes::from(&packet),
+        ))
+        .await?;
+    for attachment in packet.attachments.into_iter().flatten() {
+        session
+            .emit(EnginePacket::new(EnginePacketId::MessageBinary, attachment))
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::FutureExt;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{asynchronous::ClientBuilder, ReconnectEvent, TransportType};
+
+    async fn serve(builder: ServerBuilder) -> String {
+        let server = builder.bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        tokio::spawn(server.serve());
+        url
+    }
+
+    #[tokio::test]
+    async fn test_events_and_acks() -> Result<()> {
+        let (closed_tx, mut closed_rx) = mpsc::unbounded_channel();
+        let url = serve(ServerBuilder::new().namespace("/", move |socket| {
+            let closed_tx = closed_tx.clone();
+            async move {
+                socket.on("echo", |payload, _, ack| {
+                    async move {
+                        assert!(ack.is_requested());
+                        ack.send(payload).await.unwrap();
+                    }
+                    .boxed()
+                });
+                socket.on(Event::Close, move |reason, _, _| {
+                    let _ = closed_tx.send(reason);
+                    async {}.boxed()
+                });
+                let auth = socket.auth().cloned().unwrap_or_default();
+                socket.emit("welcome", auth).await.unwrap();
+            }
+            .boxed()
+        }))
+        .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let welcome_tx = tx.clone();
+        let client = ClientBuilder::new(url)
+            .transport_type(TransportType::Websocket)
+            .auth(json!({ "token": "abc" }))
+            .on("welcome", move |payload, _| {
+                let _ = welcome_tx.send(payload);
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        let welcome = rx.recv().await.unwrap();
+        assert_eq!(welcome, Payload::from(json!({ "token": "abc" })));
+
+        client
+            .emit_with_ack(
+                "echo",
+                json!("hi"),
+                Duration::from_secs(5),
+                move |payload, _| {
+                    let _ = tx.send(payload);
+                    async {}.boxed()
+                },
+            )
+            .await?;
+        // the client passes the arguments of an ack on as one array
+        let ack = rx.recv().await.unwrap();
+        assert_eq!(ack, Payload::Text(vec![json!(["hi"])]));
+
+        client.disconnect().await?;
+        let reason = closed_rx.recv().await.unwrap();
+        assert_eq!(reason, Payload::from("client namespace disconnect"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ack_in_connection_handler() -> Result<()> {
+        use rust_engineio::{
+            asynchronous::ClientBuilder as EngineClientBuilder, Packet as EnginePacket,
+            PacketId as EnginePacketId,
+        };
+
+        let (acked_tx, mut acked_rx) = mpsc::unbounded_channel();
+        let url = serve(ServerBuilder::new().namespace("/", move |socket| {
+            let acked_tx = acked_tx.clone();
+            async move {
+                let result = socket
+                    .emit_with_ack("ping", json!(1), Duration::from_secs(5))
+                    .await;
+                let _ = acked_tx.send(result);
+            }
+            .boxed()
+        }))
+        .await;
+
+        // the client answers the ack by hand, the clients of this crate don't
+        let url = url::Url::parse(&format!("{url}socket.io/"))?;
+        let client = EngineClientBuilder::new(url).build_websocket().await?;
+        client.connect().await?;
+        client
+            .emit(EnginePacket::new(EnginePacketId::Message, "0"))
+            .await?;
+        let mut stream = client.clone();
+        async fn next(stream: &mut rust_engineio::asynchronous::Client) -> String {
+            loop {
+                let packet = stream.next().await.unwrap().unwrap();
+                if packet.packet_id == EnginePacketId::Message {
+                    return String::from_utf8(packet.data.to_vec()).unwrap();
+                }
+            }
+        }
+        assert!(next(&mut stream).await.starts_with("0{"));
+        assert_eq!(next(&mut stream).await, "20[\"ping\",1]");
+        client
+            .emit(EnginePacket::new(EnginePacketId::Message, "30[\"pong\"]"))
+            .await?;
+
+        let result = tokio::time::timeout(Duration::from_secs(5), acked_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result?, Payload::Text(vec![json!("pong")]));
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() -> Result<()> {
+        let server = ServerBuilder::new()
+            .namespace("/", |_| async {}.boxed())
+            .shutdown_notice("maintenance", json!({ "back_in": 300 }))
+            .bind("127.0.0.1:0")
+            .await?;
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        let serving = tokio::spawn(server.clone().serve());
+
+        let mut clients = Vec::new();
+        for transport in [TransportType::Polling, TransportType::Websocket] {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let notice_tx = tx.clone();
+            let close_tx = tx.clone();
+            let client = ClientBuilder::new(url.clone())
+                .transport_type(transport)
+                .reconnect(false)
+                .on("maintenance", move |payload, _| {
+                    let _ = notice_tx.send(payload);
+                    async {}.boxed()
+                })
+                .on(Event::Close, move |_, _| {
+                    let _ = close_tx.send(Payload::from("closed"));
+                    async {}.boxed()
+                })
+                .connect()
+                .await?;
+            clients.push((client, rx));
+        }
+        while server.of("/").unwrap().sockets().len() < clients.len() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        server.shutdown(Duration::from_secs(5)).await;
+        assert!(server.of("/").unwrap().sockets().is_empty());
+        serving.await.unwrap()?;
+        for (_, rx) in &mut clients {
+            // the notice arrives before the disconnect
+            let notice = rx.recv().await.unwrap();
+            assert_eq!(notice, Payload::from(json!({ "back_in": 300 })));
+            assert_eq!(rx.recv().await.unwrap(), Payload::from("closed"));
+        }
+
+        // new connections are refused
+        let refused = ClientBuilder::new(url).reconnect(false).connect().await;
+        assert!(refused.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_polling_only() -> Result<()> {
+        let url = serve(ServerBuilder::new().namespace("/", |_| async {}.boxed())).await;
+
+        // the server offers the upgrade, which polling-only clients ignore
+        let client = ClientBuilder::new(url.clone())
+            .transport_type(TransportType::Polling)
+            .connect()
+            .await?;
+        let upgraded = ClientBuilder::new(url).connect().await?;
+        for client in [&client, &upgraded] {
+            while !client.is_connected() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        assert_eq!(
+            client.snapshot().transport,
+            Some(crate::TransportKind::Polling)
+        );
+        assert_eq!(
+            upgraded.snapshot().transport,
+            Some(crate::TransportKind::Websocket)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transport_priority() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let url = serve(ServerBuilder::new().namespace("/", |_| async {}.boxed())).await;
+        let address = url.trim_start_matches("http://").trim_end_matches('/');
+
+        // a proxy that breaks websockets and passes polling requests on
+        let proxy = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let proxy_url = format!("http://{}/", proxy.local_addr()?);
+        let address = address.to_owned();
+        tokio::spawn(async move {
+            while let Ok((mut incoming, _)) = proxy.accept().await {
+                let address = address.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![0; 4096];
+                    let read = incoming.read(&mut request).await?;
+                    if String::from_utf8_lossy(&request[..read]).contains("transport=websocket") {
+                        return Ok(());
+                    }
+                    let mut outgoing = tokio::net::TcpStream::connect(address).await?;
+                    outgoing.write_all(&request[..read]).await?;
+                    tokio::io::copy_bidirectional(&mut incoming, &mut outgoing).await?;
+                    Ok::<_, std::io::Error>(())
+                });
+            }
+        });
+
+        for (url, transport) in [
+            (url, crate::TransportKind::Websocket),
+            (proxy_url.clone(), crate::TransportKind::Polling),
+        ] {
+            let client = ClientBuilder::new(url)
+                .transports([TransportType::Websocket, TransportType::Polling])
+                .connect()
+                .await?;
+            while !client.is_connected() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert_eq!(client.snapshot().transport, Some(transport));
+        }
+
+        let result = ClientBuilder::new(proxy_url)
+            .transports([TransportType::Websocket])
+            .connect()
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transport_change() -> Result<()> {
+        let url = serve(ServerBuilder::new().namespace("/", |_| async {}.boxed())).await;
+
+        for (transport_type, transport) in [
+            (TransportType::Any, crate::TransportKind::Websocket),
+            (TransportType::Polling, crate::TransportKind::Polling),
+        ] {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let client = ClientBuilder::new(url.clone())
+                .transport_type(transport_type)
+                .on_transport_change(move |transport| {
+                    let _ = tx.send(transport);
+                    async {}.boxed()
+                })
+                .connect()
+                .await?;
+            // the upgrade of `Any` is reported once it's done
+            assert_eq!(rx.recv().await, Some(transport));
+            assert_eq!(client.snapshot().transport, Some(transport));
+            assert!(rx.try_recv().is_err());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_extractors() -> Result<()> {
+        let url = serve(ServerBuilder::new().namespace("/", |socket| {
+            async move {
+                socket.handle(
+                    "add",
+                    |Data((a, b)): Data<(i64, i64)>, ack: AckResponder| async move {
+                        ack.send(json!(a + b)).await.unwrap();
+                    },
+                );
+                socket.handle(
+                    "shout",
+                    |socket: ServerSocket, Data(text): Data<String>| async move {
+                        socket.emit("shouted", text.to_uppercase()).await.unwrap();
+                    },
+                );
+            }
+            .boxed()
+        }))
+        .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let shouted_tx = tx.clone();
+        let client = ClientBuilder::new(url)
+            .transport_type(TransportType::Websocket)
+            .on("shouted", move |payload, _| {
+                let _ = shouted_tx.send(payload);
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+
+        client.emit("shout", json!("hi")).await?;
+        assert_eq!(rx.recv().await.unwrap(), Payload::from(json!("HI")));
+
+        client
+            .emit_with_ack(
+                "add",
+                vec![json!(1), json!(2)],
+                Duration::from_secs(5),
+                move |payload, _| {
+                    let _ = tx.send(payload);
+                    async {}.boxed()
+                },
+            )
+            .await?;
+        assert_eq!(rx.recv().await.unwrap(), Payload::Text(vec![json!([3])]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rooms() -> Result<()> {
+        let server = ServerBuilder::new()
+            .namespace("/", |socket| {
+                socket.on("join", |_, socket, ack| {
+                    socket.join("chat");
+                    async move {
+                        ack.send(json!(true)).await.unwrap();
+                    }
+                    .boxed()
+                });
+                socket.on("shout", |payload, socket, _| {
+                    async move {
+                        socket.to("chat").emit("shout", payload).await.unwrap();
+                    }
+                    .boxed()
+                });
+                async {}.boxed()
+            })
+            .bind("127.0.0.1:0")
+            .await?;
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        let io = server.of("/").unwrap();
+        tokio::spawn(server.serve());
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut clients = Vec::new();
+        for name in ["a", "b", "c"] {
+            let (news_tx, shout_tx) = (tx.clone(), tx.clone());
+            let client = ClientBuilder::new(url.clone())
+                .transport_type(TransportType::Websocket)
+                .on("news", move |payload, _| {
+                    let _ = news_tx.send((name, "news", payload));
+                    async {}.boxed()
+                })
+                .on("shout", move |payload, _| {
+                    let _ = shout_tx.send((name, "shout", payload));
+                    async {}.boxed()
+                })
+                .connect()
+                .await?;
+            clients.push(client);
+        }
+        let (joined_tx, mut joined_rx) = mpsc::unbounded_channel();
+        for client in &clients[..2] {
+            let joined_tx = joined_tx.clone();
+            client
+                .emit_with_ack("join", json!(null), Duration::from_secs(5), move |_, _| {
+                    let _ = joined_tx.send(());
+                    async {}.boxed()
+                })
+                .await?;
+        }
+        joined_rx.recv().await.unwrap();
+        joined_rx.recv().await.unwrap();
+        assert_eq!(io.sockets().len(), 3);
+
+        io.to("chat").emit("news", json!("chat")).await?;
+        clients[0].emit("shout", json!("hey")).await?;
+        io.emit("news", json!("all")).await?;
+
+        let mut received = Vec::new();
+        for _ in 0..6 {
+            let (name, event, payload) = rx.recv().await.unwrap();
+            received.push(format!("{name} {event} {payload:?}"));
+        }
+        received.sort();
+        let expected = [
+            ("a", "news", "all"),
+            ("a", "news", "chat"),
+            ("b", "news", "all"),
+            ("b", "news", "chat"),
+            ("b", "shout", "hey"),
+            ("c", "news", "all"),
+        ]
+        .map(|(name, event, data)| format!("{name} {event} {:?}", Payload::from(json!(data))));
+        assert_eq!(received, expected);
+
+        // sockets leave their rooms on disconnect
+        clients[1].disconnect().await?;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while io.sockets().len() > 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let socket = io.sockets().into_iter().find(|s| s.rooms().len() > 1);
+        assert_eq!(socket.unwrap().rooms().len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_adapter() -> Result<()> {
+        let adapter = MemoryAdapter::new();
+        let mut urls = Vec::new();
+        let mut namespaces = Vec::new();
+        for _ in 0..2 {
+            let server = ServerBuilder::new()
+                .adapter(adapter.clone())
+                .namespace("/", |socket| {
+                    socket.join("lobby");
+                    async {}.boxed()
+                })
+                .bind("127.0.0.1:0")
+                .await?;
+            urls.push(format!("http://{}/", server.local_addr().unwrap()));
+            namespaces.push(server.of("/").unwrap());
+            tokio::spawn(server.serve());
+        }
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut clients = Vec::new();
+        for (name, url) in [("a", &urls[0]), ("b", &urls[1]), ("c", &urls[1])] {
+            let tx = tx.clone();
+            let client = ClientBuilder::new(url.clone())
+                .transport_type(TransportType::Websocket)
+                .auth(json!(name))
+                .on_any(move |event, _, _| {
+                    let _ = tx.send(format!("{name} {event}"));
+                    async {}.boxed()
+                })
+                .connect()
+                .await?;
+            clients.push(client);
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while namespaces[1].sockets().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let sockets = namespaces[1].sockets().into_iter();
+        let b = sockets.filter(|socket| socket.auth() == Some(&json!("b")));
+        let b = b.map(|socket| socket.id().to_owned()).next().unwrap();
+
+        // the first server reaches the sockets of the second one
+        namespaces[0].to(b.clone()).emit("direct", json!(1)).await?;
+        assert_eq!(rx.recv().await.unwrap(), "b direct");
+        namespaces[0].except(b).join("quiet").await?;
+        namespaces[0].to("quiet").emit("hush", json!(2)).await?;
+        namespaces[0].to("lobby").leave("lobby").await?;
+        namespaces[0].to("lobby").emit("news", json!(3)).await?;
+        namespaces[1].emit("done", json!(4)).await?;
+
+        // the news reach nobody, as the sockets of both servers left the lobby
+        let mut received = Vec::new();
+        for _ in 0..5 {
+            received.push(rx.recv().await.unwrap());
+        }
+        received.sort();
+        let expected = ["a done", "a hush", "b done", "c done", "c hush"];
+        assert_eq!(received, expected.map(String::from));
+        for client in clients {
+            client.disconnect().await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_middleware() -> Result<()> {
+        let url = serve(
+            ServerBuilder::new()
+                .middleware(|handshake| {
+                    let version = handshake.query("EIO");
+                    async move {
+                        assert_eq!(version.as_deref(), Some("4"));
+                        Ok(())
+                    }
+                    .boxed()
+                })
+                .middleware(|handshake| {
+                    let token = handshake.auth().and_then(|auth| auth["token"].as_str());
+                    let result = match token {
+                        Some("secret") => Ok(()),
+                        _ => Err(ConnectError::new("not authorized")
+                            .with_data(json!({ "retry": false }))),
+                    };
+                    async move { result }.boxed()
+                })
+                .namespace("/", |socket| {
+                    async move {
+                        // the handlers see what the middlewares checked
+                        let handshake = socket.handshake();
+                        assert!(handshake.issued() <= SystemTime::now());
+                        let welcome = json!({
+                            "token": handshake.auth().map(|auth| &auth["token"]),
+                            "version": handshake.query("EIO"),
+                        });
+                        socket.emit("welcome", welcome).await.unwrap();
+                    }
+                    .boxed()
+                }),
+        )
+        .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let error_tx = tx.clone();
+        let client = ClientBuilder::new(url.clone())
+            .transport_type(TransportType::Websocket)
+            .auth(json!({ "token": "guess" }))
+            .on(Event::Error, move |payload, _| {
+                let _ = error_tx.send(format!("{payload:?}"));
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        let error = rx.recv().await.unwrap();
+        assert!(error.contains("not authorized"), "{error}");
+        assert!(error.contains("retry"), "{error}");
+        let _ = client.disconnect().await;
+
+        let client = ClientBuilder::new(url)
+            .transport_type(TransportType::Websocket)
+            .auth(json!({ "token": "secret" }))
+            .on("welcome", move |payload, _| {
+                let _ = tx.send(format!("{payload:?}"));
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        let welcome = Payload::from(json!({ "token": "secret", "version": "4" }));
+        assert_eq!(rx.recv().await.unwrap(), format!("{welcome:?}"));
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    /// Waits for `event`, skipping the others, and returns its arguments.
+    async fn next(rx: &mut mpsc::UnboundedReceiver<(String, Payload)>, event: &str) -> Vec<Value> {
+        loop {
+            let (name, payload) = rx.recv().await.unwrap();
+            if name == event {
+                let Payload::Text(args) = payload else {
+                    panic!("{payload:?}");
+                };
+                return args;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_ui() -> Result<()> {
+        let admin_ui = AdminUi::new("admin", "secret").stats_interval(Duration::from_secs(60));
+        let url = serve(
+            ServerBuilder::new()
+                .admin_ui(admin_ui)
+                .namespace("/", |socket| {
+                    socket.join("lobby");
+                    async {}.boxed()
+                }),
+        )
+        .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let error_tx = tx.clone();
+        let client = ClientBuilder::new(url.clone())
+            .namespace("/admin")
+            .auth(json!({ "username": "admin", "password": "guess" }))
+            .on(Event::Error, move |payload, _| {
+                let _ = error_tx.send(("error".to_owned(), payload));
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        let (_, error) = rx.recv().await.unwrap();
+        assert!(format!("{error:?}").contains("invalid credentials"));
+        let _ = client.disconnect().await;
+
+        let admin = ClientBuilder::new(url.clone())
+            .transport_type(TransportType::Websocket)
+            .namespace("/admin")
+            .auth(json!({ "username": "admin", "password": "secret" }))
+            .on_any(move |event, payload, _| {
+                let _ = tx.send((event.to_string(), payload));
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        let config = next(&mut rx, "config").await;
+        assert_eq!(config[0]["supportedFeatures"][1], json!("JOIN"));
+        assert_eq!(next(&mut rx, "all_sockets").await, vec![json!([])]);
+        let stats = next(&mut rx, "server_stats").await;
+        assert_eq!(stats[0]["clientsCount"], json!(1));
+        assert_eq!(stats[0]["pid"], json!(std::process::id()));
+
+        let client = ClientBuilder::new(url)
+            .transport_type(TransportType::Websocket)
+            .connect()
+            .await?;
+        let connected = next(&mut rx, "socket_connected").await;
+        let id = connected[0]["id"].as_str().unwrap().to_owned();
+        assert_eq!(connected[0]["transport"], json!("websocket"));
+        assert_eq!(connected[1], json!("/"));
+        assert_eq!(
+            next(&mut rx, "room_joined").await,
+            vec![json!("/"), json!("lobby"), json!(id)]
+        );
+
+        // the dashboard acts on the sockets
+        admin
+            .emit("_join", vec![json!("/"), json!("vip"), json!("lobby")])
+            .await?;
+        assert_eq!(
+            next(&mut rx, "room_joined").await,
+            vec![json!("/"), json!("vip"), json!(id)]
+        );
+        admin
+            .emit("_disconnect", vec![json!("/"), json!(false), json!(null)])
+            .await?;
+        let disconnected = next(&mut rx, "socket_disconnected").await;
+        assert_eq!(disconnected[1], json!(id));
+        assert_eq!(disconnected[2], json!("server namespace disconnect"));
+        let _ = client.disconnect().await;
+        admin.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_namespaces() -> Result<()> {
+        let (acked_tx, mut acked_rx) = mpsc::unbounded_channel();
+        let url = serve(ServerBuilder::new().namespace("admin", move |socket| {
+            let acked_tx = acked_tx.clone();
+            async move {
+                let result = socket
+                    .emit_with_ack("ping", json!(1), Duration::from_millis(100))
+                    .await;
+                let _ = acked_tx.send(result);
+            }
+            .boxed()
+        }))
+        .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = ClientBuilder::new(url.clone())
+            .namespace("/other")
+            .on(Event::Error, move |payload, _| {
+                let _ = tx.send(payload);
+                async {}.boxed()
+            })
+            .connect()
+            .await?;
+        let error = rx.recv().await.unwrap();
+        assert!(format!("{error:?}").contains("Invalid namespace"));
+        let _ = client.disconnect().await;
+
+        let client = ClientBuilder::new(url)
+            .transport_type(TransportType::Websocket)
+            .namespace("/admin")
+            .connect()
+            .await?;
+        // the client doesn't answer the acks of the server
+        let result = acked_rx.recv().await.unwrap();
+        assert!(matches!(result, Err(Error::AckTimeout(_))));
+        client.disconnect().await?;
+        Ok(())
+    }
+
+    /// Connects a sync and an async client that may reconnect 3 times, shuts
+    /// the server down and returns the attempts the clients announced.
+    async fn reconnect_attempts(max_attempts: Option<u8>) -> Result<Vec<(&'static str, u32)>> {
+        let server = ServerBuilder::new()
+            .namespace("/", |_| async {}.boxed())
+            .bind("127.0.0.1:0")
+            .await?;
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        tokio::spawn(server.clone().serve());
+        let strategy = |attempt: u32| (attempt <= 3).then_some(Duration::from_millis(10));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let async_tx = tx.clone();
+        let mut builder = ClientBuilder::new(url.clone())
+            .transport_type(TransportType::Polling)
+            .reconnect_on_disconnect(true)
+            .backoff(strategy)
+            .on_reconnect_event(move |event| {
+                if let ReconnectEvent::Attempt(attempt) = event {
+                    let _ = async_tx.send(("async", attempt));
+                }
+                async {}.boxed()
+            });
+        if let Some(max_attempts) = max_attempts {
+            builder = builder.max_reconnect_attempts(max_attempts);
+        }
+        let client = builder.connect().await?;
+        let sync_client = tokio::task::spawn_blocking(move || {
+            let mut builder = crate::ClientBuilder::new(url)
+                .transport_type(TransportType::Polling)
+                .reconnect_on_disconnect(true)
+                .backoff(strategy)
+                .on_reconnect_event(move |event| {
+                    if let ReconnectEvent::Attempt(attempt) = event {
+                        let _ = tx.send(("sync", *attempt));
+                    }
+                });
+            if let Some(max_attempts) = max_attempts {
+                builder = builder.max_reconnect_attempts(max_attempts);
+            }
+            builder.connect()
+        })
+        .await
+        .unwrap()?;
+        while server.of("/").unwrap().sockets().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // the server refuses to reconnect, so both clients give up
+        server.shutdown(Duration::from_secs(1)).await;
+        let mut attempts = Vec::new();
+        while let Ok(Some(attempt)) = tokio::time::timeout(Duration::from_secs(2), rx.recv()).await
+        {
+            attempts.push(attempt);
+        }
+        attempts.sort();
+        assert!(!client.is_connected());
+        assert!(!sync_client.is_connected());
+        Ok(attempts)
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_attempts() -> Result<()> {
+        // the backoff strategy gives up
+        assert_eq!(
+            reconnect_attempts(None).await?,
+            [
+                ("async", 1),
+                ("async", 2),
+                ("async", 3),
+                ("sync", 1),
+                ("sync", 2),
+                ("sync", 3)
+            ]
+        );
+        // the limit of attempts is reached first
+        assert_eq!(
+            reconnect_attempts(Some(2)).await?,
+            [("async", 1), ("async", 2), ("sync", 1), ("sync", 2)]
+        );
+        Ok(())
+    }
+}
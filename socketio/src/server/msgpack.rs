@@ -0,0 +1,307 @@
+use bytes::Bytes;
+use serde_json::{Map, Number, Value as Json};
+
+use crate::{error::Result, Error};
+
+/// A MessagePack value, the encoding `notepack.io` gives the broadcasts of
+/// the Redis adapter of the JavaScript server.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bin(Bytes),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    pub(super) fn map<'a, I: IntoIterator<Item = (&'a str, Value)>>(entries: I) -> Self {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Value::Str(key.to_owned()), value))
+                .collect(),
+        )
+    }
+
+    /// The value of `key` if this is a map.
+    pub(super) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(name, _)| name.as_str() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The value as JSON, binary data becomes `null`.
+    pub(super) fn to_json(&self) -> Json {
+        match self {
+            Value::Nil | Value::Bin(_) => Json::Null,
+            Value::Bool(value) => Json::Bool(*value),
+            Value::Int(value) => Json::from(*value),
+            Value::Float(value) => Number::from_f64(*value).map_or(Json::Null, Json::Number),
+            Value::Str(text) => Json::String(text.clone()),
+            Value::Array(items) => Json::Array(items.iter().map(Value::to_json).collect()),
+            Value::Map(entries) => Json::Object(
+                entries
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.as_str()?.to_owned(), value.to_json())))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write(&mut data);
+        data
+    }
+
+    fn write(&self, data: &mut Vec<u8>) {
+        match self {
+            Value::Nil => data.push(0xc0),
+            Value::Bool(value) => data.push(if *value { 0xc3 } else { 0xc2 }),
+            Value::Int(value) => match *value {
+                -32..=0x7f => data.push(*value as u8),
+                0x80..=0xff => write_head(data, 0xcc, &[*value as u8]),
+                0x100..=0xffff => write_head(data, 0xcd, &(*value as u16).to_be_bytes()),
+                0x1_0000..=0xffff_ffff => write_head(data, 0xce, &(*value as u32).to_be_bytes()),
+                0x1_0000_0000.. => write_head(data, 0xcf, &(*value as u64).to_be_bytes()),
+                -0x80..=-33 => write_head(data, 0xd0, &(*value as i8).to_be_bytes()),
+                -0x8000..=-0x81 => write_head(data, 0xd1, &(*value as i16).to_be_bytes()),
+                -0x8000_0000..=-0x8001 => write_head(data, 0xd2, &(*value as i32).to_be_bytes()),
+                _ => write_head(data, 0xd3, &value.to_be_bytes()),
+            },
+            Value::Float(value) => write_head(data, 0xcb, &value.to_be_bytes()),
+            Value::Str(text) => {
+                write_length(data, text.len(), Some(0xa0), 32, [0xd9, 0xda, 0xdb]);
+                data.extend_from_slice(text.as_bytes());
+            }
+            Value::Bin(bytes) => {
+                write_length(data, bytes.len(), None, 0, [0xc4, 0xc5, 0xc6]);
+                data.extend_from_slice(bytes);
+            }
+            Value::Array(items) => {
+                write_length(data, items.len(), Some(0x90), 16, [0, 0xdc, 0xdd]);
+                for item in items {
+                    item.write(data);
+                }
+            }
+            Value::Map(entries) => {
+                write_length(data, entries.len(), Some(0x80), 16, [0, 0xde, 0xdf]);
+                for (key, value) in entries {
+                    key.write(data);
+                    value.write(data);
+                }
+            }
+        }
+    }
+
+    pub(super) fn decode(mut data: &[u8]) -> Result<Self> {
+        let value = Self::read(&mut data)?;
+        match data.is_empty() {
+            true => Ok(value),
+            false => Err(Error::InvalidPacket()),
+        }
+    }
+
+    fn read(data: &mut &[u8]) -> Result<Self> {
+        let marker = take(data, 1)?[0];
+        let value = match marker {
+            0x00..=0x7f => Value::Int(i64::from(marker)),
+            0x80..=0x8f => read_map(data, usize::from(marker & 0x0f))?,
+            0x90..=0x9f => read_array(data, usize::from(marker & 0x0f))?,
+            0xa0..=0xbf => read_str(data, usize::from(marker & 0x1f))?,
+            0xc0 => Value::Nil,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xc4..=0xc6 => {
+                let length = read_uint(data, 1 << (marker - 0xc4))? as usize;
+                Value::Bin(Bytes::copy_from_slice(take(data, length)?))
+            }
+            // extensions, e.g. `undefined` and dates of notepack.io
+            0xc7..=0xc9 => {
+                let length = read_uint(data, 1 << (marker - 0xc7))? as usize;
+                take(data, length + 1)?;
+                Value::Nil
+            }
+            0xd4..=0xd8 => {
+                take(data, (1 << (marker - 0xd4)) + 1)?;
+                Value::Nil
+            }
+            0xca => {
+                let bytes = take(data, 4)?;
+                Value::Float(f64::from(f32::from_be_bytes([
+                    bytes[0], bytes[1], bytes[2], bytes[3],
+                ])))
+            }
+            0xcb => Value::Float(f64::from_bits(read_uint(data, 8)?)),
+            0xcc..=0xcf => {
+                let value = read_uint(data, 1 << (marker - 0xcc))?;
+                i64::try_from(value).map_or(Value::Float(value as f64), Value::Int)
+            }
+            0xd0..=0xd3 => {
+                let size = 1 << (marker - 0xd0);
+                let value = read_uint(data, size)?;
+                // sign-extends the value from its size
+                let shift = 64 - 8 * size as u32;
+                Value::Int(((value << shift) as i64) >> shift)
+            }
+            0xd9..=0xdb => {
+                let length = read_uint(data, 1 << (marker - 0xd9))? as usize;
+                read_str(data, length)?
+            }
+            0xdc | 0xdd => {
+                let length = read_uint(data, 2 << (marker - 0xdc))? as usize;
+                read_array(data, length)?
+            }
+            0xde | 0xdf => {
+                let length = read_uint(data, 2 << (marker - 0xde))? as usize;
+                read_map(data, length)?
+            }
+            0xe0..=0xff => Value::Int(i64::from(marker as i8)),
+            0xc1 => return Err(Error::InvalidPacket()),
+        };
+        Ok(value)
+    }
+}
+
+impl From<&Json> for Value {
+    fn from(json: &Json) -> Self {
+        match json {
+            Json::Null => Value::Nil,
+            Json::Bool(value) => Value::Bool(*value),
+            Json::Number(number) => match number.as_i64() {
+                Some(value) => Value::Int(value),
+                None => Value::Float(number.as_f64().unwrap_or_default()),
+            },
+            Json::String(text) => Value::Str(text.clone()),
+            Json::Array(items) => Value::Array(items.iter().map(Value::from).collect()),
+            Json::Object(entries) => Value::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (Value::Str(key.clone()), Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn write_head(data: &mut Vec<u8>, marker: u8, bytes: &[u8]) {
+    data.push(marker);
+    data.extend_from_slice(bytes);
+}
+
+/// Writes the marker of a value of `length`, using the fixed format below
+/// `fixed_limit` if there's one, else the 8, 16 or 32 bit format.
+fn write_length(
+    data: &mut Vec<u8>,
+    length: usize,
+    fixed: Option<u8>,
+    fixed_limit: usize,
+    markers: [u8; 3],
+) {
+    match fixed {
+        Some(marker) if length < fixed_limit => data.push(marker | length as u8),
+        _ if length <= 0xff && markers[0] != 0 => write_head(data, markers[0], &[length as u8]),
+        _ if length <= 0xffff => write_head(data, markers[1], &(length as u16).to_be_bytes()),
+        _ => write_head(data, markers[2], &(length as u32).to_be_bytes()),
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if data.len() < length {
+        return Err(Error::IncompletePacket());
+    }
+    let (taken, rest) = data.split_at(length);
+    *data = rest;
+    Ok(taken)
+}
+
+/// Reads a big endian unsigned integer of `size` bytes.
+fn read_uint(data: &mut &[u8], size: usize) -> Result<u64> {
+    Ok(take(data, size)?
+        .iter()
+        .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+}
+
+fn read_str(data: &mut &[u8], length: usize) -> Result<Value> {
+    Ok(Value::Str(
+        std::str::from_utf8(take(data, length)?)?.to_owned(),
+    ))
+}
+
+fn read_array(data: &mut &[u8], length: usize) -> Result<Value> {
+    let items = (0..length)
+        .map(|_| Value::read(data))
+        .collect::<Result<_>>()?;
+    Ok(Value::Array(items))
+}
+
+fn read_map(data: &mut &[u8], length: usize) -> Result<Value> {
+    let entries = (0..length)
+        .map(|_| Ok((Value::read(data)?, Value::read(data)?)))
+        .collect::<Result<_>>()?;
+    Ok(Value::Map(entries))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let value = Value::Array(vec![
+            Value::Nil,
+            Value::Bool(true),
+            Value::Int(5),
+            Value::Int(-3),
+            Value::Int(200),
+            Value::Int(-200),
+            Value::Int(70_000),
+            Value::Int(-70_000),
+            Value::Int(i64::MAX),
+            Value::Int(i64::MIN),
+            Value::Float(1.5),
+            Value::Str("a".repeat(40)),
+            Value::Str("a".repeat(300)),
+            Value::Bin(Bytes::from_static(&[1, 2, 3])),
+            Value::Array(vec![Value::Nil; 20]),
+            Value::map([("key", Value::Int(1))]),
+        ]);
+        assert_eq!(Value::decode(&value.encode())?, value);
+        assert!(Value::decode(&value.encode()[..10]).is_err());
+
+        // what notepack.io encodes for `[-1, 300, undefined, 0.5]`
+        let data = [
+            0x94, 0xff, 0xcd, 0x01, 0x2c, 0xd4, 0x00, 0x00, 0xcb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0,
+        ];
+        let expected = [
+            Value::Int(-1),
+            Value::Int(300),
+            Value::Nil,
+            Value::Float(0.5),
+        ];
+        assert_eq!(Value::decode(&data)?, Value::Array(expected.to_vec()));
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
+
+use futures_util::future::join_all;
+
+use super::{
+    adapter::{Cluster, Operation},
+    admin::{Change, Monitor},
+    ServerSocket,
+};
+use crate::{error::Result, packet::Packet, Event, Payload};
+
+/// The sockets connected to a namespace and the rooms they joined.
+#[derive(Default)]
+pub(super) struct Rooms {
+    inner: RwLock<RoomsInner>,
+}
+
+#[derive(Default)]
+struct RoomsInner {
+    sockets: HashMap<String, ServerSocket>,
+    /// The ids of the sockets in each room.
+    rooms: HashMap<String, HashSet<String>>,
+}
+
+impl Rooms {
+    /// Adds a socket that connected, which joins the room of its id.
+    pub(super) fn insert(&self, socket: &ServerSocket) {
+        if let Ok(mut inner) = self.inner.write() {
+            inner.sockets.insert(socket.id().to_owned(), socket.clone());
+        }
+        self.join(socket.id(), socket.id().to_owned());
+    }
+
+    /// Removes a socket that disconnected from the namespace and its rooms.
+    pub(super) fn remove(&self, id: &str) {
+        if let Ok(mut inner) = self.inner.write() {
+            inner.sockets.remove(id);
+            inner.rooms.retain(|_, members| {
+                members.remove(id);
+                !members.is_empty()
+            });
+        }
+    }
+
+    /// Makes the socket with `id` join `room`, returns whether it wasn't in
+    /// the room yet.
+    pub(super) fn join(&self, id: &str, room: String) -> bool {
+        let Ok(mut inner) = self.inner.write() else {
+            return false;
+        };
+        inner.sockets.contains_key(id) && inner.rooms.entry(room).or_default().insert(id.to_owned())
+    }
+
+    /// Makes the socket with `id` leave `room`, returns whether it was in the
+    /// room.
+    pub(super) fn leave(&self, id: &str, room: &str) -> bool {
+        let Ok(mut inner) = self.inner.write() else {
+            return false;
+        };
+        let Some(members) = inner.rooms.get_mut(room) else {
+            return false;
+        };
+        let left = members.remove(id);
+        if members.is_empty() {
+            inner.rooms.remove(room);
+        }
+        left
+    }
+
+    /// The rooms the socket with `id` is in.
+    pub(super) fn rooms_of(&self, id: &str) -> Vec<String> {
+        let Ok(inner) = self.inner.read() else {
+            return Vec::new();
+        };
+        inner
+            .rooms
+            .iter()
+            .filter(|(_, members)| members.contains(id))
+            .map(|(room, _)| room.clone())
+            .collect()
+    }
+
+    /// The sockets in any of `rooms`, or all sockets if `rooms` is empty,
+    /// without the ones in any of `except`.
+    fn select(&self, rooms: &[String], except: &[String]) -> Vec<ServerSocket> {
+        let Ok(inner) = self.inner.read() else {
+            return Vec::new();
+        };
+        let members = |rooms: &[String]| -> HashSet<&String> {
+            rooms
+                .iter()
+                .filter_map(|room| inner.rooms.get(room))
+                .flatten()
+                .collect()
+        };
+        let excluded = members(except);
+        let included: Box<dyn Iterator<Item = &String>> = if rooms.is_empty() {
+            Box::new(inner.sockets.keys())
+        } else {
+            Box::new(members(rooms).into_iter())
+        };
+        included
+            .filter(|id| !excluded.contains(id))
+            .filter_map(|id| inner.sockets.get(id).cloned())
+            .collect()
+    }
+}
+
+/// A namespace of the [`super::Server`], to broadcast to its sockets, like
+/// `io.of(namespace)` of the JavaScript server. Clones share the namespace.
+#[derive(Clone)]
+pub struct Namespace {
+    nsp: String,
+    rooms: Arc<Rooms>,
+    cluster: Option<Cluster>,
+    /// Reports the changes of the sockets to the admin UI.
+    monitor: Option<Monitor>,
+}
+
+impl Namespace {
+    pub(super) fn new(nsp: String, cluster: Option<Cluster>) -> Self {
+        Namespace {
+            nsp,
+            rooms: Arc::default(),
+            cluster,
+            monitor: None,
+        }
+    }
+
+    pub(super) fn monitored(mut self, monitor: Monitor) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    pub(super) fn rooms(&self) -> &Arc<Rooms> {
+        &self.rooms
+    }
+
+    /// Reports a change of the sockets to the admin UI, if it's instrumented.
+    pub(super) fn report(&self, change: Change) {
+        if let Some(monitor) = &self.monitor {
+            monitor.report(change);
+        }
+    }
+
+    pub(super) fn join(&self, id: &str, room: String) {
+        if self.rooms.join(id, room.clone()) {
+            self.report(Change::Joined {
+                nsp: self.nsp.clone(),
+                room,
+                id: id.to_owned(),
+            });
+        }
+    }
+
+    pub(super) fn leave(&self, id: &str, room: &str) {
+        if self.rooms.leave(id, room) {
+            self.report(Change::Left {
+                nsp: self.nsp.clone(),
+                room: room.to_owned(),
+                id: id.to_owned(),
+            });
+        }
+    }
+
+    /// The name of the namespace, e.g. `/admin`.
+    pub fn name(&self) -> &str {
+        &self.nsp
+    }
+
+    /// The sockets connected to the namespace. With an adapter, the sockets
+    /// connected to the other servers of the cluster aren't included.
+    pub fn sockets(&self) -> Vec<ServerSocket> {
+        self.rooms.select(&[], &[])
+    }
+
+    /// Targets the sockets in `room`. Every socket is in the room of its id,
+    /// so this also reaches a single socket.
+    pub fn to<R: Into<String>>(&self, room: R) -> Broadcast {
+        Broadcast::new(self.clone()).to(room)
+    }
+
+    /// Targets all sockets but the ones in `room`.
+    pub fn except<R: Into<String>>(&self, room: R) -> Broadcast {
+        Broadcast::new(self.clone()).except(room)
+    }
+
+    /// Sends `data` as `event` to all sockets of the namespace.
+    pub async fn emit<E, D>(&self, event: E, data: D) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        Broadcast::new(self.clone()).emit(event, data).await
+    }
+
+    /// Applies a broadcast to the targeted sockets connected to this server.
+    pub(super) async fn apply(
+        &self,
+        rooms: &[String],
+        except: &[String],
+        operation: &Operation,
+    ) -> Result<()> {
+        let sockets = self.rooms.select(rooms, except);
+        match operation {
+            Operation::Emit(event, payload) => {
+                let packet =
+                    Packet::new_from_payload(payload.clone(), event.clone(), &self.nsp, None)?;
+                join_all(sockets.iter().map(|socket| socket.send(packet.clone()))).await;
+            }
+            Operation::Join(room) => {
+                for socket in sockets {
+                    self.join(socket.id(), room.clone());
+                }
+            }
+            Operation::Leave(room) => {
+                for socket in sockets {
+                    self.leave(socket.id(), room);
+                }
+            }
+            Operation::Disconnect(close) => {
+                join_all(
+                    sockets
+                        .iter()
+                        .map(|socket| socket.disconnect_by_server(*close)),
+                )
+                .await;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Namespace").field("nsp", &self.nsp).finish()
+    }
+}
+
+/// Sends an event to a selection of the sockets of a [`Namespace`], see
+/// [`Namespace::to`] and [`ServerSocket::to`].
+#[derive(Debug, Clone)]
+pub struct Broadcast {
+    namespace: Namespace,
+    rooms: Vec<String>,
+    except: Vec<String>,
+}
+
+impl Broadcast {
+    pub(super) fn new(namespace: Namespace) -> Self {
+        Broadcast {
+            namespace,
+            rooms: Vec::new(),
+            except: Vec::new(),
+        }
+    }
+
+    /// Also targets the sockets in `room`. A socket in several of the rooms
+    /// receives the event once.
+    pub fn to<R: Into<String>>(mut self, room: R) -> Self {
+        self.rooms.push(room.into());
+        self
+    }
+
+    /// Leaves out the sockets in `room`.
+    pub fn except<R: Into<String>>(mut self, room: R) -> Self {
+        self.except.push(room.into());
+        self
+    }
+
+    /// Sends `data` as `event` to the targeted sockets. Sockets that fail to
+    /// receive it, e.g. as they disconnect meanwhile, are skipped. Fails if
+    /// the adapter fails to publish the event to the other servers.
+    pub async fn emit<E, D>(self, event: E, data: D) -> Result<()>
+    where
+        E: Into<Event>,
+        D: Into<Payload>,
+    {
+        self.run(Operation::Emit(event.into(), data.into())).await
+    }
+
+    /// Makes the targeted sockets join `room`, like `socketsJoin` of the
+    /// JavaScript server. With an adapter, this includes the sockets connected
+    /// to the other servers of the cluster.
+    pub async fn join<R: Into<String>>(self, room: R) -> Result<()> {
+        self.run(Operation::Join(room.into())).await
+    }
+
+    /// Makes the targeted sockets leave `room`, like `socketsLeave` of the
+    /// JavaScript server.
+    pub async fn leave<R: Into<String>>(self, room: R) -> Result<()> {
+        self.run(Operation::Leave(room.into())).await
+    }
+
+    /// Disconnects the targeted sockets from the namespace, like
+    /// `disconnectSockets` of the JavaScript server. With `close`, the
+    /// connections of their clients are closed as well, which disconnects
+    /// them from all namespaces.
+    pub async fn disconnect(self, close: bool) -> Result<()> {
+        self.run(Operation::Disconnect(close)).await
+    }
+
+    async fn run(self, operation: Operation) -> Result<()> {
+        let namespace = &self.namespace;
+        namespace
+            .apply(&self.rooms, &self.except, &operation)
+            .await?;
+        match &namespace.cluster {
+            Some(cluster) => {
+                cluster
+                    .publish(&namespace.nsp, &self.rooms, &self.except, operation)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,763 @@
+//! Simulates a socket.io server in memory, so that applications can test how
+//! they cope with unreliable connections without a network or a real server.
+//!
+//! A [`MockServer`] is plugged into the async client as the HTTP layer of the
+//! polling transport via [`crate::asynchronous::ClientBuilder::http_layer`]
+//! and answers all requests itself. Tests script the failures on the server:
+//! [`MockServer::disconnect`] drops the connection like a network failure,
+//! [`MockServer::stop_pings`] stops the heartbeat so that the client runs into
+//! its ping timeout, [`MockServer::half_open`] swallows all requests like a
+//! half-open connection and [`MockServer::refuse_connections`] lets the next
+//! reconnect attempts fail, e.g. to provoke a reconnect storm.
+//!
+//! Pings, ping timeouts and the delays between reconnect attempts are all
+//! taken from the tokio clock. With paused time (`tokio::time::pause` or
+//! `#[tokio::test(start_paused = true)]`) they elapse as soon as the runtime is
+//! idle, so a scenario that spans minutes runs in milliseconds and always in
+//! the same order.
+//!
+//! The simulation guarantees:
+//! - the client sees the same packets and errors as from a real server that
+//!   behaves the scripted way, and reconnects through the regular code path,
+//!   including the configured backoff, the reconnect events and the
+//!   [`crate::EmitPolicy`]
+//! - a scripted failure affects the current connection only, the next
+//!   handshake starts a fresh, healthy session
+//! - a connection without a scripted failure stays up for any amount of
+//!   (virtual) time
+//!
+//! The server speaks engine.io over long-polling only, it doesn't offer an
+//! upgrade to websockets, and it serves a single client at a time. Binary
+//! payloads aren't supported.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rust_socketio::{
+//!     asynchronous::ClientBuilder, simulation::MockServer, Event, TransportType,
+//! };
+//!
+//! #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! async fn main() {
+//!     let server = MockServer::new();
+//!     let client = ClientBuilder::new("http://simulated/")
+//!         .http_layer(server.clone())
+//!         .transport_type(TransportType::Polling)
+//!         .reconnect_delay(1000, 1000)
+//!         .connect()
+//!         .await
+//!         .expect("connection failed");
+//!
+//!     // two attempts fail before the client is connected again
+//!     server.refuse_connections(2);
+//!     server.disconnect();
+//!     tokio::time::sleep(Duration::from_secs(60)).await;
+//!     assert!(server.is_connected());
+//!     assert_eq!(server.handshakes(), 4);
+//!
+//!     client.emit("status", "back online").await.unwrap();
+//!     tokio::time::sleep(Duration::from_secs(1)).await;
+//!     assert_eq!(server.received()[0].0, Event::from("status"));
+//! }
+//! ```
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::Instant};
+use tower::{BoxError, Layer, Service};
+
+use crate::{
+    asynchronous::{HttpRequest, HttpResponse},
+    packet::{Packet, PacketId},
+    Event, Payload,
+};
+
+/// Separates the engine.io packets in the body of a polling request.
+const SEPARATOR: &str = "\x1e";
+
+/// An in-memory socket.io server that a client is connected to via
+/// [`crate::asynchronous::ClientBuilder::http_layer`], see the
+/// [module documentation](self). Clones control the same server.
+#[derive(Clone)]
+pub struct MockServer {
+    state: Arc<Mutex<State>>,
+    /// Wakes up the pending long-polls once there is something to deliver.
+    changed: Arc<Notify>,
+}
+
+struct State {
+    ping_interval: Duration,
+    ping_timeout: Duration,
+    /// The number of handshakes that are still refused.
+    refused: usize,
+    handshakes: usize,
+    session: Option<Session>,
+    received: Vec<(Event, Payload)>,
+}
+
+/// The engine.io connection of the client.
+struct Session {
+    sid: String,
+    /// The namespace the client connected to.
+    nsp: Option<String>,
+    /// The packets for the next long-poll.
+    queue: VecDeque<String>,
+    pings: bool,
+    next_ping: Instant,
+    /// Nothing gets through anymore, requests never complete.
+    stalled: bool,
+}
+
+impl MockServer {
+    /// Creates a server with the heartbeat defaults of socket.io, a ping every
+    /// 25 seconds that must be answered within 20 seconds.
+    pub fn new() -> Self {
+        MockServer {
+            state: Arc::new(Mutex::new(State {
+                ping_interval: Duration::from_secs(25),
+                ping_timeout: Duration::from_secs(20),
+                refused: 0,
+                handshakes: 0,
+                session: None,
+                received: Vec::new(),
+            })),
+            changed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Sets the interval of the pings and the time the client has to answer
+    /// them, which the client learns in the handshake. Together they are the
+    /// time after which the client notices a missing ping.
+    pub fn ping(self, interval: Duration, timeout: Duration) -> Self {
+        {
+            let mut state = self.lock();
+            state.ping_interval = interval;
+            state.ping_timeout = timeout;
+        }
+        self
+    }
+
+    /// Emits `event` to the connected client. Returns `false` if no client is
+    /// connected, in which case the event is dropped like on a real server.
+    pub fn emit<E: Into<Event>>(&self, event: E, data: Value) -> bool {
+        let event: Event = event.into();
+        let mut state = self.lock();
+        let Some((session, nsp)) = state
+            .session
+            .as_mut()
+            .and_then(|session| session.nsp.clone().map(|nsp| (session, nsp)))
+        else {
+            return false;
+        };
+        let data = json!([event.as_str(), data]).to_string();
+        session.push(Packet::new(PacketId::Event, nsp, Some(data), None, 0, None));
+        drop(state);
+
+        self.changed.notify_waiters();
+        true
+    }
+
+    /// Drops the connection without a goodbye, like a network failure. The
+    /// pending and all further requests of the client fail until it connects
+    /// again.
+    pub fn disconnect(&self) {
+        self.lock().session = None;
+        self.changed.notify_waiters();
+    }
+
+    /// Stops sending pings on the current connection, like a server that
+    /// hangs, so that the client closes it once the ping timeout passed.
+    pub fn stop_pings(&self) {
+        if let Some(session) = self.lock().session.as_mut() {
+            session.pings = false;
+        }
+    }
+
+    /// Makes the current connection half-open, like after a NAT gateway
+    /// dropped it silently: the pending and all further requests of the client
+    /// never complete, neither with a response nor with an error.
+    pub fn half_open(&self) {
+        if let Some(session) = self.lock().session.as_mut() {
+            session.stalled = true;
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// Refuses the next `count` handshakes, like a server that is down. With
+    /// [`crate::TransportType::Any`], the client retries a refused handshake
+    /// right away before it gives the attempt up, so that every attempt takes
+    /// two handshakes.
+    pub fn refuse_connections(&self, count: usize) {
+        self.lock().refused = count;
+    }
+
+    /// The number of handshakes so far, including the refused ones.
+    pub fn handshakes(&self) -> usize {
+        self.lock().handshakes
+    }
+
+    /// Whether a client is connected to a namespace.
+    pub fn is_connected(&self) -> bool {
+        self.lock()
+            .session
+            .as_ref()
+            .is_some_and(|session| session.nsp.is_some())
+    }
+
+    /// The events emitted by the client so far, in the order they arrived.
+    pub fn received(&self) -> Vec<(Event, Payload)> {
+        self.lock().received.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // the state stays consistent even if a test panicked while holding it
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn handle(self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+        let sid = request
+            .url()
+            .query_pairs()
+            .find(|(key, _)| key == "sid")
+            .map(|(_, sid)| sid.into_owned());
+        if sid.as_deref().is_some_and(|sid| self.is_stalled(sid)) {
+            return std::future::pending().await;
+        }
+        let body = match (request.method().as_str(), sid) {
+            ("GET", None) => self.open()?,
+            ("GET", Some(sid)) => self.poll(&sid).await?,
+            ("POST", Some(sid)) => {
+                let body = request.body().and_then(|body| body.as_bytes());
+                self.receive(&sid, body.unwrap_or_default())?;
+                String::from("ok")
+            }
+            _ => return Err("unexpected request".into()),
+        };
+        Ok(HttpResponse::from(http::Response::new(body)))
+    }
+
+    /// Answers a handshake.
+    fn open(&self) -> Result<String, BoxError> {
+        let mut state = self.lock();
+        state.handshakes += 1;
+        if state.refused > 0 {
+            state.refused -= 1;
+            return Err("connection refused".into());
+        }
+
+        let sid = format!("mock-{}", state.handshakes);
+        let handshake = json!({
+            "sid": sid,
+            "upgrades": [],
+            "pingInterval": state.ping_interval.as_millis() as u64,
+            "pingTimeout": state.ping_timeout.as_millis() as u64,
+        });
+        // a new session replaces the previous one, like after a restart
+        state.session = Some(Session {
+            sid,
+            nsp: None,
+            queue: VecDeque::new(),
+            pings: true,
+            next_ping: Instant::now() + state.ping_interval,
+            stalled: false,
+        });
+        drop(state);
+
+        self.changed.notify_waiters();
+        Ok(format!("0{handshake}"))
+    }
+
+    /// Answers a long-poll once there are packets for the client.
+    async fn poll(&self, sid: &str) -> Result<String, BoxError> {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            // don't miss a notification while the state is checked
+            changed.as_mut().enable();
+
+            if self.is_stalled(sid) {
+                return std::future::pending().await;
+            }
+            let next_ping = {
+                let mut state = self.lock();
+                let interval = state.ping_interval;
+                let session = state.session_mut(sid)?;
+                if session.pings && Instant::now() >= session.next_ping {
+                    session.queue.push_back(String::from("2"));
+                    session.next_ping += interval;
+                }
+                if !session.queue.is_empty() {
+                    let packets = Vec::from(std::mem::take(&mut session.queue));
+                    return Ok(packets.join(SEPARATOR));
+                }
+                session.pings.then_some(session.next_ping)
+            };
+
+            match next_ping {
+                Some(next_ping) => {
+                    let _ = tokio::time::timeout_at(next_ping, changed).await;
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    fn is_stalled(&self, sid: &str) -> bool {
+        self.lock()
+            .session
+            .as_ref()
+            .is_some_and(|session| session.sid == sid && session.stalled)
+    }
+
+    /// Handles the packets sent by the client.
+    fn receive(&self, sid: &str, body: &[u8]) -> Result<(), BoxError> {
+        let body = std::str::from_utf8(body)?;
+        let mut state = self.lock();
+        for packet in body.split(SEPARATOR) {
+            match packet.as_bytes().first() {
+                // the client closes the connection
+                Some(b'1') => {
+                    state.session_mut(sid)?;
+                    state.session = None;
+                }
+                Some(b'4') => {
+                    let packet = Packet::try_from(Bytes::copy_from_slice(&packet.as_bytes()[1..]))?;
+                    state.handle_packet(sid, packet)?;
+                }
+                // pongs and noops
+                _ => {
+                    state.session_mut(sid)?;
+                }
+            }
+        }
+        drop(state);
+
+        self.changed.notify_waiters();
+        Ok(())
+    }
+}
+
+impl State {
+    /// The session of the client, fails like a reset connection if it was
+    /// dropped.
+    fn session_mut(&mut self, sid: &str) -> Result<&mut Session, BoxError> {
+        self.session
+            .as_mut()
+            .filter(|session| session.sid == sid)
+            .ok_or_else(|| "connection reset".into())
+    }
+
+    fn handle_packet(&mut self, sid: &str, packet: Packet) -> Result<(), BoxError> {
+        let session = self.session_mut(sid)?;
+        match packet.packet_type {
+            PacketId::Connect => {
+                let data = json!({ "sid": session.sid }).to_string();
+                session.push(Packet::new(
+                    PacketId::Connect,
+                    packet.nsp.clone(),
+                    Some(data),
+                    None,
+                    0,
+                    None,
+                ));
+                session.nsp = Some(packet.nsp);
+            }
+            PacketId::Disconnect => session.nsp = None,
+            PacketId::Event => {
+                let mut args: Vec<Value> =
+                    serde_json::from_str(packet.data.as_deref().unwrap_or("[]"))?;
+                if args.is_empty() {
+                    return Ok(());
+                }
+                let event = match args.remove(0) {
+                    Value::String(event) => Event::from(event.as_str()),
+                    _ => Event::Message,
+                };
+                self.received.push((event, Payload::Text(args)));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Session {
+    /// Queues a socket.io packet for the next long-poll.
+    fn push(&mut self, packet: Packet) {
+        let packet = Bytes::from(packet);
+        self.queue
+            .push_back(format!("4{}", String::from_utf8_lossy(&packet)));
+    }
+}
+
+impl Default for MockServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for MockServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.lock();
+        f.debug_struct("MockServer")
+            .field("ping_interval", &state.ping_interval)
+            .field("ping_timeout", &state.ping_timeout)
+            .field("handshakes", &state.handshakes)
+            .field("connected", &state.session.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Replaces the HTTP client of the polling transport, the requests never reach
+/// the network.
+impl<S> Layer<S> for MockServer {
+    type Service = MockServer;
+
+    fn layer(&self, _inner: S) -> Self::Service {
+        self.clone()
+    }
+}
+
+impl Service<HttpRequest> for MockServer {
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<HttpResponse, BoxError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        Box::pin(self.clone().handle(request))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use futures_util::FutureExt;
+    use tokio::time::sleep;
+
+    use super::*;
+    use crate::{
+        asynchronous::{Client, ClientBuilder},
+        PendingStats, ReconnectEvent, TransportType, TryEmitError,
+    };
+
+    async fn connect(server: &MockServer, builder: ClientBuilder) -> Client {
+        builder
+            .http_layer(server.clone())
+            .connect()
+            .await
+            .expect("the mock server accepts the connection")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stays_connected() {
+        let server = MockServer::new().ping(Duration::from_secs(1), Duration::from_secs(1));
+        let client = connect(&server, ClientBuilder::new("http://simulated/")).await;
+
+        sleep(Duration::from_secs(3600)).await;
+        assert_eq!(server.handshakes(), 1);
+        assert!(server.is_connected());
+
+        client.emit("test", json!({ "ok": true })).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            server.received(),
+            vec![(Event::from("test"), Payload::from(json!({ "ok": true })))]
+        );
+
+        // the server doesn't answer acks
+        client
+            .emit_with_ack("test", json!(1), Duration::from_secs(60), |_, _| {
+                async {}.boxed()
+            })
+            .await
+            .unwrap();
+        assert_eq!(client.pending().await.unwrap().acks, 1);
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_disconnect() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .reconnect_delay(100, 100)
+            .on("greeting", move |payload, _| {
+                let _ = tx.send(payload);
+                async {}.boxed()
+            });
+        let client = connect(&server, builder).await;
+
+        server.disconnect();
+        assert!(!server.emit("greeting", json!("lost")));
+        sleep(Duration::from_secs(60)).await;
+        assert_eq!(server.handshakes(), 2);
+
+        assert!(server.emit("greeting", json!("hello")));
+        let payload = rx.recv().await.unwrap();
+        assert_eq!(payload, Payload::from(json!("hello")));
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_hooks() {
+        static CONNECTS: AtomicU32 = AtomicU32::new(0);
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .reconnect_delay(100, 100)
+            .before_connect(|| {
+                CONNECTS.fetch_add(1, Ordering::SeqCst);
+                async {}.boxed()
+            })
+            .after_connect(|client| {
+                async move {
+                    client.emit("subscribe", json!("prices")).await.unwrap();
+                }
+                .boxed()
+            });
+        let client = connect(&server, builder).await;
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(server.received().len(), 1);
+
+        server.disconnect();
+        sleep(Duration::from_secs(5)).await;
+        assert_eq!(CONNECTS.load(Ordering::SeqCst), 2);
+        let subscribe = (Event::from("subscribe"), Payload::from(json!("prices")));
+        assert_eq!(server.received(), vec![subscribe.clone(), subscribe]);
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resubscribe_on_connect() {
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .reconnect_delay(100, 100)
+            .resubscribe_on_connect(vec![
+                ("subscribe", json!("prices")),
+                ("subscribe", json!("news")),
+            ]);
+        let client = connect(&server, builder).await;
+        sleep(Duration::from_secs(1)).await;
+
+        server.disconnect();
+        sleep(Duration::from_secs(5)).await;
+        let subscriptions = vec![
+            (Event::from("subscribe"), Payload::from(json!("prices"))),
+            (Event::from("subscribe"), Payload::from(json!("news"))),
+        ];
+        assert_eq!(
+            server.received(),
+            [subscriptions.clone(), subscriptions].concat()
+        );
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_try_emit() {
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/").emit_queue_capacity(1);
+        let client = connect(&server, builder).await;
+
+        assert!(client.try_emit("first", json!(1)).is_ok());
+        // the queue isn't drained before the task yields
+        let full = client.try_emit("second", json!(2)).unwrap_err();
+        assert!(matches!(full, TryEmitError::Full(..)));
+        assert_eq!(full.into_inner(), ("second".into(), json!(2).into()));
+        sleep(Duration::from_secs(1)).await;
+        assert!(client.try_emit("third", json!(3)).is_ok());
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(
+            server.received(),
+            vec![
+                (Event::from("first"), Payload::from(json!(1))),
+                (Event::from("third"), Payload::from(json!(3))),
+            ]
+        );
+
+        client.disconnect().await.unwrap();
+        assert!(matches!(
+            client.try_emit("fourth", json!(4)),
+            Err(TryEmitError::Disconnected(..))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ping_timeout() {
+        let server = MockServer::new().ping(Duration::from_secs(5), Duration::from_secs(5));
+        let builder = ClientBuilder::new("http://simulated/").reconnect_delay(100, 100);
+        let client = connect(&server, builder).await;
+
+        server.stop_pings();
+        sleep(Duration::from_secs(9)).await;
+        assert_eq!(server.handshakes(), 1);
+        sleep(Duration::from_secs(10)).await;
+        assert_eq!(server.handshakes(), 2);
+        assert!(server.is_connected());
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_half_open() {
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .reconnect_delay(100, 100)
+            .half_open_probe(Duration::from_secs(5), Duration::from_secs(2));
+        let client = connect(&server, builder).await;
+
+        // without the probe, the connection is only closed after the ping
+        // interval and the ping timeout, i.e. 45 seconds
+        server.half_open();
+        sleep(Duration::from_secs(4)).await;
+        assert_eq!(server.handshakes(), 1);
+        sleep(Duration::from_secs(4)).await;
+        assert_eq!(server.handshakes(), 2);
+        assert!(server.is_connected());
+
+        // an idle but healthy connection passes the probes
+        sleep(Duration::from_secs(60)).await;
+        assert_eq!(server.handshakes(), 2);
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_storm() {
+        static ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .transport_type(TransportType::Polling)
+            .reconnect_delay(2000, 2000)
+            .on_reconnect_event(|event| {
+                if let ReconnectEvent::Attempt(_) = event {
+                    ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+                }
+                async {}.boxed()
+            });
+        let client = connect(&server, builder).await;
+
+        server.refuse_connections(10);
+        server.disconnect();
+        sleep(Duration::from_secs(5)).await;
+        assert!(!server.is_connected());
+        sleep(Duration::from_secs(60)).await;
+        assert!(server.is_connected());
+        // one handshake per attempt with the polling transport
+        assert_eq!(server.handshakes(), 12);
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 11);
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_volatile_emit() {
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .transport_type(TransportType::Polling)
+            .reconnect_delay(100, 100)
+            .emit_policy(crate::EmitPolicy::Buffer);
+        let client = connect(&server, builder).await;
+
+        server.refuse_connections(3);
+        server.disconnect();
+        sleep(Duration::from_millis(10)).await;
+        let volatile = crate::EmitOptions::new().volatile(true);
+        client
+            .emit_opts("position", json!(1), volatile.clone())
+            .await
+            .unwrap();
+        client.emit("status", json!("away")).await.unwrap();
+        assert_eq!(client.pending().await.unwrap().buffered, 1);
+
+        sleep(Duration::from_secs(5)).await;
+        client
+            .emit_opts("position", json!(2), volatile)
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            server.received(),
+            vec![
+                (Event::from("status"), Payload::from(json!("away"))),
+                (Event::from("position"), Payload::from(json!(2))),
+            ]
+        );
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_emit_buffer_limit() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let server = MockServer::new();
+        let builder = ClientBuilder::new("http://simulated/")
+            .transport_type(TransportType::Polling)
+            .reconnect_delay(100, 100)
+            .emit_policy(crate::EmitPolicy::Buffer)
+            .emit_buffer_limit(2, crate::BufferOverflow::DropOldest)
+            .on(Event::Error, move |err, _| {
+                let _ = tx.send(err);
+                async {}.boxed()
+            });
+        let client = connect(&server, builder).await;
+
+        server.refuse_connections(3);
+        server.disconnect();
+        sleep(Duration::from_millis(10)).await;
+        for value in 1..=3 {
+            client.emit("value", json!(value)).await.unwrap();
+        }
+        let pending = client.pending().await.unwrap();
+        assert_eq!((pending.buffered, pending.buffered_bytes), (2, 2));
+        sleep(Duration::from_secs(5)).await;
+        assert!(server.is_connected());
+        assert_eq!(
+            server.received(),
+            vec![
+                (Event::from("value"), Payload::from(json!(2))),
+                (Event::from("value"), Payload::from(json!(3))),
+            ]
+        );
+        assert_eq!(client.pending().await.unwrap(), PendingStats::default());
+
+        // the failing reconnect attempts are reported as well
+        let dropped = Payload::from(crate::Error::EmitDropped(Event::from("value")).to_string());
+        let mut errors = Vec::new();
+        while let Ok(err) = rx.try_recv() {
+            errors.push(err);
+        }
+        assert_eq!(errors.iter().filter(|err| **err == dropped).count(), 1);
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failover() {
+        let server = MockServer::new();
+        server.refuse_connections(1);
+        let result = ClientBuilder::new("http://primary/")
+            .http_layer(server.clone())
+            .transport_type(TransportType::Polling)
+            .connect()
+            .await;
+        assert!(result.is_err());
+
+        // the handshake with the first server is refused, the second accepts it
+        server.refuse_connections(1);
+        let builder = ClientBuilder::new(["http://primary/", "http://backup/"])
+            .transport_type(TransportType::Polling);
+        let client = connect(&server, builder).await;
+        assert_eq!(server.handshakes(), 3);
+        sleep(Duration::from_secs(1)).await;
+        assert!(server.is_connected());
+        client.disconnect().await.unwrap();
+    }
+}
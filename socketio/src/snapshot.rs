@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{ConnectionState, NamespaceState, TransportKind};
+
+/// The state of a client that is checked on every emit, as returned by
+/// `Client::snapshot`. It's read from a single atomic without taking a lock,
+/// so that it stays cheap under concurrent emits, and its fields are
+/// consistent with each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientSnapshot {
+    /// Whether the client is connected or reconnecting.
+    pub connection: ConnectionState,
+    /// The state of the namespace of the client, `None` until it connected
+    /// for the first time.
+    pub namespace: Option<NamespaceState>,
+    /// The transport of the current connection, `None` until it connected
+    /// for the first time.
+    pub transport: Option<TransportKind>,
+}
+
+impl ClientSnapshot {
+    /// Whether the client is connected and the server accepted its
+    /// namespace, i.e. emits are sent right away.
+    pub fn is_connected(&self) -> bool {
+        self.connection == ConnectionState::Connected
+            && self.namespace == Some(NamespaceState::Connected)
+    }
+}
+
+/// Holds a [`ClientSnapshot`] packed into one atomic, a byte per field.
+#[derive(Debug)]
+pub(crate) struct SnapshotCell {
+    packed: AtomicU32,
+}
+
+const CONNECTION: u32 = 0;
+const NAMESPACE: u32 = 8;
+const TRANSPORT: u32 = 16;
+
+impl SnapshotCell {
+    pub(crate) fn new(connection: ConnectionState) -> Self {
+        SnapshotCell {
+            packed: AtomicU32::new(encode_connection(connection) << CONNECTION),
+        }
+    }
+
+    pub(crate) fn load(&self) -> ClientSnapshot {
+        let packed = self.packed.load(Ordering::Acquire);
+        let byte = |shift: u32| (packed >> shift) & 0xff;
+        ClientSnapshot {
+            connection: decode_connection(byte(CONNECTION)),
+            namespace: decode_namespace(byte(NAMESPACE)),
+            transport: decode_transport(byte(TRANSPORT)),
+        }
+    }
+
+    pub(crate) fn connection(&self) -> ConnectionState {
+        decode_connection((self.packed.load(Ordering::Acquire) >> CONNECTION) & 0xff)
+    }
+
+    pub(crate) fn set_connection(&self, state: ConnectionState) {
+        self.set(CONNECTION, encode_connection(state));
+    }
+
+    pub(crate) fn set_namespace(&self, state: NamespaceState) {
+        self.set(NAMESPACE, encode_namespace(state));
+    }
+
+    /// Sets the transport and returns the previous one.
+    pub(crate) fn set_transport(&self, kind: TransportKind) -> Option<TransportKind> {
+        let previous = self.set(TRANSPORT, encode_transport(kind));
+        decode_transport((previous >> TRANSPORT) & 0xff)
+    }
+
+    /// Sets the byte at `shift` and returns the previous packed value.
+    fn set(&self, shift: u32, value: u32) -> u32 {
+        let mask = !(0xff << shift);
+        // the closure always returns `Some`, so the update can't fail
+        self.packed
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |packed| {
+                Some(packed & mask | value << shift)
+            })
+            .unwrap_or_else(|packed| packed)
+    }
+}
+
+fn encode_connection(state: ConnectionState) -> u32 {
+    match state {
+        ConnectionState::Connected => 0,
+        ConnectionState::Reconnecting => 1,
+        ConnectionState::Disconnected => 2,
+    }
+}
+
+fn decode_connection(byte: u32) -> ConnectionState {
+    match byte {
+        0 => ConnectionState::Connected,
+        1 => ConnectionState::Reconnecting,
+        _ => ConnectionState::Disconnected,
+    }
+}
+
+fn encode_namespace(state: NamespaceState) -> u32 {
+    match state {
+        NamespaceState::Pending => 1,
+        NamespaceState::Connected => 2,
+        NamespaceState::Failed => 3,
+        NamespaceState::Disconnected => 4,
+    }
+}
+
+fn decode_namespace(byte: u32) -> Option<NamespaceState> {
+    match byte {
+        1 => Some(NamespaceState::Pending),
+        2 => Some(NamespaceState::Connected),
+        3 => Some(NamespaceState::Failed),
+        4 => Some(NamespaceState::Disconnected),
+        _ => None,
+    }
+}
+
+fn encode_transport(kind: TransportKind) -> u32 {
+    match kind {
+        TransportKind::Polling => 1,
+        TransportKind::Websocket => 2,
+        TransportKind::WebsocketSecure => 3,
+        #[cfg(any(test, feature = "test-support"))]
+        TransportKind::Loopback => 4,
+        #[cfg(any(test, feature = "test-support"))]
+        TransportKind::Mock => 5,
+        TransportKind::Custom => 6,
+        // reported as no transport
+        _ => 0,
+    }
+}
+
+fn decode_transport(byte: u32) -> Option<TransportKind> {
+    match byte {
+        1 => Some(TransportKind::Polling),
+        2 => Some(TransportKind::Websocket),
+        3 => Some(TransportKind::WebsocketSecure),
+        #[cfg(any(test, feature = "test-support"))]
+        4 => Some(TransportKind::Loopback),
+        #[cfg(any(test, feature = "test-support"))]
+        5 => Some(TransportKind::Mock),
+        6 => Some(TransportKind::Custom),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let cell = SnapshotCell::new(ConnectionState::Connected);
+        assert_eq!(
+            cell.load(),
+            ClientSnapshot {
+                connection: ConnectionState::Connected,
+                namespace: None,
+                transport: None,
+            }
+        );
+
+        assert_eq!(cell.set_transport(TransportKind::WebsocketSecure), None);
+        assert_eq!(
+            cell.set_transport(TransportKind::WebsocketSecure),
+            Some(TransportKind::WebsocketSecure)
+        );
+        cell.set_namespace(NamespaceState::Connected);
+        assert!(cell.load().is_connected());
+
+        cell.set_connection(ConnectionState::Reconnecting);
+        cell.set_namespace(NamespaceState::Pending);
+        let snapshot = cell.load();
+        assert!(!snapshot.is_connected());
+        assert_eq!(snapshot.connection, ConnectionState::Reconnecting);
+        assert_eq!(snapshot.namespace, Some(NamespaceState::Pending));
+        assert_eq!(snapshot.transport, Some(TransportKind::WebsocketSecure));
+        assert_eq!(cell.connection(), ConnectionState::Reconnecting);
+    }
+}
@@ -1,8 +1,11 @@
 use crate::error::{Error, Result};
 use crate::packet::{Packet, PacketId};
+use crate::Utf8Policy;
 use bytes::Bytes;
-use rust_engineio::{Client as EngineClient, Packet as EnginePacket, PacketId as EnginePacketId};
-use std::convert::TryFrom;
+use rust_engineio::{
+    BufferPoolStats, Client as EngineClient, HttpVersion, Packet as EnginePacket,
+    PacketId as EnginePacketId, TransportKind,
+};
 use std::sync::{atomic::AtomicBool, Arc};
 use std::{fmt::Debug, sync::atomic::Ordering};
 
@@ -14,6 +17,8 @@ pub(crate) struct Socket {
     //TODO: 0.4.0 refactor this
     engine_client: Arc<EngineClient>,
     connected: Arc<AtomicBool>,
+    small_event_threshold: Option<usize>,
+    utf8_policy: Utf8Policy,
 }
 
 impl Socket {
@@ -23,9 +28,24 @@ impl Socket {
         Ok(Socket {
             engine_client: Arc::new(engine_client),
             connected: Arc::new(AtomicBool::default()),
+            small_event_threshold: None,
+            utf8_policy: Utf8Policy::default(),
         })
     }
 
+    /// Encodes text events up to `threshold` bytes on the stack, see
+    /// [`Packet::encode_small_event`].
+    pub(super) fn with_small_event_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.small_event_threshold = threshold;
+        self
+    }
+
+    /// Decodes received text packets that aren't valid UTF-8 according to `policy`.
+    pub(super) fn with_utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
     /// Connects to the server. This includes a connection of the underlying
     /// engine.io client and afterwards an opening socket.io request.
     pub fn connect(&self) -> Result<()> {
@@ -50,6 +70,24 @@ impl Socket {
         Ok(())
     }
 
+    /// Closes the connection right away without sending a `Disconnect` or any
+    /// other packet.
+    pub fn abort(&self) {
+        self.engine_client.abort();
+        self.connected.store(false, Ordering::Release);
+    }
+
+    /// Sends an already encoded `socket.io` packet without attachments.
+    fn send_encoded(&self, encoded: Bytes) -> Result<()> {
+        if !self.is_engineio_connected()? || !self.connected.load(Ordering::Acquire) {
+            return Err(Error::IllegalActionBeforeOpen());
+        }
+
+        let engine_packet = EnginePacket::new(EnginePacketId::Message, encoded);
+        self.engine_client.emit(engine_packet)?;
+        Ok(())
+    }
+
     /// Sends a `socket.io` packet to the server using the `engine.io` client.
     pub fn send(&self, packet: Packet) -> Result<()> {
         if !self.is_engineio_connected()? || !self.connected.load(Ordering::Acquire) {
@@ -73,6 +111,12 @@ impl Socket {
     /// Emits to certain event with given data. The data needs to be JSON,
     /// otherwise this returns an `InvalidJson` error.
     pub fn emit(&self, nsp: &str, event: Event, data: Payload) -> Result<()> {
+        if let (Some(threshold), Payload::Text(values)) = (self.small_event_threshold, &data) {
+            if let Some(encoded) = Packet::encode_small_event(nsp, &event, values, threshold) {
+                return self.send_encoded(encoded);
+            }
+        }
+
         let socket_packet = Packet::new_from_payload(data, event, nsp, None)?;
 
         self.send(socket_packet)
@@ -119,7 +163,7 @@ impl Socket {
 
     /// Handles new incoming engineio packets
     fn handle_engineio_packet(&self, packet: EnginePacket) -> Result<Packet> {
-        let mut socket_packet = Packet::try_from(&packet.data)?;
+        let mut socket_packet = Packet::decode(&packet.data, self.utf8_policy)?;
 
         // Only handle attachments if there are any
         if socket_packet.attachment_count > 0 {
@@ -155,4 +199,16 @@ impl Socket {
     fn is_engineio_connected(&self) -> Result<bool> {
         Ok(self.engine_client.is_connected()?)
     }
+
+    pub(crate) fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.engine_client.buffer_pool_stats()
+    }
+
+    pub(crate) fn http_version(&self) -> Option<HttpVersion> {
+        self.engine_client.http_version()
+    }
+
+    pub(crate) fn transport_kind(&self) -> TransportKind {
+        self.engine_client.transport_kind()
+    }
 }
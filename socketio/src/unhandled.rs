@@ -0,0 +1,15 @@
+/// Describes what happens to a received event that neither has a handler of its
+/// own nor is caught by an `on_any` callback, see
+/// [`crate::ClientBuilder::unhandled_policy`]. A hook registered via
+/// [`crate::ClientBuilder::on_unhandled`] is called regardless of the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnhandledPolicy {
+    /// Drop the event silently. This is the default.
+    #[default]
+    Ignore,
+    /// Log a warning about the event.
+    Warn,
+    /// Report [`crate::Error::UnhandledEvent`] to the callbacks of
+    /// [`crate::Event::Error`].
+    Error,
+}
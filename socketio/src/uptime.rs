@@ -0,0 +1,137 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Statistics about the connection of a client to its namespace, as returned
+/// by `Client::uptime_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UptimeStats {
+    /// When the client connected for the first time.
+    pub connected_at: Option<SystemTime>,
+    /// When the client reconnected for the last time.
+    pub last_reconnect_at: Option<SystemTime>,
+    /// Whether the client is currently connected.
+    pub connected: bool,
+    /// The time the client was connected in total, including the current connection.
+    pub uptime: Duration,
+    /// The time the client was disconnected in total after it connected for
+    /// the first time, including the current disconnect.
+    pub downtime: Duration,
+    /// How often the connection was lost or closed.
+    pub disconnects: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    stats: UptimeStats,
+    // start of the current connection or disconnect
+    since: Option<Instant>,
+}
+
+/// Records the connects and disconnects of a client. Cloning yields a handle to
+/// the same records.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UptimeTracker {
+    state: Arc<Mutex<State>>,
+}
+
+impl UptimeTracker {
+    /// Records that the client connected to its namespace, does nothing if it's
+    /// connected already.
+    pub(crate) fn connected(&self) {
+        self.connected_at(Instant::now(), SystemTime::now());
+    }
+
+    /// Records that the connection was lost or closed, does nothing if the
+    /// client isn't connected.
+    pub(crate) fn disconnected(&self) {
+        self.disconnected_at(Instant::now());
+    }
+
+    /// The statistics up to now.
+    pub(crate) fn stats(&self) -> UptimeStats {
+        self.stats_at(Instant::now())
+    }
+
+    fn connected_at(&self, now: Instant, time: SystemTime) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.stats.connected {
+            return;
+        }
+        match state.since {
+            Some(since) => {
+                state.stats.downtime += now.saturating_duration_since(since);
+                state.stats.last_reconnect_at = Some(time);
+            }
+            None => state.stats.connected_at = Some(time),
+        }
+        state.stats.connected = true;
+        state.since = Some(now);
+    }
+
+    fn disconnected_at(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if !state.stats.connected {
+            return;
+        }
+        if let Some(since) = state.since {
+            state.stats.uptime += now.saturating_duration_since(since);
+        }
+        state.stats.connected = false;
+        state.stats.disconnects += 1;
+        state.since = Some(now);
+    }
+
+    fn stats_at(&self, now: Instant) -> UptimeStats {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stats = state.stats;
+        if let Some(since) = state.since {
+            let current = now.saturating_duration_since(since);
+            if stats.connected {
+                stats.uptime += current;
+            } else {
+                stats.downtime += current;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uptime_stats() {
+        let tracker = UptimeTracker::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        assert_eq!(tracker.stats_at(at(1)), UptimeStats::default());
+
+        tracker.connected_at(at(1), SystemTime::UNIX_EPOCH);
+        // connects while connected are ignored
+        tracker.connected_at(at(2), SystemTime::now());
+        let stats = tracker.stats_at(at(4));
+        assert_eq!(stats.connected_at, Some(SystemTime::UNIX_EPOCH));
+        assert_eq!(stats.uptime, Duration::from_secs(3));
+        assert_eq!(stats.downtime, Duration::ZERO);
+
+        tracker.disconnected_at(at(5));
+        tracker.disconnected_at(at(6));
+        let stats = tracker.stats_at(at(7));
+        assert!(!stats.connected);
+        assert_eq!(stats.disconnects, 1);
+        assert_eq!(stats.uptime, Duration::from_secs(4));
+        assert_eq!(stats.downtime, Duration::from_secs(2));
+
+        let reconnected = SystemTime::UNIX_EPOCH + Duration::from_secs(10);
+        tracker.connected_at(at(8), reconnected);
+        let stats = tracker.stats_at(at(10));
+        assert!(stats.connected);
+        assert_eq!(stats.connected_at, Some(SystemTime::UNIX_EPOCH));
+        assert_eq!(stats.last_reconnect_at, Some(reconnected));
+        assert_eq!(stats.uptime, Duration::from_secs(6));
+        assert_eq!(stats.downtime, Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,223 @@
+use std::env;
+
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    QueryValue,
+};
+
+/// Builds the URL of a server from its parts, as an alternative to assembling
+/// it with `format!`, which easily produces subtly wrong URLs, e.g. with
+/// unescaped characters or duplicated slashes.
+///
+/// The path may contain parameters like `{tenant}`, which are replaced by the
+/// percent-encoded values set via [`UrlBuilder::param`]. The host, the path and
+/// the query values may refer to environment variables like `${API_HOST}`.
+/// [`UrlBuilder::build`] validates the URL right away, so that a typo fails
+/// before the client connects rather than on every reconnect.
+///
+/// # Example
+/// ```rust
+/// use rust_socketio::{ClientBuilder, UrlBuilder};
+///
+/// let url = UrlBuilder::new("localhost:4200")
+///     .secure(false)
+///     .path("/tenants/{tenant}/socket.io/")
+///     .param("tenant", "acme corp")
+///     .query("token", "a&b")
+///     .build()
+///     .expect("invalid url");
+/// assert_eq!(
+///     url.as_str(),
+///     "http://localhost:4200/tenants/acme%20corp/socket.io/?token=a%26b"
+/// );
+///
+/// let socket = ClientBuilder::new(url).connect();
+/// ```
+#[derive(Debug, Clone)]
+pub struct UrlBuilder {
+    host: String,
+    secure: bool,
+    port: Option<u16>,
+    path: String,
+    params: Vec<(String, String)>,
+    query: Vec<(String, QueryValue)>,
+}
+
+impl UrlBuilder {
+    /// Starts a URL for the given host, which may include a port, e.g.
+    /// `localhost:4200`. The URL uses `https` by default.
+    pub fn new<T: Into<String>>(host: T) -> Self {
+        UrlBuilder {
+            host: host.into(),
+            secure: true,
+            port: None,
+            path: String::from("/"),
+            params: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    /// Whether the URL uses `https` or `http`. Defaults to `true`.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Sets the port, which takes precedence over a port given with the host.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sets the path, which may contain parameters like `{tenant}`. Defaults to
+    /// `/`, which the clients replace by `/socket.io/`.
+    pub fn path<T: Into<String>>(mut self, path: T) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Sets the value of the path parameter `{name}`.
+    pub fn param<K: Into<String>, V: ToString>(mut self, name: K, value: V) -> Self {
+        self.params.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Appends a query parameter, see [`QueryValue`] for the conversion of
+    /// the value.
+    pub fn query<K: Into<String>, V: Into<QueryValue>>(mut self, key: K, value: V) -> Self {
+        self.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// Builds and validates the URL. Fails with [`Error::InvalidUrlTemplate`]
+    /// if an environment variable isn't set or a path parameter has no value,
+    /// and with [`Error::InvalidUrl`] if the result isn't a valid URL.
+    pub fn build(&self) -> Result<Url> {
+        let scheme = if self.secure { "https" } else { "http" };
+        let host = expand_env(&self.host)?;
+        let mut url = Url::parse(&format!("{scheme}://{host}/"))?;
+        if url.path() != "/" || url.query().is_some() {
+            return Err(Error::InvalidUrlTemplate(format!(
+                "the host {host} must not contain a path or query"
+            )));
+        }
+        if let Some(port) = self.port {
+            url.set_port(Some(port))
+                .map_err(|_| Error::InvalidUrlTemplate(format!("invalid port {port}")))?;
+        }
+
+        let path = expand_env(&self.path)?;
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.clear();
+            for segment in path.trim_start_matches('/').split('/') {
+                segments.push(&self.expand_params(segment)?);
+            }
+        }
+
+        if !self.query.is_empty() {
+            let mut pairs = url.query_pairs_mut();
+            for (key, value) in &self.query {
+                pairs.append_pair(key, &expand_env(value.as_str())?);
+            }
+        }
+        Ok(url)
+    }
+
+    /// Replaces the parameters of a path segment by their values.
+    fn expand_params(&self, segment: &str) -> Result<String> {
+        let mut expanded = String::with_capacity(segment.len());
+        let mut rest = segment;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').ok_or_else(|| {
+                Error::InvalidUrlTemplate(format!("unclosed parameter in {segment}"))
+            })?;
+            let name = &rest[start + 1..start + end];
+            let (_, value) = self
+                .params
+                .iter()
+                .rev()
+                .find(|(param, _)| param == name)
+                .ok_or_else(|| Error::InvalidUrlTemplate(format!("no value for {{{name}}}")))?;
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(value);
+            rest = &rest[start + end + 1..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+/// Replaces references like `${NAME}` by the value of the environment variable.
+fn expand_env(template: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| Error::InvalidUrlTemplate(format!("unclosed variable in {template}")))?;
+        let name = &rest[start + 2..start + end];
+        let value = env::var(name).map_err(|_| {
+            Error::InvalidUrlTemplate(format!("environment variable {name} is not set"))
+        })?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build() -> Result<()> {
+        let url = UrlBuilder::new("example.com").build()?;
+        assert_eq!(url.as_str(), "https://example.com/");
+
+        let url = UrlBuilder::new("localhost")
+            .secure(false)
+            .port(4200)
+            .path("/rooms/{room}/{id}/ws")
+            .param("room", "a/b?c")
+            .param("id", 7)
+            .query("tenant", "x y")
+            .build()?;
+        assert_eq!(
+            url.as_str(),
+            "http://localhost:4200/rooms/a%2Fb%3Fc/7/ws?tenant=x+y"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_invalid() {
+        let err = UrlBuilder::new("localhost").path("/{room}").build();
+        assert!(matches!(err, Err(Error::InvalidUrlTemplate(_))));
+
+        let err = UrlBuilder::new("localhost").path("/{room").build();
+        assert!(matches!(err, Err(Error::InvalidUrlTemplate(_))));
+
+        let err = UrlBuilder::new("localhost/ws").build();
+        assert!(matches!(err, Err(Error::InvalidUrlTemplate(_))));
+
+        let err = UrlBuilder::new("local host").build();
+        assert!(matches!(err, Err(Error::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn test_expand_env() -> Result<()> {
+        env::set_var("RUST_SOCKETIO_TEST_HOST", "example.com");
+        let url = UrlBuilder::new("${RUST_SOCKETIO_TEST_HOST}")
+            .path("/${RUST_SOCKETIO_TEST_HOST}/")
+            .build()?;
+        assert_eq!(url.as_str(), "https://example.com/example.com/");
+
+        let err = UrlBuilder::new("${RUST_SOCKETIO_TEST_UNSET}").build();
+        assert!(matches!(err, Err(Error::InvalidUrlTemplate(_))));
+        Ok(())
+    }
+}
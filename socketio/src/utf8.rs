@@ -0,0 +1,108 @@
+/// Describes what happens to a received text packet that isn't valid UTF-8, see
+/// [`crate::ClientBuilder::utf8_policy`]. Some legacy servers occasionally send
+/// Latin-1 encoded text.
+///
+/// The policy only applies to packets received via polling. A websocket text
+/// frame that isn't valid UTF-8 violates the websocket protocol, so the
+/// websocket implementation closes the connection before the packet reaches
+/// the policy. Such servers need [`crate::TransportType::Polling`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Policy {
+    /// Drop the packet and report [`crate::Error::InvalidUtf8Packet`] to the
+    /// callbacks of [`crate::Event::Error`], the connection carries on. This is
+    /// the default.
+    #[default]
+    Reject,
+    /// Replace invalid sequences with `U+FFFD` and handle the packet as usual.
+    Lossy,
+    /// Report the error and drop the connection, which is then reconnected as
+    /// after a network error if reconnecting is enabled.
+    Terminate,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ClientBuilder, Payload, TransportType};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Answers each polling request of a connection with the next of
+    /// `polls`, then with a `Noop` every second. Emits are accepted.
+    fn serve(stream: TcpStream, polls: Arc<Mutex<Vec<Vec<u8>>>>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        loop {
+            let mut request = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                request.push_str(&line);
+            }
+            let mut body = vec![0; length];
+            let _ = reader.read_exact(&mut body);
+
+            let response = if request.starts_with("POST") {
+                b"ok".to_vec()
+            } else {
+                let next = polls.lock().unwrap().pop();
+                next.unwrap_or_else(|| {
+                    std::thread::sleep(Duration::from_secs(1));
+                    b"6".to_vec()
+                })
+            };
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=UTF-8\r\nContent-Length: {}\r\n\r\n",
+                response.len()
+            );
+            if stream.write_all(head.as_bytes()).is_err() || stream.write_all(&response).is_err() {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn test_lossy_over_polling() -> crate::error::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        // a Latin-1 encoded "café", in the order of `pop`
+        let polls = Arc::new(Mutex::new(vec![
+            b"40{\"sid\":\"latin\"}\x1e42[\"latin\",\"caf\xe9\"]".to_vec(),
+            br#"0{"sid":"legacy","upgrades":[],"pingInterval":25000,"pingTimeout":20000}"#.to_vec(),
+        ]));
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let polls = polls.clone();
+                std::thread::spawn(move || serve(stream, polls));
+            }
+        });
+
+        let (tx, rx) = mpsc::channel();
+        let socket = ClientBuilder::new(url)
+            .transport_type(TransportType::Polling)
+            .utf8_policy(Utf8Policy::Lossy)
+            .on("latin", move |payload, _| {
+                let _ = tx.send(payload);
+            })
+            .connect()?;
+
+        let payload = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(payload, Payload::Text(values) if values == [serde_json::json!("caf\u{fffd}")])
+        );
+        socket.disconnect()?;
+        Ok(())
+    }
+}
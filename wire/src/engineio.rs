@@ -0,0 +1,209 @@
+//! The framing of `engine.io` packets and of the payloads of the polling
+//! transport, which chain several packets.
+use alloc::borrow::Cow;
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use base64::{engine::general_purpose, Engine as _};
+
+/// Separates the packets of a payload, see
+/// https://en.wikipedia.org/wiki/Delimiter#ASCII_delimited_text
+pub const SEPARATOR: u8 = 0x1e;
+
+/// Enumeration of the `engine.io` packet types.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PacketId {
+    Open,
+    Close,
+    Ping,
+    Pong,
+    Message,
+    // A type of message that is base64 encoded
+    MessageBinary,
+    Upgrade,
+    Noop,
+}
+
+impl PacketId {
+    /// The number of the packet type. Binary messages share the number of
+    /// [`PacketId::Message`].
+    pub const fn number(self) -> u8 {
+        match self {
+            PacketId::Open => 0,
+            PacketId::Close => 1,
+            PacketId::Ping => 2,
+            PacketId::Pong => 3,
+            PacketId::Message => 4,
+            PacketId::MessageBinary => 4,
+            PacketId::Upgrade => 5,
+            PacketId::Noop => 6,
+        }
+    }
+
+    /// The byte an encoded packet of this type starts with, i.e. the number
+    /// as a character or `b` for a base64 encoded binary message.
+    pub const fn to_byte(self) -> u8 {
+        match self {
+            PacketId::MessageBinary => b'b',
+            _ => self.number() + b'0',
+        }
+    }
+
+    /// Parses the number of a packet type, given as a character or as the
+    /// number itself.
+    pub const fn from_byte(byte: u8) -> Result<PacketId, Error> {
+        match byte {
+            0 | b'0' => Ok(PacketId::Open),
+            1 | b'1' => Ok(PacketId::Close),
+            2 | b'2' => Ok(PacketId::Ping),
+            3 | b'3' => Ok(PacketId::Pong),
+            4 | b'4' => Ok(PacketId::Message),
+            5 | b'5' => Ok(PacketId::Upgrade),
+            6 | b'6' => Ok(PacketId::Noop),
+            _ => Err(Error::InvalidPacketId(byte)),
+        }
+    }
+}
+
+/// The errors of decoding an `engine.io` packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    IncompletePacket,
+    InvalidPacketId(u8),
+    InvalidBase64(base64::DecodeError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::IncompletePacket => write!(f, "Error while parsing an incomplete packet"),
+            Error::InvalidPacketId(byte) => write!(f, "Invalid packet id: {byte}"),
+            Error::InvalidBase64(err) => {
+                write!(f, "An error occurred while encoding/decoding base64: {err}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Decodes a single packet into its type and data. The data is borrowed from
+/// `bytes` unless it's base64 encoded.
+pub fn decode_packet(bytes: &[u8]) -> Result<(PacketId, Cow<'_, [u8]>), Error> {
+    let (&first, data) = bytes.split_first().ok_or(Error::IncompletePacket)?;
+    let is_base64 = first == b'b';
+
+    // only 'messages' packets could be encoded, the websocket transports
+    // prefix binary frames with the number of a message packet instead of
+    // its character
+    let packet_id = if is_base64 || first == PacketId::Message.number() {
+        PacketId::MessageBinary
+    } else {
+        PacketId::from_byte(first)?
+    };
+
+    if data.is_empty() && packet_id == PacketId::Message {
+        return Err(Error::IncompletePacket);
+    }
+
+    if is_base64 {
+        let decoded = general_purpose::STANDARD
+            .decode(data)
+            .map_err(Error::InvalidBase64)?;
+        Ok((packet_id, Cow::Owned(decoded)))
+    } else {
+        Ok((packet_id, Cow::Borrowed(data)))
+    }
+}
+
+/// The length of a packet with the given type and data once it's encoded.
+pub fn encoded_len(packet_id: PacketId, data: &[u8]) -> usize {
+    if packet_id == PacketId::MessageBinary {
+        1 + base64::encoded_len(data.len(), true).unwrap_or(usize::MAX)
+    } else {
+        1 + data.len()
+    }
+}
+
+/// Encodes a packet into the start of `buffer`, which has to hold at least
+/// [`encoded_len`] bytes, and returns the number of bytes written. Binary
+/// messages are base64 encoded.
+///
+/// # Panics
+/// If `buffer` is too small.
+pub fn encode_packet(packet_id: PacketId, data: &[u8], buffer: &mut [u8]) -> usize {
+    buffer[0] = packet_id.to_byte();
+    if packet_id == PacketId::MessageBinary {
+        let written = general_purpose::STANDARD
+            .encode_slice(data, &mut buffer[1..])
+            .expect("the buffer is too small for the packet");
+        1 + written
+    } else {
+        buffer[1..=data.len()].copy_from_slice(data);
+        1 + data.len()
+    }
+}
+
+/// Splits a payload of the polling transport into its encoded packets.
+pub fn split_payload(payload: &[u8]) -> impl Iterator<Item = &[u8]> {
+    payload.split(|&byte| byte == SEPARATOR)
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_packet() -> Result<(), Error> {
+        let (packet_id, data) = decode_packet(b"4Hello World")?;
+        assert_eq!(packet_id, PacketId::Message);
+        assert_eq!(data, Cow::Borrowed(b"Hello World".as_slice()));
+
+        let (packet_id, data) = decode_packet(b"bSGVsbG8=")?;
+        assert_eq!(packet_id, PacketId::MessageBinary);
+        assert_eq!(&data[..], b"Hello");
+
+        // binary websocket frames start with the number of a message
+        let (packet_id, data) = decode_packet(&[4, 1, 2, 3])?;
+        assert_eq!(packet_id, PacketId::MessageBinary);
+        assert_eq!(&data[..], &[1, 2, 3]);
+
+        assert_eq!(
+            decode_packet(b"2")?,
+            (PacketId::Ping, Cow::Borrowed(&[][..]))
+        );
+        assert_eq!(decode_packet(b""), Err(Error::IncompletePacket));
+        assert_eq!(decode_packet(b"4"), Err(Error::IncompletePacket));
+        assert_eq!(decode_packet(b"9"), Err(Error::InvalidPacketId(b'9')));
+        assert!(matches!(decode_packet(b"b!"), Err(Error::InvalidBase64(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_packet() -> Result<(), Error> {
+        for (packet_id, data, encoded) in [
+            (PacketId::Message, b"Hello".as_slice(), b"4Hello".as_slice()),
+            (PacketId::MessageBinary, b"Hello", b"bSGVsbG8="),
+            (PacketId::Ping, b"", b"2"),
+        ] {
+            let mut buffer = vec![0; encoded_len(packet_id, data)];
+            assert_eq!(encode_packet(packet_id, data, &mut buffer), encoded.len());
+            assert_eq!(buffer, encoded);
+
+            let (decoded_id, decoded) = decode_packet(&buffer)?;
+            assert_eq!(&decoded[..], data);
+            assert_eq!(decoded_id.to_byte(), packet_id.to_byte());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_payload() {
+        let packets: Vec<_> = split_payload(b"4Hello\x1e4World\x1ebSGVsbG8=").collect();
+        assert_eq!(packets, vec![b"4Hello".as_slice(), b"4World", b"bSGVsbG8="]);
+    }
+}
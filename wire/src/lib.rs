@@ -0,0 +1,33 @@
+//! The wire format of `engine.io` and `socket.io` packets, as used by
+//! `rust_engineio` and `rust_socketio`.
+//!
+//! The crate only parses and encodes packets, it doesn't open connections.
+//! This allows e.g. embedded gateways to speak the exact wire format of the
+//! clients over transports of their own. Without the default `std` feature,
+//! the crate is `no_std` and only needs `alloc`:
+//! ```toml
+//! rust_socketio_wire = { version = "0.6.0", default-features = false }
+//! ```
+//!
+//! # Example
+//! ```rust
+//! use rust_socketio_wire::{engineio, socketio};
+//!
+//! let (packet_id, data) = engineio::decode_packet(b"42[\"greeting\",\"hello\"]").unwrap();
+//! assert_eq!(packet_id, engineio::PacketId::Message);
+//!
+//! let text = core::str::from_utf8(&data).unwrap();
+//! let packet = socketio::decode_packet(text).unwrap();
+//! assert_eq!(packet.packet_type, socketio::PacketId::Event);
+//! assert_eq!(packet.data.as_deref(), Some("[\"greeting\",\"hello\"]"));
+//! ```
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::complexity)]
+#![warn(clippy::style)]
+#![warn(clippy::perf)]
+#![warn(clippy::correctness)]
+
+extern crate alloc;
+
+pub mod engineio;
+pub mod socketio;
@@ -0,0 +1,256 @@
+//! The framing of `socket.io` packets, which are sent as the data of
+//! `engine.io` messages. The binary attachments of a packet follow it in
+//! `engine.io` messages of their own.
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt::{Display, Formatter, Result as FmtResult, Write};
+
+use serde::de::IgnoredAny;
+
+/// An enumeration of the different `Packet` types in the `socket.io` protocol.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PacketId {
+    Connect = 0,
+    Disconnect = 1,
+    Event = 2,
+    Ack = 3,
+    ConnectError = 4,
+    BinaryEvent = 5,
+    BinaryAck = 6,
+}
+
+impl PacketId {
+    /// Parses the character of a packet type.
+    pub const fn from_char(c: char) -> Result<PacketId, Error> {
+        match c {
+            '0' => Ok(PacketId::Connect),
+            '1' => Ok(PacketId::Disconnect),
+            '2' => Ok(PacketId::Event),
+            '3' => Ok(PacketId::Ack),
+            '4' => Ok(PacketId::ConnectError),
+            '5' => Ok(PacketId::BinaryEvent),
+            '6' => Ok(PacketId::BinaryAck),
+            _ => Err(Error::InvalidPacketId(c)),
+        }
+    }
+
+    /// The character an encoded packet of this type starts with.
+    pub const fn to_char(self) -> char {
+        (self as u8 + b'0') as char
+    }
+
+    /// Whether packets of this type are followed by binary attachments.
+    pub const fn is_binary(self) -> bool {
+        matches!(self, PacketId::BinaryEvent | PacketId::BinaryAck)
+    }
+}
+
+/// The errors of decoding a `socket.io` packet.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    IncompletePacket,
+    InvalidPacketId(char),
+    InvalidPacket,
+    InvalidJson(serde_json::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::IncompletePacket => write!(f, "Error while parsing an incomplete packet"),
+            Error::InvalidPacketId(c) => write!(f, "Invalid packet id: {c}"),
+            Error::InvalidPacket => write!(
+                f,
+                "Got an invalid packet which did not follow the protocol format"
+            ),
+            Error::InvalidJson(err) => write!(f, "string is not json serializable: {err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// A `socket.io` packet without its binary attachments, borrowing from the
+/// text it was decoded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet<'a> {
+    pub packet_type: PacketId,
+    pub nsp: &'a str,
+    /// The JSON data. The placeholders of the attachments of binary packets
+    /// are removed, as well as the brackets around the arguments.
+    pub data: Option<Cow<'a, str>>,
+    pub id: Option<i32>,
+    pub attachment_count: u8,
+}
+
+impl Default for Packet<'_> {
+    fn default() -> Self {
+        Packet {
+            packet_type: PacketId::Event,
+            nsp: "/",
+            data: None,
+            id: None,
+            attachment_count: 0,
+        }
+    }
+}
+
+/// Decodes a packet from the text of an `engine.io` message.
+pub fn decode_packet(mut payload: &str) -> Result<Packet<'_>, Error> {
+    let mut packet = Packet::default();
+
+    // packet_type
+    let id_char = payload.chars().next().ok_or(Error::IncompletePacket)?;
+    packet.packet_type = PacketId::from_char(id_char)?;
+    payload = &payload[id_char.len_utf8()..];
+
+    // attachment_count
+    if packet.packet_type.is_binary() {
+        let (prefix, rest) = payload.split_once('-').ok_or(Error::IncompletePacket)?;
+        payload = rest;
+        packet.attachment_count = prefix.parse().map_err(|_| Error::InvalidPacket)?;
+    }
+
+    // namespace
+    if payload.starts_with('/') {
+        let (prefix, rest) = payload.split_once(',').ok_or(Error::IncompletePacket)?;
+        payload = rest;
+        packet.nsp = prefix;
+    }
+
+    // id
+    let Some((non_digit_idx, _)) = payload.char_indices().find(|(_, c)| !c.is_ascii_digit()) else {
+        return Ok(packet);
+    };
+
+    if non_digit_idx > 0 {
+        let (prefix, rest) = payload.split_at(non_digit_idx);
+        payload = rest;
+        packet.id = Some(prefix.parse().map_err(|_| Error::InvalidPacket)?);
+    }
+
+    // validate json
+    serde_json::from_str::<IgnoredAny>(payload).map_err(Error::InvalidJson)?;
+
+    if packet.packet_type.is_binary() {
+        if payload.starts_with('[') && payload.ends_with(']') {
+            payload = &payload[1..payload.len() - 1];
+        }
+
+        let mut str = payload.replace("{\"_placeholder\":true,\"num\":0}", "");
+
+        if str.ends_with(',') {
+            str.pop();
+        }
+
+        if !str.is_empty() {
+            packet.data = Some(Cow::Owned(str));
+        }
+    } else {
+        packet.data = Some(Cow::Borrowed(payload));
+    }
+
+    Ok(packet)
+}
+
+/// Encodes a packet into `buffer`. If the packet has attachments, its data
+/// is followed by the placeholder of the attachment, otherwise the data is
+/// written as is.
+pub fn encode_packet(packet: &Packet<'_>, has_attachments: bool, buffer: &mut String) {
+    // first the packet type
+    buffer.push(packet.packet_type.to_char());
+
+    // eventually a number of attachments, followed by '-'
+    if packet.packet_type.is_binary() {
+        let _ = write!(buffer, "{}-", packet.attachment_count);
+    }
+
+    // if the namespace is different from the default one append it as well,
+    // followed by ','
+    if packet.nsp != "/" {
+        buffer.push_str(packet.nsp);
+        buffer.push(',');
+    }
+
+    // if an id is present append it...
+    if let Some(id) = packet.id {
+        let _ = write!(buffer, "{id}");
+    }
+
+    if has_attachments {
+        let num = packet.attachment_count - 1;
+
+        // check if an event type is present
+        if let Some(event_type) = packet.data.as_ref() {
+            let _ = write!(
+                buffer,
+                "[{event_type},{{\"_placeholder\":true,\"num\":{num}}}]",
+            );
+        } else {
+            let _ = write!(buffer, "[{{\"_placeholder\":true,\"num\":{num}}}]");
+        }
+    } else if let Some(data) = packet.data.as_ref() {
+        buffer.push_str(data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_packet() -> Result<(), Error> {
+        let packet = decode_packet("2/admin,456[\"project:delete\",123]")?;
+        assert_eq!(
+            packet,
+            Packet {
+                packet_type: PacketId::Event,
+                nsp: "/admin",
+                data: Some(Cow::Borrowed("[\"project:delete\",123]")),
+                id: Some(456),
+                attachment_count: 0,
+            }
+        );
+
+        let packet = decode_packet("51-[\"hello\",{\"_placeholder\":true,\"num\":0}]")?;
+        assert_eq!(
+            packet,
+            Packet {
+                packet_type: PacketId::BinaryEvent,
+                data: Some(Cow::Borrowed("\"hello\"")),
+                attachment_count: 1,
+                ..Packet::default()
+            }
+        );
+
+        assert_eq!(decode_packet("1/admin,")?.nsp, "/admin");
+        assert!(matches!(decode_packet(""), Err(Error::IncompletePacket)));
+        assert!(matches!(
+            decode_packet("9"),
+            Err(Error::InvalidPacketId('9'))
+        ));
+        assert!(matches!(decode_packet("5[]"), Err(Error::IncompletePacket)));
+        assert!(matches!(
+            decode_packet("2[\"a\""),
+            Err(Error::InvalidJson(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_packet() -> Result<(), Error> {
+        for (encoded, has_attachments) in [
+            ("2/admin,456[\"project:delete\",123]", false),
+            ("0{\"token\":\"123\"}", false),
+            ("51-[\"hello\",{\"_placeholder\":true,\"num\":0}]", true),
+            ("61-/admin,456[{\"_placeholder\":true,\"num\":0}]", true),
+        ] {
+            let mut buffer = String::new();
+            encode_packet(&decode_packet(encoded)?, has_attachments, &mut buffer);
+            assert_eq!(buffer, encoded);
+        }
+        Ok(())
+    }
+}